@@ -0,0 +1,95 @@
+//! The YAML language host, served in-process for one operation.
+
+use std::net::SocketAddr;
+use std::path::Path;
+
+use pulumi_rs_yaml_language::server::YamlLanguageHost;
+use pulumi_rs_yaml_proto::pulumirpc;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tonic::transport::Server;
+
+/// A language host listening on a loopback port until stopped or dropped.
+pub(crate) struct InProcessHost {
+    address: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl InProcessHost {
+    /// Serves a host for the program in `root` on a free port.
+    ///
+    /// The host starts without an engine address: the engine passes it with
+    /// each `Run` request.
+    pub(crate) async fn start(root: &Path) -> std::io::Result<Self> {
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await?;
+        let address = listener.local_addr()?;
+        let host = YamlLanguageHost::new(String::new());
+        host.set_root_directory(root.to_string_lossy().into_owned());
+
+        let (shutdown, stopped) = oneshot::channel::<()>();
+        let incoming = tokio_stream::wrappers::TcpListenerStream::new(listener);
+        let task = tokio::spawn(async move {
+            let _ = Server::builder()
+                .add_service(pulumirpc::language_runtime_server::LanguageRuntimeServer::new(host))
+                .serve_with_incoming_shutdown(incoming, async {
+                    let _ = stopped.await;
+                })
+                .await;
+        });
+        Ok(Self {
+            address,
+            shutdown: Some(shutdown),
+            task: Some(task),
+        })
+    }
+
+    /// The address the engine connects to.
+    pub(crate) fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Stops serving and waits for the server to shut down.
+    pub(crate) async fn stop(mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+    }
+}
+
+impl Drop for InProcessHost {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_host_serves_until_stopped() {
+        let dir = tempfile::tempdir().unwrap();
+        let host = InProcessHost::start(dir.path()).await.unwrap();
+        let url = format!("http://{}", host.address());
+
+        let mut client =
+            pulumirpc::language_runtime_client::LanguageRuntimeClient::connect(url.clone())
+                .await
+                .unwrap();
+        let info = client.get_plugin_info(()).await.unwrap().into_inner();
+        assert!(!info.version.is_empty());
+
+        host.stop().await;
+        assert!(
+            pulumirpc::language_runtime_client::LanguageRuntimeClient::connect(url)
+                .await
+                .is_err()
+        );
+    }
+}
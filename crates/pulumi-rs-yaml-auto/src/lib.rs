@@ -0,0 +1,57 @@
+//! Automation API for Pulumi YAML projects.
+//!
+//! [`Stack`] previews, updates and destroys a stack of a YAML project from a
+//! Rust program, without a Node.js or Python runtime. Each operation serves
+//! the YAML language host in-process on a loopback port and runs the
+//! `pulumi` CLI against it, the way the Automation API runs inline
+//! programs: the engine attaches to the host (`--client`) instead of
+//! starting the `pulumi-language-yaml` plugin, so no plugin needs to be
+//! installed.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), pulumi_rs_yaml_auto::Error> {
+//! use pulumi_rs_yaml_auto::Stack;
+//!
+//! let stack = Stack::new("./infra", "dev")
+//!     .local_backend("/var/lib/pulumi")
+//!     .env("PULUMI_CONFIG_PASSPHRASE", "correct horse");
+//! stack.create_or_select().await?;
+//! stack.set_config("region", "us-west-2", false).await?;
+//!
+//! let preview = stack.preview().await?;
+//! println!("{:?}", preview.change_summary);
+//!
+//! let up = stack.up().await?;
+//! println!("{}", up.outputs["url"]);
+//! # Ok(())
+//! # }
+//! ```
+
+mod host;
+mod stack;
+
+pub use stack::{CommandOutput, PreviewResult, Stack, UpResult};
+
+/// Errors from running a stack operation.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("failed to start the language host: {0}")]
+    Host(#[source] std::io::Error),
+    #[error("failed to run {command}: {source}")]
+    Spawn {
+        command: String,
+        source: std::io::Error,
+    },
+    #[error("`{command}` failed ({status}): {}", stderr.trim_end())]
+    Command {
+        command: String,
+        status: std::process::ExitStatus,
+        stdout: String,
+        stderr: String,
+    },
+    #[error("unexpected output from `{command}`: {source}")]
+    Output {
+        command: String,
+        source: serde_json::Error,
+    },
+}
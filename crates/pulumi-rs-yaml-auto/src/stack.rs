@@ -0,0 +1,323 @@
+//! Stack operations, run through the `pulumi` CLI.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use serde_json::{Map, Value as Json};
+
+use crate::host::InProcessHost;
+use crate::Error;
+
+/// A stack of a YAML project.
+///
+/// Operations run `pulumi` (from `PATH` unless set with
+/// [`pulumi_command`](Self::pulumi_command)) in the project directory, with
+/// the process environment plus the variables set with [`env`](Self::env).
+#[derive(Debug, Clone)]
+pub struct Stack {
+    work_dir: PathBuf,
+    name: String,
+    pulumi: PathBuf,
+    env: Vec<(String, String)>,
+}
+
+/// The captured output of a successful `pulumi` command.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommandOutput {
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// The result of [`Stack::preview`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PreviewResult {
+    pub output: CommandOutput,
+    /// The number of resources per planned operation, e.g. `create: 2`.
+    pub change_summary: BTreeMap<String, u64>,
+}
+
+/// The result of [`Stack::up`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UpResult {
+    pub output: CommandOutput,
+    /// The stack outputs after the update, with secrets in plaintext.
+    pub outputs: Map<String, Json>,
+}
+
+impl Stack {
+    /// A stack named `name` of the project in `work_dir`, the directory
+    /// holding its `Pulumi.yaml`.
+    pub fn new(work_dir: impl Into<PathBuf>, name: impl Into<String>) -> Self {
+        Self {
+            work_dir: work_dir.into(),
+            name: name.into(),
+            pulumi: PathBuf::from("pulumi"),
+            env: Vec::new(),
+        }
+    }
+
+    /// Sets the `pulumi` executable to run.
+    pub fn pulumi_command(mut self, path: impl Into<PathBuf>) -> Self {
+        self.pulumi = path.into();
+        self
+    }
+
+    /// Sets an environment variable for the `pulumi` commands, such as
+    /// `PULUMI_ACCESS_TOKEN` or `PULUMI_CONFIG_PASSPHRASE`.
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    /// Keeps the stack's state in `dir` instead of the logged-in backend.
+    ///
+    /// Secrets in a local backend are encrypted with a passphrase, which
+    /// must be set with `PULUMI_CONFIG_PASSPHRASE`.
+    pub fn local_backend(self, dir: impl AsRef<Path>) -> Self {
+        let url = format!("file://{}", dir.as_ref().display());
+        self.env("PULUMI_BACKEND_URL", url)
+    }
+
+    /// The name of the stack.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Selects the stack, creating it if it does not exist.
+    pub async fn create_or_select(&self) -> Result<(), Error> {
+        self.run(&["stack", "select", "--create", &self.name])
+            .await
+            .map(drop)
+    }
+
+    /// Sets a config value of the stack. Bare keys are in the project's
+    /// namespace.
+    pub async fn set_config(&self, key: &str, value: &str, secret: bool) -> Result<(), Error> {
+        let secret = if secret { "--secret" } else { "--plaintext" };
+        self.run(&[
+            "config", "set", secret, "--stack", &self.name, "--", key, value,
+        ])
+        .await
+        .map(drop)
+    }
+
+    /// Previews an update of the stack.
+    pub async fn preview(&self) -> Result<PreviewResult, Error> {
+        let output = self.run_program(&["preview", "--json"]).await?;
+        let summary: Json = parse_json(&output.stdout, "pulumi preview --json")?;
+        let change_summary = summary
+            .get("changeSummary")
+            .and_then(Json::as_object)
+            .map(|ops| {
+                ops.iter()
+                    .filter_map(|(op, count)| Some((op.clone(), count.as_u64()?)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(PreviewResult {
+            output,
+            change_summary,
+        })
+    }
+
+    /// Updates the stack and returns its outputs.
+    pub async fn up(&self) -> Result<UpResult, Error> {
+        let output = self.run_program(&["up", "--yes", "--skip-preview"]).await?;
+        let outputs = self.outputs().await?;
+        Ok(UpResult { output, outputs })
+    }
+
+    /// Deletes all the stack's resources. The stack itself remains.
+    pub async fn destroy(&self) -> Result<CommandOutput, Error> {
+        self.run_program(&["destroy", "--yes", "--skip-preview"])
+            .await
+    }
+
+    /// The stack's outputs, with secrets in plaintext.
+    pub async fn outputs(&self) -> Result<Map<String, Json>, Error> {
+        let output = self
+            .run(&[
+                "stack",
+                "output",
+                "--json",
+                "--show-secrets",
+                "--stack",
+                &self.name,
+            ])
+            .await?;
+        parse_json(&output.stdout, "pulumi stack output --json")
+    }
+
+    /// Runs an operation that runs the program, against a language host
+    /// served for its duration.
+    async fn run_program(&self, args: &[&str]) -> Result<CommandOutput, Error> {
+        let host = InProcessHost::start(&self.work_dir)
+            .await
+            .map_err(Error::Host)?;
+        let client = format!("--client={}", host.address());
+        let mut args = args.to_vec();
+        args.extend([
+            "--stack",
+            &self.name,
+            "--exec-kind=auto.inline",
+            client.as_str(),
+        ]);
+        let result = self.run(&args).await;
+        host.stop().await;
+        result
+    }
+
+    async fn run(&self, args: &[&str]) -> Result<CommandOutput, Error> {
+        // Global flags go before `--`, after which everything is positional.
+        // Errors name the command without the positional arguments, which
+        // may be secret config values.
+        let split = args.iter().position(|a| *a == "--").unwrap_or(args.len());
+        let (flags, positional) = args.split_at(split);
+        let command = format!("pulumi {}", flags.join(" "));
+        let output = tokio::process::Command::new(&self.pulumi)
+            .args(flags)
+            .arg("--non-interactive")
+            .args(positional)
+            .current_dir(&self.work_dir)
+            .envs(self.env.iter().map(|(k, v)| (k, v)))
+            .stdin(Stdio::null())
+            .output()
+            .await
+            .map_err(|source| Error::Spawn {
+                command: command.clone(),
+                source,
+            })?;
+        let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+        let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+        if !output.status.success() {
+            return Err(Error::Command {
+                command,
+                status: output.status,
+                stdout,
+                stderr,
+            });
+        }
+        Ok(CommandOutput { stdout, stderr })
+    }
+}
+
+fn parse_json<T: serde::de::DeserializeOwned>(stdout: &str, command: &str) -> Result<T, Error> {
+    serde_json::from_str(stdout).map_err(|source| Error::Output {
+        command: command.to_string(),
+        source,
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    /// Writes a fake `pulumi` that logs its arguments, one invocation per
+    /// line, checks that the `--client` host accepts connections, and
+    /// prints canned output.
+    fn fake_pulumi(dir: &Path) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = dir.join("pulumi");
+        let log = dir.join("calls.log");
+        std::fs::write(
+            &path,
+            format!(
+                r#"#!/bin/bash
+echo "$@" >> {log}
+for arg in "$@"; do
+  case "$arg" in
+    --client=*) addr="${{arg#--client=}}" ;;
+  esac
+done
+if [ -n "$addr" ]; then
+  exec 3<>"/dev/tcp/${{addr%:*}}/${{addr##*:}}" || exit 3
+fi
+case "$1 $2" in
+  "preview --json") echo '{{"changeSummary": {{"create": 2, "same": 1}}}}' ;;
+  "stack output") echo '{{"url": "http://example.com", "token": "s3cret"}}' ;;
+  "destroy --yes") echo "error: stack is locked" >&2; exit 255 ;;
+  "config set") if [ "$3" = --secret ]; then exit 1; fi ;;
+esac
+"#,
+                log = log.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    fn calls(dir: &Path) -> Vec<String> {
+        std::fs::read_to_string(dir.join("calls.log"))
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_stack_operations() {
+        let dir = tempfile::tempdir().unwrap();
+        let stack = Stack::new(dir.path(), "dev")
+            .pulumi_command(fake_pulumi(dir.path()))
+            .local_backend("/tmp/state");
+
+        stack.create_or_select().await.unwrap();
+        stack
+            .set_config("region", "us-west-2", false)
+            .await
+            .unwrap();
+        let preview = stack.preview().await.unwrap();
+        assert_eq!(preview.change_summary["create"], 2);
+        assert_eq!(preview.change_summary["same"], 1);
+        let up = stack.up().await.unwrap();
+        assert_eq!(up.outputs["token"], "s3cret");
+
+        let calls = calls(dir.path());
+        assert_eq!(calls[0], "stack select --create dev --non-interactive");
+        assert_eq!(
+            calls[1],
+            "config set --plaintext --stack dev --non-interactive -- region us-west-2"
+        );
+        assert!(
+            calls[2].starts_with(
+                "preview --json --stack dev --exec-kind=auto.inline --client=127.0.0.1:"
+            ),
+            "{}",
+            calls[2]
+        );
+        assert!(calls[3].starts_with("up --yes --skip-preview --stack dev"));
+        assert_eq!(
+            calls[4],
+            "stack output --json --show-secrets --stack dev --non-interactive"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_command_reports_stderr() {
+        let dir = tempfile::tempdir().unwrap();
+        let stack = Stack::new(dir.path(), "dev").pulumi_command(fake_pulumi(dir.path()));
+
+        let err = stack.destroy().await.unwrap_err();
+        let Error::Command { stderr, .. } = &err else {
+            panic!("expected a command error, got {:?}", err);
+        };
+        assert_eq!(stderr, "error: stack is locked\n");
+        assert!(
+            err.to_string().ends_with(": error: stack is locked"),
+            "{}",
+            err
+        );
+
+        let err = stack
+            .set_config("token", "hunter2", true)
+            .await
+            .unwrap_err();
+        assert!(!err.to_string().contains("hunter2"), "{}", err);
+
+        let missing = Stack::new(dir.path(), "dev").pulumi_command(dir.path().join("missing"));
+        assert!(matches!(missing.outputs().await, Err(Error::Spawn { .. })));
+    }
+}
@@ -6,6 +6,7 @@ use pulumi_rs_yaml_core::ast::interpolation::InterpolationPart;
 use pulumi_rs_yaml_core::ast::property::{PropertyAccess, PropertyAccessor};
 use pulumi_rs_yaml_core::ast::template::*;
 use pulumi_rs_yaml_core::diag::Diagnostics;
+use pulumi_rs_yaml_core::number::Number;
 use pulumi_rs_yaml_core::packages::{canonicalize_type_token, collapse_type_token};
 use pulumi_rs_yaml_core::schema::SchemaStore;
 
@@ -240,6 +241,22 @@ impl Importer {
             .cloned()
             .unwrap_or_else(|| entry.logical_name.to_string());
 
+        // PCL has no resource scope: locals become top-level variables.
+        for local in &entry.resource.locals {
+            if self.resolve_name(&local.key) != local.key.as_ref() {
+                self.diags.warning(
+                    None,
+                    format!(
+                        "local '{}' of resource '{}' shadows a top-level name",
+                        local.key, entry.logical_name
+                    ),
+                    "locals are emitted as top-level variables in PCL; rename the local",
+                );
+            }
+            self.import_variable(local, w);
+            w.push('\n');
+        }
+
         let canonical_token = self.resolve_type_token(&entry.resource.type_);
         let display_token = collapse_type_token(&canonical_token);
 
@@ -268,14 +285,26 @@ impl Importer {
         }
 
         // Options block
-        self.import_resource_options(&entry.resource.options, w);
+        self.import_resource_options(&entry.resource.options, entry.resource.range.as_ref(), w);
 
         w.push_str("}\n");
     }
 
-    fn import_resource_options(&mut self, opts: &ResourceOptionsDecl<'_>, w: &mut String) {
+    fn import_resource_options(
+        &mut self,
+        opts: &ResourceOptionsDecl<'_>,
+        range: Option<&ResourceRange<'_>>,
+        w: &mut String,
+    ) {
         let mut options_buf = String::new();
 
+        // range: PCL binds `range.key` and `range.value` like `count:` and
+        // `range:` do, and a number ranges over the indices.
+        if let Some(range) = range {
+            let pcl = self.expr_to_pcl(range.expr(), 2);
+            let _ = writeln!(options_buf, "\t\trange = {}", pcl);
+        }
+
         // dependsOn
         if let Some(ref deps) = opts.depends_on {
             match deps {
@@ -476,11 +505,70 @@ impl Importer {
                 let i = self.expr_to_pcl(idx, indent);
                 format!("{}[{}]", v, i)
             }
+            Expr::Lookup(_, map, key, default) => {
+                let m = self.expr_to_pcl(map, indent);
+                let k = self.expr_to_pcl(key, indent);
+                match default {
+                    Some(default) => {
+                        let d = self.expr_to_pcl(default, indent);
+                        format!("lookup({}, {}, {})", m, k, d)
+                    }
+                    None => format!("{}[{}]", m, k),
+                }
+            }
             Expr::Split(_, delim, source) => {
                 let d = self.expr_to_pcl(delim, indent);
                 let s = self.expr_to_pcl(source, indent);
                 format!("split({}, {})", d, s)
             }
+            Expr::If(_, condition, then, otherwise) => {
+                let c = self.expr_to_pcl(condition, indent);
+                let t = self.expr_to_pcl(then, indent);
+                let e = self.expr_to_pcl(otherwise, indent);
+                format!("({} ? {} : {})", c, t, e)
+            }
+            Expr::Equals(_, a, b) => self.binary_op_to_pcl("==", a, b, indent),
+            Expr::LessThan(_, a, b) => self.binary_op_to_pcl("<", a, b, indent),
+            Expr::LessThanOrEqual(_, a, b) => self.binary_op_to_pcl("<=", a, b, indent),
+            Expr::GreaterThan(_, a, b) => self.binary_op_to_pcl(">", a, b, indent),
+            Expr::GreaterThanOrEqual(_, a, b) => self.binary_op_to_pcl(">=", a, b, indent),
+            Expr::Not(_, inner) => format!("!{}", self.expr_to_pcl(inner, indent)),
+            // fn::and / fn::or over a literal list → chained && / ||
+            Expr::And(_, inner) | Expr::Or(_, inner) => match inner.as_ref() {
+                Expr::List(_, items) => {
+                    let (op, empty) = match expr {
+                        Expr::And(_, _) => ("&&", "true"),
+                        _ => ("||", "false"),
+                    };
+                    if items.is_empty() {
+                        return empty.to_string();
+                    }
+                    let operands: Vec<String> = items
+                        .iter()
+                        .map(|item| self.expr_to_pcl(item, indent))
+                        .collect();
+                    format!("({})", operands.join(&format!(" {} ", op)))
+                }
+                _ => self.unsupported_builtin_to_pcl(expr),
+            },
+            Expr::Map(_, list, body) => {
+                let l = self.expr_to_pcl(list, indent);
+                let b = self.expr_to_pcl(body, indent);
+                format!("[for index, item in {} : {}]", l, b)
+            }
+            Expr::Foreach(_, foreach) => {
+                let l = self.expr_to_pcl(&foreach.list, indent);
+                let b = self.expr_to_pcl(&foreach.body, indent);
+                match &foreach.index {
+                    Some(index) => format!("[for {}, {} in {} : {}]", index, foreach.var, l, b),
+                    None => format!("[for {} in {} : {}]", foreach.var, l, b),
+                }
+            }
+            Expr::Filter(_, list, predicate) => {
+                let l = self.expr_to_pcl(list, indent);
+                let p = self.expr_to_pcl(predicate, indent);
+                format!("[for index, item in {} : item if {}]", l, p)
+            }
             Expr::ToJson(_, inner) => {
                 let v = self.expr_to_pcl(inner, indent);
                 format!("toJSON({})", v)
@@ -501,6 +589,14 @@ impl Importer {
                 let v = self.expr_to_pcl(inner, indent);
                 format!("readFile({})", v)
             }
+            Expr::ValidateJson(_, value, _) => {
+                self.diags.warning(
+                    None,
+                    "'fn::validateJson' has no PCL equivalent",
+                    "the value is emitted without its JSON Schema check",
+                );
+                self.expr_to_pcl(value, indent)
+            }
 
             // Assets and archives
             Expr::StringAsset(_, inner) => {
@@ -531,25 +627,58 @@ impl Importer {
             | Expr::Ceil(_, _)
             | Expr::Max(_, _)
             | Expr::Min(_, _)
+            | Expr::FormatDuration(_, _)
+            | Expr::FormatBytes(_, _)
             | Expr::StringLen(_, _)
+            | Expr::Sort(_, _)
+            | Expr::Unique(_, _)
+            | Expr::Length(_, _)
+            | Expr::Coalesce(_, _)
             | Expr::Substring(_, _, _, _)
+            | Expr::ToUpper(_, _)
+            | Expr::ToLower(_, _)
+            | Expr::Trim(_, _)
+            | Expr::Replace(_, _, _, _)
+            | Expr::StartsWith(_, _, _)
+            | Expr::Format(_, _, _)
+            | Expr::Assert(_, _, _)
+            | Expr::ReadFileBinary(_, _)
+            | Expr::Env(_, _, _)
+            | Expr::ParseJson(_, _)
+            | Expr::ParseYaml(_, _)
+            | Expr::CidrSubnet(_, _, _, _)
+            | Expr::CidrHost(_, _, _)
+            | Expr::CidrNetmask(_, _)
             | Expr::TimeUtc(_, _)
             | Expr::TimeUnix(_, _)
             | Expr::Uuid(_, _)
             | Expr::RandomString(_, _)
             | Expr::DateFormat(_, _)
-            | Expr::Starlark(_, _) => {
-                let name = rust_only_builtin_name(expr);
-                self.diags.warning(
-                    None,
-                    format!("unsupported builtin 'fn::{}' in PCL conversion", name),
-                    "this builtin is not available in standard PCL and will be emitted as null",
-                );
-                "null /* unsupported builtin */".to_string()
-            }
+            | Expr::TemplateFile(_, _, _)
+            | Expr::Merge(_, _)
+            | Expr::JsonPatch(_, _, _)
+            | Expr::Open(_, _, _)
+            | Expr::Encrypted(_, _)
+            | Expr::Starlark(_, _) => self.unsupported_builtin_to_pcl(expr),
         }
     }
 
+    fn unsupported_builtin_to_pcl(&mut self, expr: &Expr<'_>) -> String {
+        let name = rust_only_builtin_name(expr);
+        self.diags.warning(
+            None,
+            format!("unsupported builtin 'fn::{}' in PCL conversion", name),
+            "this builtin is not available in standard PCL and will be emitted as null",
+        );
+        "null /* unsupported builtin */".to_string()
+    }
+
+    fn binary_op_to_pcl(&mut self, op: &str, a: &Expr<'_>, b: &Expr<'_>, indent: usize) -> String {
+        let a = self.expr_to_pcl(a, indent);
+        let b = self.expr_to_pcl(b, indent);
+        format!("({} {} {})", a, op, b)
+    }
+
     fn property_access_to_pcl(&self, access: &PropertyAccess<'_>) -> String {
         let mut result = String::new();
         for (i, accessor) in access.accessors.iter().enumerate() {
@@ -573,6 +702,27 @@ impl Importer {
                 PropertyAccessor::IntSubscript(idx) => {
                     let _ = write!(result, "[{}]", idx);
                 }
+                PropertyAccessor::PropertySubscript(inner) => {
+                    let _ = write!(result, "[{}]", self.property_access_to_pcl(inner));
+                }
+                PropertyAccessor::Wildcard => {
+                    result.push_str("[*]");
+                }
+                PropertyAccessor::Slice(start, end) => {
+                    // PCL has no slice syntax; lower to the slice() function.
+                    let bound = |b: i64| {
+                        if b < 0 {
+                            format!("length({}) - {}", result, b.unsigned_abs())
+                        } else {
+                            b.to_string()
+                        }
+                    };
+                    let lo = start.map(bound).unwrap_or_else(|| "0".to_string());
+                    let hi = end
+                        .map(bound)
+                        .unwrap_or_else(|| format!("length({})", result));
+                    result = format!("slice({}, {}, {})", result, lo, hi);
+                }
             }
         }
         result
@@ -792,11 +942,10 @@ fn is_valid_pcl_attr(s: &str) -> bool {
 }
 
 /// Formats a number for PCL output (integers without decimals).
-fn format_number(n: f64) -> String {
-    if n == n.trunc() && n.is_finite() {
-        format!("{}", n as i64)
-    } else {
-        format!("{}", n)
+fn format_number(n: Number) -> String {
+    match n.as_i64() {
+        Some(i) => i.to_string(),
+        None => n.to_string(),
     }
 }
 
@@ -846,13 +995,40 @@ fn rust_only_builtin_name(expr: &Expr<'_>) -> &'static str {
         Expr::Ceil(_, _) => "ceil",
         Expr::Max(_, _) => "max",
         Expr::Min(_, _) => "min",
+        Expr::FormatDuration(_, _) => "formatDuration",
+        Expr::FormatBytes(_, _) => "formatBytes",
         Expr::StringLen(_, _) => "stringLen",
+        Expr::Sort(_, _) => "sort",
+        Expr::Unique(_, _) => "unique",
+        Expr::Length(_, _) => "length",
+        Expr::Coalesce(_, _) => "coalesce",
+        Expr::And(_, _) => "and",
+        Expr::Or(_, _) => "or",
         Expr::Substring(_, _, _, _) => "substring",
+        Expr::ToUpper(_, _) => "toUpper",
+        Expr::ToLower(_, _) => "toLower",
+        Expr::Trim(_, _) => "trim",
+        Expr::Replace(_, _, _, _) => "replace",
+        Expr::StartsWith(_, _, _) => "startsWith",
+        Expr::Format(_, _, _) => "format",
+        Expr::Assert(_, _, _) => "assert",
+        Expr::ReadFileBinary(_, _) => "readFileBinary",
+        Expr::Env(_, _, _) => "env",
+        Expr::ParseJson(_, _) => "parseJSON",
+        Expr::ParseYaml(_, _) => "parseYAML",
+        Expr::CidrSubnet(_, _, _, _) => "cidrSubnet",
+        Expr::CidrHost(_, _, _) => "cidrHost",
+        Expr::CidrNetmask(_, _) => "cidrNetmask",
         Expr::TimeUtc(_, _) => "timeUtc",
         Expr::TimeUnix(_, _) => "timeUnix",
         Expr::Uuid(_, _) => "uuid",
         Expr::RandomString(_, _) => "randomString",
         Expr::DateFormat(_, _) => "dateFormat",
+        Expr::TemplateFile(_, _, _) => "templateFile",
+        Expr::Merge(_, _) => "merge",
+        Expr::JsonPatch(_, _, _) => "jsonPatch",
+        Expr::Open(_, _, _) => "open",
+        Expr::Encrypted(_, _) => "encrypted",
         _ => "unknown",
     }
 }
@@ -863,14 +1039,18 @@ mod tests {
 
     #[test]
     fn test_format_number_integer() {
-        assert_eq!(format_number(42.0), "42");
-        assert_eq!(format_number(0.0), "0");
-        assert_eq!(format_number(-1.0), "-1");
+        assert_eq!(format_number(42.0.into()), "42");
+        assert_eq!(format_number(0.0.into()), "0");
+        assert_eq!(format_number((-1.0).into()), "-1");
+        assert_eq!(
+            format_number(Number::Int(9_007_199_254_740_993)),
+            "9007199254740993"
+        );
     }
 
     #[test]
     fn test_format_number_float() {
-        assert_eq!(format_number(2.75), "2.75");
+        assert_eq!(format_number(2.75.into()), "2.75");
     }
 
     #[test]
@@ -999,6 +1179,36 @@ variables:
         assert!(pcl.contains("encoded = toBase64(\"hello\")"));
     }
 
+    #[test]
+    fn test_comparison_import() {
+        use pulumi_rs_yaml_core::ast::parse::parse_template;
+
+        let yaml = r#"
+name: test
+runtime: yaml
+config:
+  env:
+    type: string
+  replicas:
+    type: integer
+variables:
+  large:
+    fn::and:
+      - fn::equals: ["${env}", prod]
+      - fn::not:
+          fn::lessThan: ["${replicas}", 3]
+"#;
+        let (template, _) = parse_template(yaml, None);
+        let mut importer = Importer::new();
+        let pcl = importer.import_template(&template);
+
+        assert!(
+            pcl.contains("large = ((env == \"prod\") && !(replicas < 3))"),
+            "got:\n{}",
+            pcl
+        );
+    }
+
     #[test]
     fn test_pulumi_variables() {
         use pulumi_rs_yaml_core::ast::parse::parse_template;
@@ -252,6 +252,131 @@ variables:
     );
 }
 
+#[test]
+fn test_map_and_filter_to_for_expressions() {
+    let yaml = r#"
+name: test
+runtime: yaml
+variables:
+  zones: [a, b]
+  flags: [true, false]
+  names:
+    fn::map:
+      - ${zones}
+      - subnet-${index}-${item}
+  enabled:
+    fn::filter:
+      - ${flags}
+      - ${item}
+"#;
+    let result = yaml_to_pcl(yaml);
+    let pcl = result.pcl_text;
+
+    assert!(
+        pcl.contains("names = [for index, item in zones : \"subnet-${index}-${item}\"]"),
+        "got:\n{}",
+        pcl
+    );
+    assert!(
+        pcl.contains("enabled = [for index, item in flags : item if item]"),
+        "got:\n{}",
+        pcl
+    );
+}
+
+#[test]
+fn test_foreach_to_for_expression() {
+    let yaml = r#"
+name: test
+runtime: yaml
+variables:
+  zones: [a, b]
+  names:
+    fn::foreach:
+      list: ${zones}
+      as: zone
+      index: i
+      body: subnet-${i}-${zone}
+"#;
+    let result = yaml_to_pcl(yaml);
+    let pcl = result.pcl_text;
+
+    assert!(
+        pcl.contains("names = [for i, zone in zones : \"subnet-${i}-${zone}\"]"),
+        "got:\n{}",
+        pcl
+    );
+}
+
+#[test]
+fn test_resource_count_to_range_option() {
+    let yaml = r#"
+name: test
+runtime: yaml
+resources:
+  web:
+    type: aws:ec2:Instance
+    count: 3
+    properties:
+      tags:
+        Name: web-${range.value}
+"#;
+    let result = yaml_to_pcl(yaml);
+    let pcl = result.pcl_text;
+
+    assert!(pcl.contains("\t\trange = 3"), "got:\n{}", pcl);
+    assert!(pcl.contains("\"web-${range.value}\""), "got:\n{}", pcl);
+}
+
+#[test]
+fn test_validate_json_keeps_value() {
+    let yaml = r#"
+name: test
+runtime: yaml
+variables:
+  userData:
+    fn::validateJson:
+      value:
+        packages: [git]
+      schema: ./cloud-init.schema.json
+"#;
+    let result = yaml_to_pcl(yaml);
+
+    assert!(
+        result.pcl_text.contains("packages = [\"git\"]"),
+        "got:\n{}",
+        result.pcl_text
+    );
+    assert!(result
+        .diagnostics
+        .iter()
+        .any(|d| !d.is_error() && d.summary.contains("fn::validateJson")));
+}
+
+#[test]
+fn test_resource_locals_become_variables() {
+    let yaml = r#"
+name: test
+runtime: yaml
+resources:
+  bucket:
+    type: aws:s3:Bucket
+    locals:
+      prefix: logs
+    properties:
+      bucketPrefix: ${prefix}-bucket
+"#;
+    let result = yaml_to_pcl(yaml);
+    let pcl = result.pcl_text;
+
+    assert!(pcl.contains("prefix = \"logs\""), "got:\n{}", pcl);
+    assert!(
+        pcl.contains("bucketPrefix = \"${prefix}-bucket\""),
+        "got:\n{}",
+        pcl
+    );
+}
+
 #[test]
 fn test_to_json_builtin() {
     let yaml = r#"
@@ -371,3 +496,29 @@ components:
     assert!(pcl.contains("__logicalName = \"myApp\""), "got:\n{}", pcl);
     assert!(pcl.contains("env = \"prod\""), "got:\n{}", pcl);
 }
+
+#[test]
+fn test_slice_with_minimum_bound() {
+    let yaml = r#"
+name: test
+runtime: yaml
+variables:
+  zones: [a, b, c]
+outputs:
+  none: ${zones[:-9223372036854775808]}
+  tail: ${zones[-2:]}
+"#;
+    let result = yaml_to_pcl(yaml);
+    let pcl = result.pcl_text;
+
+    assert!(
+        pcl.contains("slice(zones, 0, length(zones) - 9223372036854775808)"),
+        "got:\n{}",
+        pcl
+    );
+    assert!(
+        pcl.contains("slice(zones, length(zones) - 2, length(zones))"),
+        "got:\n{}",
+        pcl
+    );
+}
@@ -4,8 +4,10 @@ use std::collections::HashMap;
 use pulumi_rs_yaml_core::ast::parse::parse_template;
 use pulumi_rs_yaml_core::eval::evaluator::Evaluator;
 use pulumi_rs_yaml_core::eval::mock::MockCallback;
-use pulumi_rs_yaml_core::eval::protobuf::{protobuf_to_value, value_to_protobuf};
-use pulumi_rs_yaml_core::eval::value::Value;
+use pulumi_rs_yaml_core::eval::protobuf::{
+    protobuf_to_value, value_into_protobuf, value_to_protobuf,
+};
+use pulumi_rs_yaml_core::eval::value::{Number, Value};
 use pulumi_rs_yaml_core::jinja::{
     has_jinja_block_syntax, strip_jinja_blocks, validate_rendered_yaml, JinjaContext,
     JinjaPreprocessor, NoopPreprocessor, TemplatePreprocessor, UndefinedMode,
@@ -79,7 +81,7 @@ outputs:
     c.bench_function("eval_simple_template", |b| {
         b.iter(|| {
             let (template, _diags) = parse_template(source, None);
-            let template: &'static _ = Box::leak(Box::new(template));
+            let template = &template;
             let mock = MockCallback::new();
             let eval = Evaluator::with_callback(
                 "bench".to_string(),
@@ -100,7 +102,7 @@ fn bench_protobuf_round_trip(c: &mut Criterion) {
 
     let value = Value::Object(vec![
         (Cow::from("name"), Value::String("test".into())),
-        (Cow::from("count"), Value::Number(42.0)),
+        (Cow::from("count"), Value::Number(42.0.into())),
         (Cow::from("enabled"), Value::Bool(true)),
         (
             Cow::from("tags"),
@@ -114,7 +116,7 @@ fn bench_protobuf_round_trip(c: &mut Criterion) {
             Cow::from("nested"),
             Value::Object(vec![
                 (Cow::from("key"), Value::String("value".into())),
-                (Cow::from("num"), Value::Number(3.15)),
+                (Cow::from("num"), Value::Number(3.15.into())),
             ]),
         ),
     ]);
@@ -128,6 +130,78 @@ fn bench_protobuf_round_trip(c: &mut Criterion) {
     });
 }
 
+/// Builds a property payload of about 1MB: 2,000 objects, each with a few
+/// scalar fields, a tag list, and a 400-byte description.
+fn large_payload() -> Value<'static> {
+    use std::borrow::Cow;
+
+    let description = "x".repeat(400);
+    let items = (0..2000)
+        .map(|i| {
+            Value::Object(vec![
+                (
+                    Cow::Owned("name".into()),
+                    Value::String(Cow::Owned(format!("item-{}", i))),
+                ),
+                (Cow::Owned("index".into()), Value::Number(Number::Int(i))),
+                (Cow::Owned("enabled".into()), Value::Bool(i % 2 == 0)),
+                (
+                    Cow::Owned("tags".into()),
+                    Value::List(
+                        (0..4)
+                            .map(|t| Value::String(Cow::Owned(format!("tag-{}-{}", i, t))))
+                            .collect(),
+                    ),
+                ),
+                (
+                    Cow::Owned("description".into()),
+                    Value::String(Cow::Owned(description.clone())),
+                ),
+                (
+                    Cow::Owned("password".into()),
+                    Value::Secret(Box::new(Value::String(Cow::Owned(format!("secret-{}", i))))),
+                ),
+            ])
+        })
+        .collect();
+    Value::Object(vec![(Cow::Owned("items".into()), Value::List(items))])
+}
+
+/// Compares encoding a 1MB payload by reference and by move. Run with
+/// `cargo bench -p pulumi-rs-yaml-core --bench core_bench -- protobuf_1mb`;
+/// `encode_owned` should take under half the time of `encode_borrowed`.
+fn bench_protobuf_large_payload(c: &mut Criterion) {
+    use criterion::BatchSize;
+
+    let value = large_payload();
+    let proto = value_to_protobuf(&value);
+
+    let mut group = c.benchmark_group("protobuf_1mb");
+    group.sample_size(20);
+    group.bench_function("encode_borrowed", |b| {
+        b.iter_batched_ref(
+            || value.clone(),
+            |value| black_box(value_to_protobuf(value)),
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("encode_owned", |b| {
+        b.iter_batched(
+            || value.clone(),
+            |value| black_box(value_into_protobuf(value)),
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("decode", |b| {
+        b.iter_batched(
+            || proto.clone(),
+            |proto| black_box(protobuf_to_value(proto)),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
 fn bench_topological_sort(c: &mut Criterion) {
     // Generate a template with 100 resources in a chain
     let mut yaml = String::from("name: bench\nruntime: yaml\nresources:\n");
@@ -141,7 +215,7 @@ fn bench_topological_sort(c: &mut Criterion) {
     }
 
     let (template, _) = parse_template(&yaml, None);
-    let template: &'static _ = Box::leak(Box::new(template));
+    let template = &template;
 
     c.bench_function("topological_sort_100_chain", |b| {
         b.iter(|| {
@@ -173,7 +247,7 @@ config:
 "#;
 
     let (template, _) = parse_template(source, None);
-    let template: &'static _ = Box::leak(Box::new(template));
+    let template = &template;
 
     let mut raw_config = HashMap::new();
     raw_config.insert("bench:str1".to_string(), "override".to_string());
@@ -240,6 +314,7 @@ outputs:
         config: &config,
         project_dir: "/home/user",
         undefined: UndefinedMode::Strict,
+        args: &[],
         extra: &HashMap::new(),
     };
 
@@ -276,6 +351,7 @@ resources:
         config: &config,
         project_dir: "/home/user",
         undefined: UndefinedMode::Strict,
+        args: &[],
         extra: &HashMap::new(),
     };
 
@@ -445,6 +521,7 @@ fn bench_jinja_preprocess_multi_file(c: &mut Criterion) {
         config: &config,
         project_dir: dir.path().to_str().unwrap(),
         undefined: UndefinedMode::Strict,
+        args: &[],
         extra: &HashMap::new(),
     };
 
@@ -464,6 +541,7 @@ criterion_group!(
     bench_parse_complex,
     bench_eval_simple,
     bench_protobuf_round_trip,
+    bench_protobuf_large_payload,
     bench_topological_sort,
     bench_config_resolution,
     bench_noop_preprocessor,
@@ -1,20 +1,24 @@
 use crate::ast::interpolation::InterpolationPart;
 use crate::ast::property::PropertyAccess;
+use crate::number::Number;
 use crate::syntax::ExprMeta;
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 
 /// The core expression AST for Pulumi YAML.
 ///
 /// All 25+ expression variants are represented as a single enum with no dynamic dispatch.
 /// Each variant carries an `ExprMeta` for source location tracking.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub enum Expr<'src> {
     /// Null literal.
     Null(ExprMeta),
     /// Boolean literal.
     Bool(ExprMeta, bool),
-    /// Number literal (f64).
-    Number(ExprMeta, f64),
+    /// Number literal: an `Int` for integers that fit in an `i64`, else a
+    /// `Float` (see [`Number`]).
+    Number(ExprMeta, Number),
     /// String literal (may be borrowed from source or owned).
     String(ExprMeta, Cow<'src, str>),
     /// Interpolated string containing `${...}` expressions.
@@ -31,20 +35,91 @@ pub enum Expr<'src> {
     Invoke(ExprMeta, InvokeExpr<'src>),
     /// `fn::join` - joins a list with a delimiter.
     Join(ExprMeta, Box<Expr<'src>>, Box<Expr<'src>>),
-    /// `fn::select` - selects an element from a list by index.
+    /// `fn::select` - selects an element from a list by index, or from an object by key.
     Select(ExprMeta, Box<Expr<'src>>, Box<Expr<'src>>),
+    /// `fn::lookup` - looks up a key in an object: [map, key, default?]. The
+    /// default is only evaluated when the key is missing.
+    Lookup(
+        ExprMeta,
+        Box<Expr<'src>>,
+        Box<Expr<'src>>,
+        Option<Box<Expr<'src>>>,
+    ),
     /// `fn::split` - splits a string by a delimiter.
     Split(ExprMeta, Box<Expr<'src>>, Box<Expr<'src>>),
     /// `fn::toJSON` - serializes a value to JSON.
     ToJson(ExprMeta, Box<Expr<'src>>),
+    /// `fn::parseJSON` - decodes a JSON string into a value.
+    ParseJson(ExprMeta, Box<Expr<'src>>),
+    /// `fn::parseYAML` - decodes a YAML string into a value.
+    ParseYaml(ExprMeta, Box<Expr<'src>>),
     /// `fn::toBase64` - encodes a string as base64.
     ToBase64(ExprMeta, Box<Expr<'src>>),
     /// `fn::fromBase64` - decodes a base64 string.
     FromBase64(ExprMeta, Box<Expr<'src>>),
     /// `fn::secret` - marks a value as secret.
     Secret(ExprMeta, Box<Expr<'src>>),
+    /// `fn::encrypted` - a secret written as ciphertext, decrypted by the host.
+    Encrypted(ExprMeta, Box<Expr<'src>>),
     /// `fn::readFile` - reads a file at the given path.
     ReadFile(ExprMeta, Box<Expr<'src>>),
+    /// `fn::readFileBinary` - reads a file at the given path as raw bytes.
+    ReadFileBinary(ExprMeta, Box<Expr<'src>>),
+    /// `fn::env` - reads an environment variable: a name, or [name, default].
+    /// The default is only evaluated when the variable is unset. Fails unless
+    /// the evaluator allows reading the environment.
+    Env(ExprMeta, Box<Expr<'src>>, Option<Box<Expr<'src>>>),
+    /// `fn::validateJson` - checks a value against a JSON Schema and returns it
+    /// unchanged: { value, schema }. `schema` is an inline schema or the path
+    /// of a JSON or YAML schema file.
+    ValidateJson(ExprMeta, Box<Expr<'src>>, Box<Expr<'src>>),
+    /// `fn::assert` - fails evaluation with a message unless a condition
+    /// holds: { condition, message }.
+    Assert(ExprMeta, Box<Expr<'src>>, Box<Expr<'src>>),
+    /// `fn::templateFile` - reads a file and renders its `${...}` placeholders
+    /// from an arguments object: { path, arguments }.
+    TemplateFile(ExprMeta, Box<Expr<'src>>, Box<Expr<'src>>),
+    /// `fn::if` - chooses between two values: [condition, then, else]. Only
+    /// the chosen value is evaluated.
+    If(ExprMeta, Box<Expr<'src>>, Box<Expr<'src>>, Box<Expr<'src>>),
+
+    // --- Comparison and boolean builtins ---
+    /// `fn::equals` - whether two values are equal: [left, right].
+    Equals(ExprMeta, Box<Expr<'src>>, Box<Expr<'src>>),
+    /// `fn::not` - negates a boolean.
+    Not(ExprMeta, Box<Expr<'src>>),
+    /// `fn::and` - whether every boolean of a list is true. Items of a
+    /// literal list are evaluated only up to the first false one.
+    And(ExprMeta, Box<Expr<'src>>),
+    /// `fn::or` - whether any boolean of a list is true. Items of a literal
+    /// list are evaluated only up to the first true one.
+    Or(ExprMeta, Box<Expr<'src>>),
+    /// `fn::lessThan` - compares two numbers: [left, right].
+    LessThan(ExprMeta, Box<Expr<'src>>, Box<Expr<'src>>),
+    /// `fn::lessThanOrEqual` - compares two numbers: [left, right].
+    LessThanOrEqual(ExprMeta, Box<Expr<'src>>, Box<Expr<'src>>),
+    /// `fn::greaterThan` - compares two numbers: [left, right].
+    GreaterThan(ExprMeta, Box<Expr<'src>>, Box<Expr<'src>>),
+    /// `fn::greaterThanOrEqual` - compares two numbers: [left, right].
+    GreaterThanOrEqual(ExprMeta, Box<Expr<'src>>, Box<Expr<'src>>),
+
+    // --- List builtins ---
+    /// `fn::map` - evaluates an expression for each element of a list: [list, expr].
+    /// `${item}` and `${index}` are bound while the expression is evaluated.
+    Map(ExprMeta, Box<Expr<'src>>, Box<Expr<'src>>),
+    /// `fn::filter` - keeps the elements of a list for which a predicate is true:
+    /// [list, predicate], with `${item}` and `${index}` bound as for `fn::map`.
+    Filter(ExprMeta, Box<Expr<'src>>, Box<Expr<'src>>),
+    /// `fn::foreach` - like `fn::map`, with the element bound to a name of
+    /// the template's choosing, so loops can be nested.
+    Foreach(ExprMeta, ForeachExpr<'src>),
+
+    // --- Object builtins ---
+    /// `fn::merge` - merges a list of objects left to right as merge patches
+    /// (RFC 7386, with lists of named objects merged by `name`).
+    Merge(ExprMeta, Box<Expr<'src>>),
+    /// `fn::jsonPatch` - applies an RFC 6902 JSON Patch: [target, operations].
+    JsonPatch(ExprMeta, Box<Expr<'src>>, Box<Expr<'src>>),
 
     // --- Math builtins ---
     /// `fn::abs` - absolute value of a number.
@@ -57,12 +132,45 @@ pub enum Expr<'src> {
     Max(ExprMeta, Box<Expr<'src>>),
     /// `fn::min` - minimum value in a list of numbers.
     Min(ExprMeta, Box<Expr<'src>>),
+    /// `fn::formatDuration` - formats seconds as a duration such as `1h30m`.
+    FormatDuration(ExprMeta, Box<Expr<'src>>),
+    /// `fn::formatBytes` - formats a byte count as a size such as `5Gi`.
+    FormatBytes(ExprMeta, Box<Expr<'src>>),
 
     // --- String builtins ---
     /// `fn::stringLen` - length of a string (Unicode char count).
     StringLen(ExprMeta, Box<Expr<'src>>),
+    /// `fn::sort` - sorts a list of strings or numbers in ascending order.
+    Sort(ExprMeta, Box<Expr<'src>>),
+    /// `fn::unique` - removes duplicate items from a list.
+    Unique(ExprMeta, Box<Expr<'src>>),
+    /// `fn::length` - the number of items in a list or entries in an object.
+    Length(ExprMeta, Box<Expr<'src>>),
+    /// `fn::coalesce` - the first item of a list that is neither null nor an
+    /// empty string. Items of a literal list are evaluated only up to it.
+    Coalesce(ExprMeta, Box<Expr<'src>>),
     /// `fn::substring` - extracts a substring: [source, start, length].
     Substring(ExprMeta, Box<Expr<'src>>, Box<Expr<'src>>, Box<Expr<'src>>),
+    /// `fn::toUpper` - converts a string to upper case.
+    ToUpper(ExprMeta, Box<Expr<'src>>),
+    /// `fn::toLower` - converts a string to lower case.
+    ToLower(ExprMeta, Box<Expr<'src>>),
+    /// `fn::trim` - removes leading and trailing whitespace from a string.
+    Trim(ExprMeta, Box<Expr<'src>>),
+    /// `fn::replace` - replaces every occurrence of a substring: [source, search, replacement].
+    Replace(ExprMeta, Box<Expr<'src>>, Box<Expr<'src>>, Box<Expr<'src>>),
+    /// `fn::startsWith` - whether a string starts with a prefix: [source, prefix].
+    StartsWith(ExprMeta, Box<Expr<'src>>, Box<Expr<'src>>),
+    /// `fn::format` - fills the `{0}`, `{1}`, ... placeholders of a format string: [format, args].
+    Format(ExprMeta, Box<Expr<'src>>, Box<Expr<'src>>),
+
+    // --- Network builtins ---
+    /// `fn::cidrSubnet` - a subnet of a CIDR prefix: [prefix, newbits, netnum].
+    CidrSubnet(ExprMeta, Box<Expr<'src>>, Box<Expr<'src>>, Box<Expr<'src>>),
+    /// `fn::cidrHost` - an address within a CIDR prefix: [prefix, hostnum].
+    CidrHost(ExprMeta, Box<Expr<'src>>, Box<Expr<'src>>),
+    /// `fn::cidrNetmask` - the dotted netmask of an IPv4 CIDR prefix.
+    CidrNetmask(ExprMeta, Box<Expr<'src>>),
 
     // --- Time builtins ---
     /// `fn::timeUtc` - current UTC time as ISO 8601 string.
@@ -94,20 +202,28 @@ pub enum Expr<'src> {
     /// `fn::assetArchive` - creates an archive from a map of assets/archives.
     AssetArchive(ExprMeta, Vec<(Cow<'src, str>, Expr<'src>)>),
 
+    // --- ESC ---
+    /// `fn::open::<provider>` - opens an ESC provider function such as
+    /// `fn::open::aws-login`: (provider, inputs). Resolved by the host's
+    /// [`OpenResolver`](crate::eval::esc::OpenResolver).
+    Open(ExprMeta, Cow<'src, str>, Box<Expr<'src>>),
+
     // --- Starlark ---
     /// `fn::starlark` - calls a user-defined Starlark function.
     Starlark(ExprMeta, StarlarkCallExpr<'src>),
 }
 
 /// An object property: a key-value pair where the key is an expression (typically a string).
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ObjectProperty<'src> {
     pub key: Box<Expr<'src>>,
     pub value: Box<Expr<'src>>,
 }
 
 /// Arguments for `fn::invoke`.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct InvokeExpr<'src> {
     /// The function token (e.g. `aws:s3:getBucket`).
     pub token: Cow<'src, str>,
@@ -117,10 +233,13 @@ pub struct InvokeExpr<'src> {
     pub call_opts: InvokeOptions<'src>,
     /// Return directive (specific output property name).
     pub return_: Option<Cow<'src, str>>,
+    /// True when written as `fn::<token>` shorthand rather than `fn::invoke`.
+    pub shorthand: bool,
 }
 
 /// Arguments for `fn::starlark`.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct StarlarkCallExpr<'src> {
     /// The function name to invoke (e.g. "uppercase").
     pub invoke: Cow<'src, str>,
@@ -128,16 +247,55 @@ pub struct StarlarkCallExpr<'src> {
     pub input: Box<Expr<'src>>,
 }
 
+/// Arguments for `fn::foreach`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForeachExpr<'src> {
+    /// The list iterated over.
+    pub list: Box<Expr<'src>>,
+    /// The name the current element is bound to (`as:`).
+    pub var: Cow<'src, str>,
+    /// The name the current position is bound to, if any (`index:`).
+    pub index: Option<Cow<'src, str>>,
+    /// Evaluated once per element.
+    pub body: Box<Expr<'src>>,
+}
+
+impl ForeachExpr<'_> {
+    /// The names bound inside `body`.
+    pub fn bindings(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.var.as_ref()).chain(self.index.as_deref())
+    }
+}
+
 /// Options for `fn::invoke`.
-#[derive(Debug, Clone, Default, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct InvokeOptions<'src> {
     pub parent: Option<Box<Expr<'src>>>,
     pub provider: Option<Box<Expr<'src>>>,
     pub depends_on: Option<Box<Expr<'src>>>,
     pub version: Option<Cow<'src, str>>,
     pub plugin_download_url: Option<Cow<'src, str>>,
+    pub paginate: Option<PaginateDecl<'src>>,
+}
+
+/// The `paginate` invoke option: call the function again with each returned
+/// page token until it is empty, concatenating the pages' items.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaginateDecl<'src> {
+    /// The result property holding each page's list of items.
+    pub items_field: Cow<'src, str>,
+    /// The result property holding the next page's token.
+    pub token_field: Cow<'src, str>,
+    /// The argument the token is passed back in. Defaults to `token_field`.
+    pub token_argument: Option<Cow<'src, str>>,
 }
 
+/// Names bound inside the body of `fn::map` and `fn::filter`.
+pub const LOOP_BINDINGS: [&str; 2] = ["item", "index"];
+
 impl Expr<'_> {
     /// Returns the metadata (span info) for this expression.
     pub fn meta(&self) -> &ExprMeta {
@@ -154,17 +312,49 @@ impl Expr<'_> {
             | Expr::Join(m, _, _)
             | Expr::Select(m, _, _)
             | Expr::Split(m, _, _)
+            | Expr::ValidateJson(m, _, _)
+            | Expr::Assert(m, _, _)
+            | Expr::TemplateFile(m, _, _)
+            | Expr::JsonPatch(m, _, _)
+            | Expr::Merge(m, _)
+            | Expr::Map(m, _, _)
+            | Expr::Filter(m, _, _)
             | Expr::ToJson(m, _)
+            | Expr::ParseJson(m, _)
+            | Expr::ParseYaml(m, _)
             | Expr::ToBase64(m, _)
             | Expr::FromBase64(m, _)
             | Expr::Secret(m, _)
+            | Expr::Encrypted(m, _)
             | Expr::ReadFile(m, _)
+            | Expr::ReadFileBinary(m, _)
             | Expr::Abs(m, _)
             | Expr::Floor(m, _)
             | Expr::Ceil(m, _)
             | Expr::Max(m, _)
             | Expr::Min(m, _)
+            | Expr::FormatDuration(m, _)
+            | Expr::FormatBytes(m, _)
             | Expr::StringLen(m, _)
+            | Expr::Sort(m, _)
+            | Expr::Unique(m, _)
+            | Expr::Length(m, _)
+            | Expr::Coalesce(m, _)
+            | Expr::ToUpper(m, _)
+            | Expr::ToLower(m, _)
+            | Expr::Trim(m, _)
+            | Expr::StartsWith(m, _, _)
+            | Expr::Equals(m, _, _)
+            | Expr::Not(m, _)
+            | Expr::And(m, _)
+            | Expr::Or(m, _)
+            | Expr::LessThan(m, _, _)
+            | Expr::LessThanOrEqual(m, _, _)
+            | Expr::GreaterThan(m, _, _)
+            | Expr::GreaterThanOrEqual(m, _, _)
+            | Expr::Format(m, _, _)
+            | Expr::CidrHost(m, _, _)
+            | Expr::CidrNetmask(m, _)
             | Expr::TimeUtc(m, _)
             | Expr::TimeUnix(m, _)
             | Expr::Uuid(m, _)
@@ -176,8 +366,15 @@ impl Expr<'_> {
             | Expr::FileArchive(m, _)
             | Expr::RemoteArchive(m, _)
             | Expr::AssetArchive(m, _)
+            | Expr::Open(m, _, _)
+            | Expr::Foreach(m, _)
             | Expr::Starlark(m, _) => m,
-            Expr::Substring(m, _, _, _) => m,
+            Expr::Substring(m, _, _, _)
+            | Expr::Replace(m, _, _, _)
+            | Expr::CidrSubnet(m, _, _, _)
+            | Expr::If(m, _, _, _)
+            | Expr::Lookup(m, _, _, _)
+            | Expr::Env(m, _, _) => m,
         }
     }
 
@@ -194,6 +391,125 @@ impl Expr<'_> {
         matches!(self, Expr::Symbol(_, _))
     }
 
+    /// Converts into an owned `'static` expression that no longer borrows
+    /// from the source.
+    pub fn into_owned(self) -> Expr<'static> {
+        fn boxed(e: Box<Expr<'_>>) -> Box<Expr<'static>> {
+            Box::new(e.into_owned())
+        }
+        match self {
+            Expr::Null(m) => Expr::Null(m),
+            Expr::Bool(m, b) => Expr::Bool(m, b),
+            Expr::Number(m, n) => Expr::Number(m, n),
+            Expr::String(m, s) => Expr::String(m, owned(s)),
+            Expr::Interpolate(m, parts) => Expr::Interpolate(
+                m,
+                parts
+                    .into_iter()
+                    .map(InterpolationPart::into_owned)
+                    .collect(),
+            ),
+            Expr::Symbol(m, access) => Expr::Symbol(m, access.into_owned()),
+            Expr::List(m, items) => {
+                Expr::List(m, items.into_iter().map(Expr::into_owned).collect())
+            }
+            Expr::Object(m, entries) => Expr::Object(
+                m,
+                entries
+                    .into_iter()
+                    .map(ObjectProperty::into_owned)
+                    .collect(),
+            ),
+            Expr::Invoke(m, invoke) => Expr::Invoke(m, invoke.into_owned()),
+            Expr::Join(m, a, b) => Expr::Join(m, boxed(a), boxed(b)),
+            Expr::Select(m, a, b) => Expr::Select(m, boxed(a), boxed(b)),
+            Expr::Split(m, a, b) => Expr::Split(m, boxed(a), boxed(b)),
+            Expr::ValidateJson(m, a, b) => Expr::ValidateJson(m, boxed(a), boxed(b)),
+            Expr::Assert(m, a, b) => Expr::Assert(m, boxed(a), boxed(b)),
+            Expr::TemplateFile(m, a, b) => Expr::TemplateFile(m, boxed(a), boxed(b)),
+            Expr::Map(m, a, b) => Expr::Map(m, boxed(a), boxed(b)),
+            Expr::Filter(m, a, b) => Expr::Filter(m, boxed(a), boxed(b)),
+            Expr::JsonPatch(m, a, b) => Expr::JsonPatch(m, boxed(a), boxed(b)),
+            Expr::Lookup(m, a, b, c) => Expr::Lookup(m, boxed(a), boxed(b), c.map(boxed)),
+            Expr::Substring(m, a, b, c) => Expr::Substring(m, boxed(a), boxed(b), boxed(c)),
+            Expr::Replace(m, a, b, c) => Expr::Replace(m, boxed(a), boxed(b), boxed(c)),
+            Expr::If(m, a, b, c) => Expr::If(m, boxed(a), boxed(b), boxed(c)),
+            Expr::Equals(m, a, b) => Expr::Equals(m, boxed(a), boxed(b)),
+            Expr::Not(m, a) => Expr::Not(m, boxed(a)),
+            Expr::And(m, a) => Expr::And(m, boxed(a)),
+            Expr::Or(m, a) => Expr::Or(m, boxed(a)),
+            Expr::LessThan(m, a, b) => Expr::LessThan(m, boxed(a), boxed(b)),
+            Expr::LessThanOrEqual(m, a, b) => Expr::LessThanOrEqual(m, boxed(a), boxed(b)),
+            Expr::GreaterThan(m, a, b) => Expr::GreaterThan(m, boxed(a), boxed(b)),
+            Expr::GreaterThanOrEqual(m, a, b) => Expr::GreaterThanOrEqual(m, boxed(a), boxed(b)),
+            Expr::ToJson(m, a) => Expr::ToJson(m, boxed(a)),
+            Expr::ParseJson(m, a) => Expr::ParseJson(m, boxed(a)),
+            Expr::ParseYaml(m, a) => Expr::ParseYaml(m, boxed(a)),
+            Expr::ToBase64(m, a) => Expr::ToBase64(m, boxed(a)),
+            Expr::FromBase64(m, a) => Expr::FromBase64(m, boxed(a)),
+            Expr::Secret(m, a) => Expr::Secret(m, boxed(a)),
+            Expr::Encrypted(m, a) => Expr::Encrypted(m, boxed(a)),
+            Expr::ReadFile(m, a) => Expr::ReadFile(m, boxed(a)),
+            Expr::ReadFileBinary(m, a) => Expr::ReadFileBinary(m, boxed(a)),
+            Expr::Env(m, a, b) => Expr::Env(m, boxed(a), b.map(boxed)),
+            Expr::Merge(m, a) => Expr::Merge(m, boxed(a)),
+            Expr::Abs(m, a) => Expr::Abs(m, boxed(a)),
+            Expr::Floor(m, a) => Expr::Floor(m, boxed(a)),
+            Expr::Ceil(m, a) => Expr::Ceil(m, boxed(a)),
+            Expr::Max(m, a) => Expr::Max(m, boxed(a)),
+            Expr::Min(m, a) => Expr::Min(m, boxed(a)),
+            Expr::FormatDuration(m, a) => Expr::FormatDuration(m, boxed(a)),
+            Expr::FormatBytes(m, a) => Expr::FormatBytes(m, boxed(a)),
+            Expr::StringLen(m, a) => Expr::StringLen(m, boxed(a)),
+            Expr::Sort(m, a) => Expr::Sort(m, boxed(a)),
+            Expr::Unique(m, a) => Expr::Unique(m, boxed(a)),
+            Expr::Length(m, a) => Expr::Length(m, boxed(a)),
+            Expr::Coalesce(m, a) => Expr::Coalesce(m, boxed(a)),
+            Expr::ToUpper(m, a) => Expr::ToUpper(m, boxed(a)),
+            Expr::ToLower(m, a) => Expr::ToLower(m, boxed(a)),
+            Expr::Trim(m, a) => Expr::Trim(m, boxed(a)),
+            Expr::StartsWith(m, a, b) => Expr::StartsWith(m, boxed(a), boxed(b)),
+            Expr::Format(m, a, b) => Expr::Format(m, boxed(a), boxed(b)),
+            Expr::CidrSubnet(m, a, b, c) => Expr::CidrSubnet(m, boxed(a), boxed(b), boxed(c)),
+            Expr::CidrHost(m, a, b) => Expr::CidrHost(m, boxed(a), boxed(b)),
+            Expr::CidrNetmask(m, a) => Expr::CidrNetmask(m, boxed(a)),
+            Expr::TimeUtc(m, a) => Expr::TimeUtc(m, boxed(a)),
+            Expr::TimeUnix(m, a) => Expr::TimeUnix(m, boxed(a)),
+            Expr::Uuid(m, a) => Expr::Uuid(m, boxed(a)),
+            Expr::RandomString(m, a) => Expr::RandomString(m, boxed(a)),
+            Expr::DateFormat(m, a) => Expr::DateFormat(m, boxed(a)),
+            Expr::StringAsset(m, a) => Expr::StringAsset(m, boxed(a)),
+            Expr::FileAsset(m, a) => Expr::FileAsset(m, boxed(a)),
+            Expr::RemoteAsset(m, a) => Expr::RemoteAsset(m, boxed(a)),
+            Expr::FileArchive(m, a) => Expr::FileArchive(m, boxed(a)),
+            Expr::RemoteArchive(m, a) => Expr::RemoteArchive(m, boxed(a)),
+            Expr::AssetArchive(m, entries) => Expr::AssetArchive(
+                m,
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (owned(k), v.into_owned()))
+                    .collect(),
+            ),
+            Expr::Open(m, provider, inputs) => Expr::Open(m, owned(provider), boxed(inputs)),
+            Expr::Foreach(m, foreach) => Expr::Foreach(
+                m,
+                ForeachExpr {
+                    list: boxed(foreach.list),
+                    var: owned(foreach.var),
+                    index: foreach.index.map(owned),
+                    body: boxed(foreach.body),
+                },
+            ),
+            Expr::Starlark(m, call) => Expr::Starlark(
+                m,
+                StarlarkCallExpr {
+                    invoke: owned(call.invoke),
+                    input: boxed(call.input),
+                },
+            ),
+        }
+    }
+
     /// Returns true if this is an asset or archive expression.
     pub fn is_asset_or_archive(&self) -> bool {
         matches!(
@@ -208,6 +524,46 @@ impl Expr<'_> {
     }
 }
 
+impl ObjectProperty<'_> {
+    /// Converts into an owned `'static` property.
+    pub fn into_owned(self) -> ObjectProperty<'static> {
+        ObjectProperty {
+            key: Box::new(self.key.into_owned()),
+            value: Box::new(self.value.into_owned()),
+        }
+    }
+}
+
+impl InvokeExpr<'_> {
+    /// Converts into an owned `'static` invoke.
+    pub fn into_owned(self) -> InvokeExpr<'static> {
+        let opts = self.call_opts;
+        InvokeExpr {
+            token: owned(self.token),
+            call_args: self.call_args.map(|e| Box::new(e.into_owned())),
+            call_opts: InvokeOptions {
+                parent: opts.parent.map(|e| Box::new(e.into_owned())),
+                provider: opts.provider.map(|e| Box::new(e.into_owned())),
+                depends_on: opts.depends_on.map(|e| Box::new(e.into_owned())),
+                version: opts.version.map(owned),
+                plugin_download_url: opts.plugin_download_url.map(owned),
+                paginate: opts.paginate.map(|p| PaginateDecl {
+                    items_field: owned(p.items_field),
+                    token_field: owned(p.token_field),
+                    token_argument: p.token_argument.map(owned),
+                }),
+            },
+            return_: self.return_.map(owned),
+            shorthand: self.shorthand,
+        }
+    }
+}
+
+/// Detaches a string from the source it borrows from.
+pub(crate) fn owned(s: Cow<'_, str>) -> Cow<'static, str> {
+    Cow::Owned(s.into_owned())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -257,6 +613,7 @@ mod tests {
             call_args: None,
             call_opts: InvokeOptions::default(),
             return_: Some(Cow::Borrowed("arn")),
+            shorthand: false,
         };
         let expr = Expr::Invoke(ExprMeta::no_span(), invoke);
         assert!(expr.meta().span.is_none());
@@ -266,7 +623,7 @@ mod tests {
     fn test_object_property() {
         let prop = ObjectProperty {
             key: Box::new(Expr::String(ExprMeta::no_span(), Cow::Borrowed("key"))),
-            value: Box::new(Expr::Number(ExprMeta::no_span(), 42.0)),
+            value: Box::new(Expr::Number(ExprMeta::no_span(), 42.0.into())),
         };
         assert_eq!(prop.key.as_str(), Some("key"));
     }
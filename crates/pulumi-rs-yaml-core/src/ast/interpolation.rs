@@ -1,13 +1,16 @@
-use crate::ast::property::{parse_property_access, PropertyAccess};
+use crate::ast::expr::owned;
+use crate::ast::property::{parse_property_access_at, PropertyAccess};
 use crate::diag::Diagnostics;
 use crate::syntax::Span;
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 
 /// A single part of an interpolated string.
 ///
 /// Interpolations have the form `"text ${property.access} more text"`.
 /// Each part has a text prefix and an optional property access reference.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct InterpolationPart<'src> {
     /// Literal text before the property access (or the trailing text).
     pub text: Cow<'src, str>,
@@ -15,12 +18,27 @@ pub struct InterpolationPart<'src> {
     pub value: Option<PropertyAccess<'src>>,
 }
 
+impl InterpolationPart<'_> {
+    /// Converts into an owned `'static` part.
+    pub fn into_owned(self) -> InterpolationPart<'static> {
+        InterpolationPart {
+            text: owned(self.text),
+            value: self.value.map(PropertyAccess::into_owned),
+        }
+    }
+}
+
 /// Parses an interpolated string into its constituent parts.
 ///
 /// Syntax:
-/// - `$$` is an escaped dollar sign (produces a single `$`)
-/// - `${...}` is a property access expression
+/// - `$${` is an escaped interpolation (produces a literal `${`); any other
+///   `$$` is kept as written
+/// - `${...}` is a property access expression; subscripts may nest another
+///   property access (`${a[b.c]}`)
 /// - Everything else is literal text
+///
+/// Malformed sequences emit an error whose detail carries the byte offset
+/// within `input` of the offending character.
 pub fn parse_interpolation<'src>(
     input: &'src str,
     span: Option<Span>,
@@ -34,15 +52,19 @@ pub fn parse_interpolation<'src>(
     while i < bytes.len() {
         if bytes[i] == b'$' && i + 1 < bytes.len() {
             match bytes[i + 1] {
-                b'$' => {
-                    // Escaped dollar sign
+                b'$' if bytes.get(i + 2) == Some(&b'{') => {
+                    // Escaped interpolation: `$${` yields a literal `${`
                     current_text.push('$');
                     i += 2;
                 }
+                b'$' => {
+                    current_text.push_str("$$");
+                    i += 2;
+                }
                 b'{' => {
                     // Property access interpolation
                     let after_brace = &input[i + 2..];
-                    let (rest, access) = parse_property_access(after_brace, span, diags);
+                    let (rest, access) = parse_property_access_at(after_brace, i + 2, span, diags);
 
                     if let Some(access) = access {
                         let text = if current_text.is_empty() {
@@ -160,11 +182,11 @@ mod tests {
     }
 
     #[test]
-    fn test_escaped_dollar() {
-        let parts = parse_ok("cost is $$100");
+    fn test_double_dollar_kept() {
+        let parts = parse_ok("cost is $$100 for ${item}");
         assert_eq!(parts.len(), 1);
-        assert_eq!(parts[0].text.as_ref(), "cost is $100");
-        assert!(parts[0].value.is_none());
+        assert_eq!(parts[0].text.as_ref(), "cost is $$100 for ");
+        assert_eq!(parts[0].value.as_ref().unwrap().to_string(), "item");
     }
 
     #[test]
@@ -209,4 +231,39 @@ mod tests {
         let access = parts[0].value.as_ref().unwrap();
         assert_eq!(access.to_string(), "obj[\"key\"]");
     }
+
+    #[test]
+    fn test_escaped_interpolation_mixed() {
+        let parts = parse_ok("$${literal} and ${real.value}");
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].text.as_ref(), "${literal} and ");
+        assert_eq!(parts[0].value.as_ref().unwrap().to_string(), "real.value");
+    }
+
+    #[test]
+    fn test_nested_access_key() {
+        let parts = parse_ok("${amis[config.region].id}");
+        assert_eq!(parts.len(), 1);
+        let access = parts[0].value.as_ref().unwrap();
+        assert_eq!(access.to_string(), "amis[config.region].id");
+        assert_eq!(access.nested_root_names(), vec!["config"]);
+    }
+
+    #[test]
+    fn test_malformed_reports_offset() {
+        let mut diags = Diagnostics::new();
+        parse_interpolation("abc ${foo..bar}", None, &mut diags);
+        assert!(diags.has_errors());
+        let diag = diags.iter().next().unwrap();
+        assert_eq!(diag.summary, "expected a property name after '.'");
+        assert_eq!(diag.detail, "at offset 10 of the interpolation");
+    }
+
+    #[test]
+    fn test_empty_interpolation_error() {
+        let mut diags = Diagnostics::new();
+        let parts = parse_interpolation("x${}", None, &mut diags);
+        assert!(diags.has_errors());
+        assert!(parts.iter().all(|p| p.value.is_none()));
+    }
 }
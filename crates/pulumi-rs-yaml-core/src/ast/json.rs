@@ -0,0 +1,165 @@
+//! Stable JSON form of parsed templates.
+//!
+//! Plans, editor caches, and external tools can persist a [`TemplateDecl`]
+//! and load it back without the source text or re-parsing. The template is
+//! wrapped in an envelope carrying [`SCHEMA_VERSION`]:
+//!
+//! ```json
+//! {"version": 1, "template": {"name": "app", "variables": [...], ...}}
+//! ```
+//!
+//! Field and variant names are the camelCase names of the AST types, and
+//! expressions are externally tagged, e.g. `{"join": [{}, sep, values]}`
+//! where the first element is the node's metadata (its span, if any).
+//! Loaded templates own their strings, so they outlive the JSON.
+//!
+//! Unknown fields are ignored, so documents written by a newer release that
+//! only added fields still load. The version is bumped for anything else,
+//! and documents with a newer version are rejected.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ast::template::TemplateDecl;
+
+/// Version of the JSON form written by [`to_json`].
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Errors from loading a template from its JSON form.
+#[derive(Debug, thiserror::Error)]
+pub enum AstJsonError {
+    #[error(
+        "template JSON has schema version {found}, but this release reads up to {SCHEMA_VERSION}"
+    )]
+    UnsupportedVersion { found: u32 },
+    #[error("invalid template JSON: {0}")]
+    Invalid(#[from] serde_json::Error),
+}
+
+#[derive(Serialize)]
+struct EnvelopeRef<'a, 'src> {
+    version: u32,
+    template: &'a TemplateDecl<'src>,
+}
+
+#[derive(Deserialize)]
+struct Envelope {
+    version: u32,
+    template: serde_json::Value,
+}
+
+/// Returns the JSON form of `template`.
+pub fn to_json(template: &TemplateDecl<'_>) -> serde_json::Value {
+    serde_json::to_value(EnvelopeRef {
+        version: SCHEMA_VERSION,
+        template,
+    })
+    .expect("templates serialize")
+}
+
+/// Returns the JSON form of `template` as a string.
+pub fn to_string(template: &TemplateDecl<'_>) -> String {
+    to_json(template).to_string()
+}
+
+/// Loads a template from its JSON form.
+pub fn from_json(json: serde_json::Value) -> Result<TemplateDecl<'static>, AstJsonError> {
+    let envelope: Envelope = serde_json::from_value(json)?;
+    if envelope.version > SCHEMA_VERSION {
+        return Err(AstJsonError::UnsupportedVersion {
+            found: envelope.version,
+        });
+    }
+    Ok(serde_json::from_value(envelope.template)?)
+}
+
+/// Loads a template from its JSON form in a string.
+pub fn from_str(json: &str) -> Result<TemplateDecl<'static>, AstJsonError> {
+    from_json(serde_json::from_str(json)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::parse::parse_template;
+
+    const SOURCE: &str = r#"name: app
+config:
+  prefix:
+    type: string
+    default: dev
+variables:
+  names:
+    fn::map:
+      - ["a", "b"]
+      - ${prefix}-${item}
+  tags:
+    fn::merge:
+      - env: ${pulumi.stack}
+      - team: core
+resources:
+  bucket:
+    type: aws:s3:Bucket
+    name: ${prefix}-bucket
+    properties:
+      tags: ${tags}
+      website:
+        indexDocument:
+          fn::readFile: ./index.html
+    options:
+      protect: true
+      dependsOn: ["${other}"]
+      customTimeouts:
+        create: 5m
+  other:
+    type: aws:s3:Bucket
+outputs:
+  first: ${names[0]}
+  secret:
+    fn::secret: ${bucket.arn}
+"#;
+
+    #[test]
+    fn test_round_trip() {
+        let (template, diags) = parse_template(SOURCE, None);
+        assert!(!diags.has_errors(), "{}", diags);
+        let loaded = from_str(&to_string(&template)).unwrap();
+        assert_eq!(loaded, template);
+    }
+
+    #[test]
+    fn test_json_shape() {
+        let (template, _) = parse_template(SOURCE, None);
+        let json = to_json(&template);
+        assert_eq!(json["version"], SCHEMA_VERSION);
+        let resource = &json["template"]["resources"][0];
+        assert_eq!(resource["logicalName"], "bucket");
+        assert_eq!(resource["resource"]["type"], "aws:s3:Bucket");
+        assert_eq!(
+            json["template"]["outputs"][1]["value"]["secret"][1]["symbol"][1]["accessors"],
+            serde_json::json!([{"name": "bucket"}, {"name": "arn"}])
+        );
+    }
+
+    #[test]
+    fn test_unknown_fields_are_ignored() {
+        let (template, _) = parse_template(SOURCE, None);
+        let mut json = to_json(&template);
+        json["template"]["addedLater"] = serde_json::json!(true);
+        assert_eq!(from_json(json).unwrap(), template);
+    }
+
+    #[test]
+    fn test_newer_version_is_rejected() {
+        let (template, _) = parse_template(SOURCE, None);
+        let mut json = to_json(&template);
+        json["version"] = serde_json::json!(SCHEMA_VERSION + 1);
+        assert!(matches!(
+            from_json(json),
+            Err(AstJsonError::UnsupportedVersion { found }) if found == SCHEMA_VERSION + 1
+        ));
+        assert!(matches!(
+            from_str("{\"template\": {}}"),
+            Err(AstJsonError::Invalid(_))
+        ));
+    }
+}
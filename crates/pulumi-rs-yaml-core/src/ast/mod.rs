@@ -1,6 +1,9 @@
 pub mod expr;
 pub mod interpolation;
+pub mod json;
 pub mod parse;
+pub mod positions;
 pub mod property;
+pub mod snippets;
 pub mod template;
 pub mod visitor;
@@ -1,8 +1,16 @@
-use crate::ast::expr::{Expr, InvokeExpr, InvokeOptions, ObjectProperty, StarlarkCallExpr};
+use crate::ast::expr::{
+    Expr, ForeachExpr, InvokeExpr, InvokeOptions, ObjectProperty, PaginateDecl, StarlarkCallExpr,
+};
 use crate::ast::interpolation::{has_interpolations, parse_interpolation};
+use crate::ast::positions::{self, NodeKind, SourceNode};
+use crate::ast::property::is_valid_property_name;
 use crate::ast::template::*;
 use crate::diag::{unexpected_casing, Diagnostics};
+use crate::number::Number;
+use crate::source::FileId;
 use crate::syntax::{ExprMeta, Span};
+use crate::version::VersionConstraint;
+use serde::Deserialize;
 use std::borrow::Cow;
 use std::collections::HashSet;
 
@@ -12,16 +20,32 @@ use std::collections::HashSet;
 /// produced by parsing are `Cow::Owned`. The `'static` lifetime reflects this.
 /// When the source text is available (e.g., for interpolation parsing), we use
 /// owned copies of the relevant substrings.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(bytes = source.len())))]
 pub fn parse_template(source: &str, span: Option<Span>) -> (TemplateDecl<'static>, Diagnostics) {
+    parse_template_with_duplicate_keys(source, span, DuplicateKeys::Error)
+}
+
+/// Like [`parse_template`], with `duplicate_keys` as the policy for a
+/// document that does not set `pulumi.duplicateKeys` itself. Multi-file
+/// projects pass the policy of their `Pulumi.yaml` here.
+pub fn parse_template_with_duplicate_keys(
+    source: &str,
+    span: Option<Span>,
+    duplicate_keys: DuplicateKeys,
+) -> (TemplateDecl<'static>, Diagnostics) {
     let mut diags = Diagnostics::new();
 
-    let yaml: serde_yaml::Value = match serde_yaml::from_str(source) {
+    let mut yaml = match load_yaml(source, span, duplicate_keys, &mut diags) {
         Ok(v) => v,
         Err(e) => {
             diags.error(span, format!("failed to parse YAML: {}", e), "");
             return (TemplateDecl::new(), diags);
         }
     };
+    let file = span.map_or(FileId(0), |s| s.file);
+    let nodes = positions::index(source, file, span.map_or(0, |s| s.start));
+    super::snippets::expand_snippets(&mut yaml, nodes.as_ref(), &mut diags);
+    let _positions = positions::PositionsGuard::enter(&yaml, nodes.as_ref());
 
     let mapping = match yaml.as_mapping() {
         Some(m) => m,
@@ -58,16 +82,19 @@ pub fn parse_template(source: &str, span: Option<Span>) -> (TemplateDecl<'static
                 }
             }
             "runtime" => {
-                // Runtime is metadata for the engine, not parsed into AST
+                template.runtime = parse_runtime(value, &mut diags);
+            }
+            "plugins" => {
+                template.plugins = parse_plugins(value, &mut diags);
             }
             "pulumi" => {
                 template.pulumi = parse_pulumi_decl(value, &mut diags);
             }
             "config" | "configuration" => {
-                template.config = parse_config_map(value, &mut diags);
+                template.config = parse_config_map(value, None, &mut diags);
             }
             "variables" => {
-                template.variables = parse_variables_map(value, &mut diags);
+                template.variables = parse_variables_map(value, "variables", &mut diags);
             }
             "resources" => {
                 template.resources = parse_resources_map(value, &mut diags);
@@ -81,22 +108,268 @@ pub fn parse_template(source: &str, span: Option<Span>) -> (TemplateDecl<'static
             "starlark" => {
                 template.starlark_functions = parse_starlark_block(value, &mut diags);
             }
+            "imports" => {
+                template.imports = parse_imports(value, &mut diags);
+            }
+            "extends" => match value.as_str() {
+                Some(s) => template.extends = Some(Cow::Owned(s.to_string())),
+                None => diags.error(
+                    span,
+                    "extends: must be the path of a base template",
+                    "Expected:\n  extends: ../base/Pulumi.base.yaml",
+                ),
+            },
             _ => {
                 // Unknown top-level keys are ignored
             }
         }
     }
 
+    if let Some(nodes) = nodes.as_ref().filter(|_| template.pulumi.strict_scalars) {
+        check_yaml11_scalars(nodes, &mut diags);
+    }
+
     (template, diags)
 }
 
+/// Parses YAML source, applying the document's `pulumi.duplicateKeys`
+/// policy, or `default` when the document does not set one.
+///
+/// A strict parse is tried first. If it fails, the source is parsed again
+/// keeping the last value of each repeated key; when the policy is
+/// `warn`, each repeated key becomes a warning and the lenient value is
+/// returned. Otherwise the strict error is returned.
+pub fn load_yaml(
+    source: &str,
+    span: Option<Span>,
+    default: DuplicateKeys,
+    diags: &mut Diagnostics,
+) -> Result<serde_yaml::Value, serde_yaml::Error> {
+    let err = match serde_yaml::from_str(source) {
+        Ok(v) => return Ok(v),
+        Err(e) => e,
+    };
+    let Ok(lenient) = Lenient::deserialize(serde_yaml::Deserializer::from_str(source)) else {
+        return Err(err);
+    };
+    if lenient.duplicates.is_empty()
+        || duplicate_keys_policy(&lenient.value, default) != DuplicateKeys::Warn
+    {
+        return Err(err);
+    }
+    for key in &lenient.duplicates {
+        diags.warning(
+            span,
+            format!("duplicate key '{}'; keeping the last value", key),
+            "pulumi.duplicateKeys is 'warn'; remove the earlier entries to silence this",
+        );
+    }
+    Ok(lenient.value)
+}
+
+/// Reads `pulumi.duplicateKeys` from a parsed document, falling back to
+/// `default` when it is not set.
+fn duplicate_keys_policy(yaml: &serde_yaml::Value, default: DuplicateKeys) -> DuplicateKeys {
+    fn entry<'v>(value: &'v serde_yaml::Value, key: &str) -> Option<&'v serde_yaml::Value> {
+        value.as_mapping()?.iter().find_map(|(k, v)| {
+            k.as_str()
+                .is_some_and(|k| k.eq_ignore_ascii_case(key))
+                .then_some(v)
+        })
+    }
+    match entry(yaml, "pulumi")
+        .and_then(|pulumi| entry(pulumi, "duplicateKeys"))
+        .and_then(|v| v.as_str())
+    {
+        Some("warn") => DuplicateKeys::Warn,
+        Some("error") => DuplicateKeys::Error,
+        _ => default,
+    }
+}
+
+/// A YAML value deserialized without rejecting repeated mapping keys. The
+/// last value of a repeated key wins; `duplicates` holds the dotted path of
+/// each repetition.
+struct Lenient {
+    value: serde_yaml::Value,
+    duplicates: Vec<String>,
+}
+
+impl Lenient {
+    fn leaf(value: impl Into<serde_yaml::Value>) -> Self {
+        Lenient {
+            value: value.into(),
+            duplicates: Vec::new(),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Lenient {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(LenientVisitor)
+    }
+}
+
+struct LenientVisitor;
+
+impl<'de> serde::de::Visitor<'de> for LenientVisitor {
+    type Value = Lenient;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("any YAML value")
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<Lenient, E> {
+        Ok(Lenient::leaf(b))
+    }
+
+    fn visit_i64<E>(self, i: i64) -> Result<Lenient, E> {
+        Ok(Lenient::leaf(i))
+    }
+
+    fn visit_u64<E>(self, u: u64) -> Result<Lenient, E> {
+        Ok(Lenient::leaf(u))
+    }
+
+    fn visit_f64<E>(self, f: f64) -> Result<Lenient, E> {
+        Ok(Lenient::leaf(f))
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Lenient, E> {
+        Ok(Lenient::leaf(s))
+    }
+
+    fn visit_string<E>(self, s: String) -> Result<Lenient, E> {
+        Ok(Lenient::leaf(s))
+    }
+
+    fn visit_unit<E>(self) -> Result<Lenient, E> {
+        Ok(Lenient::leaf(serde_yaml::Value::Null))
+    }
+
+    fn visit_none<E>(self) -> Result<Lenient, E> {
+        Ok(Lenient::leaf(serde_yaml::Value::Null))
+    }
+
+    fn visit_some<D: serde::Deserializer<'de>>(self, d: D) -> Result<Lenient, D::Error> {
+        Lenient::deserialize(d)
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Lenient, A::Error> {
+        let mut items = Vec::new();
+        let mut duplicates = Vec::new();
+        while let Some(item) = seq.next_element::<Lenient>()? {
+            let index = items.len();
+            duplicates.extend(item.duplicates.into_iter().map(|d| format!("{index}.{d}")));
+            items.push(item.value);
+        }
+        Ok(Lenient {
+            value: serde_yaml::Value::Sequence(items),
+            duplicates,
+        })
+    }
+
+    fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> Result<Lenient, A::Error> {
+        let mut mapping = serde_yaml::Mapping::new();
+        let mut duplicates = Vec::new();
+        while let Some((key, value)) = map.next_entry::<Lenient, Lenient>()? {
+            let label = match &key.value {
+                serde_yaml::Value::String(s) => s.clone(),
+                other => serde_yaml::to_string(other)
+                    .map(|s| s.trim_end().to_string())
+                    .unwrap_or_default(),
+            };
+            if mapping.contains_key(&key.value) {
+                duplicates.push(label.clone());
+            }
+            duplicates.extend(value.duplicates.into_iter().map(|d| format!("{label}.{d}")));
+            mapping.insert(key.value, value.value);
+        }
+        Ok(Lenient {
+            value: serde_yaml::Value::Mapping(mapping),
+            duplicates,
+        })
+    }
+
+    fn visit_enum<A: serde::de::EnumAccess<'de>>(self, data: A) -> Result<Lenient, A::Error> {
+        use serde::de::VariantAccess;
+        let (tag, contents) = data.variant::<String>()?;
+        let inner = contents.newtype_variant::<Lenient>()?;
+        Ok(Lenient {
+            value: serde_yaml::Value::Tagged(Box::new(serde_yaml::value::TaggedValue {
+                tag: serde_yaml::value::Tag::new(tag),
+                value: inner.value,
+            })),
+            duplicates: inner.duplicates,
+        })
+    }
+}
+
+/// Plain scalars that YAML 1.1 reads as booleans. This parser follows the
+/// YAML 1.2 core schema and reads them as strings.
+const YAML11_BOOLS: &[&str] = &[
+    "y", "Y", "yes", "Yes", "YES", "n", "N", "no", "No", "NO", "on", "On", "ON", "off", "Off",
+    "OFF",
+];
+
+/// Warns about each unquoted [`YAML11_BOOLS`] value in the document `node`.
+///
+/// These stay strings, but tools still on YAML 1.1 (and readers used to it)
+/// take them for booleans — the "Norway problem", where a country code `NO`
+/// becomes `false`. Quoting the value makes the intent explicit. Keys are
+/// left alone.
+fn check_yaml11_scalars(node: &SourceNode, diags: &mut Diagnostics) {
+    match &node.kind {
+        NodeKind::Scalar { value, plain } => {
+            if *plain && YAML11_BOOLS.contains(&value.as_str()) {
+                diags.warning(
+                    Some(node.span),
+                    format!(
+                        "unquoted '{}' is read as the string \"{}\", not a boolean",
+                        value, value
+                    ),
+                    format!(
+                        "YAML 1.1 tools read '{}' as a boolean; quote it (\"{}\") to keep the string, or use true/false",
+                        value, value
+                    ),
+                );
+            }
+        }
+        NodeKind::Sequence(items) => {
+            for item in items {
+                check_yaml11_scalars(item, diags);
+            }
+        }
+        NodeKind::Mapping(entries) => {
+            for (_, value) in entries {
+                check_yaml11_scalars(value, diags);
+            }
+        }
+    }
+}
+
 /// Parses a `serde_yaml::Value` into an `Expr<'static>`.
 pub fn parse_expr(value: &serde_yaml::Value, diags: &mut Diagnostics) -> Expr<'static> {
-    let meta = ExprMeta::no_span();
+    let meta = ExprMeta {
+        span: positions::span_of(value),
+    };
     match value {
         serde_yaml::Value::Null => Expr::Null(meta),
         serde_yaml::Value::Bool(b) => Expr::Bool(meta, *b),
-        serde_yaml::Value::Number(n) => Expr::Number(meta, n.as_f64().unwrap_or(0.0)),
+        serde_yaml::Value::Number(n) => {
+            let number = Number::from_yaml(n);
+            if n.is_u64() && !number.is_int() {
+                diags.warning(
+                    meta.span,
+                    format!("integer {} does not fit in a 64-bit signed integer", n),
+                    format!(
+                        "it is used as the floating-point number {}; quote it to keep it exact",
+                        number
+                    ),
+                );
+            }
+            Expr::Number(meta, number)
+        }
         serde_yaml::Value::String(s) => parse_string_expr_owned(s, meta, diags),
         serde_yaml::Value::Sequence(seq) => {
             let elements: Vec<Expr<'static>> = seq.iter().map(|v| parse_expr(v, diags)).collect();
@@ -109,7 +382,9 @@ pub fn parse_expr(value: &serde_yaml::Value, diags: &mut Diagnostics) -> Expr<'s
 
 /// Parses an owned string that may contain interpolations.
 fn parse_string_expr_owned(s: &str, meta: ExprMeta, diags: &mut Diagnostics) -> Expr<'static> {
-    if !has_interpolations(s) {
+    // Strings with escaped interpolations still go through the interpolation
+    // parser so that `$${literal}` yields `${literal}`.
+    if !has_interpolations(s) && !s.contains("$${") {
         return Expr::String(meta, Cow::Owned(s.to_string()));
     }
 
@@ -124,25 +399,7 @@ fn parse_string_expr_owned(s: &str, meta: ExprMeta, diags: &mut Diagnostics) ->
         .into_iter()
         .map(|p| crate::ast::interpolation::InterpolationPart {
             text: Cow::Owned(p.text.into_owned()),
-            value: p.value.map(|a| crate::ast::property::PropertyAccess {
-                accessors: a
-                    .accessors
-                    .into_iter()
-                    .map(|acc| match acc {
-                        crate::ast::property::PropertyAccessor::Name(n) => {
-                            crate::ast::property::PropertyAccessor::Name(Cow::Owned(n.into_owned()))
-                        }
-                        crate::ast::property::PropertyAccessor::StringSubscript(s) => {
-                            crate::ast::property::PropertyAccessor::StringSubscript(Cow::Owned(
-                                s.into_owned(),
-                            ))
-                        }
-                        crate::ast::property::PropertyAccessor::IntSubscript(i) => {
-                            crate::ast::property::PropertyAccessor::IntSubscript(i)
-                        }
-                    })
-                    .collect(),
-            }),
+            value: p.value.map(|a| a.into_owned()),
         })
         .collect();
 
@@ -195,6 +452,9 @@ fn parse_object_or_builtin(
     Expr::Object(meta, entries)
 }
 
+/// The prefix of ESC provider function keys, e.g. `fn::open::aws-login`.
+const OPEN_PREFIX: &str = "fn::open::";
+
 /// Tries to parse a single-key object as a builtin function call.
 fn try_parse_builtin(
     key: &str,
@@ -251,6 +511,16 @@ fn try_parse_builtin(
             let args = parse_expr(value, diags);
             return Some(Expr::ToJson(meta, Box::new(args)));
         }
+        "fn::parsejson" => {
+            check_casing(key, "fn::parseJSON", diags);
+            let args = parse_expr(value, diags);
+            return Some(Expr::ParseJson(meta, Box::new(args)));
+        }
+        "fn::parseyaml" => {
+            check_casing(key, "fn::parseYAML", diags);
+            let args = parse_expr(value, diags);
+            return Some(Expr::ParseYaml(meta, Box::new(args)));
+        }
         "fn::tobase64" => {
             check_casing(key, "fn::toBase64", diags);
             let args = parse_expr(value, diags);
@@ -261,16 +531,111 @@ fn try_parse_builtin(
             let args = parse_expr(value, diags);
             return Some(Expr::FromBase64(meta, Box::new(args)));
         }
+        "fn::if" => {
+            check_casing(key, "fn::if", diags);
+            let args = parse_expr(value, diags);
+            return Some(parse_if(args, meta, diags));
+        }
+        "fn::equals" => {
+            check_casing(key, "fn::equals", diags);
+            let args = parse_expr(value, diags);
+            return Some(parse_comparison(
+                args,
+                meta,
+                diags,
+                "fn::equals",
+                Expr::Equals,
+            ));
+        }
+        "fn::not" => {
+            check_casing(key, "fn::not", diags);
+            let args = parse_expr(value, diags);
+            return Some(Expr::Not(meta, Box::new(args)));
+        }
+        "fn::and" => {
+            check_casing(key, "fn::and", diags);
+            let args = parse_expr(value, diags);
+            return Some(Expr::And(meta, Box::new(args)));
+        }
+        "fn::or" => {
+            check_casing(key, "fn::or", diags);
+            let args = parse_expr(value, diags);
+            return Some(Expr::Or(meta, Box::new(args)));
+        }
+        "fn::lessthan" => {
+            check_casing(key, "fn::lessThan", diags);
+            let args = parse_expr(value, diags);
+            return Some(parse_comparison(
+                args,
+                meta,
+                diags,
+                "fn::lessThan",
+                Expr::LessThan,
+            ));
+        }
+        "fn::lessthanorequal" => {
+            check_casing(key, "fn::lessThanOrEqual", diags);
+            let args = parse_expr(value, diags);
+            return Some(parse_comparison(
+                args,
+                meta,
+                diags,
+                "fn::lessThanOrEqual",
+                Expr::LessThanOrEqual,
+            ));
+        }
+        "fn::greaterthan" => {
+            check_casing(key, "fn::greaterThan", diags);
+            let args = parse_expr(value, diags);
+            return Some(parse_comparison(
+                args,
+                meta,
+                diags,
+                "fn::greaterThan",
+                Expr::GreaterThan,
+            ));
+        }
+        "fn::greaterthanorequal" => {
+            check_casing(key, "fn::greaterThanOrEqual", diags);
+            let args = parse_expr(value, diags);
+            return Some(parse_comparison(
+                args,
+                meta,
+                diags,
+                "fn::greaterThanOrEqual",
+                Expr::GreaterThanOrEqual,
+            ));
+        }
         "fn::select" => {
             check_casing(key, "fn::select", diags);
             let args = parse_expr(value, diags);
             return Some(parse_select(args, meta, diags));
         }
+        "fn::lookup" => {
+            check_casing(key, "fn::lookup", diags);
+            let args = parse_expr(value, diags);
+            return Some(parse_lookup(args, meta, diags));
+        }
         "fn::split" => {
             check_casing(key, "fn::split", diags);
             let args = parse_expr(value, diags);
             return Some(parse_split(args, meta, diags));
         }
+        "fn::map" => {
+            check_casing(key, "fn::map", diags);
+            let args = parse_expr(value, diags);
+            return Some(parse_map(args, meta, diags, "fn::map", Expr::Map));
+        }
+        "fn::filter" => {
+            check_casing(key, "fn::filter", diags);
+            let args = parse_expr(value, diags);
+            return Some(parse_map(args, meta, diags, "fn::filter", Expr::Filter));
+        }
+        "fn::foreach" => {
+            check_casing(key, "fn::foreach", diags);
+            let args = parse_expr(value, diags);
+            return Some(parse_foreach(args, meta, diags));
+        }
         "fn::stackreference" => {
             diags.error(
                 None,
@@ -289,11 +654,51 @@ fn try_parse_builtin(
             let args = parse_expr(value, diags);
             return Some(Expr::Secret(meta, Box::new(args)));
         }
+        "fn::encrypted" => {
+            check_casing(key, "fn::encrypted", diags);
+            let args = parse_expr(value, diags);
+            return Some(Expr::Encrypted(meta, Box::new(args)));
+        }
         "fn::readfile" => {
             check_casing(key, "fn::readFile", diags);
             let args = parse_expr(value, diags);
             return Some(Expr::ReadFile(meta, Box::new(args)));
         }
+        "fn::readfilebinary" => {
+            check_casing(key, "fn::readFileBinary", diags);
+            let args = parse_expr(value, diags);
+            return Some(Expr::ReadFileBinary(meta, Box::new(args)));
+        }
+        "fn::env" => {
+            check_casing(key, "fn::env", diags);
+            let args = parse_expr(value, diags);
+            return Some(parse_env(args, meta, diags));
+        }
+        "fn::templatefile" => {
+            check_casing(key, "fn::templateFile", diags);
+            let args = parse_expr(value, diags);
+            return Some(parse_template_file(args, meta, diags));
+        }
+        "fn::merge" => {
+            check_casing(key, "fn::merge", diags);
+            let args = parse_expr(value, diags);
+            return Some(Expr::Merge(meta, Box::new(args)));
+        }
+        "fn::jsonpatch" => {
+            check_casing(key, "fn::jsonPatch", diags);
+            let args = parse_expr(value, diags);
+            return Some(parse_json_patch(args, meta, diags));
+        }
+        "fn::validatejson" => {
+            check_casing(key, "fn::validateJson", diags);
+            let args = parse_expr(value, diags);
+            return Some(parse_validate_json(args, meta, diags));
+        }
+        "fn::assert" => {
+            check_casing(key, "fn::assert", diags);
+            let args = parse_expr(value, diags);
+            return Some(parse_assert(args, meta, diags));
+        }
         // Math builtins
         "fn::abs" => {
             check_casing(key, "fn::abs", diags);
@@ -320,6 +725,37 @@ fn try_parse_builtin(
             let args = parse_expr(value, diags);
             return Some(Expr::Min(meta, Box::new(args)));
         }
+        "fn::formatduration" => {
+            check_casing(key, "fn::formatDuration", diags);
+            let args = parse_expr(value, diags);
+            return Some(Expr::FormatDuration(meta, Box::new(args)));
+        }
+        "fn::formatbytes" => {
+            check_casing(key, "fn::formatBytes", diags);
+            let args = parse_expr(value, diags);
+            return Some(Expr::FormatBytes(meta, Box::new(args)));
+        }
+        // List builtins
+        "fn::sort" => {
+            check_casing(key, "fn::sort", diags);
+            let args = parse_expr(value, diags);
+            return Some(Expr::Sort(meta, Box::new(args)));
+        }
+        "fn::unique" => {
+            check_casing(key, "fn::unique", diags);
+            let args = parse_expr(value, diags);
+            return Some(Expr::Unique(meta, Box::new(args)));
+        }
+        "fn::length" => {
+            check_casing(key, "fn::length", diags);
+            let args = parse_expr(value, diags);
+            return Some(Expr::Length(meta, Box::new(args)));
+        }
+        "fn::coalesce" => {
+            check_casing(key, "fn::coalesce", diags);
+            let args = parse_expr(value, diags);
+            return Some(Expr::Coalesce(meta, Box::new(args)));
+        }
         // String builtins
         "fn::stringlen" => {
             check_casing(key, "fn::stringLen", diags);
@@ -331,6 +767,52 @@ fn try_parse_builtin(
             let args = parse_expr(value, diags);
             return Some(parse_substring(args, meta, diags));
         }
+        "fn::toupper" => {
+            check_casing(key, "fn::toUpper", diags);
+            let args = parse_expr(value, diags);
+            return Some(Expr::ToUpper(meta, Box::new(args)));
+        }
+        "fn::tolower" => {
+            check_casing(key, "fn::toLower", diags);
+            let args = parse_expr(value, diags);
+            return Some(Expr::ToLower(meta, Box::new(args)));
+        }
+        "fn::trim" => {
+            check_casing(key, "fn::trim", diags);
+            let args = parse_expr(value, diags);
+            return Some(Expr::Trim(meta, Box::new(args)));
+        }
+        "fn::replace" => {
+            check_casing(key, "fn::replace", diags);
+            let args = parse_expr(value, diags);
+            return Some(parse_replace(args, meta, diags));
+        }
+        "fn::startswith" => {
+            check_casing(key, "fn::startsWith", diags);
+            let args = parse_expr(value, diags);
+            return Some(parse_starts_with(args, meta, diags));
+        }
+        "fn::format" => {
+            check_casing(key, "fn::format", diags);
+            let args = parse_expr(value, diags);
+            return Some(parse_format(args, meta, diags));
+        }
+        // Network builtins
+        "fn::cidrsubnet" => {
+            check_casing(key, "fn::cidrSubnet", diags);
+            let args = parse_expr(value, diags);
+            return Some(parse_cidr_subnet(args, meta, diags));
+        }
+        "fn::cidrhost" => {
+            check_casing(key, "fn::cidrHost", diags);
+            let args = parse_expr(value, diags);
+            return Some(parse_cidr_host(args, meta, diags));
+        }
+        "fn::cidrnetmask" => {
+            check_casing(key, "fn::cidrNetmask", diags);
+            let args = parse_expr(value, diags);
+            return Some(Expr::CidrNetmask(meta, Box::new(args)));
+        }
         // Time builtins
         "fn::timeutc" => {
             check_casing(key, "fn::timeUtc", diags);
@@ -368,6 +850,20 @@ fn try_parse_builtin(
         _ => {}
     }
 
+    // ESC provider functions: fn::open::<provider>
+    if lower.starts_with(OPEN_PREFIX) {
+        let provider = &key[OPEN_PREFIX.len()..];
+        if provider.is_empty() {
+            diags.error(None, "fn::open:: requires a provider name", "");
+        }
+        let inputs = parse_expr(value, diags);
+        return Some(Expr::Open(
+            meta,
+            Cow::Owned(provider.to_string()),
+            Box::new(inputs),
+        ));
+    }
+
     // Check for fn::pkg:module(:name)? invoke shorthand
     if is_invoke_shorthand(key) {
         let fn_token = &key[4..]; // strip "fn::"
@@ -390,6 +886,192 @@ fn try_parse_builtin(
 }
 
 /// Checks if a key matches the fn::pkg:module(:name)? invoke shorthand pattern.
+/// Parses the top-level `imports:` block.
+///
+/// Expected structure:
+/// ```yaml
+/// imports:
+///   - url: https://example.com/fragments/network.yaml
+///     sha256: 9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08
+/// ```
+fn parse_imports(value: &serde_yaml::Value, diags: &mut Diagnostics) -> Vec<ImportDecl<'static>> {
+    let expected = "Expected:\n  imports:\n    - url: https://example.com/fragment.yaml\n      sha256: <64 hex digits>";
+    let Some(seq) = value.as_sequence() else {
+        diags.error(None, "imports: must be a list", expected);
+        return Vec::new();
+    };
+
+    let mut result = Vec::new();
+    for (i, item) in seq.iter().enumerate() {
+        let Some(mapping) = item.as_mapping() else {
+            diags.error(None, format!("imports[{}] must be a mapping", i), expected);
+            continue;
+        };
+        let field = |name: &str| mapping.get(name).and_then(|v| v.as_str());
+        let Some(url) = field("url") else {
+            diags.error(None, format!("imports[{}] is missing 'url'", i), expected);
+            continue;
+        };
+        if !url.starts_with("https://") && !url.starts_with("oci://") {
+            diags.error(
+                None,
+                format!("imports[{}]: unsupported url {:?}", i, url),
+                "Remote imports must use an https:// URL or an oci:// reference",
+            );
+            continue;
+        }
+        let Some(sha256) = field("sha256") else {
+            diags.error(
+                None,
+                format!("imports[{}] ({}) is missing 'sha256'", i, url),
+                "Remote imports must be pinned to the SHA-256 of their contents",
+            );
+            continue;
+        };
+        let sha256 = sha256.strip_prefix("sha256:").unwrap_or(sha256);
+        if sha256.len() != 64 || !sha256.bytes().all(|b| b.is_ascii_hexdigit()) {
+            diags.error(
+                None,
+                format!("imports[{}] ({}): 'sha256' must be 64 hex digits", i, url),
+                "",
+            );
+            continue;
+        }
+        result.push(ImportDecl {
+            url: Cow::Owned(url.to_string()),
+            sha256: Cow::Owned(sha256.to_ascii_lowercase()),
+        });
+    }
+    result
+}
+
+/// Parses the top-level `runtime:` setting, in either short or long form.
+///
+/// ```yaml
+/// runtime:
+///   name: yaml
+///   options:
+///     compiler: cue export
+/// ```
+fn parse_runtime(
+    value: &serde_yaml::Value,
+    diags: &mut Diagnostics,
+) -> Option<RuntimeDecl<'static>> {
+    if let Some(name) = value.as_str() {
+        return Some(RuntimeDecl {
+            name: Cow::Owned(name.to_string()),
+            options: Vec::new(),
+        });
+    }
+    let expected = "Expected:\n  runtime: yaml\nor:\n  runtime:\n    name: yaml\n    options: {}";
+    let Some(mapping) = value.as_mapping() else {
+        diags.error(None, "runtime: must be a string or a mapping", expected);
+        return None;
+    };
+    let Some(name) = mapping.get("name").and_then(|v| v.as_str()) else {
+        diags.error(None, "runtime: is missing 'name'", expected);
+        return None;
+    };
+    let options = match mapping.get("options") {
+        None => Vec::new(),
+        Some(serde_yaml::Value::Mapping(options)) => options
+            .iter()
+            .filter_map(|(k, v)| {
+                Some(PropertyEntry {
+                    key: Cow::Owned(k.as_str()?.to_string()),
+                    value: parse_expr(v, diags),
+                })
+            })
+            .collect(),
+        Some(_) => {
+            diags.error(None, "runtime: 'options' must be a mapping", expected);
+            Vec::new()
+        }
+    };
+    Some(RuntimeDecl {
+        name: Cow::Owned(name.to_string()),
+        options,
+    })
+}
+
+/// Parses the provider plugins of the top-level `plugins:` block.
+///
+/// ```yaml
+/// plugins:
+///   providers:
+///     - name: aws
+///       path: ../pulumi-aws/bin
+/// ```
+///
+/// `languages` and `analyzers` are handled by the engine and are ignored.
+fn parse_plugins(value: &serde_yaml::Value, diags: &mut Diagnostics) -> Vec<PluginDecl<'static>> {
+    let expected = "Expected:\n  plugins:\n    providers:\n      - name: aws\n        path: ./bin";
+    let Some(mapping) = value.as_mapping() else {
+        diags.error(None, "plugins: must be a mapping", expected);
+        return Vec::new();
+    };
+    let Some(providers) = mapping.get("providers") else {
+        return Vec::new();
+    };
+    let Some(seq) = providers.as_sequence() else {
+        diags.error(None, "plugins: 'providers' must be a list", expected);
+        return Vec::new();
+    };
+
+    let mut result = Vec::new();
+    for (i, item) in seq.iter().enumerate() {
+        let Some(mapping) = item.as_mapping() else {
+            diags.error(
+                None,
+                format!("plugins.providers[{}] must be a mapping", i),
+                expected,
+            );
+            continue;
+        };
+        let field = |name: &str| {
+            mapping
+                .get(name)
+                .and_then(|v| v.as_str())
+                .map(|s| Cow::Owned(s.to_string()))
+        };
+        let Some(name) = field("name") else {
+            diags.error(
+                None,
+                format!("plugins.providers[{}] is missing 'name'", i),
+                expected,
+            );
+            continue;
+        };
+        result.push(PluginDecl {
+            name,
+            path: field("path"),
+            version: mapping.get("version").and_then(|v| {
+                parse_version(v, &format!("plugins.providers[{}].version", i), diags)
+            }),
+            server: field("server"),
+        });
+    }
+    result
+}
+
+/// Reads a provider version constraint, reporting one that is not valid
+/// semver. The text is kept as written.
+fn parse_version(
+    value: &serde_yaml::Value,
+    context: &str,
+    diags: &mut Diagnostics,
+) -> Option<Cow<'static, str>> {
+    let text = value.as_str()?;
+    if let Err(e) = VersionConstraint::parse(text) {
+        diags.error(
+            None,
+            format!("{}: {}", context, e),
+            "Expected an exact version such as 4.16.0, or a requirement such as ^4.16",
+        );
+    }
+    Some(Cow::Owned(text.to_string()))
+}
+
 /// Parses the top-level `starlark:` block.
 ///
 /// Expected structure:
@@ -613,7 +1295,7 @@ fn parse_starlark_call(
     )
 }
 
-fn is_invoke_shorthand(key: &str) -> bool {
+pub(crate) fn is_invoke_shorthand(key: &str) -> bool {
     let lower = key.to_lowercase();
     if !lower.starts_with("fn::") {
         return false;
@@ -689,6 +1371,9 @@ fn parse_invoke(args: Expr<'static>, meta: ExprMeta, diags: &mut Diagnostics) ->
                                             .as_str()
                                             .map(|s| Cow::Owned(s.to_string()))
                                     }
+                                    "paginate" => {
+                                        opts.paginate = parse_paginate(&opt_entry.value, diags)
+                                    }
                                     _ => {}
                                 }
                             }
@@ -708,6 +1393,17 @@ fn parse_invoke(args: Expr<'static>, meta: ExprMeta, diags: &mut Diagnostics) ->
         }
     };
 
+    if opts.paginate.is_some() && return_.is_some() {
+        diags.error(
+            None,
+            format!(
+                "fn::invoke of {} cannot combine 'return' with 'paginate'",
+                token
+            ),
+            "a paginated invoke returns the list of items",
+        );
+    }
+
     Expr::Invoke(
         meta,
         InvokeExpr {
@@ -715,21 +1411,58 @@ fn parse_invoke(args: Expr<'static>, meta: ExprMeta, diags: &mut Diagnostics) ->
             call_args: call_args.map(Box::new),
             call_opts: opts,
             return_,
+            shorthand: false,
         },
     )
 }
 
+/// Parses the `paginate` invoke option:
+/// `{ itemsField: <name>, tokenField: <name>, tokenArgument: <name> }`.
+fn parse_paginate(value: &Expr<'static>, diags: &mut Diagnostics) -> Option<PaginateDecl<'static>> {
+    let Expr::Object(_, entries) = value else {
+        diags.error(
+            None,
+            "the 'paginate' option of fn::invoke must be an object containing 'itemsField' and 'tokenField'",
+            "",
+        );
+        return None;
+    };
+    let field = |name: &str| {
+        entries
+            .iter()
+            .find(|e| e.key.as_str() == Some(name))
+            .and_then(|e| e.value.as_str())
+            .map(|s| Cow::Owned(s.to_string()))
+    };
+    let (Some(items_field), Some(token_field)) = (field("itemsField"), field("tokenField")) else {
+        diags.error(
+            None,
+            "the 'paginate' option of fn::invoke must set 'itemsField' and 'tokenField' to property names",
+            "",
+        );
+        return None;
+    };
+    Some(PaginateDecl {
+        items_field,
+        token_field,
+        token_argument: field("tokenArgument"),
+    })
+}
+
 fn parse_invoke_shorthand(
     fn_token: &str,
     value: &serde_yaml::Value,
     meta: ExprMeta,
     diags: &mut Diagnostics,
 ) -> Expr<'static> {
-    let call_args = if value.is_mapping() {
-        Some(Box::new(parse_expr(value, diags)))
-    } else {
+    // Non-object arguments are kept so host-provided builtins
+    // (`fn::<namespace>:<name>: <value>`) receive them; provider invokes
+    // reject them at evaluation time.
+    let call_args = if value.is_null() {
         None
-    };
+    } else {
+        Some(Box::new(parse_expr(value, diags)))
+    };
 
     Expr::Invoke(
         meta,
@@ -738,6 +1471,7 @@ fn parse_invoke_shorthand(
             call_args,
             call_opts: InvokeOptions::default(),
             return_: None,
+            shorthand: true,
         },
     )
 }
@@ -780,6 +1514,177 @@ fn parse_select(args: Expr<'static>, meta: ExprMeta, diags: &mut Diagnostics) ->
     }
 }
 
+fn parse_if(args: Expr<'static>, meta: ExprMeta, diags: &mut Diagnostics) -> Expr<'static> {
+    match args {
+        Expr::List(_, elements) if elements.len() == 3 => {
+            let mut iter = elements.into_iter();
+            let condition = iter.next().unwrap();
+            let then = iter.next().unwrap();
+            let otherwise = iter.next().unwrap();
+            Expr::If(
+                meta,
+                Box::new(condition),
+                Box::new(then),
+                Box::new(otherwise),
+            )
+        }
+        _ => {
+            diags.error(
+                None,
+                "the argument to fn::if must be a three-valued list [condition, then, else]",
+                "",
+            );
+            args
+        }
+    }
+}
+
+fn parse_lookup(args: Expr<'static>, meta: ExprMeta, diags: &mut Diagnostics) -> Expr<'static> {
+    match args {
+        Expr::List(_, elements) if elements.len() == 2 || elements.len() == 3 => {
+            let mut iter = elements.into_iter();
+            let map = iter.next().unwrap();
+            let key = iter.next().unwrap();
+            let default = iter.next().map(Box::new);
+            Expr::Lookup(meta, Box::new(map), Box::new(key), default)
+        }
+        _ => {
+            diags.error(
+                None,
+                "the argument to fn::lookup must be a list of [map, key] or [map, key, default]",
+                "",
+            );
+            args
+        }
+    }
+}
+
+fn parse_env(args: Expr<'static>, meta: ExprMeta, diags: &mut Diagnostics) -> Expr<'static> {
+    match args {
+        Expr::List(_, elements) if elements.len() == 2 => {
+            let mut iter = elements.into_iter();
+            let name = iter.next().unwrap();
+            let default = iter.next().map(Box::new);
+            Expr::Env(meta, Box::new(name), default)
+        }
+        Expr::List(..) | Expr::Object(..) => {
+            diags.error(
+                None,
+                "the argument to fn::env must be a variable name or a list of [name, default]",
+                "",
+            );
+            args
+        }
+        name => Expr::Env(meta, Box::new(name), None),
+    }
+}
+
+fn parse_validate_json(
+    args: Expr<'static>,
+    meta: ExprMeta,
+    diags: &mut Diagnostics,
+) -> Expr<'static> {
+    let Expr::Object(_, entries) = &args else {
+        diags.error(
+            None,
+            "the argument to fn::validateJson must be an object containing 'value' and 'schema'",
+            "",
+        );
+        return args;
+    };
+    let field = |name: &str| {
+        entries
+            .iter()
+            .find(|e| e.key.as_str() == Some(name))
+            .map(|e| Box::new((*e.value).clone()))
+    };
+    match (field("value"), field("schema")) {
+        (Some(value), Some(schema)) if entries.len() == 2 => {
+            Expr::ValidateJson(meta, value, schema)
+        }
+        _ => {
+            diags.error(
+                None,
+                "the argument to fn::validateJson must be an object containing 'value' and 'schema'",
+                "",
+            );
+            args
+        }
+    }
+}
+
+fn parse_assert(args: Expr<'static>, meta: ExprMeta, diags: &mut Diagnostics) -> Expr<'static> {
+    let expected =
+        "the argument to fn::assert must be an object containing 'condition' and 'message'";
+    let Expr::Object(_, entries) = &args else {
+        diags.error(meta.span, expected, "");
+        return args;
+    };
+    let field = |name: &str| {
+        entries
+            .iter()
+            .find(|e| e.key.as_str() == Some(name))
+            .map(|e| Box::new((*e.value).clone()))
+    };
+    match (field("condition"), field("message")) {
+        (Some(condition), Some(message)) if entries.len() == 2 => {
+            Expr::Assert(meta, condition, message)
+        }
+        _ => {
+            diags.error(meta.span, expected, "");
+            args
+        }
+    }
+}
+
+fn parse_template_file(
+    args: Expr<'static>,
+    meta: ExprMeta,
+    diags: &mut Diagnostics,
+) -> Expr<'static> {
+    let expected = "the argument to fn::templateFile must be an object containing 'path' and optionally 'arguments'";
+    let Expr::Object(_, entries) = &args else {
+        diags.error(None, expected, "");
+        return args;
+    };
+    let field = |name: &str| {
+        entries
+            .iter()
+            .find(|e| e.key.as_str() == Some(name))
+            .map(|e| Box::new((*e.value).clone()))
+    };
+    let arguments = field("arguments");
+    match field("path") {
+        Some(path) if entries.len() == 1 + usize::from(arguments.is_some()) => {
+            let arguments = arguments.unwrap_or_else(|| Box::new(Expr::Object(meta, Vec::new())));
+            Expr::TemplateFile(meta, path, arguments)
+        }
+        _ => {
+            diags.error(None, expected, "");
+            args
+        }
+    }
+}
+
+fn parse_json_patch(args: Expr<'static>, meta: ExprMeta, diags: &mut Diagnostics) -> Expr<'static> {
+    match args {
+        Expr::List(_, elements) if elements.len() == 2 => {
+            let mut iter = elements.into_iter();
+            let target = iter.next().unwrap();
+            let operations = iter.next().unwrap();
+            Expr::JsonPatch(meta, Box::new(target), Box::new(operations))
+        }
+        _ => {
+            diags.error(
+                None,
+                "the argument to fn::jsonPatch must be a two-valued list [target, operations]",
+                "",
+            );
+            args
+        }
+    }
+}
+
 fn parse_split(args: Expr<'static>, meta: ExprMeta, diags: &mut Diagnostics) -> Expr<'static> {
     match args {
         Expr::List(_, elements) if elements.len() == 2 => {
@@ -799,6 +1704,99 @@ fn parse_split(args: Expr<'static>, meta: ExprMeta, diags: &mut Diagnostics) ->
     }
 }
 
+/// Parses the `[list, expr]` arguments of `fn::map` and `fn::filter`.
+fn parse_map(
+    args: Expr<'static>,
+    meta: ExprMeta,
+    diags: &mut Diagnostics,
+    name: &str,
+    build: fn(ExprMeta, Box<Expr<'static>>, Box<Expr<'static>>) -> Expr<'static>,
+) -> Expr<'static> {
+    match args {
+        Expr::List(_, elements) if elements.len() == 2 => {
+            let mut iter = elements.into_iter();
+            let list = iter.next().unwrap();
+            let body = iter.next().unwrap();
+            build(meta, Box::new(list), Box::new(body))
+        }
+        _ => {
+            diags.error(
+                None,
+                format!(
+                    "the argument to {} must be a two-valued list [list, expr]",
+                    name
+                ),
+                format!(
+                    "Expected:\n  {}:\n    - ${{config.cidrs}}\n    - ${{item}}\n\n\
+                     ${{item}} is the current element and ${{index}} its position.",
+                    name
+                ),
+            );
+            args
+        }
+    }
+}
+
+/// Parses the `{list, as, index?, body}` arguments of `fn::foreach`.
+fn parse_foreach(args: Expr<'static>, meta: ExprMeta, diags: &mut Diagnostics) -> Expr<'static> {
+    let expected = "the argument to fn::foreach must be an object containing 'list', 'as', and 'body', and optionally 'index'";
+    let example =
+        "Expected:\n  fn::foreach:\n    list: ${zones}\n    as: zone\n    body: subnet-${zone}";
+    let Expr::Object(_, entries) = &args else {
+        diags.error(None, expected, example);
+        return args;
+    };
+    let field = |name: &str| {
+        entries
+            .iter()
+            .find(|e| e.key.as_str() == Some(name))
+            .map(|e| &*e.value)
+    };
+    let index = field("index");
+    let (Some(list), Some(var), Some(body)) = (field("list"), field("as"), field("body")) else {
+        diags.error(None, expected, example);
+        return args;
+    };
+    if entries.len() != 3 + usize::from(index.is_some()) {
+        diags.error(None, expected, example);
+        return args;
+    }
+
+    let mut name = |field: &str, value: &Expr<'static>| match value.as_str() {
+        Some(name) if is_valid_property_name(name) => Some(Cow::Owned(name.to_string())),
+        _ => {
+            diags.error(
+                None,
+                format!("fn::foreach: '{}' must be a name, like 'item'", field),
+                "the name may contain letters, digits, '_' and '$', and may not start with a digit",
+            );
+            None
+        }
+    };
+    let var = name("as", var);
+    let index = index.map(|index| name("index", index).ok_or(()));
+    let (Some(var), Ok(index)) = (var, index.transpose()) else {
+        return args;
+    };
+    if index.as_ref() == Some(&var) {
+        diags.error(
+            None,
+            format!("fn::foreach: 'as' and 'index' both bind '{}'", var),
+            "",
+        );
+        return args;
+    }
+    Expr::Foreach(
+        meta,
+        ForeachExpr {
+            list: Box::new(list.clone()),
+            var,
+            index,
+            body: Box::new(body.clone()),
+        },
+    )
+}
+
 fn parse_asset_archive(
     args: Expr<'static>,
     meta: ExprMeta,
@@ -857,6 +1855,144 @@ fn parse_substring(args: Expr<'static>, meta: ExprMeta, diags: &mut Diagnostics)
     }
 }
 
+fn parse_replace(args: Expr<'static>, meta: ExprMeta, diags: &mut Diagnostics) -> Expr<'static> {
+    match args {
+        Expr::List(_, elements) if elements.len() == 3 => {
+            let mut iter = elements.into_iter();
+            let source = iter.next().unwrap();
+            let search = iter.next().unwrap();
+            let replacement = iter.next().unwrap();
+            Expr::Replace(
+                meta,
+                Box::new(source),
+                Box::new(search),
+                Box::new(replacement),
+            )
+        }
+        _ => {
+            diags.error(
+                None,
+                "the argument to fn::replace must be a three-valued list [string, search, replacement]",
+                "",
+            );
+            args
+        }
+    }
+}
+
+fn parse_comparison(
+    args: Expr<'static>,
+    meta: ExprMeta,
+    diags: &mut Diagnostics,
+    name: &str,
+    build: fn(ExprMeta, Box<Expr<'static>>, Box<Expr<'static>>) -> Expr<'static>,
+) -> Expr<'static> {
+    match args {
+        Expr::List(_, elements) if elements.len() == 2 => {
+            let mut iter = elements.into_iter();
+            let left = iter.next().unwrap();
+            let right = iter.next().unwrap();
+            build(meta, Box::new(left), Box::new(right))
+        }
+        _ => {
+            diags.error(
+                None,
+                format!(
+                    "the argument to {} must be a two-valued list [left, right]",
+                    name
+                ),
+                "",
+            );
+            args
+        }
+    }
+}
+
+fn parse_starts_with(
+    args: Expr<'static>,
+    meta: ExprMeta,
+    diags: &mut Diagnostics,
+) -> Expr<'static> {
+    match args {
+        Expr::List(_, elements) if elements.len() == 2 => {
+            let mut iter = elements.into_iter();
+            let source = iter.next().unwrap();
+            let prefix = iter.next().unwrap();
+            Expr::StartsWith(meta, Box::new(source), Box::new(prefix))
+        }
+        _ => {
+            diags.error(
+                None,
+                "the argument to fn::startsWith must be a two-valued list [string, prefix]",
+                "",
+            );
+            args
+        }
+    }
+}
+
+fn parse_format(args: Expr<'static>, meta: ExprMeta, diags: &mut Diagnostics) -> Expr<'static> {
+    match args {
+        Expr::List(_, elements) if elements.len() == 2 => {
+            let mut iter = elements.into_iter();
+            let format = iter.next().unwrap();
+            let arguments = iter.next().unwrap();
+            Expr::Format(meta, Box::new(format), Box::new(arguments))
+        }
+        _ => {
+            diags.error(
+                None,
+                "the argument to fn::format must be a two-valued list [format, arguments]",
+                "",
+            );
+            args
+        }
+    }
+}
+
+fn parse_cidr_subnet(
+    args: Expr<'static>,
+    meta: ExprMeta,
+    diags: &mut Diagnostics,
+) -> Expr<'static> {
+    match args {
+        Expr::List(_, elements) if elements.len() == 3 => {
+            let mut iter = elements.into_iter();
+            let prefix = iter.next().unwrap();
+            let newbits = iter.next().unwrap();
+            let netnum = iter.next().unwrap();
+            Expr::CidrSubnet(meta, Box::new(prefix), Box::new(newbits), Box::new(netnum))
+        }
+        _ => {
+            diags.error(
+                None,
+                "the argument to fn::cidrSubnet must be a three-valued list [prefix, newbits, netnum]",
+                "",
+            );
+            args
+        }
+    }
+}
+
+fn parse_cidr_host(args: Expr<'static>, meta: ExprMeta, diags: &mut Diagnostics) -> Expr<'static> {
+    match args {
+        Expr::List(_, elements) if elements.len() == 2 => {
+            let mut iter = elements.into_iter();
+            let prefix = iter.next().unwrap();
+            let hostnum = iter.next().unwrap();
+            Expr::CidrHost(meta, Box::new(prefix), Box::new(hostnum))
+        }
+        _ => {
+            diags.error(
+                None,
+                "the argument to fn::cidrHost must be a two-valued list [prefix, hostnum]",
+                "",
+            );
+            args
+        }
+    }
+}
+
 // --- Template-level parsing helpers ---
 
 fn parse_pulumi_decl(value: &serde_yaml::Value, diags: &mut Diagnostics) -> PulumiDecl<'static> {
@@ -864,8 +2000,20 @@ fn parse_pulumi_decl(value: &serde_yaml::Value, diags: &mut Diagnostics) -> Pulu
     if let Some(map) = value.as_mapping() {
         for (k, v) in map {
             if let Some(key) = k.as_str() {
-                if key.to_lowercase() == "requiredversion" {
-                    decl.required_version = Some(parse_expr(v, diags));
+                match key.to_lowercase().as_str() {
+                    "requiredversion" => decl.required_version = Some(parse_expr(v, diags)),
+                    "strictscalars" => match v.as_bool() {
+                        Some(b) => decl.strict_scalars = b,
+                        None => diags.error(None, "pulumi.strictScalars must be a boolean", ""),
+                    },
+                    "duplicatekeys" => match v.as_str() {
+                        Some("error") => decl.duplicate_keys = DuplicateKeys::Error,
+                        Some("warn") => decl.duplicate_keys = DuplicateKeys::Warn,
+                        _ => {
+                            diags.error(None, "pulumi.duplicateKeys must be 'error' or 'warn'", "")
+                        }
+                    },
+                    _ => {}
                 }
             }
         }
@@ -873,14 +2021,23 @@ fn parse_pulumi_decl(value: &serde_yaml::Value, diags: &mut Diagnostics) -> Pulu
     decl
 }
 
+/// Parses a `config:` block, or the `inputs:` of `component` when set.
 fn parse_config_map(
     value: &serde_yaml::Value,
+    component: Option<&str>,
     diags: &mut Diagnostics,
 ) -> Vec<ConfigEntry<'static>> {
     let map = match value.as_mapping() {
         Some(m) => m,
         None => {
-            diags.error(None, "config must be an object", "");
+            match component {
+                Some(name) => diags.error(
+                    None,
+                    format!("the inputs of component \"{}\" must be an object", name),
+                    "",
+                ),
+                None => diags.error(None, "config must be an object", ""),
+            }
             return Vec::new();
         }
     };
@@ -891,8 +2048,17 @@ fn parse_config_map(
             Some(s) => s,
             None => continue,
         };
-        let param = if v.is_mapping() {
-            parse_config_param(v, diags)
+        let param = if is_open_call(v) {
+            ConfigParamDecl {
+                default: Some(parse_expr(v, diags)),
+                ..Default::default()
+            }
+        } else if v.is_mapping() {
+            let label = match component {
+                Some(name) => format!("input \"{}\" of component \"{}\"", key, name),
+                None => format!("config \"{}\"", key),
+            };
+            parse_config_param(v, &label, diags)
         } else {
             ConfigParamDecl {
                 value: Some(parse_expr(v, diags)),
@@ -900,7 +2066,9 @@ fn parse_config_map(
             }
         };
         entries.push(ConfigEntry {
-            meta: ExprMeta::no_span(),
+            meta: ExprMeta {
+                span: positions::span_of(k),
+            },
             key: Cow::Owned(key.to_string()),
             param,
         });
@@ -908,8 +2076,24 @@ fn parse_config_map(
     entries
 }
 
+/// Returns true if `value` is a single-key `fn::open::<provider>` mapping,
+/// which a config entry takes as its default rather than as a declaration,
+/// so that the stack's config can still override it.
+fn is_open_call(value: &serde_yaml::Value) -> bool {
+    match value.as_mapping() {
+        Some(map) if map.len() == 1 => map
+            .keys()
+            .next()
+            .and_then(|k| k.as_str())
+            .is_some_and(|k| k.to_lowercase().starts_with(OPEN_PREFIX)),
+        _ => false,
+    }
+}
+
+/// Parses a config parameter declaration. `label` names the entry in errors.
 fn parse_config_param(
     value: &serde_yaml::Value,
+    label: &str,
     diags: &mut Diagnostics,
 ) -> ConfigParamDecl<'static> {
     let mut param = ConfigParamDecl::default();
@@ -917,13 +2101,34 @@ fn parse_config_param(
         for (k, v) in map {
             if let Some(key) = k.as_str() {
                 match key.to_lowercase().as_str() {
-                    "type" => param.type_ = v.as_str().map(|s| Cow::Owned(s.to_string())),
+                    "type" => {
+                        param.type_ = v.as_str().map(|s| Cow::Owned(s.to_string()));
+                        if param.type_.is_none() {
+                            diags.error(
+                                None,
+                                format!("the type of {} must be a string", label),
+                                "",
+                            );
+                        }
+                    }
                     "name" => param.name = v.as_str().map(|s| Cow::Owned(s.to_string())),
-                    "secret" => param.secret = v.as_bool(),
+                    "secret" => {
+                        param.secret = v.as_bool();
+                        if param.secret.is_none() {
+                            diags.error(
+                                None,
+                                format!("the secret flag of {} must be true or false", label),
+                                "",
+                            );
+                        }
+                    }
+                    "description" => {
+                        param.description = v.as_str().map(|s| Cow::Owned(s.to_string()))
+                    }
                     "default" => param.default = Some(parse_expr(v, diags)),
                     "value" => param.value = Some(parse_expr(v, diags)),
                     "items" => {
-                        param.items = Some(Box::new(parse_config_param(v, diags)));
+                        param.items = Some(Box::new(parse_config_param(v, label, diags)));
                     }
                     _ => {}
                 }
@@ -935,12 +2140,13 @@ fn parse_config_param(
 
 fn parse_variables_map(
     value: &serde_yaml::Value,
+    section: &str,
     diags: &mut Diagnostics,
 ) -> Vec<VariableEntry<'static>> {
     let map = match value.as_mapping() {
         Some(m) => m,
         None => {
-            diags.error(None, "variables must be an object", "");
+            diags.error(None, format!("{} must be an object", section), "");
             return Vec::new();
         }
     };
@@ -952,7 +2158,9 @@ fn parse_variables_map(
             None => continue,
         };
         entries.push(VariableEntry {
-            meta: ExprMeta::no_span(),
+            meta: ExprMeta {
+                span: positions::span_of(k),
+            },
             key: Cow::Owned(key.to_string()),
             value: parse_expr(v, diags),
         });
@@ -978,9 +2186,24 @@ fn parse_resources_map(
             Some(s) => s,
             None => continue,
         };
+        if key.replace("$$", "").contains("${") {
+            diags.error(
+                None,
+                format!("resource logical name '{}' cannot be interpolated", key),
+                format!(
+                    "logical names are static keys that identify a resource in the dependency \
+                     graph and its URN; to compute the resource's name, use a static key and \
+                     set `name: {}` instead",
+                    key
+                ),
+            );
+            continue;
+        }
         let resource = parse_resource_decl(v, diags);
         entries.push(ResourceEntry {
-            meta: ExprMeta::no_span(),
+            meta: ExprMeta {
+                span: positions::span_of(k),
+            },
             logical_name: Cow::Owned(key.to_string()),
             resource,
         });
@@ -1003,6 +2226,8 @@ fn parse_resource_decl(
                 properties: ResourceProperties::default(),
                 options: ResourceOptionsDecl::default(),
                 get: None,
+                locals: Vec::new(),
+                range: None,
             };
         }
     };
@@ -1013,6 +2238,8 @@ fn parse_resource_decl(
     let mut properties = ResourceProperties::default();
     let mut options = ResourceOptionsDecl::default();
     let mut get = None;
+    let mut locals = Vec::new();
+    let mut range = None;
 
     for (k, v) in map {
         let key = match k.as_str() {
@@ -1025,7 +2252,7 @@ fn parse_resource_decl(
                     type_ = Cow::Owned(s.to_string());
                 }
             }
-            "name" => name = v.as_str().map(|s| Cow::Owned(s.to_string())),
+            "name" => name = Some(parse_expr(v, diags)),
             "defaultprovider" => default_provider = v.as_bool(),
             "properties" => {
                 if let Some(m) = v.as_mapping() {
@@ -1050,6 +2277,20 @@ fn parse_resource_decl(
             "get" => {
                 get = Some(parse_get_resource(v, diags));
             }
+            "locals" => {
+                locals = parse_variables_map(v, "locals", diags);
+            }
+            "count" | "range" => {
+                if range.is_some() {
+                    diags.error(None, "a resource cannot set both count and range", "");
+                }
+                let expr = parse_expr(v, diags);
+                range = Some(if key.eq_ignore_ascii_case("count") {
+                    ResourceRange::Count(expr)
+                } else {
+                    ResourceRange::Items(expr)
+                });
+            }
             _ => {}
         }
     }
@@ -1061,6 +2302,8 @@ fn parse_resource_decl(
         properties,
         options,
         get,
+        locals,
+        range,
     }
 }
 
@@ -1097,7 +2340,7 @@ fn parse_resource_options(
             "protect" => opts.protect = Some(parse_expr(v, diags)),
             "provider" => opts.provider = Some(parse_expr(v, diags)),
             "providers" => opts.providers = Some(parse_expr(v, diags)),
-            "version" => opts.version = v.as_str().map(|s| Cow::Owned(s.to_string())),
+            "version" => opts.version = parse_version(v, "options.version", diags),
             "plugindownloadurl" => {
                 opts.plugin_download_url = v.as_str().map(|s| Cow::Owned(s.to_string()));
             }
@@ -1224,7 +2467,7 @@ fn parse_components(
             Some(s) => s,
             None => continue,
         };
-        let comp = parse_component_param(v, diags);
+        let comp = parse_component_param(key, v, diags);
         components.push(ComponentDecl {
             key: Cow::Owned(key.to_string()),
             component: comp,
@@ -1234,6 +2477,7 @@ fn parse_components(
 }
 
 fn parse_component_param(
+    component: &str,
     value: &serde_yaml::Value,
     diags: &mut Diagnostics,
 ) -> ComponentParamDecl<'static> {
@@ -1256,8 +2500,8 @@ fn parse_component_param(
                         comp.description = v.as_str().map(|s| Cow::Owned(s.to_string()))
                     }
                     "pulumi" => comp.pulumi = parse_pulumi_decl(v, diags),
-                    "inputs" => comp.inputs = parse_config_map(v, diags),
-                    "variables" => comp.variables = parse_variables_map(v, diags),
+                    "inputs" => comp.inputs = parse_config_map(v, Some(component), diags),
+                    "variables" => comp.variables = parse_variables_map(v, "variables", diags),
                     "resources" => comp.resources = parse_resources_map(v, diags),
                     "outputs" => comp.outputs = parse_outputs_map(v, diags),
                     _ => {}
@@ -1331,6 +2575,34 @@ config:
         assert_eq!(template.config[0].param.type_.as_deref(), Some("string"));
     }
 
+    #[test]
+    fn test_parse_malformed_input_flags() {
+        let source = r#"
+name: test
+runtime: yaml
+config:
+  region:
+    type: [string]
+components:
+  App:
+    inputs:
+      password:
+        type: string
+        secret: "yes"
+"#;
+        let (template, diags) = parse_template(source, None);
+        let messages: Vec<String> = diags.iter().map(|d| d.summary.clone()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "the type of config \"region\" must be a string".to_string(),
+                "the secret flag of input \"password\" of component \"App\" must be true or false"
+                    .to_string(),
+            ]
+        );
+        assert_eq!(template.components[0].component.inputs.len(), 1);
+    }
+
     #[test]
     fn test_parse_template_with_variables() {
         let source = r#"
@@ -1357,6 +2629,48 @@ variables:
         }
     }
 
+    #[test]
+    fn test_parse_invoke_paginate() {
+        let source = r#"
+name: test
+runtime: yaml
+variables:
+  zones:
+    fn::invoke:
+      function: aws:route53:listZones
+      options:
+        paginate:
+          itemsField: zones
+          tokenField: nextMarker
+          tokenArgument: marker
+  broken:
+    fn::invoke:
+      function: aws:route53:listZones
+      options:
+        paginate:
+          itemsField: zones
+      return: zones
+"#;
+        let (template, diags) = parse_template(source, None);
+        match &template.variables[0].value {
+            Expr::Invoke(_, invoke) => assert_eq!(
+                invoke.call_opts.paginate,
+                Some(PaginateDecl {
+                    items_field: "zones".into(),
+                    token_field: "nextMarker".into(),
+                    token_argument: Some("marker".into()),
+                })
+            ),
+            other => panic!("expected invoke, got {:?}", other),
+        }
+        let errors = diags.to_string();
+        assert!(
+            errors.contains("must set 'itemsField' and 'tokenField'"),
+            "{}",
+            errors
+        );
+    }
+
     #[test]
     fn test_parse_template_with_outputs() {
         let source = r#"
@@ -1408,6 +2722,28 @@ outputs:
         }
     }
 
+    #[test]
+    fn test_parse_integers_beyond_i64_warn() {
+        let source =
+            "name: test\nvariables:\n  id: 9223372036854775807\n  big: 18446744073709551615\n";
+        let (template, diags) = parse_template(source, None);
+        assert!(matches!(
+            template.variables[0].value,
+            Expr::Number(_, Number::Int(i64::MAX))
+        ));
+        assert!(matches!(
+            template.variables[1].value,
+            Expr::Number(_, Number::Float(_))
+        ));
+        let warnings: Vec<_> = diags.iter().filter(|d| !d.is_error()).collect();
+        assert_eq!(warnings.len(), 1, "{}", diags);
+        assert_eq!(
+            warnings[0].summary,
+            "integer 18446744073709551615 does not fit in a 64-bit signed integer"
+        );
+        assert!(warnings[0].span.is_some());
+    }
+
     #[test]
     fn test_parse_join() {
         let source = r#"
@@ -1452,6 +2788,28 @@ variables:
         }
     }
 
+    #[test]
+    fn test_parse_encrypted() {
+        let source = r#"
+name: test
+runtime: yaml
+variables:
+  password:
+    fn::encrypted: AAABAKsg2Tq2vrNmjpIHb3Pw8JGe0Iw1CpoUWvuNpA==
+"#;
+        let (template, diags) = parse_template(source, None);
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        match &template.variables[0].value {
+            Expr::Encrypted(_, inner) => {
+                assert_eq!(
+                    inner.as_str(),
+                    Some("AAABAKsg2Tq2vrNmjpIHb3Pw8JGe0Iw1CpoUWvuNpA==")
+                );
+            }
+            other => panic!("expected encrypted, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_parse_resource_options() {
         let source = r#"
@@ -1512,9 +2870,33 @@ variables:
 "#;
         let (template, diags) = parse_template(source, None);
         assert!(!diags.has_errors(), "errors: {}", diags);
-        match &template.variables[0].value {
-            Expr::ToJson(_, _) => {}
-            other => panic!("expected toJSON, got {:?}", other),
+        match &template.variables[0].value {
+            Expr::ToJson(_, _) => {}
+            other => panic!("expected toJSON, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_parse_json_and_yaml() {
+        let source = r#"
+name: test
+runtime: yaml
+variables:
+  fromJson:
+    fn::parseJSON: '{"a": 1}'
+  fromYaml:
+    fn::parseYAML:
+      fn::readFile: ./config.yaml
+"#;
+        let (template, diags) = parse_template(source, None);
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        assert!(matches!(
+            &template.variables[0].value,
+            Expr::ParseJson(_, _)
+        ));
+        match &template.variables[1].value {
+            Expr::ParseYaml(_, inner) => assert!(matches!(**inner, Expr::ReadFile(_, _))),
+            other => panic!("expected parseYAML, got {:?}", other),
         }
     }
 
@@ -1614,6 +2996,132 @@ resources:
         }
     }
 
+    #[test]
+    fn test_strict_scalars_warns_about_yaml11_booleans() {
+        let source = r#"
+name: test
+pulumi:
+  strictScalars: true
+variables:
+  country: NO
+  quoted: "no"
+  enabled: true
+  codes: [se, no, dk]
+  switches:
+    - on # comment
+    - "off"
+  flags: {verbose: off, yes: 1}
+  script: |
+    no: y
+"#;
+        let (template, diags) = parse_template(source, None);
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        assert!(template.pulumi.strict_scalars);
+        let warnings: Vec<(String, &str)> = diags
+            .iter()
+            .map(|d| {
+                let span = d.span.unwrap();
+                (
+                    d.summary.clone(),
+                    &source[span.start as usize..span.end as usize],
+                )
+            })
+            .collect();
+        assert_eq!(
+            warnings,
+            vec![
+                (
+                    "unquoted 'NO' is read as the string \"NO\", not a boolean".to_string(),
+                    "NO"
+                ),
+                (
+                    "unquoted 'no' is read as the string \"no\", not a boolean".to_string(),
+                    "no"
+                ),
+                (
+                    "unquoted 'on' is read as the string \"on\", not a boolean".to_string(),
+                    "on"
+                ),
+                (
+                    "unquoted 'off' is read as the string \"off\", not a boolean".to_string(),
+                    "off"
+                ),
+            ]
+        );
+        match &template.variables[0].value {
+            Expr::String(_, s) => assert_eq!(s.as_ref(), "NO"),
+            other => panic!("expected string, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_duplicate_keys_are_errors_by_default() {
+        let source = "name: test\nvariables:\n  a: 1\n  a: 2\n";
+        let (_, diags) = parse_template(source, None);
+        assert!(diags.has_errors());
+        assert!(diags.to_string().contains("duplicate"), "{}", diags);
+    }
+
+    #[test]
+    fn test_duplicate_keys_warn_keeps_last_value() {
+        let source = r#"
+name: test
+pulumi:
+  duplicateKeys: warn
+resources:
+  bucket:
+    type: aws:s3:Bucket
+    properties:
+      acl: private
+      acl: public-read
+variables:
+  a: 1
+  a: 2
+"#;
+        let (template, diags) = parse_template(source, None);
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        assert_eq!(template.pulumi.duplicate_keys, DuplicateKeys::Warn);
+        let warnings: Vec<String> = diags.iter().map(|d| d.summary.clone()).collect();
+        assert_eq!(
+            warnings,
+            vec![
+                "duplicate key 'resources.bucket.properties.acl'; keeping the last value",
+                "duplicate key 'variables.a'; keeping the last value",
+            ]
+        );
+        assert_eq!(template.variables.len(), 1);
+        assert!(matches!(&template.variables[0].value, Expr::Number(_, n) if *n == 2.0));
+    }
+
+    #[test]
+    fn test_duplicate_keys_default_applies_unless_the_document_sets_one() {
+        let source = "name: test\nvariables:\n  a: 1\n  a: 2\n";
+        let (template, diags) =
+            parse_template_with_duplicate_keys(source, None, DuplicateKeys::Warn);
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        assert_eq!(template.variables.len(), 1);
+
+        let source = "name: test\npulumi:\n  duplicateKeys: error\nvariables:\n  a: 1\n  a: 2\n";
+        let (_, diags) = parse_template_with_duplicate_keys(source, None, DuplicateKeys::Warn);
+        assert!(diags.has_errors());
+    }
+
+    #[test]
+    fn test_duplicate_keys_rejects_unknown_policy() {
+        let source = "name: test\npulumi:\n  duplicateKeys: ignore\n";
+        let (_, diags) = parse_template(source, None);
+        assert!(diags.has_errors());
+    }
+
+    #[test]
+    fn test_yaml11_booleans_are_strings_without_warning_by_default() {
+        let source = "name: test\nvariables:\n  country: NO\n";
+        let (template, diags) = parse_template(source, None);
+        assert!(diags.is_empty(), "diagnostics: {}", diags);
+        assert!(!template.pulumi.strict_scalars);
+        assert!(matches!(&template.variables[0].value, Expr::String(_, s) if s == "NO"));
+    }
+
     #[test]
     fn test_parse_pulumi_required_version() {
         let source = r#"
@@ -1671,6 +3179,17 @@ pulumi:
         assert!(matches!(&template.variables[0].value, Expr::Min(_, _)));
     }
 
+    #[test]
+    fn test_parse_list_builtins() {
+        let source = "name: test\nruntime: yaml\nvariables:\n  a:\n    fn::sort: [b, a]\n  b:\n    fn::unique: ${a}\n  c:\n    fn::length: ${b}\n  d:\n    fn::coalesce: [null, a]\n";
+        let (template, diags) = parse_template(source, None);
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        assert!(matches!(&template.variables[0].value, Expr::Sort(_, _)));
+        assert!(matches!(&template.variables[1].value, Expr::Unique(_, _)));
+        assert!(matches!(&template.variables[2].value, Expr::Length(_, _)));
+        assert!(matches!(&template.variables[3].value, Expr::Coalesce(_, _)));
+    }
+
     #[test]
     fn test_parse_string_len() {
         let source = "name: test\nruntime: yaml\nvariables:\n  v:\n    fn::stringLen: hello\n";
@@ -1693,6 +3212,138 @@ pulumi:
         ));
     }
 
+    #[test]
+    fn test_parse_string_case_builtins() {
+        let source = "name: test\nruntime: yaml\nvariables:\n  a:\n    fn::toUpper: x\n  b:\n    fn::toLower: X\n  c:\n    fn::trim: \" x \"\n  d:\n    fn::replace: [a.b, ., \"-\"]\n  e:\n    fn::startsWith: [prod-1, prod]\n";
+        let (template, diags) = parse_template(source, None);
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        let values: Vec<_> = template.variables.iter().map(|v| &v.value).collect();
+        assert!(matches!(values[0], Expr::ToUpper(_, _)));
+        assert!(matches!(values[1], Expr::ToLower(_, _)));
+        assert!(matches!(values[2], Expr::Trim(_, _)));
+        assert!(matches!(values[3], Expr::Replace(_, _, _, _)));
+        assert!(matches!(values[4], Expr::StartsWith(_, _, _)));
+
+        let source = "name: test\nruntime: yaml\nvariables:\n  v:\n    fn::replace: [a, b]\n";
+        let (_, diags) = parse_template(source, None);
+        assert!(diags
+            .to_string()
+            .contains("fn::replace must be a three-valued list"));
+    }
+
+    #[test]
+    fn test_parse_format() {
+        let source = "name: test\nruntime: yaml\nvariables:\n  v:\n    fn::format:\n      - \"{0}-{1}\"\n      - [a, 1]\n";
+        let (template, diags) = parse_template(source, None);
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        assert!(matches!(
+            &template.variables[0].value,
+            Expr::Format(_, _, _)
+        ));
+
+        let source = "name: test\nruntime: yaml\nvariables:\n  v:\n    fn::format: \"{0}\"\n";
+        let (_, diags) = parse_template(source, None);
+        assert!(diags
+            .to_string()
+            .contains("fn::format must be a two-valued list"));
+    }
+
+    #[test]
+    fn test_parse_cidr_builtins() {
+        let source = "name: test\nruntime: yaml\nvariables:\n  subnet:\n    fn::cidrSubnet: [10.0.0.0/16, 8, 1]\n  host:\n    fn::cidrHost: [10.0.1.0/24, 5]\n  mask:\n    fn::cidrNetmask: 10.0.0.0/16\n";
+        let (template, diags) = parse_template(source, None);
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        assert!(matches!(
+            &template.variables[0].value,
+            Expr::CidrSubnet(_, _, _, _)
+        ));
+        assert!(matches!(
+            &template.variables[1].value,
+            Expr::CidrHost(_, _, _)
+        ));
+        assert!(matches!(
+            &template.variables[2].value,
+            Expr::CidrNetmask(_, _)
+        ));
+
+        let source =
+            "name: test\nruntime: yaml\nvariables:\n  v:\n    fn::cidrSubnet: [10.0.0.0/16, 8]\n";
+        let (_, diags) = parse_template(source, None);
+        assert!(diags
+            .to_string()
+            .contains("fn::cidrSubnet must be a three-valued list"));
+    }
+
+    #[test]
+    fn test_parse_if() {
+        let source = "name: test\nruntime: yaml\nvariables:\n  v:\n    fn::if:\n      - ${flag}\n      - a\n      - b\n";
+        let (template, diags) = parse_template(source, None);
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        assert!(matches!(&template.variables[0].value, Expr::If(_, _, _, _)));
+
+        let source = "name: test\nruntime: yaml\nvariables:\n  v:\n    fn::if: [true, a]\n";
+        let (_, diags) = parse_template(source, None);
+        assert!(diags
+            .to_string()
+            .contains("fn::if must be a three-valued list"));
+    }
+
+    #[test]
+    fn test_parse_comparison_builtins() {
+        let source = "name: test\nruntime: yaml\nvariables:\n  a:\n    fn::equals: [prod, prod]\n  b:\n    fn::not: ${a}\n  c:\n    fn::and: [true, false]\n  d:\n    fn::or: [true, false]\n  e:\n    fn::lessThan: [1, 2]\n  f:\n    fn::lessThanOrEqual: [1, 2]\n  g:\n    fn::greaterThan: [1, 2]\n  h:\n    fn::greaterThanOrEqual: [1, 2]\n";
+        let (template, diags) = parse_template(source, None);
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        let values: Vec<_> = template.variables.iter().map(|v| &v.value).collect();
+        assert!(matches!(values[0], Expr::Equals(_, _, _)));
+        assert!(matches!(values[1], Expr::Not(_, _)));
+        assert!(matches!(values[2], Expr::And(_, _)));
+        assert!(matches!(values[3], Expr::Or(_, _)));
+        assert!(matches!(values[4], Expr::LessThan(_, _, _)));
+        assert!(matches!(values[5], Expr::LessThanOrEqual(_, _, _)));
+        assert!(matches!(values[6], Expr::GreaterThan(_, _, _)));
+        assert!(matches!(values[7], Expr::GreaterThanOrEqual(_, _, _)));
+
+        let source = "name: test\nruntime: yaml\nvariables:\n  v:\n    fn::greaterThan: [1]\n";
+        let (_, diags) = parse_template(source, None);
+        assert!(diags
+            .to_string()
+            .contains("fn::greaterThan must be a two-valued list"));
+    }
+
+    #[test]
+    fn test_parse_lookup() {
+        let source = "name: test\nruntime: yaml\nvariables:\n  v:\n    fn::lookup:\n      - {a: 1}\n      - a\n      - 0\n";
+        let (template, diags) = parse_template(source, None);
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        assert!(matches!(
+            &template.variables[0].value,
+            Expr::Lookup(_, _, _, Some(_))
+        ));
+
+        let source = "name: test\nruntime: yaml\nvariables:\n  v:\n    fn::lookup: [a]\n";
+        let (_, diags) = parse_template(source, None);
+        assert!(diags.has_errors());
+    }
+
+    #[test]
+    fn test_parse_env() {
+        let source = "name: test\nruntime: yaml\nvariables:\n  a:\n    fn::env: CI_TOKEN\n  b:\n    fn::env: [REGION, us-east-1]\n";
+        let (template, diags) = parse_template(source, None);
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        assert!(matches!(
+            &template.variables[0].value,
+            Expr::Env(_, _, None)
+        ));
+        assert!(matches!(
+            &template.variables[1].value,
+            Expr::Env(_, _, Some(_))
+        ));
+
+        let source = "name: test\nruntime: yaml\nvariables:\n  v:\n    fn::env: [a, b, c]\n";
+        let (_, diags) = parse_template(source, None);
+        assert!(diags.has_errors());
+    }
+
     #[test]
     fn test_parse_time_utc() {
         let source = "name: test\nruntime: yaml\nvariables:\n  v:\n    fn::timeUtc: {}\n";
@@ -1717,6 +3368,39 @@ pulumi:
         assert!(matches!(&template.variables[0].value, Expr::Uuid(_, _)));
     }
 
+    #[test]
+    fn test_interpolated_logical_name_errors() {
+        let source = "name: test\nruntime: yaml\nresources:\n  ${env}-bucket:\n    type: aws:s3:Bucket\n  bucket:\n    type: aws:s3:Bucket\n    name: ${env}-bucket\n";
+        let (template, diags) = parse_template(source, None);
+        assert!(diags.has_errors());
+        let message = diags.to_string();
+        assert!(
+            message.contains("resource logical name '${env}-bucket' cannot be interpolated"),
+            "{}",
+            message
+        );
+        assert_eq!(template.resources.len(), 1);
+        assert!(matches!(
+            template.resources[0].resource.name,
+            Some(Expr::Interpolate(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_parse_open_config() {
+        let source = "name: test\nruntime: yaml\nconfig:\n  aws:\n    fn::open::aws-login:\n      oidc: {}\n";
+        let (template, diags) = parse_template(source, None);
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        match &template.config[0].param.default {
+            Some(Expr::Open(_, provider, _)) => assert_eq!(provider, "aws-login"),
+            other => panic!("expected fn::open default, got {:?}", other),
+        }
+
+        let source = "name: test\nruntime: yaml\nvariables:\n  v:\n    fn::open::: {}\n";
+        let (_, diags) = parse_template(source, None);
+        assert!(diags.has_errors());
+    }
+
     #[test]
     fn test_parse_random_string() {
         let source = "name: test\nruntime: yaml\nvariables:\n  v:\n    fn::randomString: 32\n";
@@ -1739,4 +3423,266 @@ pulumi:
             Expr::DateFormat(_, _)
         ));
     }
+
+    #[test]
+    fn test_parse_imports() {
+        let source = r#"
+name: test
+runtime: yaml
+imports:
+  - url: https://example.com/network.yaml
+    sha256: sha256:9F86D081884C7D659A2FEAA0C55AD015A3BF4F1B2B0B822CD15D6C15B0F00A08
+  - url: oci://ghcr.io/acme/fragments:v1
+    sha256: 9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08
+"#;
+        let (template, diags) = parse_template(source, None);
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        assert_eq!(template.imports.len(), 2);
+        assert_eq!(template.imports[0].url, "https://example.com/network.yaml");
+        assert_eq!(
+            template.imports[0].sha256,
+            "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+        );
+    }
+
+    #[test]
+    fn test_parse_imports_requires_pin() {
+        let source = "name: test\nimports:\n  - url: https://example.com/a.yaml\n  - url: http://example.com/b.yaml\n    sha256: abc\n";
+        let (template, diags) = parse_template(source, None);
+        assert!(template.imports.is_empty());
+        let messages = diags.to_string();
+        assert!(messages.contains("missing 'sha256'"), "{}", messages);
+        assert!(messages.contains("unsupported url"), "{}", messages);
+    }
+
+    #[test]
+    fn test_parse_extends() {
+        let (template, diags) =
+            parse_template("name: test\nextends: ../base/Pulumi.base.yaml\n", None);
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        assert_eq!(
+            template.extends.as_deref(),
+            Some("../base/Pulumi.base.yaml")
+        );
+
+        let (template, diags) = parse_template("name: test\nextends: [a, b]\n", None);
+        assert!(template.extends.is_none());
+        assert!(diags.to_string().contains("extends: must be the path"));
+    }
+
+    #[test]
+    fn test_parse_map_and_filter() {
+        let source = "name: test\nvariables:\n  a:\n    fn::map: [[1, 2], '${item}']\n  b:\n    fn::filter: [[true], '${item}']\n  c:\n    fn::map: [[1, 2]]\n";
+        let (template, diags) = parse_template(source, None);
+        assert!(matches!(template.variables[0].value, Expr::Map(_, _, _)));
+        assert!(matches!(template.variables[1].value, Expr::Filter(_, _, _)));
+        assert!(diags
+            .to_string()
+            .contains("the argument to fn::map must be a two-valued list"));
+    }
+
+    #[test]
+    fn test_parse_foreach() {
+        let source = r#"
+name: test
+variables:
+  a:
+    fn::foreach:
+      list: [1, 2]
+      as: zone
+      index: i
+      body: ${zone}-${i}
+  b:
+    fn::foreach: [[1, 2], zone, '${zone}']
+  c:
+    fn::foreach:
+      list: [1, 2]
+      as: my-zone
+      body: ${my-zone}
+  d:
+    fn::foreach:
+      list: [1, 2]
+      as: zone
+      index: zone
+      body: ${zone}
+"#;
+        let (template, diags) = parse_template(source, None);
+        let Expr::Foreach(_, foreach) = &template.variables[0].value else {
+            panic!(
+                "expected fn::foreach, got {:?}",
+                template.variables[0].value
+            );
+        };
+        assert_eq!(foreach.bindings().collect::<Vec<_>>(), vec!["zone", "i"]);
+        let diags = diags.to_string();
+        assert!(diags.contains("the argument to fn::foreach must be an object"));
+        assert!(diags.contains("fn::foreach: 'as' must be a name"));
+        assert!(diags.contains("fn::foreach: 'as' and 'index' both bind 'zone'"));
+    }
+
+    #[test]
+    fn test_parse_validate_json() {
+        let source = "name: test\nvariables:\n  a:\n    fn::validateJson:\n      value: ${x}\n      schema: ./schema.json\n  b:\n    fn::validateJson: [a]\n";
+        let (template, diags) = parse_template(source, None);
+        assert!(matches!(
+            template.variables[0].value,
+            Expr::ValidateJson(_, _, _)
+        ));
+        assert!(diags.to_string().contains(
+            "the argument to fn::validateJson must be an object containing 'value' and 'schema'"
+        ));
+    }
+
+    #[test]
+    fn test_parse_assert() {
+        let source = "name: test\nvariables:\n  a:\n    fn::assert:\n      condition: ${ok}\n      message: not ok\n  b:\n    fn::assert:\n      condition: true\n";
+        let (template, diags) = parse_template(source, None);
+        assert!(matches!(
+            &template.variables[0].value,
+            Expr::Assert(_, _, _)
+        ));
+        assert_eq!(diags.len(), 1);
+        assert!(diags.to_string().contains(
+            "the argument to fn::assert must be an object containing 'condition' and 'message'"
+        ));
+    }
+
+    #[test]
+    fn test_parse_template_file() {
+        let source = "name: test\nvariables:\n  a:\n    fn::templateFile:\n      path: ./user-data.sh\n  b:\n    fn::templateFile:\n      arguments: {}\n";
+        let (template, diags) = parse_template(source, None);
+        let Expr::TemplateFile(_, _, arguments) = &template.variables[0].value else {
+            panic!("expected fn::templateFile");
+        };
+        assert!(matches!(**arguments, Expr::Object(_, ref e) if e.is_empty()));
+        assert!(diags
+            .to_string()
+            .contains("the argument to fn::templateFile must be an object containing 'path'"));
+    }
+
+    #[test]
+    fn test_parse_merge_and_json_patch() {
+        let source = "name: test\nvariables:\n  a:\n    fn::merge: [{x: 1}, {x: 2}]\n  b:\n    fn::jsonPatch: [{x: 1}, []]\n  c:\n    fn::jsonPatch: [{x: 1}]\n";
+        let (template, diags) = parse_template(source, None);
+        assert!(matches!(template.variables[0].value, Expr::Merge(_, _)));
+        assert!(matches!(
+            template.variables[1].value,
+            Expr::JsonPatch(_, _, _)
+        ));
+        assert!(diags
+            .to_string()
+            .contains("the argument to fn::jsonPatch must be a two-valued list"));
+    }
+
+    #[test]
+    fn test_parse_resource_count_and_range() {
+        let source = "name: test\nresources:\n  a:\n    type: test:Resource\n    count: 3\n  b:\n    type: test:Resource\n    range: ${zones}\n  c:\n    type: test:Resource\n    count: 1\n    range: [x]\n";
+        let (template, diags) = parse_template(source, None);
+        assert!(matches!(
+            template.resources[0].resource.range,
+            Some(ResourceRange::Count(Expr::Number(..)))
+        ));
+        assert!(matches!(
+            template.resources[1].resource.range,
+            Some(ResourceRange::Items(Expr::Symbol(..)))
+        ));
+        assert!(diags
+            .to_string()
+            .contains("a resource cannot set both count and range"));
+    }
+
+    #[test]
+    fn test_parse_resource_locals() {
+        let source = "name: test\nresources:\n  r:\n    type: test:Resource\n    locals:\n      a: 1\n      b: ${a}\n  s:\n    type: test:Resource\n    locals: [1]\n";
+        let (template, diags) = parse_template(source, None);
+        let locals = &template.resources[0].resource.locals;
+        assert_eq!(locals.len(), 2);
+        assert_eq!(locals[1].key, "b");
+        assert!(diags.to_string().contains("locals must be an object"));
+    }
+
+    #[test]
+    fn test_parse_validates_version_constraints() {
+        let source = r#"
+name: test
+runtime: yaml
+plugins:
+  providers:
+    - name: aws
+      version: ^6.1
+    - name: gcp
+      version: latest
+resources:
+  bucket:
+    type: aws:s3:Bucket
+    options:
+      version: ">=6.0.0, <7.0.0"
+  zone:
+    type: gcp:dns:ManagedZone
+    options:
+      version: 7.x.1
+"#;
+        let (template, diags) = parse_template(source, None);
+        let errors: Vec<_> = diags
+            .iter()
+            .filter(|d| d.is_error())
+            .map(|d| d.summary.as_str())
+            .collect();
+        assert_eq!(errors.len(), 2, "{}", diags);
+        assert!(errors[0]
+            .starts_with("plugins.providers[1].version: invalid version constraint 'latest'"));
+        assert!(errors[1].starts_with("options.version: invalid version constraint '7.x.1'"));
+        assert_eq!(template.plugins[0].version.as_deref(), Some("^6.1"));
+        assert_eq!(
+            template.resources[0].resource.options.version.as_deref(),
+            Some(">=6.0.0, <7.0.0")
+        );
+    }
+
+    #[test]
+    fn test_parse_runtime_and_plugins() {
+        let source = r#"
+name: test
+runtime:
+  name: yaml
+  options:
+    compiler: cue export
+    projectRelativePaths: true
+    allowedTypes: [kubernetes:yaml:ConfigFile]
+plugins:
+  providers:
+    - name: aws
+      path: ../pulumi-aws/bin
+    - name: acme
+      version: 1.2.0
+      server: https://plugins.example.com
+    - path: ./nameless
+"#;
+        let (template, diags) = parse_template(source, None);
+        let runtime = template.runtime.unwrap();
+        assert_eq!(runtime.name, "yaml");
+        assert_eq!(runtime.options[0].key, "compiler");
+        assert_eq!(runtime.bool_option("projectRelativePaths"), Some(true));
+        assert_eq!(runtime.bool_option("compiler"), None);
+        assert_eq!(
+            runtime.string_list_option("allowedTypes"),
+            Some(vec!["kubernetes:yaml:ConfigFile".to_string()])
+        );
+        assert_eq!(runtime.string_list_option("compiler"), None);
+        assert_eq!(template.plugins.len(), 2);
+        assert_eq!(
+            template.plugins[0].path.as_deref(),
+            Some("../pulumi-aws/bin")
+        );
+        assert_eq!(
+            template.plugins[1].server.as_deref(),
+            Some("https://plugins.example.com")
+        );
+        assert!(diags
+            .to_string()
+            .contains("plugins.providers[2] is missing 'name'"));
+
+        let (template, _) = parse_template("name: test\nruntime: yaml\n", None);
+        assert_eq!(template.runtime.unwrap().name, "yaml");
+    }
 }
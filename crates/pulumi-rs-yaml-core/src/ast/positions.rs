@@ -0,0 +1,534 @@
+//! Source positions of YAML nodes.
+//!
+//! `serde_yaml` values carry no positions, so [`index`] reads the document a
+//! second time with the libyaml event parser that `serde_yaml` is built on
+//! and records the span of every node in a [`SourceNode`] tree.
+//!
+//! While a template is parsed, [`PositionsGuard`] maps each node of the
+//! `serde_yaml::Value` tree to the span it was read from, and [`span_of`]
+//! looks a node up. The parser functions take plain values, so this is how
+//! every expression and declaration gets its place in the source without
+//! threading the tree through each of them.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::mem::MaybeUninit;
+
+use crate::source::FileId;
+use crate::syntax::Span;
+
+/// A YAML node and the span of its text.
+#[derive(Debug, Clone)]
+pub struct SourceNode {
+    pub span: Span,
+    pub kind: NodeKind,
+}
+
+/// The shape of a [`SourceNode`].
+#[derive(Debug, Clone)]
+pub enum NodeKind {
+    /// A scalar with its value. `plain` is set for unquoted, non-block
+    /// scalars, the only ones whose type depends on their text.
+    Scalar {
+        value: String,
+        plain: bool,
+    },
+    Sequence(Vec<SourceNode>),
+    /// Key and value nodes in document order, repeated keys included.
+    Mapping(Vec<(SourceNode, SourceNode)>),
+}
+
+impl SourceNode {
+    /// Returns the key and value nodes of the last entry whose key is the
+    /// scalar `key`, the one a lenient parse keeps.
+    pub fn entry(&self, key: &str) -> Option<(&SourceNode, &SourceNode)> {
+        match &self.kind {
+            NodeKind::Mapping(entries) => entries
+                .iter()
+                .rev()
+                .find(|(k, _)| k.as_str() == Some(key))
+                .map(|(k, v)| (k, v)),
+            _ => None,
+        }
+    }
+
+    /// Returns the value node of the last entry whose key is `key`.
+    pub fn get(&self, key: &str) -> Option<&SourceNode> {
+        self.entry(key).map(|(_, v)| v)
+    }
+
+    /// Returns the `index`th item of a sequence.
+    pub fn item(&self, index: usize) -> Option<&SourceNode> {
+        match &self.kind {
+            NodeKind::Sequence(items) => items.get(index),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a scalar.
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.kind {
+            NodeKind::Scalar { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Follows `path`, a list of mapping keys and sequence indices written
+    /// as numbers, from this node.
+    pub fn find(&self, path: &[&str]) -> Option<&SourceNode> {
+        path.iter()
+            .try_fold(self, |node, segment| match &node.kind {
+                NodeKind::Sequence(_) => node.item(segment.parse().ok()?),
+                _ => node.get(segment),
+            })
+    }
+
+    /// Calls `f` with this node and every node below it, keys included,
+    /// in document order.
+    pub fn walk<'a>(&'a self, f: &mut impl FnMut(&'a SourceNode)) {
+        f(self);
+        match &self.kind {
+            NodeKind::Scalar { .. } => {}
+            NodeKind::Sequence(items) => items.iter().for_each(|item| item.walk(f)),
+            NodeKind::Mapping(entries) => {
+                for (key, value) in entries {
+                    key.walk(f);
+                    value.walk(f);
+                }
+            }
+        }
+    }
+}
+
+/// Reads the first document of `source` into a tree of nodes. Spans are in
+/// `file`, offset by `base` bytes. Returns `None` for an empty stream or a
+/// document libyaml rejects; `serde_yaml` reports those.
+pub fn index(source: &str, file: FileId, base: u32) -> Option<SourceNode> {
+    let mut parser = EventParser::new(source);
+    let mut builder = TreeBuilder {
+        file,
+        base,
+        stack: Vec::new(),
+        anchors: HashMap::new(),
+        root: None,
+    };
+    loop {
+        match parser.next()? {
+            Event::StreamEnd | Event::DocumentEnd => return builder.root,
+            event => builder.push(event),
+        }
+        if builder.root.is_some() {
+            return builder.root;
+        }
+    }
+}
+
+/// One libyaml event with the byte range of its text.
+enum Event {
+    StreamEnd,
+    DocumentEnd,
+    Scalar {
+        anchor: Option<String>,
+        value: String,
+        plain: bool,
+        start: usize,
+        end: usize,
+    },
+    Alias {
+        anchor: String,
+        start: usize,
+        end: usize,
+    },
+    SequenceStart {
+        anchor: Option<String>,
+        start: usize,
+    },
+    MappingStart {
+        anchor: Option<String>,
+        start: usize,
+    },
+    /// The end of a sequence or a mapping.
+    CollectionEnd {
+        end: usize,
+    },
+    Other,
+}
+
+/// A libyaml parser over a borrowed string.
+struct EventParser<'a> {
+    // Boxed so the parser's address stays fixed for libyaml.
+    parser: Box<MaybeUninit<unsafe_libyaml::yaml_parser_t>>,
+    _input: &'a str,
+    initialized: bool,
+    failed: bool,
+}
+
+impl<'a> EventParser<'a> {
+    fn new(input: &'a str) -> Self {
+        let mut parser = Box::new(MaybeUninit::<unsafe_libyaml::yaml_parser_t>::uninit());
+        // SAFETY: `yaml_parser_initialize` fully initializes the parser, and
+        // `input` outlives it, so libyaml may keep a pointer to it.
+        let initialized = unsafe {
+            let ptr = parser.as_mut_ptr();
+            let ok = !unsafe_libyaml::yaml_parser_initialize(ptr).fail;
+            if ok {
+                unsafe_libyaml::yaml_parser_set_encoding(ptr, unsafe_libyaml::YAML_UTF8_ENCODING);
+                unsafe_libyaml::yaml_parser_set_input_string(
+                    ptr,
+                    input.as_ptr(),
+                    input.len() as u64,
+                );
+            }
+            ok
+        };
+        EventParser {
+            parser,
+            _input: input,
+            initialized,
+            failed: !initialized,
+        }
+    }
+
+    /// Returns the next event, or `None` once libyaml reports an error.
+    fn next(&mut self) -> Option<Event> {
+        if self.failed {
+            return None;
+        }
+        let mut event = MaybeUninit::<unsafe_libyaml::yaml_event_t>::uninit();
+        // SAFETY: the parser was initialized in `new`. A successful
+        // `yaml_parser_parse` initializes `event`, which is read before
+        // `yaml_event_delete` frees its strings.
+        unsafe {
+            let parser = self.parser.as_mut_ptr();
+            let event = event.as_mut_ptr();
+            if unsafe_libyaml::yaml_parser_parse(parser, event).fail {
+                self.failed = true;
+                return None;
+            }
+            let converted = convert(&*event);
+            unsafe_libyaml::yaml_event_delete(event);
+            Some(converted)
+        }
+    }
+}
+
+impl Drop for EventParser<'_> {
+    fn drop(&mut self) {
+        if self.initialized {
+            // SAFETY: the parser was initialized in `new`.
+            unsafe { unsafe_libyaml::yaml_parser_delete(self.parser.as_mut_ptr()) }
+        }
+    }
+}
+
+/// Copies what the tree needs out of a libyaml event.
+///
+/// # Safety
+///
+/// `event` must be an event produced by `yaml_parser_parse` and not yet
+/// deleted.
+unsafe fn convert(event: &unsafe_libyaml::yaml_event_t) -> Event {
+    let start = event.start_mark.index as usize;
+    let end = event.end_mark.index as usize;
+    match event.type_ {
+        unsafe_libyaml::YAML_STREAM_END_EVENT => Event::StreamEnd,
+        unsafe_libyaml::YAML_DOCUMENT_END_EVENT => Event::DocumentEnd,
+        unsafe_libyaml::YAML_SCALAR_EVENT => {
+            let scalar = unsafe { event.data.scalar };
+            let bytes = unsafe { std::slice::from_raw_parts(scalar.value, scalar.length as usize) };
+            Event::Scalar {
+                anchor: unsafe { c_string(scalar.anchor) },
+                value: String::from_utf8_lossy(bytes).into_owned(),
+                plain: scalar.style == unsafe_libyaml::YAML_PLAIN_SCALAR_STYLE,
+                start,
+                end,
+            }
+        }
+        unsafe_libyaml::YAML_ALIAS_EVENT => Event::Alias {
+            anchor: unsafe { c_string(event.data.alias.anchor) }.unwrap_or_default(),
+            start,
+            end,
+        },
+        unsafe_libyaml::YAML_SEQUENCE_START_EVENT => Event::SequenceStart {
+            anchor: unsafe { c_string(event.data.sequence_start.anchor) },
+            start,
+        },
+        unsafe_libyaml::YAML_MAPPING_START_EVENT => Event::MappingStart {
+            anchor: unsafe { c_string(event.data.mapping_start.anchor) },
+            start,
+        },
+        unsafe_libyaml::YAML_SEQUENCE_END_EVENT | unsafe_libyaml::YAML_MAPPING_END_EVENT => {
+            Event::CollectionEnd { end }
+        }
+        _ => Event::Other,
+    }
+}
+
+/// Reads a nullable C string owned by libyaml.
+///
+/// # Safety
+///
+/// `ptr` must be null or point to a NUL-terminated string.
+unsafe fn c_string(ptr: *const u8) -> Option<String> {
+    if ptr.is_null() {
+        return None;
+    }
+    let s = unsafe { CStr::from_ptr(ptr.cast()) };
+    Some(s.to_string_lossy().into_owned())
+}
+
+/// A sequence or mapping whose end event has not been read yet.
+struct OpenCollection {
+    anchor: Option<String>,
+    start: usize,
+    mapping: bool,
+    nodes: Vec<SourceNode>,
+}
+
+/// Assembles nodes from events.
+struct TreeBuilder {
+    file: FileId,
+    base: u32,
+    stack: Vec<OpenCollection>,
+    anchors: HashMap<String, SourceNode>,
+    root: Option<SourceNode>,
+}
+
+impl TreeBuilder {
+    fn span(&self, start: usize, end: usize) -> Span {
+        Span::new(
+            self.file,
+            self.base + start as u32,
+            self.base + end.max(start) as u32,
+        )
+    }
+
+    fn push(&mut self, event: Event) {
+        match event {
+            Event::Scalar {
+                anchor,
+                value,
+                plain,
+                start,
+                end,
+            } => {
+                let node = SourceNode {
+                    span: self.span(start, end),
+                    kind: NodeKind::Scalar { value, plain },
+                };
+                self.complete(anchor, node);
+            }
+            Event::Alias { anchor, start, end } => {
+                // The alias stands for the anchored node; its children keep
+                // the positions where they are written.
+                let kind = match self.anchors.get(&anchor) {
+                    Some(node) => node.kind.clone(),
+                    None => NodeKind::Scalar {
+                        value: String::new(),
+                        plain: false,
+                    },
+                };
+                let node = SourceNode {
+                    span: self.span(start, end),
+                    kind,
+                };
+                self.complete(None, node);
+            }
+            Event::SequenceStart { anchor, start } => self.stack.push(OpenCollection {
+                anchor,
+                start,
+                mapping: false,
+                nodes: Vec::new(),
+            }),
+            Event::MappingStart { anchor, start } => self.stack.push(OpenCollection {
+                anchor,
+                start,
+                mapping: true,
+                nodes: Vec::new(),
+            }),
+            Event::CollectionEnd { end } => {
+                let Some(open) = self.stack.pop() else {
+                    return;
+                };
+                let kind = if open.mapping {
+                    let mut entries = Vec::with_capacity(open.nodes.len() / 2);
+                    let mut nodes = open.nodes.into_iter();
+                    while let (Some(key), Some(value)) = (nodes.next(), nodes.next()) {
+                        entries.push((key, value));
+                    }
+                    NodeKind::Mapping(entries)
+                } else {
+                    NodeKind::Sequence(open.nodes)
+                };
+                let node = SourceNode {
+                    span: self.span(open.start, end),
+                    kind,
+                };
+                self.complete(open.anchor, node);
+            }
+            Event::StreamEnd | Event::DocumentEnd | Event::Other => {}
+        }
+    }
+
+    /// Adds a finished node to the enclosing collection, or makes it the
+    /// root.
+    fn complete(&mut self, anchor: Option<String>, node: SourceNode) {
+        if let Some(anchor) = anchor {
+            self.anchors.insert(anchor, node.clone());
+        }
+        match self.stack.last_mut() {
+            Some(open) => open.nodes.push(node),
+            None => self.root = Some(node),
+        }
+    }
+}
+
+thread_local! {
+    /// Spans of the `serde_yaml::Value` nodes being parsed on this thread,
+    /// keyed by address.
+    static SPANS: RefCell<Option<HashMap<usize, Span>>> = const { RefCell::new(None) };
+}
+
+/// Makes [`span_of`] answer for the nodes of one `serde_yaml::Value` on
+/// this thread until it drops. The value must not be moved or changed
+/// while the guard is alive.
+pub(crate) struct PositionsGuard(Option<HashMap<usize, Span>>);
+
+impl PositionsGuard {
+    /// Maps the nodes of `value` to the spans of the matching nodes of
+    /// `node`, the tree `value` was parsed from.
+    pub(crate) fn enter(value: &serde_yaml::Value, node: Option<&SourceNode>) -> Self {
+        let mut spans = HashMap::new();
+        if let Some(node) = node {
+            record(value, node, &mut spans);
+        }
+        PositionsGuard(SPANS.with(|s| s.replace(Some(spans))))
+    }
+}
+
+impl Drop for PositionsGuard {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        SPANS.with(|s| *s.borrow_mut() = previous);
+    }
+}
+
+/// Returns the span `value` was parsed from, if a [`PositionsGuard`] for its
+/// document is alive on this thread.
+pub fn span_of(value: &serde_yaml::Value) -> Option<Span> {
+    SPANS.with(|s| s.borrow().as_ref()?.get(&address(value)).copied())
+}
+
+fn address(value: &serde_yaml::Value) -> usize {
+    value as *const serde_yaml::Value as usize
+}
+
+/// Records the span of `value` and of everything below it.
+fn record(value: &serde_yaml::Value, node: &SourceNode, spans: &mut HashMap<usize, Span>) {
+    use serde_yaml::Value;
+
+    spans.insert(address(value), node.span);
+    match (value, &node.kind) {
+        (Value::Mapping(map), NodeKind::Mapping(_)) => {
+            for (key, item) in map {
+                match key.as_str().and_then(|k| node.entry(k)) {
+                    Some((key_node, item_node)) => {
+                        spans.insert(address(key), key_node.span);
+                        record(item, item_node, spans);
+                    }
+                    // Entries a snippet call expanded to are placed at the
+                    // call.
+                    None => {
+                        fill(key, node.span, spans);
+                        fill(item, node.span, spans);
+                    }
+                }
+            }
+        }
+        (Value::Sequence(items), NodeKind::Sequence(nodes)) => {
+            for (i, item) in items.iter().enumerate() {
+                match nodes.get(i) {
+                    Some(item_node) => record(item, item_node, spans),
+                    None => fill(item, node.span, spans),
+                }
+            }
+        }
+        (Value::Tagged(tagged), _) => record(&tagged.value, node, spans),
+        _ => fill(value, node.span, spans),
+    }
+}
+
+/// Places `value` and everything below it at `span`.
+fn fill(value: &serde_yaml::Value, span: Span, spans: &mut HashMap<usize, Span>) {
+    use serde_yaml::Value;
+
+    spans.insert(address(value), span);
+    match value {
+        Value::Mapping(map) => {
+            for (key, item) in map {
+                fill(key, span, spans);
+                fill(item, span, spans);
+            }
+        }
+        Value::Sequence(items) => items.iter().for_each(|item| fill(item, span, spans)),
+        Value::Tagged(tagged) => fill(&tagged.value, span, spans),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(source: &str, span: Span) -> &str {
+        &source[span.start as usize..span.end as usize]
+    }
+
+    #[test]
+    fn test_index_records_node_spans() {
+        let source = "name: test\nresources:\n  bucket:\n    type: aws:s3:Bucket\n    properties:\n      tags: [a, \"b\"]\n      acl: &acl private\n      copy: *acl\n";
+        let root = index(source, FileId(3), 0).unwrap();
+        assert_eq!(root.span.file, FileId(3));
+
+        let bucket = root.find(&["resources", "bucket"]).unwrap();
+        let (key, _) = root.get("resources").unwrap().entry("bucket").unwrap();
+        assert_eq!(text(source, key.span), "bucket");
+        let type_ = bucket.get("type").unwrap();
+        assert_eq!(text(source, type_.span), "aws:s3:Bucket");
+        let tag = bucket.find(&["properties", "tags", "1"]).unwrap();
+        assert_eq!(text(source, tag.span), "\"b\"");
+        assert!(matches!(tag.kind, NodeKind::Scalar { plain: false, .. }));
+        let copy = bucket.find(&["properties", "copy"]).unwrap();
+        assert_eq!(text(source, copy.span), "*acl");
+        assert_eq!(copy.as_str(), Some("private"));
+    }
+
+    #[test]
+    fn test_index_rejects_invalid_yaml() {
+        assert!(index("a: [1, 2\n", FileId(0), 0).is_none());
+    }
+
+    #[test]
+    fn test_span_of_follows_value_tree() {
+        let source = "outer:\n  - key: value\n  - !Tag tagged\n";
+        let value: serde_yaml::Value = serde_yaml::from_str(source).unwrap();
+        let root = index(source, FileId(0), 10);
+        assert!(span_of(&value).is_none());
+        {
+            let _guard = PositionsGuard::enter(&value, root.as_ref());
+            let items = value["outer"].as_sequence().unwrap();
+            let span = span_of(&items[0]["key"]).unwrap();
+            assert_eq!((span.start, span.end), (26, 31));
+            let serde_yaml::Value::Tagged(tagged) = &items[1] else {
+                panic!("expected a tagged value");
+            };
+            let span = span_of(&tagged.value).unwrap();
+            assert_eq!(
+                text(source, Span::new(span.file, span.start - 10, span.end - 10)),
+                "!Tag tagged"
+            );
+        }
+        assert!(span_of(&value).is_none());
+    }
+}
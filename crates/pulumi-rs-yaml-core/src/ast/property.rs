@@ -1,23 +1,44 @@
 use crate::diag::Diagnostics;
 use crate::syntax::Span;
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::fmt;
 
 /// A chain of property accesses (e.g. `resource.nested[0].prop`).
-#[derive(Debug, Clone, PartialEq)]
+///
+/// Besides names and subscripts, chains support negative indices
+/// (`items[-1]`), slices (`items[1:3]`) and `*` projections
+/// (`buckets.*.id` or `buckets[*].id`), which yield lists.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct PropertyAccess<'src> {
     pub accessors: Vec<PropertyAccessor<'src>>,
 }
 
 /// A single step in a property access chain.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub enum PropertyAccessor<'src> {
     /// A named property access (e.g. `.name` or the root `name`).
     Name(Cow<'src, str>),
     /// A subscript access with a string key (e.g. `["key"]`).
     StringSubscript(Cow<'src, str>),
     /// A subscript access with an integer index (e.g. `[0]`).
+    /// Negative indices count from the end of the list (`[-1]` is the last item).
     IntSubscript(i64),
+    /// A list slice `[start:end]` with Python semantics: either bound may be
+    /// omitted, negative bounds count from the end, and out-of-range bounds
+    /// are clamped.
+    Slice(Option<i64>, Option<i64>),
+    /// A `*` projection (`.*` or `[*]`). The rest of the chain is applied to
+    /// every list element (or object value) and the non-null results are
+    /// collected into a list.
+    Wildcard,
+    /// A subscript whose key is another property access (e.g. `[b.c]`).
+    ///
+    /// The key is resolved against the same scope as the root at evaluation
+    /// time and must produce a string (object key) or an integer (list index).
+    PropertySubscript(Box<PropertyAccess<'src>>),
 }
 
 impl PropertyAccess<'_> {
@@ -27,6 +48,66 @@ impl PropertyAccess<'_> {
             PropertyAccessor::Name(n) => Ok(n.as_ref()),
             PropertyAccessor::StringSubscript(n) => Ok(n.as_ref()),
             PropertyAccessor::IntSubscript(_) => Err("root cannot be integer subscript"),
+            PropertyAccessor::Slice(_, _) => Err("root cannot be a slice"),
+            PropertyAccessor::Wildcard => Err("root cannot be a wildcard"),
+            PropertyAccessor::PropertySubscript(_) => {
+                Err("root cannot be a property access subscript")
+            }
+        }
+    }
+
+    /// Returns the root names of all property accesses nested inside
+    /// subscripts of this chain (e.g. `b` for `a[b.c]`), recursively.
+    pub fn nested_root_names(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        self.collect_nested_roots(&mut names);
+        names
+    }
+
+    fn collect_nested_roots<'a>(&'a self, names: &mut Vec<&'a str>) {
+        for accessor in &self.accessors {
+            if let PropertyAccessor::PropertySubscript(inner) = accessor {
+                if let Ok(root) = inner.root_name() {
+                    names.push(root);
+                }
+                inner.collect_nested_roots(names);
+            }
+        }
+    }
+
+    /// Returns true if any subscript in the chain is itself a property access.
+    pub fn has_nested_access(&self) -> bool {
+        self.accessors
+            .iter()
+            .any(|a| matches!(a, PropertyAccessor::PropertySubscript(_)))
+    }
+
+    /// Converts into an owned `'static` access chain.
+    pub fn into_owned(self) -> PropertyAccess<'static> {
+        PropertyAccess {
+            accessors: self
+                .accessors
+                .into_iter()
+                .map(PropertyAccessor::into_owned)
+                .collect(),
+        }
+    }
+}
+
+impl PropertyAccessor<'_> {
+    /// Converts into an owned `'static` accessor.
+    pub fn into_owned(self) -> PropertyAccessor<'static> {
+        match self {
+            PropertyAccessor::Name(n) => PropertyAccessor::Name(Cow::Owned(n.into_owned())),
+            PropertyAccessor::StringSubscript(s) => {
+                PropertyAccessor::StringSubscript(Cow::Owned(s.into_owned()))
+            }
+            PropertyAccessor::IntSubscript(i) => PropertyAccessor::IntSubscript(i),
+            PropertyAccessor::Slice(start, end) => PropertyAccessor::Slice(start, end),
+            PropertyAccessor::Wildcard => PropertyAccessor::Wildcard,
+            PropertyAccessor::PropertySubscript(inner) => {
+                PropertyAccessor::PropertySubscript(Box::new(inner.into_owned()))
+            }
         }
     }
 }
@@ -48,6 +129,23 @@ impl fmt::Display for PropertyAccess<'_> {
                 PropertyAccessor::IntSubscript(idx) => {
                     write!(f, "[{}]", idx)?;
                 }
+                PropertyAccessor::Slice(start, end) => {
+                    write!(f, "[")?;
+                    if let Some(start) = start {
+                        write!(f, "{}", start)?;
+                    }
+                    write!(f, ":")?;
+                    if let Some(end) = end {
+                        write!(f, "{}", end)?;
+                    }
+                    write!(f, "]")?;
+                }
+                PropertyAccessor::Wildcard => {
+                    write!(f, "[*]")?;
+                }
+                PropertyAccessor::PropertySubscript(inner) => {
+                    write!(f, "[{}]", inner)?;
+                }
             }
         }
         Ok(())
@@ -69,100 +167,246 @@ pub fn is_valid_property_name(s: &str) -> bool {
 /// Returns `(remaining_input, parsed_access)`.
 ///
 /// The input is expected to start after the `${` of an interpolation expression.
-/// The parser consumes through the matching `}`.
+/// The parser consumes through the matching `}`. Subscripts may themselves
+/// contain a property access (e.g. `${a[b.c]}`), which is parsed recursively.
 pub fn parse_property_access<'src>(
     input: &'src str,
     span: Option<Span>,
     diags: &mut Diagnostics,
 ) -> (&'src str, Option<PropertyAccess<'src>>) {
-    let mut accessors: Vec<PropertyAccessor<'src>> = Vec::new();
-    let mut remaining = input;
-
-    while !remaining.is_empty() {
-        let first = remaining.as_bytes()[0];
-        match first {
-            b'}' => {
-                // End of interpolation
-                return (&remaining[1..], Some(PropertyAccess { accessors }));
-            }
-            b'.' => {
-                remaining = &remaining[1..];
+    parse_property_access_at(input, 0, span, diags)
+}
+
+/// Like [`parse_property_access`], but `base` is the byte offset of `input`
+/// within the string that `span` covers. Used by the interpolation parser so
+/// that diagnostics point at the offending character rather than the whole
+/// string.
+pub(crate) fn parse_property_access_at<'src>(
+    input: &'src str,
+    base: usize,
+    span: Option<Span>,
+    diags: &mut Diagnostics,
+) -> (&'src str, Option<PropertyAccess<'src>>) {
+    let mut parser = AccessParser {
+        input,
+        base,
+        span,
+        diags,
+    };
+    match parser.parse(input, b'}') {
+        Some((rest, access)) => (rest, Some(access)),
+        None => ("", None),
+    }
+}
+
+/// Recursive-descent parser for property access chains.
+struct AccessParser<'src, 'd> {
+    /// The full input handed to `parse_property_access_at`.
+    input: &'src str,
+    /// Byte offset of `input` within the string covered by `span`.
+    base: usize,
+    span: Option<Span>,
+    diags: &'d mut Diagnostics,
+}
+
+impl<'src> AccessParser<'src, '_> {
+    /// Byte offset of `at` within the enclosing string.
+    fn offset_of(&self, at: &str) -> usize {
+        self.base + (self.input.len() - at.len())
+    }
+
+    /// Emits an error pointing at the start of `at`.
+    ///
+    /// When the enclosing span is known, it is narrowed to the offending
+    /// character; the detail always carries the byte offset so the location
+    /// is recoverable even without a span.
+    fn error(&mut self, at: &str, summary: &str) {
+        let offset = self.offset_of(at);
+        let span = self.span.map(|s| {
+            let start = s.start.saturating_add(offset as u32).min(s.end);
+            let end = start.saturating_add(1).min(s.end);
+            Span::new(s.file, start, end)
+        });
+        let detail = format!("at offset {} of the interpolation", offset);
+        self.diags.error(span, summary, detail);
+    }
+
+    /// Parses accessors until the `close` byte (`}` at the top level, `]` for
+    /// a nested key), returning the input following it.
+    fn parse(&mut self, start: &'src str, close: u8) -> Option<(&'src str, PropertyAccess<'src>)> {
+        let mut accessors: Vec<PropertyAccessor<'src>> = Vec::new();
+        let mut remaining = start;
+        let name_terminators = ['.', '[', close as char];
+
+        while !remaining.is_empty() {
+            let first = remaining.as_bytes()[0];
+            if first == close {
+                if accessors.is_empty() {
+                    self.error(remaining, "empty property access");
+                    return None;
+                }
+                return Some((&remaining[1..], PropertyAccess { accessors }));
             }
-            b'[' => {
-                // Check for string key: ["..."]
-                if remaining.len() > 1 && remaining.as_bytes()[1] == b'"' {
-                    let mut key = Vec::new();
-                    let mut i = 2;
-                    let bytes = remaining.as_bytes();
-                    loop {
-                        if i >= bytes.len() {
-                            diags.error(span, "missing closing quote in property name", "");
-                            return ("", None);
-                        } else if bytes[i] == b'"' {
-                            i += 1;
-                            break;
-                        } else if bytes[i] == b'\\' && i + 1 < bytes.len() && bytes[i + 1] == b'"' {
-                            key.push(b'"');
-                            i += 2;
-                        } else {
-                            key.push(bytes[i]);
-                            i += 1;
-                        }
+            match first {
+                b'.' => {
+                    if accessors.is_empty() {
+                        self.error(remaining, "property access cannot start with '.'");
+                        return None;
                     }
-                    if i >= bytes.len() || bytes[i] != b']' {
-                        diags.error(span, "missing closing bracket in property access", "");
-                        return ("", None);
+                    let next = remaining.as_bytes().get(1).copied();
+                    if next == Some(b'.') || next == Some(b'[') || next == Some(close) {
+                        self.error(&remaining[1..], "expected a property name after '.'");
+                        return None;
                     }
-                    let key_str = match String::from_utf8(key) {
-                        Ok(s) => s,
-                        Err(_) => {
-                            diags.error(span, "property key contains invalid UTF-8", "");
-                            return ("", None);
+                    remaining = &remaining[1..];
+                }
+                b'[' => {
+                    let (rest, accessor) = self.parse_subscript(remaining, accessors.is_empty())?;
+                    accessors.push(accessor);
+                    remaining = rest;
+                }
+                _ => {
+                    // Read a property name
+                    let end = remaining.find(name_terminators).unwrap_or(remaining.len());
+                    let name = &remaining[..end];
+                    if name == "*" {
+                        if accessors.is_empty() {
+                            self.error(remaining, "root cannot be a wildcard");
+                            return None;
                         }
-                    };
-                    accessors.push(PropertyAccessor::StringSubscript(Cow::Owned(key_str)));
-                    remaining = &remaining[i + 1..];
+                        accessors.push(PropertyAccessor::Wildcard);
+                    } else {
+                        accessors.push(PropertyAccessor::Name(Cow::Borrowed(name)));
+                    }
+                    remaining = &remaining[end..];
+                }
+            }
+        }
+
+        if close == b']' {
+            self.error(start, "missing closing bracket in property access");
+        } else {
+            self.error(start, "unterminated interpolation");
+        }
+        None
+    }
+
+    /// Parses one bound of a `[start:end]` slice; empty means unbounded.
+    fn parse_slice_bound(&mut self, bound: &str, at: &str) -> Option<Option<i64>> {
+        if bound.is_empty() {
+            return Some(None);
+        }
+        match bound.parse() {
+            Ok(v) => Some(Some(v)),
+            Err(_) => {
+                self.error(at, "invalid slice bound");
+                None
+            }
+        }
+    }
+
+    /// Parses a `[...]` subscript starting at the opening bracket.
+    fn parse_subscript(
+        &mut self,
+        remaining: &'src str,
+        is_root: bool,
+    ) -> Option<(&'src str, PropertyAccessor<'src>)> {
+        let bytes = remaining.as_bytes();
+        match bytes.get(1) {
+            // String key: ["..."]
+            Some(b'"') => {
+                let mut key = Vec::new();
+                let mut i = 2;
+                loop {
+                    if i >= bytes.len() {
+                        self.error(remaining, "missing closing quote in property name");
+                        return None;
+                    } else if bytes[i] == b'"' {
+                        i += 1;
+                        break;
+                    } else if bytes[i] == b'\\' && i + 1 < bytes.len() && bytes[i + 1] == b'"' {
+                        key.push(b'"');
+                        i += 2;
+                    } else {
+                        key.push(bytes[i]);
+                        i += 1;
+                    }
+                }
+                if i >= bytes.len() || bytes[i] != b']' {
+                    self.error(
+                        &remaining[i.min(remaining.len())..],
+                        "missing closing bracket in property access",
+                    );
+                    return None;
+                }
+                let key_str = match String::from_utf8(key) {
+                    Ok(s) => s,
+                    Err(_) => {
+                        self.error(remaining, "property key contains invalid UTF-8");
+                        return None;
+                    }
+                };
+                Some((
+                    &remaining[i + 1..],
+                    PropertyAccessor::StringSubscript(Cow::Owned(key_str)),
+                ))
+            }
+            // Numeric index [N] or slice [start:end]
+            Some(c) if c.is_ascii_digit() || *c == b'-' || *c == b':' || *c == b'*' => {
+                let rbracket = match remaining.find(']') {
+                    Some(pos) => pos,
+                    None => {
+                        self.error(remaining, "missing closing bracket in list index");
+                        return None;
+                    }
+                };
+                if is_root {
+                    self.error(
+                        remaining,
+                        "the root property must be a string subscript or a name",
+                    );
+                    return None;
+                }
+                let index_str = &remaining[1..rbracket];
+                let accessor = if index_str == "*" {
+                    PropertyAccessor::Wildcard
+                } else if let Some((start, end)) = index_str.split_once(':') {
+                    let start = self.parse_slice_bound(start, &remaining[1..])?;
+                    let end = self.parse_slice_bound(end, &remaining[1..])?;
+                    PropertyAccessor::Slice(start, end)
                 } else {
-                    // Numeric index: [N]
-                    let rbracket = match remaining.find(']') {
-                        Some(pos) => pos,
-                        None => {
-                            diags.error(span, "missing closing bracket in list index", "");
-                            return ("", None);
-                        }
-                    };
-                    let index_str = &remaining[1..rbracket];
-                    let index: i64 = match index_str.parse() {
-                        Ok(v) => v,
+                    match index_str.parse() {
+                        Ok(v) => PropertyAccessor::IntSubscript(v),
                         Err(_) => {
-                            diags.error(span, "invalid list index", "");
-                            return ("", None);
+                            self.error(&remaining[1..], "invalid list index");
+                            return None;
                         }
-                    };
-                    if accessors.is_empty() {
-                        diags.error(
-                            span,
-                            "the root property must be a string subscript or a name",
-                            "",
-                        );
-                        return ("", None);
                     }
-                    accessors.push(PropertyAccessor::IntSubscript(index));
-                    remaining = &remaining[rbracket + 1..];
+                };
+                Some((&remaining[rbracket + 1..], accessor))
+            }
+            Some(b']') => {
+                self.error(&remaining[1..], "empty subscript in property access");
+                None
+            }
+            // Nested property access used as the key: [b.c]
+            Some(_) => {
+                if is_root {
+                    self.error(
+                        remaining,
+                        "the root property must be a string subscript or a name",
+                    );
+                    return None;
                 }
+                let (rest, key) = self.parse(&remaining[1..], b']')?;
+                Some((rest, PropertyAccessor::PropertySubscript(Box::new(key))))
             }
-            _ => {
-                // Read a property name
-                let end = remaining.find(['.', '[', '}']).unwrap_or(remaining.len());
-                let name = &remaining[..end];
-                accessors.push(PropertyAccessor::Name(Cow::Borrowed(name)));
-                remaining = &remaining[end..];
+            None => {
+                self.error(remaining, "missing closing bracket in property access");
+                None
             }
         }
     }
-
-    diags.error(span, "unterminated interpolation", "");
-    ("", None)
 }
 
 #[cfg(test)]
@@ -267,6 +511,79 @@ mod tests {
         assert!(access.is_none());
     }
 
+    #[test]
+    fn test_nested_property_subscript() {
+        let (rest, access) = parse_ok("amis[config.region][vars[\"k\"]].id} tail");
+        assert_eq!(rest, " tail");
+        assert_eq!(access.to_string(), "amis[config.region][vars[\"k\"]].id");
+        assert!(access.has_nested_access());
+        assert_eq!(access.nested_root_names(), vec!["config", "vars"]);
+        match &access.accessors[1] {
+            PropertyAccessor::PropertySubscript(inner) => {
+                assert_eq!(inner.to_string(), "config.region")
+            }
+            other => panic!("expected nested subscript, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_negative_index_slice_and_wildcard() {
+        let (_, access) = parse_ok("items[-1]}");
+        assert_eq!(access.accessors[1], PropertyAccessor::IntSubscript(-1));
+
+        let (_, access) = parse_ok("items[1:3]}");
+        assert_eq!(
+            access.accessors[1],
+            PropertyAccessor::Slice(Some(1), Some(3))
+        );
+        assert_eq!(access.to_string(), "items[1:3]");
+
+        let (_, access) = parse_ok("items[:-2]}");
+        assert_eq!(access.accessors[1], PropertyAccessor::Slice(None, Some(-2)));
+
+        let (_, access) = parse_ok("buckets.*.id}");
+        assert_eq!(access.accessors[1], PropertyAccessor::Wildcard);
+        assert_eq!(access.to_string(), "buckets[*].id");
+
+        let (_, access) = parse_ok("buckets[*].id}");
+        assert_eq!(access.accessors[1], PropertyAccessor::Wildcard);
+    }
+
+    #[test]
+    fn test_invalid_slice_bound_error() {
+        let mut diags = Diagnostics::new();
+        let (_, access) = parse_property_access("items[1:x]}", None, &mut diags);
+        assert!(diags.has_errors());
+        assert!(access.is_none());
+    }
+
+    #[test]
+    fn test_nested_unterminated_error() {
+        let mut diags = Diagnostics::new();
+        let (_, access) = parse_property_access("a[b.c}", None, &mut diags);
+        assert!(diags.has_errors());
+        assert!(access.is_none());
+    }
+
+    #[test]
+    fn test_empty_access_error() {
+        let mut diags = Diagnostics::new();
+        let (_, access) = parse_property_access("}", None, &mut diags);
+        assert!(diags.has_errors());
+        assert!(access.is_none());
+    }
+
+    #[test]
+    fn test_error_span_is_narrowed() {
+        use crate::source::FileId;
+        let mut diags = Diagnostics::new();
+        let span = Span::new(FileId(0), 100, 120);
+        let (_, access) = parse_property_access("root.[0]}", Some(span), &mut diags);
+        assert!(access.is_none());
+        let diag = diags.iter().next().unwrap();
+        assert_eq!(diag.span, Some(Span::new(FileId(0), 105, 106)));
+    }
+
     #[test]
     fn test_is_valid_property_name() {
         assert!(is_valid_property_name("foo"));
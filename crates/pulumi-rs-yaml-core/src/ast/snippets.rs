@@ -0,0 +1,725 @@
+//! Parameterized YAML snippets.
+//!
+//! A template's `snippets:` section defines reusable YAML fragments:
+//!
+//! ```yaml
+//! snippets:
+//!   tags:
+//!     params:
+//!       team: {}
+//!       env: { default: dev }
+//!     body:
+//!       team: ${params.team}
+//!       environment: ${params.env}
+//!       owner: ${params.team}-${params.env}
+//! resources:
+//!   bucket:
+//!     type: aws:s3:Bucket
+//!     properties:
+//!       tags:
+//!         fn::snippet:
+//!           name: tags
+//!           params: { team: web }
+//! ```
+//!
+//! `params:` may also be a list of names, all of them required.
+//!
+//! Calls are expanded while the template is parsed, before anything else sees
+//! it: each `fn::snippet` is replaced by the snippet's body with every
+//! `${params.<name>}` substituted, in values and in keys. A string that is
+//! exactly one placeholder takes the parameter's value as is, which may be
+//! any YAML value; a placeholder inside a longer string takes its scalar
+//! text. `$${params.<name>}` is left alone. Bodies may call other snippets,
+//! but not recursively.
+//!
+//! Unlike Jinja blocks, expansion is purely structural: it cannot loop, read
+//! the environment, or produce malformed YAML. Calls may nest at most
+//! [`MAX_DEPTH`] deep, and a file's calls may produce at most
+//! [`MAX_EXPANDED_VALUES`] values in all, so snippets that call others
+//! several times cannot grow the document exponentially. Snippets are
+//! visible only in the file that declares them. Errors point at the call,
+//! or inside the snippet's body for calls made there, and name the path of
+//! the call, e.g. `resources.bucket.properties.tags`.
+
+use std::collections::HashMap;
+
+use serde_yaml::{Mapping, Value};
+
+use crate::ast::positions::{NodeKind, SourceNode};
+use crate::diag::Diagnostics;
+use crate::syntax::Span;
+
+/// How deep snippet calls may nest inside snippet bodies.
+pub const MAX_DEPTH: usize = 32;
+
+/// How many YAML values the snippet calls of one file may produce in all.
+pub const MAX_EXPANDED_VALUES: usize = 100_000;
+
+/// A snippet definition.
+struct Snippet<'n> {
+    /// Parameter names with their defaults, in declaration order.
+    params: Vec<(String, Option<Value>)>,
+    body: Value,
+    /// Where the body was read from.
+    body_node: Option<&'n SourceNode>,
+}
+
+/// Removes the top-level `snippets:` section of `doc` and expands every
+/// `fn::snippet` call in the rest of it. `node` is the tree `doc` was read
+/// from, which errors take their spans from.
+pub fn expand_snippets(doc: &mut Value, node: Option<&SourceNode>, diags: &mut Diagnostics) {
+    let Some(mapping) = doc.as_mapping_mut() else {
+        return;
+    };
+    let section = mapping
+        .keys()
+        .find(|k| {
+            k.as_str()
+                .is_some_and(|s| s.eq_ignore_ascii_case("snippets"))
+        })
+        .cloned();
+    let snippets = match section.and_then(|k| {
+        let section_node = node.and_then(|n| n.get(k.as_str()?));
+        Some((mapping.remove(&k)?, section_node))
+    }) {
+        Some((defs, section_node)) => parse_snippets(&defs, section_node, diags),
+        None => HashMap::new(),
+    };
+
+    let mut expander = Expander {
+        snippets: &snippets,
+        calls: Vec::new(),
+        path: Vec::new(),
+        expanded: 0,
+        exhausted: false,
+        diags,
+    };
+    for (key, value) in mapping.iter_mut() {
+        let key = key.as_str().unwrap_or("?");
+        expander.path.push(key.to_string());
+        expander.expand(value, node.and_then(|n| n.get(key)).or(node));
+        expander.path.pop();
+    }
+}
+
+/// Parses the `snippets:` section, read from `node`.
+fn parse_snippets<'n>(
+    defs: &Value,
+    node: Option<&'n SourceNode>,
+    diags: &mut Diagnostics,
+) -> HashMap<String, Snippet<'n>> {
+    let mut snippets = HashMap::new();
+    let Some(defs) = defs.as_mapping() else {
+        diags.error(
+            node.map(|n| n.span),
+            "snippets: must be a map of snippet names to definitions",
+            "Expected:\n  snippets:\n    tags:\n      params: [team]\n      body:\n        team: ${params.team}",
+        );
+        return snippets;
+    };
+    for (name, def) in defs {
+        let Some(name) = name.as_str() else {
+            continue;
+        };
+        let (name_span, def_node) = match node.and_then(|n| n.entry(name)) {
+            Some((key, value)) => (Some(key.span), Some(value)),
+            None => (node.map(|n| n.span), None),
+        };
+        let Some(def) = def.as_mapping() else {
+            diags.error(
+                name_span,
+                format!("snippet '{}' must be an object containing 'body'", name),
+                "",
+            );
+            continue;
+        };
+        let mut body = None;
+        let mut params = Vec::new();
+        for (key, value) in def {
+            let key = key.as_str().unwrap_or_default();
+            let field = def_node.and_then(|n| n.entry(key));
+            match key {
+                "body" => body = Some(value.clone()),
+                "params" => {
+                    let span = field.map(|(_, v)| v.span).or(name_span);
+                    params = parse_params(name, value, span, diags);
+                }
+                other => diags.error(
+                    field.map(|(k, _)| k.span).or(name_span),
+                    format!("snippet '{}' has unknown field '{}'", name, other),
+                    "snippets may only declare 'params' and 'body'",
+                ),
+            }
+        }
+        match body {
+            Some(body) => {
+                let body_node = def_node.and_then(|n| n.get("body"));
+                snippets.insert(
+                    name.to_string(),
+                    Snippet {
+                        params,
+                        body,
+                        body_node,
+                    },
+                );
+            }
+            None => diags.error(
+                name_span,
+                format!("snippet '{}' must be an object containing 'body'", name),
+                "",
+            ),
+        }
+    }
+    snippets
+}
+
+/// Parses a snippet's `params:`, a list of required names or a map of names
+/// to `{ default: <value> }`.
+fn parse_params(
+    snippet: &str,
+    value: &Value,
+    span: Option<Span>,
+    diags: &mut Diagnostics,
+) -> Vec<(String, Option<Value>)> {
+    match value {
+        Value::Sequence(names) => names
+            .iter()
+            .filter_map(|n| n.as_str())
+            .map(|n| (n.to_string(), None))
+            .collect(),
+        Value::Mapping(params) => params
+            .iter()
+            .filter_map(|(name, spec)| {
+                let default = spec.as_mapping().and_then(|m| m.get("default")).cloned();
+                Some((name.as_str()?.to_string(), default))
+            })
+            .collect(),
+        Value::Null => Vec::new(),
+        _ => {
+            diags.error(
+                span,
+                format!(
+                    "the params of snippet '{}' must be a list of names or a map of names to {{ default: <value> }}",
+                    snippet
+                ),
+                "",
+            );
+            Vec::new()
+        }
+    }
+}
+
+struct Expander<'a> {
+    snippets: &'a HashMap<String, Snippet<'a>>,
+    /// Snippets being expanded, outermost first.
+    calls: Vec<&'a str>,
+    /// Path of the value being expanded, for errors.
+    path: Vec<String>,
+    /// Values produced by the calls expanded so far.
+    expanded: usize,
+    /// Set once [`MAX_EXPANDED_VALUES`] is exceeded, after which calls are
+    /// left unexpanded without further errors.
+    exhausted: bool,
+    diags: &'a mut Diagnostics,
+}
+
+impl<'a> Expander<'a> {
+    /// Expands the `fn::snippet` calls in `value`. `node` is where `value`
+    /// was read from, or the nearest enclosing node that was read.
+    fn expand(&mut self, value: &mut Value, node: Option<&'a SourceNode>) {
+        match value {
+            Value::Mapping(map) => {
+                if let Some(arg) = snippet_call(map) {
+                    let arg = arg.clone();
+                    *value = self.instantiate(&arg, node).unwrap_or(Value::Null);
+                    return;
+                }
+                for (key, v) in map.iter_mut() {
+                    let key = key.as_str().unwrap_or("?");
+                    self.path.push(key.to_string());
+                    self.expand(v, node.and_then(|n| n.get(key)).or(node));
+                    self.path.pop();
+                }
+            }
+            Value::Sequence(items) => {
+                for (i, item) in items.iter_mut().enumerate() {
+                    self.path.push(format!("[{}]", i));
+                    self.expand(item, node.and_then(|n| n.item(i)).or(node));
+                    self.path.pop();
+                }
+            }
+            Value::Tagged(tagged) => self.expand(&mut tagged.value, node),
+            _ => {}
+        }
+    }
+
+    /// Returns the expansion of a call with argument `arg`, read from `node`.
+    fn instantiate(&mut self, arg: &Value, node: Option<&'a SourceNode>) -> Option<Value> {
+        if self.exhausted {
+            return None;
+        }
+        let site = self.site();
+        let span = node.map(|n| n.span);
+        let name = arg.get("name").and_then(|n| n.as_str());
+        let args = match arg.get("params") {
+            Some(Value::Mapping(args)) => Some(args.clone()),
+            None | Some(Value::Null) => Some(Mapping::new()),
+            Some(_) => None,
+        };
+        let (Some(name), Some(mut args)) = (name, args) else {
+            self.diags.error(
+                span,
+                format!(
+                    "fn::snippet at {}: the argument must be an object containing 'name' and optionally 'params'",
+                    site
+                ),
+                "",
+            );
+            return None;
+        };
+
+        let snippets = self.snippets;
+        let Some((name, snippet)) = snippets.get_key_value(name) else {
+            let mut known: Vec<&str> = snippets.keys().map(String::as_str).collect();
+            known.sort_unstable();
+            self.diags.error(
+                span,
+                format!("fn::snippet at {}: unknown snippet '{}'", site, name),
+                if known.is_empty() {
+                    "this file declares no snippets".to_string()
+                } else {
+                    format!("declared snippets: {}", known.join(", "))
+                },
+            );
+            return None;
+        };
+        if self.calls.contains(&name.as_str()) {
+            self.diags.error(
+                span,
+                format!(
+                    "fn::snippet at {}: snippet '{}' calls itself through {} -> {}",
+                    site,
+                    name,
+                    self.calls.join(" -> "),
+                    name
+                ),
+                "",
+            );
+            return None;
+        }
+        if self.calls.len() >= MAX_DEPTH {
+            self.diags.error(
+                span,
+                format!(
+                    "fn::snippet at {}: snippet calls nest more than {} deep",
+                    site, MAX_DEPTH
+                ),
+                format!("through {} -> {}", self.calls.join(" -> "), name),
+            );
+            return None;
+        }
+
+        // Arguments are expanded where the call is, before substitution
+        let params_node = node.and_then(call_arg).and_then(|n| n.get("params"));
+        self.path.push("params".to_string());
+        for (key, value) in args.iter_mut() {
+            let key = key.as_str().unwrap_or("?");
+            self.path.push(key.to_string());
+            self.expand(value, params_node.and_then(|n| n.get(key)).or(node));
+            self.path.pop();
+        }
+        self.path.pop();
+
+        let mut values = HashMap::new();
+        let mut ok = true;
+        for (key, _) in args.iter() {
+            let key = key.as_str().unwrap_or_default();
+            if !snippet.params.iter().any(|(p, _)| p == key) {
+                self.diags.error(
+                    span,
+                    format!(
+                        "fn::snippet at {}: snippet '{}' has no parameter '{}'",
+                        site, name, key
+                    ),
+                    "",
+                );
+                ok = false;
+            }
+        }
+        for (param, default) in &snippet.params {
+            match args.get(param.as_str()).or(default.as_ref()) {
+                Some(value) => {
+                    values.insert(param.as_str(), value.clone());
+                }
+                None => {
+                    self.diags.error(
+                        span,
+                        format!(
+                            "fn::snippet at {}: snippet '{}' requires parameter '{}'",
+                            site, name, param
+                        ),
+                        "",
+                    );
+                    ok = false;
+                }
+            }
+        }
+        if !ok {
+            return None;
+        }
+
+        let mut body = snippet.body.clone();
+        if let Err(e) = substitute(&mut body, &values) {
+            self.diags.error(
+                span,
+                format!("fn::snippet at {}: snippet '{}' {}", site, name, e),
+                "",
+            );
+            return None;
+        }
+        self.expanded += count_values(&body);
+        if self.expanded > MAX_EXPANDED_VALUES {
+            self.exhausted = true;
+            self.diags.error(
+                span,
+                format!(
+                    "fn::snippet at {}: snippet calls produce more than {} values",
+                    site, MAX_EXPANDED_VALUES
+                ),
+                "snippets that call other snippets several times grow exponentially; \
+                 flatten them or use variables for the repeated parts",
+            );
+            return None;
+        }
+        self.calls.push(name);
+        self.expand(&mut body, snippet.body_node.or(node));
+        self.calls.pop();
+        Some(body)
+    }
+
+    /// The path of the value being expanded.
+    fn site(&self) -> String {
+        let mut site = String::new();
+        for segment in &self.path {
+            if !site.is_empty() && !segment.starts_with('[') {
+                site.push('.');
+            }
+            site.push_str(segment);
+        }
+        site
+    }
+}
+
+/// Returns the argument node of a `fn::snippet` call node.
+fn call_arg(node: &SourceNode) -> Option<&SourceNode> {
+    match &node.kind {
+        NodeKind::Mapping(entries) => entries
+            .iter()
+            .find(|(k, _)| {
+                k.as_str()
+                    .is_some_and(|k| k.eq_ignore_ascii_case("fn::snippet"))
+            })
+            .map(|(_, v)| v),
+        _ => None,
+    }
+}
+
+/// Counts `value` and the values below it, keys included.
+fn count_values(value: &Value) -> usize {
+    1 + match value {
+        Value::Mapping(map) => map
+            .iter()
+            .map(|(k, v)| count_values(k) + count_values(v))
+            .sum(),
+        Value::Sequence(items) => items.iter().map(count_values).sum(),
+        Value::Tagged(tagged) => count_values(&tagged.value),
+        _ => 0,
+    }
+}
+
+/// Returns the argument of `map` if it is a `fn::snippet` call.
+fn snippet_call(map: &Mapping) -> Option<&Value> {
+    if map.len() != 1 {
+        return None;
+    }
+    let (key, value) = map.iter().next()?;
+    key.as_str()
+        .is_some_and(|k| k.eq_ignore_ascii_case("fn::snippet"))
+        .then_some(value)
+}
+
+/// Substitutes `${params.<name>}` placeholders in `value`.
+fn substitute(value: &mut Value, params: &HashMap<&str, Value>) -> Result<(), String> {
+    match value {
+        Value::String(s) => {
+            if let Some(name) = whole_placeholder(s) {
+                *value = params
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| format!("references undefined parameter '{}'", name))?;
+            } else if s.contains("${params.") {
+                *s = substitute_text(s, params)?;
+            }
+        }
+        Value::Mapping(map) => {
+            let entries = std::mem::take(map);
+            for (mut key, mut v) in entries {
+                substitute(&mut key, params)?;
+                substitute(&mut v, params)?;
+                map.insert(key, v);
+            }
+        }
+        Value::Sequence(items) => {
+            for item in items {
+                substitute(item, params)?;
+            }
+        }
+        Value::Tagged(tagged) => substitute(&mut tagged.value, params)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Returns the parameter name if `s` is exactly one placeholder.
+fn whole_placeholder(s: &str) -> Option<&str> {
+    let name = s.strip_prefix("${params.")?.strip_suffix('}')?;
+    (!name.is_empty() && !name.contains('}')).then_some(name)
+}
+
+/// Substitutes placeholders inside a string with the parameters' text.
+fn substitute_text(s: &str, params: &HashMap<&str, Value>) -> Result<String, String> {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+        if let Some(after) = rest.strip_prefix("$$") {
+            // Escaped: keep both dollars for the interpolation parser
+            out.push_str("$$");
+            rest = after;
+            continue;
+        }
+        let Some(after) = rest.strip_prefix("${params.") else {
+            out.push('$');
+            rest = &rest[1..];
+            continue;
+        };
+        let end = after
+            .find('}')
+            .ok_or_else(|| format!("has an unterminated placeholder in '{}'", s))?;
+        let name = &after[..end];
+        let text = match params.get(name) {
+            Some(Value::String(v)) => v.clone(),
+            Some(Value::Number(n)) => n.to_string(),
+            Some(Value::Bool(b)) => b.to_string(),
+            Some(Value::Null) => String::new(),
+            Some(_) => {
+                return Err(format!(
+                    "embeds parameter '{}' in a string, but its value is not a scalar",
+                    name
+                ))
+            }
+            None => return Err(format!("references undefined parameter '{}'", name)),
+        };
+        out.push_str(&text);
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(source: &str) -> (Value, Diagnostics) {
+        let mut doc: Value = serde_yaml::from_str(source).unwrap();
+        let mut diags = Diagnostics::new();
+        expand_snippets(&mut doc, None, &mut diags);
+        (doc, diags)
+    }
+
+    fn yaml(source: &str) -> Value {
+        serde_yaml::from_str(source).unwrap()
+    }
+
+    #[test]
+    fn test_expand_substitutes_params() {
+        let (doc, diags) = expand(
+            r#"
+snippets:
+  tags:
+    params:
+      team: {}
+      env: { default: dev }
+    body:
+      team: ${params.team}
+      owner: ${params.team}-${params.env}
+      literal: $${params.team}
+      ${params.env}Only: true
+  ports:
+    params: [ports]
+    body: ${params.ports}
+resources:
+  bucket:
+    properties:
+      tags:
+        fn::snippet: { name: tags, params: { team: web } }
+      ports:
+        fn::snippet: { name: ports, params: { ports: [80, 443] } }
+"#,
+        );
+        assert!(!diags.has_errors(), "{}", diags);
+        assert_eq!(
+            doc,
+            yaml(
+                r#"
+resources:
+  bucket:
+    properties:
+      tags:
+        team: web
+        owner: web-dev
+        literal: $${params.team}
+        devOnly: true
+      ports: [80, 443]
+"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_expand_nested_snippets() {
+        let (doc, diags) = expand(
+            r#"
+snippets:
+  name:
+    params: [app]
+    body: ${params.app}-bucket
+  bucket:
+    params: [app]
+    body:
+      type: aws:s3:Bucket
+      properties:
+        bucket:
+          fn::snippet: { name: name, params: { app: "${params.app}" } }
+resources:
+  web:
+    fn::snippet: { name: bucket, params: { app: web } }
+"#,
+        );
+        assert!(!diags.has_errors(), "{}", diags);
+        assert_eq!(
+            doc,
+            yaml("resources:\n  web:\n    type: aws:s3:Bucket\n    properties:\n      bucket: web-bucket\n")
+        );
+    }
+
+    #[test]
+    fn test_expand_errors() {
+        let (doc, diags) = expand(
+            r#"
+snippets:
+  loop:
+    body:
+      fn::snippet: { name: loop }
+  tags:
+    params: [team]
+    body: { team: "${params.team}", env: "${params.env}" }
+variables:
+  a:
+    fn::snippet: { name: tagz }
+  b:
+    - fn::snippet: { name: tags, params: { owner: x } }
+  c:
+    fn::snippet: { name: tags, params: { team: x } }
+  d:
+    fn::snippet: { name: loop }
+"#,
+        );
+        let errors = diags.to_string();
+        for expected in [
+            "fn::snippet at variables.a: unknown snippet 'tagz'",
+            "fn::snippet at variables.b[0]: snippet 'tags' has no parameter 'owner'",
+            "fn::snippet at variables.b[0]: snippet 'tags' requires parameter 'team'",
+            "fn::snippet at variables.c: snippet 'tags' references undefined parameter 'env'",
+            "fn::snippet at variables.d: snippet 'loop' calls itself through loop -> loop",
+        ] {
+            assert!(
+                errors.contains(expected),
+                "missing '{}' in:\n{}",
+                expected,
+                errors
+            );
+        }
+        assert_eq!(doc["variables"]["a"], Value::Null);
+    }
+
+    #[test]
+    fn test_expand_errors_point_at_the_call() {
+        let source =
+            "snippets:\n  tags:\n    body: {}\nvariables:\n  a:\n    fn::snippet: { name: tagz }\n";
+        let node = crate::ast::positions::index(source, crate::source::FileId(0), 0);
+        let mut doc: Value = serde_yaml::from_str(source).unwrap();
+        let mut diags = Diagnostics::new();
+        expand_snippets(&mut doc, node.as_ref(), &mut diags);
+        let diag = diags.iter().next().unwrap();
+        assert_eq!(
+            diag.summary,
+            "fn::snippet at variables.a: unknown snippet 'tagz'"
+        );
+        let span = diag.span.unwrap();
+        assert_eq!(
+            &source[span.start as usize..span.end as usize],
+            "fn::snippet: { name: tagz }\n"
+        );
+    }
+
+    #[test]
+    fn test_expand_limits_size_and_depth() {
+        // Each level calls the next twice: 2^30 leaves unbounded.
+        let mut source = String::from("snippets:\n");
+        for level in 0..30 {
+            source.push_str(&format!(
+                "  s{0}:\n    body:\n      - fn::snippet: {{ name: s{1} }}\n      - fn::snippet: {{ name: s{1} }}\n",
+                level,
+                level + 1
+            ));
+        }
+        source
+            .push_str("  s30:\n    body: leaf\nvariables:\n  a:\n    fn::snippet: { name: s0 }\n");
+        let (_, diags) = expand(&source);
+        assert_eq!(
+            diags.iter().filter(|d| d.is_error()).count(),
+            1,
+            "{}",
+            diags
+        );
+        assert!(
+            diags
+                .to_string()
+                .contains("snippet calls produce more than 100000 values"),
+            "{}",
+            diags
+        );
+
+        let mut source = String::from("snippets:\n");
+        for level in 0..40 {
+            source.push_str(&format!(
+                "  s{}:\n    body:\n      fn::snippet: {{ name: s{} }}\n",
+                level,
+                level + 1
+            ));
+        }
+        source
+            .push_str("  s40:\n    body: leaf\nvariables:\n  a:\n    fn::snippet: { name: s0 }\n");
+        let (_, diags) = expand(&source);
+        assert!(
+            diags
+                .to_string()
+                .contains("fn::snippet at variables.a: snippet calls nest more than 32 deep"),
+            "{}",
+            diags
+        );
+    }
+}
@@ -1,9 +1,11 @@
-use crate::ast::expr::Expr;
+use crate::ast::expr::{owned, Expr};
 use crate::syntax::ExprMeta;
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 
 /// A Pulumi YAML template declaration - the top-level structure of a Pulumi.yaml program.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct TemplateDecl<'src> {
     pub meta: ExprMeta,
     pub name: Option<Cow<'src, str>>,
@@ -17,23 +19,115 @@ pub struct TemplateDecl<'src> {
     pub components: Vec<ComponentDecl<'src>>,
     /// Starlark function declarations from the `starlark:` top-level block.
     pub starlark_functions: Vec<StarlarkFunctionDecl<'src>>,
+    /// Remote fragments from the `imports:` top-level block.
+    pub imports: Vec<ImportDecl<'src>>,
+    /// Path of the base template from `extends:`, relative to this file.
+    pub extends: Option<Cow<'src, str>>,
+    /// The `runtime:` setting, if given.
+    pub runtime: Option<RuntimeDecl<'src>>,
+    /// Provider plugins from the `plugins: providers:` list.
+    pub plugins: Vec<PluginDecl<'src>>,
+}
+
+/// The `runtime:` setting: `runtime: yaml` or `runtime: { name: yaml, options: ... }`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeDecl<'src> {
+    pub name: Cow<'src, str>,
+    pub options: Vec<PropertyEntry<'src>>,
+}
+
+impl RuntimeDecl<'_> {
+    /// Returns the boolean runtime option `key`, if it is set to a literal.
+    pub fn bool_option(&self, key: &str) -> Option<bool> {
+        self.options
+            .iter()
+            .find(|o| o.key == key)
+            .and_then(|o| match o.value {
+                Expr::Bool(_, b) => Some(b),
+                _ => None,
+            })
+    }
+
+    /// Returns the runtime option `key` as a list of strings, if it is set to
+    /// a literal list of literal strings.
+    pub fn string_list_option(&self, key: &str) -> Option<Vec<String>> {
+        let option = self.options.iter().find(|o| o.key == key)?;
+        let Expr::List(_, items) = &option.value else {
+            return None;
+        };
+        items
+            .iter()
+            .map(|item| match item {
+                Expr::String(_, s) => Some(s.to_string()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// A provider plugin from the project's `plugins: providers:` list.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginDecl<'src> {
+    /// The package name, e.g. `aws`.
+    pub name: Cow<'src, str>,
+    /// A local directory holding the plugin binary, relative to the project.
+    pub path: Option<Cow<'src, str>>,
+    /// The plugin version to require.
+    pub version: Option<Cow<'src, str>>,
+    /// The server to download the plugin from.
+    pub server: Option<Cow<'src, str>>,
+}
+
+/// A pinned remote template fragment (`imports: - url: ... sha256: ...`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportDecl<'src> {
+    /// `https://` URL or `oci://<registry>/<repository>:<tag>` reference.
+    pub url: Cow<'src, str>,
+    /// Expected SHA-256 of the fragment contents, lowercase hex.
+    pub sha256: Cow<'src, str>,
 }
 
 /// Pulumi settings (e.g. `pulumi: requiredVersion: ">=3.0.0"`).
-#[derive(Debug, Clone, Default, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct PulumiDecl<'src> {
     pub meta: ExprMeta,
     pub required_version: Option<Expr<'src>>,
+    /// `strictScalars: true` warns about unquoted scalars that YAML 1.1
+    /// tools read as booleans, such as `no` or `on`.
+    #[serde(default)]
+    pub strict_scalars: bool,
+    /// How repeated mapping keys are handled (`duplicateKeys: error|warn`).
+    #[serde(default)]
+    pub duplicate_keys: DuplicateKeys,
 }
 
 impl PulumiDecl<'_> {
     pub fn has_settings(&self) -> bool {
         self.required_version.is_some()
+            || self.strict_scalars
+            || self.duplicate_keys != DuplicateKeys::Error
     }
 }
 
+/// Policy for a mapping that repeats a key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DuplicateKeys {
+    /// The template fails to parse.
+    #[default]
+    Error,
+    /// Each repeated key is reported as a warning and the last value wins,
+    /// as some legacy tooling accepted.
+    Warn,
+}
+
 /// A configuration parameter entry.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ConfigEntry<'src> {
     pub meta: ExprMeta,
     pub key: Cow<'src, str>,
@@ -41,18 +135,21 @@ pub struct ConfigEntry<'src> {
 }
 
 /// A configuration parameter declaration.
-#[derive(Debug, Clone, Default, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ConfigParamDecl<'src> {
     pub type_: Option<Cow<'src, str>>,
     pub name: Option<Cow<'src, str>>,
     pub secret: Option<bool>,
+    pub description: Option<Cow<'src, str>>,
     pub default: Option<Expr<'src>>,
     pub value: Option<Expr<'src>>,
     pub items: Option<Box<ConfigParamDecl<'src>>>,
 }
 
 /// A variables map entry.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct VariableEntry<'src> {
     pub meta: ExprMeta,
     pub key: Cow<'src, str>,
@@ -60,7 +157,8 @@ pub struct VariableEntry<'src> {
 }
 
 /// A resource map entry.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ResourceEntry<'src> {
     pub meta: ExprMeta,
     pub logical_name: Cow<'src, str>,
@@ -68,18 +166,73 @@ pub struct ResourceEntry<'src> {
 }
 
 /// A resource declaration.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ResourceDecl<'src> {
     pub type_: Cow<'src, str>,
-    pub name: Option<Cow<'src, str>>,
+    /// The physical name, which defaults to the logical name. Unlike the
+    /// logical name it may be any expression, e.g. `${prefix}-bucket`.
+    pub name: Option<Expr<'src>>,
     pub default_provider: Option<bool>,
     pub properties: ResourceProperties<'src>,
     pub options: ResourceOptionsDecl<'src>,
     pub get: Option<GetResourceDecl<'src>>,
+    /// Variables from `locals:`, visible only in this resource's properties,
+    /// options, and later locals.
+    pub locals: Vec<VariableEntry<'src>>,
+    /// Set by `count:` or `range:` to register several instances of the
+    /// resource instead of one.
+    pub range: Option<ResourceRange<'src>>,
+}
+
+/// The name each instance of a resource with `count:` or `range:` sees its
+/// key and value under.
+pub const RANGE_BINDING: &str = "range";
+
+/// The instances a resource entry with `count:` or `range:` registers.
+///
+/// Each instance sees `${range.key}` and `${range.value}` in its name,
+/// locals, properties, and options, and is referenced as `${name[key]}`;
+/// `${name}` alone is the list (or object) of all instances.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ResourceRange<'src> {
+    /// `count: n` registers `n` instances, with both the key and the value
+    /// set to the index.
+    Count(Expr<'src>),
+    /// `range: <list or object>` registers one instance per element, keyed by
+    /// its index or property name.
+    Items(Expr<'src>),
+}
+
+impl<'src> ResourceRange<'src> {
+    pub fn expr(&self) -> &Expr<'src> {
+        match self {
+            ResourceRange::Count(expr) | ResourceRange::Items(expr) => expr,
+        }
+    }
+
+    pub fn expr_mut(&mut self) -> &mut Expr<'src> {
+        match self {
+            ResourceRange::Count(expr) | ResourceRange::Items(expr) => expr,
+        }
+    }
+}
+
+impl ResourceDecl<'_> {
+    /// The physical name when it is a literal, or the logical name when no
+    /// name is set. `None` for computed names, known only once evaluated.
+    pub fn static_name<'a>(&'a self, logical_name: &'a str) -> Option<&'a str> {
+        match &self.name {
+            Some(name) => name.as_str(),
+            None => Some(logical_name),
+        }
+    }
 }
 
 /// Resource properties: either an object map or a single expression.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub enum ResourceProperties<'src> {
     /// Standard properties as key-value pairs.
     Map(Vec<PropertyEntry<'src>>),
@@ -94,14 +247,16 @@ impl Default for ResourceProperties<'_> {
 }
 
 /// A property key-value pair within a resource's properties or outputs.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct PropertyEntry<'src> {
     pub key: Cow<'src, str>,
     pub value: Expr<'src>,
 }
 
 /// Resource options (dependsOn, protect, provider, etc.).
-#[derive(Debug, Clone, Default, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ResourceOptionsDecl<'src> {
     pub additional_secret_outputs: Option<Vec<Cow<'src, str>>>,
     pub aliases: Option<Expr<'src>>,
@@ -124,7 +279,8 @@ pub struct ResourceOptionsDecl<'src> {
 }
 
 /// Custom timeouts for resource operations.
-#[derive(Debug, Clone, Default, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct CustomTimeoutsDecl<'src> {
     pub create: Option<Cow<'src, str>>,
     pub update: Option<Cow<'src, str>>,
@@ -132,28 +288,32 @@ pub struct CustomTimeoutsDecl<'src> {
 }
 
 /// Get-resource declaration (for importing existing resources).
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct GetResourceDecl<'src> {
     pub id: Expr<'src>,
     pub state: Vec<PropertyEntry<'src>>,
 }
 
 /// An output entry.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct OutputEntry<'src> {
     pub key: Cow<'src, str>,
     pub value: Expr<'src>,
 }
 
 /// A component declaration.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ComponentDecl<'src> {
     pub key: Cow<'src, str>,
     pub component: ComponentParamDecl<'src>,
 }
 
 /// A component parameter declaration.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ComponentParamDecl<'src> {
     pub name: Option<Cow<'src, str>>,
     pub description: Option<Cow<'src, str>>,
@@ -165,7 +325,8 @@ pub struct ComponentParamDecl<'src> {
 }
 
 /// A Starlark function declaration from the `starlark:` top-level block.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct StarlarkFunctionDecl<'src> {
     /// The function name (e.g. "uppercase").
     pub name: Cow<'src, str>,
@@ -188,6 +349,10 @@ impl TemplateDecl<'_> {
             outputs: Vec::new(),
             components: Vec::new(),
             starlark_functions: Vec::new(),
+            imports: Vec::new(),
+            extends: None,
+            runtime: None,
+            plugins: Vec::new(),
         }
     }
 }
@@ -198,6 +363,171 @@ impl Default for TemplateDecl<'_> {
     }
 }
 
+impl TemplateDecl<'_> {
+    /// Converts into an owned `'static` template that no longer borrows from
+    /// the source, so long-lived embedders can keep it after the source is
+    /// dropped.
+    pub fn into_owned(self) -> TemplateDecl<'static> {
+        TemplateDecl {
+            meta: self.meta,
+            name: self.name.map(owned),
+            namespace: self.namespace.map(owned),
+            description: self.description.map(owned),
+            pulumi: pulumi_into_owned(self.pulumi),
+            config: owned_vec(self.config, config_into_owned),
+            variables: owned_vec(self.variables, variable_into_owned),
+            resources: owned_vec(self.resources, resource_entry_into_owned),
+            outputs: owned_vec(self.outputs, |o| OutputEntry {
+                key: owned(o.key),
+                value: o.value.into_owned(),
+            }),
+            components: owned_vec(self.components, |c| ComponentDecl {
+                key: owned(c.key),
+                component: ComponentParamDecl {
+                    name: c.component.name.map(owned),
+                    description: c.component.description.map(owned),
+                    pulumi: pulumi_into_owned(c.component.pulumi),
+                    inputs: owned_vec(c.component.inputs, config_into_owned),
+                    variables: owned_vec(c.component.variables, variable_into_owned),
+                    resources: owned_vec(c.component.resources, resource_entry_into_owned),
+                    outputs: owned_vec(c.component.outputs, |o| OutputEntry {
+                        key: owned(o.key),
+                        value: o.value.into_owned(),
+                    }),
+                },
+            }),
+            starlark_functions: owned_vec(self.starlark_functions, |f| StarlarkFunctionDecl {
+                name: owned(f.name),
+                script: owned(f.script),
+            }),
+            imports: owned_vec(self.imports, |i| ImportDecl {
+                url: owned(i.url),
+                sha256: owned(i.sha256),
+            }),
+            extends: self.extends.map(owned),
+            runtime: self.runtime.map(|r| RuntimeDecl {
+                name: owned(r.name),
+                options: owned_vec(r.options, property_into_owned),
+            }),
+            plugins: owned_vec(self.plugins, |p| PluginDecl {
+                name: owned(p.name),
+                path: p.path.map(owned),
+                version: p.version.map(owned),
+                server: p.server.map(owned),
+            }),
+        }
+    }
+}
+
+fn owned_vec<T, U>(items: Vec<T>, f: impl FnMut(T) -> U) -> Vec<U> {
+    items.into_iter().map(f).collect()
+}
+
+fn owned_strs(items: Option<Vec<Cow<'_, str>>>) -> Option<Vec<Cow<'static, str>>> {
+    items.map(|items| owned_vec(items, owned))
+}
+
+fn pulumi_into_owned(pulumi: PulumiDecl<'_>) -> PulumiDecl<'static> {
+    PulumiDecl {
+        meta: pulumi.meta,
+        required_version: pulumi.required_version.map(Expr::into_owned),
+        strict_scalars: pulumi.strict_scalars,
+        duplicate_keys: pulumi.duplicate_keys,
+    }
+}
+
+fn config_into_owned(entry: ConfigEntry<'_>) -> ConfigEntry<'static> {
+    ConfigEntry {
+        meta: entry.meta,
+        key: owned(entry.key),
+        param: config_param_into_owned(entry.param),
+    }
+}
+
+fn config_param_into_owned(param: ConfigParamDecl<'_>) -> ConfigParamDecl<'static> {
+    ConfigParamDecl {
+        type_: param.type_.map(owned),
+        name: param.name.map(owned),
+        secret: param.secret,
+        description: param.description.map(owned),
+        default: param.default.map(Expr::into_owned),
+        value: param.value.map(Expr::into_owned),
+        items: param
+            .items
+            .map(|items| Box::new(config_param_into_owned(*items))),
+    }
+}
+
+fn variable_into_owned(entry: VariableEntry<'_>) -> VariableEntry<'static> {
+    VariableEntry {
+        meta: entry.meta,
+        key: owned(entry.key),
+        value: entry.value.into_owned(),
+    }
+}
+
+fn property_into_owned(entry: PropertyEntry<'_>) -> PropertyEntry<'static> {
+    PropertyEntry {
+        key: owned(entry.key),
+        value: entry.value.into_owned(),
+    }
+}
+
+fn resource_entry_into_owned(entry: ResourceEntry<'_>) -> ResourceEntry<'static> {
+    let resource = entry.resource;
+    let opts = resource.options;
+    ResourceEntry {
+        meta: entry.meta,
+        logical_name: owned(entry.logical_name),
+        resource: ResourceDecl {
+            type_: owned(resource.type_),
+            name: resource.name.map(Expr::into_owned),
+            default_provider: resource.default_provider,
+            properties: match resource.properties {
+                ResourceProperties::Map(props) => {
+                    ResourceProperties::Map(owned_vec(props, property_into_owned))
+                }
+                ResourceProperties::Expr(expr) => {
+                    ResourceProperties::Expr(Box::new(expr.into_owned()))
+                }
+            },
+            options: ResourceOptionsDecl {
+                additional_secret_outputs: owned_strs(opts.additional_secret_outputs),
+                aliases: opts.aliases.map(Expr::into_owned),
+                custom_timeouts: opts.custom_timeouts.map(|t| CustomTimeoutsDecl {
+                    create: t.create.map(owned),
+                    update: t.update.map(owned),
+                    delete: t.delete.map(owned),
+                }),
+                delete_before_replace: opts.delete_before_replace,
+                depends_on: opts.depends_on.map(Expr::into_owned),
+                ignore_changes: owned_strs(opts.ignore_changes),
+                import: opts.import.map(owned),
+                parent: opts.parent.map(Expr::into_owned),
+                protect: opts.protect.map(Expr::into_owned),
+                provider: opts.provider.map(Expr::into_owned),
+                providers: opts.providers.map(Expr::into_owned),
+                version: opts.version.map(owned),
+                plugin_download_url: opts.plugin_download_url.map(owned),
+                replace_on_changes: owned_strs(opts.replace_on_changes),
+                retain_on_delete: opts.retain_on_delete,
+                replace_with: opts.replace_with.map(Expr::into_owned),
+                deleted_with: opts.deleted_with.map(Expr::into_owned),
+                hide_diffs: owned_strs(opts.hide_diffs),
+            },
+            get: resource.get.map(|get| GetResourceDecl {
+                id: get.id.into_owned(),
+                state: owned_vec(get.state, property_into_owned),
+            }),
+            locals: owned_vec(resource.locals, variable_into_owned),
+            range: resource.range.map(|range| match range {
+                ResourceRange::Count(expr) => ResourceRange::Count(expr.into_owned()),
+                ResourceRange::Items(expr) => ResourceRange::Items(expr.into_owned()),
+            }),
+        },
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -220,6 +550,8 @@ mod tests {
         let pd = PulumiDecl {
             meta: ExprMeta::no_span(),
             required_version: Some(Expr::String(ExprMeta::no_span(), Cow::Borrowed(">=3.0.0"))),
+            strict_scalars: false,
+            duplicate_keys: DuplicateKeys::Error,
         };
         assert!(pd.has_settings());
     }
@@ -240,4 +572,22 @@ mod tests {
         assert!(opts.protect.is_none());
         assert!(opts.provider.is_none());
     }
+
+    #[test]
+    fn test_into_owned_outlives_source() {
+        let owned: TemplateDecl<'static> = {
+            let source = String::from("app bucket");
+            let mut template = TemplateDecl::new();
+            template.name = Some(Cow::Borrowed(&source[..3]));
+            template.variables.push(VariableEntry {
+                meta: ExprMeta::no_span(),
+                key: Cow::Borrowed(&source[4..]),
+                value: Expr::String(ExprMeta::no_span(), Cow::Borrowed(&source[..3])),
+            });
+            template.into_owned()
+        };
+        assert_eq!(owned.name.as_deref(), Some("app"));
+        assert_eq!(owned.variables[0].key, "bucket");
+        assert_eq!(owned.variables[0].value.as_str(), Some("app"));
+    }
 }
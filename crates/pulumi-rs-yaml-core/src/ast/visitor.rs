@@ -1,85 +1,249 @@
-//! Zero-cost expression visitor. GAT controls accumulator type.
+//! Expression traversal.
 //!
-//! Three visitors unify ~300 lines of duplicated recursive walkers
-//! into a single `walk_expr` function + zero-sized-type visitors.
-
-use crate::ast::expr::{Expr, InvokeExpr};
-use crate::ast::template::{ResourceDecl, ResourceProperties};
-
-/// Expression visitor trait. Each impl is a zero-sized type that
-/// monomorphizes to the same assembly as a hand-written match.
-pub trait ExprVisitor {
-    /// Accumulator type — GAT so each visitor controls its own.
-    type Acc<'a>;
-
-    /// Called for each `${symbol}` reference.
-    fn visit_symbol<'a>(&self, root: &'a str, acc: &mut Self::Acc<'a>);
+//! [`for_each_child`] and [`for_each_child_mut`] are the only exhaustive
+//! matches over the children of every `Expr` variant; everything that
+//! recurses into expressions goes through them, so a new variant only needs
+//! to be added there.
+//!
+//! On top of them:
+//! - [`ExprVisitor`] reads an expression tree. Each impl is a zero-sized
+//!   type whose GAT controls the accumulator, and monomorphizes to the same
+//!   code as a hand-written match. Used for dependency collection, reference
+//!   validation, and package scanning.
+//! - [`ExprRewriter`] rewrites an expression tree in place, bottom-up. Used
+//!   for constant folding and [renaming references](RenameRefs).
+//!
+//! Both track which names are local — resource locals, the `range` of a
+//! resource with `count:` or `range:`, and the loop bindings inside
+//! `fn::map`/`fn::filter`/`fn::foreach` bodies — so references to
+//! them are not mistaken for references to top-level entries.
 
-    /// Called for each `${ref}` inside an interpolated string.
-    fn visit_interpolation_ref<'a>(&self, root: &'a str, acc: &mut Self::Acc<'a>);
+use std::borrow::Cow;
 
-    /// Called for each `fn::invoke` expression. Default: no-op.
-    fn visit_invoke<'a>(&self, _invoke: &'a InvokeExpr<'a>, _acc: &mut Self::Acc<'a>) {}
-}
+use crate::ast::expr::{Expr, InvokeExpr, LOOP_BINDINGS};
+use crate::ast::property::{PropertyAccess, PropertyAccessor};
+use crate::ast::template::{ResourceDecl, ResourceProperties, RANGE_BINDING};
 
-/// Walk an expression tree, calling visitor methods at each leaf node.
-pub fn walk_expr<'a, V: ExprVisitor>(expr: &'a Expr<'a>, visitor: &V, acc: &mut V::Acc<'a>) {
+/// Calls `f` on each direct child of `expr`, in source order.
+pub fn for_each_child<'a, 'src>(expr: &'a Expr<'src>, mut f: impl FnMut(&'a Expr<'src>)) {
     match expr {
-        Expr::Symbol(_, access) => {
-            if let Ok(root) = access.root_name() {
-                visitor.visit_symbol(root, acc);
+        Expr::Invoke(_, invoke) => {
+            let opts = &invoke.call_opts;
+            for child in [
+                &invoke.call_args,
+                &opts.parent,
+                &opts.provider,
+                &opts.depends_on,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                f(child);
             }
         }
-        Expr::Interpolate(_, parts) => {
-            for part in parts {
-                if let Some(ref access) = part.value {
-                    if let Ok(root) = access.root_name() {
-                        visitor.visit_interpolation_ref(root, acc);
-                    }
-                }
+        Expr::List(_, items) => items.iter().for_each(f),
+        Expr::Object(_, entries) => {
+            for entry in entries {
+                f(&entry.key);
+                f(&entry.value);
             }
         }
-        Expr::Invoke(_, invoke) => {
-            visitor.visit_invoke(invoke, acc);
-            if let Some(ref args) = invoke.call_args {
-                walk_expr(args, visitor, acc);
-            }
-            if let Some(ref parent) = invoke.call_opts.parent {
-                walk_expr(parent, visitor, acc);
+        Expr::Join(_, a, b)
+        | Expr::Select(_, a, b)
+        | Expr::Split(_, a, b)
+        | Expr::StartsWith(_, a, b)
+        | Expr::Equals(_, a, b)
+        | Expr::LessThan(_, a, b)
+        | Expr::LessThanOrEqual(_, a, b)
+        | Expr::GreaterThan(_, a, b)
+        | Expr::GreaterThanOrEqual(_, a, b)
+        | Expr::Format(_, a, b)
+        | Expr::CidrHost(_, a, b)
+        | Expr::ValidateJson(_, a, b)
+        | Expr::Assert(_, a, b)
+        | Expr::TemplateFile(_, a, b)
+        | Expr::JsonPatch(_, a, b)
+        | Expr::Map(_, a, b)
+        | Expr::Filter(_, a, b) => {
+            f(a);
+            f(b);
+        }
+        Expr::Lookup(_, a, b, c) => {
+            f(a);
+            f(b);
+            if let Some(c) = c {
+                f(c);
             }
-            if let Some(ref provider) = invoke.call_opts.provider {
-                walk_expr(provider, visitor, acc);
+        }
+        Expr::Env(_, a, b) => {
+            f(a);
+            if let Some(b) = b {
+                f(b);
             }
-            if let Some(ref depends_on) = invoke.call_opts.depends_on {
-                walk_expr(depends_on, visitor, acc);
+        }
+        Expr::Substring(_, a, b, c)
+        | Expr::Replace(_, a, b, c)
+        | Expr::CidrSubnet(_, a, b, c)
+        | Expr::If(_, a, b, c) => {
+            f(a);
+            f(b);
+            f(c);
+        }
+        Expr::ToJson(_, inner)
+        | Expr::ParseJson(_, inner)
+        | Expr::ParseYaml(_, inner)
+        | Expr::ToBase64(_, inner)
+        | Expr::FromBase64(_, inner)
+        | Expr::Secret(_, inner)
+        | Expr::Encrypted(_, inner)
+        | Expr::ReadFile(_, inner)
+        | Expr::ReadFileBinary(_, inner)
+        | Expr::Merge(_, inner)
+        | Expr::Abs(_, inner)
+        | Expr::Floor(_, inner)
+        | Expr::Ceil(_, inner)
+        | Expr::Max(_, inner)
+        | Expr::Min(_, inner)
+        | Expr::FormatDuration(_, inner)
+        | Expr::FormatBytes(_, inner)
+        | Expr::StringLen(_, inner)
+        | Expr::Sort(_, inner)
+        | Expr::Unique(_, inner)
+        | Expr::Length(_, inner)
+        | Expr::Coalesce(_, inner)
+        | Expr::Not(_, inner)
+        | Expr::And(_, inner)
+        | Expr::Or(_, inner)
+        | Expr::ToUpper(_, inner)
+        | Expr::ToLower(_, inner)
+        | Expr::Trim(_, inner)
+        | Expr::CidrNetmask(_, inner)
+        | Expr::TimeUtc(_, inner)
+        | Expr::TimeUnix(_, inner)
+        | Expr::Uuid(_, inner)
+        | Expr::RandomString(_, inner)
+        | Expr::DateFormat(_, inner)
+        | Expr::StringAsset(_, inner)
+        | Expr::FileAsset(_, inner)
+        | Expr::RemoteAsset(_, inner)
+        | Expr::FileArchive(_, inner)
+        | Expr::RemoteArchive(_, inner)
+        | Expr::Open(_, _, inner) => f(inner),
+        Expr::AssetArchive(_, entries) => {
+            for (_, v) in entries {
+                f(v);
             }
         }
-        Expr::List(_, elements) => {
-            for elem in elements {
-                walk_expr(elem, visitor, acc);
+        Expr::Foreach(_, foreach) => {
+            f(&foreach.list);
+            f(&foreach.body);
+        }
+        Expr::Starlark(_, call) => f(&call.input),
+        // Terminals
+        Expr::Null(_)
+        | Expr::Bool(..)
+        | Expr::Number(..)
+        | Expr::String(..)
+        | Expr::Interpolate(..)
+        | Expr::Symbol(..) => {}
+    }
+}
+
+/// Calls `f` on each direct child of `expr`, in source order, allowing it to
+/// be replaced.
+pub fn for_each_child_mut<'src>(expr: &mut Expr<'src>, mut f: impl FnMut(&mut Expr<'src>)) {
+    match expr {
+        Expr::Invoke(_, invoke) => {
+            let opts = &mut invoke.call_opts;
+            for child in [
+                &mut invoke.call_args,
+                &mut opts.parent,
+                &mut opts.provider,
+                &mut opts.depends_on,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                f(child);
             }
         }
+        Expr::List(_, items) => items.iter_mut().for_each(f),
         Expr::Object(_, entries) => {
             for entry in entries {
-                walk_expr(&entry.key, visitor, acc);
-                walk_expr(&entry.value, visitor, acc);
+                f(&mut entry.key);
+                f(&mut entry.value);
+            }
+        }
+        Expr::Join(_, a, b)
+        | Expr::Select(_, a, b)
+        | Expr::Split(_, a, b)
+        | Expr::StartsWith(_, a, b)
+        | Expr::Equals(_, a, b)
+        | Expr::LessThan(_, a, b)
+        | Expr::LessThanOrEqual(_, a, b)
+        | Expr::GreaterThan(_, a, b)
+        | Expr::GreaterThanOrEqual(_, a, b)
+        | Expr::Format(_, a, b)
+        | Expr::CidrHost(_, a, b)
+        | Expr::ValidateJson(_, a, b)
+        | Expr::Assert(_, a, b)
+        | Expr::TemplateFile(_, a, b)
+        | Expr::JsonPatch(_, a, b)
+        | Expr::Map(_, a, b)
+        | Expr::Filter(_, a, b) => {
+            f(a);
+            f(b);
+        }
+        Expr::Lookup(_, a, b, c) => {
+            f(a);
+            f(b);
+            if let Some(c) = c {
+                f(c);
             }
         }
-        Expr::Join(_, a, b) | Expr::Select(_, a, b) | Expr::Split(_, a, b) => {
-            walk_expr(a, visitor, acc);
-            walk_expr(b, visitor, acc);
+        Expr::Env(_, a, b) => {
+            f(a);
+            if let Some(b) = b {
+                f(b);
+            }
+        }
+        Expr::Substring(_, a, b, c)
+        | Expr::Replace(_, a, b, c)
+        | Expr::CidrSubnet(_, a, b, c)
+        | Expr::If(_, a, b, c) => {
+            f(a);
+            f(b);
+            f(c);
         }
         Expr::ToJson(_, inner)
+        | Expr::ParseJson(_, inner)
+        | Expr::ParseYaml(_, inner)
         | Expr::ToBase64(_, inner)
         | Expr::FromBase64(_, inner)
         | Expr::Secret(_, inner)
+        | Expr::Encrypted(_, inner)
         | Expr::ReadFile(_, inner)
+        | Expr::ReadFileBinary(_, inner)
+        | Expr::Merge(_, inner)
         | Expr::Abs(_, inner)
         | Expr::Floor(_, inner)
         | Expr::Ceil(_, inner)
         | Expr::Max(_, inner)
         | Expr::Min(_, inner)
+        | Expr::FormatDuration(_, inner)
+        | Expr::FormatBytes(_, inner)
         | Expr::StringLen(_, inner)
+        | Expr::Sort(_, inner)
+        | Expr::Unique(_, inner)
+        | Expr::Length(_, inner)
+        | Expr::Coalesce(_, inner)
+        | Expr::Not(_, inner)
+        | Expr::And(_, inner)
+        | Expr::Or(_, inner)
+        | Expr::ToUpper(_, inner)
+        | Expr::ToLower(_, inner)
+        | Expr::Trim(_, inner)
+        | Expr::CidrNetmask(_, inner)
         | Expr::TimeUtc(_, inner)
         | Expr::TimeUnix(_, inner)
         | Expr::Uuid(_, inner)
@@ -89,73 +253,260 @@ pub fn walk_expr<'a, V: ExprVisitor>(expr: &'a Expr<'a>, visitor: &V, acc: &mut
         | Expr::FileAsset(_, inner)
         | Expr::RemoteAsset(_, inner)
         | Expr::FileArchive(_, inner)
-        | Expr::RemoteArchive(_, inner) => {
-            walk_expr(inner, visitor, acc);
-        }
-        Expr::Substring(_, a, b, c) => {
-            walk_expr(a, visitor, acc);
-            walk_expr(b, visitor, acc);
-            walk_expr(c, visitor, acc);
-        }
+        | Expr::RemoteArchive(_, inner)
+        | Expr::Open(_, _, inner) => f(inner),
         Expr::AssetArchive(_, entries) => {
             for (_, v) in entries {
-                walk_expr(v, visitor, acc);
+                f(v);
             }
         }
-        Expr::Starlark(_, call) => {
-            walk_expr(&call.input, visitor, acc);
+        Expr::Foreach(_, foreach) => {
+            f(&mut foreach.list);
+            f(&mut foreach.body);
         }
+        Expr::Starlark(_, call) => f(&mut call.input),
         // Terminals
-        Expr::Null(_) | Expr::Bool(_, _) | Expr::Number(_, _) | Expr::String(_, _) => {}
+        Expr::Null(_)
+        | Expr::Bool(..)
+        | Expr::Number(..)
+        | Expr::String(..)
+        | Expr::Interpolate(..)
+        | Expr::Symbol(..) => {}
+    }
+}
+
+/// Returns `scope` extended with the names a loop binds in its body.
+fn loop_scope<'s>(scope: &[&'s str], bindings: impl IntoIterator<Item = &'s str>) -> Vec<&'s str> {
+    scope.iter().copied().chain(bindings).collect()
+}
+
+/// Expression visitor trait. Each impl is a zero-sized type that
+/// monomorphizes to the same assembly as a hand-written match.
+pub trait ExprVisitor {
+    /// Accumulator type — GAT so each visitor controls its own.
+    type Acc<'a>;
+
+    /// Called for each `${symbol}` reference.
+    fn visit_symbol<'a>(&self, root: &'a str, acc: &mut Self::Acc<'a>);
+
+    /// Called for each `${ref}` inside an interpolated string.
+    fn visit_interpolation_ref<'a>(&self, root: &'a str, acc: &mut Self::Acc<'a>);
+
+    /// Called for each `fn::invoke` expression. Default: no-op.
+    fn visit_invoke<'a>(&self, _invoke: &'a InvokeExpr<'a>, _acc: &mut Self::Acc<'a>) {}
+}
+
+/// Walk an expression tree, calling visitor methods at each leaf node.
+pub fn walk_expr<'a, V: ExprVisitor>(expr: &'a Expr<'a>, visitor: &V, acc: &mut V::Acc<'a>) {
+    walk(expr, visitor, acc, &[]);
+}
+
+/// Walks `expr`. References to `scope` names are local and are not reported.
+fn walk<'a, V: ExprVisitor>(expr: &'a Expr<'a>, visitor: &V, acc: &mut V::Acc<'a>, scope: &[&str]) {
+    let bound = |root: &str| scope.contains(&root);
+    match expr {
+        Expr::Symbol(_, access) => {
+            for root in reference_roots(access) {
+                if !bound(root) {
+                    visitor.visit_symbol(root, acc);
+                }
+            }
+        }
+        Expr::Interpolate(_, parts) => {
+            for access in parts.iter().filter_map(|p| p.value.as_ref()) {
+                for root in reference_roots(access) {
+                    if !bound(root) {
+                        visitor.visit_interpolation_ref(root, acc);
+                    }
+                }
+            }
+        }
+        Expr::Map(_, list, body) | Expr::Filter(_, list, body) => {
+            walk(list, visitor, acc, scope);
+            walk(body, visitor, acc, &loop_scope(scope, LOOP_BINDINGS));
+        }
+        Expr::Foreach(_, foreach) => {
+            walk(&foreach.list, visitor, acc, scope);
+            walk(
+                &foreach.body,
+                visitor,
+                acc,
+                &loop_scope(scope, foreach.bindings()),
+            );
+        }
+        _ => {
+            if let Expr::Invoke(_, invoke) = expr {
+                visitor.visit_invoke(invoke, acc);
+            }
+            for_each_child(expr, |child| walk(child, visitor, acc, scope));
+        }
     }
 }
 
+/// The root names an access refers to: its own root, then the roots of any
+/// `[a.b]` subscripts.
+fn reference_roots<'a>(access: &'a PropertyAccess<'a>) -> impl Iterator<Item = &'a str> {
+    access
+        .root_name()
+        .ok()
+        .into_iter()
+        .chain(access.nested_root_names())
+}
+
 /// Walk all expressions in a resource declaration.
 pub fn walk_resource<'a, V: ExprVisitor>(
     resource: &'a ResourceDecl<'a>,
     visitor: &V,
     acc: &mut V::Acc<'a>,
 ) {
+    // The range is evaluated once, outside the instances; everything else
+    // sees its `range` binding. Each local sees the locals declared before
+    // it; everything else sees all.
+    let ranged = usize::from(resource.range.is_some());
+    if let Some(ref range) = resource.range {
+        walk(range.expr(), visitor, acc, &[]);
+    }
+    let locals: Vec<&str> = resource
+        .range
+        .iter()
+        .map(|_| RANGE_BINDING)
+        .chain(resource.locals.iter().map(|l| l.key.as_ref()))
+        .collect();
+    for (i, local) in resource.locals.iter().enumerate() {
+        walk(&local.value, visitor, acc, &locals[..ranged + i]);
+    }
+
+    if let Some(ref name) = resource.name {
+        walk(name, visitor, acc, &locals);
+    }
+
     match &resource.properties {
         ResourceProperties::Map(props) => {
             for prop in props {
-                walk_expr(&prop.value, visitor, acc);
+                walk(&prop.value, visitor, acc, &locals);
             }
         }
         ResourceProperties::Expr(expr) => {
-            walk_expr(expr, visitor, acc);
+            walk(expr, visitor, acc, &locals);
         }
     }
 
     let opts = &resource.options;
     if let Some(ref expr) = opts.depends_on {
-        walk_expr(expr, visitor, acc);
+        walk(expr, visitor, acc, &locals);
     }
     if let Some(ref expr) = opts.parent {
-        walk_expr(expr, visitor, acc);
+        walk(expr, visitor, acc, &locals);
     }
     if let Some(ref expr) = opts.provider {
-        walk_expr(expr, visitor, acc);
+        walk(expr, visitor, acc, &locals);
     }
     if let Some(ref expr) = opts.providers {
-        walk_expr(expr, visitor, acc);
+        walk(expr, visitor, acc, &locals);
     }
     if let Some(ref expr) = opts.protect {
-        walk_expr(expr, visitor, acc);
+        walk(expr, visitor, acc, &locals);
     }
     if let Some(ref expr) = opts.aliases {
-        walk_expr(expr, visitor, acc);
+        walk(expr, visitor, acc, &locals);
     }
     if let Some(ref expr) = opts.replace_with {
-        walk_expr(expr, visitor, acc);
+        walk(expr, visitor, acc, &locals);
     }
     if let Some(ref expr) = opts.deleted_with {
-        walk_expr(expr, visitor, acc);
+        walk(expr, visitor, acc, &locals);
     }
     if let Some(ref get) = resource.get {
-        walk_expr(&get.id, visitor, acc);
+        walk(&get.id, visitor, acc, &locals);
         for prop in &get.state {
-            walk_expr(&prop.value, visitor, acc);
+            walk(&prop.value, visitor, acc, &locals);
+        }
+    }
+}
+
+/// Expression rewriter. Called bottom-up, so each expression is rewritten
+/// after its children.
+pub trait ExprRewriter {
+    /// Rewrites `expr` in place. `scope` holds the names that are local at
+    /// this point.
+    fn rewrite(&mut self, expr: &mut Expr<'_>, scope: &[&str]);
+}
+
+/// Rewrites an expression tree, children first.
+pub fn rewrite_expr<R: ExprRewriter>(expr: &mut Expr<'_>, rewriter: &mut R) {
+    rewrite(expr, rewriter, &[]);
+}
+
+fn rewrite<R: ExprRewriter>(expr: &mut Expr<'_>, rewriter: &mut R, scope: &[&str]) {
+    match expr {
+        Expr::Map(_, list, body) | Expr::Filter(_, list, body) => {
+            rewrite(list, rewriter, scope);
+            rewrite(body, rewriter, &loop_scope(scope, LOOP_BINDINGS));
+        }
+        Expr::Foreach(_, foreach) => {
+            rewrite(&mut foreach.list, rewriter, scope);
+            let bindings: Vec<String> = foreach.bindings().map(str::to_string).collect();
+            let scope = loop_scope(scope, bindings.iter().map(String::as_str));
+            rewrite(&mut foreach.body, rewriter, &scope);
+        }
+        _ => for_each_child_mut(expr, |child| rewrite(child, rewriter, scope)),
+    }
+    rewriter.rewrite(expr, scope);
+}
+
+/// Rewrites all expressions in a resource declaration, with the same scoping
+/// as [`walk_resource`].
+pub fn rewrite_resource<R: ExprRewriter>(resource: &mut ResourceDecl<'_>, rewriter: &mut R) {
+    let ranged = usize::from(resource.range.is_some());
+    if let Some(ref mut range) = resource.range {
+        rewrite(range.expr_mut(), rewriter, &[]);
+    }
+    let keys: Vec<String> = resource.locals.iter().map(|l| l.key.to_string()).collect();
+    let locals: Vec<&str> = resource
+        .range
+        .iter()
+        .map(|_| RANGE_BINDING)
+        .chain(keys.iter().map(String::as_str))
+        .collect();
+    for (i, local) in resource.locals.iter_mut().enumerate() {
+        rewrite(&mut local.value, rewriter, &locals[..ranged + i]);
+    }
+
+    if let Some(ref mut name) = resource.name {
+        rewrite(name, rewriter, &locals);
+    }
+
+    match &mut resource.properties {
+        ResourceProperties::Map(props) => {
+            for prop in props {
+                rewrite(&mut prop.value, rewriter, &locals);
+            }
+        }
+        ResourceProperties::Expr(expr) => {
+            rewrite(expr, rewriter, &locals);
+        }
+    }
+
+    let opts = &mut resource.options;
+    for expr in [
+        &mut opts.depends_on,
+        &mut opts.parent,
+        &mut opts.provider,
+        &mut opts.providers,
+        &mut opts.protect,
+        &mut opts.aliases,
+        &mut opts.replace_with,
+        &mut opts.deleted_with,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        rewrite(expr, rewriter, &locals);
+    }
+    if let Some(ref mut get) = resource.get {
+        rewrite(&mut get.id, rewriter, &locals);
+        for prop in &mut get.state {
+            rewrite(&mut prop.value, rewriter, &locals);
         }
     }
 }
@@ -218,9 +569,175 @@ impl ExprVisitor for InvokePackageCollector {
     }
 }
 
+/// Collects every `fn::invoke` expression, including invokes nested in the
+/// arguments of another.
+pub struct InvokeCollector;
+
+impl ExprVisitor for InvokeCollector {
+    type Acc<'a> = Vec<&'a InvokeExpr<'a>>;
+
+    fn visit_symbol<'a>(&self, _root: &'a str, _acc: &mut Self::Acc<'a>) {}
+    fn visit_interpolation_ref<'a>(&self, _root: &'a str, _acc: &mut Self::Acc<'a>) {}
+
+    fn visit_invoke<'a>(&self, invoke: &'a InvokeExpr<'a>, acc: &mut Self::Acc<'a>) {
+        acc.push(invoke);
+    }
+}
+
 /// Info about an invoke expression collected by InvokePackageCollector.
 pub struct InvokeInfo<'a> {
     pub token: &'a str,
     pub version: Option<&'a str>,
     pub plugin_download_url: Option<&'a str>,
 }
+
+// ---------- Concrete rewriters ----------
+
+/// Renames references to the entry `from` to `to`, e.g. after renaming a
+/// resource, leaving the rest of each access and local names untouched.
+pub struct RenameRefs<'n> {
+    pub from: &'n str,
+    pub to: &'n str,
+    /// Number of references renamed.
+    pub renamed: usize,
+}
+
+impl<'n> RenameRefs<'n> {
+    pub fn new(from: &'n str, to: &'n str) -> Self {
+        RenameRefs {
+            from,
+            to,
+            renamed: 0,
+        }
+    }
+
+    fn rename(&mut self, access: &mut PropertyAccess<'_>) {
+        for accessor in &mut access.accessors {
+            if let PropertyAccessor::PropertySubscript(inner) = accessor {
+                self.rename(inner);
+            }
+        }
+        if let Some(PropertyAccessor::Name(root) | PropertyAccessor::StringSubscript(root)) =
+            access.accessors.first_mut()
+        {
+            if root == self.from {
+                *root = Cow::Owned(self.to.to_string());
+                self.renamed += 1;
+            }
+        }
+    }
+}
+
+impl ExprRewriter for RenameRefs<'_> {
+    fn rewrite(&mut self, expr: &mut Expr<'_>, scope: &[&str]) {
+        if scope.contains(&self.from) {
+            return;
+        }
+        match expr {
+            Expr::Symbol(_, access) => self.rename(access),
+            Expr::Interpolate(_, parts) => {
+                for access in parts.iter_mut().filter_map(|p| p.value.as_mut()) {
+                    self.rename(access);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::parse::parse_template;
+
+    fn variable<'a>(
+        template: &'a crate::ast::template::TemplateDecl<'a>,
+        key: &str,
+    ) -> &'a Expr<'a> {
+        &template
+            .variables
+            .iter()
+            .find(|v| v.key == key)
+            .unwrap()
+            .value
+    }
+
+    #[test]
+    fn test_walk_skips_loop_bindings() {
+        let source =
+            "name: test\nvariables:\n  a:\n    fn::map: ['${list}', '${item}-${prefix}']\n";
+        let (template, diags) = parse_template(source, None);
+        assert!(!diags.has_errors(), "{}", diags);
+        let mut refs = HashSet::new();
+        walk_expr(variable(&template, "a"), &AllRefsCollector, &mut refs);
+        assert_eq!(refs, HashSet::from(["list", "prefix"]));
+    }
+
+    #[test]
+    fn test_walk_skips_foreach_bindings() {
+        let source = r#"name: test
+variables:
+  a:
+    fn::foreach:
+      list: ${zones}
+      as: zone
+      body: ${zone}-${item}-${zones[0]}
+"#;
+        let (template, diags) = parse_template(source, None);
+        assert!(!diags.has_errors(), "{}", diags);
+        let mut refs = HashSet::new();
+        walk_expr(variable(&template, "a"), &AllRefsCollector, &mut refs);
+        // Only `fn::map` and `fn::filter` bind `item`.
+        assert_eq!(refs, HashSet::from(["zones", "item"]));
+    }
+
+    #[test]
+    fn test_invoke_collector_finds_nested_invokes() {
+        let source = r#"name: test
+variables:
+  a:
+    fn::join:
+      - ","
+      - fn::invoke:
+          function: test:index:outer
+          arguments:
+            inner:
+              fn::test:index:inner: {}
+"#;
+        let (template, diags) = parse_template(source, None);
+        assert!(!diags.has_errors(), "{}", diags);
+        let mut invokes = Vec::new();
+        walk_expr(variable(&template, "a"), &InvokeCollector, &mut invokes);
+        let tokens: Vec<&str> = invokes.iter().map(|i| i.token.as_ref()).collect();
+        assert_eq!(tokens, ["test:index:outer", "test:index:inner"]);
+    }
+
+    #[test]
+    fn test_rename_refs() {
+        let source = r#"name: test
+variables:
+  a:
+    fn::map:
+      - ${bucket.tags[bucket.name]}
+      - ${item}-${bucket.arn}
+  b:
+    fn::map: ['${items}', '${item.bucket}']
+"#;
+        let (mut template, diags) = parse_template(source, None);
+        assert!(!diags.has_errors(), "{}", diags);
+        let mut rename = RenameRefs::new("bucket", "store");
+        for var in &mut template.variables {
+            rewrite_expr(&mut var.value, &mut rename);
+        }
+        assert_eq!(rename.renamed, 3);
+        let mut refs = HashSet::new();
+        walk_expr(variable(&template, "a"), &AllRefsCollector, &mut refs);
+        assert_eq!(refs, HashSet::from(["store"]));
+
+        let mut rename = RenameRefs::new("item", "element");
+        for var in &mut template.variables {
+            rewrite_expr(&mut var.value, &mut rename);
+        }
+        assert_eq!(rename.renamed, 0, "loop bindings are not renamed");
+    }
+}
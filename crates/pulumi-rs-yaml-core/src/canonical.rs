@@ -0,0 +1,854 @@
+//! Canonical plans: a diff-friendly rendering of what a project deploys.
+//!
+//! [`canonical_plan`] renders a loaded project as YAML with every mapping
+//! sorted by key, so two renderings differ only where the program's intent
+//! differs. Config is resolved from the stack's config, and variables whose
+//! values follow from config and literals are computed, as far as that is
+//! possible without the engine: everything that depends on a resource
+//! output, an invoke, or another value only known at deployment time is
+//! kept as written, like `${bucket.arn}` or `fn::invoke: ...`.
+//! [`canonical_plans`] does the same for several stacks of a project at
+//! once.
+//!
+//! Secrets are never printed. Secret config, `fn::secret` and
+//! `fn::encrypted` values, and (with a schema) secret resource inputs are
+//! rendered as [`REDACTED`], and values derived from them stay symbolic.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use serde_json::{json, Map, Value as Json};
+
+use crate::ast::expr::{Expr, InvokeExpr};
+use crate::ast::interpolation::InterpolationPart;
+use crate::ast::property::{PropertyAccess, PropertyAccessor};
+use crate::ast::template::{ResourceDecl, ResourceOptionsDecl, ResourceProperties, ResourceRange};
+use crate::ast::visitor::{rewrite_expr, rewrite_resource, ExprRewriter};
+use crate::config_types::ConfigType;
+use crate::diag::Diagnostics;
+use crate::eval::config::{self, RawConfig};
+use crate::eval::value::Value;
+use crate::eval::{fold, graph};
+use crate::multi_file::MergedTemplate;
+use crate::packages::canonicalize_type_token;
+use crate::schema::SchemaStore;
+
+/// Printed in place of a secret value.
+pub const REDACTED: &str = "[secret]";
+
+/// What a canonical plan is resolved against.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CanonicalOptions<'a> {
+    /// The stack, for `${pulumi.stack}`.
+    pub stack: Option<&'a str>,
+    /// Stack config values, keyed as the engine passes them (`project:key`)
+    /// or by bare key.
+    pub config: Option<&'a RawConfig>,
+    /// Keys of the config values that are secret.
+    pub secret_keys: &'a [String],
+    /// Provider schemas, for canonical type tokens and secret inputs.
+    pub schema: Option<&'a SchemaStore>,
+}
+
+/// Renders the canonical plan of a loaded project.
+///
+/// Returns the diagnostics if the dependency graph is invalid or a config
+/// value does not match its declared type.
+pub fn canonical_plan(
+    merged: &MergedTemplate,
+    options: &CanonicalOptions<'_>,
+) -> Result<String, Diagnostics> {
+    let order = evaluation_order(merged)?;
+    render_plan(merged, &order, options)
+}
+
+/// Renders the canonical plans of several stacks of one project at once,
+/// e.g. to preview dev, staging and production side by side.
+///
+/// The project is parsed and sorted once; the plans are rendered
+/// concurrently and returned in the order of `stacks`. Fails as a whole,
+/// with the diagnostics, only if the dependency graph is invalid; a config
+/// error fails just the plan of that stack.
+pub fn canonical_plans(
+    merged: &MergedTemplate,
+    stacks: &[CanonicalOptions<'_>],
+) -> Result<Vec<Result<String, Diagnostics>>, Diagnostics> {
+    use rayon::prelude::*;
+
+    let order = evaluation_order(merged)?;
+    Ok(stacks
+        .par_iter()
+        .map(|options| render_plan(merged, &order, options))
+        .collect())
+}
+
+fn evaluation_order(merged: &MergedTemplate) -> Result<Vec<String>, Diagnostics> {
+    let template = merged.as_template_decl();
+    let (sort, diags) = graph::topological_sort_with_deps(&template, Some(merged.source_map()));
+    if diags.has_errors() {
+        return Err(diags);
+    }
+    Ok(sort.order)
+}
+
+fn render_plan(
+    merged: &MergedTemplate,
+    order: &[String],
+    options: &CanonicalOptions<'_>,
+) -> Result<String, Diagnostics> {
+    let mut template = merged.as_template_decl();
+    let project = merged.name().unwrap_or("unknown");
+    let empty = RawConfig::new();
+    let raw_config = options.config.unwrap_or(&empty);
+    let mut known = Known::default();
+    let mut settings = vec![(Cow::Borrowed("project"), string(project))];
+    if let Some(stack) = options.stack {
+        settings.push((Cow::Borrowed("stack"), string(stack)));
+    }
+    known
+        .0
+        .insert("pulumi".to_string(), Value::Object(settings));
+
+    let mut diags = Diagnostics::new();
+    let mut config = Map::new();
+    let mut variables = Map::new();
+    let mut resources = Map::new();
+    for name in order {
+        if let Some(entry) = template.config.iter_mut().find(|c| c.key == *name) {
+            let param = &mut entry.param;
+            let default = param.default.as_mut().or(param.value.as_mut());
+            let default = default.and_then(|expr| known.resolve(expr));
+            let is_set = raw_config.contains_key(&format!("{}:{}", project, name))
+                || raw_config.contains_key(name.as_str());
+            let secret_in_config = options
+                .secret_keys
+                .iter()
+                .any(|k| config::strip_config_namespace(project, k) == name.as_str());
+            let rendered = if !is_set && default.is_none() {
+                Json::String(format!("${{{}}}", name))
+            } else {
+                let declared_type = param.type_.as_deref().and_then(ConfigType::parse);
+                let resolved = config::resolve_config_entry(
+                    name,
+                    project,
+                    declared_type,
+                    default,
+                    secret_in_config,
+                    param.secret.unwrap_or(false),
+                    raw_config,
+                    &mut diags,
+                );
+                match resolved {
+                    Some(resolved) if resolved.is_secret => Json::String(REDACTED.to_string()),
+                    Some(resolved) => {
+                        let json = resolved.value.to_json();
+                        known.0.insert(name.clone(), resolved.value.into_owned());
+                        json
+                    }
+                    None => continue,
+                }
+            };
+            config.insert(name.clone(), rendered);
+        } else if let Some(entry) = template.variables.iter_mut().find(|v| v.key == *name) {
+            if let Some(value) = known.resolve(&mut entry.value) {
+                known.0.insert(name.clone(), value);
+            }
+            variables.insert(name.clone(), render(&entry.value));
+        } else if let Some(entry) = template
+            .resources
+            .iter_mut()
+            .find(|r| r.logical_name == *name)
+        {
+            rewrite_resource(&mut entry.resource, &mut Substitute(&known));
+            let decl = &mut entry.resource;
+            for expr in resource_exprs(decl) {
+                fold::fold_expr(expr);
+            }
+            resources.insert(name.clone(), render_resource(decl, options.schema));
+        }
+    }
+    if diags.has_errors() {
+        return Err(diags);
+    }
+
+    let mut outputs = Map::new();
+    for entry in &mut template.outputs {
+        known.resolve(&mut entry.value);
+        outputs.insert(entry.key.to_string(), render(&entry.value));
+    }
+
+    let mut plan = Map::new();
+    plan.insert("project".to_string(), json!(project));
+    if let Some(stack) = options.stack {
+        plan.insert("stack".to_string(), json!(stack));
+    }
+    for (section, entries) in [
+        ("config", config),
+        ("variables", variables),
+        ("resources", resources),
+        ("outputs", outputs),
+    ] {
+        if !entries.is_empty() {
+            plan.insert(section.to_string(), Json::Object(entries));
+        }
+    }
+    Ok(serde_yaml::to_string(&Json::Object(plan)).unwrap_or_default())
+}
+
+fn string(s: &str) -> Value<'static> {
+    Value::String(Cow::Owned(s.to_string()))
+}
+
+/// The config and variables whose values are known before deployment.
+#[derive(Default)]
+struct Known(HashMap<String, Value<'static>>);
+
+impl Known {
+    /// Substitutes the known values into `expr` and folds it, returning its
+    /// value if that makes it a literal.
+    fn resolve(&self, expr: &mut Expr<'_>) -> Option<Value<'static>> {
+        rewrite_expr(expr, &mut Substitute(self));
+        fold::fold_expr(expr);
+        fold::literal(expr).map(Value::into_owned)
+    }
+
+    /// Returns the known value `access` refers to, unless its root is local.
+    fn lookup(&self, access: &PropertyAccess<'_>, scope: &[&str]) -> Option<&Value<'static>> {
+        let mut accessors = access.accessors.iter();
+        let root = match accessors.next()? {
+            PropertyAccessor::Name(root) | PropertyAccessor::StringSubscript(root) => root,
+            _ => return None,
+        };
+        if scope.contains(&root.as_ref()) {
+            return None;
+        }
+        let mut value = self.0.get(root.as_ref())?;
+        for accessor in accessors {
+            value = match (accessor, value) {
+                (
+                    PropertyAccessor::Name(key) | PropertyAccessor::StringSubscript(key),
+                    Value::Object(entries),
+                ) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v)?,
+                (PropertyAccessor::IntSubscript(i), Value::List(items)) => {
+                    items.get(usize::try_from(*i).ok()?)?
+                }
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+}
+
+/// Replaces references to known values with the values.
+struct Substitute<'k>(&'k Known);
+
+impl ExprRewriter for Substitute<'_> {
+    fn rewrite(&mut self, expr: &mut Expr<'_>, scope: &[&str]) {
+        match expr {
+            Expr::Symbol(meta, access) => {
+                let literal = self
+                    .0
+                    .lookup(access, scope)
+                    .and_then(|value| fold::value_to_literal(value.clone(), *meta));
+                if let Some(literal) = literal {
+                    *expr = literal;
+                }
+            }
+            Expr::Interpolate(_, parts) => {
+                let mut merged: Vec<InterpolationPart<'_>> = Vec::new();
+                let mut text = String::new();
+                for part in parts.drain(..) {
+                    text.push_str(&part.text);
+                    let Some(access) = part.value else {
+                        continue;
+                    };
+                    match self.0.lookup(&access, scope) {
+                        Some(Value::String(s)) => text.push_str(s),
+                        Some(Value::Number(n)) => text.push_str(&n.to_string()),
+                        Some(Value::Bool(b)) => text.push_str(&b.to_string()),
+                        _ => merged.push(InterpolationPart {
+                            text: Cow::Owned(std::mem::take(&mut text)),
+                            value: Some(access),
+                        }),
+                    }
+                }
+                if !text.is_empty() || merged.is_empty() {
+                    merged.push(InterpolationPart {
+                        text: Cow::Owned(text),
+                        value: None,
+                    });
+                }
+                *parts = merged;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The top-level expressions of a resource.
+fn resource_exprs<'a, 'src>(decl: &'a mut ResourceDecl<'src>) -> Vec<&'a mut Expr<'src>> {
+    let mut exprs: Vec<&mut Expr<'src>> = Vec::new();
+    exprs.extend(decl.name.as_mut());
+    exprs.extend(decl.range.as_mut().map(ResourceRange::expr_mut));
+    match &mut decl.properties {
+        ResourceProperties::Map(props) => exprs.extend(props.iter_mut().map(|p| &mut p.value)),
+        ResourceProperties::Expr(expr) => exprs.push(expr),
+    }
+    exprs.extend(decl.locals.iter_mut().map(|l| &mut l.value));
+    if let Some(get) = &mut decl.get {
+        exprs.push(&mut get.id);
+        exprs.extend(get.state.iter_mut().map(|s| &mut s.value));
+    }
+    exprs
+}
+
+fn render_resource(decl: &ResourceDecl<'_>, schema: Option<&SchemaStore>) -> Json {
+    let type_token = schema
+        .and_then(|store| store.resolve_resource_token(&decl.type_))
+        .map(Cow::into_owned)
+        .unwrap_or_else(|| canonicalize_type_token(&decl.type_));
+    let secret_inputs = schema.map(|store| store.secret_input_properties(&type_token));
+
+    let mut resource = Map::new();
+    resource.insert("type".to_string(), json!(type_token));
+    if let Some(name) = &decl.name {
+        resource.insert("name".to_string(), render(name));
+    }
+    match &decl.range {
+        Some(ResourceRange::Count(expr)) => {
+            resource.insert("count".to_string(), render(expr));
+        }
+        Some(ResourceRange::Items(expr)) => {
+            resource.insert("range".to_string(), render(expr));
+        }
+        None => {}
+    }
+    if !decl.locals.is_empty() {
+        let locals = decl
+            .locals
+            .iter()
+            .map(|l| (l.key.to_string(), render(&l.value)))
+            .collect();
+        resource.insert("locals".to_string(), Json::Object(locals));
+    }
+    if let Some(get) = &decl.get {
+        let state: Map<String, Json> = get
+            .state
+            .iter()
+            .map(|s| (s.key.to_string(), render(&s.value)))
+            .collect();
+        resource.insert(
+            "get".to_string(),
+            json!({ "id": render(&get.id), "state": state }),
+        );
+    }
+    let properties = match &decl.properties {
+        ResourceProperties::Map(props) => Json::Object(
+            props
+                .iter()
+                .map(|p| {
+                    let secret = secret_inputs.is_some_and(|s| s.contains(p.key.as_ref()));
+                    let value = if secret {
+                        json!(REDACTED)
+                    } else {
+                        render(&p.value)
+                    };
+                    (p.key.to_string(), value)
+                })
+                .collect(),
+        ),
+        ResourceProperties::Expr(expr) => render(expr),
+    };
+    if properties.as_object().is_none_or(|p| !p.is_empty()) {
+        resource.insert("properties".to_string(), properties);
+    }
+    let options = render_options(&decl.options);
+    if !options.is_empty() {
+        resource.insert("options".to_string(), Json::Object(options));
+    }
+    Json::Object(resource)
+}
+
+/// Renders the resource options that are set.
+fn render_options(opts: &ResourceOptionsDecl<'_>) -> Map<String, Json> {
+    let mut map = Map::new();
+    for (key, expr) in [
+        ("dependsOn", &opts.depends_on),
+        ("parent", &opts.parent),
+        ("provider", &opts.provider),
+        ("providers", &opts.providers),
+        ("aliases", &opts.aliases),
+        ("protect", &opts.protect),
+        ("replaceWith", &opts.replace_with),
+        ("deletedWith", &opts.deleted_with),
+    ] {
+        if let Some(expr) = expr {
+            map.insert(key.to_string(), render(expr));
+        }
+    }
+    let mut plain = |key: &str, value: Option<Json>| {
+        if let Some(value) = value {
+            map.insert(key.to_string(), value);
+        }
+    };
+    plain(
+        "deleteBeforeReplace",
+        opts.delete_before_replace.map(|b| json!(b)),
+    );
+    plain(
+        "ignoreChanges",
+        opts.ignore_changes.as_ref().map(|v| json!(v)),
+    );
+    plain("import", opts.import.as_ref().map(|v| json!(v)));
+    plain("version", opts.version.as_ref().map(|v| json!(v)));
+    plain(
+        "pluginDownloadURL",
+        opts.plugin_download_url.as_ref().map(|v| json!(v)),
+    );
+    plain(
+        "additionalSecretOutputs",
+        opts.additional_secret_outputs.as_ref().map(|v| json!(v)),
+    );
+    plain(
+        "customTimeouts",
+        opts.custom_timeouts.as_ref().map(|ct| {
+            let timeouts: Map<String, Json> = [
+                ("create", &ct.create),
+                ("update", &ct.update),
+                ("delete", &ct.delete),
+            ]
+            .into_iter()
+            .filter_map(|(k, v)| Some((k.to_string(), json!(v.as_ref()?))))
+            .collect();
+            Json::Object(timeouts)
+        }),
+    );
+    plain(
+        "replaceOnChanges",
+        opts.replace_on_changes.as_ref().map(|v| json!(v)),
+    );
+    plain("retainOnDelete", opts.retain_on_delete.map(|b| json!(b)));
+    plain("hideDiffs", opts.hide_diffs.as_ref().map(|v| json!(v)));
+    map
+}
+
+/// Renders an expression the way it would be written in a template, with
+/// mappings sorted by key and secrets redacted.
+fn render(expr: &Expr<'_>) -> Json {
+    match expr {
+        Expr::Null(_) => Json::Null,
+        Expr::Bool(_, b) => json!(b),
+        Expr::Number(_, n) => match n.as_i64() {
+            Some(i) => json!(i),
+            None => json!(n.as_f64()),
+        },
+        Expr::String(_, s) => json!(s),
+        Expr::Symbol(_, access) => json!(format!("${{{}}}", access)),
+        Expr::Interpolate(_, parts) => {
+            let mut s = String::new();
+            for part in parts {
+                s.push_str(&part.text.replace("${", "$${"));
+                if let Some(access) = &part.value {
+                    s.push_str(&format!("${{{}}}", access));
+                }
+            }
+            json!(s)
+        }
+        Expr::List(_, items) => Json::Array(items.iter().map(render).collect()),
+        Expr::Object(_, entries) => Json::Object(
+            entries
+                .iter()
+                .map(|e| {
+                    let key = match render(&e.key) {
+                        Json::String(key) => key,
+                        other => other.to_string(),
+                    };
+                    (key, render(&e.value))
+                })
+                .collect(),
+        ),
+        Expr::Secret(..) | Expr::Encrypted(..) => json!(REDACTED),
+        Expr::Invoke(_, invoke) => render_invoke(invoke),
+        Expr::Join(_, a, b) => builtin("join", &[a, b]),
+        Expr::Select(_, a, b) => builtin("select", &[a, b]),
+        Expr::Split(_, a, b) => builtin("split", &[a, b]),
+        Expr::JsonPatch(_, a, b) => builtin("jsonPatch", &[a, b]),
+        Expr::Map(_, a, b) => builtin("map", &[a, b]),
+        Expr::Filter(_, a, b) => builtin("filter", &[a, b]),
+        Expr::Lookup(_, map, key, default) => {
+            let mut args = vec![render(map), render(key)];
+            args.extend(default.as_deref().map(render));
+            json!({ "fn::lookup": args })
+        }
+        Expr::Env(_, name, None) => builtin("env", &[name]),
+        Expr::Env(_, name, Some(default)) => builtin("env", &[name, default]),
+        Expr::Substring(_, a, b, c) => builtin("substring", &[a, b, c]),
+        Expr::Replace(_, a, b, c) => builtin("replace", &[a, b, c]),
+        Expr::StartsWith(_, a, b) => builtin("startsWith", &[a, b]),
+        Expr::Equals(_, a, b) => builtin("equals", &[a, b]),
+        Expr::LessThan(_, a, b) => builtin("lessThan", &[a, b]),
+        Expr::LessThanOrEqual(_, a, b) => builtin("lessThanOrEqual", &[a, b]),
+        Expr::GreaterThan(_, a, b) => builtin("greaterThan", &[a, b]),
+        Expr::GreaterThanOrEqual(_, a, b) => builtin("greaterThanOrEqual", &[a, b]),
+        Expr::Format(_, a, b) => builtin("format", &[a, b]),
+        Expr::CidrSubnet(_, a, b, c) => builtin("cidrSubnet", &[a, b, c]),
+        Expr::CidrHost(_, a, b) => builtin("cidrHost", &[a, b]),
+        Expr::If(_, a, b, c) => builtin("if", &[a, b, c]),
+        Expr::ValidateJson(_, value, schema) => json!({
+            "fn::validateJson": { "value": render(value), "schema": render(schema) },
+        }),
+        Expr::Assert(_, condition, message) => json!({
+            "fn::assert": { "condition": render(condition), "message": render(message) },
+        }),
+        Expr::TemplateFile(_, path, arguments) => json!({
+            "fn::templateFile": { "path": render(path), "arguments": render(arguments) },
+        }),
+        Expr::Foreach(_, foreach) => {
+            let mut args = Map::new();
+            args.insert("list".to_string(), render(&foreach.list));
+            args.insert("as".to_string(), json!(foreach.var));
+            if let Some(index) = &foreach.index {
+                args.insert("index".to_string(), json!(index));
+            }
+            args.insert("do".to_string(), render(&foreach.body));
+            json!({ "fn::foreach": args })
+        }
+        Expr::ToJson(_, a) => builtin("toJSON", &[a]),
+        Expr::ParseJson(_, a) => builtin("parseJSON", &[a]),
+        Expr::ParseYaml(_, a) => builtin("parseYAML", &[a]),
+        Expr::ToBase64(_, a) => builtin("toBase64", &[a]),
+        Expr::FromBase64(_, a) => builtin("fromBase64", &[a]),
+        Expr::ReadFile(_, a) => builtin("readFile", &[a]),
+        Expr::ReadFileBinary(_, a) => builtin("readFileBinary", &[a]),
+        Expr::Merge(_, a) => builtin("merge", &[a]),
+        Expr::Abs(_, a) => builtin("abs", &[a]),
+        Expr::Floor(_, a) => builtin("floor", &[a]),
+        Expr::Ceil(_, a) => builtin("ceil", &[a]),
+        Expr::Max(_, a) => builtin("max", &[a]),
+        Expr::Min(_, a) => builtin("min", &[a]),
+        Expr::FormatDuration(_, a) => builtin("formatDuration", &[a]),
+        Expr::FormatBytes(_, a) => builtin("formatBytes", &[a]),
+        Expr::StringLen(_, a) => builtin("stringLen", &[a]),
+        Expr::Sort(_, a) => builtin("sort", &[a]),
+        Expr::Unique(_, a) => builtin("unique", &[a]),
+        Expr::Length(_, a) => builtin("length", &[a]),
+        Expr::Coalesce(_, a) => builtin("coalesce", &[a]),
+        Expr::Not(_, a) => builtin("not", &[a]),
+        Expr::And(_, a) => builtin("and", &[a]),
+        Expr::Or(_, a) => builtin("or", &[a]),
+        Expr::ToUpper(_, a) => builtin("toUpper", &[a]),
+        Expr::ToLower(_, a) => builtin("toLower", &[a]),
+        Expr::Trim(_, a) => builtin("trim", &[a]),
+        Expr::CidrNetmask(_, a) => builtin("cidrNetmask", &[a]),
+        Expr::TimeUtc(_, a) => builtin("timeUtc", &[a]),
+        Expr::TimeUnix(_, a) => builtin("timeUnix", &[a]),
+        Expr::Uuid(_, a) => builtin("uuid", &[a]),
+        Expr::RandomString(_, a) => builtin("randomString", &[a]),
+        Expr::DateFormat(_, a) => builtin("dateFormat", &[a]),
+        Expr::StringAsset(_, a) => builtin("stringAsset", &[a]),
+        Expr::FileAsset(_, a) => builtin("fileAsset", &[a]),
+        Expr::RemoteAsset(_, a) => builtin("remoteAsset", &[a]),
+        Expr::FileArchive(_, a) => builtin("fileArchive", &[a]),
+        Expr::RemoteArchive(_, a) => builtin("remoteArchive", &[a]),
+        Expr::AssetArchive(_, entries) => {
+            let entries: Map<String, Json> = entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), render(v)))
+                .collect();
+            json!({ "fn::assetArchive": entries })
+        }
+        Expr::Starlark(_, call) => json!({
+            "fn::starlark": { "invoke": call.invoke, "input": render(&call.input) },
+        }),
+        Expr::Open(_, provider, inputs) => {
+            json!({ format!("fn::open::{}", provider): render(inputs) })
+        }
+    }
+}
+
+/// Renders a builtin call; multiple arguments are written as a list.
+fn builtin(name: &str, args: &[&Expr<'_>]) -> Json {
+    let args = match args {
+        [arg] => render(arg),
+        args => Json::Array(args.iter().map(|arg| render(arg)).collect()),
+    };
+    json!({ format!("fn::{}", name): args })
+}
+
+fn render_invoke(invoke: &InvokeExpr<'_>) -> Json {
+    let mut call = Map::new();
+    call.insert(
+        "function".to_string(),
+        json!(canonicalize_type_token(&invoke.token)),
+    );
+    if let Some(args) = &invoke.call_args {
+        call.insert("arguments".to_string(), render(args));
+    }
+    let opts = &invoke.call_opts;
+    let mut options = Map::new();
+    for (key, expr) in [
+        ("parent", &opts.parent),
+        ("provider", &opts.provider),
+        ("dependsOn", &opts.depends_on),
+    ] {
+        if let Some(expr) = expr {
+            options.insert(key.to_string(), render(expr));
+        }
+    }
+    if let Some(version) = &opts.version {
+        options.insert("version".to_string(), json!(version));
+    }
+    if let Some(url) = &opts.plugin_download_url {
+        options.insert("pluginDownloadURL".to_string(), json!(url));
+    }
+    if !options.is_empty() {
+        call.insert("options".to_string(), Json::Object(options));
+    }
+    if let Some(ret) = &invoke.return_ {
+        call.insert("return".to_string(), json!(ret));
+    }
+    json!({ "fn::invoke": call })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::parse::parse_template;
+    use crate::multi_file::merge_templates;
+    use crate::schema::{PackageSchema, ResourceTypeInfo};
+
+    const SOURCE: &str = r#"name: shop
+runtime: yaml
+config:
+  env:
+    type: string
+  replicas:
+    type: integer
+    default: 2
+  dbPassword:
+    type: string
+    secret: true
+variables:
+  prefix: shop-${env}
+  tags:
+    fn::merge:
+      - team: web
+        env: ${env}
+      - stack: ${pulumi.stack}
+  ami:
+    fn::invoke:
+      function: aws:ec2:getAmi
+      arguments:
+        owners: [amazon]
+      return: id
+resources:
+  bucket:
+    type: aws:s3:Bucket
+    properties:
+      bucket: ${prefix}-assets
+      tags: ${tags}
+  db:
+    type: aws:rds:Instance
+    properties:
+      password: ${dbPassword}
+      masterPassword:
+        fn::secret: hunter2
+      instanceClass: db.t3.micro
+      imageId: ${ami}
+      allocatedStorage: ${replicas}
+    options:
+      dependsOn:
+        - ${bucket}
+      protect: true
+outputs:
+  bucketArn: ${bucket.arn}
+  name: ${prefix}
+"#;
+
+    fn plan(source: &str, options: &CanonicalOptions<'_>) -> String {
+        let (template, diags) = parse_template(source, None);
+        assert!(!diags.has_errors(), "{}", diags);
+        let (merged, diags) = merge_templates(template, "Pulumi.yaml", Vec::new());
+        assert!(!diags.has_errors(), "{}", diags);
+        canonical_plan(&merged, options).unwrap()
+    }
+
+    #[test]
+    fn test_canonical_plan() {
+        let config: RawConfig = [
+            ("shop:env".to_string(), "prod".to_string()),
+            ("shop:dbPassword".to_string(), "s3cret".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        let options = CanonicalOptions {
+            stack: Some("prod"),
+            config: Some(&config),
+            ..Default::default()
+        };
+        let rendered = plan(SOURCE, &options);
+        assert_eq!(
+            rendered,
+            r#"config:
+  dbPassword: '[secret]'
+  env: prod
+  replicas: 2
+outputs:
+  bucketArn: ${bucket.arn}
+  name: shop-prod
+project: shop
+resources:
+  bucket:
+    properties:
+      bucket: shop-prod-assets
+      tags:
+        env: prod
+        stack: prod
+        team: web
+    type: aws:s3/bucket:Bucket
+  db:
+    options:
+      dependsOn:
+      - ${bucket}
+      protect: true
+    properties:
+      allocatedStorage: 2
+      imageId: ${ami}
+      instanceClass: db.t3.micro
+      masterPassword: '[secret]'
+      password: ${dbPassword}
+    type: aws:rds/instance:Instance
+stack: prod
+variables:
+  ami:
+    fn::invoke:
+      arguments:
+        owners:
+        - amazon
+      function: aws:ec2/getAmi:getAmi
+      return: id
+  prefix: shop-prod
+  tags:
+    env: prod
+    stack: prod
+    team: web
+"#
+        );
+        assert!(!rendered.contains("s3cret") && !rendered.contains("hunter2"));
+
+        // Reordering the template does not change the plan.
+        let reordered = SOURCE.replace("  replicas:\n    type: integer\n    default: 2\n", "");
+        let reordered = reordered.replace(
+            "  dbPassword:\n",
+            "  replicas:\n    type: integer\n    default: 2\n  dbPassword:\n",
+        );
+        assert_eq!(plan(&reordered, &options), rendered);
+    }
+
+    #[test]
+    fn test_canonical_plan_without_config() {
+        let rendered = plan(SOURCE, &CanonicalOptions::default());
+        assert!(rendered.contains("  env: ${env}\n"), "{}", rendered);
+        assert!(rendered.contains("  prefix: shop-${env}\n"), "{}", rendered);
+        // Values only partly known are referenced by name.
+        assert!(
+            rendered.contains("      bucket: ${prefix}-assets\n"),
+            "{}",
+            rendered
+        );
+        assert!(
+            rendered.contains("    - stack: ${pulumi.stack}\n"),
+            "{}",
+            rendered
+        );
+    }
+
+    #[test]
+    fn test_canonical_plans_per_stack() {
+        let (template, _) = parse_template(SOURCE, None);
+        let (merged, _) = merge_templates(template, "Pulumi.yaml", Vec::new());
+        let config = |env: &str, replicas: &str| -> RawConfig {
+            [
+                ("shop:env".to_string(), env.to_string()),
+                ("shop:replicas".to_string(), replicas.to_string()),
+            ]
+            .into_iter()
+            .collect()
+        };
+        let (dev, prod, broken) = (config("dev", "1"), config("prod", "3"), config("qa", "x"));
+        let stacks = [
+            CanonicalOptions {
+                stack: Some("dev"),
+                config: Some(&dev),
+                ..Default::default()
+            },
+            CanonicalOptions {
+                stack: Some("prod"),
+                config: Some(&prod),
+                ..Default::default()
+            },
+            CanonicalOptions {
+                stack: Some("qa"),
+                config: Some(&broken),
+                ..Default::default()
+            },
+        ];
+        let plans = canonical_plans(&merged, &stacks).unwrap();
+        assert_eq!(plans.len(), 3);
+        assert_eq!(
+            plans[0].as_ref().unwrap(),
+            &canonical_plan(&merged, &stacks[0]).unwrap()
+        );
+        assert!(plans[0]
+            .as_ref()
+            .unwrap()
+            .contains("      bucket: shop-dev-assets\n"));
+        assert!(plans[1]
+            .as_ref()
+            .unwrap()
+            .contains("      allocatedStorage: 3\n"));
+        assert!(plans[2].is_err());
+    }
+
+    #[test]
+    fn test_canonical_plan_redacts_schema_secrets() {
+        let mut store = SchemaStore::new();
+        let mut info = ResourceTypeInfo::default();
+        info.secret_input_properties.insert("password".to_string());
+        store.insert(PackageSchema {
+            name: "aws".to_string(),
+            resources: [("aws:rds/instance:Instance".to_string(), info)]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        });
+        let config: RawConfig = [("env".to_string(), "dev".to_string())]
+            .into_iter()
+            .collect();
+        let options = CanonicalOptions {
+            config: Some(&config),
+            schema: Some(&store),
+            ..Default::default()
+        };
+        let rendered = plan(SOURCE, &options);
+        assert!(
+            rendered.contains("      password: '[secret]'\n"),
+            "{}",
+            rendered
+        );
+
+        let config: RawConfig = [("env".to_string(), "dev".to_string())]
+            .into_iter()
+            .collect();
+        let secret_keys = vec!["shop:env".to_string()];
+        let options = CanonicalOptions {
+            config: Some(&config),
+            secret_keys: &secret_keys,
+            ..Default::default()
+        };
+        let rendered = plan(SOURCE, &options);
+        assert!(rendered.contains("  env: '[secret]'\n"), "{}", rendered);
+        assert!(rendered.contains("  prefix: shop-${env}\n"), "{}", rendered);
+        assert!(!rendered.contains("dev"), "{}", rendered);
+    }
+}
@@ -0,0 +1,473 @@
+//! Offline preview diffing against a stack checkpoint.
+//!
+//! Compares the resources recorded in a `pulumi stack export` with the
+//! registrations of a fresh (mock) evaluation, matching by URN and comparing
+//! input hashes. No provider is contacted, so the result is best-effort:
+//!
+//! - provider-computed defaults are not applied, so a resource whose inputs
+//!   were normalized by its provider may show up as an update;
+//! - secrets are compared as opaque (the checkpoint only has ciphertext);
+//! - an unknown planned input always counts as a change.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value as Json};
+
+use crate::eval::mock::CapturedRegistration;
+use crate::eval::protobuf::{ARCHIVE_SIG, ASSET_SIG, RESOURCE_SIG, SECRET_SIG, SIG_KEY};
+use crate::eval::value::{Archive, Asset, Value};
+
+const SECRET_MARKER: &str = "<secret>";
+const UNKNOWN_MARKER: &str = "<unknown>";
+
+/// The subset of `pulumi stack export` output used for diffing.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct StackExport {
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub deployment: Deployment,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Deployment {
+    #[serde(default)]
+    pub resources: Vec<CheckpointResource>,
+}
+
+/// A resource as recorded in the checkpoint.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CheckpointResource {
+    pub urn: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+    #[serde(default)]
+    pub custom: bool,
+    #[serde(default)]
+    pub inputs: Map<String, Json>,
+    /// Pending deletion from an earlier replace.
+    #[serde(default)]
+    pub delete: bool,
+    /// Read with `get`, not managed by the stack.
+    #[serde(default)]
+    pub external: bool,
+}
+
+impl StackExport {
+    /// Parses the JSON written by `pulumi stack export`.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+impl CheckpointResource {
+    /// Whether the program itself registers this resource. The stack
+    /// resource, default providers, reads, and pending deletes are not.
+    fn is_managed(&self) -> bool {
+        if self.delete || self.external || self.type_ == "pulumi:pulumi:Stack" {
+            return false;
+        }
+        let name = self.urn.rsplit("::").next().unwrap_or_default();
+        !(self.type_.starts_with("pulumi:providers:") && name.starts_with("default"))
+    }
+
+    fn canonical_inputs(&self) -> Map<String, Json> {
+        self.inputs
+            .iter()
+            .map(|(k, v)| (k.clone(), checkpoint_to_canonical(v)))
+            .collect()
+    }
+}
+
+/// A resource registered by the evaluated program.
+#[derive(Debug, Clone)]
+pub struct PlannedResource {
+    pub urn: String,
+    pub type_token: String,
+    pub inputs: Map<String, Json>,
+}
+
+impl PlannedResource {
+    /// Converts captured registrations (in registration order) to planned
+    /// resources, computing the URNs the engine would assign in `stack` of
+    /// `project`, including the parent type chain.
+    pub fn from_registrations(
+        project: &str,
+        stack: &str,
+        registrations: &[CapturedRegistration],
+    ) -> Vec<Self> {
+        let mut qualified_types: Vec<String> = Vec::with_capacity(registrations.len());
+        let mut planned = Vec::with_capacity(registrations.len());
+        for (i, reg) in registrations.iter().enumerate() {
+            // Parents register first; the parent URN ends in `::<type>::<name>`.
+            let parent = reg.options.parent_urn.as_deref().and_then(|parent_urn| {
+                registrations[..i]
+                    .iter()
+                    .rposition(|p| parent_urn.ends_with(&format!("::{}::{}", p.type_token, p.name)))
+            });
+            let qualified = match parent {
+                Some(p) => format!("{}${}", qualified_types[p], reg.type_token),
+                None => reg.type_token.clone(),
+            };
+            planned.push(PlannedResource {
+                urn: format!(
+                    "urn:pulumi:{}::{}::{}::{}",
+                    stack, project, qualified, reg.name
+                ),
+                type_token: reg.type_token.clone(),
+                inputs: reg
+                    .inputs
+                    .iter()
+                    .map(|(k, v)| (k.clone(), value_to_canonical(v)))
+                    .collect(),
+            });
+            qualified_types.push(qualified);
+        }
+        planned
+    }
+}
+
+/// A resource that would be created, deleted, or updated.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResourceChange {
+    pub urn: String,
+    pub type_token: String,
+    /// Input hash in the checkpoint, if the resource exists there.
+    pub old_hash: Option<String>,
+    /// Input hash of the planned registration, if the program registers it.
+    pub new_hash: Option<String>,
+    /// Top-level input keys that differ, sorted. Empty for adds and deletes.
+    pub changed_keys: Vec<String>,
+}
+
+/// The result of [`preview_diff`]. Every list is sorted by URN.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PreviewDiff {
+    pub adds: Vec<ResourceChange>,
+    pub deletes: Vec<ResourceChange>,
+    pub updates: Vec<ResourceChange>,
+    /// URNs of resources whose inputs are unchanged.
+    pub same: Vec<String>,
+}
+
+impl PreviewDiff {
+    /// Returns true if nothing would change.
+    pub fn is_empty(&self) -> bool {
+        self.adds.is_empty() && self.deletes.is_empty() && self.updates.is_empty()
+    }
+}
+
+/// Computes what would change if `planned` were deployed over `export`.
+pub fn preview_diff(export: &StackExport, planned: &[PlannedResource]) -> PreviewDiff {
+    let old: BTreeMap<&str, &CheckpointResource> = export
+        .deployment
+        .resources
+        .iter()
+        .filter(|r| r.is_managed())
+        .map(|r| (r.urn.as_str(), r))
+        .collect();
+    let new: BTreeMap<&str, &PlannedResource> =
+        planned.iter().map(|r| (r.urn.as_str(), r)).collect();
+
+    let mut diff = PreviewDiff::default();
+    for (urn, res) in &new {
+        let new_hash = input_hash(&res.inputs);
+        let Some(prev) = old.get(urn) else {
+            diff.adds.push(ResourceChange {
+                urn: urn.to_string(),
+                type_token: res.type_token.clone(),
+                old_hash: None,
+                new_hash: Some(new_hash),
+                changed_keys: Vec::new(),
+            });
+            continue;
+        };
+        let prev_inputs = prev.canonical_inputs();
+        let changed_keys = changed_keys(&prev_inputs, &res.inputs);
+        if changed_keys.is_empty() {
+            diff.same.push(urn.to_string());
+        } else {
+            diff.updates.push(ResourceChange {
+                urn: urn.to_string(),
+                type_token: res.type_token.clone(),
+                old_hash: Some(input_hash(&prev_inputs)),
+                new_hash: Some(new_hash),
+                changed_keys,
+            });
+        }
+    }
+    for (urn, prev) in &old {
+        if !new.contains_key(urn) {
+            let prev_inputs = prev.canonical_inputs();
+            diff.deletes.push(ResourceChange {
+                urn: urn.to_string(),
+                type_token: prev.type_.clone(),
+                old_hash: Some(input_hash(&prev_inputs)),
+                new_hash: None,
+                changed_keys: Vec::new(),
+            });
+        }
+    }
+    diff
+}
+
+fn changed_keys(old: &Map<String, Json>, new: &Map<String, Json>) -> Vec<String> {
+    let mut keys: Vec<String> = old
+        .keys()
+        .chain(new.keys())
+        .filter(|k| {
+            let (o, n) = (old.get(*k), new.get(*k));
+            o != n || n.is_some_and(contains_unknown)
+        })
+        .cloned()
+        .collect();
+    keys.sort();
+    keys.dedup();
+    keys
+}
+
+fn contains_unknown(value: &Json) -> bool {
+    match value {
+        Json::String(s) => s == UNKNOWN_MARKER,
+        Json::Array(items) => items.iter().any(contains_unknown),
+        Json::Object(map) => map.values().any(contains_unknown),
+        _ => false,
+    }
+}
+
+/// A stable 64-bit FNV-1a hash of the canonical inputs, as hex.
+fn input_hash(inputs: &Map<String, Json>) -> String {
+    // `serde_json::Map` is ordered by key, so the encoding is canonical.
+    let encoded = Json::Object(inputs.clone()).to_string();
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in encoded.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// Numbers are compared as floats, so an integer and the equal float are
+/// the same input, and input hashes stay stable across versions.
+fn canonical_number(n: f64) -> Json {
+    serde_json::Number::from_f64(n)
+        .map(Json::Number)
+        .unwrap_or(Json::Null)
+}
+
+/// Converts an evaluated value to the canonical comparison form.
+fn value_to_canonical(value: &Value<'_>) -> Json {
+    match value {
+        Value::Null => Json::Null,
+        Value::Bool(b) => Json::Bool(*b),
+        Value::Number(n) => canonical_number(n.as_f64()),
+        Value::String(s) => Json::String(s.to_string()),
+        Value::Bytes(b) => Json::String(crate::eval::value::encode_base64(b)),
+        Value::List(items) => Json::Array(items.iter().map(value_to_canonical).collect()),
+        Value::Object(entries) => Json::Object(
+            entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), value_to_canonical(v)))
+                .collect(),
+        ),
+        Value::Secret(_) => Json::String(SECRET_MARKER.into()),
+        Value::Unknown => Json::String(UNKNOWN_MARKER.into()),
+        // Mock evaluation cannot resolve a reference to a checkpoint URN.
+        Value::Resource(_) => Json::String(UNKNOWN_MARKER.into()),
+        Value::Asset(Asset::String(s) | Asset::File(s, _) | Asset::Remote(s)) => {
+            json!({ "<asset>": s })
+        }
+        Value::Archive(Archive::File(s) | Archive::Remote(s)) => json!({ "<archive>": s }),
+        Value::Archive(Archive::Assets(entries)) => {
+            let map: Map<String, Json> = entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), value_to_canonical(v)))
+                .collect();
+            json!({ "<archive>": map })
+        }
+    }
+}
+
+/// Converts a checkpoint input value to the canonical comparison form.
+fn checkpoint_to_canonical(value: &Json) -> Json {
+    match value {
+        Json::Number(n) => n.as_f64().map(canonical_number).unwrap_or(Json::Null),
+        Json::Array(items) => Json::Array(items.iter().map(checkpoint_to_canonical).collect()),
+        Json::Object(map) => {
+            let field = |key: &str| map.get(key).and_then(Json::as_str);
+            match field(SIG_KEY) {
+                Some(SECRET_SIG) => Json::String(SECRET_MARKER.into()),
+                Some(RESOURCE_SIG) => Json::String(UNKNOWN_MARKER.into()),
+                Some(ASSET_SIG) => {
+                    let source = field("text").or(field("path")).or(field("uri"));
+                    json!({ "<asset>": source })
+                }
+                Some(ARCHIVE_SIG) => match map.get("assets").and_then(Json::as_object) {
+                    Some(assets) => {
+                        let assets: Map<String, Json> = assets
+                            .iter()
+                            .map(|(k, v)| (k.clone(), checkpoint_to_canonical(v)))
+                            .collect();
+                        json!({ "<archive>": assets })
+                    }
+                    None => json!({ "<archive>": field("path").or(field("uri")) }),
+                },
+                _ => Json::Object(
+                    map.iter()
+                        .map(|(k, v)| (k.clone(), checkpoint_to_canonical(v)))
+                        .collect(),
+                ),
+            }
+        }
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::resource::ResolvedResourceOptions;
+    use std::borrow::Cow;
+
+    fn registration(
+        type_token: &str,
+        name: &str,
+        inputs: Vec<(&str, Value<'static>)>,
+        parent_urn: Option<&str>,
+    ) -> CapturedRegistration {
+        CapturedRegistration {
+            type_token: type_token.to_string(),
+            name: name.to_string(),
+            custom: true,
+            remote: false,
+            inputs: inputs
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+            options: ResolvedResourceOptions {
+                parent_urn: parent_urn.map(str::to_string),
+                ..Default::default()
+            },
+        }
+    }
+
+    fn string(s: &'static str) -> Value<'static> {
+        Value::String(Cow::Borrowed(s))
+    }
+
+    const EXPORT: &str = r#"{
+      "version": 3,
+      "deployment": {
+        "resources": [
+          { "urn": "urn:pulumi:dev::proj::pulumi:pulumi:Stack::proj-dev",
+            "type": "pulumi:pulumi:Stack", "custom": false },
+          { "urn": "urn:pulumi:dev::proj::pulumi:providers:aws::default_6_0_0",
+            "type": "pulumi:providers:aws", "custom": true },
+          { "urn": "urn:pulumi:dev::proj::aws:s3/bucket:Bucket::logs",
+            "type": "aws:s3/bucket:Bucket", "custom": true,
+            "inputs": { "acl": "private", "versions": 3 } },
+          { "urn": "urn:pulumi:dev::proj::aws:s3/bucket:Bucket::site",
+            "type": "aws:s3/bucket:Bucket", "custom": true,
+            "inputs": { "acl": "private",
+                        "token": { "4dabf18193072939515e22adb298388d": "1b47061264138c4ac30d75fd1eb44270",
+                                   "ciphertext": "AAAB" } } },
+          { "urn": "urn:pulumi:dev::proj::aws:s3/bucket:Bucket::old",
+            "type": "aws:s3/bucket:Bucket", "custom": true, "inputs": {} }
+        ]
+      }
+    }"#;
+
+    #[test]
+    fn test_preview_diff() {
+        let export = StackExport::from_json(EXPORT).unwrap();
+        let registrations = vec![
+            registration(
+                "aws:s3/bucket:Bucket",
+                "logs",
+                vec![
+                    ("acl", string("private")),
+                    ("versions", Value::Number(3.0.into())),
+                ],
+                None,
+            ),
+            registration(
+                "aws:s3/bucket:Bucket",
+                "site",
+                vec![
+                    ("acl", string("public-read")),
+                    ("token", Value::Secret(Box::new(string("hunter2")))),
+                ],
+                None,
+            ),
+            registration("aws:s3/bucket:Bucket", "new", vec![], None),
+        ];
+        let planned = PlannedResource::from_registrations("proj", "dev", &registrations);
+        let diff = preview_diff(&export, &planned);
+
+        assert_eq!(
+            diff.same,
+            ["urn:pulumi:dev::proj::aws:s3/bucket:Bucket::logs"]
+        );
+        assert_eq!(diff.adds.len(), 1);
+        assert_eq!(
+            diff.adds[0].urn,
+            "urn:pulumi:dev::proj::aws:s3/bucket:Bucket::new"
+        );
+        assert_eq!(diff.deletes.len(), 1);
+        assert_eq!(
+            diff.deletes[0].urn,
+            "urn:pulumi:dev::proj::aws:s3/bucket:Bucket::old"
+        );
+        assert_eq!(diff.updates.len(), 1);
+        let update = &diff.updates[0];
+        assert_eq!(
+            update.urn,
+            "urn:pulumi:dev::proj::aws:s3/bucket:Bucket::site"
+        );
+        // The secret compares as opaque; only the plain input changed.
+        assert_eq!(update.changed_keys, ["acl"]);
+        assert_ne!(update.old_hash, update.new_hash);
+    }
+
+    #[test]
+    fn test_unknown_inputs_count_as_changes() {
+        let export = StackExport::from_json(EXPORT).unwrap();
+        let registrations = vec![registration(
+            "aws:s3/bucket:Bucket",
+            "logs",
+            vec![("acl", string("private")), ("versions", Value::Unknown)],
+            None,
+        )];
+        let planned = PlannedResource::from_registrations("proj", "dev", &registrations);
+        let diff = preview_diff(&export, &planned);
+        assert_eq!(diff.updates[0].changed_keys, ["versions"]);
+    }
+
+    #[test]
+    fn test_child_urns_include_parent_type() {
+        let registrations = vec![
+            registration("my:index:Component", "parent", vec![], None),
+            registration(
+                "aws:s3/bucket:Bucket",
+                "child",
+                vec![],
+                Some("urn:pulumi:test::test::my:index:Component::parent"),
+            ),
+        ];
+        let planned = PlannedResource::from_registrations("proj", "dev", &registrations);
+        assert_eq!(
+            planned[1].urn,
+            "urn:pulumi:dev::proj::my:index:Component$aws:s3/bucket:Bucket::child"
+        );
+    }
+
+    #[test]
+    fn test_input_hash_is_stable() {
+        let mut inputs = Map::new();
+        inputs.insert("b".into(), json!(1.0));
+        inputs.insert("a".into(), json!("x"));
+        assert_eq!(input_hash(&inputs), input_hash(&inputs.clone()));
+        assert_eq!(input_hash(&Map::new()), "08f44b07b5901a25");
+    }
+}
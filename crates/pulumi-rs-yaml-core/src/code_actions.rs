@@ -0,0 +1,769 @@
+//! Quick fixes for common template problems, for IDE support.
+//!
+//! [`code_actions`] checks a template's source against provider schemas and
+//! returns each problem it knows how to fix as a diagnostic together with
+//! the text edits that fix it:
+//!
+//! - a missing required property is added with a placeholder value,
+//! - a shorthand type token such as `aws:s3:Bucket` is replaced with the
+//!   canonical token from the schema,
+//! - a plaintext value of a secret property is wrapped in `fn::secret`,
+//! - a property that names another resource without referencing it gets
+//!   that resource added to `dependsOn`, so the two are created in order.
+//!
+//! The AST has no source positions, so the edits are placed from the
+//! [`crate::outline`] of the source. Parts of a template written in flow
+//! style get no fixes.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::expr::Expr;
+use crate::ast::parse::parse_template;
+use crate::ast::template::{ResourceEntry, ResourceProperties};
+use crate::ast::visitor::{walk_resource, AllRefsCollector};
+use crate::diag::Diagnostic;
+use crate::outline::{indent_of, Entry, Outline};
+use crate::schema::{PropertyInfo, ResourceTypeInfo, SchemaPropertyType, SchemaStore};
+
+/// A zero-based position in a template's source, the column counted in
+/// characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+/// A range of a template's source; `end` is exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// Replaces `range` with `new_text`. An empty range inserts the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: Range,
+    pub new_text: String,
+}
+
+/// What a code action does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeActionKind {
+    /// Adds a missing required property with a placeholder value.
+    AddRequiredProperty,
+    /// Replaces a shorthand type token with its canonical form.
+    CanonicalTypeToken,
+    /// Wraps a plaintext value of a secret property in `fn::secret`.
+    WrapInSecret,
+    /// Adds a resource to `dependsOn`.
+    AddDependsOn,
+}
+
+/// A fix for one problem in a template.
+#[derive(Debug, Clone)]
+pub struct CodeAction {
+    pub title: String,
+    pub kind: CodeActionKind,
+    /// The problem the action fixes.
+    pub diagnostic: Diagnostic,
+    /// Where the problem is.
+    pub range: Range,
+    /// The edits making the fix, which do not overlap.
+    pub edits: Vec<TextEdit>,
+}
+
+/// Returns the code actions for a template's source.
+pub fn code_actions(store: &SchemaStore, source: &str) -> Vec<CodeAction> {
+    let (template, _) = parse_template(source, None);
+    let outline = Outline::new(source);
+    let Some(resources) = outline.find(&outline.entries(None), "resources") else {
+        return Vec::new();
+    };
+    let declared = outline.entries(Some(&resources));
+
+    // Names a property may use for a resource: its logical name, and its
+    // physical name when that is a literal.
+    let mut names: HashMap<&str, &str> = HashMap::new();
+    for entry in &template.resources {
+        let logical = entry.logical_name.as_ref();
+        names.insert(logical, logical);
+        if let Some(name) = entry.resource.name.as_ref().and_then(Expr::as_str) {
+            names.entry(name).or_insert(logical);
+        }
+    }
+
+    let mut actions = Vec::new();
+    for entry in &template.resources {
+        let Some(declaration) = declared.iter().find(|d| d.key == entry.logical_name) else {
+            continue;
+        };
+        let fields = outline.entries(Some(declaration));
+        let Some(first) = fields.first() else {
+            continue;
+        };
+        let resource = ResourceFixer {
+            outline: &outline,
+            entry,
+            declaration,
+            step: first.indent - declaration.indent,
+            properties: outline.find(&fields, "properties"),
+            fields,
+        };
+        resource.canonical_type_token(store, &mut actions);
+        let canonical = store
+            .resolve_resource_token(&entry.resource.type_)
+            .map(|token| token.into_owned());
+        if let Some(info) = canonical.and_then(|token| store.lookup_resource(&token)) {
+            resource.missing_required_properties(info, &mut actions);
+            resource.plaintext_secrets(info, &mut actions);
+        }
+        resource.unreferenced_names(&names, &mut actions);
+    }
+    actions
+}
+
+/// Applies non-overlapping edits to `source`.
+pub fn apply_edits(source: &str, edits: &[TextEdit]) -> String {
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let offset = |p: Position| {
+        let start = line_starts.get(p.line).copied().unwrap_or(source.len());
+        let line = source[start..].lines().next().unwrap_or("");
+        start
+            + line
+                .char_indices()
+                .nth(p.character)
+                .map_or(line.len(), |(i, _)| i)
+    };
+    let mut replacements: Vec<(usize, usize, &str)> = edits
+        .iter()
+        .map(|e| {
+            (
+                offset(e.range.start),
+                offset(e.range.end),
+                e.new_text.as_str(),
+            )
+        })
+        .collect();
+    replacements.sort_by_key(|&(start, _, _)| std::cmp::Reverse(start));
+    let mut result = source.to_string();
+    for (start, end, text) in replacements {
+        result.replace_range(start..end, text);
+    }
+    result
+}
+
+impl Outline<'_> {
+    fn end_of_line(&self, line: usize) -> Position {
+        Position {
+            line,
+            character: self.lines[line].chars().count(),
+        }
+    }
+
+    /// An edit adding `new_lines` after line `after`.
+    fn insert_after(&self, after: usize, new_lines: &[String]) -> TextEdit {
+        let at = self.end_of_line(after);
+        TextEdit {
+            range: Range { start: at, end: at },
+            new_text: new_lines
+                .iter()
+                .flat_map(|line| [self.newline, line.as_str()])
+                .collect(),
+        }
+    }
+}
+
+impl Entry<'_> {
+    fn key_range(&self) -> Range {
+        Range {
+            start: Position {
+                line: self.line,
+                character: self.indent,
+            },
+            end: Position {
+                line: self.line,
+                character: self.colon_end - 1,
+            },
+        }
+    }
+
+    fn value_range(&self) -> Range {
+        Range {
+            start: Position {
+                line: self.line,
+                character: self.value_start,
+            },
+            end: Position {
+                line: self.line,
+                character: self.value_start + self.value.chars().count(),
+            },
+        }
+    }
+}
+
+/// Finds the fixable problems of one resource.
+struct ResourceFixer<'a, 's> {
+    outline: &'a Outline<'s>,
+    entry: &'a ResourceEntry<'s>,
+    declaration: &'a Entry<'s>,
+    /// The indentation added per nesting level.
+    step: usize,
+    fields: Vec<Entry<'s>>,
+    properties: Option<Entry<'s>>,
+}
+
+impl ResourceFixer<'_, '_> {
+    fn name(&self) -> &str {
+        &self.entry.logical_name
+    }
+
+    fn property_value(&self, key: &str) -> Option<&Expr<'_>> {
+        match &self.entry.resource.properties {
+            ResourceProperties::Map(props) => props.iter().find(|p| p.key == key).map(|p| &p.value),
+            ResourceProperties::Expr(_) => None,
+        }
+    }
+
+    fn canonical_type_token(&self, store: &SchemaStore, actions: &mut Vec<CodeAction>) {
+        let written = self.entry.resource.type_.as_ref();
+        let Some(canonical) = store.resolve_resource_token(written) else {
+            return;
+        };
+        let Some(type_) = self.outline.find(&self.fields, "type") else {
+            return;
+        };
+        if canonical == written || type_.value.trim_matches(['"', '\'']) != written {
+            return;
+        }
+        let mut range = type_.value_range();
+        if type_.value.starts_with(['"', '\'']) {
+            range.start.character += 1;
+            range.end.character -= 1;
+        }
+        actions.push(CodeAction {
+            title: format!("Use canonical type token '{}'", canonical),
+            kind: CodeActionKind::CanonicalTypeToken,
+            diagnostic: Diagnostic::warning(
+                None,
+                format!(
+                    "resource '{}' uses the shorthand type token '{}'",
+                    self.name(),
+                    written
+                ),
+                format!("the canonical token is '{}'", canonical),
+            ),
+            range,
+            edits: vec![TextEdit {
+                range,
+                new_text: canonical.into_owned(),
+            }],
+        });
+    }
+
+    fn missing_required_properties(&self, info: &ResourceTypeInfo, actions: &mut Vec<CodeAction>) {
+        if matches!(self.entry.resource.properties, ResourceProperties::Expr(_)) {
+            return;
+        }
+        let mut required: Vec<&String> = info.required_inputs.iter().collect();
+        required.sort();
+        for name in required {
+            let has_const = info
+                .property_types
+                .get(name)
+                .and_then(|p| p.const_value.as_ref())
+                .is_some();
+            if has_const || self.property_value(name).is_some() {
+                continue;
+            }
+            let line = format!(
+                "{}: {}",
+                name,
+                placeholder(info.input_property_types.get(name))
+            );
+            let edit = match &self.properties {
+                Some(properties) if !properties.value.is_empty() => continue,
+                Some(properties) => {
+                    let indent = self
+                        .outline
+                        .entries(Some(properties))
+                        .first()
+                        .map_or(properties.indent + self.step, |p| p.indent);
+                    self.outline
+                        .insert_after(properties.last_line, &[format!("{:indent$}{}", "", line)])
+                }
+                None => {
+                    let indent = self.declaration.indent + self.step;
+                    self.outline.insert_after(
+                        self.declaration.last_line,
+                        &[
+                            format!("{:indent$}properties:", ""),
+                            format!("{:width$}{}", "", line, width = indent + self.step),
+                        ],
+                    )
+                }
+            };
+            actions.push(CodeAction {
+                title: format!("Add required property '{}'", name),
+                kind: CodeActionKind::AddRequiredProperty,
+                diagnostic: Diagnostic::warning(
+                    None,
+                    format!(
+                        "missing required property '{}' on resource '{}'",
+                        name,
+                        self.name()
+                    ),
+                    format!(
+                        "resource type '{}' requires property '{}'",
+                        self.entry.resource.type_, name
+                    ),
+                ),
+                range: self.declaration.key_range(),
+                edits: vec![edit],
+            });
+        }
+    }
+
+    fn plaintext_secrets(&self, info: &ResourceTypeInfo, actions: &mut Vec<CodeAction>) {
+        let Some(properties) = &self.properties else {
+            return;
+        };
+        for property in self.outline.entries(Some(properties)) {
+            let secret = info
+                .input_property_types
+                .get(property.key)
+                .is_some_and(|p| p.secret);
+            let literal = matches!(
+                self.property_value(property.key),
+                Some(Expr::String(..) | Expr::Number(..) | Expr::Bool(..))
+            );
+            if !secret
+                || !literal
+                || property.value.is_empty()
+                || property.value.starts_with(['|', '>'])
+            {
+                continue;
+            }
+            let range = property.value_range();
+            let replaced = Range {
+                start: Position {
+                    line: property.line,
+                    character: property.colon_end,
+                },
+                end: range.end,
+            };
+            actions.push(CodeAction {
+                title: "Wrap value in fn::secret".to_string(),
+                kind: CodeActionKind::WrapInSecret,
+                diagnostic: Diagnostic::warning(
+                    None,
+                    format!(
+                        "secret property '{}' on resource '{}' has a plaintext value",
+                        property.key,
+                        self.name()
+                    ),
+                    "mark the value with fn::secret, or read it from secret config",
+                ),
+                range,
+                edits: vec![TextEdit {
+                    range: replaced,
+                    new_text: format!(
+                        "{}{:indent$}fn::secret: {}",
+                        self.outline.newline,
+                        "",
+                        property.value,
+                        indent = property.indent + self.step
+                    ),
+                }],
+            });
+        }
+    }
+
+    /// Properties whose value is another resource's name, written as a
+    /// plain string. Without a reference the two resources are unordered.
+    fn unreferenced_names(&self, names: &HashMap<&str, &str>, actions: &mut Vec<CodeAction>) {
+        let (Some(properties), ResourceProperties::Map(props)) =
+            (&self.properties, &self.entry.resource.properties)
+        else {
+            return;
+        };
+        let mut refs = HashSet::new();
+        walk_resource(&self.entry.resource, &AllRefsCollector, &mut refs);
+        let mut reported = HashSet::new();
+        let entries = self.outline.entries(Some(properties));
+        for prop in props {
+            let values = match &prop.value {
+                Expr::List(_, items) => items.iter().collect(),
+                value => vec![value],
+            };
+            for value in values.into_iter().filter_map(Expr::as_str) {
+                let Some(&target) = names.get(value) else {
+                    continue;
+                };
+                if target == self.name() || refs.contains(target) || !reported.insert(target) {
+                    continue;
+                }
+                let Some(property) = entries.iter().find(|e| e.key == prop.key) else {
+                    continue;
+                };
+                let Some(edit) = self.depends_on_edit(target) else {
+                    continue;
+                };
+                actions.push(CodeAction {
+                    title: format!("Add '{}' to dependsOn", target),
+                    kind: CodeActionKind::AddDependsOn,
+                    diagnostic: Diagnostic::warning(
+                        None,
+                        format!(
+                            "property '{}' of resource '{}' names resource '{}' without referencing it",
+                            prop.key,
+                            self.name(),
+                            target
+                        ),
+                        format!(
+                            "'{}' may be created before '{}'; use ${{{}}} or add it to dependsOn",
+                            self.name(),
+                            target,
+                            target
+                        ),
+                    ),
+                    range: property.key_range(),
+                    edits: vec![edit],
+                });
+            }
+        }
+    }
+
+    fn depends_on_edit(&self, target: &str) -> Option<TextEdit> {
+        let outline = self.outline;
+        let reference = format!("${{{}}}", target);
+        let Some(options) = outline.find(&self.fields, "options") else {
+            let indent = self.declaration.indent + self.step;
+            return Some(outline.insert_after(
+                self.declaration.last_line,
+                &[
+                    format!("{:indent$}options:", ""),
+                    format!("{:width$}dependsOn:", "", width = indent + self.step),
+                    format!(
+                        "{:width$}- {}",
+                        "",
+                        reference,
+                        width = indent + 2 * self.step
+                    ),
+                ],
+            ));
+        };
+        if !options.value.is_empty() {
+            return None;
+        }
+        let fields = outline.entries(Some(&options));
+        let Some(depends_on) = outline.find(&fields, "dependsOn") else {
+            let indent = fields
+                .first()
+                .map_or(options.indent + self.step, |f| f.indent);
+            return Some(outline.insert_after(
+                options.last_line,
+                &[
+                    format!("{:indent$}dependsOn:", ""),
+                    format!("{:width$}- {}", "", reference, width = indent + self.step),
+                ],
+            ));
+        };
+
+        if depends_on.value.is_empty() {
+            // A block list: add an item like the existing ones.
+            let indent = (depends_on.line + 1..=depends_on.last_line)
+                .map(|n| outline.lines[n])
+                .find(|text| text.trim_start().starts_with('-'))
+                .map_or(depends_on.indent + self.step, indent_of);
+            return Some(outline.insert_after(
+                depends_on.last_line,
+                &[format!("{:indent$}- {}", "", reference)],
+            ));
+        }
+        let items = depends_on.value.strip_prefix('[')?.strip_suffix(']')?;
+        // `{` cannot appear in a plain scalar inside a flow list.
+        let mut at = depends_on.value_range().end;
+        at.character -= 1;
+        Some(TextEdit {
+            range: Range { start: at, end: at },
+            new_text: if items.trim().is_empty() {
+                format!("\"{}\"", reference)
+            } else {
+                format!(", \"{}\"", reference)
+            },
+        })
+    }
+}
+
+/// A placeholder value for a required property: the first allowed value of
+/// an enum, or the empty value of the property's type.
+fn placeholder(prop: Option<&PropertyInfo>) -> String {
+    let Some(prop) = prop else {
+        return "\"\"".to_string();
+    };
+    if let Some(value) = prop
+        .enum_values
+        .iter()
+        .find(|v| v.deprecation_message.is_none())
+    {
+        return value.value.to_string();
+    }
+    match prop.type_ {
+        SchemaPropertyType::Number | SchemaPropertyType::Integer => "0",
+        SchemaPropertyType::Boolean => "false",
+        SchemaPropertyType::Array(_) => "[]",
+        SchemaPropertyType::Object => "{}",
+        SchemaPropertyType::String
+        | SchemaPropertyType::Asset
+        | SchemaPropertyType::Archive
+        | SchemaPropertyType::Unknown => "\"\"",
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::template::TemplateDecl;
+    use crate::schema::{EnumValue, PackageSchema};
+
+    fn prop(type_: SchemaPropertyType, secret: bool, required: bool) -> PropertyInfo {
+        PropertyInfo {
+            type_,
+            secret,
+            const_value: None,
+            required,
+            enum_values: Vec::new(),
+        }
+    }
+
+    fn store() -> SchemaStore {
+        let mut info = ResourceTypeInfo::default();
+        let mut acl = prop(SchemaPropertyType::String, false, true);
+        acl.enum_values = vec![
+            EnumValue {
+                value: serde_json::json!("authenticated"),
+                description: None,
+                deprecation_message: Some("Use private.".to_string()),
+            },
+            EnumValue {
+                value: serde_json::json!("private"),
+                description: None,
+                deprecation_message: None,
+            },
+        ];
+        for (name, info_) in [
+            ("bucketName", prop(SchemaPropertyType::String, false, true)),
+            ("acl", acl),
+            ("password", prop(SchemaPropertyType::String, true, false)),
+            ("target", prop(SchemaPropertyType::String, false, false)),
+        ] {
+            info.input_properties.insert(name.to_string());
+            if info_.required {
+                info.required_inputs.insert(name.to_string());
+            }
+            info.input_property_types.insert(name.to_string(), info_);
+        }
+        let mut store = SchemaStore::new();
+        store.insert(PackageSchema {
+            name: "test".to_string(),
+            version: "1.0.0".to_string(),
+            resources: [("test:index/bucket:Bucket".to_string(), info)]
+                .into_iter()
+                .collect(),
+            functions: HashMap::new(),
+        });
+        store
+    }
+
+    const SOURCE: &str = "\
+resources:
+  logs:
+    type: \"test:Bucket\"
+    properties:
+      bucketName: logs
+      acl: private
+  app:
+    type: test:index/bucket:Bucket
+    properties:
+      bucketName: app # the app's bucket
+      acl: private
+      password: hunter2
+      target: logs
+  empty:
+    type: test:index/bucket:Bucket
+    options:
+      protect: true
+";
+
+    fn fix(source: &str, kind: CodeActionKind) -> String {
+        let actions = code_actions(&store(), source);
+        let action = actions
+            .iter()
+            .find(|a| a.kind == kind)
+            .unwrap_or_else(|| panic!("no {:?} action", kind));
+        apply_edits(source, &action.edits)
+    }
+
+    fn parsed(source: &str, check: impl FnOnce(&TemplateDecl<'_>)) {
+        let (template, diags) = parse_template(source, None);
+        assert!(!diags.has_errors(), "{}\n{}", diags, source);
+        check(&template);
+    }
+
+    fn resource<'a>(template: &'a TemplateDecl<'a>, name: &str) -> &'a ResourceEntry<'a> {
+        template
+            .resources
+            .iter()
+            .find(|r| r.logical_name == name)
+            .unwrap()
+    }
+
+    fn property<'a>(entry: &'a ResourceEntry<'a>, key: &str) -> Option<&'a Expr<'a>> {
+        match &entry.resource.properties {
+            ResourceProperties::Map(props) => props.iter().find(|p| p.key == key).map(|p| &p.value),
+            ResourceProperties::Expr(_) => None,
+        }
+    }
+
+    #[test]
+    fn test_code_actions_found() {
+        let actions = code_actions(&store(), SOURCE);
+        let found: Vec<(CodeActionKind, &str)> = actions
+            .iter()
+            .map(|a| (a.kind, a.diagnostic.summary.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    CodeActionKind::CanonicalTypeToken,
+                    "resource 'logs' uses the shorthand type token 'test:Bucket'"
+                ),
+                (
+                    CodeActionKind::WrapInSecret,
+                    "secret property 'password' on resource 'app' has a plaintext value"
+                ),
+                (
+                    CodeActionKind::AddDependsOn,
+                    "property 'target' of resource 'app' names resource 'logs' without referencing it"
+                ),
+                (
+                    CodeActionKind::AddRequiredProperty,
+                    "missing required property 'acl' on resource 'empty'"
+                ),
+                (
+                    CodeActionKind::AddRequiredProperty,
+                    "missing required property 'bucketName' on resource 'empty'"
+                ),
+            ]
+        );
+        let depends_on = &actions[2];
+        assert_eq!(
+            depends_on.range.start,
+            Position {
+                line: 12,
+                character: 6
+            }
+        );
+        assert_eq!(
+            depends_on.range.end,
+            Position {
+                line: 12,
+                character: 12
+            }
+        );
+    }
+
+    #[test]
+    fn test_canonical_type_token() {
+        let fixed = fix(SOURCE, CodeActionKind::CanonicalTypeToken);
+        assert!(fixed.contains("    type: \"test:index/bucket:Bucket\"\n"));
+        parsed(&fixed, |t| {
+            assert_eq!(
+                resource(t, "logs").resource.type_,
+                "test:index/bucket:Bucket"
+            );
+        });
+    }
+
+    #[test]
+    fn test_wrap_in_secret() {
+        let fixed = fix(SOURCE, CodeActionKind::WrapInSecret);
+        assert!(fixed.contains("      password:\n        fn::secret: hunter2\n"));
+        parsed(&fixed, |t| {
+            assert!(matches!(
+                property(resource(t, "app"), "password"),
+                Some(Expr::Secret(..))
+            ));
+        });
+    }
+
+    #[test]
+    fn test_add_required_property() {
+        let fixed = fix(SOURCE, CodeActionKind::AddRequiredProperty);
+        // Deprecated enum values are not used as placeholders.
+        assert!(fixed.ends_with("      protect: true\n    properties:\n      acl: \"private\"\n"));
+        parsed(&fixed, |t| {
+            let acl = property(resource(t, "empty"), "acl").and_then(Expr::as_str);
+            assert_eq!(acl, Some("private"));
+        });
+
+        let source = SOURCE.replace("      bucketName: logs\n", "");
+        let fixed = fix(&source, CodeActionKind::AddRequiredProperty);
+        assert!(fixed.contains("      acl: private\n      bucketName: \"\"\n  app:"));
+    }
+
+    #[test]
+    fn test_add_depends_on() {
+        let fixed = fix(SOURCE, CodeActionKind::AddDependsOn);
+        assert!(fixed
+            .contains("      target: logs\n    options:\n      dependsOn:\n        - ${logs}\n"));
+        parsed(&fixed, |t| {
+            assert!(resource(t, "app").resource.options.depends_on.is_some());
+        });
+
+        // Existing options, dependsOn lists in block and flow style.
+        for (options, expected) in [
+            (
+                "    options:\n      protect: true\n",
+                "      protect: true\n      dependsOn:\n        - ${logs}\n",
+            ),
+            (
+                "    options:\n      dependsOn:\n      - ${empty}\n",
+                "      - ${empty}\n      - ${logs}\n",
+            ),
+            (
+                "    options:\n      dependsOn: [\"${empty}\"]\n",
+                "dependsOn: [\"${empty}\", \"${logs}\"]\n",
+            ),
+            (
+                "    options:\n      dependsOn: []\n",
+                "dependsOn: [\"${logs}\"]\n",
+            ),
+        ] {
+            let source = SOURCE.replace("  empty:\n", &format!("{}  empty:\n", options));
+            let fixed = fix(&source, CodeActionKind::AddDependsOn);
+            assert!(fixed.contains(expected), "{}", fixed);
+            parsed(&fixed, |_| {});
+        }
+
+        // A reference already orders the resources.
+        let source = SOURCE.replace("target: logs", "target: logs\n      region: ${logs.region}");
+        let actions = code_actions(&store(), &source);
+        assert!(actions
+            .iter()
+            .all(|a| a.kind != CodeActionKind::AddDependsOn));
+    }
+
+    #[test]
+    fn test_edits_keep_crlf() {
+        let source = SOURCE.replace('\n', "\r\n");
+        let fixed = fix(&source, CodeActionKind::WrapInSecret);
+        assert!(fixed.contains("      password:\r\n        fn::secret: hunter2\r\n"));
+        assert!(!fixed.replace("\r\n", "").contains('\n'));
+    }
+}
@@ -1,21 +1,79 @@
 //! Schema-driven completion API for IDE support.
 //!
-//! Provides completion items for resource properties based on provider schemas.
+//! Provides completion items for resource properties based on provider
+//! schemas, resource option keys, and the allowed values of enum-typed
+//! properties. [`complete_at`] picks among them from the cursor position in
+//! a template's source.
 
-use crate::schema::SchemaStore;
+use std::borrow::Cow;
 
-/// A single completion item for a resource property.
+use crate::outline::{indent_of, key_of};
+use crate::schema::{EnumValue, SchemaStore};
+
+/// What a completion item inserts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    /// A resource property key.
+    Property,
+    /// A key under a resource's `options:`.
+    Option,
+    /// An allowed value of an enum-typed property.
+    EnumValue,
+}
+
+/// A single completion item.
 pub struct CompletionItem<'a> {
-    /// Property name.
-    pub name: &'a str,
+    /// Property or option name, or the enum value as written in YAML.
+    pub name: Cow<'a, str>,
     /// Type label (e.g. "string", "integer", "array").
     pub type_label: &'a str,
     /// Whether this property is required.
     pub required: bool,
     /// Whether this property is secret.
     pub secret: bool,
+    pub kind: CompletionKind,
+    /// Description of an enum value, from the schema.
+    pub description: Option<&'a str>,
+    /// Set when the enum value is deprecated.
+    pub deprecation_message: Option<&'a str>,
+}
+
+/// Where in a template the cursor is, as far as completion is concerned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompletionContext {
+    /// A key under a resource's `properties:`.
+    PropertyKey { resource_type: String },
+    /// The value of one of a resource's properties.
+    PropertyValue {
+        resource_type: String,
+        property: String,
+    },
+    /// A key under a resource's `options:`.
+    OptionKey,
 }
 
+/// Resource option keys and their type hints.
+const RESOURCE_OPTIONS: &[(&str, &str)] = &[
+    ("additionalSecretOutputs", "list(string)"),
+    ("aliases", "list(string | alias)"),
+    ("customTimeouts", "{create, update, delete}"),
+    ("deleteBeforeReplace", "boolean"),
+    ("deletedWith", "resource"),
+    ("dependsOn", "list(resource)"),
+    ("hideDiffs", "list(string)"),
+    ("ignoreChanges", "list(string)"),
+    ("import", "string"),
+    ("parent", "resource"),
+    ("pluginDownloadURL", "string"),
+    ("protect", "boolean"),
+    ("provider", "resource"),
+    ("providers", "map(resource)"),
+    ("replaceOnChanges", "list(string)"),
+    ("replaceWith", "list(resource)"),
+    ("retainOnDelete", "boolean"),
+    ("version", "string"),
+];
+
 /// Returns completion items for a resource type's input properties.
 ///
 /// Used by IDE integrations (e.g. Python bindings) to provide autocomplete
@@ -32,18 +90,177 @@ pub fn complete_resource_properties<'a>(
         .input_property_types
         .iter()
         .map(|(name, prop)| CompletionItem {
-            name: name.as_str(),
+            name: Cow::Borrowed(name.as_str()),
             type_label: prop.type_.label(),
             required: prop.required,
             secret: prop.secret,
+            kind: CompletionKind::Property,
+            description: None,
+            deprecation_message: None,
         })
         .collect();
 
     // Sort: required first, then alphabetical
-    items.sort_by(|a, b| b.required.cmp(&a.required).then(a.name.cmp(b.name)));
+    items.sort_by(|a, b| b.required.cmp(&a.required).then(a.name.cmp(&b.name)));
+    items
+}
+
+/// Returns completion items for the keys of a resource's `options:`, with
+/// their type hints as the type label.
+pub fn complete_resource_options() -> Vec<CompletionItem<'static>> {
+    RESOURCE_OPTIONS
+        .iter()
+        .map(|&(name, type_label)| CompletionItem {
+            name: Cow::Borrowed(name),
+            type_label,
+            required: false,
+            secret: false,
+            kind: CompletionKind::Option,
+            description: None,
+            deprecation_message: None,
+        })
+        .collect()
+}
+
+/// Returns the allowed values of an enum-typed input property, in schema
+/// order, with deprecated values last.
+pub fn complete_enum_values<'a>(
+    store: &'a SchemaStore,
+    resource_type: &str,
+    property: &str,
+) -> Vec<CompletionItem<'a>> {
+    let Some(prop) = store
+        .lookup_resource(resource_type)
+        .and_then(|info| info.input_property_types.get(property))
+    else {
+        return Vec::new();
+    };
+
+    let mut items: Vec<CompletionItem<'a>> = prop
+        .enum_values
+        .iter()
+        .map(|v| CompletionItem {
+            name: enum_label(v),
+            type_label: prop.type_.label(),
+            required: false,
+            secret: prop.secret,
+            kind: CompletionKind::EnumValue,
+            description: v.description.as_deref(),
+            deprecation_message: v.deprecation_message.as_deref(),
+        })
+        .collect();
+    items.sort_by_key(|item| item.deprecation_message.is_some());
     items
 }
 
+/// Returns completion items for the cursor at `line` and `column` (both
+/// zero-based, the column counted in characters) of a template's source.
+pub fn complete_at<'a>(
+    store: &'a SchemaStore,
+    source: &str,
+    line: usize,
+    column: usize,
+) -> Vec<CompletionItem<'a>> {
+    let resolve = |token: &str| {
+        store
+            .resolve_resource_token(token)
+            .map(Cow::into_owned)
+            .unwrap_or_else(|| crate::packages::canonicalize_type_token(token))
+    };
+    match completion_context(source, line, column) {
+        Some(CompletionContext::OptionKey) => complete_resource_options(),
+        Some(CompletionContext::PropertyKey { resource_type }) => {
+            complete_resource_properties(store, &resolve(&resource_type))
+        }
+        Some(CompletionContext::PropertyValue {
+            resource_type,
+            property,
+        }) => complete_enum_values(store, &resolve(&resource_type), &property),
+        None => Vec::new(),
+    }
+}
+
+/// Works out what is being completed at `line` and `column` from the
+/// indentation of the lines above, without parsing the (often incomplete)
+/// template.
+pub fn completion_context(source: &str, line: usize, column: usize) -> Option<CompletionContext> {
+    let lines: Vec<&str> = source.lines().collect();
+    let current = lines.get(line).copied().unwrap_or("");
+    let prefix: String = current.chars().take(column).collect();
+
+    // On `key: ` the cursor is in the value of `key`; otherwise it is
+    // writing a key at the prefix's indentation.
+    let value_of = key_of(&prefix);
+    let ancestors = ancestors(&lines[..line.min(lines.len())], indent_of(&prefix));
+    let names: Vec<&str> = ancestors.iter().map(|&(_, key)| key).collect();
+    let resource_type = |at: usize| resource_type(&lines, ancestors[at].0);
+    match (value_of, names.as_slice()) {
+        (None, ["options", _, "resources"]) => Some(CompletionContext::OptionKey),
+        (None, ["properties", _, "resources"]) => Some(CompletionContext::PropertyKey {
+            resource_type: resource_type(1)?,
+        }),
+        (Some(property), ["properties", _, "resources"]) => {
+            Some(CompletionContext::PropertyValue {
+                resource_type: resource_type(1)?,
+                property: property.to_string(),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// The keys enclosing a line indented by `indent`, innermost first, with
+/// their line numbers.
+fn ancestors<'s>(lines: &[&'s str], mut indent: usize) -> Vec<(usize, &'s str)> {
+    let mut keys = Vec::new();
+    for (n, text) in lines.iter().enumerate().rev() {
+        if indent == 0 {
+            break;
+        }
+        let trimmed = text.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') || indent_of(text) >= indent {
+            continue;
+        }
+        indent = indent_of(text);
+        match key_of(text) {
+            Some(key) => keys.push((n, key)),
+            None => break,
+        }
+    }
+    keys
+}
+
+/// The `type:` of the resource declared at line `at`.
+fn resource_type(lines: &[&str], at: usize) -> Option<String> {
+    let indent = indent_of(lines[at]);
+    let mut child_indent = None;
+    for text in &lines[at + 1..] {
+        let trimmed = text.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let this = indent_of(text);
+        if this <= indent {
+            break;
+        }
+        if *child_indent.get_or_insert(this) != this || key_of(text) != Some("type") {
+            continue;
+        }
+        let value = trimmed["type:".len()..].trim();
+        let value = value.trim_matches(|c| c == '"' || c == '\'');
+        return Some(value.to_string()).filter(|v| !v.is_empty());
+    }
+    None
+}
+
+/// How an enum value is written in YAML.
+fn enum_label(v: &EnumValue) -> Cow<'_, str> {
+    match &v.value {
+        serde_json::Value::String(s) => Cow::Borrowed(s.as_str()),
+        other => Cow::Owned(other.to_string()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -61,6 +278,7 @@ mod tests {
                 secret: false,
                 const_value: None,
                 required: true,
+                enum_values: Vec::new(),
             },
         );
         info.input_property_types.insert(
@@ -70,6 +288,7 @@ mod tests {
                 secret: false,
                 const_value: None,
                 required: false,
+                enum_values: Vec::new(),
             },
         );
         info.input_property_types.insert(
@@ -79,6 +298,7 @@ mod tests {
                 secret: true,
                 const_value: None,
                 required: true,
+                enum_values: Vec::new(),
             },
         );
 
@@ -106,4 +326,100 @@ mod tests {
         let items = complete_resource_properties(&store, "missing:index/res:Res");
         assert!(items.is_empty());
     }
+
+    fn enum_store() -> SchemaStore {
+        let mut info = ResourceTypeInfo::default();
+        info.input_property_types.insert(
+            "acl".to_string(),
+            PropertyInfo {
+                type_: SchemaPropertyType::String,
+                secret: false,
+                const_value: None,
+                required: false,
+                enum_values: vec![
+                    EnumValue {
+                        value: serde_json::json!("authenticated"),
+                        description: None,
+                        deprecation_message: Some("Use private.".to_string()),
+                    },
+                    EnumValue {
+                        value: serde_json::json!("private"),
+                        description: Some("Owner only.".to_string()),
+                        deprecation_message: None,
+                    },
+                ],
+            },
+        );
+        let mut store = SchemaStore::new();
+        store.insert(PackageSchema {
+            name: "test".to_string(),
+            version: "1.0.0".to_string(),
+            resources: [("test:index/bucket:Bucket".to_string(), info)]
+                .into_iter()
+                .collect(),
+            functions: HashMap::new(),
+        });
+        store
+    }
+
+    const SOURCE: &str = "\
+resources:
+  bucket:
+    type: test:Bucket
+    properties:
+      acl: pr
+      # a comment
+
+    options:
+      prot
+";
+
+    #[test]
+    fn test_completion_context() {
+        assert_eq!(
+            completion_context(SOURCE, 4, 6),
+            Some(CompletionContext::PropertyKey {
+                resource_type: "test:Bucket".to_string()
+            })
+        );
+        assert_eq!(
+            completion_context(SOURCE, 4, 12),
+            Some(CompletionContext::PropertyValue {
+                resource_type: "test:Bucket".to_string(),
+                property: "acl".to_string(),
+            })
+        );
+        assert_eq!(
+            completion_context(SOURCE, 8, 10),
+            Some(CompletionContext::OptionKey)
+        );
+        assert_eq!(completion_context(SOURCE, 2, 4), None);
+        assert_eq!(completion_context(SOURCE, 0, 0), None);
+    }
+
+    #[test]
+    fn test_complete_resource_options() {
+        let store = SchemaStore::new();
+        let items = complete_at(&store, SOURCE, 8, 10);
+        assert_eq!(items.len(), RESOURCE_OPTIONS.len());
+        let protect = items.iter().find(|i| i.name == "protect").unwrap();
+        assert_eq!(protect.type_label, "boolean");
+        assert_eq!(protect.kind, CompletionKind::Option);
+    }
+
+    #[test]
+    fn test_complete_enum_values() {
+        let store = enum_store();
+        let items = complete_at(&store, SOURCE, 4, 12);
+        let names: Vec<&str> = items.iter().map(|i| i.name.as_ref()).collect();
+        // Deprecated values sort last.
+        assert_eq!(names, vec!["private", "authenticated"]);
+        assert_eq!(items[0].kind, CompletionKind::EnumValue);
+        assert_eq!(items[0].description, Some("Owner only."));
+        assert_eq!(items[1].deprecation_message, Some("Use private."));
+
+        let keys = complete_at(&store, SOURCE, 4, 6);
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].name, "acl");
+    }
 }
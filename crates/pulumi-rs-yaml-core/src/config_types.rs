@@ -3,7 +3,9 @@ use std::fmt;
 /// Represents the type of a configuration parameter.
 ///
 /// Matches the Go `config.Type` interface with known config types:
-/// String, Number, Int, Boolean, Object, and List variants of each.
+/// String, Number, Int, Boolean, Object, and List variants of each. Duration
+/// and Bytes are extensions: they accept strings with units (`30s`, `5Gi`)
+/// and resolve to a number of seconds or bytes.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum ConfigType {
     String,
@@ -11,6 +13,8 @@ pub enum ConfigType {
     Int,
     Boolean,
     Object,
+    Duration,
+    Bytes,
     StringList,
     NumberList,
     IntList,
@@ -45,6 +49,8 @@ impl ConfigType {
             "int" | "integer" => Some(ConfigType::Int),
             "boolean" | "bool" => Some(ConfigType::Boolean),
             "object" => Some(ConfigType::Object),
+            "duration" => Some(ConfigType::Duration),
+            "bytes" => Some(ConfigType::Bytes),
             _ => None,
         }
     }
@@ -58,6 +64,8 @@ impl ConfigType {
                 | ConfigType::Int
                 | ConfigType::Boolean
                 | ConfigType::Object
+                | ConfigType::Duration
+                | ConfigType::Bytes
         )
     }
 
@@ -99,6 +107,8 @@ impl fmt::Display for ConfigType {
             ConfigType::Int => write!(f, "Int"),
             ConfigType::Boolean => write!(f, "Boolean"),
             ConfigType::Object => write!(f, "Object"),
+            ConfigType::Duration => write!(f, "Duration"),
+            ConfigType::Bytes => write!(f, "Bytes"),
             ConfigType::StringList => write!(f, "List<String>"),
             ConfigType::NumberList => write!(f, "List<Number>"),
             ConfigType::IntList => write!(f, "List<Int>"),
@@ -115,6 +125,8 @@ pub const PRIMITIVES: &[ConfigType] = &[
     ConfigType::Int,
     ConfigType::Boolean,
     ConfigType::Object,
+    ConfigType::Duration,
+    ConfigType::Bytes,
 ];
 
 /// All known config types (primitives + list variants).
@@ -124,6 +136,8 @@ pub const CONFIG_TYPES: &[ConfigType] = &[
     ConfigType::Int,
     ConfigType::Boolean,
     ConfigType::Object,
+    ConfigType::Duration,
+    ConfigType::Bytes,
     ConfigType::StringList,
     ConfigType::NumberList,
     ConfigType::IntList,
@@ -131,6 +145,154 @@ pub const CONFIG_TYPES: &[ConfigType] = &[
     ConfigType::ObjectList,
 ];
 
+/// Duration units and their length in seconds, longest suffix first.
+const DURATION_UNITS: &[(&str, f64)] = &[
+    ("ns", 1e-9),
+    ("us", 1e-6),
+    ("µs", 1e-6),
+    ("ms", 1e-3),
+    ("s", 1.0),
+    ("m", 60.0),
+    ("h", 3600.0),
+    ("d", 86400.0),
+];
+
+/// Byte size suffixes and their multipliers: SI (`k`, `M`, `G`, ...) and
+/// binary (`Ki`, `Mi`, `Gi`, ...), each optionally followed by `B`.
+const BYTE_UNITS: &[(&str, f64)] = &[
+    ("Ki", 1024.0),
+    ("Mi", 1048576.0),
+    ("Gi", 1073741824.0),
+    ("Ti", 1099511627776.0),
+    ("Pi", 1125899906842624.0),
+    ("Ei", 1152921504606846976.0),
+    ("k", 1e3),
+    ("K", 1e3),
+    ("M", 1e6),
+    ("G", 1e9),
+    ("T", 1e12),
+    ("P", 1e15),
+    ("E", 1e18),
+];
+
+/// Parses a duration such as `30s`, `1h30m`, or `1.5d` into seconds.
+pub fn parse_duration(s: &str) -> Result<f64, String> {
+    let mut rest = s.trim();
+    if rest.is_empty() {
+        return Err("empty duration".to_string());
+    }
+    let mut seconds = 0.0;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let amount: f64 = rest[..digits]
+            .parse()
+            .map_err(|_| format!("expected a number at '{}'", rest))?;
+        rest = &rest[digits..];
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        let unit = &rest[..unit_len];
+        let (_, scale) =
+            DURATION_UNITS
+                .iter()
+                .find(|(u, _)| *u == unit)
+                .ok_or_else(|| match unit {
+                    "" => "missing unit; use one of ns, us, ms, s, m, h, d".to_string(),
+                    _ => format!("unknown unit '{}'; use one of ns, us, ms, s, m, h, d", unit),
+                })?;
+        seconds += amount * scale;
+        rest = &rest[unit_len..];
+    }
+    Ok(seconds)
+}
+
+/// Formats seconds as a duration such as `1h30m` or `500ms`.
+pub fn format_duration(seconds: f64) -> String {
+    if seconds == 0.0 {
+        return "0s".to_string();
+    }
+    let sign = if seconds < 0.0 { "-" } else { "" };
+    let seconds = seconds.abs();
+    if seconds < 1.0 {
+        return format!("{}{}ms", sign, trim_number(seconds * 1e3));
+    }
+    let hours = (seconds / 3600.0).floor();
+    let minutes = ((seconds - hours * 3600.0) / 60.0).floor();
+    let secs = seconds - hours * 3600.0 - minutes * 60.0;
+    let mut out = sign.to_string();
+    if hours > 0.0 {
+        out.push_str(&format!("{}h", hours));
+    }
+    if minutes > 0.0 {
+        out.push_str(&format!("{}m", minutes));
+    }
+    if secs > 0.0 {
+        out.push_str(&format!("{}s", trim_number(secs)));
+    }
+    out
+}
+
+/// Parses a byte size such as `512`, `100MB`, or `5Gi` into bytes.
+pub fn parse_bytes(s: &str) -> Result<f64, String> {
+    let trimmed = s.trim();
+    let digits = trimmed
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(trimmed.len());
+    let amount: f64 = trimmed[..digits]
+        .parse()
+        .map_err(|_| format!("expected a number at '{}'", trimmed))?;
+    let suffix = &trimmed[digits..];
+    let unit = suffix.strip_suffix('B').unwrap_or(suffix);
+    let scale = if unit.is_empty() {
+        1.0
+    } else {
+        BYTE_UNITS
+            .iter()
+            .find(|(u, _)| *u == unit)
+            .map(|(_, scale)| *scale)
+            .ok_or_else(|| {
+                format!(
+                    "unknown unit '{}'; use k, M, G, T, P, E or Ki, Mi, Gi, Ti, Pi, Ei",
+                    suffix
+                )
+            })?
+    };
+    let bytes = amount * scale;
+    if bytes.fract() != 0.0 {
+        return Err(format!("{} is not a whole number of bytes", bytes));
+    }
+    Ok(bytes)
+}
+
+/// Formats bytes with the largest unit that divides them exactly, preferring
+/// binary units: 5368709120 is `5Gi`, 2000000 is `2M`, 1500 is `1500`.
+pub fn format_bytes(bytes: f64) -> String {
+    if bytes != 0.0 && bytes.fract() == 0.0 {
+        let binary = BYTE_UNITS.iter().filter(|(u, _)| u.ends_with('i')).rev();
+        let si = BYTE_UNITS
+            .iter()
+            .filter(|(u, _)| !u.ends_with('i') && *u != "K")
+            .rev();
+        for (unit, scale) in binary.chain(si) {
+            if (bytes / scale).fract() == 0.0 {
+                return format!("{}{}", bytes / scale, unit);
+            }
+        }
+    }
+    trim_number(bytes)
+}
+
+/// Formats a number without a trailing `.0`.
+fn trim_number(n: f64) -> String {
+    if n.fract() == 0.0 {
+        format!("{}", n as i64)
+    } else {
+        format!("{}", n)
+    }
+}
+
 /// Attempts to infer the config type of a JSON/YAML value.
 #[derive(Debug)]
 pub enum TypeInferenceError {
@@ -264,6 +426,50 @@ mod tests {
         assert_eq!(ConfigType::StringList.as_list(), None);
     }
 
+    #[test]
+    fn test_parse_duration_and_bytes_types() {
+        assert_eq!(ConfigType::parse("Duration"), Some(ConfigType::Duration));
+        assert_eq!(ConfigType::parse("bytes"), Some(ConfigType::Bytes));
+        assert!(ConfigType::Duration.is_primitive());
+        assert_eq!(ConfigType::Bytes.as_list(), None);
+    }
+
+    #[test]
+    fn test_parse_and_format_duration() {
+        assert_eq!(parse_duration("30s"), Ok(30.0));
+        assert_eq!(parse_duration("1h30m"), Ok(5400.0));
+        assert_eq!(parse_duration("1.5d"), Ok(129600.0));
+        assert_eq!(parse_duration("250ms"), Ok(0.25));
+        assert!(parse_duration("30").unwrap_err().contains("missing unit"));
+        assert!(parse_duration("5y")
+            .unwrap_err()
+            .contains("unknown unit 'y'"));
+        assert!(parse_duration("").is_err());
+
+        assert_eq!(format_duration(5400.0), "1h30m");
+        assert_eq!(format_duration(90061.5), "25h1m1.5s");
+        assert_eq!(format_duration(0.25), "250ms");
+        assert_eq!(format_duration(0.0), "0s");
+    }
+
+    #[test]
+    fn test_parse_and_format_bytes() {
+        assert_eq!(parse_bytes("512"), Ok(512.0));
+        assert_eq!(parse_bytes("5Gi"), Ok(5368709120.0));
+        assert_eq!(parse_bytes("100MB"), Ok(1e8));
+        assert_eq!(parse_bytes("1.5KiB"), Ok(1536.0));
+        assert!(parse_bytes("0.5").unwrap_err().contains("whole number"));
+        assert!(parse_bytes("5Gb")
+            .unwrap_err()
+            .contains("unknown unit 'Gb'"));
+
+        assert_eq!(format_bytes(5368709120.0), "5Gi");
+        assert_eq!(format_bytes(2e6), "2M");
+        assert_eq!(format_bytes(1536.0), "1536");
+        assert_eq!(format_bytes(1500.0), "1500");
+        assert_eq!(format_bytes(0.0), "0");
+    }
+
     #[test]
     fn test_infer_type_string() {
         let v = serde_json::json!("hello");
@@ -0,0 +1,272 @@
+//! Dead code reports: variables and resources a program can do without.
+//!
+//! [`find_dead_code`] walks the dependency graph of a template, and of each
+//! of its components, backwards from the entries that have an effect:
+//!
+//! - outputs, and the outputs a component exports;
+//! - resources that register something, i.e. every resource except those
+//!   read with `get:` and those whose `count:` or `range:` is literally
+//!   empty;
+//! - variables whose value has side effects of its own: an `fn::invoke`
+//!   (functions may do more than read), an `fn::assert` or `fn::validateJson`
+//!   that can fail the deployment, or a Starlark call.
+//!
+//! Every variable or resource not reached from one of these is reported.
+//! Removing it leaves the program's outputs and registrations unchanged.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::expr::Expr;
+use crate::ast::template::{
+    OutputEntry, ResourceEntry, ResourceRange, TemplateDecl, VariableEntry,
+};
+use crate::ast::visitor::{for_each_child, walk_expr, walk_resource, DepCollector};
+use crate::eval::fold;
+use crate::eval::value::Value;
+use crate::syntax::Span;
+
+/// A variable or resource nothing with an effect depends on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeadEntry {
+    /// `"variable"` or `"resource"`.
+    pub kind: &'static str,
+    pub name: String,
+    /// The component whose body declares the entry, if any.
+    pub component: Option<String>,
+    /// Why a resource is not live on its own; `None` for variables.
+    pub reason: Option<&'static str>,
+    pub span: Option<Span>,
+}
+
+/// Returns the dead variables and resources of `template` and its
+/// components, in declaration order, the template's own entries first.
+pub fn find_dead_code(template: &TemplateDecl<'_>) -> Vec<DeadEntry> {
+    let mut dead = scope_dead_code(
+        &template.variables,
+        &template.resources,
+        &template.outputs,
+        None,
+    );
+    for component in &template.components {
+        let body = &component.component;
+        dead.extend(scope_dead_code(
+            &body.variables,
+            &body.resources,
+            &body.outputs,
+            Some(component.key.as_ref()),
+        ));
+    }
+    dead
+}
+
+/// Finds the dead entries of one scope: the template, or a component body.
+fn scope_dead_code(
+    variables: &[VariableEntry<'_>],
+    resources: &[ResourceEntry<'_>],
+    outputs: &[OutputEntry<'_>],
+    component: Option<&str>,
+) -> Vec<DeadEntry> {
+    let mut names: HashMap<&str, &str> = HashMap::new();
+    for entry in variables {
+        names.insert(entry.key.as_ref(), "variable");
+    }
+    for entry in resources {
+        names.insert(entry.logical_name.as_ref(), "resource");
+    }
+    let collector = DepCollector {
+        known_names: &names,
+    };
+
+    let mut deps: HashMap<&str, HashSet<&str>> = HashMap::new();
+    let mut live: Vec<&str> = Vec::new();
+    for entry in variables {
+        let mut node_deps = HashSet::new();
+        walk_expr(&entry.value, &collector, &mut node_deps);
+        deps.insert(entry.key.as_ref(), node_deps);
+        if has_side_effects(&entry.value) {
+            live.push(entry.key.as_ref());
+        }
+    }
+    for entry in resources {
+        let mut node_deps = HashSet::new();
+        walk_resource(&entry.resource, &collector, &mut node_deps);
+        deps.insert(entry.logical_name.as_ref(), node_deps);
+        if not_registered(entry).is_none() {
+            live.push(entry.logical_name.as_ref());
+        }
+    }
+    for output in outputs {
+        let mut node_deps = HashSet::new();
+        walk_expr(&output.value, &collector, &mut node_deps);
+        live.extend(node_deps);
+    }
+
+    let mut reached: HashSet<&str> = HashSet::new();
+    while let Some(name) = live.pop() {
+        if reached.insert(name) {
+            live.extend(deps.get(name).into_iter().flatten().copied());
+        }
+    }
+
+    let component = component.map(str::to_string);
+    let dead_variables = variables
+        .iter()
+        .filter(|entry| !reached.contains(entry.key.as_ref()))
+        .map(|entry| DeadEntry {
+            kind: "variable",
+            name: entry.key.to_string(),
+            component: component.clone(),
+            reason: None,
+            span: entry.meta.span,
+        });
+    let dead_resources = resources
+        .iter()
+        .filter(|entry| !reached.contains(entry.logical_name.as_ref()))
+        .map(|entry| DeadEntry {
+            kind: "resource",
+            name: entry.logical_name.to_string(),
+            component: component.clone(),
+            reason: not_registered(entry),
+            span: entry.meta.span,
+        });
+    dead_variables.chain(dead_resources).collect()
+}
+
+/// Explains why a resource registers nothing, or `None` if it does.
+fn not_registered(entry: &ResourceEntry<'_>) -> Option<&'static str> {
+    let resource = &entry.resource;
+    if resource.get.is_some() {
+        return Some("read with get:");
+    }
+    let empty = match &resource.range {
+        Some(ResourceRange::Count(expr)) => {
+            matches!(fold::literal(expr), Some(Value::Number(n)) if n.as_f64() <= 0.0)
+        }
+        Some(ResourceRange::Items(expr)) => match fold::literal(expr) {
+            Some(Value::List(items)) => items.is_empty(),
+            Some(Value::Object(entries)) => entries.is_empty(),
+            _ => false,
+        },
+        None => false,
+    };
+    empty.then_some("registers no instances")
+}
+
+/// Whether evaluating `expr` has an effect beyond computing its value.
+fn has_side_effects(expr: &Expr<'_>) -> bool {
+    if matches!(
+        expr,
+        Expr::Invoke(..) | Expr::Assert(..) | Expr::ValidateJson(..) | Expr::Starlark(..)
+    ) {
+        return true;
+    }
+    let mut effects = false;
+    for_each_child(expr, |child| effects = effects || has_side_effects(child));
+    effects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::parse::parse_template;
+
+    fn dead(source: &str) -> Vec<(Option<String>, &'static str, String, Option<&'static str>)> {
+        let (template, diags) = parse_template(source, None);
+        assert!(!diags.has_errors(), "parse errors: {}", diags);
+        find_dead_code(&template)
+            .into_iter()
+            .map(|e| (e.component, e.kind, e.name, e.reason))
+            .collect()
+    }
+
+    #[test]
+    fn test_reports_entries_unreachable_from_outputs_and_registrations() {
+        let source = r#"
+name: test
+runtime: yaml
+variables:
+  prefix: app
+  bucketName: ${prefix}-data
+  legacyName: ${prefix}-legacy
+  orphan:
+    fn::join: ["-", ["${legacyName}", x]]
+  amiId:
+    fn::invoke:
+      function: aws:ec2:getAmi
+      arguments:
+        mostRecent: true
+      return: id
+  check:
+    fn::assert:
+      condition: true
+      message: never fails
+resources:
+  bucket:
+    type: aws:s3:Bucket
+    properties:
+      bucket: ${bucketName}
+  existing:
+    type: aws:s3:Bucket
+    get:
+      id: legacy-bucket
+  used:
+    type: aws:s3:Bucket
+    get:
+      id: shared-bucket
+  none:
+    type: aws:sqs:Queue
+    count: 0
+outputs:
+  shared: ${used.arn}
+"#;
+        assert_eq!(
+            dead(source),
+            vec![
+                (None, "variable", "legacyName".to_string(), None),
+                (None, "variable", "orphan".to_string(), None),
+                (
+                    None,
+                    "resource",
+                    "existing".to_string(),
+                    Some("read with get:")
+                ),
+                (
+                    None,
+                    "resource",
+                    "none".to_string(),
+                    Some("registers no instances")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reports_dead_code_in_component_bodies() {
+        let source = r#"
+name: test
+runtime: yaml
+components:
+  web:
+    inputs:
+      size:
+        type: String
+    variables:
+      exported: ${size}-web
+      scratch: ${size}-scratch
+    resources:
+      server:
+        type: aws:ec2:Instance
+    outputs:
+      name: ${exported}
+"#;
+        assert_eq!(
+            dead(source),
+            vec![(
+                Some("web".to_string()),
+                "variable",
+                "scratch".to_string(),
+                None
+            )]
+        );
+    }
+}
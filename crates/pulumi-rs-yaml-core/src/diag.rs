@@ -16,6 +16,9 @@ pub struct Diagnostic {
     pub span: Option<Span>,
     pub summary: String,
     pub detail: String,
+    /// A second location the diagnostic refers to, such as the first
+    /// declaration of a duplicate name.
+    pub related: Option<Span>,
     /// Whether the diagnostic has been shown to the user.
     pub shown: bool,
 }
@@ -32,6 +35,7 @@ impl Diagnostic {
             span,
             summary: summary.into(),
             detail: detail.into(),
+            related: None,
             shown: false,
         }
     }
@@ -47,10 +51,17 @@ impl Diagnostic {
             span,
             summary: summary.into(),
             detail: detail.into(),
+            related: None,
             shown: false,
         }
     }
 
+    /// Sets the related location.
+    pub fn with_related(mut self, related: Option<Span>) -> Self {
+        self.related = related;
+        self
+    }
+
     /// Returns true if this is an error-level diagnostic.
     pub fn is_error(&self) -> bool {
         self.severity == Severity::Error
@@ -83,6 +94,11 @@ impl Diagnostics {
         Self { diags: Vec::new() }
     }
 
+    /// Removes all diagnostics, keeping the allocation.
+    pub fn clear(&mut self) {
+        self.diags.clear();
+    }
+
     /// Adds a diagnostic.
     pub fn add(&mut self, diag: Diagnostic) {
         self.diags.push(diag);
@@ -203,6 +219,11 @@ impl<'a> FileTable<'a> {
         Self { arena, indices }
     }
 
+    /// Returns true if `file` is in the table.
+    pub fn contains(&self, file: FileId) -> bool {
+        (file.0 as usize) < self.indices.len()
+    }
+
     /// Returns the file name for a file ID.
     pub fn file_name(&self, file: FileId) -> &str {
         self.arena.name(file)
@@ -233,11 +254,15 @@ impl<'a> FileTable<'a> {
             Some(span) => format!("{}: ", self.format_span(span)),
             None => String::new(),
         };
-        if diag.detail.is_empty() {
+        let mut message = if diag.detail.is_empty() {
             format!("{}{}: {}", location, prefix, diag.summary)
         } else {
             format!("{}{}: {}; {}", location, prefix, diag.summary, diag.detail)
+        };
+        if let Some(related) = diag.related.filter(|r| self.contains(r.file)) {
+            message.push_str(&format!(" (see {})", self.format_span(related)));
         }
+        message
     }
 }
 
@@ -403,6 +428,7 @@ mod tests {
             span: None,
             summary: "shown".into(),
             detail: String::new(),
+            related: None,
             shown: true,
         });
         diags.add(Diagnostic::error(None, "unshown", ""));
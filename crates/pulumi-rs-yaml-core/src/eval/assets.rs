@@ -0,0 +1,154 @@
+//! Checks file assets and archives before they are sent to the engine.
+//!
+//! During an update, every `fn::fileAsset` and `fn::fileArchive` path in a
+//! resource's inputs is resolved against the project directory (not the
+//! process working directory). A missing file is reported against the
+//! resource property that holds it instead of failing later in the provider.
+//! File assets are read and their SHA-256 recorded in the value, which the
+//! engine uses as the asset hash. Archive hashes depend on the engine's own
+//! serialization of the archive, so archives are only checked for existence.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::eval::value::{Archive, Asset, Value};
+use crate::paths;
+use crate::remote_import::sha256_hex;
+use crate::vfs::FsProvider;
+
+/// A file asset or archive that could not be read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileError {
+    /// Path to the value within the resource inputs, e.g. `code.assets.index.js`.
+    pub property: String,
+    /// `fileAsset` or `fileArchive`.
+    pub kind: &'static str,
+    /// The path as written in the template.
+    pub path: String,
+    /// The path the file was looked up at.
+    pub resolved: PathBuf,
+    pub reason: String,
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "property '{}': {} '{}' {}",
+            self.property, self.kind, self.path, self.reason
+        )
+    }
+}
+
+/// Hashes the file assets in `value` and checks that its file archives exist,
+/// resolving relative paths against `base` and reading them from `fs`.
+/// `property` names `value` in the returned errors.
+pub fn resolve_files(
+    value: &mut Value<'_>,
+    base: &Path,
+    fs: &dyn FsProvider,
+    property: &str,
+    errors: &mut Vec<FileError>,
+) {
+    match value {
+        Value::Secret(inner) => resolve_files(inner, base, fs, property, errors),
+        Value::List(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                resolve_files(item, base, fs, &format!("{}[{}]", property, i), errors);
+            }
+        }
+        Value::Object(entries) | Value::Archive(Archive::Assets(entries)) => {
+            for (key, item) in entries.iter_mut() {
+                resolve_files(item, base, fs, &format!("{}.{}", property, key), errors);
+            }
+        }
+        Value::Asset(Asset::File(path, hash)) => {
+            let resolved = paths::resolve(base, path);
+            match fs.read(&resolved) {
+                Ok(contents) => *hash = Some(sha256_hex(&contents)),
+                Err(e) => errors.push(FileError {
+                    property: property.to_string(),
+                    kind: "fileAsset",
+                    path: path.to_string(),
+                    reason: describe(&e),
+                    resolved,
+                }),
+            }
+        }
+        Value::Archive(Archive::File(path)) => {
+            let resolved = paths::resolve(base, path);
+            if let Err(e) = fs.kind(&resolved) {
+                errors.push(FileError {
+                    property: property.to_string(),
+                    kind: "fileArchive",
+                    path: path.to_string(),
+                    reason: describe(&e),
+                    resolved,
+                });
+            }
+        }
+        _ => {}
+    }
+}
+
+fn describe(e: &std::io::Error) -> String {
+    match e.kind() {
+        std::io::ErrorKind::NotFound => "does not exist".to_string(),
+        _ => format!("cannot be read: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::OsFs;
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_resolve_files_hashes_and_reports() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("index.html"), "hello").unwrap();
+        std::fs::create_dir(dir.path().join("site")).unwrap();
+
+        let mut value = Value::Object(vec![
+            (
+                Cow::Borrowed("page"),
+                Value::Asset(Asset::File(Cow::Borrowed("index.html"), None)),
+            ),
+            (
+                Cow::Borrowed("code"),
+                Value::Archive(Archive::Assets(vec![
+                    (
+                        Cow::Borrowed("site"),
+                        Value::Archive(Archive::File(Cow::Borrowed("./site"))),
+                    ),
+                    (
+                        Cow::Borrowed("app.js"),
+                        Value::Asset(Asset::File(Cow::Borrowed("./app.js"), None)),
+                    ),
+                ])),
+            ),
+        ]);
+        let mut errors = Vec::new();
+        resolve_files(&mut value, dir.path(), &OsFs, "source", &mut errors);
+
+        let Value::Object(entries) = &value else {
+            unreachable!()
+        };
+        assert_eq!(
+            entries[0].1,
+            Value::Asset(Asset::File(
+                Cow::Borrowed("index.html"),
+                Some(
+                    "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824".to_string()
+                )
+            ))
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].resolved, dir.path().join("./app.js"));
+        assert_eq!(
+            errors[0].to_string(),
+            "property 'source.code.app.js': fileAsset './app.js' does not exist"
+        );
+    }
+}
@@ -0,0 +1,144 @@
+//! Resource types the YAML runtime refuses to register.
+//!
+//! The built-in entries match Go's `pkg/pulumiyaml/packages.go:270-324`:
+//! Kubernetes resources that expand client-side, Helm charts, and Docker
+//! images before Docker provider v4. Hosts can block more types or allow
+//! built-in ones through [`Blocklist::block`] and [`Blocklist::allow`]; the
+//! language host reads them from the `blockedTypes` and `allowedTypes`
+//! runtime options and the `PULUMI_YAML_BLOCKED_TYPES` and
+//! `PULUMI_YAML_ALLOWED_TYPES` environment variables.
+
+use std::collections::HashSet;
+
+use semver::Version;
+
+const KUBERNETES_ISSUE: &str = "https://github.com/pulumi/pulumi-kubernetes/issues/1971";
+
+/// Docker image types, supported from Docker provider major version 4.
+const DOCKER_IMAGE_TYPES: &[&str] = &["docker:image:Image", "docker:Image"];
+
+/// Why a type is blocked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Reason {
+    /// Not supported in YAML; see the linked issue.
+    Unsupported(&'static str),
+    /// Not supported in YAML; use the named type instead.
+    UseInstead(&'static str),
+    /// Only supported from this provider major version.
+    MinMajor(u64),
+    /// Blocked by the host.
+    Host,
+}
+
+/// The set of blocked resource types.
+#[derive(Debug, Clone)]
+pub struct Blocklist {
+    blocked: Vec<(String, Reason)>,
+    allowed: HashSet<String>,
+}
+
+impl Default for Blocklist {
+    fn default() -> Self {
+        let mut blocked: Vec<(String, Reason)> = [
+            "kubernetes:apiextensions.k8s.io:CustomResource",
+            "kubernetes:kustomize:Directory",
+            "kubernetes:yaml:ConfigFile",
+            "kubernetes:yaml:ConfigGroup",
+        ]
+        .iter()
+        .map(|t| (t.to_string(), Reason::Unsupported(KUBERNETES_ISSUE)))
+        .collect();
+        for chart in ["kubernetes:helm.sh/v2:Chart", "kubernetes:helm.sh/v3:Chart"] {
+            blocked.push((
+                chart.to_string(),
+                Reason::UseInstead("kubernetes:helm.sh/v3:Release"),
+            ));
+        }
+        for image in DOCKER_IMAGE_TYPES {
+            blocked.push((image.to_string(), Reason::MinMajor(4)));
+        }
+        Self {
+            blocked,
+            allowed: HashSet::new(),
+        }
+    }
+}
+
+impl Blocklist {
+    /// Blocks `type_token` in addition to the current entries.
+    pub fn block(&mut self, type_token: &str) {
+        self.allowed.remove(type_token);
+        if !self.blocked.iter().any(|(t, _)| t == type_token) {
+            self.blocked.push((type_token.to_string(), Reason::Host));
+        }
+    }
+
+    /// Allows `type_token` even if it is blocked.
+    pub fn allow(&mut self, type_token: &str) {
+        self.allowed.insert(type_token.to_string());
+    }
+
+    /// Returns the error for a blocked type, or `None` if it may be
+    /// registered. `provider_version` is the version of the provider the
+    /// resource uses, if known; types blocked below a provider version stay
+    /// blocked when it is not.
+    pub fn check(&self, type_token: &str, provider_version: Option<&Version>) -> Option<String> {
+        if self.allowed.contains(type_token) {
+            return None;
+        }
+        let (_, reason) = self.blocked.iter().find(|(t, _)| t == type_token)?;
+        match reason {
+            Reason::Unsupported(url) => Some(format!(
+                "The resource type {} is not supported in YAML at this time, see: {}",
+                type_token, url
+            )),
+            Reason::UseInstead(replacement) => Some(format!(
+                "The resource type {} is not supported in YAML. Use {} instead",
+                type_token, replacement
+            )),
+            Reason::MinMajor(major) if provider_version.is_some_and(|v| v.major >= *major) => None,
+            Reason::MinMajor(major) => Some(format!(
+                "Docker Image resources are not supported in YAML without Docker provider \
+                 major version >= {}. See: https://github.com/pulumi/pulumi-yaml/issues/421",
+                major
+            )),
+            Reason::Host => Some(format!(
+                "The resource type {} is blocked by this host's configuration",
+                type_token
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_docker_image_allowed_from_v4() {
+        let list = Blocklist::default();
+        assert!(list.check("docker:image:Image", None).is_some());
+        let v3 = Version::new(3, 6, 1);
+        assert!(list.check("docker:Image", Some(&v3)).is_some());
+        let v4 = Version::new(4, 0, 0);
+        assert_eq!(list.check("docker:image:Image", Some(&v4)), None);
+    }
+
+    #[test]
+    fn test_host_block_and_allow() {
+        let mut list = Blocklist::default();
+        list.block("aws:iam:AccessKey");
+        list.allow("kubernetes:yaml:ConfigFile");
+        assert_eq!(
+            list.check("aws:iam:AccessKey", None).as_deref(),
+            Some("The resource type aws:iam:AccessKey is blocked by this host's configuration")
+        );
+        assert_eq!(list.check("kubernetes:yaml:ConfigFile", None), None);
+        assert!(list.check("kubernetes:yaml:ConfigGroup", None).is_some());
+        assert_eq!(list.check("aws:s3:Bucket", None), None);
+
+        // Blocking again takes precedence over an earlier allow.
+        list.block("kubernetes:yaml:ConfigFile");
+        assert!(list.check("kubernetes:yaml:ConfigFile", None).is_some());
+    }
+}
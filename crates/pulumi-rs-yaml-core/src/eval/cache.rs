@@ -0,0 +1,402 @@
+//! Caching pure node evaluations across evaluations.
+//!
+//! A host previewing the same program repeatedly — a watch loop or a language
+//! server — evaluates the same variables again and again. With
+//! [`Evaluator::eval_cache`](super::evaluator::Evaluator::eval_cache) set,
+//! the value of a variable whose expression is pure (see [`is_pure`]) is
+//! looked up by a key hashing the expression and the values of the names it
+//! references, so only variables whose inputs changed are evaluated again.
+//!
+//! A cache is in memory, or backed by a JSON file ([`EvalCache::on_disk`])
+//! that [`EvalCache::save`] writes. Only plain values are written to the
+//! file: secrets, unknowns, assets, and archives stay in memory.
+//!
+//! Entries only live as long as evaluations use them: [`EvalCache::prune`],
+//! which a host calls between runs and `save` calls before writing, drops
+//! the ones not looked up or recorded since the last prune.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use sha2::{Digest, Sha256};
+
+use crate::ast::expr::Expr;
+use crate::ast::visitor::{for_each_child, walk_expr, AllRefsCollector};
+use crate::eval::value::Value;
+use crate::plan::expr_to_json;
+
+/// Version of the on-disk format. Files with another version are ignored.
+const FORMAT_VERSION: u64 = 1;
+
+/// Values of pure node evaluations, keyed by [`node_key`].
+#[derive(Debug, Default)]
+pub struct EvalCache {
+    entries: Mutex<HashMap<String, Value<'static>>>,
+    /// Keys looked up or recorded since the last prune.
+    used: Mutex<HashSet<String>>,
+    path: Option<PathBuf>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl EvalCache {
+    /// Creates an empty cache kept in memory.
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Creates a cache backed by the file at `path`, starting from the
+    /// entries it holds. A missing, unreadable, or corrupt file starts an
+    /// empty cache; [`save`](Self::save) replaces it.
+    pub fn on_disk(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let entries = read_entries(&path).unwrap_or_default();
+        Self {
+            entries: Mutex::new(entries),
+            path: Some(path),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the cached value for `key`, counting a hit or a miss.
+    pub fn get(&self, key: &str) -> Option<Value<'static>> {
+        let value = self.entries.lock().unwrap().get(key).cloned();
+        let counter = if value.is_some() {
+            self.used.lock().unwrap().insert(key.to_string());
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+
+    /// Records the value of the node with `key`.
+    pub fn insert(&self, key: String, value: Value<'static>) {
+        self.used.lock().unwrap().insert(key.clone());
+        self.entries.lock().unwrap().insert(key, value);
+    }
+
+    /// Drops the entries not looked up or recorded since the last prune.
+    pub fn prune(&self) {
+        let mut used = self.used.lock().unwrap();
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|key, _| used.contains(key));
+        used.clear();
+    }
+
+    /// Prunes the cache and writes the plain entries left to the cache's
+    /// file. The file is replaced atomically, so a concurrent run never
+    /// reads a partial one. Only prunes an in-memory cache.
+    pub fn save(&self) -> std::io::Result<()> {
+        self.prune();
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let entries: serde_json::Map<String, serde_json::Value> = self
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, value)| is_plain(value))
+            .map(|(key, value)| (key.clone(), value.to_json()))
+            .collect();
+        let file = serde_json::json!({ "version": FORMAT_VERSION, "entries": entries });
+        let dir = path.parent().filter(|d| !d.as_os_str().is_empty());
+        if let Some(dir) = dir {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let tmp_name = format!(".{}.{}.tmp", file_name, std::process::id());
+        let tmp = dir.map_or_else(|| PathBuf::from(&tmp_name), |dir| dir.join(&tmp_name));
+        std::fs::write(&tmp, file.to_string())?;
+        std::fs::rename(&tmp, path)
+    }
+
+    /// Number of cached values.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Whether the cache holds no values.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of lookups that found a value.
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Number of lookups that found none.
+    pub fn misses(&self) -> usize {
+        self.misses.load(Ordering::Relaxed)
+    }
+}
+
+fn read_entries(path: &Path) -> Option<HashMap<String, Value<'static>>> {
+    let text = std::fs::read_to_string(path).ok()?;
+    let json: serde_json::Value = serde_json::from_str(&text).ok()?;
+    if json.get("version")?.as_u64()? != FORMAT_VERSION {
+        return None;
+    }
+    let entries = json.get("entries")?.as_object()?;
+    Some(
+        entries
+            .iter()
+            .map(|(key, value)| (key.clone(), Value::from_json_owned(value.clone())))
+            .collect(),
+    )
+}
+
+/// Whether a value survives a round trip through JSON unchanged.
+fn is_plain(value: &Value<'_>) -> bool {
+    match value {
+        Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => true,
+        Value::List(items) => items.iter().all(is_plain),
+        Value::Object(entries) => entries.iter().all(|(_, v)| is_plain(v)),
+        _ => false,
+    }
+}
+
+/// Whether evaluating `expr` depends only on the values it references: it
+/// calls no functions, reads no files or clocks, and generates no random
+/// values.
+pub fn is_pure(expr: &Expr<'_>) -> bool {
+    let impure = matches!(
+        expr,
+        Expr::Invoke(..)
+            | Expr::Encrypted(..)
+            | Expr::ReadFile(..)
+            | Expr::ReadFileBinary(..)
+            | Expr::Env(..)
+            | Expr::ValidateJson(..)
+            | Expr::TemplateFile(..)
+            | Expr::TimeUtc(..)
+            | Expr::TimeUnix(..)
+            | Expr::Uuid(..)
+            | Expr::RandomString(..)
+            | Expr::DateFormat(..)
+            | Expr::FileAsset(..)
+            | Expr::RemoteAsset(..)
+            | Expr::FileArchive(..)
+            | Expr::RemoteArchive(..)
+            | Expr::Open(..)
+            | Expr::Starlark(..)
+    );
+    if impure {
+        return false;
+    }
+    let mut pure = true;
+    for_each_child(expr, |child| pure = pure && is_pure(child));
+    pure
+}
+
+/// Returns the cache key of `expr`: a hash of the expression and the value
+/// `lookup` returns for each name it references. Returns `None` when `expr`
+/// is not pure, or `lookup` has no value for a name it references.
+pub fn node_key<'a>(
+    expr: &'a Expr<'a>,
+    lookup: impl Fn(&str) -> Option<Value<'static>>,
+) -> Option<String> {
+    if !is_pure(expr) {
+        return None;
+    }
+    let mut refs = Default::default();
+    walk_expr(expr, &AllRefsCollector, &mut refs);
+    let mut refs: Vec<&str> = refs.into_iter().collect();
+    refs.sort_unstable();
+
+    let mut hasher = Sha256::new();
+    hasher.update(expr_to_json(expr).to_string());
+    for name in refs {
+        let value = lookup(name)?;
+        hasher.update([0]);
+        hasher.update(name);
+        hasher.update([0]);
+        hash_value(&value, &mut hasher)?;
+    }
+    Some(format!("{:x}", hasher.finalize()))
+}
+
+/// Feeds a tagged encoding of `value` to `hasher`. Returns `None` for values
+/// that identify state outside the template, such as resources.
+fn hash_value(value: &Value<'_>, hasher: &mut Sha256) -> Option<()> {
+    match value {
+        Value::Null => hasher.update(b"n"),
+        Value::Bool(b) => hasher.update(if *b { b"t" } else { b"f" }),
+        // Integers are hashed exactly, so keys never collide above 2^53.
+        // A float with no fraction hashes as the integer it equals.
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => {
+                hasher.update(b"i");
+                hasher.update(i.to_le_bytes());
+            }
+            None => {
+                hasher.update(b"d");
+                hasher.update(n.as_f64().to_bits().to_le_bytes());
+            }
+        },
+        Value::String(s) => {
+            hasher.update(b"s");
+            hasher.update((s.len() as u64).to_le_bytes());
+            hasher.update(s.as_bytes());
+        }
+        Value::Bytes(b) => {
+            hasher.update(b"b");
+            hasher.update((b.len() as u64).to_le_bytes());
+            hasher.update(b);
+        }
+        Value::List(items) => {
+            hasher.update(b"l");
+            hasher.update((items.len() as u64).to_le_bytes());
+            for item in items {
+                hash_value(item, hasher)?;
+            }
+        }
+        Value::Object(entries) => {
+            hasher.update(b"o");
+            hasher.update((entries.len() as u64).to_le_bytes());
+            for (key, item) in entries {
+                hasher.update((key.len() as u64).to_le_bytes());
+                hasher.update(key.as_bytes());
+                hash_value(item, hasher)?;
+            }
+        }
+        Value::Secret(inner) => {
+            hasher.update(b"x");
+            hash_value(inner, hasher)?;
+        }
+        Value::Unknown => hasher.update(b"u"),
+        Value::Resource(_) | Value::Asset(_) | Value::Archive(_) => return None,
+    }
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::parse::parse_template;
+    use std::borrow::Cow;
+
+    fn variable_key(
+        source: &str,
+        lookup: impl Fn(&str) -> Option<Value<'static>>,
+    ) -> Option<String> {
+        let (template, diags) = parse_template(source, None);
+        assert!(!diags.has_errors(), "{:?}", diags);
+        node_key(&template.variables[0].value, lookup)
+    }
+
+    fn string(s: &str) -> Option<Value<'static>> {
+        Some(Value::String(Cow::Owned(s.to_string())))
+    }
+
+    #[test]
+    fn test_key_depends_on_referenced_values() {
+        let source = "name: p\nvariables:\n  a:\n    fn::toUpper: ${name}\n";
+        let first = variable_key(source, |_| string("web")).unwrap();
+        assert_eq!(first, variable_key(source, |_| string("web")).unwrap());
+        assert_ne!(first, variable_key(source, |_| string("api")).unwrap());
+        let secret = variable_key(source, |_| {
+            Some(Value::Secret(Box::new(string("web").unwrap())))
+        });
+        assert_ne!(Some(first), secret);
+    }
+
+    #[test]
+    fn test_key_keeps_integers_exact() {
+        use crate::number::Number;
+        let source = "name: p\nvariables:\n  a:\n    fn::toJSON: ${name}\n";
+        let number = |n: Number| move |_: &str| Some(Value::Number(n));
+        let low = variable_key(source, number(Number::Int(9007199254740992))).unwrap();
+        let high = variable_key(source, number(Number::Int(9007199254740993))).unwrap();
+        assert_ne!(low, high);
+        assert_eq!(
+            variable_key(source, number(Number::Int(1))),
+            variable_key(source, number(Number::Float(1.0)))
+        );
+        assert_ne!(
+            variable_key(source, number(Number::Int(1))),
+            variable_key(source, number(Number::Float(1.5)))
+        );
+    }
+
+    #[test]
+    fn test_impure_and_unresolved_nodes_have_no_key() {
+        assert!(variable_key(
+            "name: p\nvariables:\n  a:\n    fn::readFile: ./x.txt\n",
+            |_| None
+        )
+        .is_none());
+        assert!(variable_key(
+            "name: p\nvariables:\n  a:\n    fn::join: [\",\", [\"${x}\", {fn::uuid: {}}]]\n",
+            |_| string("x")
+        )
+        .is_none());
+        assert!(variable_key("name: p\nvariables:\n  a: ${missing}\n", |_| None).is_none());
+        assert!(
+            variable_key("name: p\nvariables:\n  a: ${bucket}\n", |_| Some(
+                Value::Resource(crate::eval::value::ResourceRef(0))
+            ))
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_on_disk_round_trip_keeps_plain_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache").join("eval.json");
+        let cache = EvalCache::on_disk(&path);
+        assert!(cache.is_empty());
+        cache.insert("plain".into(), Value::List(vec![string("a").unwrap()]));
+        cache.insert("secret".into(), Value::Secret(Box::new(Value::Null)));
+        cache.save().unwrap();
+
+        let reloaded = EvalCache::on_disk(&path);
+        assert_eq!(reloaded.len(), 1);
+        assert_eq!(
+            reloaded.get("plain"),
+            Some(Value::List(vec![string("a").unwrap()]))
+        );
+        assert_eq!(reloaded.get("secret"), None);
+        assert_eq!((reloaded.hits(), reloaded.misses()), (1, 1));
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(EvalCache::on_disk(&path).is_empty());
+        assert_eq!(
+            std::fs::read_dir(path.parent().unwrap()).unwrap().count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_save_prunes_unused_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("eval.json");
+        let cache = EvalCache::on_disk(&path);
+        cache.insert("a".into(), Value::Bool(true));
+        cache.insert("b".into(), Value::Bool(false));
+        cache.save().unwrap();
+
+        let reloaded = EvalCache::on_disk(&path);
+        assert_eq!(reloaded.get("a"), Some(Value::Bool(true)));
+        reloaded.insert("c".into(), Value::Null);
+        reloaded.save().unwrap();
+        assert_eq!(reloaded.len(), 2);
+        let reloaded = EvalCache::on_disk(&path);
+        assert_eq!(reloaded.get("b"), None);
+        assert_eq!(reloaded.len(), 2);
+
+        let memory = EvalCache::in_memory();
+        memory.insert("a".into(), Value::Null);
+        memory.prune();
+        assert_eq!(memory.len(), 1);
+        memory.prune();
+        assert!(memory.is_empty());
+    }
+}
@@ -13,6 +13,21 @@ pub struct RegisterResponse {
     pub stables: Vec<String>,
 }
 
+impl RegisterResponse {
+    /// Wraps the named outputs as secrets (no-op for missing or already
+    /// secret outputs), mirroring the engine's `additionalSecretOutputs`.
+    pub fn mark_secret_outputs(&mut self, names: &[String]) {
+        for name in names {
+            if let Some(val) = self.outputs.get_mut(name) {
+                if !val.is_secret() {
+                    let inner = std::mem::replace(val, Value::Null);
+                    *val = Value::Secret(Box::new(inner));
+                }
+            }
+        }
+    }
+}
+
 /// Response from invoking a function via callback.
 #[derive(Debug, Clone)]
 pub struct InvokeResponse {
@@ -41,15 +56,16 @@ pub trait ResourceCallback: Send + Sync {
     ) -> Result<RegisterResponse, EngineError>;
 
     /// Read an existing resource from the engine.
+    ///
+    /// Uses the same resolved options as registration: parent, provider,
+    /// version, dependencies, and `additional_secret_outputs`.
     fn read_resource(
         &self,
         type_token: &str,
         name: &str,
         id: &str,
-        parent_urn: &str,
         inputs: HashMap<String, Value<'static>>,
-        provider_ref: &str,
-        version: &str,
+        options: ResolvedResourceOptions,
     ) -> Result<RegisterResponse, EngineError>;
 
     /// Invoke a provider function.
@@ -104,10 +120,8 @@ impl ResourceCallback for NoopCallback {
         _type_token: &str,
         _name: &str,
         _id: &str,
-        _parent_urn: &str,
         inputs: HashMap<String, Value<'static>>,
-        _provider_ref: &str,
-        _version: &str,
+        _options: ResolvedResourceOptions,
     ) -> Result<RegisterResponse, EngineError> {
         Ok(RegisterResponse {
             urn: String::new(),
@@ -180,7 +194,13 @@ mod tests {
         let mut inputs = HashMap::new();
         inputs.insert("prop".to_string(), Value::Bool(true));
         let resp = noop
-            .read_resource("test:Type", "name", "id-1", "", inputs, "", "")
+            .read_resource(
+                "test:Type",
+                "name",
+                "id-1",
+                inputs,
+                ResolvedResourceOptions::default(),
+            )
             .unwrap();
         assert_eq!(
             resp.outputs.get("prop").and_then(|v| v.as_bool()),
@@ -188,6 +208,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_mark_secret_outputs() {
+        let mut resp = RegisterResponse {
+            urn: String::new(),
+            id: String::new(),
+            outputs: HashMap::from([
+                ("password".to_string(), Value::Bool(true)),
+                ("name".to_string(), Value::Bool(false)),
+            ]),
+            stables: Vec::new(),
+        };
+        resp.mark_secret_outputs(&["password".to_string(), "missing".to_string()]);
+        resp.mark_secret_outputs(&["password".to_string()]);
+        assert_eq!(
+            resp.outputs["password"],
+            Value::Secret(Box::new(Value::Bool(true)))
+        );
+        assert!(!resp.outputs["name"].is_secret());
+        assert!(!resp.outputs.contains_key("missing"));
+    }
+
     #[test]
     fn test_noop_invoke_returns_empty() {
         let noop = NoopCallback;
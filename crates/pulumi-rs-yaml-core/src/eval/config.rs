@@ -1,7 +1,7 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
 
-use crate::config_types::ConfigType;
+use crate::config_types::{self, ConfigType};
 use crate::diag::Diagnostics;
 use crate::eval::value::Value;
 
@@ -50,13 +50,21 @@ pub fn resolve_config_entry<'src>(
         }
     });
 
+    // Durations and sizes may be given with units; normalize them first
+    let default_value = match (&declared_type, default_value) {
+        (Some(t @ (ConfigType::Duration | ConfigType::Bytes)), Some(Value::String(s))) => {
+            Some(parse_config_value(&s, t.clone(), diags)?)
+        }
+        (_, default) => default,
+    };
+
     // Validate default type matches declared type
     if let (Some(decl_type), Some(ref default)) = (declared_type, &default_value) {
         let default_type = infer_type_from_value(default);
         // Allow Int default for Number type
         if decl_type == ConfigType::Int && default_type == ConfigType::Number {
             if let Value::Number(n) = default {
-                if n.fract() == 0.0 {
+                if n.as_i64().is_some() {
                     // OK: integer value for Int type
                 } else {
                     diags.error(
@@ -71,7 +79,10 @@ pub fn resolve_config_entry<'src>(
                 }
             }
         } else if default_type != decl_type
-            && !(decl_type == ConfigType::Number && default_type == ConfigType::Int)
+            && !(matches!(
+                decl_type,
+                ConfigType::Number | ConfigType::Duration | ConfigType::Bytes
+            ) && matches!(default_type, ConfigType::Int | ConfigType::Number))
         {
             diags.error(
                 None,
@@ -118,8 +129,8 @@ pub fn resolve_config_entry<'src>(
 pub fn validate_config_type(value: &Value<'_>, declared: &str, key: &str, diags: &mut Diagnostics) {
     let ok = match declared {
         "string" => matches!(value, Value::String(_)),
-        "int" | "integer" => matches!(value, Value::Number(n) if n.fract() == 0.0),
-        "number" => matches!(value, Value::Number(_)),
+        "int" | "integer" => matches!(value, Value::Number(n) if n.as_i64().is_some()),
+        "number" | "duration" | "bytes" => matches!(value, Value::Number(_)),
         "boolean" => matches!(value, Value::Bool(_)),
         _ => true, // unknown types pass through
     };
@@ -137,6 +148,27 @@ pub fn validate_config_type(value: &Value<'_>, declared: &str, key: &str, diags:
     }
 }
 
+/// Returns whether `value` is acceptable for a parameter declared as
+/// `declared`. Durations and sizes may also be strings with units.
+pub(crate) fn value_matches_type(value: &Value<'_>, declared: &ConfigType) -> bool {
+    if let Some(element) = declared.element_type() {
+        return match value {
+            Value::List(items) => items.iter().all(|v| value_matches_type(v, &element)),
+            _ => false,
+        };
+    }
+    match (declared, value) {
+        (ConfigType::String, Value::String(_)) => true,
+        (ConfigType::Int, Value::Number(n)) => n.as_i64().is_some(),
+        (ConfigType::Number | ConfigType::Duration | ConfigType::Bytes, Value::Number(_)) => true,
+        (ConfigType::Duration, Value::String(s)) => config_types::parse_duration(s).is_ok(),
+        (ConfigType::Bytes, Value::String(s)) => config_types::parse_bytes(s).is_ok(),
+        (ConfigType::Boolean, Value::Bool(_)) => true,
+        (ConfigType::Object, Value::Object(_)) => true,
+        _ => false,
+    }
+}
+
 /// Parses a raw string config value into a typed Value.
 fn parse_config_value<'src>(
     raw: &str,
@@ -146,7 +178,7 @@ fn parse_config_value<'src>(
     match expected_type {
         ConfigType::String => Some(Value::String(Cow::Owned(raw.to_string()))),
         ConfigType::Number => match raw.parse::<f64>() {
-            Ok(n) => Some(Value::Number(n)),
+            Ok(n) => Some(Value::Number(n.into())),
             Err(_) => {
                 diags.error(
                     None,
@@ -157,7 +189,7 @@ fn parse_config_value<'src>(
             }
         },
         ConfigType::Int => match raw.parse::<i64>() {
-            Ok(n) => Some(Value::Number(n as f64)),
+            Ok(n) => Some(Value::Number(n.into())),
             Err(_) => {
                 diags.error(
                     None,
@@ -179,6 +211,28 @@ fn parse_config_value<'src>(
                 None
             }
         },
+        ConfigType::Duration => match config_types::parse_duration(raw) {
+            Ok(seconds) => Some(Value::Number(seconds.into())),
+            Err(e) => {
+                diags.error(
+                    None,
+                    format!("config value '{}' is not a valid duration: {}", raw, e),
+                    "",
+                );
+                None
+            }
+        },
+        ConfigType::Bytes => match config_types::parse_bytes(raw) {
+            Ok(bytes) => Some(Value::Number(bytes.into())),
+            Err(e) => {
+                diags.error(
+                    None,
+                    format!("config value '{}' is not a valid byte size: {}", raw, e),
+                    "",
+                );
+                None
+            }
+        },
         ConfigType::Object | ConfigType::ObjectList => {
             // Objects and lists are JSON-encoded in config
             parse_json_config(raw, diags)
@@ -210,7 +264,7 @@ fn infer_type_from_value(value: &Value<'_>) -> ConfigType {
     match value {
         Value::String(_) => ConfigType::String,
         Value::Number(n) => {
-            if n.fract() == 0.0 {
+            if n.as_i64().is_some() {
                 ConfigType::Int
             } else {
                 ConfigType::Number
@@ -385,6 +439,47 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_resolve_config_duration_and_bytes() {
+        let resolve = |type_, default: Option<Value<'static>>, raw: Option<&str>| {
+            let mut diags = Diagnostics::new();
+            let mut raw_config = HashMap::new();
+            if let Some(raw) = raw {
+                raw_config.insert("proj:key".to_string(), raw.to_string());
+            }
+            let result = resolve_config_entry(
+                "key",
+                "proj",
+                Some(type_),
+                default,
+                false,
+                false,
+                &raw_config,
+                &mut diags,
+            );
+            (result.map(|r| r.value.as_number().unwrap()), diags)
+        };
+        let text = |s: &str| Some(Value::String(Cow::Owned(s.to_string())));
+
+        let (value, _) = resolve(ConfigType::Duration, text("1m30s"), None);
+        assert_eq!(value, Some(90.0));
+        let (value, _) = resolve(ConfigType::Duration, text("1m30s"), Some("2h"));
+        assert_eq!(value, Some(7200.0));
+        let (value, _) = resolve(ConfigType::Bytes, Some(Value::Number(512.0.into())), None);
+        assert_eq!(value, Some(512.0));
+        let (value, _) = resolve(ConfigType::Bytes, None, Some("5Gi"));
+        assert_eq!(value, Some(5368709120.0));
+
+        let (value, diags) = resolve(ConfigType::Bytes, None, Some("5 gigs"));
+        assert_eq!(value, None);
+        assert!(diags
+            .to_string()
+            .contains("config value '5 gigs' is not a valid byte size"));
+        let (value, diags) = resolve(ConfigType::Duration, Some(Value::Bool(true)), None);
+        assert_eq!(value, None);
+        assert!(diags.has_errors());
+    }
+
     #[test]
     fn test_resolve_config_missing_required() {
         let mut diags = Diagnostics::new();
@@ -447,9 +542,9 @@ mod tests {
             &mut diags,
         );
         assert!(!diags.has_errors());
-        validate_config_type(&Value::Number(42.0), "int", "key", &mut diags);
+        validate_config_type(&Value::Number(42.0.into()), "int", "key", &mut diags);
         assert!(!diags.has_errors());
-        validate_config_type(&Value::Number(2.5), "number", "key", &mut diags);
+        validate_config_type(&Value::Number(2.5.into()), "number", "key", &mut diags);
         assert!(!diags.has_errors());
         validate_config_type(&Value::Bool(true), "boolean", "key", &mut diags);
         assert!(!diags.has_errors());
@@ -488,9 +583,12 @@ mod tests {
             infer_type_from_value(&Value::String(Cow::Borrowed("hi"))),
             ConfigType::String
         );
-        assert_eq!(infer_type_from_value(&Value::Number(42.0)), ConfigType::Int);
         assert_eq!(
-            infer_type_from_value(&Value::Number(2.75)),
+            infer_type_from_value(&Value::Number(42.0.into())),
+            ConfigType::Int
+        );
+        assert_eq!(
+            infer_type_from_value(&Value::Number(2.75.into())),
             ConfigType::Number
         );
         assert_eq!(
@@ -1,3 +1,7 @@
+use std::collections::HashMap;
+
+use crate::jinja::{JinjaContext, UndefinedMode};
+
 /// Errors from engine/monitor communication.
 #[derive(Debug, thiserror::Error)]
 pub enum EngineError {
@@ -11,6 +15,133 @@ pub enum EngineError {
     FeatureNotSupported(String),
 }
 
+/// The program settings both the evaluator's `pulumi` variable and the Jinja
+/// context expose: which stack runs, where, and for whom.
+///
+/// Build one with [`EvaluatorContext::builder`], then pass it to
+/// [`Evaluator::from_context`](super::evaluator::Evaluator::from_context) and
+/// [`EvaluatorContext::jinja_context`] so both see the same values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvaluatorContext {
+    pub project: String,
+    pub stack: String,
+    /// The directory relative file paths resolve against (`pulumi.cwd`).
+    pub cwd: String,
+    /// The directory containing the project (`pulumi.rootDirectory`).
+    pub root_directory: String,
+    /// The organization running the stack, empty if none (`pulumi.organization`).
+    pub organization: String,
+    /// Arguments passed to the program (`pulumi.args`).
+    pub args: Vec<String>,
+    /// Whether this is a preview.
+    pub dry_run: bool,
+}
+
+impl EvaluatorContext {
+    /// Starts building a context for `stack` of `project`.
+    pub fn builder(
+        project: impl Into<String>,
+        stack: impl Into<String>,
+    ) -> EvaluatorContextBuilder {
+        EvaluatorContextBuilder {
+            project: project.into(),
+            stack: stack.into(),
+            cwd: None,
+            root_directory: None,
+            organization: None,
+            args: Vec::new(),
+            dry_run: false,
+        }
+    }
+
+    /// Returns a Jinja context with the same settings.
+    pub fn jinja_context<'a>(
+        &'a self,
+        config: &'a HashMap<String, String>,
+        project_dir: &'a str,
+        undefined: UndefinedMode,
+        extra: &'a HashMap<String, String>,
+    ) -> JinjaContext<'a> {
+        JinjaContext {
+            project_name: &self.project,
+            stack_name: &self.stack,
+            cwd: &self.cwd,
+            organization: &self.organization,
+            root_directory: &self.root_directory,
+            config,
+            project_dir,
+            args: &self.args,
+            undefined,
+            extra,
+        }
+    }
+}
+
+/// Builds an [`EvaluatorContext`]. The working directory, root directory and
+/// organization must be set, even if to an empty string, so that a host
+/// cannot forget to pass one through.
+#[derive(Debug, Clone)]
+pub struct EvaluatorContextBuilder {
+    project: String,
+    stack: String,
+    cwd: Option<String>,
+    root_directory: Option<String>,
+    organization: Option<String>,
+    args: Vec<String>,
+    dry_run: bool,
+}
+
+impl EvaluatorContextBuilder {
+    pub fn cwd(mut self, cwd: impl Into<String>) -> Self {
+        self.cwd = Some(cwd.into());
+        self
+    }
+
+    pub fn root_directory(mut self, root_directory: impl Into<String>) -> Self {
+        self.root_directory = Some(root_directory.into());
+        self
+    }
+
+    pub fn organization(mut self, organization: impl Into<String>) -> Self {
+        self.organization = Some(organization.into());
+        self
+    }
+
+    pub fn args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Returns the context, or the first required setting that is missing.
+    pub fn build(self) -> Result<EvaluatorContext, ContextError> {
+        Ok(EvaluatorContext {
+            project: self.project,
+            stack: self.stack,
+            cwd: self.cwd.ok_or(ContextError::Missing("cwd"))?,
+            root_directory: self
+                .root_directory
+                .ok_or(ContextError::Missing("root_directory"))?,
+            organization: self
+                .organization
+                .ok_or(ContextError::Missing("organization"))?,
+            args: self.args,
+            dry_run: self.dry_run,
+        })
+    }
+}
+
+/// Errors from building an [`EvaluatorContext`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ContextError {
+    #[error("evaluator context is missing {0}")]
+    Missing(&'static str),
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -26,4 +157,30 @@ mod tests {
             "resource registration failed: missing type"
         );
     }
+
+    #[test]
+    fn test_context_builder_requires_every_directory() {
+        let context = EvaluatorContext::builder("proj", "dev")
+            .cwd("/work/app")
+            .root_directory("/work")
+            .organization("")
+            .args(vec!["blue".to_string()])
+            .build()
+            .unwrap();
+        let config = HashMap::new();
+        let jinja = context.jinja_context(&config, "/work/app", UndefinedMode::Strict, &config);
+        assert_eq!(jinja.cwd, "/work/app");
+        assert_eq!(jinja.root_directory, "/work");
+        assert_eq!(jinja.args, ["blue".to_string()]);
+
+        let err = EvaluatorContext::builder("proj", "dev")
+            .cwd("/work/app")
+            .organization("acme")
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "evaluator context is missing root_directory"
+        );
+    }
 }
@@ -0,0 +1,32 @@
+//! Encrypted secret literals.
+//!
+//! A secret can be committed to the template itself as ciphertext produced
+//! with the stack's secrets provider, the same ciphertext `pulumi config set
+//! --secret` stores in stack config:
+//!
+//! ```yaml
+//! variables:
+//!   dbPassword:
+//!     fn::encrypted: AAABAKsg2Tq2vrNmjpIHb3Pw8JGe0Iw1CpoUWvuNpA==
+//! ```
+//!
+//! The evaluator does not decrypt anything itself. Each `fn::encrypted`
+//! value is passed to the host's [`Decrypter`], and the plaintext is always
+//! a secret.
+
+/// Decrypts `fn::encrypted` values for the evaluator.
+///
+/// `decrypt` receives the ciphertext as written in the template and returns
+/// the plaintext. Returning an error fails the expression with the message.
+pub trait Decrypter: Send + Sync {
+    fn decrypt(&self, ciphertext: &str) -> Result<String, String>;
+}
+
+impl<F> Decrypter for F
+where
+    F: Fn(&str) -> Result<String, String> + Send + Sync,
+{
+    fn decrypt(&self, ciphertext: &str) -> Result<String, String> {
+        self(ciphertext)
+    }
+}
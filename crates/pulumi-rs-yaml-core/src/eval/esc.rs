@@ -0,0 +1,41 @@
+//! ESC provider functions.
+//!
+//! Config entries can be written exactly like the values of an ESC
+//! environment, so the same definitions work in both places:
+//!
+//! ```yaml
+//! config:
+//!   aws:
+//!     fn::open::aws-login:
+//!       oidc:
+//!         roleArn: arn:aws:iam::123456789012:role/deploy
+//!         sessionName: pulumi-yaml
+//! ```
+//!
+//! The evaluator does not implement any provider itself. Each
+//! `fn::open::<provider>` call is delegated to the host's [`OpenResolver`];
+//! the language host resolves them through Pulumi Cloud, like ESC
+//! environments attached to the stack. A value set in the stack's config
+//! takes precedence over the call, which is then never opened.
+
+use crate::eval::value::Value;
+
+/// Opens ESC provider functions for the evaluator.
+///
+/// `open` receives the provider name (`aws-login` for
+/// `fn::open::aws-login`) and the evaluated inputs, with secrets unwrapped,
+/// and returns the provider's output. Secret parts of the output must be
+/// wrapped in [`Value::Secret`]. Returning an error fails the entry with the
+/// message.
+pub trait OpenResolver: Send + Sync {
+    fn open(&self, provider: &str, inputs: &Value<'static>) -> Result<Value<'static>, String>;
+}
+
+impl<F> OpenResolver for F
+where
+    F: Fn(&str, &Value<'static>) -> Result<Value<'static>, String> + Send + Sync,
+{
+    fn open(&self, provider: &str, inputs: &Value<'static>) -> Result<Value<'static>, String> {
+        self(provider, inputs)
+    }
+}
@@ -1,33 +1,57 @@
 use std::borrow::Cow;
-use std::collections::{HashMap, HashSet};
-use std::fmt::Write;
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
+use std::time::Instant;
 
-use crate::ast::expr::{Expr, InvokeExpr};
-use crate::ast::property::PropertyAccess;
+use crate::ast::expr::{Expr, InvokeExpr, PaginateDecl};
+use crate::ast::property::{PropertyAccess, PropertyAccessor};
 use crate::ast::template::*;
+use crate::ast::visitor::{walk_expr, AllRefsCollector};
 use crate::config_types::ConfigType;
-use crate::diag::Diagnostics;
+use crate::diag::{Diagnostics, FileTable};
+use crate::eval::assets;
+use crate::eval::blocklist::Blocklist;
 use crate::eval::builtins;
+use crate::eval::cache::{self, EvalCache};
 use crate::eval::callback::{NoopCallback, ResourceCallback};
 use crate::eval::config::{self, RawConfig};
-use crate::eval::graph::{collect_expr_deps, topological_levels, topological_sort_with_deps};
-use crate::eval::resource::{ResolvedResourceOptions, ResourceState};
+use crate::eval::context::{EngineError, EvaluatorContext};
+use crate::eval::encrypted::Decrypter;
+use crate::eval::esc::OpenResolver;
+use crate::eval::extensions::{BuiltinRegistry, ExtensionError};
+use crate::eval::graph::{
+    collect_expr_deps, collect_local_deps, expand_local_deps, topological_levels,
+    topological_sort_with_deps, SortResultWithDeps,
+};
+use crate::eval::hooks::{RegisteredResource, ResourceOutputHook};
+use crate::eval::limits::{approx_heap_size, format_bytes, DepthGuard, EvalLimits};
+use crate::eval::resource::{ResolvedResourceOptions, ResourceGroup, ResourceState};
+use crate::eval::scope::{self, ScopeGuard};
+use crate::eval::stable_random;
+use crate::eval::timing::{self, NodeTiming, TimingReport};
 use crate::eval::value::{Archive, Asset, Value};
-use crate::packages::canonicalize_type_token;
-use crate::schema::SchemaStore;
+use crate::packages::{
+    self, canonicalize_type_token, resolve_pkg_name, ResolvedTypeToken, TypeTokenResolver,
+};
+use crate::schema::{expand_component_token, SchemaStore};
+use crate::source::SourceArena;
+use crate::syntax::Span;
+use crate::version::VersionConstraint;
+use crate::vfs::{FsProvider, OsFs};
 
 /// Trait for receiving progress events during evaluation.
 ///
 /// Implementations can display progress bars, emit structured logs, or
-/// collect timing data. The default `NoopProgress` is monomorphized away
-/// to zero-cost when unused.
+/// collect timing data. Set one as [`Evaluator::progress`]; the default
+/// `NoopProgress` ignores every event.
 pub trait ProgressSink {
     /// Called at the start of each topological level.
     fn on_level_start(&mut self, level: usize, count: usize);
     /// Called after a resource is fully registered.
     fn on_resource_done(&mut self, name: &str);
+    /// Called once evaluation finishes, with the time each entry took.
+    fn on_timing_report(&mut self, _report: &TimingReport) {}
 }
 
 /// Zero-cost no-op progress sink.
@@ -49,6 +73,9 @@ pub struct EvalState {
     pub variables: RwLock<HashMap<String, Value<'static>>>,
     /// Registered resource states, keyed by logical name.
     pub resources: RwLock<HashMap<String, ResourceState>>,
+    /// The instances of resources with `count:` or `range:`, keyed by the
+    /// logical name of the entry. The instances are in `resources`.
+    pub resource_groups: RwLock<HashMap<String, ResourceGroup>>,
     /// Evaluated output values, keyed by output name.
     pub outputs: Mutex<HashMap<String, Value<'static>>>,
     /// Diagnostics accumulated during evaluation.
@@ -68,6 +95,24 @@ pub struct EvalState {
     pub stack_ref_cache: Mutex<HashMap<String, crate::eval::callback::RegisterResponse>>,
     /// Compiled Starlark runtime (None if no starlark functions defined).
     pub starlark_runtime: RwLock<Option<crate::eval::starlark_runtime::StarlarkRuntime>>,
+    /// Approximate heap bytes held by stored values, checked against
+    /// `EvalLimits::max_value_bytes`.
+    pub value_bytes: AtomicUsize,
+    /// URNs of the resources registered so far → call site and span of the
+    /// resource that claimed each. See
+    /// [`Evaluator::check_duplicate_registrations`].
+    pub registrations: Mutex<HashMap<String, (String, Option<Span>)>>,
+    /// Entries this evaluation does not resolve: those outside the dependency
+    /// closure of [`Evaluator::targets`], and resources a refresh does not
+    /// register. References to them evaluate to unknown.
+    pub skipped: RwLock<HashSet<String>>,
+    /// Resources in deletion order, filled in [`EvalMode::DestroyPlan`].
+    pub destroy_plan: Mutex<Vec<PlannedResource>>,
+    /// Time spent on each level and entry so far.
+    pub timings: Mutex<TimingReport>,
+    /// Values returned by stable `fn::uuid` and `fn::randomString` calls,
+    /// keyed by call. See [`Evaluator::stable_random`].
+    pub random_values: Mutex<BTreeMap<String, String>>,
 }
 
 // Compile-time assertion that EvalState is Send + Sync.
@@ -85,6 +130,7 @@ impl EvalState {
             config: RwLock::new(HashMap::new()),
             variables: RwLock::new(HashMap::new()),
             resources: RwLock::new(HashMap::new()),
+            resource_groups: RwLock::new(HashMap::new()),
             outputs: Mutex::new(HashMap::new()),
             diags: Mutex::new(Diagnostics::new()),
             resource_counter: AtomicU32::new(0),
@@ -93,10 +139,81 @@ impl EvalState {
             default_providers: Mutex::new(HashMap::new()),
             stack_ref_cache: Mutex::new(HashMap::new()),
             starlark_runtime: RwLock::new(None),
+            value_bytes: AtomicUsize::new(0),
+            registrations: Mutex::new(HashMap::new()),
+            skipped: RwLock::new(HashSet::new()),
+            destroy_plan: Mutex::new(Vec::new()),
+            timings: Mutex::new(TimingReport::default()),
+            random_values: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Empties the state for another evaluation, keeping the capacity the
+    /// maps have grown to.
+    pub fn reset(&mut self) {
+        fn lock<T>(m: &mut Mutex<T>) -> &mut T {
+            m.get_mut().unwrap_or_else(|e| e.into_inner())
+        }
+        fn write<T>(m: &mut RwLock<T>) -> &mut T {
+            m.get_mut().unwrap_or_else(|e| e.into_inner())
         }
+        write(&mut self.config).clear();
+        write(&mut self.variables).clear();
+        write(&mut self.resources).clear();
+        write(&mut self.resource_groups).clear();
+        lock(&mut self.outputs).clear();
+        lock(&mut self.diags).clear();
+        *self.resource_counter.get_mut() = 0;
+        lock(&mut self.resource_indices).clear();
+        write(&mut self.poisoned).clear();
+        lock(&mut self.default_providers).clear();
+        lock(&mut self.stack_ref_cache).clear();
+        *write(&mut self.starlark_runtime) = None;
+        *self.value_bytes.get_mut() = 0;
+        lock(&mut self.registrations).clear();
+        write(&mut self.skipped).clear();
+        lock(&mut self.destroy_plan).clear();
+        *lock(&mut self.timings) = TimingReport::default();
+        lock(&mut self.random_values).clear();
+    }
+}
+
+impl Default for EvalState {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
+/// What [`Evaluator::evaluate_template`] does with the resources it reaches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EvalMode {
+    /// Register every resource, as for `pulumi up` and `pulumi preview`.
+    #[default]
+    Update,
+    /// Resolve resources read-only: `get` resources and stack references are
+    /// read and invokes run, but no other resource is registered. References
+    /// to the resources that are not registered evaluate to unknown.
+    Refresh,
+    /// List the resources in the order they would be deleted, without
+    /// evaluating any expression or calling the engine. See
+    /// [`Evaluator::destroy_plan`].
+    DestroyPlan,
+}
+
+/// A resource listed by [`EvalMode::DestroyPlan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedResource {
+    pub logical_name: String,
+    /// The canonical type token.
+    pub type_token: String,
+    /// The resource name: its `name` if set to a literal, else the logical
+    /// name.
+    pub name: String,
+    /// Logical names of the resources it depends on, directly or through
+    /// variables. They are deleted after it.
+    pub dependencies: Vec<String>,
+}
+
 /// The main evaluator that walks a template in dependency order
 /// and evaluates expressions, config, variables, and resources.
 ///
@@ -118,6 +235,8 @@ pub struct Evaluator<'schema, C: ResourceCallback = NoopCallback> {
     pub organization: String,
     /// The root directory of the project.
     pub root_directory: String,
+    /// Arguments passed to the program.
+    pub args: Vec<String>,
     /// Whether we're in preview mode (dry run).
     pub dry_run: bool,
     /// URN of the root stack resource (set during Run).
@@ -125,17 +244,98 @@ pub struct Evaluator<'schema, C: ResourceCallback = NoopCallback> {
     /// Optional source file map for multi-file rich error messages.
     /// Maps logical name → source filename.
     pub source_map: Option<Arc<HashMap<String, String>>>,
+    /// The parsed files the template's spans point into. When set, error
+    /// messages start with the `file:line:col` of their span.
+    pub sources: Option<Arc<SourceArena>>,
+    /// Resolve relative paths in `fn::readFile`, `fn::fileAsset`, and similar
+    /// builtins against the project directory instead of the directory of
+    /// the file declaring them (the `projectRelativePaths` runtime option).
+    pub project_relative_paths: bool,
+    /// Keep evaluating after an error, skipping only the entries that depend
+    /// on a failed one, so every independent failure is reported in one run
+    /// (the `continueOnError` runtime option). Otherwise evaluation stops at
+    /// the first level with an error.
+    pub continue_on_error: bool,
+    /// Report the check failures a provider returns for `fn::invoke`
+    /// arguments or resource properties against the expression that
+    /// produced the rejected value, at its span and naming its path in the
+    /// template (the `richCheckFailures` runtime option). Otherwise each
+    /// failure is a flat message naming the provider's property path.
+    pub rich_check_failures: bool,
+    /// Whether `fn::env` may read the host's environment variables (the
+    /// `allowEnv` runtime option). Otherwise `fn::env` fails, so a template
+    /// only sees the values its stack configuration gives it.
+    pub allow_env: bool,
+    /// Type tokens (`pkg:index:Name`) of the components declared by the
+    /// template being served. A resource of one of these types, written
+    /// `pkg:index:Name` or `pkg:Name`, instantiates that component: it is
+    /// registered as a remote component for the engine to construct.
+    pub local_components: HashSet<String>,
+    /// What evaluation does with resources. Defaults to [`EvalMode::Update`].
+    pub mode: EvalMode,
+    /// When set, only these entries and the entries they depend on are
+    /// evaluated, like `pulumi up --target`. Each target is a logical name or
+    /// a resource URN, which matches the resource with that name.
+    pub targets: Option<Vec<String>>,
     /// Optional schema store for provider metadata (output properties, secrets, aliases).
     pub schema_store: Option<&'schema SchemaStore>,
     /// Package references: package name → package ref UUID.
     /// Populated by runner.rs via RegisterPackage RPC before evaluation.
     pub package_refs: HashMap<String, String>,
+    /// Concrete versions that package version requirements (`^4.1`) resolved
+    /// to, keyed by package name. A resource or invoke whose `version` is a
+    /// requirement is sent with this version, or none if it is missing.
+    pub package_versions: HashMap<String, String>,
     /// Parallelism level: number of concurrent resource registrations per level.
     /// 0 or 1 means sequential (default). >1 enables parallel registration.
     pub parallel: i32,
     /// Component parent URN: when evaluating a component's inner resources,
     /// this is set so that resources without an explicit parent inherit the component.
     pub component_parent_urn: Option<String>,
+    /// Provider references a component's inner resources inherit, keyed by
+    /// package name. Used when a resource sets no provider of its own.
+    pub component_providers: HashMap<String, String>,
+    /// URNs every inner resource of a component depends on.
+    pub component_depends_on: Vec<String>,
+    /// Whether a component's inner resources are protected.
+    pub component_protect: bool,
+    /// Host-provided `fn::<namespace>:<name>` builtins.
+    pub extensions: BuiltinRegistry,
+    /// Host-provided hooks applied to resource outputs after registration.
+    pub output_hooks: Vec<Arc<dyn ResourceOutputHook>>,
+    /// Opens `fn::open::<provider>` calls. Without one, such calls fail.
+    pub open_resolver: Option<Arc<dyn OpenResolver>>,
+    /// Decrypts `fn::encrypted` values. Without one, such values fail.
+    pub decrypter: Option<Arc<dyn Decrypter>>,
+    /// Where `fn::readFile`, `fn::templateFile`, `fn::validateJson`, and the
+    /// file asset checks read files. Defaults to [`OsFs`].
+    pub fs: Arc<dyn FsProvider>,
+    /// Recursion, string size, and memory limits.
+    pub limits: EvalLimits,
+    /// Whether to fail a resource whose URN matches one already registered
+    /// or read, naming both declarations (the
+    /// `checkDuplicateRegistrations` runtime option). The engine would
+    /// otherwise reject the second registration with a duplicate URN error
+    /// that does not say where either came from.
+    pub check_duplicate_registrations: bool,
+    /// Resource types that may not be registered. Defaults to the types Go's
+    /// YAML runtime blocks; hosts can block or allow more.
+    pub blocklist: Blocklist,
+    /// Receives progress events and the timing report.
+    pub progress: Mutex<Box<dyn ProgressSink + Send>>,
+    /// When set, evaluation ends with a warning listing this many of the
+    /// slowest resources and invokes (the `timingSummary` runtime option).
+    pub timing_summary: Option<usize>,
+    /// Values stable `fn::uuid` and `fn::randomString` calls returned in the
+    /// previous deployment, keyed by call (see [`stable_random`]). When set,
+    /// calls reuse them instead of generating new values, and record what
+    /// they return in [`Evaluator::random_values`] (the `stableRandom`
+    /// runtime option).
+    pub stable_random: Option<HashMap<String, String>>,
+    /// Values of pure variables from earlier evaluations, keyed by their
+    /// expression and the values it references (see [`cache`]). When set,
+    /// such a variable is evaluated only when the cache has no value for it.
+    pub eval_cache: Option<Arc<EvalCache>>,
     /// The callback for resource operations (registration, invoke, etc.).
     callback: C,
     /// Interior-mutable evaluation state.
@@ -164,23 +364,216 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
             cwd,
             organization: String::new(),
             root_directory: String::new(),
+            args: Vec::new(),
             dry_run,
             callback,
             stack_urn: None,
             source_map: None,
+            sources: None,
+            project_relative_paths: false,
+            continue_on_error: false,
+            rich_check_failures: false,
+            allow_env: false,
+            local_components: HashSet::new(),
+            mode: EvalMode::default(),
+            targets: None,
             schema_store: None,
             package_refs: HashMap::new(),
+            package_versions: HashMap::new(),
             parallel: 0,
             component_parent_urn: None,
+            component_providers: HashMap::new(),
+            component_depends_on: Vec::new(),
+            component_protect: false,
+            extensions: BuiltinRegistry::new(),
+            output_hooks: Vec::new(),
+            open_resolver: None,
+            decrypter: None,
+            fs: Arc::new(OsFs),
+            limits: EvalLimits::default(),
+            check_duplicate_registrations: false,
+            blocklist: Blocklist::default(),
+            progress: Mutex::new(Box::new(NoopProgress)),
+            timing_summary: None,
+            stable_random: None,
+            eval_cache: None,
             state: EvalState::new(),
         }
     }
 
+    /// Creates a new evaluator for `context` with the given callback.
+    pub fn from_context(context: &EvaluatorContext, callback: C) -> Self {
+        let mut eval = Self::with_callback(
+            context.project.clone(),
+            context.stack.clone(),
+            context.cwd.clone(),
+            context.dry_run,
+            callback,
+        );
+        eval.organization = context.organization.clone();
+        eval.root_directory = context.root_directory.clone();
+        eval.args = context.args.clone();
+        eval
+    }
+
+    /// Clears the results of the last evaluation so that another template
+    /// can be evaluated, keeping the settings, the callback, and the capacity
+    /// the state has grown to. The component settings are cleared as well.
+    pub fn reset(&mut self) {
+        self.state.reset();
+        self.stack_urn = None;
+        self.component_parent_urn = None;
+        self.component_providers.clear();
+        self.component_depends_on.clear();
+        self.component_protect = false;
+    }
+
+    /// Consumes the evaluator, returning its callback and state.
+    pub fn into_parts(self) -> (C, EvalState) {
+        (self.callback, self.state)
+    }
+
     /// Returns a reference to the callback.
     pub fn callback(&self) -> &C {
         &self.callback
     }
 
+    /// Registers a host-provided builtin callable as `fn::<namespace>:<name>`.
+    ///
+    /// See [`BuiltinRegistry`] for the namespacing rules.
+    pub fn register_builtin<F>(&mut self, token: &str, func: F) -> Result<(), ExtensionError>
+    where
+        F: Fn(&Value<'static>) -> Result<Value<'static>, String> + Send + Sync + 'static,
+    {
+        self.extensions.register(token, func)
+    }
+
+    /// Adds a hook that transforms resource outputs after registration, run
+    /// after the hooks added before it.
+    ///
+    /// See [`crate::eval::hooks`].
+    pub fn add_output_hook<H>(&mut self, hook: H)
+    where
+        H: ResourceOutputHook + 'static,
+    {
+        self.output_hooks.push(Arc::new(hook));
+    }
+
+    /// Sets the resolver for `fn::open::<provider>` calls.
+    ///
+    /// See [`crate::eval::esc`].
+    pub fn set_open_resolver<R>(&mut self, resolver: R)
+    where
+        R: OpenResolver + 'static,
+    {
+        self.open_resolver = Some(Arc::new(resolver));
+    }
+
+    /// Sets the decrypter for `fn::encrypted` values.
+    ///
+    /// See [`crate::eval::encrypted`].
+    pub fn set_decrypter<D>(&mut self, decrypter: D)
+    where
+        D: Decrypter + 'static,
+    {
+        self.decrypter = Some(Arc::new(decrypter));
+    }
+
+    /// Sets the filesystem file-reading builtins use.
+    ///
+    /// See [`crate::vfs`].
+    pub fn set_fs<F>(&mut self, fs: F)
+    where
+        F: FsProvider + 'static,
+    {
+        self.fs = Arc::new(fs);
+    }
+
+    /// Checks that the inputs of an engine request fit the payload limit.
+    ///
+    /// On failure, records an error naming the largest properties, since the
+    /// usual cause is file contents inlined with `fn::readFile`.
+    fn check_payload_size(&self, what: &str, inputs: &HashMap<String, Value<'static>>) -> bool {
+        let mut sizes: Vec<(&str, usize)> = inputs
+            .iter()
+            .map(|(k, v)| (k.as_str(), k.len() + approx_heap_size(v)))
+            .collect();
+        let total: usize = sizes.iter().map(|(_, n)| n).sum();
+        if total <= self.limits.max_payload_bytes {
+            return true;
+        }
+        sizes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        let largest: Vec<String> = sizes
+            .iter()
+            .take(3)
+            .map(|(k, n)| format!("{} ({})", k, format_bytes(*n)))
+            .collect();
+        self.state.diags.lock().unwrap().error(
+            None,
+            format!(
+                "inputs of {} are about {}, exceeding the {} request limit",
+                what,
+                format_bytes(total),
+                format_bytes(self.limits.max_payload_bytes)
+            ),
+            format!(
+                "largest properties: {}; pass large file contents as assets \
+                 (fn::fileAsset, fn::fileArchive) instead of inlining them with fn::readFile",
+                largest.join(", ")
+            ),
+        );
+        false
+    }
+
+    /// Hashes the file assets in a resource's inputs and checks that its file
+    /// archives exist (see [`assets`]). Skipped during preview.
+    ///
+    /// Records an error per missing file and returns false if there were any.
+    fn check_file_assets(
+        &self,
+        logical_name: &str,
+        inputs: &mut HashMap<String, Value<'static>>,
+    ) -> bool {
+        if self.dry_run {
+            return true;
+        }
+        let base = std::path::Path::new(&self.cwd);
+        let mut errors = Vec::new();
+        for (key, value) in inputs.iter_mut() {
+            assets::resolve_files(value, base, self.fs.as_ref(), key, &mut errors);
+        }
+        errors.sort_by(|a, b| a.property.cmp(&b.property));
+        let mut diags = self.state.diags.lock().unwrap();
+        for error in &errors {
+            diags.error(
+                None,
+                format!("resource '{}' {}", logical_name, error),
+                format!("resolved to {}", error.resolved.display()),
+            );
+        }
+        errors.is_empty()
+    }
+
+    /// Adds the approximate size of a stored value to the memory budget.
+    ///
+    /// Returns false (and records an error) once the budget is exhausted.
+    fn charge_value(&self, name: &str, value: &Value<'_>) -> bool {
+        let size = approx_heap_size(value);
+        let total = self.state.value_bytes.fetch_add(size, Ordering::Relaxed) + size;
+        if total <= self.limits.max_value_bytes {
+            return true;
+        }
+        self.state.diags.lock().unwrap().error(
+            None,
+            format!(
+                "evaluating '{}' exceeds the memory budget of {} bytes",
+                name, self.limits.max_value_bytes
+            ),
+            "raise PULUMI_YAML_MAX_VALUE_BYTES to allow larger templates",
+        );
+        false
+    }
+
     // -----------------------------------------------------------------------
     // Accessor methods for post-evaluation inspection
     // -----------------------------------------------------------------------
@@ -190,6 +583,12 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
         self.state.diags.lock().unwrap().has_errors()
     }
 
+    /// Returns the resources listed by an [`EvalMode::DestroyPlan`]
+    /// evaluation, in the order they would be deleted.
+    pub fn destroy_plan(&self) -> Vec<PlannedResource> {
+        self.state.destroy_plan.lock().unwrap().clone()
+    }
+
     /// Collects error diagnostic summaries.
     pub fn diag_errors(&self) -> Vec<String> {
         let diags = self.state.diags.lock().unwrap();
@@ -200,6 +599,38 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
             .collect()
     }
 
+    /// Collects error diagnostics as messages, each summary followed by its
+    /// detail on the next line when it has one. With `sources` set, a
+    /// message with a span starts with its `file:line:col`, and one with a
+    /// related span ends with a `see file:line:col` line.
+    pub fn diag_error_messages(&self) -> Vec<String> {
+        let diags = self.state.diags.lock().unwrap();
+        let mut files = self.sources.as_deref().map(FileTable::new);
+        (&*diags)
+            .into_iter()
+            .filter(|d| d.is_error())
+            .map(|d| {
+                let summary = match (files.as_mut(), d.span) {
+                    (Some(files), Some(span)) if files.contains(span.file) => {
+                        format!("{}: {}", files.format_span(span), d.summary)
+                    }
+                    _ => d.summary.clone(),
+                };
+                let mut message = if d.detail.is_empty() {
+                    summary
+                } else {
+                    format!("{}\n{}", summary, d.detail)
+                };
+                if let (Some(files), Some(related)) = (files.as_mut(), d.related) {
+                    if files.contains(related.file) {
+                        message.push_str(&format!("\nsee {}", files.format_span(related)));
+                    }
+                }
+                message
+            })
+            .collect()
+    }
+
     /// Collects warning diagnostic summaries.
     pub fn diag_warnings(&self) -> Vec<String> {
         let diags = self.state.diags.lock().unwrap();
@@ -210,11 +641,67 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
             .collect()
     }
 
+    /// Returns the time spent on each level and entry.
+    pub fn timing_report(&self) -> TimingReport {
+        self.state.timings.lock().unwrap().clone()
+    }
+
+    /// Returns the values stable `fn::uuid` and `fn::randomString` calls
+    /// returned, to be passed back as [`Evaluator::stable_random`] next time.
+    pub fn random_values(&self) -> BTreeMap<String, String> {
+        self.state.random_values.lock().unwrap().clone()
+    }
+
     /// Drains and returns all outputs.
     pub fn take_outputs(&self) -> HashMap<String, Value<'static>> {
         std::mem::take(&mut *self.state.outputs.lock().unwrap())
     }
 
+    /// Returns the URNs of the resources each output of `template` depends
+    /// on, directly or through variables. Outputs that depend on no
+    /// registered resource are left out.
+    pub fn output_dependencies<'t>(
+        &self,
+        template: &'t TemplateDecl<'t>,
+    ) -> HashMap<String, Vec<String>> {
+        let mut known_names: HashMap<&str, &str> = template
+            .resources
+            .iter()
+            .map(|r| (r.logical_name.as_ref(), "resource"))
+            .collect();
+        let variables: HashMap<&str, &Expr<'t>> = template
+            .variables
+            .iter()
+            .map(|v| (v.key.as_ref(), &v.value))
+            .collect();
+        known_names.extend(variables.keys().map(|k| (*k, "variable")));
+
+        let mut result = HashMap::new();
+        for output in &template.outputs {
+            let mut pending = HashSet::new();
+            collect_expr_deps(&output.value, &known_names, &mut pending);
+            let mut pending: Vec<&str> = pending.into_iter().collect();
+            let mut seen = HashSet::new();
+            let mut urns = std::collections::BTreeSet::new();
+            while let Some(name) = pending.pop() {
+                if !seen.insert(name) {
+                    continue;
+                }
+                if let Some(expr) = variables.get(name) {
+                    let mut deps = HashSet::new();
+                    collect_expr_deps(expr, &known_names, &mut deps);
+                    pending.extend(deps);
+                } else {
+                    urns.extend(self.registered_urns(name));
+                }
+            }
+            if !urns.is_empty() {
+                result.insert(output.key.to_string(), urns.into_iter().collect());
+            }
+        }
+        result
+    }
+
     /// Gets a cloned output value by key.
     pub fn get_output(&self, key: &str) -> Option<Value<'static>> {
         self.state.outputs.lock().unwrap().get(key).cloned()
@@ -263,6 +750,7 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
     /// 3. Walks nodes level-by-level in dependency order
     /// 4. Evaluates config, variables, and resources
     /// 5. Evaluates output declarations
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(project = %self.project_name, stack = %self.stack_name, dry_run = self.dry_run)))]
     pub fn evaluate_template<'t>(
         &self,
         template: &'t TemplateDecl<'t>,
@@ -320,6 +808,15 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
                 Cow::Borrowed("rootDirectory"),
                 Value::String(Cow::Owned(self.root_directory.clone())),
             ),
+            (
+                Cow::Borrowed("args"),
+                Value::List(
+                    self.args
+                        .iter()
+                        .map(|a| Value::String(Cow::Owned(a.clone())))
+                        .collect(),
+                ),
+            ),
         ]);
         self.state
             .variables
@@ -352,80 +849,345 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
             }
         }
 
+        if self.mode == EvalMode::DestroyPlan {
+            let plan = destroy_order(template, &result, |raw| self.resolve_type_token(raw).token);
+            *self.state.destroy_plan.lock().unwrap() = plan;
+            return;
+        }
+
+        if let Some(ref targets) = self.targets {
+            let untargeted = self.untargeted_nodes(template, targets, &result);
+            *self.state.skipped.write().unwrap() = untargeted;
+        }
+
         // Compute topological levels for level-aware evaluation
         let levels = topological_levels(&result.order, &result.deps);
 
         // Evaluate nodes level-by-level.
         // Within each level, nodes have no inter-dependencies and can be
-        // processed in parallel when self.parallel > 1.
-        for level in &levels {
-            if self.has_errors() {
-                break;
-            }
+        // processed in parallel when self.parallel > 1. One pool, capped at
+        // `parallel` threads, runs the levels and the instances of `count:`
+        // and `range:` entries alike.
+        let eval_levels = || {
+            for (index, level) in levels.iter().enumerate() {
+                if self.should_stop() {
+                    break;
+                }
+                #[cfg(feature = "tracing")]
+                tracing::debug!(level = index, nodes = level.len(), "evaluating level");
+                self.progress
+                    .lock()
+                    .unwrap()
+                    .on_level_start(index, level.len());
+                let level_start = Instant::now();
 
-            if self.parallel > 1 && level.len() > 1 {
-                // Parallel: all nodes in this level are independent.
-                // Build a scoped thread pool capped at min(parallel, level size).
-                let num_threads = (self.parallel as usize).min(level.len());
-                let pool = rayon::ThreadPoolBuilder::new()
-                    .num_threads(num_threads)
-                    .build()
-                    .expect("failed to build rayon thread pool");
-                pool.install(|| {
+                if self.parallel > 1 && level.len() > 1 {
+                    // Parallel: all nodes in this level are independent.
                     use rayon::prelude::*;
                     level.par_iter().for_each(|node_name| {
-                        self.eval_node(node_name, template, raw_config, secret_keys);
+                        self.eval_node(node_name, index, template, raw_config, secret_keys);
                     });
-                });
-            } else {
-                // Sequential: default behavior (parallel <= 1 or single-node level).
-                for node_name in level {
-                    if self.has_errors() {
-                        break;
+                } else {
+                    // Sequential: default behavior (parallel <= 1 or single-node level).
+                    for node_name in level {
+                        if self.should_stop() {
+                            break;
+                        }
+                        self.eval_node(node_name, index, template, raw_config, secret_keys);
                     }
-                    self.eval_node(node_name, template, raw_config, secret_keys);
                 }
+                self.state
+                    .timings
+                    .lock()
+                    .unwrap()
+                    .levels
+                    .push(level_start.elapsed());
             }
+        };
+        if self.parallel > 1 {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(self.parallel as usize)
+                .build()
+                .expect("failed to build rayon thread pool")
+                .install(eval_levels);
+        } else {
+            eval_levels();
         }
 
-        // Evaluate outputs
+        // Evaluate outputs. Those depending on an untargeted entry have no
+        // value this run and are left out rather than recorded as unknown.
         for output in &template.outputs {
-            if self.state.diags.lock().unwrap().has_errors() {
+            if self.should_stop() {
                 break;
             }
+            if self.targets.is_some() && self.references_skipped(&output.value) {
+                continue;
+            }
             self.eval_output(output);
         }
+
+        let report = self.timing_report();
+        if let Some(n) = self.timing_summary {
+            self.state
+                .diags
+                .lock()
+                .unwrap()
+                .warning(None, report.render(n), "");
+        }
+        self.progress.lock().unwrap().on_timing_report(&report);
+    }
+
+    /// Returns the entries that neither match a target nor are depended on,
+    /// directly or transitively, by one that does. Warns about targets that
+    /// match nothing.
+    fn untargeted_nodes(
+        &self,
+        template: &TemplateDecl<'_>,
+        targets: &[String],
+        result: &SortResultWithDeps,
+    ) -> HashSet<String> {
+        let mut pending: Vec<&str> = Vec::new();
+        for target in targets {
+            let urn = target.strip_prefix("urn:pulumi:").map(parse_target_urn);
+            let before = pending.len();
+            for node in &result.order {
+                let matches = match &urn {
+                    Some(Some(urn)) => template
+                        .resources
+                        .iter()
+                        .find(|r| r.logical_name.as_ref() == node.as_str())
+                        .is_some_and(|r| self.resource_matches_urn(node, &r.resource, urn)),
+                    Some(None) => false,
+                    None => node == target,
+                };
+                if matches {
+                    pending.push(node);
+                }
+            }
+            if pending.len() == before {
+                self.state.diags.lock().unwrap().warning(
+                    None,
+                    format!("target '{}' does not match any entry", target),
+                    "",
+                );
+            }
+        }
+
+        let mut needed: HashSet<&str> = HashSet::new();
+        while let Some(node) = pending.pop() {
+            if needed.insert(node) {
+                if let Some(deps) = result.deps.get(node) {
+                    pending.extend(deps.iter().map(|d| d.as_str()));
+                }
+            }
+        }
+        result
+            .order
+            .iter()
+            .filter(|n| !needed.contains(n.as_str()))
+            .cloned()
+            .collect()
+    }
+
+    /// Whether `expr` refers to a skipped entry.
+    fn references_skipped(&self, expr: &Expr<'_>) -> bool {
+        let skipped = self.state.skipped.read().unwrap();
+        let mut refs = HashSet::new();
+        walk_expr(expr, &AllRefsCollector, &mut refs);
+        refs.iter().any(|r| skipped.contains(*r))
+    }
+
+    /// Whether the resource entry `logical_name` registers the resource
+    /// `urn` names: the stack, project, and type must match, and the name
+    /// must be the entry's name or, for a `range` entry named after its
+    /// logical name, one of its `<name>-<key>` instances.
+    fn resource_matches_urn(
+        &self,
+        logical_name: &str,
+        resource: &ResourceDecl<'_>,
+        urn: &TargetUrn<'_>,
+    ) -> bool {
+        if urn.stack != self.stack_name || urn.project != self.project_name {
+            return false;
+        }
+        let own_type = self.resolve_type_token(resource.type_.as_ref()).token;
+        if own_type != self.resolve_type_token(urn.type_token).token {
+            return false;
+        }
+        match resource.static_name(logical_name) {
+            Some(name) if name == urn.name => true,
+            _ if resource.range.is_some() && resource.name.is_none() => urn
+                .name
+                .strip_prefix(logical_name)
+                .is_some_and(|rest| rest.starts_with('-')),
+            _ => false,
+        }
+    }
+
+    /// Resolves `raw` to the token the resource is registered with: the
+    /// token of a component declared in this project, the type the schema
+    /// declares, or else the heuristic guess.
+    fn resolve_type_token(&self, raw: &str) -> ResolvedTypeToken {
+        packages::resolve_type_token(
+            raw,
+            &self.local_components,
+            self.schema_store.map(|s| s as &dyn TypeTokenResolver),
+        )
+    }
+
+    /// Replaces a version requirement with the version it resolved to (see
+    /// [`Evaluator::package_versions`]). Exact versions are returned as is.
+    fn concrete_version(&self, pkg_name: &str, version: String) -> String {
+        if version.is_empty() {
+            return version;
+        }
+        match VersionConstraint::parse(&version) {
+            Ok(VersionConstraint::Range(_)) => self
+                .package_versions
+                .get(pkg_name)
+                .cloned()
+                .unwrap_or_default(),
+            _ => version,
+        }
+    }
+
+    /// The provider version a resource will be registered with, if known:
+    /// its own exact `version` option, else the version its package resolved
+    /// to, else the version of the loaded schema.
+    fn provider_version(
+        &self,
+        type_token: &str,
+        resource: &ResourceDecl<'_>,
+    ) -> Option<semver::Version> {
+        if let Some(version) = resource.options.version.as_deref() {
+            if let Ok(VersionConstraint::Exact(exact)) = VersionConstraint::parse(version) {
+                return Some(exact);
+            }
+        }
+        let pkg_name = resolve_pkg_name(type_token);
+        let exact = |v: &str| match VersionConstraint::parse(v) {
+            Ok(VersionConstraint::Exact(exact)) => Some(exact),
+            _ => None,
+        };
+        self.package_versions
+            .get(pkg_name)
+            .and_then(|v| exact(v))
+            .or_else(|| {
+                let store = self.schema_store?;
+                exact(&store.packages().get(pkg_name)?.version)
+            })
+    }
+
+    /// Returns what the current `fn::uuid` or `fn::randomString` call returned
+    /// in the previous deployment if `reuse` accepts it, else a new value
+    /// from `generate`. See [`Evaluator::stable_random`].
+    fn stable_random_value<'e>(
+        &self,
+        reuse: impl Fn(&str) -> bool,
+        generate: impl FnOnce() -> Option<Value<'e>>,
+    ) -> Option<Value<'e>> {
+        let (Some(prior), Some(key)) = (&self.stable_random, stable_random::next_key()) else {
+            return generate();
+        };
+        let value = match prior.get(&key).filter(|v| reuse(v)) {
+            Some(v) => v.clone(),
+            None => match generate()? {
+                Value::String(s) => s.into_owned(),
+                other => return Some(other),
+            },
+        };
+        self.state
+            .random_values
+            .lock()
+            .unwrap()
+            .insert(key, value.clone());
+        Some(Value::String(Cow::Owned(value)))
+    }
+
+    /// Whether evaluation should stop because of an earlier error.
+    fn should_stop(&self) -> bool {
+        !self.continue_on_error && self.has_errors()
     }
 
-    /// Evaluates a config entry.
     /// Dispatches a single node for evaluation (config, variable, or resource).
+    ///
+    /// A node that produces no value is poisoned, so that its dependents are
+    /// skipped without further errors when evaluation continues past it.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(node = node_name)))]
     fn eval_node<'t>(
         &self,
         node_name: &str,
+        level: usize,
         template: &'t TemplateDecl<'t>,
         raw_config: &RawConfig,
         secret_keys: &[String],
     ) {
-        if let Some(entry) = template.config.iter().find(|e| e.key.as_ref() == node_name) {
-            self.eval_config_entry(entry, raw_config, secret_keys);
-            return;
-        }
-        if let Some(entry) = template
-            .variables
-            .iter()
-            .find(|e| e.key.as_ref() == node_name)
-        {
-            self.eval_variable(entry);
+        if self.state.skipped.read().unwrap().contains(node_name) {
             return;
         }
-        if let Some(entry) = template
-            .resources
-            .iter()
-            .find(|e| e.logical_name.as_ref() == node_name)
-        {
-            self.eval_resource_entry(entry);
+        let _source = self.enter_source(node_name);
+        let _entry = stable_random::EntryGuard::enter(node_name.to_string());
+        let start = Instant::now();
+        timing::take_rpc_time();
+        let (kind, evaluated) =
+            if let Some(entry) = template.config.iter().find(|e| e.key.as_ref() == node_name) {
+                self.eval_config_entry(entry, raw_config, secret_keys);
+                (
+                    "config",
+                    self.state.config.read().unwrap().contains_key(node_name),
+                )
+            } else if let Some(entry) = template
+                .variables
+                .iter()
+                .find(|e| e.key.as_ref() == node_name)
+            {
+                self.eval_variable(entry);
+                let kind = match entry.value {
+                    Expr::Invoke(..) => "invoke",
+                    _ => "variable",
+                };
+                (
+                    kind,
+                    self.state.variables.read().unwrap().contains_key(node_name),
+                )
+            } else if let Some(entry) = template
+                .resources
+                .iter()
+                .find(|e| e.logical_name.as_ref() == node_name)
+            {
+                self.eval_resource_entry(entry);
+                let registered = self.state.resources.read().unwrap().contains_key(node_name)
+                    || self
+                        .state
+                        .resource_groups
+                        .read()
+                        .unwrap()
+                        .contains_key(node_name);
+                if registered {
+                    self.progress.lock().unwrap().on_resource_done(node_name);
+                }
+                (
+                    "resource",
+                    registered || self.state.skipped.read().unwrap().contains(node_name),
+                )
+            } else {
+                // "pulumi" settings node — no-op
+                return;
+            };
+        self.state.timings.lock().unwrap().nodes.push(NodeTiming {
+            name: node_name.to_string(),
+            kind,
+            level,
+            total: start.elapsed(),
+            rpc: timing::take_rpc_time(),
+        });
+        if !evaluated {
+            self.state
+                .poisoned
+                .write()
+                .unwrap()
+                .insert(node_name.to_string());
         }
-        // "pulumi" settings node — no-op
     }
 
     fn eval_config_entry<'t>(
@@ -443,11 +1205,15 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
             .as_ref()
             .and_then(|t| ConfigType::parse(t.as_ref()));
 
-        // Evaluate the default value if present
+        // Evaluate the default value if present. An `fn::open` default is
+        // not opened when the stack's config sets the key.
+        let is_set = raw_config.contains_key(&format!("{}:{}", self.project_name, key))
+            || raw_config.contains_key(key);
         let default_value = entry
             .param
             .default
             .as_ref()
+            .filter(|expr| !(is_set && matches!(expr, Expr::Open(..))))
             .and_then(|expr| self.eval_expr(expr))
             .map(|v| v.into_owned());
 
@@ -485,30 +1251,179 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
     /// Evaluates a variable entry.
     fn eval_variable<'t>(&self, entry: &'t VariableEntry<'t>) {
         let key = entry.key.as_ref();
-        match self.eval_expr(&entry.value) {
-            Some(value) => {
+        match self.eval_cached(&entry.value) {
+            Some(value) if self.charge_value(key, &value) => {
                 self.state
                     .variables
                     .write()
                     .unwrap()
                     .insert(key.to_string(), value.into_owned());
             }
-            None => {
+            _ => {
                 // Mark as poisoned to prevent cascading errors
                 self.state.poisoned.write().unwrap().insert(key.to_string());
             }
         }
     }
 
+    /// Evaluates `expr`, going through [`Evaluator::eval_cache`] when it is
+    /// set and `expr` is pure. A value is only cached when evaluating it
+    /// reported no diagnostics, so a cache hit never hides a warning.
+    fn eval_cached<'e>(&self, expr: &'e Expr<'e>) -> Option<Value<'e>> {
+        let Some(cache) = &self.eval_cache else {
+            return self.eval_expr(expr);
+        };
+        let Some(key) = cache::node_key(expr, |name| self.cacheable_ref(name)) else {
+            return self.eval_expr(expr);
+        };
+        if let Some(value) = cache.get(&key) {
+            return Some(value);
+        }
+        let diags_before = self.state.diags.lock().unwrap().len();
+        let value = self.eval_expr(expr)?;
+        if self.state.diags.lock().unwrap().len() == diags_before {
+            cache.insert(key, value.clone().into_owned());
+        }
+        Some(value)
+    }
+
+    /// Returns the value a reference to `name` resolves to, for hashing into
+    /// a cache key. Resources, and entries that failed or were skipped, have
+    /// none.
+    fn cacheable_ref(&self, name: &str) -> Option<Value<'static>> {
+        if self.state.poisoned.read().unwrap().contains(name)
+            || self.state.skipped.read().unwrap().contains(name)
+            || self.state.resources.read().unwrap().contains_key(name)
+            || self
+                .state
+                .resource_groups
+                .read()
+                .unwrap()
+                .contains_key(name)
+        {
+            return None;
+        }
+        let stripped = config::strip_config_namespace(&self.project_name, name);
+        let cfg = {
+            let guard = self.state.config.read().unwrap();
+            guard.get(name).or_else(|| guard.get(stripped)).cloned()
+        };
+        cfg.or_else(|| self.state.variables.read().unwrap().get(name).cloned())
+    }
+
+    /// Claims the URN of a resource about to be registered or read. Returns
+    /// false, recording an error at `span` that names both call sites, if
+    /// another resource already claimed it.
+    fn claim_registration(
+        &self,
+        logical_name: &str,
+        type_token: &str,
+        resource_name: &str,
+        parent_urn: Option<&str>,
+        span: Option<Span>,
+    ) -> bool {
+        if !self.check_duplicate_registrations {
+            return true;
+        }
+        let urn = self.registration_urn(type_token, resource_name, parent_urn);
+        let site = match scope::source_file() {
+            Some(file) => format!("resource '{}' in {}", logical_name, file),
+            None => format!("resource '{}'", logical_name),
+        };
+        let mut registrations = self.state.registrations.lock().unwrap();
+        match registrations.get(&urn) {
+            Some((first, first_span)) => {
+                let summary = format!(
+                    "duplicate registration of {} '{}': {} and {} both register it",
+                    type_token, resource_name, first, site
+                );
+                let detail = match self.locate(*first_span) {
+                    Some(location) => format!(
+                        "{} is declared at {}; resources with the same parent need distinct `name`s",
+                        first, location
+                    ),
+                    None => "resources with the same parent need distinct `name`s".to_string(),
+                };
+                drop(registrations);
+                self.state
+                    .diags
+                    .lock()
+                    .unwrap()
+                    .error(span, summary, detail);
+                false
+            }
+            None => {
+                registrations.insert(urn, (site, span));
+                true
+            }
+        }
+    }
+
+    /// The URN the engine gives a resource: its type is qualified by the
+    /// types of its parents, except the stack.
+    fn registration_urn(
+        &self,
+        type_token: &str,
+        resource_name: &str,
+        parent_urn: Option<&str>,
+    ) -> String {
+        let parent_type = parent_urn
+            .filter(|urn| Some(*urn) != self.stack_urn.as_deref())
+            .map(|urn| {
+                urn.strip_prefix("urn:pulumi:")
+                    .and_then(parse_qualified_type)
+                    .unwrap_or(urn)
+            })
+            .filter(|qualified| *qualified != "pulumi:pulumi:Stack");
+        let qualified_type = match parent_type {
+            Some(parent) => format!("{}${}", parent, type_token),
+            None => type_token.to_string(),
+        };
+        format!(
+            "urn:pulumi:{}::{}::{}::{}",
+            self.stack_name, self.project_name, qualified_type, resource_name
+        )
+    }
+
+    /// Renders `span` as `file:line:col` when [`Evaluator::sources`] holds
+    /// its file.
+    fn locate(&self, span: Option<Span>) -> Option<String> {
+        let span = span?;
+        let mut files = FileTable::new(self.sources.as_deref()?);
+        files.contains(span.file).then(|| files.format_span(span))
+    }
+
     /// Stores a resource state after successful registration or read.
     fn store_resource(
         &self,
         logical_name: &str,
-        resp: crate::eval::callback::RegisterResponse,
+        type_token: &str,
+        mut resp: crate::eval::callback::RegisterResponse,
         is_provider: bool,
         is_component: bool,
         is_default_provider: bool,
     ) {
+        if !self.output_hooks.is_empty() {
+            let resource = RegisteredResource {
+                logical_name,
+                type_token,
+                urn: &resp.urn,
+                id: &resp.id,
+                is_provider,
+                is_component,
+            };
+            for hook in &self.output_hooks {
+                if let Err(e) = hook.transform_outputs(&resource, &mut resp.outputs) {
+                    self.state.diags.lock().unwrap().error(
+                        None,
+                        format!("output hook failed for resource '{}': {}", logical_name, e),
+                        "",
+                    );
+                    return;
+                }
+            }
+        }
+
         let idx = self.state.resource_counter.fetch_add(1, Ordering::SeqCst);
         self.state
             .resource_indices
@@ -536,8 +1451,14 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
             }
         }
 
-        let state = ResourceState {
-            urn,
+        for value in resp.outputs.values() {
+            if !self.charge_value(logical_name, value) {
+                break;
+            }
+        }
+
+        let state = ResourceState {
+            urn,
             id,
             is_provider,
             is_component,
@@ -563,12 +1484,232 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
     }
 
     /// Evaluates a resource entry and registers it via the callback.
+    ///
+    /// The instances of a `count:` or `range:` entry are expanded once the
+    /// range is known and, as they cannot depend on each other, registered
+    /// in parallel like the nodes of a level when `parallel` is above 1. An
+    /// instance that fails does not stop the others; the entry's group lists
+    /// the instances that were registered.
     fn eval_resource_entry<'t>(&self, entry: &'t ResourceEntry<'t>) {
         let logical_name = entry.logical_name.as_ref();
+        let Some(range) = &entry.resource.range else {
+            self.eval_resource_instance(entry, logical_name, logical_name);
+            return;
+        };
+        let Some((instances, keyed)) = self.eval_range(logical_name, range) else {
+            self.state
+                .poisoned
+                .write()
+                .unwrap()
+                .insert(logical_name.to_string());
+            return;
+        };
+
+        // Each instance is registered with `range` bound to its key and
+        // value, and named `<name>-<key>` unless the entry sets a name.
+        let instances: Vec<RangeInstance> = instances
+            .into_iter()
+            .map(|(key, value)| {
+                let (name, suffix) = match &key {
+                    Value::String(k) => (format!("{}[{:?}]", logical_name, k), k.to_string()),
+                    other => {
+                        let index = other.to_string();
+                        (format!("{}[{}]", logical_name, index), index)
+                    }
+                };
+                RangeInstance {
+                    name,
+                    suffix,
+                    key,
+                    value,
+                }
+            })
+            .collect();
+        let register = |instance: &RangeInstance| {
+            if self.should_stop() {
+                return;
+            }
+            let binding = Value::Object(vec![
+                (Cow::Borrowed("key"), instance.key.clone()),
+                (Cow::Borrowed("value"), instance.value.clone()),
+            ]);
+            let _range = ScopeGuard::push(vec![(RANGE_BINDING.to_string(), binding)]);
+            let _entry = stable_random::EntryGuard::enter(instance.name.clone());
+            let default_name = format!("{}-{}", logical_name, instance.suffix);
+            self.eval_resource_instance(entry, &instance.name, &default_name);
+        };
+        if self.parallel > 1 && instances.len() > 1 {
+            // Runs on the evaluation's pool (see `evaluate_template`).
+            use rayon::prelude::*;
+            instances.par_iter().for_each(|instance| {
+                let _source = self.enter_source(logical_name);
+                register(instance);
+            });
+        } else {
+            instances.iter().for_each(register);
+        }
+
+        let names: Vec<(String, String)> = {
+            let resources = self.state.resources.read().unwrap();
+            instances
+                .into_iter()
+                .filter(|i| resources.contains_key(&i.name))
+                .map(|i| (i.suffix, i.name))
+                .collect()
+        };
+        let group = if keyed {
+            ResourceGroup::Object(names)
+        } else {
+            ResourceGroup::List(names.into_iter().map(|(_, name)| name).collect())
+        };
+        self.state
+            .resource_groups
+            .write()
+            .unwrap()
+            .insert(logical_name.to_string(), group);
+    }
+
+    /// Evaluates the `count:` or `range:` of a resource entry to the key and
+    /// value of each instance: the index twice for a count, the index and
+    /// element of a list, or the property name and value of an object. The
+    /// flag is set for an object, whose instances are keyed by name.
+    #[allow(clippy::type_complexity)]
+    fn eval_range(
+        &self,
+        logical_name: &str,
+        range: &ResourceRange<'_>,
+    ) -> Option<(Vec<(Value<'static>, Value<'static>)>, bool)> {
+        let value = self.eval_expr(range.expr())?.into_owned();
+        let is_secret = value.is_secret();
+        let keyed = matches!(value.unwrap_secret(), Value::Object(_));
+        let size = match (range, value.unwrap_secret()) {
+            (ResourceRange::Count(_), Value::Number(n)) => n
+                .as_i64()
+                .filter(|c| *c >= 0)
+                .map(|c| usize::try_from(c).unwrap_or(usize::MAX)),
+            (ResourceRange::Items(_), Value::List(items)) => Some(items.len()),
+            (ResourceRange::Items(_), Value::Object(entries)) => Some(entries.len()),
+            _ => None,
+        };
+        if let Some(size) = size.filter(|size| *size > self.limits.max_instances) {
+            self.state.diags.lock().unwrap().error(
+                None,
+                format!(
+                    "resource '{}' expands to {} instances, more than the limit of {}",
+                    logical_name, size, self.limits.max_instances
+                ),
+                "raise PULUMI_YAML_MAX_INSTANCES to allow more",
+            );
+            return None;
+        }
+        let instances: Vec<(Value<'static>, Value<'static>)> = match (range, value.unwrap_secret())
+        {
+            (ResourceRange::Count(_), Value::Number(n)) if n.as_i64().is_some_and(|c| c >= 0) => {
+                let count = n.as_i64().unwrap() as usize;
+                (0..count)
+                    .map(|i| (Value::Number(i.into()), Value::Number(i.into())))
+                    .collect()
+            }
+            (ResourceRange::Items(_), Value::List(items)) => items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| (Value::Number(i.into()), item.clone()))
+                .collect(),
+            (ResourceRange::Items(_), Value::Object(entries)) => entries
+                .iter()
+                .map(|(k, v)| (Value::String(k.clone()), v.clone()))
+                .collect(),
+            (range, other) => {
+                let field = match range {
+                    ResourceRange::Count(_) => "count",
+                    ResourceRange::Items(_) => "range",
+                };
+                let summary = match (range, other) {
+                    (_, Value::Unknown) => format!(
+                        "the {} of resource '{}' must be known before it is registered, \
+                         so it cannot depend on resource outputs",
+                        field, logical_name
+                    ),
+                    (ResourceRange::Count(_), Value::Number(n)) => format!(
+                        "the count of resource '{}' must be a non-negative integer, not {}",
+                        logical_name, n
+                    ),
+                    (ResourceRange::Count(_), other) => format!(
+                        "the count of resource '{}' must be a number, not {}",
+                        logical_name,
+                        other.type_name()
+                    ),
+                    (ResourceRange::Items(_), other) => format!(
+                        "the range of resource '{}' must be a list or an object, not {}",
+                        logical_name,
+                        other.type_name()
+                    ),
+                };
+                self.state.diags.lock().unwrap().error(None, summary, "");
+                return None;
+            }
+        };
+        // The values of a secret range stay secret in each instance.
+        let instances = instances
+            .into_iter()
+            .map(|(key, value)| {
+                if is_secret && !value.is_secret() {
+                    (key, Value::Secret(Box::new(value)))
+                } else {
+                    (key, value)
+                }
+            })
+            .collect();
+        Some((instances, keyed))
+    }
+
+    /// Evaluates one resource instance and registers it via the callback.
+    /// `logical_name` is the entry's name, or the instance's for a resource
+    /// with `count:` or `range:`; `default_name` is the physical name used
+    /// when the entry sets none.
+    fn eval_resource_instance<'t>(
+        &self,
+        entry: &'t ResourceEntry<'t>,
+        logical_name: &str,
+        default_name: &str,
+    ) {
         let resource = &entry.resource;
 
+        // Evaluate locals in order; each stays bound until the resource is done.
+        let mut local_scopes = Vec::with_capacity(resource.locals.len());
+        for local in &resource.locals {
+            match self.eval_expr(&local.value) {
+                Some(value) => local_scopes.push(ScopeGuard::push(vec![(
+                    local.key.to_string(),
+                    value.into_owned(),
+                )])),
+                None => {
+                    self.state
+                        .poisoned
+                        .write()
+                        .unwrap()
+                        .insert(entry.logical_name.to_string());
+                    return;
+                }
+            }
+        }
+
         // Use explicit name if set, otherwise fall back to logical key (Go compat)
-        let resource_name = resource.name.as_deref().unwrap_or(logical_name);
+        let resource_name = match &resource.name {
+            Some(expr) => match self.eval_resource_name(logical_name, expr) {
+                Some(name) => Cow::Owned(name),
+                None => {
+                    self.state
+                        .poisoned
+                        .write()
+                        .unwrap()
+                        .insert(entry.logical_name.to_string());
+                    return;
+                }
+            },
+            None => Cow::Borrowed(default_name),
+        };
+        let resource_name = resource_name.as_ref();
 
         // Evaluate resource properties
         let inputs = match &resource.properties {
@@ -590,7 +1731,7 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
                         .poisoned
                         .write()
                         .unwrap()
-                        .insert(logical_name.to_string());
+                        .insert(entry.logical_name.to_string());
                     return;
                 }
                 map
@@ -610,7 +1751,7 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
                         .poisoned
                         .write()
                         .unwrap()
-                        .insert(logical_name.to_string());
+                        .insert(entry.logical_name.to_string());
                     return;
                 }
                 None => {
@@ -618,21 +1759,54 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
                         .poisoned
                         .write()
                         .unwrap()
-                        .insert(logical_name.to_string());
+                        .insert(entry.logical_name.to_string());
                     return;
                 }
             },
         };
 
+        let mut inputs = inputs;
+        if !self.check_payload_size(&format!("resource '{}'", logical_name), &inputs)
+            || !self.check_file_assets(logical_name, &mut inputs)
+        {
+            self.state
+                .poisoned
+                .write()
+                .unwrap()
+                .insert(entry.logical_name.to_string());
+            return;
+        }
+
         // Determine resource characteristics
         let raw_type_token = resource.type_.as_ref();
-        let canonical_type = canonicalize_type_token(raw_type_token);
+        let local_component = self
+            .local_components
+            .get(expand_component_token(raw_type_token).as_ref())
+            .cloned();
+        let resolved = self.resolve_type_token(raw_type_token);
+        if resolved.heuristic {
+            self.state.diags.lock().unwrap().warning(
+                None,
+                format!(
+                    "resource '{}': type '{}' is not in the {} schema; guessed '{}'",
+                    logical_name,
+                    raw_type_token,
+                    resolve_pkg_name(raw_type_token),
+                    resolved.token
+                ),
+                "",
+            );
+        }
+        let canonical_type = resolved.token;
         let type_token = canonical_type.as_str();
 
         // Token blocklist: block known-unsupported resource types (Go: packages.go:270-324)
         // Check both the raw user token and the canonical form.
-        if let Some(err_msg) =
-            check_blocked_type(raw_type_token).or_else(|| check_blocked_type(type_token))
+        let provider_version = self.provider_version(type_token, resource);
+        if let Some(err_msg) = self
+            .blocklist
+            .check(raw_type_token, provider_version.as_ref())
+            .or_else(|| self.blocklist.check(type_token, provider_version.as_ref()))
         {
             self.state.diags.lock().unwrap().error(None, err_msg, "");
             return;
@@ -642,7 +1816,9 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
         let mut property_deps: HashMap<String, Vec<String>> = HashMap::new();
 
         // Component detection: check schema for isComponent flag
-        let is_component = if !is_provider {
+        let is_component = if local_component.is_some() {
+            true
+        } else if !is_provider {
             self.schema_store
                 .as_ref()
                 .map(|store| store.is_component(type_token))
@@ -659,7 +1835,6 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
 
         // Wrap secret input properties with Value::Secret (matching Go behavior:
         // pkg/pulumiyaml/run.go:1489 — IsResourcePropertySecret + ToSecret)
-        let mut inputs = inputs;
         if let Some(info) = schema_resource_info {
             for prop_name in &info.secret_input_properties {
                 if let Some(val) = inputs.get_mut(prop_name) {
@@ -686,30 +1861,21 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
 
         // Collect per-property dependencies (resource URNs referenced by each property)
         if let ResourceProperties::Map(props) = &resource.properties {
-            // Lock resources once, clone the keys, then release the lock
-            let resource_keys: Vec<String> = self
-                .state
-                .resources
-                .read()
-                .unwrap()
-                .keys()
-                .cloned()
-                .collect();
+            let resource_keys = self.registered_names();
             let resource_names: HashMap<&str, &str> = resource_keys
                 .iter()
                 .map(|k| (k.as_str(), "resource"))
                 .collect();
+            let local_deps = collect_local_deps(&resource.locals, &resource_names);
             for prop in props {
                 let mut prop_refs = std::collections::HashSet::new();
                 collect_expr_deps(&prop.value, &resource_names, &mut prop_refs);
+                expand_local_deps(&prop.value, &local_deps, &mut prop_refs);
                 if !prop_refs.is_empty() {
-                    let resources_guard = self.state.resources.read().unwrap();
                     let urns: Vec<String> = prop_refs
                         .iter()
-                        .filter_map(|name| resources_guard.get(*name).map(|r| r.urn.clone()))
-                        .filter(|urn| !urn.is_empty())
+                        .flat_map(|name| self.registered_urns(name))
                         .collect();
-                    drop(resources_guard);
                     if !urns.is_empty() {
                         property_deps.insert(prop.key.to_string(), urns);
                     }
@@ -721,6 +1887,25 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
         let mut options = self.resolve_resource_options(&resource.options);
         options.property_dependencies = property_deps;
 
+        // A computed name depends on the resources it references
+        if let Some(name) = &resource.name {
+            let resource_keys = self.registered_names();
+            let resource_names: HashMap<&str, &str> = resource_keys
+                .iter()
+                .map(|k| (k.as_str(), "resource"))
+                .collect();
+            let mut name_refs = std::collections::HashSet::new();
+            collect_expr_deps(name, &resource_names, &mut name_refs);
+            for urn in name_refs
+                .into_iter()
+                .flat_map(|dep| self.registered_urns(dep))
+            {
+                if !options.depends_on.contains(&urn) {
+                    options.depends_on.push(urn);
+                }
+            }
+        }
+
         // Enrich resource options from schema (secrets, aliases)
         if let Some(info) = schema_resource_info {
             for prop in &info.secret_properties {
@@ -745,6 +1930,14 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
             if let Some(pkg_ref) = self.package_refs.get(pkg_name) {
                 options.package_ref = pkg_ref.clone();
             }
+            options.version = self.concrete_version(pkg_name, options.version);
+        }
+
+        // Inner resources of a component inherit the providers passed to it
+        if !is_provider && options.provider_ref.is_none() {
+            if let Some(pkg_name) = type_token.split(':').next() {
+                options.provider_ref = self.component_providers.get(pkg_name).cloned();
+            }
         }
 
         // Auto-assign default provider if no explicit provider is set
@@ -769,6 +1962,12 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
                 options.parent_urn = Some(parent.clone());
             }
         }
+        for urn in &self.component_depends_on {
+            if !options.depends_on.contains(urn) {
+                options.depends_on.push(urn.clone());
+            }
+        }
+        options.protect |= self.component_protect;
 
         // StackReference special handling: convert to read resource (Go: run.go:1895-1908)
         if type_token == "pulumi:pulumi:StackReference" {
@@ -813,26 +2012,21 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
                 .get(&id_str)
                 .cloned()
             {
-                self.store_resource(logical_name, cached, false, false, false);
+                self.store_resource(logical_name, type_token, cached, false, false, false);
                 return;
             }
 
-            match self.callback.read_resource(
-                type_token,
-                resource_name,
-                &id_str,
-                options.parent_urn.as_deref().unwrap_or(""),
-                inputs,
-                options.provider_ref.as_deref().unwrap_or(""),
-                &options.version,
-            ) {
+            match timing::time_rpc(|| {
+                self.callback
+                    .read_resource(type_token, resource_name, &id_str, inputs, options)
+            }) {
                 Ok(resp) => {
                     self.state
                         .stack_ref_cache
                         .lock()
                         .unwrap()
                         .insert(id_str, resp.clone());
-                    self.store_resource(logical_name, resp, false, false, false);
+                    self.store_resource(logical_name, type_token, resp, false, false, false);
                 }
                 Err(e) => {
                     self.state.diags.lock().unwrap().error(
@@ -845,6 +2039,16 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
             return;
         }
 
+        if !self.claim_registration(
+            logical_name,
+            type_token,
+            resource_name,
+            options.parent_urn.as_deref(),
+            entry.meta.span,
+        ) {
+            return;
+        }
+
         // Handle get resources (reading existing resources)
         if let Some(ref get) = resource.get {
             let id_val = match self.eval_expr(&get.id) {
@@ -863,17 +2067,21 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
                 None => return,
             };
 
-            match self.callback.read_resource(
-                type_token,
-                resource_name,
-                &id_val,
-                options.parent_urn.as_deref().unwrap_or(""),
-                inputs,
-                options.provider_ref.as_deref().unwrap_or(""),
-                &options.version,
-            ) {
-                Ok(resp) => {
-                    self.store_resource(logical_name, resp, is_provider, is_component, false);
+            let secret_outputs = options.additional_secret_outputs.clone();
+            match timing::time_rpc(|| {
+                self.callback
+                    .read_resource(type_token, resource_name, &id_val, inputs, options)
+            }) {
+                Ok(mut resp) => {
+                    resp.mark_secret_outputs(&secret_outputs);
+                    self.store_resource(
+                        logical_name,
+                        type_token,
+                        resp,
+                        is_provider,
+                        is_component,
+                        false,
+                    );
                 }
                 Err(e) => {
                     self.state.diags.lock().unwrap().error(
@@ -886,16 +2094,33 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
             return;
         }
 
-        // Register the resource via callback
-        match self.callback.register_resource(
-            type_token,
-            resource_name,
-            custom,
-            is_component,
-            inputs,
-            options,
-        ) {
+        // A refresh only reads; resources it would register stay unresolved.
+        if self.mode == EvalMode::Refresh {
+            self.state
+                .skipped
+                .write()
+                .unwrap()
+                .insert(entry.logical_name.to_string());
+            return;
+        }
+
+        // Register the resource via callback. Outputs marked secret by the
+        // options or the schema stay secret even if the engine returns them
+        // in plaintext, so they are also secret when exported as stack outputs.
+        let secret_outputs = options.additional_secret_outputs.clone();
+        match timing::time_rpc(|| {
+            self.callback.register_resource(
+                type_token,
+                resource_name,
+                custom,
+                is_component,
+                inputs,
+                options,
+            )
+        }) {
             Ok(mut resp) => {
+                resp.mark_secret_outputs(&secret_outputs);
+
                 // In preview mode, fill output-only properties with Unknown
                 // so downstream references don't fail
                 if self.dry_run {
@@ -911,6 +2136,7 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
                 let is_default_provider = resource.default_provider == Some(true);
                 self.store_resource(
                     logical_name,
+                    type_token,
                     resp,
                     is_provider,
                     is_component,
@@ -918,13 +2144,55 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
                 );
             }
             Err(e) => {
-                self.state.diags.lock().unwrap().error(
-                    None,
-                    format!("failed to register resource '{}': {}", logical_name, e),
-                    "",
-                );
+                let failures = match &e {
+                    EngineError::Registration(message) if self.rich_check_failures => {
+                        parse_check_failures(message)
+                    }
+                    _ => Vec::new(),
+                };
+                let mut diags = self.state.diags.lock().unwrap();
+                if failures.is_empty() {
+                    diags.error(
+                        entry.meta.span,
+                        format!("failed to register resource '{}': {}", logical_name, e),
+                        self.resource_help(type_token),
+                    );
+                }
+                for (prop, reason) in failures {
+                    let inputs = CheckedInputs::Resource(&resource.properties, entry.meta.span);
+                    let (span, detail) = check_failure_location(inputs, &prop);
+                    diags.error(
+                        span,
+                        format!(
+                            "resource '{}' rejected property '{}': {}",
+                            logical_name, prop, reason
+                        ),
+                        detail,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Where to read up on a resource type after the provider rejected it:
+    /// its registry page and, if the schema has one, an example.
+    fn resource_help(&self, type_token: &str) -> String {
+        let mut help = String::new();
+        if let Some(url) = crate::schema::registry_docs_url(type_token) {
+            help.push_str(&format!("see {}", url));
+        }
+        let example = self
+            .schema_store
+            .and_then(|store| store.lookup_resource(type_token))
+            .and_then(|info| info.example.as_deref());
+        if let Some(example) = example {
+            if !help.is_empty() {
+                help.push('\n');
             }
+            help.push_str("example:\n");
+            help.push_str(example);
         }
+        help
     }
 
     /// Resolves resource options from the AST declaration to concrete values.
@@ -1149,7 +2417,12 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
     /// Evaluates an output entry and stores the result.
     fn eval_output<'t>(&self, output: &'t OutputEntry<'t>) {
         let key = output.key.as_ref();
+        let _source = self.enter_source(key);
+        let _entry = stable_random::EntryGuard::enter(format!("outputs.{}", key));
         if let Some(value) = self.eval_expr(&output.value) {
+            if !self.charge_value(key, &value) {
+                return;
+            }
             self.state
                 .outputs
                 .lock()
@@ -1158,6 +2431,43 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
         }
     }
 
+    /// Records the file declaring `name` as the one being evaluated on this
+    /// thread until the guard drops.
+    fn enter_source(&self, name: &str) -> scope::SourceFileGuard {
+        let file = self
+            .source_map
+            .as_ref()
+            .and_then(|sm| sm.get(name))
+            .cloned();
+        scope::SourceFileGuard::enter(file)
+    }
+
+    /// Rewrites a relative path written in the entry being evaluated so it is
+    /// relative to the project directory, given that it was written relative
+    /// to the file declaring the entry. Only files outside the project
+    /// directory, such as `extends:` bases, change anything.
+    fn source_relative<'e>(&self, path: Value<'e>) -> Value<'e> {
+        if self.project_relative_paths {
+            return path;
+        }
+        let Value::String(s) = &path else {
+            return path;
+        };
+        let Some(file) = scope::source_file() else {
+            return path;
+        };
+        // Remote imports have no directory to resolve against.
+        if file.contains("://") || crate::paths::is_absolute(s) {
+            return path;
+        }
+        let Some(dir) = crate::paths::parent(&file) else {
+            return path;
+        };
+        let dir = crate::paths::resolve(std::path::Path::new(""), dir);
+        let joined = crate::paths::resolve(&dir, s);
+        Value::String(Cow::Owned(joined.to_string_lossy().into_owned()))
+    }
+
     /// Evaluates an expression, returning its Value.
     ///
     /// This is the core expression evaluator, dispatching based on
@@ -1167,6 +2477,17 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
     /// callers holding stack-local expressions to evaluate them without
     /// requiring a `'static` bound.
     pub fn eval_expr<'e>(&self, expr: &'e Expr<'e>) -> Option<Value<'e>> {
+        let Some(_depth) = DepthGuard::enter(self.limits.max_expr_depth) else {
+            self.state.diags.lock().unwrap().error(
+                None,
+                format!(
+                    "expression nesting exceeds the maximum depth of {}",
+                    self.limits.max_expr_depth
+                ),
+                "simplify the expression or raise PULUMI_YAML_MAX_EXPR_DEPTH",
+            );
+            return None;
+        };
         match expr {
             Expr::Null(_) => Some(Value::Null),
             Expr::Bool(_, b) => Some(Value::Bool(*b)),
@@ -1212,7 +2533,7 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
 
             Expr::Symbol(_, access) => self.eval_property_access_expr(access),
 
-            Expr::Invoke(_, invoke) => self.eval_invoke(invoke),
+            Expr::Invoke(meta, invoke) => self.eval_invoke(invoke, meta.span),
 
             Expr::Join(_, delim, values) => {
                 let d = self.eval_expr(delim)?;
@@ -1232,11 +2553,46 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
                 builtins::eval_select(&i, &v, &mut self.state.diags.lock().unwrap())
             }
 
+            Expr::Lookup(_, map, key, default) => {
+                let m = self.eval_expr(map)?;
+                let k = self.eval_expr(key)?;
+                let d = match default {
+                    Some(d) if builtins::lookup_misses(&m, &k) => Some(self.eval_expr(d)?),
+                    _ => None,
+                };
+                builtins::eval_lookup(&m, &k, d, &mut self.state.diags.lock().unwrap())
+            }
+
+            Expr::Map(_, list, body) => {
+                self.eval_loop("fn::map", list, body, "item", Some("index"), false)
+            }
+            Expr::Filter(_, list, predicate) => {
+                self.eval_loop("fn::filter", list, predicate, "item", Some("index"), true)
+            }
+            Expr::Foreach(_, foreach) => self.eval_loop(
+                "fn::foreach",
+                &foreach.list,
+                &foreach.body,
+                &foreach.var,
+                foreach.index.as_deref(),
+                false,
+            ),
+
             Expr::ToJson(_, inner) => {
                 let v = self.eval_expr(inner)?;
                 builtins::eval_to_json(&v, &mut self.state.diags.lock().unwrap())
             }
 
+            Expr::ParseJson(_, inner) => {
+                let v = self.eval_expr(inner)?;
+                builtins::eval_parse_json(&v, &mut self.state.diags.lock().unwrap())
+            }
+
+            Expr::ParseYaml(_, inner) => {
+                let v = self.eval_expr(inner)?;
+                builtins::eval_parse_yaml(&v, &mut self.state.diags.lock().unwrap())
+            }
+
             Expr::ToBase64(_, inner) => {
                 let v = self.eval_expr(inner)?;
                 builtins::eval_to_base64(&v, &mut self.state.diags.lock().unwrap())
@@ -1252,9 +2608,67 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
                 Some(builtins::eval_secret(v))
             }
 
+            Expr::Encrypted(_, inner) => self.eval_encrypted(inner),
+
             Expr::ReadFile(_, inner) => {
+                let v = self.source_relative(self.eval_expr(inner)?);
+                builtins::eval_read_file(
+                    &v,
+                    &self.cwd,
+                    self.fs.as_ref(),
+                    &mut self.state.diags.lock().unwrap(),
+                )
+            }
+            Expr::ReadFileBinary(_, inner) => {
+                let v = self.source_relative(self.eval_expr(inner)?);
+                builtins::eval_read_file_binary(
+                    &v,
+                    &self.cwd,
+                    self.fs.as_ref(),
+                    &mut self.state.diags.lock().unwrap(),
+                )
+            }
+            Expr::Env(_, name, default) => self.eval_env(name, default.as_deref()),
+
+            Expr::Merge(_, inner) => {
                 let v = self.eval_expr(inner)?;
-                builtins::eval_read_file(&v, &self.cwd, &mut self.state.diags.lock().unwrap())
+                builtins::eval_merge(&v, &mut self.state.diags.lock().unwrap())
+            }
+
+            Expr::JsonPatch(_, target, operations) => {
+                let t = self.eval_expr(target)?;
+                let o = self.eval_expr(operations)?;
+                builtins::eval_json_patch(&t, &o, &mut self.state.diags.lock().unwrap())
+            }
+
+            Expr::TemplateFile(_, path, arguments) => {
+                let p = self.source_relative(self.eval_expr(path)?);
+                let a = self.eval_expr(arguments)?;
+                builtins::eval_template_file(
+                    &p,
+                    &a,
+                    &self.cwd,
+                    self.fs.as_ref(),
+                    &mut self.state.diags.lock().unwrap(),
+                )
+            }
+
+            Expr::ValidateJson(_, value, schema) => {
+                let v = self.eval_expr(value)?;
+                let s = self.source_relative(self.eval_expr(schema)?);
+                builtins::eval_validate_json(
+                    v,
+                    &s,
+                    &self.cwd,
+                    self.fs.as_ref(),
+                    &mut self.state.diags.lock().unwrap(),
+                )
+            }
+
+            Expr::Assert(meta, condition, message) => {
+                let c = self.eval_expr(condition)?;
+                let m = self.eval_expr(message)?;
+                builtins::eval_assert(&c, &m, meta.span, &mut self.state.diags.lock().unwrap())
             }
 
             // Math builtins
@@ -1278,18 +2692,136 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
                 let v = self.eval_expr(inner)?;
                 builtins::eval_min(&v, &mut self.state.diags.lock().unwrap())
             }
+            Expr::FormatDuration(_, inner) => {
+                let v = self.eval_expr(inner)?;
+                builtins::eval_format_duration(&v, &mut self.state.diags.lock().unwrap())
+            }
+            Expr::FormatBytes(_, inner) => {
+                let v = self.eval_expr(inner)?;
+                builtins::eval_format_bytes(&v, &mut self.state.diags.lock().unwrap())
+            }
+
+            // List builtins
+            Expr::Sort(_, inner) => {
+                let v = self.eval_expr(inner)?;
+                builtins::eval_sort(&v, &mut self.state.diags.lock().unwrap())
+            }
+            Expr::Unique(_, inner) => {
+                let v = self.eval_expr(inner)?;
+                builtins::eval_unique(&v, &mut self.state.diags.lock().unwrap())
+            }
+            Expr::Length(_, inner) => {
+                let v = self.eval_expr(inner)?;
+                builtins::eval_length(&v, &mut self.state.diags.lock().unwrap())
+            }
+            Expr::Coalesce(_, inner) => match inner.as_ref() {
+                Expr::List(_, items) => self.eval_coalesce(items),
+                _ => {
+                    let v = self.eval_expr(inner)?;
+                    builtins::eval_coalesce(&v, &mut self.state.diags.lock().unwrap())
+                }
+            },
 
             // String builtins
             Expr::StringLen(_, inner) => {
                 let v = self.eval_expr(inner)?;
                 builtins::eval_string_len(&v, &mut self.state.diags.lock().unwrap())
             }
+            Expr::If(_, condition, then, otherwise) => self.eval_if(condition, then, otherwise),
+            Expr::Equals(_, left, right) => {
+                let l = self.eval_expr(left)?;
+                let r = self.eval_expr(right)?;
+                Some(builtins::eval_equals(&l, &r))
+            }
+            Expr::Not(_, inner) => {
+                let v = self.eval_expr(inner)?;
+                builtins::eval_not(&v, &mut self.state.diags.lock().unwrap())
+            }
+            Expr::And(_, inner) => match inner.as_ref() {
+                Expr::List(_, items) => self.eval_logical("fn::and", false, items),
+                _ => {
+                    let v = self.eval_expr(inner)?;
+                    builtins::eval_and(&v, &mut self.state.diags.lock().unwrap())
+                }
+            },
+            Expr::Or(_, inner) => match inner.as_ref() {
+                Expr::List(_, items) => self.eval_logical("fn::or", true, items),
+                _ => {
+                    let v = self.eval_expr(inner)?;
+                    builtins::eval_or(&v, &mut self.state.diags.lock().unwrap())
+                }
+            },
+            Expr::LessThan(_, left, right) => {
+                let l = self.eval_expr(left)?;
+                let r = self.eval_expr(right)?;
+                builtins::eval_less_than(&l, &r, &mut self.state.diags.lock().unwrap())
+            }
+            Expr::LessThanOrEqual(_, left, right) => {
+                let l = self.eval_expr(left)?;
+                let r = self.eval_expr(right)?;
+                builtins::eval_less_than_or_equal(&l, &r, &mut self.state.diags.lock().unwrap())
+            }
+            Expr::GreaterThan(_, left, right) => {
+                let l = self.eval_expr(left)?;
+                let r = self.eval_expr(right)?;
+                builtins::eval_greater_than(&l, &r, &mut self.state.diags.lock().unwrap())
+            }
+            Expr::GreaterThanOrEqual(_, left, right) => {
+                let l = self.eval_expr(left)?;
+                let r = self.eval_expr(right)?;
+                builtins::eval_greater_than_or_equal(&l, &r, &mut self.state.diags.lock().unwrap())
+            }
             Expr::Substring(_, source, start, length) => {
                 let s = self.eval_expr(source)?;
                 let st = self.eval_expr(start)?;
                 let len = self.eval_expr(length)?;
                 builtins::eval_substring(&s, &st, &len, &mut self.state.diags.lock().unwrap())
             }
+            Expr::ToUpper(_, inner) => {
+                let v = self.eval_expr(inner)?;
+                builtins::eval_to_upper(&v, &mut self.state.diags.lock().unwrap())
+            }
+            Expr::ToLower(_, inner) => {
+                let v = self.eval_expr(inner)?;
+                builtins::eval_to_lower(&v, &mut self.state.diags.lock().unwrap())
+            }
+            Expr::Trim(_, inner) => {
+                let v = self.eval_expr(inner)?;
+                builtins::eval_trim(&v, &mut self.state.diags.lock().unwrap())
+            }
+            Expr::Replace(_, source, search, replacement) => {
+                let s = self.eval_expr(source)?;
+                let se = self.eval_expr(search)?;
+                let r = self.eval_expr(replacement)?;
+                builtins::eval_replace(&s, &se, &r, &mut self.state.diags.lock().unwrap())
+            }
+            Expr::StartsWith(_, source, prefix) => {
+                let s = self.eval_expr(source)?;
+                let p = self.eval_expr(prefix)?;
+                builtins::eval_starts_with(&s, &p, &mut self.state.diags.lock().unwrap())
+            }
+            Expr::Format(_, format, args) => {
+                let f = self.eval_expr(format)?;
+                let a = self.eval_expr(args)?;
+                builtins::eval_format(&f, &a, &mut self.state.diags.lock().unwrap())
+            }
+
+            // Network builtins
+            Expr::CidrSubnet(_, prefix, newbits, netnum) => {
+                let p = self.eval_expr(prefix)?;
+                let b = self.eval_expr(newbits)?;
+                let n = self.eval_expr(netnum)?;
+                builtins::eval_cidr_subnet(&p, &b, &n, &mut self.state.diags.lock().unwrap())
+            }
+            Expr::CidrHost(_, prefix, hostnum) => {
+                let p = self.eval_expr(prefix)?;
+                let h = self.eval_expr(hostnum)?;
+                builtins::eval_cidr_host(&p, &h, &mut self.state.diags.lock().unwrap())
+            }
+            Expr::CidrNetmask(_, inner) => {
+                let v = self.eval_expr(inner)?;
+                builtins::eval_cidr_netmask(&v, &mut self.state.diags.lock().unwrap())
+            }
 
             // Time builtins
             Expr::TimeUtc(_, inner) => {
@@ -1304,11 +2836,18 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
             // UUID/Random builtins
             Expr::Uuid(_, inner) => {
                 let v = self.eval_expr(inner)?;
-                builtins::eval_uuid(&v, &mut self.state.diags.lock().unwrap())
+                self.stable_random_value(
+                    |_| true,
+                    || builtins::eval_uuid(&v, &mut self.state.diags.lock().unwrap()),
+                )
             }
             Expr::RandomString(_, inner) => {
                 let v = self.eval_expr(inner)?;
-                builtins::eval_random_string(&v, &mut self.state.diags.lock().unwrap())
+                let length = v.as_number();
+                self.stable_random_value(
+                    |prior| length == Some(prior.chars().count() as f64),
+                    || builtins::eval_random_string(&v, &mut self.state.diags.lock().unwrap()),
+                )
             }
 
             // Date builtins
@@ -1336,9 +2875,9 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
             }
 
             Expr::FileAsset(_, inner) => {
-                let v = self.eval_expr(inner)?;
+                let v = self.source_relative(self.eval_expr(inner)?);
                 match &v {
-                    Value::String(s) => Some(Value::Asset(Asset::File(s.clone()))),
+                    Value::String(s) => Some(Value::Asset(Asset::File(s.clone(), None))),
                     _ => {
                         self.state.diags.lock().unwrap().error(
                             None,
@@ -1372,7 +2911,7 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
             }
 
             Expr::FileArchive(_, inner) => {
-                let v = self.eval_expr(inner)?;
+                let v = self.source_relative(self.eval_expr(inner)?);
                 match &v {
                     Value::String(s) => Some(Value::Archive(Archive::File(s.clone()))),
                     _ => {
@@ -1416,6 +2955,8 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
                 Some(Value::Archive(Archive::Assets(result)))
             }
 
+            Expr::Open(_, provider, inputs) => self.eval_open(provider, inputs),
+
             Expr::Starlark(_, call) => {
                 let input_val = self.eval_expr(&call.input)?;
 
@@ -1489,25 +3030,22 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
                 } else {
                     &val
                 };
-                match effective {
-                    Value::String(s) => result.push_str(s.as_ref()),
-                    Value::Number(n) => {
-                        // Format integers without decimal point
-                        if n.fract() == 0.0 {
-                            write!(result, "{}", *n as i64).ok();
-                        } else {
-                            write!(result, "{}", n).ok();
-                        }
-                    }
-                    Value::Bool(b) => {
-                        write!(result, "{}", b).ok();
-                    }
-                    Value::Null => {} // null interpolates as empty
-                    Value::Unknown => return Some(Value::Unknown),
-                    _ => {
-                        write!(result, "{}", effective).ok();
-                    }
+                if effective.is_unknown() {
+                    return Some(Value::Unknown);
                 }
+                builtins::push_interpolated(&mut result, effective);
+            }
+
+            if result.len() > self.limits.max_string_bytes {
+                self.state.diags.lock().unwrap().error(
+                    None,
+                    format!(
+                        "interpolation result exceeds the maximum size of {} bytes",
+                        self.limits.max_string_bytes
+                    ),
+                    "raise PULUMI_YAML_MAX_STRING_BYTES to allow larger strings",
+                );
+                return None;
             }
         }
 
@@ -1519,6 +3057,157 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
         }
     }
 
+    /// Evaluates `fn::if`, leaving the branch not taken unevaluated. A secret
+    /// condition makes the result secret.
+    fn eval_if<'e>(
+        &self,
+        condition: &'e Expr<'e>,
+        then: &'e Expr<'e>,
+        otherwise: &'e Expr<'e>,
+    ) -> Option<Value<'e>> {
+        let condition = self.eval_expr(condition)?;
+        let chosen = match condition.unwrap_secret() {
+            Value::Bool(true) => then,
+            Value::Bool(false) => otherwise,
+            Value::Unknown => return Some(Value::Unknown),
+            other => {
+                self.state.diags.lock().unwrap().error(
+                    None,
+                    format!(
+                        "the condition of fn::if must be a boolean, not {}",
+                        other.type_name()
+                    ),
+                    "",
+                );
+                return None;
+            }
+        };
+        let value = self.eval_expr(chosen)?;
+        if condition.is_secret() && !value.is_secret() {
+            Some(builtins::eval_secret(value))
+        } else {
+            Some(value)
+        }
+    }
+
+    /// Evaluates `fn::and` (`decisive` false) or `fn::or` (`decisive` true)
+    /// over a literal list, one item at a time: the items after the first one
+    /// equal to `decisive` are never evaluated. See [`builtins::eval_and`] for
+    /// how unknown and secret items are treated.
+    fn eval_logical<'e>(
+        &self,
+        name: &str,
+        decisive: bool,
+        items: &'e [Expr<'e>],
+    ) -> Option<Value<'e>> {
+        let (mut secret, mut unknown) = (false, false);
+        for (i, item) in items.iter().enumerate() {
+            let value = self.eval_expr(item)?;
+            secret |= value.is_secret();
+            let operand =
+                builtins::logical_operand(name, i, &value, &mut self.state.diags.lock().unwrap())?;
+            match operand {
+                Some(b) if b == decisive => return Some(builtins::bool_result(decisive, secret)),
+                Some(_) => {}
+                None => unknown = true,
+            }
+        }
+        if unknown {
+            return Some(Value::Unknown);
+        }
+        Some(builtins::bool_result(!decisive, secret))
+    }
+
+    /// Evaluates `fn::coalesce` over a literal list, one item at a time: the
+    /// items after the first defined one are never evaluated, so a fallback
+    /// such as an `fn::invoke` only runs when it is needed.
+    fn eval_coalesce<'e>(&self, items: &'e [Expr<'e>]) -> Option<Value<'e>> {
+        for item in items {
+            let value = self.eval_expr(item)?;
+            if value.unwrap_secret().is_unknown() {
+                return Some(Value::Unknown);
+            }
+            if !builtins::coalesce_skips(&value) {
+                return Some(value);
+            }
+        }
+        Some(Value::Null)
+    }
+
+    /// Evaluates `fn::map`, `fn::foreach`, or `fn::filter` when `filter` is
+    /// set: `body` is evaluated once per element of `list`, with the element
+    /// bound to `item_name` and its position to `index_name`.
+    fn eval_loop<'e>(
+        &self,
+        name: &str,
+        list: &'e Expr<'e>,
+        body: &'e Expr<'e>,
+        item_name: &str,
+        index_name: Option<&str>,
+        filter: bool,
+    ) -> Option<Value<'e>> {
+        let list = self.eval_expr(list)?;
+        let is_secret = list.is_secret();
+        let items = match list.unwrap_secret() {
+            Value::List(items) => items.clone(),
+            Value::Unknown => return Some(Value::Unknown),
+            other => {
+                self.state.diags.lock().unwrap().error(
+                    None,
+                    format!(
+                        "{} must be a list, not {}",
+                        if name == "fn::foreach" {
+                            "the 'list' of fn::foreach".to_string()
+                        } else {
+                            format!("the first argument to {}", name)
+                        },
+                        other.type_name()
+                    ),
+                    "",
+                );
+                return None;
+            }
+        };
+
+        let mut result = Vec::with_capacity(items.len());
+        for (index, item) in items.into_iter().enumerate() {
+            let item = item.into_owned();
+            let mut bindings = vec![(item_name.to_string(), item.clone())];
+            if let Some(index_name) = index_name {
+                bindings.push((index_name.to_string(), Value::Number(index.into())));
+            }
+            let _scope = ScopeGuard::push(bindings);
+            let value = self.eval_expr(body)?.into_owned();
+            if !filter {
+                result.push(value);
+                continue;
+            }
+            match value.unwrap_secret() {
+                Value::Bool(true) => result.push(item),
+                Value::Bool(false) => {}
+                Value::Unknown => return Some(Value::Unknown),
+                other => {
+                    self.state.diags.lock().unwrap().error(
+                        None,
+                        format!(
+                            "the predicate of fn::filter must evaluate to a boolean, not {}",
+                            other.type_name()
+                        ),
+                        "",
+                    );
+                    return None;
+                }
+            }
+        }
+
+        let result = Value::List(result);
+        if is_secret {
+            Some(Value::Secret(Box::new(result)))
+        } else {
+            Some(result)
+        }
+    }
+
     /// Evaluates a property access expression like `${resource.output.field}`.
     fn eval_property_access_expr<'e>(&self, access: &'e PropertyAccess<'e>) -> Option<Value<'e>> {
         let root_name = match access.root_name() {
@@ -1533,11 +3222,18 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
             }
         };
 
+        // Names bound by `fn::map`/`fn::filter` shadow everything else.
+        let scoped = scope::lookup(root_name);
+
         // If the root is poisoned (failed evaluation), silently return None
         // to prevent cascading errors
-        if self.state.poisoned.read().unwrap().contains(root_name) {
+        if scoped.is_none() && self.state.poisoned.read().unwrap().contains(root_name) {
             return None;
         }
+        // Skipped entries have no value during this evaluation.
+        if scoped.is_none() && self.state.skipped.read().unwrap().contains(root_name) {
+            return Some(Value::Unknown);
+        }
 
         // Look up the root name in config, variables, or resources.
         // Values are cloned from Mutex-protected maps and converted to owned
@@ -1545,11 +3241,22 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
         //
         // Each lookup acquires and releases its own lock to avoid holding
         // multiple locks simultaneously (which clippy's if_let_mutex forbids).
-        let receiver: Value<'static> = {
+        let receiver: Value<'static> = if let Some(val) = scoped {
+            val
+        } else {
             // Try resources first
             let res = self.state.resources.read().unwrap().get(root_name).cloned();
+            let group = self
+                .state
+                .resource_groups
+                .read()
+                .unwrap()
+                .get(root_name)
+                .cloned();
             if let Some(val) = res {
                 self.resource_to_value(root_name, &val)
+            } else if let Some(group) = group {
+                self.resource_group_to_value(&group)
             } else {
                 // Try config (by exact name, then stripped namespace)
                 let stripped = config::strip_config_namespace(&self.project_name, root_name);
@@ -1587,11 +3294,59 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
             return Some(receiver);
         }
 
-        builtins::eval_property_access(
+        if !access.has_nested_access() {
+            return builtins::eval_property_access(
+                &receiver,
+                &access.accessors[1..],
+                &mut self.state.diags.lock().unwrap(),
+            );
+        }
+
+        // Resolve nested keys (`${a[b.c]}`) into literal subscripts first.
+        let mut resolved = Vec::with_capacity(access.accessors.len() - 1);
+        let mut key_is_secret = false;
+        for accessor in &access.accessors[1..] {
+            match accessor {
+                PropertyAccessor::PropertySubscript(inner) => {
+                    let key = self.eval_property_access_expr(inner)?;
+                    if key.is_secret() {
+                        key_is_secret = true;
+                    }
+                    match key.unwrap_secret() {
+                        Value::String(s) => resolved
+                            .push(PropertyAccessor::StringSubscript(Cow::Owned(s.to_string()))),
+                        Value::Number(n) if n.as_i64().is_some() => {
+                            resolved.push(PropertyAccessor::IntSubscript(n.as_i64().unwrap()))
+                        }
+                        Value::Unknown => return Some(Value::Unknown),
+                        other => {
+                            self.state.diags.lock().unwrap().error(
+                                None,
+                                format!(
+                                    "property access key ${{{}}} must evaluate to a string or an integer, not {}",
+                                    inner,
+                                    other.type_name()
+                                ),
+                                "",
+                            );
+                            return None;
+                        }
+                    }
+                }
+                other => resolved.push(other.clone().into_owned()),
+            }
+        }
+
+        let result = builtins::eval_property_access(
             &receiver,
-            &access.accessors[1..],
+            &resolved,
             &mut self.state.diags.lock().unwrap(),
-        )
+        )?;
+        if key_is_secret && !result.is_secret() {
+            Some(Value::Secret(Box::new(result)))
+        } else {
+            Some(result)
+        }
     }
 
     /// Converts a resource state to a Value for property access.
@@ -1613,11 +3368,62 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
         Value::Object(entries)
     }
 
+    /// Converts the instances of a resource with `count:` or `range:` to a
+    /// list, or an object for a `range:` object, of their values.
+    fn resource_group_to_value(&self, group: &ResourceGroup) -> Value<'static> {
+        let resources = self.state.resources.read().unwrap();
+        let instance = |name: &str| {
+            resources
+                .get(name)
+                .map(|state| self.resource_to_value(name, state))
+                .unwrap_or(Value::Unknown)
+        };
+        match group {
+            ResourceGroup::List(names) => Value::List(names.iter().map(|n| instance(n)).collect()),
+            ResourceGroup::Object(entries) => Value::Object(
+                entries
+                    .iter()
+                    .map(|(key, name)| (Cow::Owned(key.clone()), instance(name)))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// The URNs registered under a logical name: the resource's, or each
+    /// instance's for a resource with `count:` or `range:`.
+    fn registered_urns(&self, name: &str) -> Vec<String> {
+        let resources = self.state.resources.read().unwrap();
+        let groups = self.state.resource_groups.read().unwrap();
+        let names = match groups.get(name) {
+            Some(group) => group.instances(),
+            None => vec![name],
+        };
+        names
+            .into_iter()
+            .filter_map(|n| resources.get(n))
+            .map(|state| state.urn.clone())
+            .filter(|urn| !urn.is_empty())
+            .collect()
+    }
+
+    /// The logical names references can resolve to registered resources.
+    fn registered_names(&self) -> Vec<String> {
+        let resources = self.state.resources.read().unwrap();
+        let groups = self.state.resource_groups.read().unwrap();
+        resources.keys().chain(groups.keys()).cloned().collect()
+    }
+
     /// Evaluates an invoke expression (fn::invoke).
     ///
     /// Evaluates the arguments and calls the invoke method on the callback.
     /// If a `return` field is specified, extracts the named property from the result.
-    fn eval_invoke<'e>(&self, invoke: &'e InvokeExpr<'e>) -> Option<Value<'e>> {
+    /// `span` is where the call is written.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(token = %invoke.token)))]
+    fn eval_invoke<'e>(&self, invoke: &'e InvokeExpr<'e>, span: Option<Span>) -> Option<Value<'e>> {
+        if self.extensions.owns(invoke.token.as_ref()) {
+            return self.eval_extension(invoke);
+        }
+
         // Evaluate arguments into a map
         let args: HashMap<String, Value<'static>> = if let Some(ref args_expr) = invoke.call_args {
             match self.eval_expr(args_expr) {
@@ -1641,6 +3447,9 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
         } else {
             HashMap::new()
         };
+        if !self.check_payload_size(&format!("invoke '{}'", invoke.token), &args) {
+            return None;
+        }
 
         // Resolve provider and version from invoke options
         let provider = if let Some(ref provider_expr) = invoke.call_opts.provider {
@@ -1664,6 +3473,7 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
             .as_ref()
             .map(|v| v.to_string())
             .unwrap_or_default();
+        let version = self.concrete_version(resolve_pkg_name(invoke.token.as_ref()), version);
 
         // Resolve parent URN from invoke options
         let parent = if let Some(ref parent_expr) = invoke.call_opts.parent {
@@ -1690,50 +3500,102 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
             Vec::new()
         };
 
+        // Prefer the schema's spelling of the token; fall back to heuristics.
         let raw_token = invoke.token.as_ref();
-        let canonical_token = canonicalize_type_token(raw_token);
+        let canonical_token = self
+            .schema_store
+            .and_then(|s| s.resolve_function_token(raw_token))
+            .map(|c| c.into_owned())
+            .unwrap_or_else(|| canonicalize_type_token(raw_token));
         let token = canonical_token.as_str();
 
-        // Call the callback
-        match self
-            .callback
-            .invoke(token, args, &provider, &version, &parent, &depends_on)
-        {
+        let inputs = CheckedInputs::Invoke(invoke.call_args.as_deref(), span);
+        let call = |args| {
+            self.call_invoke(
+                token,
+                args,
+                inputs,
+                &provider,
+                &version,
+                &parent,
+                &depends_on,
+            )
+        };
+        if let Some(ref paginate) = invoke.call_opts.paginate {
+            return self.paginate_invoke(token, paginate, args, call);
+        }
+        let return_values = call(args)?;
+
+        // If a return field is specified, extract that property
+        if let Some(ref return_field) = invoke.return_ {
+            // Return null if the field doesn't exist
+            Some(
+                return_values
+                    .get(return_field.as_ref())
+                    .cloned()
+                    .unwrap_or(Value::Null),
+            )
+        } else {
+            // Return the full result as an object
+            let entries: Vec<(Cow<'e, str>, Value<'e>)> = return_values
+                .into_iter()
+                .map(|(k, v)| (Cow::Owned(k), v))
+                .collect();
+            Some(Value::Object(entries))
+        }
+    }
+
+    /// Calls a provider function, reporting failures as errors.
+    ///
+    /// `inputs` are the template's arguments, used to locate the values
+    /// behind check failures when `rich_check_failures` is set.
+    #[allow(clippy::too_many_arguments)]
+    fn call_invoke(
+        &self,
+        token: &str,
+        args: HashMap<String, Value<'static>>,
+        inputs: CheckedInputs<'_, '_>,
+        provider: &str,
+        version: &str,
+        parent: &str,
+        depends_on: &[String],
+    ) -> Option<HashMap<String, Value<'static>>> {
+        match timing::time_rpc(|| {
+            self.callback
+                .invoke(token, args, provider, version, parent, depends_on)
+        }) {
             Ok(resp) => {
                 if !resp.failures.is_empty() {
+                    let mut diags = self.state.diags.lock().unwrap();
                     for (prop, reason) in &resp.failures {
-                        self.state.diags.lock().unwrap().error(
-                            None,
-                            format!("invoke {} failed on property '{}': {}", token, prop, reason),
-                            "",
-                        );
-                    }
-                    return None;
-                }
-
-                // If a return field is specified, extract that property
-                if let Some(ref return_field) = invoke.return_ {
-                    let field_name = return_field.as_ref();
-                    match resp.return_values.get(field_name) {
-                        Some(val) => Some(val.clone()),
-                        None => {
-                            // Return null if the field doesn't exist
-                            Some(Value::Null)
+                        if self.rich_check_failures {
+                            let (span, detail) = check_failure_location(inputs, prop);
+                            diags.error(
+                                span,
+                                format!(
+                                    "invoke {} rejected argument '{}': {}",
+                                    token, prop, reason
+                                ),
+                                detail,
+                            );
+                        } else {
+                            diags.error(
+                                None,
+                                format!(
+                                    "invoke {} failed on property '{}': {}",
+                                    token, prop, reason
+                                ),
+                                "",
+                            );
                         }
                     }
-                } else {
-                    // Return the full result as an object
-                    let entries: Vec<(Cow<'e, str>, Value<'e>)> = resp
-                        .return_values
-                        .into_iter()
-                        .map(|(k, v)| (Cow::Owned(k), v))
-                        .collect();
-                    Some(Value::Object(entries))
+                    return None;
                 }
+                Some(resp.return_values)
             }
             Err(e) => {
                 self.state.diags.lock().unwrap().error(
-                    None,
+                    inputs.span(),
                     format!("invoke {} failed: {}", token, e),
                     "",
                 );
@@ -1741,114 +3603,546 @@ impl<C: ResourceCallback> Evaluator<'_, C> {
             }
         }
     }
-}
 
-/// Converts a `serde_json::Value` to an eval `Value<'static>`.
-/// Used for injecting schema constant values into resource inputs.
-fn json_value_to_eval_value(json: &serde_json::Value) -> Option<Value<'static>> {
-    Some(Value::from_json(json))
-}
+    /// Calls a provider function once per page (the `paginate` invoke
+    /// option), passing each returned page token back until it is empty, and
+    /// returns the concatenated items. The result is unknown if an unknown
+    /// page or token is returned, and secret if any page's items are. At
+    /// most [`EvalLimits::max_pages`] pages are fetched.
+    fn paginate_invoke(
+        &self,
+        token: &str,
+        paginate: &PaginateDecl<'_>,
+        mut args: HashMap<String, Value<'static>>,
+        call: impl Fn(HashMap<String, Value<'static>>) -> Option<HashMap<String, Value<'static>>>,
+    ) -> Option<Value<'static>> {
+        let items_field = paginate.items_field.as_ref();
+        let token_field = paginate.token_field.as_ref();
+        let token_argument = paginate
+            .token_argument
+            .as_deref()
+            .unwrap_or(token_field)
+            .to_string();
+        let mut items = Vec::new();
+        let mut is_secret = false;
+        let mut seen_tokens = HashSet::new();
+        let mut pages = 0;
+        loop {
+            if pages == self.limits.max_pages {
+                self.state.diags.lock().unwrap().error(
+                    None,
+                    format!(
+                        "invoke {} returned more than {} pages",
+                        token, self.limits.max_pages
+                    ),
+                    "raise PULUMI_YAML_MAX_PAGES to fetch more",
+                );
+                return None;
+            }
+            pages += 1;
+            let mut page = call(args.clone())?;
+
+            let page_items = page.remove(items_field).unwrap_or(Value::Null);
+            is_secret |= page_items.is_secret();
+            match page_items.unwrap_secret() {
+                Value::Null => {}
+                Value::List(page_items) => items.extend(page_items.iter().cloned()),
+                Value::Unknown => return Some(Value::Unknown),
+                other => {
+                    self.state.diags.lock().unwrap().error(
+                        None,
+                        format!(
+                            "invoke {}: paginated property '{}' must be a list, got {}",
+                            token,
+                            items_field,
+                            other.type_name()
+                        ),
+                        "",
+                    );
+                    return None;
+                }
+            }
 
-/// Check whether a resource type token is on the blocklist.
-/// Returns `Some(error_message)` if blocked, `None` if allowed.
-///
-/// Matches Go behavior in `pkg/pulumiyaml/packages.go:270-324`.
-fn check_blocked_type(type_token: &str) -> Option<String> {
-    // Kubernetes resources not supported in YAML
-    const KUBERNETES_BLOCKED: &[(&str, &str)] = &[
-        (
-            "kubernetes:apiextensions.k8s.io:CustomResource",
-            "https://github.com/pulumi/pulumi-kubernetes/issues/1971",
-        ),
-        (
-            "kubernetes:kustomize:Directory",
-            "https://github.com/pulumi/pulumi-kubernetes/issues/1971",
-        ),
-        (
-            "kubernetes:yaml:ConfigFile",
-            "https://github.com/pulumi/pulumi-kubernetes/issues/1971",
-        ),
-        (
-            "kubernetes:yaml:ConfigGroup",
-            "https://github.com/pulumi/pulumi-kubernetes/issues/1971",
-        ),
-    ];
-
-    for (token, url) in KUBERNETES_BLOCKED {
-        if type_token == *token {
-            return Some(format!(
-                "The resource type {} is not supported in YAML at this time, see: {}",
-                type_token, url
-            ));
+            let next = page.remove(token_field).unwrap_or(Value::Null);
+            let secret_token = next.is_secret();
+            let next = match next.unwrap_secret() {
+                Value::Null => break,
+                Value::String(next) if next.is_empty() => break,
+                Value::String(next) => next.to_string(),
+                Value::Unknown => return Some(Value::Unknown),
+                other => {
+                    self.state.diags.lock().unwrap().error(
+                        None,
+                        format!(
+                            "invoke {}: page token property '{}' must be a string, got {}",
+                            token,
+                            token_field,
+                            other.type_name()
+                        ),
+                        "",
+                    );
+                    return None;
+                }
+            };
+            if !seen_tokens.insert(next.clone()) {
+                self.state.diags.lock().unwrap().error(
+                    None,
+                    if secret_token {
+                        format!("invoke {} returned a secret page token twice", token)
+                    } else {
+                        format!("invoke {} returned page token '{}' twice", token, next)
+                    },
+                    "pagination stopped to avoid an endless loop",
+                );
+                return None;
+            }
+            args.insert(token_argument.clone(), Value::String(Cow::Owned(next)));
         }
-    }
-
-    // Helm Chart resources — suggest Helm Release instead
-    if type_token == "kubernetes:helm.sh/v2:Chart" || type_token == "kubernetes:helm.sh/v3:Chart" {
-        return Some(format!(
-            "The resource type {} is not supported in YAML. Use kubernetes:helm.sh/v3:Release instead",
-            type_token
-        ));
-    }
 
-    // Docker Image resources — blocked for versions < 4
-    // Note: In the full runtime with schema loading, we'd check the provider version.
-    // For now, block docker:image:Image and docker:Image unconditionally since we can't
-    // easily query the provider version at this layer.
-    if type_token == "docker:image:Image" || type_token == "docker:Image" {
-        return Some(
-            "Docker Image resources are not supported in YAML without Docker provider major version >= 4. \
-            See: https://github.com/pulumi/pulumi-yaml/issues/421"
-                .to_string(),
-        );
+        let items = Value::List(items);
+        Some(if is_secret {
+            Value::Secret(Box::new(items))
+        } else {
+            items
+        })
     }
-
-    None
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::ast::parse::parse_template;
-
-    fn new_evaluator() -> Evaluator<'static> {
-        Evaluator::new(
-            "test".to_string(),
-            "dev".to_string(),
-            "/tmp".to_string(),
-            false,
-        )
+impl<C: ResourceCallback> Evaluator<'_, C> {
+    /// Evaluates a computed resource name. During a preview a name that is
+    /// not known yet is previewed as the logical name, with a warning.
+    fn eval_resource_name(&self, logical_name: &str, expr: &Expr<'_>) -> Option<String> {
+        let value = self.eval_expr(expr)?;
+        match &value {
+            Value::String(name) => Some(name.to_string()),
+            Value::Unknown if self.dry_run => {
+                self.state.diags.lock().unwrap().warning(
+                    None,
+                    format!(
+                        "the name of resource '{}' is not known until the update",
+                        logical_name
+                    ),
+                    format!("it is previewed as '{}'", logical_name),
+                );
+                Some(logical_name.to_string())
+            }
+            _ => {
+                self.state.diags.lock().unwrap().error(
+                    None,
+                    format!(
+                        "the name of resource '{}' must be a string, got {}",
+                        logical_name,
+                        value.type_name()
+                    ),
+                    if value.is_secret() {
+                        "resource names are part of the URN and cannot be secret"
+                    } else {
+                        ""
+                    },
+                );
+                None
+            }
+        }
     }
 
-    #[test]
-    fn test_eval_null() {
-        let eval = new_evaluator();
-        let expr = Expr::Null(Default::default());
-        assert_eq!(eval.eval_expr(&expr), Some(Value::Null));
-    }
+    /// Opens an ESC provider function through the host's resolver.
+    fn eval_open(&self, provider: &str, inputs: &Expr<'_>) -> Option<Value<'static>> {
+        let inputs = self.eval_expr(inputs)?.into_owned();
+        if builtins::has_unknown(&inputs) {
+            return Some(Value::Unknown);
+        }
+        let Some(resolver) = self.open_resolver.clone() else {
+            self.state.diags.lock().unwrap().error(
+                None,
+                format!("fn::open::{} cannot be opened", provider),
+                "this host has no ESC resolver for fn::open provider functions",
+            );
+            return None;
+        };
+        let is_secret = inputs.is_secret();
+        let inputs = if is_secret {
+            inputs.unwrap_secret().clone()
+        } else {
+            inputs
+        };
 
-    #[test]
-    fn test_eval_bool() {
-        let eval = new_evaluator();
-        let expr = Expr::Bool(Default::default(), true);
-        assert_eq!(eval.eval_expr(&expr), Some(Value::Bool(true)));
+        match timing::time_rpc(|| resolver.open(provider, &inputs)) {
+            Ok(v) if is_secret => Some(Value::Secret(Box::new(v))),
+            Ok(v) => Some(v),
+            Err(e) => {
+                self.state.diags.lock().unwrap().error(
+                    None,
+                    format!("fn::open::{} failed: {}", provider, e),
+                    "",
+                );
+                None
+            }
+        }
     }
 
-    #[test]
-    fn test_eval_number() {
-        let eval = new_evaluator();
-        let expr = Expr::Number(Default::default(), 42.0);
-        assert_eq!(eval.eval_expr(&expr), Some(Value::Number(42.0)));
+    /// Evaluates `fn::env`, if the host allows it. The default is evaluated
+    /// only when the variable is unset.
+    fn eval_env<'e>(&self, name: &'e Expr<'e>, default: Option<&'e Expr<'e>>) -> Option<Value<'e>> {
+        if !self.allow_env {
+            self.state.diags.lock().unwrap().error(
+                None,
+                "fn::env is not allowed",
+                "set the allowEnv runtime option to read environment variables",
+            );
+            return None;
+        }
+        let name = self.eval_expr(name)?;
+        let default = match default {
+            Some(d) if builtins::env_unset(&name) => Some(self.eval_expr(d)?),
+            _ => None,
+        };
+        builtins::eval_env(&name, default, &mut self.state.diags.lock().unwrap())
     }
 
-    #[test]
-    fn test_eval_string() {
-        let eval = new_evaluator();
-        let expr = Expr::String(Default::default(), Cow::Owned("hello".to_string()));
-        assert_eq!(
-            eval.eval_expr(&expr),
-            Some(Value::String(Cow::Owned("hello".to_string())))
-        );
+    /// Evaluates `fn::encrypted` by passing the ciphertext to the host's
+    /// [`Decrypter`](crate::eval::encrypted::Decrypter). The plaintext is
+    /// always a secret.
+    fn eval_encrypted(&self, ciphertext: &Expr<'_>) -> Option<Value<'static>> {
+        let ciphertext = self.eval_expr(ciphertext)?;
+        if builtins::has_unknown(&ciphertext) {
+            return Some(Value::Unknown);
+        }
+        let ciphertext = {
+            let mut diags = self.state.diags.lock().unwrap();
+            builtins::expect_string(ciphertext.unwrap_secret(), "fn::encrypted", &mut diags)?
+                .to_string()
+        };
+        let Some(decrypter) = self.decrypter.clone() else {
+            self.state.diags.lock().unwrap().error(
+                None,
+                "fn::encrypted cannot be decrypted",
+                "this host has no decrypter for fn::encrypted values",
+            );
+            return None;
+        };
+
+        match timing::time_rpc(|| decrypter.decrypt(&ciphertext)) {
+            Ok(plaintext) => Some(Value::Secret(Box::new(Value::String(Cow::Owned(
+                plaintext,
+            ))))),
+            Err(e) => {
+                self.state.diags.lock().unwrap().error(
+                    None,
+                    format!("fn::encrypted failed: {}", e),
+                    "",
+                );
+                None
+            }
+        }
+    }
+
+    /// Evaluates a call to a host-provided builtin (`fn::<namespace>:<name>`).
+    ///
+    /// Unknown arguments short-circuit to unknown during preview, and a secret
+    /// argument produces a secret result.
+    fn eval_extension<'e>(&self, invoke: &'e InvokeExpr<'e>) -> Option<Value<'e>> {
+        let token = invoke.token.as_ref();
+        let func = match self.extensions.get(token) {
+            Some(f) => f.clone(),
+            None => {
+                let namespace = token.split_once(':').map(|(ns, _)| ns).unwrap_or(token);
+                self.state.diags.lock().unwrap().error(
+                    None,
+                    format!("unknown builtin fn::{}", token),
+                    format!(
+                        "namespace '{}' provides: {}",
+                        namespace,
+                        self.extensions.names_in(namespace).join(", ")
+                    ),
+                );
+                return None;
+            }
+        };
+
+        let arg = match invoke.call_args {
+            Some(ref args) => self.eval_expr(args)?.into_owned(),
+            None => Value::Null,
+        };
+        if builtins::has_unknown(&arg) {
+            return Some(Value::Unknown);
+        }
+        let is_secret = arg.is_secret();
+        let arg = if is_secret {
+            arg.unwrap_secret().clone()
+        } else {
+            arg
+        };
+
+        match func(&arg) {
+            Ok(v) if is_secret => Some(Value::Secret(Box::new(v))),
+            Ok(v) => Some(v),
+            Err(e) => {
+                self.state.diags.lock().unwrap().error(
+                    None,
+                    format!("fn::{} failed: {}", token, e),
+                    "",
+                );
+                None
+            }
+        }
+    }
+}
+
+/// The template expressions behind the inputs a provider checked.
+#[derive(Clone, Copy)]
+enum CheckedInputs<'a, 'e> {
+    /// The arguments of an invoke, if it has any, and the invoke's span.
+    Invoke(Option<&'a Expr<'e>>, Option<Span>),
+    /// The properties of a resource and the span of its entry.
+    Resource(&'a ResourceProperties<'e>, Option<Span>),
+}
+
+impl CheckedInputs<'_, '_> {
+    /// The span of the call or resource entry the inputs belong to.
+    fn span(self) -> Option<Span> {
+        match self {
+            CheckedInputs::Invoke(_, span) | CheckedInputs::Resource(_, span) => span,
+        }
+    }
+}
+
+/// Locates the input behind a check failure. The provider's property path
+/// (`filters[0].name`) is followed through the property list and the
+/// literal objects and lists of `inputs` as far as the template spells it
+/// out. Returns the span of the deepest expression reached and a detail
+/// naming it.
+fn check_failure_location(inputs: CheckedInputs<'_, '_>, path: &str) -> (Option<Span>, String) {
+    let input = format!("{}}}", path);
+    let mut scratch = Diagnostics::new();
+    let accessors = match crate::ast::property::parse_property_access(&input, None, &mut scratch) {
+        (_, Some(access)) => access.accessors,
+        _ => Vec::new(),
+    };
+    let (what, one) = match inputs {
+        CheckedInputs::Invoke(..) => ("the invoke arguments", "argument"),
+        CheckedInputs::Resource(..) => ("the resource properties", "property"),
+    };
+    let mut matched = 0;
+    let mut expr = match inputs {
+        CheckedInputs::Invoke(Some(args), _) => Some(args),
+        CheckedInputs::Invoke(None, span) => {
+            return (span, "the invoke has no arguments".to_string());
+        }
+        CheckedInputs::Resource(ResourceProperties::Expr(expr), _) => Some(expr.as_ref()),
+        CheckedInputs::Resource(ResourceProperties::Map(props), _) => {
+            let first = match accessors.first() {
+                Some(PropertyAccessor::Name(key) | PropertyAccessor::StringSubscript(key)) => {
+                    props.iter().find(|p| p.key == key.as_ref())
+                }
+                _ => None,
+            };
+            first.map(|p| {
+                matched = 1;
+                &p.value
+            })
+        }
+    };
+    if let Some(mut current) = expr {
+        for accessor in &accessors[matched..] {
+            let next = match (accessor, current) {
+                (
+                    PropertyAccessor::Name(key) | PropertyAccessor::StringSubscript(key),
+                    Expr::Object(_, props),
+                ) => props
+                    .iter()
+                    .find(|p| p.key.as_str() == Some(key.as_ref()))
+                    .map(|p| p.value.as_ref()),
+                (PropertyAccessor::IntSubscript(i), Expr::List(_, items)) => {
+                    let len = items.len() as i64;
+                    let idx = if *i < 0 { len + i } else { *i };
+                    usize::try_from(idx).ok().and_then(|idx| items.get(idx))
+                }
+                _ => None,
+            };
+            match next {
+                Some(next) => {
+                    current = next;
+                    matched += 1;
+                }
+                None => break,
+            }
+        }
+        expr = Some(current);
+    }
+    let detail = if !accessors.is_empty() && matched == accessors.len() {
+        format!("the value is set by '{}' in {}", path, what)
+    } else if matched == 0 {
+        format!("'{}' is not set in {}", path, what)
+    } else {
+        let prefix = PropertyAccess {
+            accessors: accessors[..matched].to_vec(),
+        };
+        format!(
+            "'{}' is not set in {}; the closest {} is '{}'",
+            path, what, one, prefix
+        )
+    };
+    let span = expr.and_then(|e| e.meta().span).or(inputs.span());
+    (span, detail)
+}
+
+/// Extracts the property check failures from the message of a failed
+/// registration. The engine reports each failure the provider's `Check`
+/// returned as a line of the form
+/// `<type> resource '<name>': property <path> value {<value>} has a problem: <reason>`.
+fn parse_check_failures(message: &str) -> Vec<(String, String)> {
+    static FAILURE: std::sync::OnceLock<regex::Regex> = std::sync::OnceLock::new();
+    let failure = FAILURE.get_or_init(|| {
+        regex::Regex::new(r"property (\S+) value .*? has a problem: (.+)").expect("valid regex")
+    });
+    message
+        .lines()
+        .filter_map(|line| failure.captures(line))
+        .map(|c| (c[1].to_string(), c[2].trim().to_string()))
+        .collect()
+}
+
+/// Converts a `serde_json::Value` to an eval `Value<'static>`.
+/// Used for injecting schema constant values into resource inputs.
+fn json_value_to_eval_value(json: &serde_json::Value) -> Option<Value<'static>> {
+    Some(Value::from_json(json))
+}
+
+/// One instance of a `count:` or `range:` resource entry.
+struct RangeInstance {
+    /// The instance's logical name, e.g. `bucket[0]` or `bucket["us"]`.
+    name: String,
+    /// The key as it appears in the default physical name.
+    suffix: String,
+    key: Value<'static>,
+    value: Value<'static>,
+}
+
+/// The parts of a `--target` URN that identify a resource.
+struct TargetUrn<'a> {
+    stack: &'a str,
+    project: &'a str,
+    /// The resource's own type, without the types of its parents.
+    type_token: &'a str,
+    name: &'a str,
+}
+
+/// Splits `<stack>::<project>::<parent types $ type>::<name>`, the part of
+/// a URN after `urn:pulumi:`. Returns `None` for anything else.
+fn parse_target_urn(rest: &str) -> Option<TargetUrn<'_>> {
+    let mut parts = rest.splitn(4, "::");
+    let stack = parts.next()?;
+    let project = parts.next()?;
+    let qualified_type = parts.next()?;
+    let name = parts.next()?;
+    Some(TargetUrn {
+        stack,
+        project,
+        type_token: qualified_type.rsplit('$').next()?,
+        name,
+    })
+}
+
+/// The `<parent types $ type>` part of a URN after `urn:pulumi:`.
+fn parse_qualified_type(rest: &str) -> Option<&str> {
+    let mut parts = rest.splitn(4, "::");
+    let qualified_type = parts.nth(2)?;
+    parts.next()?;
+    Some(qualified_type)
+}
+
+/// Lists the resources of `template` in reverse dependency order, each with
+/// the resources it depends on directly or through variables and config.
+fn destroy_order(
+    template: &TemplateDecl<'_>,
+    result: &SortResultWithDeps,
+    resolve_type_token: impl Fn(&str) -> String,
+) -> Vec<PlannedResource> {
+    let resources: HashMap<&str, &ResourceEntry<'_>> = template
+        .resources
+        .iter()
+        .map(|r| (r.logical_name.as_ref(), r))
+        .collect();
+    result
+        .order
+        .iter()
+        .rev()
+        .filter_map(|node| {
+            let entry = resources.get(node.as_str())?;
+            let mut dependencies = BTreeSet::new();
+            let mut seen = HashSet::new();
+            let mut pending: Vec<&str> = result
+                .deps
+                .get(node)
+                .map(|d| d.iter().map(|s| s.as_str()).collect())
+                .unwrap_or_default();
+            while let Some(dep) = pending.pop() {
+                if !seen.insert(dep) {
+                    continue;
+                }
+                if resources.contains_key(dep) {
+                    dependencies.insert(dep.to_string());
+                } else if let Some(deps) = result.deps.get(dep) {
+                    pending.extend(deps.iter().map(|s| s.as_str()));
+                }
+            }
+            Some(PlannedResource {
+                logical_name: node.clone(),
+                type_token: resolve_type_token(entry.resource.type_.as_ref()),
+                name: entry
+                    .resource
+                    .static_name(node)
+                    .unwrap_or(node.as_str())
+                    .to_string(),
+                dependencies: dependencies.into_iter().collect(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::parse::parse_template;
+
+    fn new_evaluator() -> Evaluator<'static> {
+        Evaluator::new(
+            "test".to_string(),
+            "dev".to_string(),
+            "/tmp".to_string(),
+            false,
+        )
+    }
+
+    #[test]
+    fn test_eval_null() {
+        let eval = new_evaluator();
+        let expr = Expr::Null(Default::default());
+        assert_eq!(eval.eval_expr(&expr), Some(Value::Null));
+    }
+
+    #[test]
+    fn test_eval_bool() {
+        let eval = new_evaluator();
+        let expr = Expr::Bool(Default::default(), true);
+        assert_eq!(eval.eval_expr(&expr), Some(Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_eval_number() {
+        let eval = new_evaluator();
+        let expr = Expr::Number(Default::default(), 42.0.into());
+        assert_eq!(eval.eval_expr(&expr), Some(Value::Number(42.0.into())));
+    }
+
+    #[test]
+    fn test_eval_string() {
+        let eval = new_evaluator();
+        let expr = Expr::String(Default::default(), Cow::Owned("hello".to_string()));
+        assert_eq!(
+            eval.eval_expr(&expr),
+            Some(Value::String(Cow::Owned("hello".to_string())))
+        );
     }
 
     #[test]
@@ -1857,16 +4151,16 @@ mod tests {
         let expr = Expr::List(
             Default::default(),
             vec![
-                Expr::Number(Default::default(), 1.0),
-                Expr::Number(Default::default(), 2.0),
+                Expr::Number(Default::default(), 1.0.into()),
+                Expr::Number(Default::default(), 2.0.into()),
             ],
         );
         let result = eval.eval_expr(&expr).unwrap();
         match result {
             Value::List(items) => {
                 assert_eq!(items.len(), 2);
-                assert_eq!(items[0], Value::Number(1.0));
-                assert_eq!(items[1], Value::Number(2.0));
+                assert_eq!(items[0], Value::Number(1.0.into()));
+                assert_eq!(items[1], Value::Number(2.0.into()));
             }
             _ => panic!("expected list"),
         }
@@ -1933,7 +4227,7 @@ mod tests {
     #[test]
     fn test_eval_select() {
         let eval = new_evaluator();
-        let index = Expr::Number(Default::default(), 1.0);
+        let index = Expr::Number(Default::default(), 1.0.into());
         let values = Expr::List(
             Default::default(),
             vec![
@@ -2197,28 +4491,28 @@ variables:
     #[test]
     fn test_eval_abs() {
         let eval = new_evaluator();
-        let inner = Expr::Number(Default::default(), -42.0);
+        let inner = Expr::Number(Default::default(), (-42.0).into());
         let expr = Expr::Abs(Default::default(), Box::new(inner));
         let result = eval.eval_expr(&expr).unwrap();
-        assert_eq!(result, Value::Number(42.0));
+        assert_eq!(result, Value::Number(42.0.into()));
     }
 
     #[test]
     fn test_eval_floor() {
         let eval = new_evaluator();
-        let inner = Expr::Number(Default::default(), 3.7);
+        let inner = Expr::Number(Default::default(), 3.7.into());
         let expr = Expr::Floor(Default::default(), Box::new(inner));
         let result = eval.eval_expr(&expr).unwrap();
-        assert_eq!(result, Value::Number(3.0));
+        assert_eq!(result, Value::Number(3.0.into()));
     }
 
     #[test]
     fn test_eval_ceil() {
         let eval = new_evaluator();
-        let inner = Expr::Number(Default::default(), 3.2);
+        let inner = Expr::Number(Default::default(), 3.2.into());
         let expr = Expr::Ceil(Default::default(), Box::new(inner));
         let result = eval.eval_expr(&expr).unwrap();
-        assert_eq!(result, Value::Number(4.0));
+        assert_eq!(result, Value::Number(4.0.into()));
     }
 
     #[test]
@@ -2227,14 +4521,14 @@ variables:
         let inner = Expr::List(
             Default::default(),
             vec![
-                Expr::Number(Default::default(), 1.0),
-                Expr::Number(Default::default(), 5.0),
-                Expr::Number(Default::default(), 3.0),
+                Expr::Number(Default::default(), 1.0.into()),
+                Expr::Number(Default::default(), 5.0.into()),
+                Expr::Number(Default::default(), 3.0.into()),
             ],
         );
         let expr = Expr::Max(Default::default(), Box::new(inner));
         let result = eval.eval_expr(&expr).unwrap();
-        assert_eq!(result, Value::Number(5.0));
+        assert_eq!(result, Value::Number(5.0.into()));
     }
 
     #[test]
@@ -2243,14 +4537,14 @@ variables:
         let inner = Expr::List(
             Default::default(),
             vec![
-                Expr::Number(Default::default(), 1.0),
-                Expr::Number(Default::default(), 5.0),
-                Expr::Number(Default::default(), 3.0),
+                Expr::Number(Default::default(), 1.0.into()),
+                Expr::Number(Default::default(), 5.0.into()),
+                Expr::Number(Default::default(), 3.0.into()),
             ],
         );
         let expr = Expr::Min(Default::default(), Box::new(inner));
         let result = eval.eval_expr(&expr).unwrap();
-        assert_eq!(result, Value::Number(1.0));
+        assert_eq!(result, Value::Number(1.0.into()));
     }
 
     #[test]
@@ -2259,15 +4553,15 @@ variables:
         let inner = Expr::String(Default::default(), Cow::Owned("hello".to_string()));
         let expr = Expr::StringLen(Default::default(), Box::new(inner));
         let result = eval.eval_expr(&expr).unwrap();
-        assert_eq!(result, Value::Number(5.0));
+        assert_eq!(result, Value::Number(5.0.into()));
     }
 
     #[test]
     fn test_eval_substring() {
         let eval = new_evaluator();
         let source = Expr::String(Default::default(), Cow::Owned("hello world".to_string()));
-        let start = Expr::Number(Default::default(), 0.0);
-        let length = Expr::Number(Default::default(), 5.0);
+        let start = Expr::Number(Default::default(), 0.0.into());
+        let length = Expr::Number(Default::default(), 5.0.into());
         let expr = Expr::Substring(
             Default::default(),
             Box::new(source),
@@ -2279,149 +4573,1368 @@ variables:
     }
 
     #[test]
-    fn test_eval_time_utc() {
-        let eval = new_evaluator();
-        let inner = Expr::Object(Default::default(), vec![]);
-        let expr = Expr::TimeUtc(Default::default(), Box::new(inner));
-        let result = eval.eval_expr(&expr).unwrap();
-        let s = result.as_str().unwrap();
-        assert!(s.ends_with('Z'));
-        assert!(s.contains('T'));
+    fn test_eval_time_utc() {
+        let eval = new_evaluator();
+        let inner = Expr::Object(Default::default(), vec![]);
+        let expr = Expr::TimeUtc(Default::default(), Box::new(inner));
+        let result = eval.eval_expr(&expr).unwrap();
+        let s = result.as_str().unwrap();
+        assert!(s.ends_with('Z'));
+        assert!(s.contains('T'));
+    }
+
+    #[test]
+    fn test_eval_time_unix() {
+        let eval = new_evaluator();
+        let inner = Expr::Object(Default::default(), vec![]);
+        let expr = Expr::TimeUnix(Default::default(), Box::new(inner));
+        let result = eval.eval_expr(&expr).unwrap();
+        match result {
+            Value::Number(n) => assert!(n > 1_700_000_000.0),
+            _ => panic!("expected number"),
+        }
+    }
+
+    #[test]
+    fn test_eval_uuid() {
+        let eval = new_evaluator();
+        let inner = Expr::Object(Default::default(), vec![]);
+        let expr = Expr::Uuid(Default::default(), Box::new(inner));
+        let result = eval.eval_expr(&expr).unwrap();
+        let id = result.as_str().unwrap();
+        assert_eq!(id.len(), 36);
+        assert_eq!(id.split('-').count(), 5);
+    }
+
+    #[test]
+    fn test_eval_random_string() {
+        let eval = new_evaluator();
+        let inner = Expr::Number(Default::default(), 16.0.into());
+        let expr = Expr::RandomString(Default::default(), Box::new(inner));
+        let result = eval.eval_expr(&expr).unwrap();
+        assert_eq!(result.as_str().unwrap().len(), 16);
+    }
+
+    #[test]
+    fn test_eval_date_format() {
+        let eval = new_evaluator();
+        let inner = Expr::String(Default::default(), Cow::Owned("%Y-%m-%d".to_string()));
+        let expr = Expr::DateFormat(Default::default(), Box::new(inner));
+        let result = eval.eval_expr(&expr).unwrap();
+        let formatted = result.as_str().unwrap();
+        assert_eq!(formatted.len(), 10);
+        assert_eq!(&formatted[4..5], "-");
+    }
+
+    #[test]
+    fn test_eval_new_builtins_template() {
+        let source = r#"
+name: test
+runtime: yaml
+variables:
+  absResult:
+    fn::abs: -42
+  floorResult:
+    fn::floor: 3.7
+  ceilResult:
+    fn::ceil: 3.2
+  maxResult:
+    fn::max: [1, 5, 3, 2, 4]
+  minResult:
+    fn::min: [1, 5, 3, 2, 4]
+  strLen:
+    fn::stringLen: "hello world"
+  substr:
+    fn::substring:
+      - "hello world"
+      - 0
+      - 5
+outputs:
+  abs: ${absResult}
+  floor: ${floorResult}
+  ceil: ${ceilResult}
+  max: ${maxResult}
+  min: ${minResult}
+  stringLen: ${strLen}
+  substring: ${substr}
+"#;
+        let (template, parse_diags) = parse_template(source, None);
+        assert!(!parse_diags.has_errors(), "parse errors: {}", parse_diags);
+
+        let eval = Evaluator::new(
+            "test".to_string(),
+            "dev".to_string(),
+            "/tmp".to_string(),
+            false,
+        );
+        eval.evaluate_template(&template, &HashMap::new(), &[]);
+        assert!(!eval.has_errors(), "eval errors: {}", eval.diags_display());
+
+        assert_eq!(
+            eval.get_output("abs").and_then(|v| match v {
+                Value::Number(n) => Some(n.as_f64()),
+                _ => None,
+            }),
+            Some(42.0)
+        );
+        assert_eq!(
+            eval.get_output("floor").and_then(|v| match v {
+                Value::Number(n) => Some(n.as_f64()),
+                _ => None,
+            }),
+            Some(3.0)
+        );
+        assert_eq!(
+            eval.get_output("ceil").and_then(|v| match v {
+                Value::Number(n) => Some(n.as_f64()),
+                _ => None,
+            }),
+            Some(4.0)
+        );
+        assert_eq!(
+            eval.get_output("max").and_then(|v| match v {
+                Value::Number(n) => Some(n.as_f64()),
+                _ => None,
+            }),
+            Some(5.0)
+        );
+        assert_eq!(
+            eval.get_output("min").and_then(|v| match v {
+                Value::Number(n) => Some(n.as_f64()),
+                _ => None,
+            }),
+            Some(1.0)
+        );
+        assert_eq!(
+            eval.get_output("stringLen").and_then(|v| match v {
+                Value::Number(n) => Some(n.as_f64()),
+                _ => None,
+            }),
+            Some(11.0)
+        );
+        assert_eq!(
+            eval.get_output("substring")
+                .and_then(|v| v.as_str().map(|s| s.to_string())),
+            Some("hello".to_string())
+        );
+    }
+
+    #[test]
+    fn test_eval_map_and_filter_template() {
+        let source = r#"
+name: test
+runtime: yaml
+variables:
+  subnets:
+    fn::map:
+      - ${zones}
+      - name: subnet-${index}
+        zone: ${item.name}
+  publicZones:
+    fn::filter:
+      - ${zones}
+      - ${item.public}
+  zones:
+    - name: us-east-1a
+      public: true
+    - name: us-east-1b
+      public: false
+outputs:
+  subnets: ${subnets}
+  publicZones:
+    fn::map:
+      - ${publicZones}
+      - ${item.name}
+"#;
+        let (template, parse_diags) = parse_template(source, None);
+        assert!(!parse_diags.has_errors(), "parse errors: {}", parse_diags);
+
+        let eval = new_evaluator();
+        eval.evaluate_template(&template, &HashMap::new(), &[]);
+        assert!(!eval.has_errors(), "eval errors: {}", eval.diags_display());
+
+        let subnets = eval.get_output("subnets").unwrap();
+        let Value::List(subnets) = subnets else {
+            panic!("expected a list, got {:?}", subnets);
+        };
+        assert_eq!(subnets.len(), 2);
+        let Value::Object(second) = &subnets[1] else {
+            panic!("expected an object, got {:?}", subnets[1]);
+        };
+        assert_eq!(second[0].1.as_str(), Some("subnet-1"));
+        assert_eq!(second[1].1.as_str(), Some("us-east-1b"));
+
+        assert_eq!(
+            eval.get_output("publicZones"),
+            Some(Value::List(vec![Value::String(Cow::Borrowed(
+                "us-east-1a"
+            ))]))
+        );
+    }
+
+    #[test]
+    fn test_eval_foreach() {
+        let source = r#"
+name: test
+runtime: yaml
+config:
+  zones:
+    type: List<String>
+    default: [a, b]
+variables:
+  ports: [80, 443]
+  rules:
+    fn::foreach:
+      list: ${zones}
+      as: zone
+      index: i
+      body:
+        fn::foreach:
+          list: ${ports}
+          as: port
+          body: ${zone}-${i}:${port}
+"#;
+        let (template, parse_diags) = parse_template(source, None);
+        assert!(!parse_diags.has_errors(), "parse errors: {}", parse_diags);
+
+        let eval = new_evaluator();
+        eval.evaluate_template(&template, &HashMap::new(), &[]);
+        assert!(!eval.has_errors(), "eval errors: {}", eval.diags_display());
+        assert_eq!(
+            eval.get_variable("rules").unwrap().to_json(),
+            serde_json::json!([["a-0:80", "a-0:443"], ["b-1:80", "b-1:443"]])
+        );
+        assert!(scope::lookup("zone").is_none());
+    }
+
+    #[test]
+    fn test_eval_map_errors() {
+        let cases = [
+            (
+                "fn::map: [hello, '${item}']",
+                "the first argument to fn::map must be a list, not string",
+            ),
+            (
+                "fn::filter: [[1, 2], '${item}']",
+                "the predicate of fn::filter must evaluate to a boolean, not number",
+            ),
+            (
+                "fn::foreach: { list: hello, as: x, body: '${x}' }",
+                "the 'list' of fn::foreach must be a list, not string",
+            ),
+        ];
+        for (expr, expected) in cases {
+            let source = format!(
+                "name: test\nruntime: yaml\nvariables:\n  v:\n    {}\n",
+                expr
+            );
+            let (template, parse_diags) = parse_template(&source, None);
+            assert!(!parse_diags.has_errors(), "parse errors: {}", parse_diags);
+
+            let eval = new_evaluator();
+            eval.evaluate_template(&template, &HashMap::new(), &[]);
+            let errors = eval.diag_errors().join("\n");
+            assert!(errors.contains(expected), "{}", errors);
+        }
+        // The loop bindings do not leak out of the body.
+        assert!(scope::lookup("item").is_none());
+    }
+
+    #[test]
+    fn test_eval_read_file_binary() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("logo.png"), [0x89, b'P', 0xff, 0x00]).unwrap();
+        let source = r#"
+name: test
+runtime: yaml
+variables:
+  logo:
+    fn::readFileBinary: logo.png
+  encoded:
+    fn::toBase64: ${logo}
+resources:
+  object:
+    type: test:index:Object
+    properties:
+      content: ${logo}
+      contentBase64: ${encoded}
+"#;
+        let (template, parse_diags) = parse_template(source, None);
+        assert!(!parse_diags.has_errors(), "parse errors: {}", parse_diags);
+        let eval = Evaluator::with_callback(
+            "test".to_string(),
+            "dev".to_string(),
+            dir.path().display().to_string(),
+            false,
+            crate::eval::mock::MockCallback::new(),
+        );
+        eval.evaluate_template(&template, &HashMap::new(), &[]);
+        assert!(!eval.has_errors(), "eval errors: {}", eval.diags_display());
+
+        let regs = eval.callback().registrations();
+        let object = &regs[0].inputs;
+        assert_eq!(
+            object["content"].as_bytes(),
+            Some(&[0x89, b'P', 0xff, 0x00][..])
+        );
+        assert_eq!(object["contentBase64"].as_str(), Some("iVD/AA=="));
+
+        let source = "name: test\nruntime: yaml\nvariables:\n  logo:\n    fn::readFile: logo.png\n";
+        let (template, _) = parse_template(source, None);
+        let eval = Evaluator::new(
+            "test".to_string(),
+            "dev".to_string(),
+            dir.path().display().to_string(),
+            false,
+        );
+        eval.evaluate_template(&template, &HashMap::new(), &[]);
+        let errors = eval.diag_errors().join("\n");
+        assert!(errors.contains("use fn::readFileBinary"), "{}", errors);
+    }
+
+    #[test]
+    fn test_eval_template_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("user-data.sh"),
+            "#!/bin/sh\necho ${greeting} from ${region}\nZONES='${zones}' ZONE=${zones[0]} PID=$$ HOME=$${HOME}\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("broken.sh"), "echo ${missing}\n").unwrap();
+        std::fs::write(dir.path().join("settings.json"), "${settings}\n").unwrap();
+        let eval = |source: &str| {
+            let (template, parse_diags) = parse_template(source, None);
+            assert!(!parse_diags.has_errors(), "parse errors: {}", parse_diags);
+            let eval = Evaluator::new(
+                "test".to_string(),
+                "dev".to_string(),
+                dir.path().display().to_string(),
+                false,
+            );
+            eval.evaluate_template(&template, &HashMap::new(), &[]);
+            (eval.get_variable("script"), eval.diag_errors().join("\n"))
+        };
+
+        let (script, errors) = eval(
+            r#"
+name: test
+runtime: yaml
+variables:
+  region: us-east-1
+  script:
+    fn::templateFile:
+      path: user-data.sh
+      arguments:
+        greeting:
+          fn::secret: hello
+        region: ${region}
+        zones: [a, b]
+"#,
+        );
+        assert!(errors.is_empty(), "{}", errors);
+        let script = script.unwrap();
+        assert!(script.is_secret());
+        assert_eq!(
+            script.unwrap_secret().as_str(),
+            Some("#!/bin/sh\necho hello from us-east-1\nZONES='[\"a\",\"b\"]' ZONE=a PID=$$ HOME=${HOME}\n")
+        );
+
+        // A secret nested inside a rendered object still makes the result secret.
+        let (script, errors) = eval(
+            r#"
+name: test
+runtime: yaml
+variables:
+  script:
+    fn::templateFile:
+      path: settings.json
+      arguments:
+        settings:
+          user: admin
+          password:
+            fn::secret: hunter2
+"#,
+        );
+        assert!(errors.is_empty(), "{}", errors);
+        let script = script.unwrap();
+        assert!(script.is_secret());
+        assert!(script.unwrap_secret().as_str().unwrap().contains("hunter2"));
+
+        let (script, errors) = eval(
+            r#"
+name: test
+runtime: yaml
+variables:
+  script:
+    fn::templateFile:
+      path: broken.sh
+      arguments:
+        region: us-east-1
+"#,
+        );
+        assert!(script.is_none());
+        assert!(
+            errors.contains(
+                "fn::templateFile: broken.sh references ${missing}, which is not in its arguments"
+            ),
+            "{}",
+            errors
+        );
+    }
+
+    #[test]
+    fn test_eval_paths_relative_to_declaring_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("base")).unwrap();
+        std::fs::write(dir.path().join("base/motd.txt"), "from base").unwrap();
+        std::fs::write(dir.path().join("motd.txt"), "from project").unwrap();
+        let source = r#"
+name: test
+runtime: yaml
+variables:
+  inherited:
+    fn::readFile: ./motd.txt
+  local:
+    fn::readFile: ./motd.txt
+  site:
+    fn::fileArchive: ./site
+"#;
+        let (template, parse_diags) = parse_template(source, None);
+        assert!(!parse_diags.has_errors(), "parse errors: {}", parse_diags);
+        let source_map: HashMap<String, String> = [
+            ("inherited", "base/Pulumi.yaml"),
+            ("local", "Pulumi.yaml"),
+            ("site", "base/Pulumi.yaml"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let eval = |project_relative_paths: bool| {
+            let mut eval = Evaluator::new(
+                "test".to_string(),
+                "dev".to_string(),
+                dir.path().display().to_string(),
+                true,
+            );
+            eval.source_map = Some(Arc::new(source_map.clone()));
+            eval.project_relative_paths = project_relative_paths;
+            eval.evaluate_template(&template, &HashMap::new(), &[]);
+            assert!(!eval.has_errors(), "{:?}", eval.diag_errors());
+            eval
+        };
+
+        let eval_default = eval(false);
+        let text = |eval: &Evaluator, name: &str| {
+            eval.get_variable(name)
+                .unwrap()
+                .as_str()
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(text(&eval_default, "inherited"), "from base");
+        assert_eq!(text(&eval_default, "local"), "from project");
+        assert_eq!(
+            eval_default.get_variable("site"),
+            Some(Value::Archive(Archive::File(Cow::Borrowed("base/site"))))
+        );
+
+        let eval_compat = eval(true);
+        assert_eq!(text(&eval_compat, "inherited"), "from project");
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_eval_windows_style_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("base/scripts")).unwrap();
+        std::fs::write(dir.path().join("base/scripts/motd.txt"), "from base").unwrap();
+        let source = r#"
+name: test
+runtime: yaml
+variables:
+  motd:
+    fn::readFile: .\scripts\motd.txt
+  unc:
+    fn::readFile: \\server\share\motd.txt
+"#;
+        let (template, parse_diags) = parse_template(source, None);
+        assert!(!parse_diags.has_errors(), "parse errors: {}", parse_diags);
+        let mut eval = Evaluator::new(
+            "test".to_string(),
+            "dev".to_string(),
+            dir.path().display().to_string(),
+            true,
+        );
+        let source_map: HashMap<String, String> =
+            [("motd", "base\\Pulumi.yaml"), ("unc", "Pulumi.yaml")]
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+        eval.source_map = Some(Arc::new(source_map));
+        eval.evaluate_template(&template, &HashMap::new(), &[]);
+        assert_eq!(
+            eval.get_variable("motd").as_ref().and_then(|v| v.as_str()),
+            Some("from base")
+        );
+        // UNC paths are absolute and never joined to the project directory.
+        let errors = eval.diag_errors().join("\n");
+        assert!(
+            errors.contains("at path \\\\server\\share\\motd.txt:"),
+            "{}",
+            errors
+        );
+    }
+
+    #[test]
+    fn test_eval_if() {
+        let source = r#"
+name: test
+runtime: yaml
+config:
+  large:
+    type: boolean
+  secretFlag:
+    type: boolean
+    secret: true
+variables:
+  size:
+    fn::if:
+      - ${large}
+      - { cpu: 4, memory: 16 }
+      - fn::readFile: ./does-not-exist.txt
+  tier:
+    fn::if: ["${secretFlag}", gold, silver]
+"#;
+        let (template, parse_diags) = parse_template(source, None);
+        assert!(!parse_diags.has_errors(), "parse errors: {}", parse_diags);
+        let eval = Evaluator::new("test".into(), "dev".into(), ".".into(), true);
+        let config: HashMap<String, String> = [
+            ("test:large".to_string(), "true".to_string()),
+            ("test:secretFlag".to_string(), "false".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        eval.evaluate_template(&template, &config, &["test:secretFlag".to_string()]);
+
+        // The else branch would fail to read its file; it is never evaluated.
+        let errors = eval.diag_errors().join("\n");
+        assert!(errors.is_empty(), "{}", errors);
+        let size = eval.get_variable("size").unwrap();
+        assert_eq!(
+            size.to_json(),
+            serde_json::json!({ "cpu": 4, "memory": 16 })
+        );
+        let tier = eval.get_variable("tier").unwrap();
+        assert!(tier.is_secret());
+        assert_eq!(tier.unwrap_secret().as_str(), Some("silver"));
+    }
+
+    #[test]
+    fn test_eval_if_requires_boolean_condition() {
+        let source = r#"
+name: test
+runtime: yaml
+variables:
+  bad:
+    fn::if: [yes-please, a, b]
+"#;
+        let (template, parse_diags) = parse_template(source, None);
+        assert!(!parse_diags.has_errors(), "parse errors: {}", parse_diags);
+        let eval = Evaluator::new("test".into(), "dev".into(), ".".into(), true);
+        eval.evaluate_template(&template, &HashMap::new(), &[]);
+        let errors = eval.diag_errors().join("\n");
+        assert!(
+            errors.contains("the condition of fn::if must be a boolean, not string"),
+            "{}",
+            errors
+        );
+    }
+
+    #[test]
+    fn test_eval_encrypted() {
+        let source = r#"
+name: test
+runtime: yaml
+variables:
+  password:
+    fn::encrypted: c2VhbGVk
+"#;
+        let (template, parse_diags) = parse_template(source, None);
+        assert!(!parse_diags.has_errors(), "parse errors: {}", parse_diags);
+        type Decrypt = fn(&str) -> Result<String, String>;
+        let evaluate = |decrypter: Option<Decrypt>| {
+            let mut eval = Evaluator::new("test".into(), "dev".into(), ".".into(), true);
+            if let Some(decrypter) = decrypter {
+                eval.set_decrypter(decrypter);
+            }
+            eval.evaluate_template(&template, &HashMap::new(), &[]);
+            (eval.get_variable("password"), eval.diag_errors().join("\n"))
+        };
+
+        let (password, errors) = evaluate(Some(|ciphertext| match ciphertext {
+            "c2VhbGVk" => Ok("hunter2".to_string()),
+            _ => Err("invalid ciphertext".to_string()),
+        }));
+        assert!(errors.is_empty(), "{}", errors);
+        let password = password.unwrap();
+        assert!(password.is_secret());
+        assert_eq!(password.unwrap_secret().as_str(), Some("hunter2"));
+
+        let (_, errors) = evaluate(Some(|_| Err("invalid ciphertext".to_string())));
+        assert!(
+            errors.contains("fn::encrypted failed: invalid ciphertext"),
+            "{}",
+            errors
+        );
+
+        let (_, errors) = evaluate(None);
+        assert!(
+            errors.contains("fn::encrypted cannot be decrypted"),
+            "{}",
+            errors
+        );
+    }
+
+    #[test]
+    fn test_eval_read_file_through_fs_provider() {
+        let source = r#"
+name: test
+runtime: yaml
+variables:
+  motd:
+    fn::readFile: ./motd.txt
+  secret:
+    fn::readFile: ../secrets/key
+"#;
+        let (template, parse_diags) = parse_template(source, None);
+        assert!(!parse_diags.has_errors(), "parse errors: {}", parse_diags);
+        let mut eval = Evaluator::new(
+            "test".to_string(),
+            "dev".to_string(),
+            "/project".to_string(),
+            true,
+        );
+        let fs = crate::vfs::MemoryFs::new()
+            .with_file("/project/motd.txt", "hello")
+            .with_file("/secrets/key", "hunter2");
+        eval.set_fs(crate::vfs::RootedFs::new(fs, ["/project"]));
+        eval.evaluate_template(&template, &HashMap::new(), &[]);
+        assert_eq!(
+            eval.get_variable("motd").as_ref().and_then(|v| v.as_str()),
+            Some("hello")
+        );
+        let errors = eval.diag_errors().join("\n");
+        assert!(
+            errors.contains("outside the allowed directories"),
+            "{}",
+            errors
+        );
+    }
+
+    #[test]
+    fn test_eval_validate_json() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("user.schema.yaml"),
+            "type: object\nrequired: [name]\nproperties:\n  groups:\n    type: array\n    items: { type: string }\n",
+        )
+        .unwrap();
+        let eval = |source: &str| {
+            let (template, parse_diags) = parse_template(source, None);
+            assert!(!parse_diags.has_errors(), "parse errors: {}", parse_diags);
+            let eval = Evaluator::new(
+                "test".to_string(),
+                "dev".to_string(),
+                dir.path().display().to_string(),
+                false,
+            );
+            eval.evaluate_template(&template, &HashMap::new(), &[]);
+            let user = eval.get_variable("user");
+            (user, eval.diag_errors().join("\n"))
+        };
+
+        let (user, errors) = eval(
+            r#"
+name: test
+runtime: yaml
+variables:
+  user:
+    fn::validateJson:
+      value:
+        name: ops
+      schema:
+        type: object
+        properties:
+          name: { type: string, minLength: 2 }
+"#,
+        );
+        assert!(errors.is_empty(), "{}", errors);
+        assert_eq!(
+            user.unwrap().to_json(),
+            serde_json::json!({ "name": "ops" })
+        );
+
+        let (user, errors) = eval(
+            r#"
+name: test
+runtime: yaml
+variables:
+  user:
+    fn::validateJson:
+      value:
+        groups: [wheel, 10]
+      schema: user.schema.yaml
+"#,
+        );
+        assert!(user.is_none());
+        assert!(
+            errors.contains("fn::validateJson: (root): missing required property \"name\""),
+            "{}",
+            errors
+        );
+        assert!(
+            errors.contains("fn::validateJson: /groups/1: expected string, found number"),
+            "{}",
+            errors
+        );
+    }
+
+    #[test]
+    fn test_eval_assert() {
+        let source = r#"
+name: test
+runtime: yaml
+config:
+  azCount:
+    type: integer
+    default: 3
+variables:
+  subnets: [a, b]
+  ok:
+    fn::assert:
+      condition: true
+      message: unused
+  matches:
+    fn::assert:
+      condition:
+        fn::startsWith: [prod, pro]
+      message: never shown
+  counts:
+    fn::assert:
+      condition: false
+      message: expected ${azCount} subnets, found 2
+"#;
+        let (template, parse_diags) = parse_template(source, None);
+        assert!(!parse_diags.has_errors(), "parse errors: {}", parse_diags);
+        let eval = Evaluator::new(
+            "test".to_string(),
+            "dev".to_string(),
+            "/tmp".to_string(),
+            false,
+        );
+        eval.evaluate_template(&template, &HashMap::new(), &[]);
+        assert_eq!(eval.get_variable("ok"), Some(Value::Bool(true)));
+        assert_eq!(eval.get_variable("matches"), Some(Value::Bool(true)));
+        assert_eq!(
+            eval.diag_errors(),
+            ["assertion failed: expected 3 subnets, found 2"]
+        );
+        let span = eval.state.diags.lock().unwrap().iter().next().unwrap().span;
+        let span = span.expect("the assertion has a span");
+        assert!(source[span.start as usize..].starts_with("fn::assert:\n      condition: false"));
+    }
+
+    #[test]
+    fn test_eval_conditions() {
+        let source = r#"
+name: test
+runtime: yaml
+config:
+  env:
+    type: string
+    default: prod
+  replicas:
+    type: integer
+    default: 3
+variables:
+  size:
+    fn::if:
+      - fn::and:
+          - fn::equals: ["${env}", prod]
+          - fn::greaterThanOrEqual: ["${replicas}", 3]
+      - large
+      - small
+  single:
+    fn::not:
+      fn::greaterThan: ["${replicas}", 1]
+  allowed:
+    fn::or:
+      - fn::lessThan: ["${replicas}", 1]
+      - fn::lessThanOrEqual: ["${replicas}", 5]
+"#;
+        let (template, parse_diags) = parse_template(source, None);
+        assert!(!parse_diags.has_errors(), "parse errors: {}", parse_diags);
+        let eval = Evaluator::new(
+            "test".to_string(),
+            "dev".to_string(),
+            "/tmp".to_string(),
+            false,
+        );
+        eval.evaluate_template(&template, &HashMap::new(), &[]);
+        assert!(!eval.has_errors(), "eval errors: {}", eval.diags_display());
+        assert_eq!(
+            eval.get_variable("size"),
+            Some(Value::String("large".into()))
+        );
+        assert_eq!(eval.get_variable("single"), Some(Value::Bool(false)));
+        assert_eq!(eval.get_variable("allowed"), Some(Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_eval_and_or_are_lazy() {
+        let source = |op: &str, first: bool| {
+            format!(
+                r#"
+name: test
+runtime: yaml
+variables:
+  found:
+    fn::{}:
+      - {}
+      - fn::equals:
+          - fn::invoke:
+              function: aws:ec2:getAmi
+              arguments:
+                mostRecent: true
+              return: id
+          - ami-0
+"#,
+                op, first
+            )
+        };
+        for (op, first, invokes) in [
+            ("and", false, 0),
+            ("and", true, 1),
+            ("or", true, 0),
+            ("or", false, 1),
+        ] {
+            let (template, parse_diags) = parse_template(&source(op, first), None);
+            assert!(!parse_diags.has_errors(), "parse errors: {}", parse_diags);
+            let eval = Evaluator::with_callback(
+                "test".to_string(),
+                "dev".to_string(),
+                "/tmp".to_string(),
+                false,
+                crate::eval::mock::MockCallback::new(),
+            );
+            eval.evaluate_template(&template, &HashMap::new(), &[]);
+            assert!(!eval.has_errors(), "eval errors: {}", eval.diags_display());
+            assert_eq!(
+                eval.callback().invocations().len(),
+                invokes,
+                "fn::{} starting with {}",
+                op,
+                first
+            );
+        }
+    }
+
+    #[test]
+    fn test_eval_lookup_default_is_lazy() {
+        let source = |region: &str| {
+            format!(
+                r#"
+name: test
+runtime: yaml
+variables:
+  amis:
+    us-east-1: ami-east
+    us-west-2: ami-west
+  ami:
+    fn::lookup:
+      - ${{amis}}
+      - {}
+      - fn::invoke:
+          function: aws:ec2:getAmi
+          arguments:
+            mostRecent: true
+          return: id
+"#,
+                region
+            )
+        };
+        for (region, invokes) in [("us-west-2", 0), ("eu-west-1", 1)] {
+            let (template, parse_diags) = parse_template(&source(region), None);
+            assert!(!parse_diags.has_errors(), "parse errors: {}", parse_diags);
+            let eval = Evaluator::with_callback(
+                "test".to_string(),
+                "dev".to_string(),
+                "/tmp".to_string(),
+                false,
+                crate::eval::mock::MockCallback::new(),
+            );
+            eval.evaluate_template(&template, &HashMap::new(), &[]);
+            assert!(!eval.has_errors(), "eval errors: {}", eval.diags_display());
+            assert_eq!(eval.callback().invocations().len(), invokes, "{}", region);
+        }
+    }
+
+    #[test]
+    fn test_eval_env_requires_allow_env() {
+        let source = r#"
+name: test
+runtime: yaml
+variables:
+  token:
+    fn::env: PULUMI_YAML_TEST_EVAL_ENV
+  region:
+    fn::env: [PULUMI_YAML_TEST_EVAL_ENV_UNSET, us-east-1]
+"#;
+        std::env::set_var("PULUMI_YAML_TEST_EVAL_ENV", "from-ci");
+        let (template, parse_diags) = parse_template(source, None);
+        assert!(!parse_diags.has_errors(), "parse errors: {}", parse_diags);
+        let mut eval = Evaluator::new(
+            "test".to_string(),
+            "dev".to_string(),
+            "/tmp".to_string(),
+            false,
+        );
+        eval.evaluate_template(&template, &HashMap::new(), &[]);
+        assert!(eval.diags_display().contains("fn::env is not allowed"));
+
+        eval.reset();
+        eval.allow_env = true;
+        eval.evaluate_template(&template, &HashMap::new(), &[]);
+        assert!(!eval.has_errors(), "eval errors: {}", eval.diags_display());
+        assert_eq!(
+            eval.get_variable("token"),
+            Some(Value::String("from-ci".into()))
+        );
+        assert_eq!(
+            eval.get_variable("region"),
+            Some(Value::String("us-east-1".into()))
+        );
     }
 
     #[test]
-    fn test_eval_time_unix() {
-        let eval = new_evaluator();
-        let inner = Expr::Object(Default::default(), vec![]);
-        let expr = Expr::TimeUnix(Default::default(), Box::new(inner));
-        let result = eval.eval_expr(&expr).unwrap();
-        match result {
-            Value::Number(n) => assert!(n > 1_700_000_000.0),
-            _ => panic!("expected number"),
+    fn test_eval_coalesce_is_lazy() {
+        let source = |override_value: &str| {
+            format!(
+                r#"
+name: test
+runtime: yaml
+variables:
+  override: {}
+  ami:
+    fn::coalesce:
+      - ${{override}}
+      - fn::invoke:
+          function: aws:ec2:getAmi
+          arguments:
+            mostRecent: true
+          return: id
+"#,
+                override_value
+            )
+        };
+        for (override_value, invokes) in [("ami-pinned", 0), ("''", 1), ("null", 1)] {
+            let (template, parse_diags) = parse_template(&source(override_value), None);
+            assert!(!parse_diags.has_errors(), "parse errors: {}", parse_diags);
+            let eval = Evaluator::with_callback(
+                "test".to_string(),
+                "dev".to_string(),
+                "/tmp".to_string(),
+                false,
+                crate::eval::mock::MockCallback::new(),
+            );
+            eval.evaluate_template(&template, &HashMap::new(), &[]);
+            assert!(!eval.has_errors(), "eval errors: {}", eval.diags_display());
+            assert_eq!(
+                eval.callback().invocations().len(),
+                invokes,
+                "{}",
+                override_value
+            );
         }
     }
 
     #[test]
-    fn test_eval_uuid() {
-        let eval = new_evaluator();
-        let inner = Expr::Object(Default::default(), vec![]);
-        let expr = Expr::Uuid(Default::default(), Box::new(inner));
-        let result = eval.eval_expr(&expr).unwrap();
-        let id = result.as_str().unwrap();
-        assert_eq!(id.len(), 36);
-        assert_eq!(id.split('-').count(), 5);
-    }
+    fn test_eval_merge_and_json_patch() {
+        let eval = |source: &str| {
+            let (template, parse_diags) = parse_template(source, None);
+            assert!(!parse_diags.has_errors(), "parse errors: {}", parse_diags);
+            let eval = Evaluator::new("test".to_string(), "dev".to_string(), ".".to_string(), true);
+            eval.evaluate_template(&template, &HashMap::new(), &[]);
+            eval
+        };
+        let base = r#"
+name: test
+runtime: yaml
+variables:
+  base:
+    replicas: 1
+    containers:
+      - name: web
+        image: nginx:1.25
+"#;
 
-    #[test]
-    fn test_eval_random_string() {
-        let eval = new_evaluator();
-        let inner = Expr::Number(Default::default(), 16.0);
-        let expr = Expr::RandomString(Default::default(), Box::new(inner));
-        let result = eval.eval_expr(&expr).unwrap();
-        assert_eq!(result.as_str().unwrap().len(), 16);
+        let ok = eval(&format!(
+            "{}{}",
+            base,
+            r#"
+  merged:
+    fn::merge:
+      - ${base}
+      - replicas: 3
+        containers:
+          - name: web
+            image: nginx:1.27
+          - name: metrics
+            image: exporter
+  patched:
+    fn::jsonPatch:
+      - ${base}
+      - - op: replace
+          path: /containers/0/image
+          value: nginx:1.27
+        - op: remove
+          path: /replicas
+"#
+        ));
+        assert!(ok.diag_errors().is_empty(), "{:?}", ok.diag_errors());
+        assert_eq!(
+            ok.get_variable("merged").unwrap().to_json(),
+            serde_json::json!({
+                "replicas": 3,
+                "containers": [
+                    { "name": "web", "image": "nginx:1.27" },
+                    { "name": "metrics", "image": "exporter" },
+                ],
+            })
+        );
+        assert_eq!(
+            ok.get_variable("patched").unwrap().to_json(),
+            serde_json::json!({ "containers": [{ "name": "web", "image": "nginx:1.27" }] })
+        );
+
+        let broken = eval(&format!(
+            "{}{}",
+            base,
+            r#"
+  broken:
+    fn::jsonPatch:
+      - ${base}
+      - - op: remove
+          path: /missing
+"#
+        ));
+        assert!(broken.get_variable("broken").is_none());
+        assert!(broken
+            .diag_errors()
+            .join("\n")
+            .contains("fn::jsonPatch operation 0 failed: path not found"));
     }
 
     #[test]
-    fn test_eval_date_format() {
-        let eval = new_evaluator();
-        let inner = Expr::String(Default::default(), Cow::Owned("%Y-%m-%d".to_string()));
-        let expr = Expr::DateFormat(Default::default(), Box::new(inner));
-        let result = eval.eval_expr(&expr).unwrap();
-        let formatted = result.as_str().unwrap();
-        assert_eq!(formatted.len(), 10);
-        assert_eq!(&formatted[4..5], "-");
+    fn test_eval_resource_locals() {
+        let source = r#"
+name: test
+runtime: yaml
+variables:
+  env: prod
+resources:
+  app:
+    type: test:index:App
+    locals:
+      prefix: ${env}-app
+      target: ${logs.id}
+      name: ${prefix}-1
+    properties:
+      name: ${name}
+      logBucket: ${target}
+  logs:
+    type: test:index:Bucket
+"#;
+        let (template, parse_diags) = parse_template(source, None);
+        assert!(!parse_diags.has_errors(), "parse errors: {}", parse_diags);
+
+        let eval = Evaluator::with_callback(
+            "test".to_string(),
+            "dev".to_string(),
+            "/tmp".to_string(),
+            false,
+            crate::eval::mock::MockCallback::new(),
+        );
+        eval.evaluate_template(&template, &HashMap::new(), &[]);
+        assert!(!eval.has_errors(), "eval errors: {}", eval.diags_display());
+
+        let regs = eval.callback().registrations();
+        let app = regs.iter().find(|r| r.name == "app").unwrap();
+        assert_eq!(app.inputs["name"].as_str(), Some("prod-app-1"));
+        assert_eq!(
+            app.options.property_dependencies["logBucket"],
+            vec!["urn:pulumi:test::test::test:index/bucket:Bucket::logs".to_string()]
+        );
+        assert!(!eval.has_variable("prefix"));
+        assert!(scope::lookup("prefix").is_none());
     }
 
     #[test]
-    fn test_eval_new_builtins_template() {
+    fn test_eval_resource_count_and_range() {
         let source = r#"
 name: test
 runtime: yaml
 variables:
-  absResult:
-    fn::abs: -42
-  floorResult:
-    fn::floor: 3.7
-  ceilResult:
-    fn::ceil: 3.2
-  maxResult:
-    fn::max: [1, 5, 3, 2, 4]
-  minResult:
-    fn::min: [1, 5, 3, 2, 4]
-  strLen:
-    fn::stringLen: "hello world"
-  substr:
-    fn::substring:
-      - "hello world"
-      - 0
-      - 5
+  zones:
+    east: us-east-1a
+    west: us-west-2a
+resources:
+  web:
+    type: test:index:Server
+    count: 2
+    properties:
+      index: ${range.value}
+  subnets:
+    type: test:index:Subnet
+    range: ${zones}
+    name: subnet-${range.key}
+    properties:
+      zone: ${range.value}
+      server: ${web[0].id}
+  disks:
+    type: test:index:Disk
+    range: [small, large]
+    locals:
+      label: ${range.key}-${range.value}
+    properties:
+      label: ${label}
+    options:
+      dependsOn: ${web}
 outputs:
-  abs: ${absResult}
-  floor: ${floorResult}
-  ceil: ${ceilResult}
-  max: ${maxResult}
-  min: ${minResult}
-  stringLen: ${strLen}
-  substring: ${substr}
+  secondIndex: ${web[1].index}
+  eastZone: ${subnets.east.zone}
+  largeDisk: ${disks[1].label}
 "#;
         let (template, parse_diags) = parse_template(source, None);
         assert!(!parse_diags.has_errors(), "parse errors: {}", parse_diags);
 
-        let eval = Evaluator::new(
+        let eval = Evaluator::with_callback(
             "test".to_string(),
             "dev".to_string(),
             "/tmp".to_string(),
             false,
+            crate::eval::mock::MockCallback::new(),
         );
         eval.evaluate_template(&template, &HashMap::new(), &[]);
         assert!(!eval.has_errors(), "eval errors: {}", eval.diags_display());
 
+        let regs = eval.callback().registrations();
+        let names: Vec<&str> = regs.iter().map(|r| r.name.as_str()).collect();
+        for name in [
+            "web-0",
+            "web-1",
+            "subnet-east",
+            "subnet-west",
+            "disks-0",
+            "disks-1",
+        ] {
+            assert!(names.contains(&name), "{} not in {:?}", name, names);
+        }
+        let west = regs.iter().find(|r| r.name == "subnet-west").unwrap();
+        assert_eq!(west.inputs["zone"].as_str(), Some("us-west-2a"));
         assert_eq!(
-            eval.get_output("abs").and_then(|v| match v {
-                Value::Number(n) => Some(n),
-                _ => None,
-            }),
-            Some(42.0)
+            west.options.property_dependencies["server"],
+            vec![
+                "urn:pulumi:test::test::test:index/server:Server::web-0".to_string(),
+                "urn:pulumi:test::test::test:index/server:Server::web-1".to_string(),
+            ]
         );
+        let large = regs.iter().find(|r| r.name == "disks-1").unwrap();
+        assert_eq!(large.inputs["label"].as_str(), Some("1-large"));
+        assert_eq!(large.options.depends_on.len(), 2);
+
+        assert!(eval.has_resource("web[1]"));
+        assert!(eval.has_resource("subnets[\"east\"]"));
+        assert!(!eval.has_resource("web"));
         assert_eq!(
-            eval.get_output("floor").and_then(|v| match v {
-                Value::Number(n) => Some(n),
-                _ => None,
-            }),
-            Some(3.0)
+            eval.get_output("secondIndex"),
+            Some(Value::Number(1i64.into()))
         );
         assert_eq!(
-            eval.get_output("ceil").and_then(|v| match v {
-                Value::Number(n) => Some(n),
-                _ => None,
-            }),
-            Some(4.0)
+            eval.get_output("eastZone").unwrap().as_str(),
+            Some("us-east-1a")
         );
         assert_eq!(
-            eval.get_output("max").and_then(|v| match v {
-                Value::Number(n) => Some(n),
-                _ => None,
-            }),
-            Some(5.0)
+            eval.get_output("largeDisk").unwrap().as_str(),
+            Some("1-large")
         );
-        assert_eq!(
-            eval.get_output("min").and_then(|v| match v {
-                Value::Number(n) => Some(n),
-                _ => None,
-            }),
-            Some(1.0)
+        assert!(scope::lookup("range").is_none());
+    }
+
+    #[test]
+    fn test_eval_resource_range_errors() {
+        for (range, expected) in [
+            ("count: -1", "must be a non-negative integer, not -1"),
+            ("count: two", "must be a number, not string"),
+            ("range: 3", "must be a list or an object, not number"),
+        ] {
+            let source = format!(
+                "name: test\nruntime: yaml\nresources:\n  web:\n    type: test:index:Server\n    {}\n",
+                range
+            );
+            let (template, _) = parse_template(&source, None);
+            let eval = new_evaluator();
+            eval.evaluate_template(&template, &HashMap::new(), &[]);
+            let errors = eval.diag_errors();
+            assert!(
+                errors.iter().any(|e| e.contains(expected)),
+                "{}: {:?}",
+                range,
+                errors
+            );
+        }
+    }
+
+    #[test]
+    fn test_eval_resource_range_keeps_registered_instances() {
+        let source = r#"
+name: test
+runtime: yaml
+variables:
+  none: {}
+resources:
+  web:
+    type: test:index:Server
+    count: 3
+  empty:
+    type: test:index:Server
+    range: ${none}
+outputs:
+  servers: ${web}
+  emptyGroup: ${empty}
+"#;
+        let (template, _) = parse_template(source, None);
+        for parallel in [0, 4] {
+            let callback = crate::eval::mock::MockCallback::new();
+            callback.fail_registration("web-1", "quota exceeded");
+            let mut eval = Evaluator::with_callback(
+                "test".to_string(),
+                "dev".to_string(),
+                "/tmp".to_string(),
+                false,
+                callback,
+            );
+            eval.continue_on_error = true;
+            eval.parallel = parallel;
+            eval.evaluate_template(&template, &HashMap::new(), &[]);
+
+            let mut names: Vec<String> = eval
+                .callback()
+                .registrations()
+                .into_iter()
+                .map(|r| r.name)
+                .filter(|n| n.starts_with("web-"))
+                .collect();
+            names.sort();
+            assert_eq!(names, vec!["web-0", "web-1", "web-2"]);
+            assert_eq!(eval.diag_errors().len(), 1, "{:?}", eval.diag_errors());
+            assert!(matches!(
+                eval.state.resource_groups.read().unwrap().get("web"),
+                Some(ResourceGroup::List(instances)) if instances == &["web[0]", "web[2]"]
+            ));
+            assert!(matches!(
+                eval.state.resource_groups.read().unwrap().get("empty"),
+                Some(ResourceGroup::Object(instances)) if instances.is_empty()
+            ));
+        }
+    }
+
+    #[test]
+    fn test_eval_resource_range_limit() {
+        let source = r#"
+name: test
+runtime: yaml
+variables:
+  zones: [a, b, c]
+resources:
+  huge:
+    type: test:index:Server
+    count: 100000000
+  zoned:
+    type: test:index:Server
+    range: ${zones}
+"#;
+        let (template, _) = parse_template(source, None);
+        let mut eval = Evaluator::with_callback(
+            "test".to_string(),
+            "dev".to_string(),
+            "/tmp".to_string(),
+            false,
+            crate::eval::mock::MockCallback::new(),
         );
+        eval.limits.max_instances = 2;
+        eval.continue_on_error = true;
+        eval.evaluate_template(&template, &HashMap::new(), &[]);
+        let mut errors = eval.diag_errors();
+        errors.sort();
         assert_eq!(
-            eval.get_output("stringLen").and_then(|v| match v {
-                Value::Number(n) => Some(n),
-                _ => None,
-            }),
-            Some(11.0)
+            errors,
+            vec![
+                "resource 'huge' expands to 100000000 instances, more than the limit of 2"
+                    .to_string(),
+                "resource 'zoned' expands to 3 instances, more than the limit of 2".to_string(),
+            ]
         );
-        assert_eq!(
-            eval.get_output("substring")
-                .and_then(|v| v.as_str().map(|s| s.to_string())),
-            Some("hello".to_string())
+        assert!(eval.callback().registrations().is_empty());
+    }
+
+    #[test]
+    fn test_eval_register_failure_links_docs() {
+        let source = "name: test\nruntime: yaml\nresources:\n  site:\n    type: aws:s3/bucketV2:BucketV2\n    properties:\n      bucket: Not_Valid\n";
+        let (template, _) = parse_template(source, None);
+        let mut store = SchemaStore::new();
+        store.insert(crate::schema::PackageSchema {
+            name: "aws".to_string(),
+            resources: [(
+                "aws:s3/bucketV2:BucketV2".to_string(),
+                crate::schema::ResourceTypeInfo {
+                    example: Some(
+                        "resources:\n  example:\n    type: aws:s3:BucketV2\n".to_string(),
+                    ),
+                    ..Default::default()
+                },
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        });
+        let callback = crate::eval::mock::MockCallback::new();
+        callback.fail_registration("site", "invalid bucket name \"Not_Valid\"");
+        let mut eval = Evaluator::with_callback(
+            "test".to_string(),
+            "dev".to_string(),
+            "/tmp".to_string(),
+            false,
+            callback,
+        );
+        eval.schema_store = Some(&store);
+        eval.evaluate_template(&template, &HashMap::new(), &[]);
+
+        let messages = eval.diag_error_messages();
+        assert!(
+            messages[0].starts_with("failed to register resource 'site': resource registration failed: invalid bucket name \"Not_Valid\"\nsee https://"),
+            "{:?}",
+            messages
+        );
+        let display = eval.diags_display();
+        assert!(
+            display.contains(
+                "failed to register resource 'site': resource registration failed: invalid bucket name \"Not_Valid\"; \
+                 see https://www.pulumi.com/registry/packages/aws/api-docs/s3/bucketv2/\n\
+                 example:\nresources:\n  example:\n    type: aws:s3:BucketV2\n"
+            ),
+            "{}",
+            display
         );
     }
 
@@ -2448,7 +5961,7 @@ resources:
 "#;
         let (template, parse_diags) = parse_template(source, None);
         assert!(!parse_diags.has_errors());
-        let template: &'static _ = Box::leak(Box::new(template));
+        let template = &template;
 
         let mock = crate::eval::mock::MockCallback::new();
         let mut eval = Evaluator::with_callback(
@@ -2493,7 +6006,7 @@ resources:
 "#;
         let (template, parse_diags) = parse_template(source, None);
         assert!(!parse_diags.has_errors());
-        let template: &'static _ = Box::leak(Box::new(template));
+        let template = &template;
 
         let mock = crate::eval::mock::MockCallback::new();
         let mut eval = Evaluator::with_callback(
@@ -2533,7 +6046,7 @@ resources:
 "#;
         let (template, parse_diags) = parse_template(source, None);
         assert!(!parse_diags.has_errors());
-        let template: &'static _ = Box::leak(Box::new(template));
+        let template = &template;
 
         // Create a mock that fails on "test:Bad"
         let mock = crate::eval::mock::MockCallback::new();
@@ -2571,7 +6084,7 @@ resources:
 "#;
         let (template, parse_diags) = parse_template(source, None);
         assert!(!parse_diags.has_errors());
-        let template: &'static _ = Box::leak(Box::new(template));
+        let template = &template;
 
         let mock = crate::eval::mock::MockCallback::new();
         let mut eval = Evaluator::with_callback(
@@ -2604,7 +6117,7 @@ resources:
 "#;
         let (template, parse_diags) = parse_template(source, None);
         assert!(!parse_diags.has_errors());
-        let template: &'static _ = Box::leak(Box::new(template));
+        let template = &template;
 
         let mock = crate::eval::mock::MockCallback::new();
         let mut eval = Evaluator::with_callback(
@@ -2646,7 +6159,7 @@ resources:
 "#;
         let (template, parse_diags) = parse_template(source, None);
         assert!(!parse_diags.has_errors());
-        let template: &'static _ = Box::leak(Box::new(template));
+        let template = &template;
 
         let mock = crate::eval::mock::MockCallback::new();
         let mut eval = Evaluator::with_callback(
@@ -2662,4 +6175,86 @@ resources:
         assert!(!eval.has_errors(), "errors: {}", eval.diags_display());
         assert_eq!(eval.callback().registrations().len(), 2);
     }
+
+    #[test]
+    fn test_expr_depth_limit() {
+        let mut eval = new_evaluator();
+        eval.limits.max_expr_depth = 8;
+        let mut expr = Expr::Null(Default::default());
+        for _ in 0..16 {
+            expr = Expr::List(Default::default(), vec![expr]);
+        }
+        assert!(eval.eval_expr(&expr).is_none());
+        assert!(eval
+            .diag_errors()
+            .iter()
+            .any(|e| e.contains("maximum depth of 8")));
+    }
+
+    #[test]
+    fn test_interpolation_size_limit() {
+        let source = r#"
+name: test
+runtime: yaml
+variables:
+  word: abcdefghij
+  big: ${word}${word}${word}
+"#;
+        let (template, _) = parse_template(source, None);
+        let mut eval = new_evaluator();
+        eval.limits.max_string_bytes = 25;
+        eval.evaluate_template(&template, &HashMap::new(), &[]);
+        assert!(eval
+            .diag_errors()
+            .iter()
+            .any(|e| e.contains("interpolation result exceeds the maximum size of 25 bytes")));
+        assert!(eval.get_variable("big").is_none());
+    }
+
+    #[test]
+    fn test_payload_size_limit_suggests_assets() {
+        let source = r#"
+name: test
+runtime: yaml
+resources:
+  obj:
+    type: aws:s3:BucketObject
+    properties:
+      bucket: my-bucket
+      content: aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa
+"#;
+        let (template, _) = parse_template(source, None);
+        let mut eval = new_evaluator();
+        eval.limits.max_payload_bytes = 40;
+        eval.evaluate_template(&template, &HashMap::new(), &[]);
+        let diags = eval.state.diags.lock().unwrap();
+        let diag = diags
+            .iter()
+            .find(|d| d.summary.contains("inputs of resource 'obj'"))
+            .expect("expected payload size error");
+        assert!(diag.summary.contains("exceeding the 40 B request limit"));
+        assert!(diag
+            .detail
+            .starts_with("largest properties: content (57 B)"));
+        assert!(diag.detail.contains("fn::fileAsset"));
+    }
+
+    #[test]
+    fn test_value_memory_budget() {
+        let source = r#"
+name: test
+runtime: yaml
+variables:
+  a: aaaaaaaaaaaaaaaaaaaa
+  b: bbbbbbbbbbbbbbbbbbbb
+"#;
+        let (template, _) = parse_template(source, None);
+        let mut eval = new_evaluator();
+        eval.limits.max_value_bytes = 30;
+        eval.evaluate_template(&template, &HashMap::new(), &[]);
+        assert!(eval
+            .diag_errors()
+            .iter()
+            .any(|e| e.contains("exceeds the memory budget of 30 bytes")));
+    }
 }
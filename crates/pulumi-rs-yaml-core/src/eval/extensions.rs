@@ -0,0 +1,214 @@
+//! Host-provided builtin functions.
+//!
+//! Embedders can register custom `fn::<namespace>:<name>` builtins on an
+//! `Evaluator` without forking the crate. Templates call them with the
+//! invoke shorthand syntax:
+//!
+//! ```yaml
+//! variables:
+//!   slug:
+//!     fn::acme:slugify: ${config.displayName}
+//! ```
+//!
+//! Namespacing rules:
+//! - Tokens must have exactly the form `<namespace>:<name>`, so an extension
+//!   can never shadow a core `fn::` builtin (those have no namespace).
+//! - The `pulumi` namespace is reserved.
+//! - Once a namespace is registered, every `fn::<namespace>:*` call is routed
+//!   to the registry instead of the engine, and the namespace is excluded from
+//!   plugin resolution. Namespaces must therefore not coincide with provider
+//!   package names.
+//!
+//! Package discovery ([`get_referenced_packages`]) reports a namespace like
+//! any other invoked package; hosts drop it with
+//! [`BuiltinRegistry::retain_plugin_packages`].
+//!
+//! [`get_referenced_packages`]: crate::packages::get_referenced_packages
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::eval::value::Value;
+use crate::packages::PackageDependency;
+
+/// A host-provided builtin. Receives the evaluated argument (null when the
+/// call has none) and returns the result or an error message.
+pub type BuiltinFn = Arc<dyn Fn(&Value<'static>) -> Result<Value<'static>, String> + Send + Sync>;
+
+/// Namespaces that extensions may not claim.
+const RESERVED_NAMESPACES: &[&str] = &["pulumi"];
+
+/// Errors from registering an extension builtin.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ExtensionError {
+    #[error("invalid builtin token {0:?}: expected '<namespace>:<name>'")]
+    InvalidToken(String),
+    #[error("builtin namespace {0:?} is reserved")]
+    ReservedNamespace(String),
+    #[error("builtin fn::{0} is already registered")]
+    AlreadyRegistered(String),
+}
+
+/// Registry of host-provided `fn::<namespace>:<name>` builtins.
+#[derive(Clone, Default)]
+pub struct BuiltinRegistry {
+    functions: HashMap<String, BuiltinFn>,
+    namespaces: HashSet<String>,
+}
+
+impl std::fmt::Debug for BuiltinRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut names: Vec<&String> = self.functions.keys().collect();
+        names.sort();
+        f.debug_struct("BuiltinRegistry")
+            .field("functions", &names)
+            .finish()
+    }
+}
+
+impl BuiltinRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `func` under `token` (`<namespace>:<name>`, without `fn::`).
+    pub fn register<F>(&mut self, token: &str, func: F) -> Result<(), ExtensionError>
+    where
+        F: Fn(&Value<'static>) -> Result<Value<'static>, String> + Send + Sync + 'static,
+    {
+        let (namespace, _) = split_token(token)?;
+        if RESERVED_NAMESPACES.contains(&namespace) {
+            return Err(ExtensionError::ReservedNamespace(namespace.to_string()));
+        }
+        if self.functions.contains_key(token) {
+            return Err(ExtensionError::AlreadyRegistered(token.to_string()));
+        }
+        self.namespaces.insert(namespace.to_string());
+        self.functions.insert(token.to_string(), Arc::new(func));
+        Ok(())
+    }
+
+    /// Returns true if no builtins are registered.
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+    }
+
+    /// Returns true if `token` belongs to a registered namespace, meaning the
+    /// call must be handled by the registry rather than the engine.
+    pub fn owns(&self, token: &str) -> bool {
+        match token.split_once(':') {
+            Some((namespace, _)) => self.namespaces.contains(namespace),
+            None => false,
+        }
+    }
+
+    /// Returns the builtin registered under `token`, if any.
+    pub fn get(&self, token: &str) -> Option<&BuiltinFn> {
+        self.functions.get(token)
+    }
+
+    /// Returns the sorted names registered in `namespace` (for diagnostics).
+    pub fn names_in(&self, namespace: &str) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .functions
+            .keys()
+            .filter(|k| k.split_once(':').map(|(ns, _)| ns) == Some(namespace))
+            .map(|k| k.as_str())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Drops packages whose name is a registered extension namespace, so that
+    /// `fn::<namespace>:*` calls do not trigger plugin installation.
+    pub fn retain_plugin_packages(&self, packages: &mut Vec<PackageDependency>) {
+        packages.retain(|p| !self.namespaces.contains(&p.name));
+    }
+}
+
+/// Splits and validates a `<namespace>:<name>` token.
+fn split_token(token: &str) -> Result<(&str, &str), ExtensionError> {
+    let invalid = || ExtensionError::InvalidToken(token.to_string());
+    let (namespace, name) = token.split_once(':').ok_or_else(invalid)?;
+    if !is_identifier(namespace) || !is_identifier(name) {
+        return Err(invalid());
+    }
+    Ok((namespace, name))
+}
+
+/// `[A-Za-z][A-Za-z0-9_-]*`
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    fn upper(v: &Value<'static>) -> Result<Value<'static>, String> {
+        match v {
+            Value::String(s) => Ok(Value::String(Cow::Owned(s.to_uppercase()))),
+            other => Err(format!("expected a string, got {}", other.type_name())),
+        }
+    }
+
+    #[test]
+    fn test_register_and_lookup() {
+        let mut registry = BuiltinRegistry::new();
+        registry.register("acme:upper", upper).unwrap();
+        assert!(registry.owns("acme:upper"));
+        assert!(registry.owns("acme:missing"));
+        assert!(!registry.owns("aws:ec2:getAmi"));
+        let f = registry.get("acme:upper").unwrap();
+        assert_eq!(
+            f(&Value::String(Cow::Borrowed("hi"))).unwrap(),
+            Value::String(Cow::Borrowed("HI"))
+        );
+        assert_eq!(registry.names_in("acme"), vec!["acme:upper"]);
+    }
+
+    #[test]
+    fn test_namespacing_rules() {
+        let mut registry = BuiltinRegistry::new();
+        assert_eq!(
+            registry.register("join", upper),
+            Err(ExtensionError::InvalidToken("join".into()))
+        );
+        assert_eq!(
+            registry.register("acme:mod:fn", upper),
+            Err(ExtensionError::InvalidToken("acme:mod:fn".into()))
+        );
+        assert_eq!(
+            registry.register("pulumi:upper", upper),
+            Err(ExtensionError::ReservedNamespace("pulumi".into()))
+        );
+        registry.register("acme:upper", upper).unwrap();
+        assert_eq!(
+            registry.register("acme:upper", upper),
+            Err(ExtensionError::AlreadyRegistered("acme:upper".into()))
+        );
+    }
+
+    #[test]
+    fn test_retain_plugin_packages() {
+        let mut registry = BuiltinRegistry::new();
+        registry.register("acme:upper", upper).unwrap();
+        let pkg = |name: &str| PackageDependency {
+            name: name.to_string(),
+            version: String::new(),
+            download_url: String::new(),
+            parameterization: None,
+        };
+        let mut packages = vec![pkg("acme"), pkg("aws")];
+        registry.retain_plugin_packages(&mut packages);
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].name, "aws");
+    }
+}
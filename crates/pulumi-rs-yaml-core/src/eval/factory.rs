@@ -0,0 +1,229 @@
+//! Reusing evaluators across evaluations.
+//!
+//! A component provider evaluates a component body per `Construct` call, and
+//! a host previewing repeatedly evaluates the same program many times. Each
+//! fresh [`Evaluator`] allocates its state maps anew and has the host's
+//! settings applied again. An [`EvaluatorFactory`] holds those settings once
+//! — the schema store, limits, blocklist, extension builtins, output hooks,
+//! `fn::open` resolver, `fn::encrypted` decrypter, filesystem, and evaluation
+//! cache — and keeps the state of finished evaluators in a pool, so the next
+//! evaluator starts with maps that already have the capacity a typical
+//! evaluation needs:
+//!
+//! ```ignore
+//! let factory = EvaluatorFactory::new().with_schema_store(&store);
+//! for request in requests {
+//!     let eval = factory.create(&request.context, connect(&request)?);
+//!     eval.evaluate_template(&template, &request.config, &[]);
+//!     respond(eval.take_outputs());
+//!     factory.recycle(eval);
+//! }
+//! ```
+//!
+//! For a single evaluator reused in place, see [`Evaluator::reset`].
+
+use std::sync::{Arc, Mutex};
+
+use crate::eval::blocklist::Blocklist;
+use crate::eval::cache::EvalCache;
+use crate::eval::callback::ResourceCallback;
+use crate::eval::context::EvaluatorContext;
+use crate::eval::encrypted::Decrypter;
+use crate::eval::esc::OpenResolver;
+use crate::eval::evaluator::{EvalState, Evaluator};
+use crate::eval::extensions::BuiltinRegistry;
+use crate::eval::hooks::ResourceOutputHook;
+use crate::eval::limits::EvalLimits;
+use crate::schema::SchemaStore;
+use crate::vfs::{FsProvider, OsFs};
+
+/// Number of idle states a factory keeps by default.
+pub const DEFAULT_POOL_SIZE: usize = 8;
+
+/// Creates evaluators sharing one set of host settings, reusing the state of
+/// recycled evaluators.
+pub struct EvaluatorFactory<'schema> {
+    pub schema_store: Option<&'schema SchemaStore>,
+    pub limits: EvalLimits,
+    pub blocklist: Blocklist,
+    pub extensions: BuiltinRegistry,
+    pub output_hooks: Vec<Arc<dyn ResourceOutputHook>>,
+    pub open_resolver: Option<Arc<dyn OpenResolver>>,
+    pub decrypter: Option<Arc<dyn Decrypter>>,
+    pub fs: Arc<dyn FsProvider>,
+    /// Shared by every evaluator the factory creates, so repeated
+    /// evaluations reuse the values of unchanged pure variables.
+    pub eval_cache: Option<Arc<EvalCache>>,
+    /// Maximum number of idle states kept for reuse.
+    pub pool_size: usize,
+    pool: Mutex<Vec<EvalState>>,
+}
+
+impl Default for EvaluatorFactory<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'schema> EvaluatorFactory<'schema> {
+    /// Creates a factory with the evaluator defaults.
+    pub fn new() -> Self {
+        Self {
+            schema_store: None,
+            limits: EvalLimits::default(),
+            blocklist: Blocklist::default(),
+            extensions: BuiltinRegistry::new(),
+            output_hooks: Vec::new(),
+            open_resolver: None,
+            decrypter: None,
+            fs: Arc::new(OsFs),
+            eval_cache: None,
+            pool_size: DEFAULT_POOL_SIZE,
+            pool: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn with_schema_store(mut self, store: &'schema SchemaStore) -> Self {
+        self.schema_store = Some(store);
+        self
+    }
+
+    /// Returns an evaluator for `context` with the factory's settings,
+    /// starting from a pooled state when one is available.
+    pub fn create<C: ResourceCallback>(
+        &self,
+        context: &EvaluatorContext,
+        callback: C,
+    ) -> Evaluator<'schema, C> {
+        let mut eval = Evaluator::from_context(context, callback);
+        eval.schema_store = self.schema_store;
+        eval.limits = self.limits;
+        eval.blocklist = self.blocklist.clone();
+        eval.extensions = self.extensions.clone();
+        eval.output_hooks = self.output_hooks.clone();
+        eval.open_resolver = self.open_resolver.clone();
+        eval.decrypter = self.decrypter.clone();
+        eval.fs = Arc::clone(&self.fs);
+        eval.eval_cache = self.eval_cache.clone();
+        if let Some(state) = self.pool.lock().unwrap().pop() {
+            eval.state = state;
+        }
+        eval
+    }
+
+    /// Returns the state of a finished evaluator to the pool, and its
+    /// callback to the caller.
+    pub fn recycle<C: ResourceCallback>(&self, eval: Evaluator<'schema, C>) -> C {
+        let (callback, mut state) = eval.into_parts();
+        let mut pool = self.pool.lock().unwrap();
+        if pool.len() < self.pool_size {
+            state.reset();
+            pool.push(state);
+        }
+        callback
+    }
+
+    /// Number of idle states in the pool.
+    pub fn pooled(&self) -> usize {
+        self.pool.lock().unwrap().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::parse::parse_template;
+    use crate::eval::mock::MockCallback;
+    use std::collections::HashMap;
+
+    fn context(stack: &str) -> EvaluatorContext {
+        EvaluatorContext::builder("proj", stack)
+            .cwd(".")
+            .root_directory(".")
+            .organization("")
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_recycled_state_starts_empty() {
+        let (template, _) = parse_template(
+            "name: proj\nresources:\n  bucket:\n    type: test:index:Bucket\noutputs:\n  stack: ${pulumi.stack}\n",
+            None,
+        );
+        let factory = EvaluatorFactory::new();
+
+        let first = factory.create(&context("dev"), MockCallback::new());
+        first.evaluate_template(&template, &HashMap::new(), &[]);
+        assert!(!first.has_errors());
+        assert_eq!(first.state.resources.read().unwrap().len(), 1);
+        factory.recycle(first);
+        assert_eq!(factory.pooled(), 1);
+
+        let second = factory.create(&context("prod"), MockCallback::new());
+        assert_eq!(factory.pooled(), 0);
+        assert!(second.state.resources.read().unwrap().is_empty());
+        assert!(second.state.outputs.lock().unwrap().is_empty());
+        second.evaluate_template(&template, &HashMap::new(), &[]);
+        assert!(!second.has_errors());
+        assert_eq!(second.callback().registrations().len(), 1);
+        assert_eq!(
+            second.take_outputs()["stack"],
+            crate::eval::value::Value::String("prod".into())
+        );
+    }
+
+    #[test]
+    fn test_pool_is_bounded() {
+        let mut factory = EvaluatorFactory::new();
+        factory.pool_size = 1;
+        let a = factory.create(&context("dev"), MockCallback::new());
+        let b = factory.create(&context("dev"), MockCallback::new());
+        factory.recycle(a);
+        factory.recycle(b);
+        assert_eq!(factory.pooled(), 1);
+    }
+
+    #[test]
+    fn test_eval_cache_reuses_unchanged_variables() {
+        let (template, _) = parse_template(
+            "name: proj\nconfig:\n  env:\n    type: string\nvariables:\n  upper:\n    fn::toUpper: ${env}\n  stack: ${pulumi.stack}\noutputs:\n  upper: ${upper}\n",
+            None,
+        );
+        let mut factory = EvaluatorFactory::new();
+        let cache = Arc::new(EvalCache::in_memory());
+        factory.eval_cache = Some(Arc::clone(&cache));
+        let config = |env: &str| HashMap::from([("proj:env".to_string(), env.to_string())]);
+
+        let first = factory.create(&context("dev"), MockCallback::new());
+        first.evaluate_template(&template, &config("dev"), &[]);
+        assert!(!first.has_errors());
+        assert_eq!((cache.hits(), cache.misses(), cache.len()), (0, 2, 2));
+
+        let second = factory.create(&context("dev"), MockCallback::new());
+        second.evaluate_template(&template, &config("dev"), &[]);
+        assert_eq!((cache.hits(), cache.misses()), (2, 2));
+        assert_eq!(
+            second.take_outputs()["upper"],
+            crate::eval::value::Value::String("DEV".into())
+        );
+
+        let third = factory.create(&context("prod"), MockCallback::new());
+        third.evaluate_template(&template, &config("prod"), &[]);
+        assert_eq!((cache.hits(), cache.misses(), cache.len()), (2, 4, 4));
+        assert_eq!(
+            third.take_outputs()["upper"],
+            crate::eval::value::Value::String("PROD".into())
+        );
+    }
+
+    #[test]
+    fn test_reset_clears_errors() {
+        let (template, _) = parse_template("name: proj\nvariables:\n  a: ${missing}\n", None);
+        let mut eval = Evaluator::new("proj".into(), "dev".into(), ".".into(), true);
+        eval.evaluate_template(&template, &HashMap::new(), &[]);
+        assert!(eval.has_errors());
+        eval.reset();
+        assert!(!eval.has_errors());
+    }
+}
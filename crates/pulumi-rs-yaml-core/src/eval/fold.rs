@@ -0,0 +1,425 @@
+//! Constant folding of pure builtin calls.
+//!
+//! Before evaluation (or before a template is serialized into an execution
+//! plan), builtin calls whose arguments are all literals are replaced by their
+//! result, e.g. `fn::join: ["-", [a, b]]` becomes the string `"a-b"` and
+//! `fn::toBase64: hello` becomes `"aGVsbG8="`. Folding runs bottom-up, so
+//! nested calls such as a join over a split collapse completely.
+//!
+//! Only deterministic, side-effect free builtins are folded. Calls that would
+//! produce a diagnostic are left in place so the evaluator reports the error
+//! with its usual context, and `fn::secret`, assets, file reads, time,
+//! random builtins, and ESC provider functions are never folded. An `fn::if`
+//! whose condition is a literal is replaced by the value it chooses.
+
+use std::borrow::Cow;
+
+use crate::ast::expr::{Expr, ObjectProperty};
+use crate::ast::template::{ConfigEntry, OutputEntry, ResourceEntry, TemplateDecl, VariableEntry};
+use crate::ast::visitor::{rewrite_expr, rewrite_resource, ExprRewriter};
+use crate::diag::Diagnostics;
+use crate::eval::builtins;
+use crate::eval::value::Value;
+use crate::syntax::ExprMeta;
+
+/// Folds every expression in `template`, including component bodies.
+/// Returns the number of builtin calls that were replaced.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, ret))]
+pub fn fold_template(template: &mut TemplateDecl<'_>) -> usize {
+    let mut folded = fold_body(
+        &mut template.config,
+        &mut template.variables,
+        &mut template.resources,
+        &mut template.outputs,
+    );
+    for component in &mut template.components {
+        let c = &mut component.component;
+        folded += fold_body(
+            &mut c.inputs,
+            &mut c.variables,
+            &mut c.resources,
+            &mut c.outputs,
+        );
+    }
+    folded
+}
+
+fn fold_body(
+    config: &mut [ConfigEntry<'_>],
+    variables: &mut [VariableEntry<'_>],
+    resources: &mut [ResourceEntry<'_>],
+    outputs: &mut [OutputEntry<'_>],
+) -> usize {
+    let mut folder = Folder::default();
+    for entry in config {
+        for expr in [&mut entry.param.default, &mut entry.param.value]
+            .into_iter()
+            .flatten()
+        {
+            rewrite_expr(expr, &mut folder);
+        }
+    }
+    for entry in variables {
+        rewrite_expr(&mut entry.value, &mut folder);
+    }
+    for entry in resources {
+        rewrite_resource(&mut entry.resource, &mut folder);
+    }
+    for entry in outputs {
+        rewrite_expr(&mut entry.value, &mut folder);
+    }
+    folder.folded
+}
+
+/// Folds `expr` in place, children first. Returns the number of builtin
+/// calls that were replaced.
+pub fn fold_expr(expr: &mut Expr<'_>) -> usize {
+    let mut folder = Folder::default();
+    rewrite_expr(expr, &mut folder);
+    folder.folded
+}
+
+/// Replaces each foldable call with its result, counting the replacements.
+#[derive(Default)]
+struct Folder {
+    folded: usize,
+}
+
+impl ExprRewriter for Folder {
+    fn rewrite(&mut self, expr: &mut Expr<'_>, _scope: &[&str]) {
+        let chosen = match expr {
+            Expr::If(_, condition, then, otherwise) => match **condition {
+                Expr::Bool(_, true) => Some(std::mem::replace(
+                    &mut **then,
+                    Expr::Null(ExprMeta::default()),
+                )),
+                Expr::Bool(_, false) => Some(std::mem::replace(
+                    &mut **otherwise,
+                    Expr::Null(ExprMeta::default()),
+                )),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(chosen) = chosen {
+            *expr = chosen;
+            self.folded += 1;
+            return;
+        }
+        if let Some(value) = try_fold(expr) {
+            if let Some(literal) = value_to_literal(value, *expr.meta()) {
+                *expr = literal;
+                self.folded += 1;
+            }
+        }
+    }
+}
+
+/// Evaluates `expr` if it is a pure builtin call over literals and the call
+/// succeeds without diagnostics.
+fn try_fold<'src>(expr: &Expr<'src>) -> Option<Value<'src>> {
+    let mut diags = Diagnostics::new();
+    let result = match expr {
+        Expr::Interpolate(_, parts) => {
+            if parts.iter().any(|p| p.value.is_some()) {
+                return None;
+            }
+            Some(Value::String(Cow::Owned(
+                parts.iter().map(|p| p.text.as_ref()).collect(),
+            )))
+        }
+        Expr::Join(_, a, b) => builtins::eval_join(&literal(a)?, &literal(b)?, &mut diags),
+        Expr::Split(_, a, b) => builtins::eval_split(&literal(a)?, &literal(b)?, &mut diags),
+        Expr::Select(_, a, b) => builtins::eval_select(&literal(a)?, &literal(b)?, &mut diags),
+        Expr::Lookup(_, a, b, c) => {
+            let (map, key) = (literal(a)?, literal(b)?);
+            let default = match c {
+                Some(c) if builtins::lookup_misses(&map, &key) => Some(literal(c)?),
+                _ => None,
+            };
+            builtins::eval_lookup(&map, &key, default, &mut diags)
+        }
+        Expr::Substring(_, a, b, c) => {
+            builtins::eval_substring(&literal(a)?, &literal(b)?, &literal(c)?, &mut diags)
+        }
+        Expr::ToJson(_, inner) => builtins::eval_to_json(&literal(inner)?, &mut diags),
+        Expr::ParseJson(_, inner) => builtins::eval_parse_json(&literal(inner)?, &mut diags),
+        Expr::ParseYaml(_, inner) => builtins::eval_parse_yaml(&literal(inner)?, &mut diags),
+        Expr::ToBase64(_, inner) => builtins::eval_to_base64(&literal(inner)?, &mut diags),
+        Expr::FromBase64(_, inner) => builtins::eval_from_base64(&literal(inner)?, &mut diags),
+        Expr::Abs(_, inner) => builtins::eval_abs(&literal(inner)?, &mut diags),
+        Expr::Floor(_, inner) => builtins::eval_floor(&literal(inner)?, &mut diags),
+        Expr::Ceil(_, inner) => builtins::eval_ceil(&literal(inner)?, &mut diags),
+        Expr::Max(_, inner) => builtins::eval_max(&literal(inner)?, &mut diags),
+        Expr::Min(_, inner) => builtins::eval_min(&literal(inner)?, &mut diags),
+        Expr::FormatDuration(_, inner) => {
+            builtins::eval_format_duration(&literal(inner)?, &mut diags)
+        }
+        Expr::FormatBytes(_, inner) => builtins::eval_format_bytes(&literal(inner)?, &mut diags),
+        Expr::StringLen(_, inner) => builtins::eval_string_len(&literal(inner)?, &mut diags),
+        Expr::Sort(_, inner) => builtins::eval_sort(&literal(inner)?, &mut diags),
+        Expr::Unique(_, inner) => builtins::eval_unique(&literal(inner)?, &mut diags),
+        Expr::Length(_, inner) => builtins::eval_length(&literal(inner)?, &mut diags),
+        Expr::Coalesce(_, inner) => builtins::eval_coalesce(&literal(inner)?, &mut diags),
+        Expr::ToUpper(_, inner) => builtins::eval_to_upper(&literal(inner)?, &mut diags),
+        Expr::ToLower(_, inner) => builtins::eval_to_lower(&literal(inner)?, &mut diags),
+        Expr::Trim(_, inner) => builtins::eval_trim(&literal(inner)?, &mut diags),
+        Expr::Replace(_, a, b, c) => {
+            builtins::eval_replace(&literal(a)?, &literal(b)?, &literal(c)?, &mut diags)
+        }
+        Expr::StartsWith(_, a, b) => {
+            builtins::eval_starts_with(&literal(a)?, &literal(b)?, &mut diags)
+        }
+        Expr::Equals(_, a, b) => Some(builtins::eval_equals(&literal(a)?, &literal(b)?)),
+        Expr::Not(_, inner) => builtins::eval_not(&literal(inner)?, &mut diags),
+        Expr::And(_, inner) => builtins::eval_and(&literal(inner)?, &mut diags),
+        Expr::Or(_, inner) => builtins::eval_or(&literal(inner)?, &mut diags),
+        Expr::LessThan(_, a, b) => builtins::eval_less_than(&literal(a)?, &literal(b)?, &mut diags),
+        Expr::LessThanOrEqual(_, a, b) => {
+            builtins::eval_less_than_or_equal(&literal(a)?, &literal(b)?, &mut diags)
+        }
+        Expr::GreaterThan(_, a, b) => {
+            builtins::eval_greater_than(&literal(a)?, &literal(b)?, &mut diags)
+        }
+        Expr::GreaterThanOrEqual(_, a, b) => {
+            builtins::eval_greater_than_or_equal(&literal(a)?, &literal(b)?, &mut diags)
+        }
+        Expr::Format(_, a, b) => builtins::eval_format(&literal(a)?, &literal(b)?, &mut diags),
+        Expr::Assert(meta, a, b) => {
+            builtins::eval_assert(&literal(a)?, &literal(b)?, meta.span, &mut diags)
+        }
+        Expr::CidrSubnet(_, a, b, c) => {
+            builtins::eval_cidr_subnet(&literal(a)?, &literal(b)?, &literal(c)?, &mut diags)
+        }
+        Expr::CidrHost(_, a, b) => builtins::eval_cidr_host(&literal(a)?, &literal(b)?, &mut diags),
+        Expr::CidrNetmask(_, inner) => builtins::eval_cidr_netmask(&literal(inner)?, &mut diags),
+        Expr::Merge(_, inner) => builtins::eval_merge(&literal(inner)?, &mut diags),
+        Expr::JsonPatch(_, a, b) => {
+            builtins::eval_json_patch(&literal(a)?, &literal(b)?, &mut diags)
+        }
+        _ => return None,
+    };
+    if diags.iter().next().is_some() {
+        return None;
+    }
+    result
+}
+
+/// Returns the value of a literal expression tree.
+pub(crate) fn literal<'src>(expr: &Expr<'src>) -> Option<Value<'src>> {
+    match expr {
+        Expr::Null(_) => Some(Value::Null),
+        Expr::Bool(_, b) => Some(Value::Bool(*b)),
+        Expr::Number(_, n) => Some(Value::Number(*n)),
+        Expr::String(_, s) => Some(Value::String(s.clone())),
+        Expr::List(_, items) => items
+            .iter()
+            .map(literal)
+            .collect::<Option<_>>()
+            .map(Value::List),
+        Expr::Object(_, entries) => entries
+            .iter()
+            .map(|e| match literal(&e.key)? {
+                Value::String(k) => Some((k, literal(&e.value)?)),
+                _ => None,
+            })
+            .collect::<Option<_>>()
+            .map(Value::Object),
+        _ => None,
+    }
+}
+
+/// Converts a folded result back into a literal expression. Nested nodes are
+/// synthetic and carry no span; the root keeps the span of the folded call.
+pub(crate) fn value_to_literal(value: Value<'_>, meta: ExprMeta) -> Option<Expr<'_>> {
+    Some(match value {
+        Value::Null => Expr::Null(meta),
+        Value::Bool(b) => Expr::Bool(meta, b),
+        Value::Number(n) => Expr::Number(meta, n),
+        Value::String(s) => Expr::String(meta, s),
+        Value::List(items) => Expr::List(
+            meta,
+            items
+                .into_iter()
+                .map(|v| value_to_literal(v, ExprMeta::no_span()))
+                .collect::<Option<_>>()?,
+        ),
+        Value::Object(entries) => Expr::Object(
+            meta,
+            entries
+                .into_iter()
+                .map(|(k, v)| {
+                    Some(ObjectProperty {
+                        key: Box::new(Expr::String(ExprMeta::no_span(), k)),
+                        value: Box::new(value_to_literal(v, ExprMeta::no_span())?),
+                    })
+                })
+                .collect::<Option<_>>()?,
+        ),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::parse::parse_template;
+
+    fn folded_variable(source: &str, name: &str) -> (Expr<'static>, usize) {
+        let (template, diags) = parse_template(source, None);
+        assert!(!diags.has_errors(), "{}", diags);
+        let mut template = template;
+        let count = fold_template(&mut template);
+        let var = template
+            .variables
+            .iter()
+            .find(|v| v.key == name)
+            .unwrap()
+            .value
+            .clone();
+        (var, count)
+    }
+
+    fn string(s: &str) -> Expr<'static> {
+        Expr::String(ExprMeta::no_span(), Cow::Owned(s.to_string()))
+    }
+
+    fn strip(expr: Expr<'_>) -> Expr<'_> {
+        match expr {
+            Expr::String(_, s) => Expr::String(ExprMeta::no_span(), s),
+            Expr::Number(_, n) => Expr::Number(ExprMeta::no_span(), n),
+            other => other,
+        }
+    }
+
+    #[test]
+    fn test_folds_join_and_base64() {
+        let source = r#"
+variables:
+  joined:
+    fn::join: ["-", [a, b, c]]
+  encoded:
+    fn::toBase64: hello
+"#;
+        let (joined, count) = folded_variable(source, "joined");
+        assert_eq!(strip(joined), string("a-b-c"));
+        assert_eq!(count, 2);
+        let (encoded, _) = folded_variable(source, "encoded");
+        assert_eq!(strip(encoded), string("aGVsbG8="));
+    }
+
+    #[test]
+    fn test_folds_lookup_hit_with_symbolic_default() {
+        let source = r#"
+variables:
+  hit:
+    fn::lookup: [{ us-east-1: ami-east }, us-east-1, "${fallback}"]
+  miss:
+    fn::lookup: [{ us-east-1: ami-east }, eu-west-1, "${fallback}"]
+"#;
+        let (hit, _) = folded_variable(source, "hit");
+        assert_eq!(strip(hit), string("ami-east"));
+        let (miss, _) = folded_variable(source, "miss");
+        assert!(matches!(miss, Expr::Lookup(..)));
+    }
+
+    #[test]
+    fn test_folds_if_with_literal_condition() {
+        let source = r#"
+variables:
+  chosen:
+    fn::if: [false, "${other}", { fn::join: ["-", [a, b]] }]
+  kept:
+    fn::if: ["${flag}", a, b]
+"#;
+        let (chosen, count) = folded_variable(source, "chosen");
+        assert_eq!(strip(chosen), string("a-b"));
+        assert_eq!(count, 2);
+        let (kept, _) = folded_variable(source, "kept");
+        assert!(matches!(kept, Expr::If(_, _, _, _)));
+    }
+
+    #[test]
+    fn test_folds_nested_calls() {
+        let source = r#"
+variables:
+  second:
+    fn::select:
+      - 1
+      - fn::split: [",", "x,y,z"]
+  len:
+    fn::stringLen:
+      fn::join: ["", [ab, cd]]
+"#;
+        let (second, count) = folded_variable(source, "second");
+        assert_eq!(strip(second), string("y"));
+        assert_eq!(count, 4);
+        let (len, _) = folded_variable(source, "len");
+        assert_eq!(strip(len), Expr::Number(ExprMeta::no_span(), 4.0.into()));
+    }
+
+    #[test]
+    fn test_keeps_references_and_impure_calls() {
+        let source = r#"
+config:
+  name:
+    type: string
+variables:
+  withRef:
+    fn::join: ["-", [a, "${name}"]]
+  secret:
+    fn::secret: hidden
+  id:
+    fn::uuid: {}
+  bad:
+    fn::toBase64: 42
+"#;
+        let (template, _) = parse_template(source, None);
+        let original = template.clone();
+        let mut template = template;
+        assert_eq!(fold_template(&mut template), 0);
+        assert_eq!(template, original);
+    }
+
+    #[test]
+    fn test_folded_template_evaluates_identically() {
+        use crate::eval::evaluator::Evaluator;
+        use crate::eval::mock::MockCallback;
+        use std::collections::HashMap;
+
+        let source = r#"
+resources:
+  bucket:
+    type: test:Bucket
+    properties:
+      tags:
+        fn::toJSON:
+          names:
+            fn::split: [",", "a,b"]
+      encoded:
+        fn::toBase64:
+          fn::join: [":", [user, pass]]
+outputs:
+  encoded: ${bucket.encoded}
+  tags: ${bucket.tags}
+"#;
+        let run = |fold: bool| {
+            let (mut template, _) = parse_template(source, None);
+            if fold {
+                assert_eq!(fold_template(&mut template), 4);
+            }
+            let template = &template;
+            let eval = Evaluator::with_callback(
+                "test".into(),
+                "dev".into(),
+                "/tmp".into(),
+                false,
+                MockCallback::new(),
+            );
+            eval.evaluate_template(template, &HashMap::new(), &[]);
+            assert!(!eval.has_errors(), "{}", eval.diags_display());
+            eval.take_outputs()
+        };
+        assert_eq!(run(true), run(false));
+    }
+}
@@ -1,7 +1,12 @@
 use crate::ast::expr::Expr;
 use crate::ast::template::*;
 use crate::ast::visitor::{walk_expr, walk_resource, AllRefsCollector, DepCollector};
-use crate::diag::{self, Diagnostics};
+use crate::config_types::ConfigType;
+use crate::diag::{self, Diagnostic, Diagnostics};
+use crate::eval::{config, fold};
+use crate::packages::resolve_type_token;
+use crate::schema::{component_package, component_type_token, component_type_tokens};
+use crate::syntax::Span;
 use std::collections::{HashMap, HashSet};
 
 /// A node in the dependency graph. Replaces Go's `graphNode` interface.
@@ -62,64 +67,50 @@ fn topological_sort_inner<'a>(
     let node_count =
         template.config.len() + template.variables.len() + template.resources.len() + 1; // +1 for "pulumi"
     let mut names: HashMap<&str, &str> = HashMap::with_capacity(node_count);
+    let mut spans: HashMap<&str, Option<Span>> = HashMap::with_capacity(node_count);
 
     // Always insert "pulumi" as a node — Go always does this regardless of settings
     names.insert("pulumi", "pulumi");
 
     for entry in &template.config {
-        let key = entry.key.as_ref();
-        if key == "pulumi" {
-            diags.error(None, "\"pulumi\" is a reserved name", "");
-            continue;
-        }
-        if let Some(existing_kind) = names.insert(key, "config") {
-            diags.error(
-                None,
-                format!(
-                    "duplicate node name \"{}\": already defined as {}",
-                    key, existing_kind
-                ),
-                "",
-            );
-        }
+        declare(
+            &mut names,
+            &mut spans,
+            &entry.key,
+            "config",
+            entry.meta.span,
+            &mut diags,
+        );
     }
-
     for entry in &template.variables {
-        let key = entry.key.as_ref();
-        if key == "pulumi" {
-            diags.error(None, "\"pulumi\" is a reserved name", "");
-            continue;
-        }
-        if let Some(existing_kind) = names.insert(key, "variable") {
-            diags.error(
-                None,
-                format!(
-                    "duplicate node name \"{}\": already defined as {}",
-                    key, existing_kind
-                ),
-                "",
-            );
-        }
+        declare(
+            &mut names,
+            &mut spans,
+            &entry.key,
+            "variable",
+            entry.meta.span,
+            &mut diags,
+        );
     }
-
     for entry in &template.resources {
-        let key = entry.logical_name.as_ref();
-        if key == "pulumi" {
-            diags.error(None, "\"pulumi\" is a reserved name", "");
-            continue;
-        }
-        if let Some(existing_kind) = names.insert(key, "resource") {
-            diags.error(
-                None,
-                format!(
-                    "duplicate node name \"{}\": already defined as {}",
-                    key, existing_kind
-                ),
-                "",
-            );
-        }
+        declare(
+            &mut names,
+            &mut spans,
+            &entry.logical_name,
+            "resource",
+            entry.meta.span,
+            &mut diags,
+        );
     }
 
+    // Component bodies are evaluated as templates of their own, with the
+    // inputs bound as config, so check their names up front as well
+    for component in &template.components {
+        check_component_names(component, &mut diags);
+        check_component_interface(component, &mut diags);
+    }
+    check_component_cycles(template, &mut diags);
+
     if diags.has_errors() {
         return (Vec::new(), HashMap::new(), diags);
     }
@@ -136,9 +127,13 @@ fn topological_sort_inner<'a>(
         known_names: &names,
     };
 
-    // Config entries have no dependencies (they come from external config)
+    // Config entries depend on the config their default references
     for entry in &template.config {
-        deps.entry(entry.key.as_ref()).or_default();
+        let mut node_deps = HashSet::new();
+        if let Some(ref default) = entry.param.default {
+            walk_expr(default, &dep_collector, &mut node_deps);
+        }
+        deps.insert(entry.key.as_ref(), node_deps);
     }
 
     // Variables depend on whatever their expression references
@@ -199,6 +194,250 @@ fn topological_sort_inner<'a>(
     (order, deps, diags)
 }
 
+/// Explains why `key` cannot be declared, if it is reserved: `pulumi` is
+/// the injected project/stack variable and `fn::` keys are builtin calls.
+fn reserved_name(key: &str) -> Option<&'static str> {
+    if key == "pulumi" {
+        Some("the built-in \"pulumi\" variable (pulumi.project, pulumi.stack, ...)")
+    } else if key.get(..4).is_some_and(|p| p.eq_ignore_ascii_case("fn::")) {
+        Some("a builtin function")
+    } else {
+        None
+    }
+}
+
+/// Registers a config, variable or resource name, reporting reserved and
+/// duplicate names. `spans` holds the span of each name's first
+/// declaration, which a duplicate's error refers to.
+fn declare<'a>(
+    names: &mut HashMap<&'a str, &'a str>,
+    spans: &mut HashMap<&'a str, Option<Span>>,
+    key: &'a str,
+    kind: &'static str,
+    span: Option<Span>,
+    diags: &mut Diagnostics,
+) {
+    if let Some(reserved) = reserved_name(key) {
+        diags.error(
+            span,
+            format!("\"{}\" is a reserved name", key),
+            format!("{} \"{}\" would shadow {}", kind, key, reserved),
+        );
+        return;
+    }
+    match names.get(key) {
+        Some(existing_kind) => diags.add(
+            Diagnostic::error(
+                span,
+                format!(
+                    "duplicate node name \"{}\": already defined as {}",
+                    key, existing_kind
+                ),
+                "",
+            )
+            .with_related(spans.get(key).copied().flatten()),
+        ),
+        None => {
+            names.insert(key, kind);
+            spans.insert(key, span);
+        }
+    }
+}
+
+/// Reports component inputs with reserved names, and variables or resources
+/// of the component body that shadow one of its inputs.
+pub fn check_component_names(component: &ComponentDecl<'_>, diags: &mut Diagnostics) {
+    let body = &component.component;
+    let mut inputs: HashMap<&str, &ConfigEntry<'_>> = HashMap::with_capacity(body.inputs.len());
+    for input in &body.inputs {
+        if let Some(reserved) = reserved_name(&input.key) {
+            diags.error(
+                input.meta.span,
+                format!("\"{}\" is a reserved name", input.key),
+                format!(
+                    "input \"{}\" of component \"{}\" would shadow {}",
+                    input.key, component.key, reserved
+                ),
+            );
+        }
+        inputs.insert(&input.key, input);
+    }
+
+    let body_names = body
+        .variables
+        .iter()
+        .map(|v| (v.key.as_ref(), "variable", v.meta.span))
+        .chain(
+            body.resources
+                .iter()
+                .map(|r| (r.logical_name.as_ref(), "resource", r.meta.span)),
+        );
+    for (key, kind, span) in body_names {
+        if let Some(reserved) = reserved_name(key) {
+            diags.error(
+                span,
+                format!("\"{}\" is a reserved name", key),
+                format!(
+                    "{} \"{}\" of component \"{}\" would shadow {}",
+                    kind, key, component.key, reserved
+                ),
+            );
+        } else if let Some(input) = inputs.get(key) {
+            diags.add(
+                Diagnostic::error(
+                    span,
+                    format!(
+                        "{} \"{}\" shadows an input of component \"{}\"",
+                        kind, key, component.key
+                    ),
+                    format!(
+                        "rename the {} or the input; within the component body \"${{{}}}\" would be ambiguous",
+                        kind, key
+                    ),
+                )
+                .with_related(input.meta.span),
+            );
+        }
+    }
+}
+
+/// Reports component inputs with an unknown type or a literal default that
+/// does not match the declared type, and outputs that reference a name the
+/// component body does not define.
+pub fn check_component_interface(component: &ComponentDecl<'_>, diags: &mut Diagnostics) {
+    let body = &component.component;
+    for input in &body.inputs {
+        let Some(type_) = input.param.type_.as_deref() else {
+            continue;
+        };
+        let Some(declared) = ConfigType::parse(type_) else {
+            diags.error(
+                input.meta.span,
+                format!(
+                    "input \"{}\" of component \"{}\" has unknown type \"{}\"",
+                    input.key, component.key, type_
+                ),
+                "expected String, Number, Int, Boolean, Object, Duration, Bytes, or a List<...> of a primitive type",
+            );
+            continue;
+        };
+        let default = input.param.default.as_ref().and_then(fold::literal);
+        if let Some(default) = default {
+            if !config::value_matches_type(&default, &declared) {
+                diags.error(
+                    input.meta.span,
+                    format!(
+                        "the default of input \"{}\" of component \"{}\" does not match its type {}",
+                        input.key, component.key, declared
+                    ),
+                    format!("got {}", default.type_name()),
+                );
+            }
+        }
+    }
+
+    let defined: HashSet<&str> = body
+        .inputs
+        .iter()
+        .map(|i| i.key.as_ref())
+        .chain(body.variables.iter().map(|v| v.key.as_ref()))
+        .chain(body.resources.iter().map(|r| r.logical_name.as_ref()))
+        .collect();
+    for output in &body.outputs {
+        let mut refs = HashSet::new();
+        collect_all_expr_refs(&output.value, &mut refs);
+        let mut missing: Vec<&str> = refs
+            .into_iter()
+            .filter(|name| *name != "pulumi" && !defined.contains(name))
+            .collect();
+        missing.sort_unstable();
+        for name in missing {
+            diags.error(
+                output.value.meta().span,
+                format!(
+                    "output \"{}\" of component \"{}\" references \"{}\", which the component does not define",
+                    output.key, component.key, name
+                ),
+                "component outputs may only reference the component's inputs, variables and resources",
+            );
+        }
+    }
+}
+
+/// Reports components that instantiate themselves, directly or through
+/// other components of the template. A component body instantiates another
+/// component by declaring a resource of its type, `pkg:index:Name` or
+/// `pkg:Name`.
+pub fn check_component_cycles(template: &TemplateDecl<'_>, diags: &mut Diagnostics) {
+    let pkg = component_package(template);
+    let tokens: HashMap<String, &str> = template
+        .components
+        .iter()
+        .map(|c| (component_type_token(pkg, &c.key), c.key.as_ref()))
+        .collect();
+    let local_components = component_type_tokens(template);
+    let instantiates: HashMap<&str, Vec<&str>> = template
+        .components
+        .iter()
+        .map(|c| {
+            let mut used: Vec<&str> = c
+                .component
+                .resources
+                .iter()
+                .filter_map(|r| {
+                    let token = resolve_type_token(&r.resource.type_, &local_components, None);
+                    tokens.get(&token.token).copied()
+                })
+                .collect();
+            used.sort_unstable();
+            used.dedup();
+            (c.key.as_ref(), used)
+        })
+        .collect();
+
+    // Depth-first search from each component in declaration order; each
+    // cycle is reported once, at the component the search reaches first
+    let mut done: HashSet<&str> = HashSet::new();
+    for component in &template.components {
+        let mut path = Vec::new();
+        find_component_cycle(
+            component.key.as_ref(),
+            &instantiates,
+            &mut path,
+            &mut done,
+            diags,
+        );
+    }
+}
+
+fn find_component_cycle<'a>(
+    name: &'a str,
+    instantiates: &HashMap<&'a str, Vec<&'a str>>,
+    path: &mut Vec<&'a str>,
+    done: &mut HashSet<&'a str>,
+    diags: &mut Diagnostics,
+) {
+    if done.contains(name) {
+        return;
+    }
+    if let Some(start) = path.iter().position(|&n| n == name) {
+        let mut cycle = path[start..].to_vec();
+        cycle.push(name);
+        diags.error(
+            None,
+            format!("component \"{}\" instantiates itself", name),
+            format!("instantiation cycle: {}", cycle.join(" -> ")),
+        );
+        return;
+    }
+    path.push(name);
+    for &used in instantiates.get(name).into_iter().flatten() {
+        find_component_cycle(used, instantiates, path, done, diags);
+    }
+    path.pop();
+    done.insert(name);
+}
+
 /// Performs a topological sort of all nodes in a template.
 ///
 /// Returns the nodes in dependency order (dependencies come first).
@@ -223,6 +462,7 @@ pub fn topological_sort_with_sources<'a>(
 /// Performs a topological sort and returns the dependency graph alongside the ordering.
 ///
 /// This variant is used by the parallel evaluator to compute topological levels.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
 pub fn topological_sort_with_deps<'a>(
     template: &'a TemplateDecl<'a>,
     source_map: Option<&HashMap<String, String>>,
@@ -250,6 +490,7 @@ pub fn topological_sort_with_deps<'a>(
 ///
 /// This enables parallel evaluation: all nodes at the same level can be
 /// evaluated concurrently since they have no inter-dependencies.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(nodes = sorted.len())))]
 pub fn topological_levels(
     sorted: &[String],
     deps: &HashMap<String, HashSet<String>>,
@@ -288,6 +529,49 @@ pub fn topological_levels(
     result
 }
 
+/// Returns the nodes that transitively depend on `name`, sorted.
+///
+/// These are the entries re-evaluated when `name` changes. `deps` maps each
+/// node to the nodes it depends on, as in [`SortResultWithDeps::deps`].
+pub fn descendants(deps: &HashMap<String, HashSet<String>>, name: &str) -> Vec<String> {
+    let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (node, node_deps) in deps {
+        for dep in node_deps {
+            dependents
+                .entry(dep.as_str())
+                .or_default()
+                .push(node.as_str());
+        }
+    }
+    reachable(&dependents, name)
+}
+
+/// Returns the nodes `name` transitively depends on, sorted.
+pub fn ancestors(deps: &HashMap<String, HashSet<String>>, name: &str) -> Vec<String> {
+    let edges: HashMap<&str, Vec<&str>> = deps
+        .iter()
+        .map(|(node, d)| (node.as_str(), d.iter().map(String::as_str).collect()))
+        .collect();
+    reachable(&edges, name)
+}
+
+/// Collects the nodes reachable from `start` along `edges`, excluding
+/// `start` itself.
+fn reachable(edges: &HashMap<&str, Vec<&str>>, start: &str) -> Vec<String> {
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut stack = vec![start];
+    while let Some(node) = stack.pop() {
+        for &next in edges.get(node).into_iter().flatten() {
+            if next != start && seen.insert(next) {
+                stack.push(next);
+            }
+        }
+    }
+    let mut result: Vec<String> = seen.into_iter().map(str::to_string).collect();
+    result.sort();
+    result
+}
+
 /// Validates that all `${ref}` references in the template refer to defined names.
 ///
 /// Scans variables, resources, and outputs for references. Any reference whose
@@ -301,6 +585,37 @@ fn validate_references(
 ) {
     let known_names: Vec<String> = names.keys().map(|k| k.to_string()).collect();
 
+    // Check config defaults: they are evaluated before anything is
+    // registered, so they may only reference other config and `pulumi`
+    for entry in &template.config {
+        let Some(ref default) = entry.param.default else {
+            continue;
+        };
+        let mut refs = HashSet::new();
+        collect_all_expr_refs(default, &mut refs);
+        for ref_name in refs {
+            match names.get(ref_name) {
+                Some(&kind) if kind != "config" && kind != "pulumi" => diags.error(
+                    None,
+                    format!(
+                        "the default of config '{}' references {} '{}'; defaults may only reference other config and pulumi variables",
+                        entry.key, kind, ref_name
+                    ),
+                    "",
+                ),
+                _ => check_ref(
+                    ref_name,
+                    entry.key.as_ref(),
+                    "config",
+                    names,
+                    &known_names,
+                    source_map,
+                    diags,
+                ),
+            }
+        }
+    }
+
     // Check variables
     for entry in &template.variables {
         let mut refs = HashSet::new();
@@ -477,6 +792,42 @@ pub fn collect_expr_deps<'a>(
     walk_expr(expr, &DepCollector { known_names }, deps);
 }
 
+/// Maps each resource local to the known names it depends on, directly or
+/// through earlier locals.
+pub fn collect_local_deps<'a>(
+    locals: &'a [VariableEntry<'a>],
+    known_names: &HashMap<&str, &str>,
+) -> HashMap<&'a str, HashSet<&'a str>> {
+    let mut local_deps: HashMap<&'a str, HashSet<&'a str>> = HashMap::new();
+    for local in locals {
+        let mut deps = HashSet::new();
+        collect_expr_deps(&local.value, known_names, &mut deps);
+        expand_local_deps(&local.value, &local_deps, &mut deps);
+        local_deps.insert(local.key.as_ref(), deps);
+    }
+    local_deps
+}
+
+/// Replaces references to resource locals in `deps` with the locals' own
+/// dependencies.
+pub fn expand_local_deps<'a>(
+    expr: &'a Expr<'a>,
+    local_deps: &HashMap<&'a str, HashSet<&'a str>>,
+    deps: &mut HashSet<&'a str>,
+) {
+    if local_deps.is_empty() {
+        return;
+    }
+    let mut refs = HashSet::new();
+    collect_all_expr_refs(expr, &mut refs);
+    for name in refs {
+        if let Some(local) = local_deps.get(name) {
+            deps.remove(name);
+            deps.extend(local.iter().copied());
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -576,6 +927,15 @@ resources:
         let (template, _) = parse_template(source, None);
         let (_, diags) = topological_sort(&template);
         assert!(diags.has_errors());
+        let diag = diags.iter().next().unwrap();
+        assert_eq!(
+            diag.span.unwrap().start as usize,
+            source.rfind("dup:").unwrap()
+        );
+        assert_eq!(
+            diag.related.unwrap().start as usize,
+            source.find("dup:").unwrap()
+        );
     }
 
     #[test]
@@ -592,6 +952,176 @@ resources:
         assert!(diags.has_errors());
     }
 
+    #[test]
+    fn test_reserved_names_are_reported() {
+        let source = r#"
+name: test
+runtime: yaml
+config:
+  pulumi:
+    type: string
+variables:
+  fn::join: hello
+"#;
+        let (template, _) = parse_template(source, None);
+        let (_, diags) = topological_sort(&template);
+        let messages: Vec<String> = diags.iter().map(|d| d.to_string()).collect();
+        assert_eq!(messages.len(), 2, "{:?}", messages);
+        assert!(messages[0].contains("\"pulumi\" is a reserved name"));
+        assert!(messages[0].contains("config \"pulumi\" would shadow the built-in"));
+        assert!(messages[1].contains("variable \"fn::join\" would shadow a builtin function"));
+    }
+
+    #[test]
+    fn test_component_body_shadowing_input() {
+        let source = r#"
+name: test
+runtime: yaml
+components:
+  App:
+    inputs:
+      prefix:
+        type: string
+      pulumi:
+        type: string
+    variables:
+      prefix: ${prefix}-app
+    resources:
+      bucket:
+        type: test:Bucket
+"#;
+        let (template, _) = parse_template(source, None);
+        let (_, diags) = topological_sort(&template);
+        let messages: Vec<String> = diags.iter().map(|d| d.to_string()).collect();
+        assert_eq!(messages.len(), 2, "{:?}", messages);
+        assert!(messages[0].contains("input \"pulumi\" of component \"App\""));
+        assert!(messages[1].contains("variable \"prefix\" shadows an input of component \"App\""));
+        let shadow = diags.iter().nth(1).unwrap();
+        assert_eq!(
+            shadow.span.unwrap().start as usize,
+            source.find("prefix: ${").unwrap()
+        );
+        assert_eq!(
+            shadow.related.unwrap().start as usize,
+            source.find("prefix:").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_component_interface_validation() {
+        let source = r#"
+name: test
+runtime: yaml
+components:
+  App:
+    inputs:
+      size:
+        type: integer
+        default: 1.5
+      tags:
+        type: List<String>
+        default: [a, b]
+      timeout:
+        type: duration
+        default: 30s
+      mode:
+        type: enum
+    variables:
+      name: app-${size}
+    resources:
+      bucket:
+        type: test:Bucket
+    outputs:
+      bucketId: ${bucket.id}
+      project: ${pulumi.project}
+      url: ${site.url}/${nme}
+"#;
+        let (template, _) = parse_template(source, None);
+        let mut diags = Diagnostics::new();
+        check_component_interface(&template.components[0], &mut diags);
+        let messages: Vec<String> = diags.iter().map(|d| d.to_string()).collect();
+        assert_eq!(messages.len(), 4, "{:?}", messages);
+        assert!(messages[0].contains("the default of input \"size\" of component \"App\" does not match its type Int; got number"));
+        assert!(
+            messages[1].contains("input \"mode\" of component \"App\" has unknown type \"enum\"")
+        );
+        assert!(messages[2].contains("output \"url\" of component \"App\" references \"nme\""));
+        assert!(messages[3].contains("references \"site\""));
+        let url = diags.iter().nth(2).unwrap().span.unwrap();
+        assert_eq!(
+            &source[url.start as usize..url.end as usize],
+            "${site.url}/${nme}"
+        );
+
+        // The checks run with the rest of the template validation
+        let (_, diags) = topological_sort(&template);
+        assert_eq!(diags.iter().count(), 4);
+    }
+
+    #[test]
+    fn test_component_instantiation_cycles() {
+        let source = r#"
+name: shapes
+runtime: yaml
+components:
+  Outer:
+    resources:
+      middle:
+        type: shapes:index:Middle
+      leaf:
+        type: shapes:Leaf
+  Middle:
+    resources:
+      inner:
+        type: shapes:Inner
+  Inner:
+    resources:
+      back:
+        type: shapes:index:Middle
+  Leaf:
+    resources:
+      bucket:
+        type: aws:s3:Bucket
+  Mirror:
+    resources:
+      self:
+        type: shapes:Mirror
+"#;
+        let (template, _) = parse_template(source, None);
+        let mut diags = Diagnostics::new();
+        check_component_cycles(&template, &mut diags);
+        let messages: Vec<String> = diags.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "error: component \"Middle\" instantiates itself; instantiation cycle: Middle -> Inner -> Middle",
+                "error: component \"Mirror\" instantiates itself; instantiation cycle: Mirror -> Mirror",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_component_instantiating_other_components() {
+        let source = r#"
+name: shapes
+runtime: yaml
+components:
+  Site:
+    resources:
+      assets:
+        type: shapes:Bucket
+      logs:
+        type: shapes:index:Bucket
+  Bucket:
+    resources:
+      bucket:
+        type: aws:s3:Bucket
+"#;
+        let (template, _) = parse_template(source, None);
+        let (_, diags) = topological_sort(&template);
+        assert!(!diags.has_errors(), "{}", diags);
+    }
+
     #[test]
     fn test_config_first() {
         let source = r#"
@@ -615,6 +1145,77 @@ resources:
         assert!(config_pos < a_pos);
     }
 
+    #[test]
+    fn test_descendants_and_ancestors() {
+        let source = r#"
+name: test
+runtime: yaml
+config:
+  region:
+    default: us-east-1
+variables:
+  prefix: app-${region}
+resources:
+  bucket:
+    type: aws:s3:Bucket
+    properties:
+      bucket: ${prefix}-assets
+  policy:
+    type: aws:s3:BucketPolicy
+    properties:
+      bucket: ${bucket.id}
+  queue:
+    type: aws:sqs:Queue
+"#;
+        let (template, _) = parse_template(source, None);
+        let (result, diags) = topological_sort_with_deps(&template, None);
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        assert_eq!(
+            descendants(&result.deps, "region"),
+            vec!["bucket", "policy", "prefix"]
+        );
+        assert_eq!(descendants(&result.deps, "policy"), Vec::<String>::new());
+        assert_eq!(
+            ancestors(&result.deps, "policy"),
+            vec!["bucket", "prefix", "region"]
+        );
+        assert_eq!(ancestors(&result.deps, "queue"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_config_default_dependencies() {
+        let source = r#"
+name: test
+runtime: yaml
+config:
+  a:
+    default: ${b}-suffix
+  b:
+    default: ${pulumi.stack}
+"#;
+        let (template, _) = parse_template(source, None);
+        let (order, diags) = topological_sort(&template);
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        let a_pos = order.iter().position(|x| x == "a").unwrap();
+        let b_pos = order.iter().position(|x| x == "b").unwrap();
+        assert!(b_pos < a_pos);
+
+        let source = r#"
+name: test
+runtime: yaml
+config:
+  a:
+    default: ${prefix}
+variables:
+  prefix: hello
+"#;
+        let (template, _) = parse_template(source, None);
+        let (_, diags) = topological_sort(&template);
+        assert!(diags.to_string().contains(
+            "the default of config 'a' references variable 'prefix'; defaults may only reference other config and pulumi variables"
+        ), "{}", diags);
+    }
+
     #[test]
     fn test_default_provider_ordering() {
         let source = r#"
@@ -669,6 +1270,85 @@ resources:
         );
     }
 
+    #[test]
+    fn test_resource_locals_are_scoped() {
+        let source = r#"
+name: test
+runtime: yaml
+resources:
+  a:
+    type: test:Resource
+  b:
+    type: test:Resource
+    locals:
+      target: ${a.id}
+    properties:
+      dep: ${target}
+"#;
+        let (template, _) = parse_template(source, None);
+        let (order, diags) = topological_sort(&template);
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        let a_pos = order.iter().position(|x| x == "a").unwrap();
+        let b_pos = order.iter().position(|x| x == "b").unwrap();
+        assert!(a_pos < b_pos, "a should come before b");
+
+        // Another resource cannot see b's locals.
+        let source = format!(
+            "{}  c:\n    type: test:Resource\n    properties:\n      dep: ${{target}}\n",
+            source
+        );
+        let (template, _) = parse_template(&source, None);
+        let (_, diags) = topological_sort(&template);
+        let errors: Vec<String> = diags
+            .iter()
+            .filter(|d| d.is_error())
+            .map(|d| d.summary.clone())
+            .collect();
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(
+            errors[0].contains("target") && errors[0].contains("not defined"),
+            "{:?}",
+            errors
+        );
+    }
+
+    #[test]
+    fn test_resource_range_dependencies() {
+        let source = r#"
+name: test
+runtime: yaml
+resources:
+  subnets:
+    type: test:Subnet
+    range: ${vpc.zones}
+    properties:
+      zone: ${range.value}
+  vpc:
+    type: test:Vpc
+  routes:
+    type: test:Route
+    count: 2
+    properties:
+      subnet: ${subnets[range.key].id}
+"#;
+        let (template, _) = parse_template(source, None);
+        let (order, diags) = topological_sort(&template);
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        let pos = |name: &str| order.iter().position(|x| x == name).unwrap();
+        assert!(pos("vpc") < pos("subnets"));
+        assert!(pos("subnets") < pos("routes"));
+
+        // `range` is only bound inside resources with count or range.
+        let source = format!(
+            "{}  other:\n    type: test:Resource\n    properties:\n      zone: ${{range.value}}\n",
+            source
+        );
+        let (template, _) = parse_template(&source, None);
+        let (_, diags) = topological_sort(&template);
+        assert!(diags.has_errors());
+        assert!(diags.to_string().contains("range"), "{}", diags);
+    }
+
     #[test]
     fn test_missing_reference_with_suggestion() {
         let source = r#"
@@ -0,0 +1,59 @@
+//! Host-provided hooks over registered resources.
+//!
+//! Embedders can transform the outputs the engine returns for a resource
+//! before they are stored in its [`ResourceState`] and become visible to
+//! `${resource.property}` references, e.g. to normalize property casing or
+//! add computed fields their own output model expects.
+//!
+//! Hooks run in the order they were added, for every registered or read
+//! resource (including `get:` resources and stack references), after secret
+//! outputs have been marked and before outputs are charged against the
+//! evaluation memory limit.
+//!
+//! [`ResourceState`]: crate::eval::resource::ResourceState
+
+use std::collections::HashMap;
+
+use crate::eval::value::Value;
+
+/// The resource a hook is called for.
+#[derive(Debug, Clone, Copy)]
+pub struct RegisteredResource<'a> {
+    /// The logical name in the template.
+    pub logical_name: &'a str,
+    /// The type token the resource was registered with.
+    pub type_token: &'a str,
+    pub urn: &'a str,
+    /// Empty for component resources.
+    pub id: &'a str,
+    pub is_provider: bool,
+    pub is_component: bool,
+}
+
+/// Transforms the outputs of a resource after registration.
+///
+/// Returning an error fails the resource: the message is reported as an
+/// evaluation error and the resource is not stored, so nothing downstream of
+/// it is evaluated.
+pub trait ResourceOutputHook: Send + Sync {
+    fn transform_outputs(
+        &self,
+        resource: &RegisteredResource<'_>,
+        outputs: &mut HashMap<String, Value<'static>>,
+    ) -> Result<(), String>;
+}
+
+impl<F> ResourceOutputHook for F
+where
+    F: Fn(&RegisteredResource<'_>, &mut HashMap<String, Value<'static>>) -> Result<(), String>
+        + Send
+        + Sync,
+{
+    fn transform_outputs(
+        &self,
+        resource: &RegisteredResource<'_>,
+        outputs: &mut HashMap<String, Value<'static>>,
+    ) -> Result<(), String> {
+        self(resource, outputs)
+    }
+}
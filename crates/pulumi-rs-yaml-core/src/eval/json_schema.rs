@@ -0,0 +1,415 @@
+//! A JSON Schema validator for `fn::validateJson`.
+//!
+//! Supports the structural keywords of draft 7 / 2020-12 that matter for
+//! checking configuration documents: `type`, `enum`, `const`, `properties`,
+//! `required`, `additionalProperties`, `minProperties`, `maxProperties`,
+//! `items`, `minItems`, `maxItems`, `uniqueItems`, `minLength`, `maxLength`,
+//! `pattern`, `minimum`, `maximum`, `exclusiveMinimum`, `exclusiveMaximum`,
+//! `multipleOf`, `allOf`, `anyOf`, `oneOf`, `not`, and `$ref` to a JSON
+//! pointer within the same schema (e.g. `#/$defs/user`). Other keywords,
+//! such as `format`, are ignored.
+//!
+//! Messages never include the offending value, which may be a secret.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, LazyLock, Mutex};
+
+use serde_json::Value as Json;
+
+/// Maximum number of nested `$ref`s followed, to stop recursive schemas
+/// from looping.
+const MAX_REF_DEPTH: usize = 64;
+
+/// A violation of the schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaError {
+    /// JSON pointer to the offending value; empty for the root.
+    pub pointer: String,
+    pub message: String,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.pointer.is_empty() {
+            write!(f, "(root): {}", self.message)
+        } else {
+            write!(f, "{}: {}", self.pointer, self.message)
+        }
+    }
+}
+
+/// Validates `instance` against `schema`, returning every violation found.
+pub fn validate(schema: &Json, instance: &Json) -> Vec<SchemaError> {
+    let mut errors = Vec::new();
+    Validator { root: schema }.check(schema, instance, "", 0, &mut errors);
+    errors
+}
+
+struct Validator<'s> {
+    root: &'s Json,
+}
+
+impl Validator<'_> {
+    fn check(
+        &self,
+        schema: &Json,
+        instance: &Json,
+        pointer: &str,
+        depth: usize,
+        errors: &mut Vec<SchemaError>,
+    ) {
+        let mut fail = |message: String| {
+            errors.push(SchemaError {
+                pointer: pointer.to_string(),
+                message,
+            })
+        };
+        let schema = match schema {
+            Json::Bool(true) => return,
+            Json::Bool(false) => return fail("no value is allowed here".to_string()),
+            Json::Object(schema) => schema,
+            _ => return fail("the schema must be an object or a boolean".to_string()),
+        };
+
+        if let Some(reference) = schema.get("$ref").and_then(Json::as_str) {
+            let target = reference
+                .strip_prefix('#')
+                .and_then(|fragment| self.root.pointer(fragment));
+            match target {
+                _ if depth >= MAX_REF_DEPTH => fail(format!("$ref {} nests too deeply", reference)),
+                Some(target) => self.check(target, instance, pointer, depth + 1, errors),
+                None => fail(format!("cannot resolve $ref {}", reference)),
+            }
+            return;
+        }
+
+        if let Some(expected) = schema.get("type") {
+            let allowed: Vec<&str> = match expected {
+                Json::String(t) => vec![t.as_str()],
+                Json::Array(ts) => ts.iter().filter_map(Json::as_str).collect(),
+                _ => Vec::new(),
+            };
+            if !allowed.is_empty() && !allowed.iter().any(|t| has_type(instance, t)) {
+                return fail(format!(
+                    "expected {}, found {}",
+                    allowed.join(" or "),
+                    type_name(instance)
+                ));
+            }
+        }
+        if let Some(options) = schema.get("enum").and_then(Json::as_array) {
+            if !options.contains(instance) {
+                let options: Vec<String> = options.iter().map(Json::to_string).collect();
+                fail(format!("expected one of {}", options.join(", ")));
+            }
+        }
+        if let Some(expected) = schema.get("const") {
+            if instance != expected {
+                fail(format!("expected {}", expected));
+            }
+        }
+
+        match instance {
+            Json::Object(map) => {
+                if let Some(required) = schema.get("required").and_then(Json::as_array) {
+                    for key in required.iter().filter_map(Json::as_str) {
+                        if !map.contains_key(key) {
+                            fail(format!("missing required property {:?}", key));
+                        }
+                    }
+                }
+                if let Some(min) = schema.get("minProperties").and_then(Json::as_u64) {
+                    if (map.len() as u64) < min {
+                        fail(format!(
+                            "expected at least {} properties, found {}",
+                            min,
+                            map.len()
+                        ));
+                    }
+                }
+                if let Some(max) = schema.get("maxProperties").and_then(Json::as_u64) {
+                    if map.len() as u64 > max {
+                        fail(format!(
+                            "expected at most {} properties, found {}",
+                            max,
+                            map.len()
+                        ));
+                    }
+                }
+                let properties = schema.get("properties").and_then(Json::as_object);
+                for (key, value) in map {
+                    let child = format!("{}/{}", pointer, escape(key));
+                    match (
+                        properties.and_then(|p| p.get(key)),
+                        schema.get("additionalProperties"),
+                    ) {
+                        (Some(sub), _) => self.check(sub, value, &child, depth, errors),
+                        (None, Some(Json::Bool(false))) => errors.push(SchemaError {
+                            pointer: child,
+                            message: "additional properties are not allowed".to_string(),
+                        }),
+                        (None, Some(sub)) => self.check(sub, value, &child, depth, errors),
+                        (None, None) => {}
+                    }
+                }
+            }
+            Json::Array(items) => {
+                if let Some(min) = schema.get("minItems").and_then(Json::as_u64) {
+                    if (items.len() as u64) < min {
+                        fail(format!(
+                            "expected at least {} items, found {}",
+                            min,
+                            items.len()
+                        ));
+                    }
+                }
+                if let Some(max) = schema.get("maxItems").and_then(Json::as_u64) {
+                    if items.len() as u64 > max {
+                        fail(format!(
+                            "expected at most {} items, found {}",
+                            max,
+                            items.len()
+                        ));
+                    }
+                }
+                if schema.get("uniqueItems") == Some(&Json::Bool(true)) {
+                    let duplicate = items
+                        .iter()
+                        .enumerate()
+                        .find(|(i, item)| items[..*i].contains(item));
+                    if let Some((i, _)) = duplicate {
+                        fail(format!("item {} is a duplicate", i));
+                    }
+                }
+                if let Some(sub) = schema.get("items") {
+                    for (i, item) in items.iter().enumerate() {
+                        self.check(sub, item, &format!("{}/{}", pointer, i), depth, errors);
+                    }
+                }
+            }
+            Json::String(s) => {
+                let len = s.chars().count() as u64;
+                if let Some(min) = schema.get("minLength").and_then(Json::as_u64) {
+                    if len < min {
+                        fail(format!(
+                            "expected at least {} characters, found {}",
+                            min, len
+                        ));
+                    }
+                }
+                if let Some(max) = schema.get("maxLength").and_then(Json::as_u64) {
+                    if len > max {
+                        fail(format!(
+                            "expected at most {} characters, found {}",
+                            max, len
+                        ));
+                    }
+                }
+                if let Some(pattern) = schema.get("pattern").and_then(Json::as_str) {
+                    match compiled_pattern(pattern) {
+                        Ok(re) if !re.is_match(s) => {
+                            fail(format!("does not match pattern {:?}", pattern))
+                        }
+                        Ok(_) => {}
+                        Err(e) => fail(format!("invalid pattern {:?}: {}", pattern, e)),
+                    }
+                }
+            }
+            Json::Number(n) => {
+                let n = n.as_f64().unwrap_or(f64::NAN);
+                let bound = |key: &str| schema.get(key).and_then(Json::as_f64);
+                if let Some(min) = bound("minimum") {
+                    if n < min {
+                        fail(format!("must be at least {}", min));
+                    }
+                }
+                if let Some(max) = bound("maximum") {
+                    if n > max {
+                        fail(format!("must be at most {}", max));
+                    }
+                }
+                if let Some(min) = bound("exclusiveMinimum") {
+                    if n <= min {
+                        fail(format!("must be greater than {}", min));
+                    }
+                }
+                if let Some(max) = bound("exclusiveMaximum") {
+                    if n >= max {
+                        fail(format!("must be less than {}", max));
+                    }
+                }
+                if let Some(divisor) = bound("multipleOf").filter(|d| *d > 0.0) {
+                    if (n / divisor).fract() != 0.0 {
+                        fail(format!("must be a multiple of {}", divisor));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        if let Some(all) = schema.get("allOf").and_then(Json::as_array) {
+            for sub in all {
+                self.check(sub, instance, pointer, depth, errors);
+            }
+        }
+        let matching = |subs: &[Json]| {
+            subs.iter()
+                .filter(|sub| {
+                    let mut sub_errors = Vec::new();
+                    self.check(sub, instance, pointer, depth, &mut sub_errors);
+                    sub_errors.is_empty()
+                })
+                .count()
+        };
+        let mut fail = |message: String| {
+            errors.push(SchemaError {
+                pointer: pointer.to_string(),
+                message,
+            })
+        };
+        if let Some(any) = schema.get("anyOf").and_then(Json::as_array) {
+            if matching(any) == 0 {
+                fail("does not match any of the anyOf schemas".to_string());
+            }
+        }
+        if let Some(one) = schema.get("oneOf").and_then(Json::as_array) {
+            let n = matching(one);
+            if n != 1 {
+                fail(format!(
+                    "expected to match exactly one oneOf schema, matched {}",
+                    n
+                ));
+            }
+        }
+        if let Some(not) = schema.get("not") {
+            if matching(std::slice::from_ref(not)) == 1 {
+                fail("must not match the schema in 'not'".to_string());
+            }
+        }
+    }
+}
+
+/// Compiled `pattern` keywords, shared across validations so schemas
+/// checked on every evaluation compile each pattern once per process.
+static PATTERNS: LazyLock<Mutex<HashMap<String, Arc<regex::Regex>>>> =
+    LazyLock::new(Default::default);
+
+fn compiled_pattern(pattern: &str) -> Result<Arc<regex::Regex>, regex::Error> {
+    if let Some(re) = PATTERNS.lock().unwrap().get(pattern) {
+        return Ok(re.clone());
+    }
+    let re = Arc::new(regex::Regex::new(pattern)?);
+    PATTERNS
+        .lock()
+        .unwrap()
+        .insert(pattern.to_string(), re.clone());
+    Ok(re)
+}
+
+fn has_type(instance: &Json, type_: &str) -> bool {
+    match type_ {
+        "integer" => instance.as_f64().is_some_and(|n| n.fract() == 0.0),
+        "number" => instance.is_number(),
+        other => type_name(instance) == other,
+    }
+}
+
+fn type_name(instance: &Json) -> &'static str {
+    match instance {
+        Json::Null => "null",
+        Json::Bool(_) => "boolean",
+        Json::Number(_) => "number",
+        Json::String(_) => "string",
+        Json::Array(_) => "array",
+        Json::Object(_) => "object",
+    }
+}
+
+/// Escapes a property name as a JSON pointer segment (RFC 6901).
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn errors(schema: Json, instance: Json) -> Vec<String> {
+        validate(&schema, &instance)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_validate_reports_pointers() {
+        let schema = json!({
+            "type": "object",
+            "required": ["users", "version"],
+            "additionalProperties": false,
+            "properties": {
+                "version": { "type": "integer", "minimum": 1 },
+                "users": {
+                    "type": "array",
+                    "items": { "$ref": "#/$defs/user" },
+                },
+            },
+            "$defs": {
+                "user": {
+                    "type": "object",
+                    "required": ["name"],
+                    "properties": {
+                        "name": { "type": "string", "pattern": "^[a-z]+$" },
+                        "shell": { "enum": ["/bin/bash", "/bin/sh"] },
+                    },
+                },
+            },
+        });
+        assert!(errors(
+            schema.clone(),
+            json!({ "version": 2, "users": [{ "name": "ops", "shell": "/bin/sh" }] })
+        )
+        .is_empty());
+        assert_eq!(
+            errors(
+                schema,
+                json!({
+                    "version": 0,
+                    "users": [{ "name": "ops" }, { "name": "Ops", "shell": "zsh" }, {}],
+                    "a/b": true,
+                })
+            ),
+            [
+                "/a~1b: additional properties are not allowed",
+                "/users/1/name: does not match pattern \"^[a-z]+$\"",
+                "/users/1/shell: expected one of \"/bin/bash\", \"/bin/sh\"",
+                "/users/2: missing required property \"name\"",
+                "/version: must be at least 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_combinators_and_types() {
+        let schema = json!({ "oneOf": [{ "type": "string" }, { "type": "integer" }] });
+        assert!(errors(schema.clone(), json!(3)).is_empty());
+        assert_eq!(
+            errors(schema, json!(1.5)),
+            ["(root): expected to match exactly one oneOf schema, matched 0"]
+        );
+        assert_eq!(
+            errors(json!({ "type": ["string", "null"] }), json!([])),
+            ["(root): expected string or null, found array"]
+        );
+        assert_eq!(
+            errors(json!({ "$ref": "#/definitions/missing" }), json!(1)),
+            ["(root): cannot resolve $ref #/definitions/missing"]
+        );
+        assert!(errors(json!({ "$ref": "#" }), json!(1))
+            .last()
+            .unwrap()
+            .contains("nests too deeply"));
+    }
+}
@@ -0,0 +1,223 @@
+//! Resource limits for template evaluation.
+//!
+//! Guards the language host against pathological or hostile templates:
+//! - expression recursion depth (prevents stack overflow),
+//! - the size of a single interpolated string,
+//! - the approximate heap footprint of all stored values (variables,
+//!   resource outputs, and stack outputs),
+//! - the number of pages a paginated invoke fetches,
+//! - the number of instances a `count:` or `range:` resource expands to.
+//!
+//! Exceeding a limit produces an error diagnostic instead of a crash.
+
+use std::cell::Cell;
+
+use crate::eval::value::{Archive, Asset, Value};
+
+/// Environment variable overriding [`EvalLimits::max_expr_depth`].
+pub const MAX_EXPR_DEPTH_ENV: &str = "PULUMI_YAML_MAX_EXPR_DEPTH";
+/// Environment variable overriding [`EvalLimits::max_string_bytes`].
+pub const MAX_STRING_BYTES_ENV: &str = "PULUMI_YAML_MAX_STRING_BYTES";
+/// Environment variable overriding [`EvalLimits::max_value_bytes`].
+pub const MAX_VALUE_BYTES_ENV: &str = "PULUMI_YAML_MAX_VALUE_BYTES";
+/// Environment variable overriding [`EvalLimits::max_payload_bytes`].
+pub const MAX_PAYLOAD_BYTES_ENV: &str = "PULUMI_YAML_MAX_PAYLOAD_BYTES";
+/// Environment variable overriding [`EvalLimits::max_pages`].
+pub const MAX_PAGES_ENV: &str = "PULUMI_YAML_MAX_PAGES";
+/// Environment variable overriding [`EvalLimits::max_instances`].
+pub const MAX_INSTANCES_ENV: &str = "PULUMI_YAML_MAX_INSTANCES";
+
+/// The Pulumi engine's gRPC receive limit (`rpcutil.MaxRPCMessageSize`).
+/// Requests above it are rejected by the engine with an opaque transport
+/// error, so they are caught during evaluation instead.
+pub const ENGINE_MAX_MESSAGE_BYTES: usize = 400 * 1024 * 1024;
+
+/// Configurable evaluation limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvalLimits {
+    /// Maximum nesting depth of expression evaluation.
+    pub max_expr_depth: usize,
+    /// Maximum length in bytes of a single interpolation result.
+    pub max_string_bytes: usize,
+    /// Maximum approximate heap usage in bytes of all stored values.
+    pub max_value_bytes: usize,
+    /// Maximum approximate size in bytes of the inputs sent to the engine in
+    /// a single resource registration, read, or invoke.
+    pub max_payload_bytes: usize,
+    /// Maximum number of pages a paginated invoke fetches.
+    pub max_pages: usize,
+    /// Maximum number of instances a `count:` or `range:` resource expands
+    /// to.
+    pub max_instances: usize,
+}
+
+impl Default for EvalLimits {
+    fn default() -> Self {
+        Self {
+            max_expr_depth: 256,
+            max_string_bytes: 64 * 1024 * 1024,
+            max_value_bytes: 1024 * 1024 * 1024,
+            max_payload_bytes: ENGINE_MAX_MESSAGE_BYTES,
+            max_pages: 1000,
+            max_instances: 10_000,
+        }
+    }
+}
+
+impl EvalLimits {
+    /// Limits that never trigger.
+    pub fn unlimited() -> Self {
+        Self {
+            max_expr_depth: usize::MAX,
+            max_string_bytes: usize::MAX,
+            max_value_bytes: usize::MAX,
+            max_payload_bytes: usize::MAX,
+            max_pages: usize::MAX,
+            max_instances: usize::MAX,
+        }
+    }
+
+    /// Returns the default limits, overridden by the `PULUMI_YAML_MAX_*`
+    /// environment variables. Unparseable values are ignored.
+    pub fn from_env() -> Self {
+        let mut limits = Self::default();
+        let read = |name: &str| std::env::var(name).ok()?.trim().parse::<usize>().ok();
+        if let Some(n) = read(MAX_EXPR_DEPTH_ENV) {
+            limits.max_expr_depth = n;
+        }
+        if let Some(n) = read(MAX_STRING_BYTES_ENV) {
+            limits.max_string_bytes = n;
+        }
+        if let Some(n) = read(MAX_VALUE_BYTES_ENV) {
+            limits.max_value_bytes = n;
+        }
+        if let Some(n) = read(MAX_PAYLOAD_BYTES_ENV) {
+            limits.max_payload_bytes = n;
+        }
+        if let Some(n) = read(MAX_PAGES_ENV) {
+            limits.max_pages = n;
+        }
+        if let Some(n) = read(MAX_INSTANCES_ENV) {
+            limits.max_instances = n;
+        }
+        limits
+    }
+}
+
+thread_local! {
+    static EXPR_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// RAII guard tracking the expression evaluation depth of the current thread.
+///
+/// Depth is tracked per thread because levels may be evaluated in parallel;
+/// each expression tree is evaluated entirely on one thread.
+pub(crate) struct DepthGuard(());
+
+impl DepthGuard {
+    /// Enters one level of nesting, or returns None if `max` would be exceeded.
+    pub(crate) fn enter(max: usize) -> Option<Self> {
+        EXPR_DEPTH.with(|d| {
+            let depth = d.get();
+            if depth >= max {
+                return None;
+            }
+            d.set(depth + 1);
+            Some(DepthGuard(()))
+        })
+    }
+}
+
+impl Drop for DepthGuard {
+    fn drop(&mut self) {
+        EXPR_DEPTH.with(|d| d.set(d.get().saturating_sub(1)));
+    }
+}
+
+/// Approximate heap footprint of a value in bytes.
+///
+/// Counts string bytes and container element storage; it is an estimate for
+/// budgeting, not an exact allocator measurement.
+pub fn approx_heap_size(value: &Value<'_>) -> usize {
+    let elem = std::mem::size_of::<Value<'_>>();
+    match value {
+        Value::Null | Value::Bool(_) | Value::Number(_) | Value::Resource(_) | Value::Unknown => 0,
+        Value::String(s) => s.len(),
+        Value::Bytes(b) => b.len(),
+        Value::List(items) => {
+            items.len() * elem + items.iter().map(approx_heap_size).sum::<usize>()
+        }
+        Value::Object(entries) => entries
+            .iter()
+            .map(|(k, v)| k.len() + elem + approx_heap_size(v))
+            .sum(),
+        Value::Secret(inner) => elem + approx_heap_size(inner),
+        Value::Asset(Asset::String(s) | Asset::File(s, _) | Asset::Remote(s)) => s.len(),
+        Value::Archive(Archive::File(s) | Archive::Remote(s)) => s.len(),
+        Value::Archive(Archive::Assets(entries)) => entries
+            .iter()
+            .map(|(k, v)| k.len() + elem + approx_heap_size(v))
+            .sum(),
+    }
+}
+
+/// Formats a byte count for diagnostics (e.g. "512 B", "1.5 MiB").
+pub(crate) fn format_bytes(n: usize) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if n < 1024 {
+        return format!("{} B", n);
+    }
+    let mut value = n as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_depth_guard_limits_and_unwinds() {
+        let a = DepthGuard::enter(2).unwrap();
+        let b = DepthGuard::enter(2).unwrap();
+        assert!(DepthGuard::enter(2).is_none());
+        drop(b);
+        let c = DepthGuard::enter(2).unwrap();
+        drop(c);
+        drop(a);
+        EXPR_DEPTH.with(|d| assert_eq!(d.get(), 0));
+    }
+
+    #[test]
+    fn test_approx_heap_size() {
+        assert_eq!(approx_heap_size(&Value::Null), 0);
+        assert_eq!(approx_heap_size(&Value::String(Cow::Borrowed("abcd"))), 4);
+        let list = Value::List(vec![
+            Value::String(Cow::Borrowed("ab")),
+            Value::Number(1.0.into()),
+        ]);
+        assert_eq!(
+            approx_heap_size(&list),
+            2 * std::mem::size_of::<Value<'_>>() + 2
+        );
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(ENGINE_MAX_MESSAGE_BYTES), "400.0 MiB");
+    }
+
+    #[test]
+    fn test_unlimited() {
+        let limits = EvalLimits::unlimited();
+        assert_eq!(limits.max_expr_depth, usize::MAX);
+        assert!(EvalLimits::default().max_expr_depth < limits.max_expr_depth);
+    }
+}
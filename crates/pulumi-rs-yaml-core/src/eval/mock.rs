@@ -44,6 +44,8 @@ pub struct CapturedRead {
     pub inputs: HashMap<String, Value<'static>>,
     pub provider_ref: String,
     pub version: String,
+    pub depends_on: Vec<String>,
+    pub additional_secret_outputs: Vec<String>,
 }
 
 /// Mock resource callback that records calls and returns pre-configured responses.
@@ -68,6 +70,8 @@ pub struct MockCallback {
     pub reads: Arc<Mutex<Vec<CapturedRead>>>,
     /// Pre-configured read responses, consumed in order.
     pub read_responses: Arc<Mutex<VecDeque<RegisterResponse>>>,
+    /// Errors registrations fail with, keyed by resource name.
+    pub register_errors: Arc<Mutex<HashMap<String, String>>>,
     /// Default URN prefix for auto-generated responses.
     pub urn_prefix: String,
     /// Counter for auto-generating URNs.
@@ -87,6 +91,7 @@ impl MockCallback {
             logs: Arc::new(Mutex::new(Vec::new())),
             reads: Arc::new(Mutex::new(Vec::new())),
             read_responses: Arc::new(Mutex::new(VecDeque::new())),
+            register_errors: Arc::new(Mutex::new(HashMap::new())),
             urn_prefix: "urn:pulumi:test::test".to_string(),
             counter: Arc::new(AtomicU32::new(0)),
         }
@@ -113,6 +118,14 @@ impl MockCallback {
         mock
     }
 
+    /// Makes registering the resource named `name` fail with `message`.
+    pub fn fail_registration(&self, name: &str, message: &str) {
+        self.register_errors
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), message.to_string());
+    }
+
     /// Returns captured registrations.
     pub fn registrations(&self) -> Vec<CapturedRegistration> {
         self.registrations.lock().unwrap().clone()
@@ -179,6 +192,10 @@ impl ResourceCallback for MockCallback {
                 options,
             });
 
+        if let Some(message) = self.register_errors.lock().unwrap().get(name) {
+            return Err(EngineError::Registration(message.clone()));
+        }
+
         // Return pre-configured response or auto-generate one
         if let Some(resp) = self.register_responses.lock().unwrap().pop_front() {
             Ok(resp)
@@ -197,20 +214,20 @@ impl ResourceCallback for MockCallback {
         type_token: &str,
         name: &str,
         id: &str,
-        parent_urn: &str,
         inputs: HashMap<String, Value<'static>>,
-        provider_ref: &str,
-        version: &str,
+        options: ResolvedResourceOptions,
     ) -> Result<RegisterResponse, EngineError> {
         // Capture the call
         self.reads.lock().unwrap().push(CapturedRead {
             type_token: type_token.to_string(),
             name: name.to_string(),
             id: id.to_string(),
-            parent_urn: parent_urn.to_string(),
+            parent_urn: options.parent_urn.unwrap_or_default(),
             inputs: inputs.clone(),
-            provider_ref: provider_ref.to_string(),
-            version: version.to_string(),
+            provider_ref: options.provider_ref.unwrap_or_default(),
+            version: options.version,
+            depends_on: options.depends_on,
+            additional_secret_outputs: options.additional_secret_outputs,
         });
 
         // Return pre-configured response or auto-generate one
@@ -1,11 +1,26 @@
+pub mod assets;
+pub mod blocklist;
 pub mod builtins;
+pub mod cache;
 pub mod callback;
 pub mod config;
 pub mod context;
+pub mod encrypted;
+pub mod esc;
 pub mod evaluator;
+pub mod extensions;
+pub mod factory;
+pub mod fold;
 pub mod graph;
+pub mod hooks;
+pub mod json_schema;
+pub mod limits;
 pub mod mock;
+pub mod patch;
 pub mod protobuf;
 pub mod resource;
+pub(crate) mod scope;
+pub mod stable_random;
 pub mod starlark_runtime;
+pub mod timing;
 pub mod value;
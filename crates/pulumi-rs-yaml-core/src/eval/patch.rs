@@ -0,0 +1,351 @@
+//! Merge patches and JSON Patch over [`Value`]s, for `fn::merge` and
+//! `fn::jsonPatch`.
+//!
+//! Both operate on values directly rather than on JSON, so secrets nested in
+//! the inputs stay secret in the result.
+
+use std::borrow::Cow;
+
+use crate::eval::value::Value;
+
+/// Applies `patch` to `target` as an RFC 7386 merge patch, with Kubernetes
+/// strategic-merge handling of named lists.
+///
+/// Objects are merged key by key and a `null` in the patch removes the key.
+/// Two lists whose elements are all objects with a string `name` (containers,
+/// env vars, ports, volumes) are merged element by element on `name`, with
+/// new elements appended; any other patch value replaces the target.
+pub fn merge_patch<'src>(target: Value<'src>, patch: &Value<'src>) -> Value<'src> {
+    if let Value::Secret(inner) = patch {
+        return Value::Secret(Box::new(merge_patch(target, inner)));
+    }
+    let (target, secret) = match target {
+        Value::Secret(inner) => (*inner, true),
+        other => (other, false),
+    };
+    let merged = match (target, patch) {
+        (Value::Object(mut entries), Value::Object(patch_entries)) => {
+            for (key, value) in patch_entries {
+                let pos = entries.iter().position(|(k, _)| k == key);
+                match (pos, value.is_null()) {
+                    (Some(pos), true) => {
+                        entries.remove(pos);
+                    }
+                    (None, true) => {}
+                    (Some(pos), false) => {
+                        let existing = std::mem::replace(&mut entries[pos].1, Value::Null);
+                        entries[pos].1 = merge_patch(existing, value);
+                    }
+                    (None, false) => entries.push((key.clone(), merge_patch(Value::Null, value))),
+                }
+            }
+            Value::Object(entries)
+        }
+        // The keys of an unknown target are unknown too.
+        (Value::Unknown, Value::Object(_)) => return Value::Unknown,
+        (_, Value::Object(_)) => {
+            return wrap(merge_patch(Value::Object(Vec::new()), patch), secret)
+        }
+        (Value::List(mut items), Value::List(patch_items))
+            if is_named_list(&items) && is_named_list(patch_items) =>
+        {
+            for patch_item in patch_items {
+                let name = element_name(patch_item);
+                match items.iter().position(|item| element_name(item) == name) {
+                    Some(pos) => {
+                        let existing = std::mem::replace(&mut items[pos], Value::Null);
+                        items[pos] = merge_patch(existing, patch_item);
+                    }
+                    None => items.push(merge_patch(Value::Null, patch_item)),
+                }
+            }
+            Value::List(items)
+        }
+        _ => return patch.clone(),
+    };
+    wrap(merged, secret)
+}
+
+fn wrap(value: Value<'_>, secret: bool) -> Value<'_> {
+    if secret {
+        Value::Secret(Box::new(value))
+    } else {
+        value
+    }
+}
+
+fn element_name<'a>(value: &'a Value<'_>) -> Option<&'a str> {
+    match value.unwrap_secret() {
+        Value::Object(entries) => entries
+            .iter()
+            .find(|(k, _)| k == "name")
+            .and_then(|(_, v)| v.unwrap_secret().as_str()),
+        _ => None,
+    }
+}
+
+fn is_named_list(items: &[Value<'_>]) -> bool {
+    !items.is_empty() && items.iter().all(|item| element_name(item).is_some())
+}
+
+/// Applies an RFC 6902 JSON Patch (a list of operations) to `target`.
+///
+/// Returns the index of the failing operation and the reason on error.
+pub fn json_patch<'src>(
+    mut target: Value<'src>,
+    operations: &[Value<'src>],
+) -> Result<Value<'src>, (usize, String)> {
+    for (i, operation) in operations.iter().enumerate() {
+        apply(&mut target, operation).map_err(|e| (i, e))?;
+    }
+    Ok(target)
+}
+
+fn apply<'src>(target: &mut Value<'src>, operation: &Value<'src>) -> Result<(), String> {
+    let Value::Object(fields) = operation.unwrap_secret() else {
+        return Err(format!(
+            "expected an object, found {}",
+            operation.type_name()
+        ));
+    };
+    let field = |name: &str| fields.iter().find(|(k, _)| k == name).map(|(_, v)| v);
+    let string = |name: &str| {
+        field(name)
+            .and_then(|v| v.unwrap_secret().as_str())
+            .ok_or_else(|| format!("missing string '{}'", name))
+    };
+    let op = string("op")?;
+    let path = pointer(string("path")?)?;
+    let value = || field("value").cloned().ok_or("missing 'value'".to_string());
+    match op {
+        "add" => add(target, &path, value()?),
+        "remove" => remove(target, &path).map(drop),
+        "replace" => {
+            *get_mut(target, &path).ok_or("path not found")? = value()?;
+            Ok(())
+        }
+        "move" => {
+            let from = pointer(string("from")?)?;
+            if path.len() > from.len() && path[..from.len()] == from[..] {
+                return Err("cannot move a value into itself".to_string());
+            }
+            let moved = remove(target, &from)?;
+            add(target, &path, moved)
+        }
+        "copy" => {
+            let from = pointer(string("from")?)?;
+            let copied = get_mut(target, &from)
+                .ok_or("'from' path not found")?
+                .clone();
+            add(target, &path, copied)
+        }
+        "test" => {
+            let actual = get_mut(target, &path).ok_or("path not found")?;
+            if actual.to_json() == value()?.to_json() {
+                Ok(())
+            } else {
+                Err("test failed: the value differs".to_string())
+            }
+        }
+        other => Err(format!("unknown op {:?}", other)),
+    }
+}
+
+/// Splits an RFC 6901 JSON pointer into its unescaped tokens.
+fn pointer(path: &str) -> Result<Vec<String>, String> {
+    if path.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(rest) = path.strip_prefix('/') else {
+        return Err(format!("invalid JSON pointer {:?}", path));
+    };
+    Ok(rest
+        .split('/')
+        .map(|t| t.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
+
+/// Parses a list index token: digits without leading zeros.
+fn index(token: &str) -> Option<usize> {
+    if token.len() > 1 && token.starts_with('0') {
+        return None;
+    }
+    token.parse().ok()
+}
+
+fn get_mut<'a, 'src>(value: &'a mut Value<'src>, path: &[String]) -> Option<&'a mut Value<'src>> {
+    let Some((token, rest)) = path.split_first() else {
+        return Some(value);
+    };
+    let child = match unwrap_secret_mut(value) {
+        Value::Object(entries) => entries
+            .iter_mut()
+            .find(|(k, _)| k == token)
+            .map(|(_, v)| v)?,
+        Value::List(items) => items.get_mut(index(token)?)?,
+        _ => return None,
+    };
+    get_mut(child, rest)
+}
+
+fn unwrap_secret_mut<'a, 'src>(value: &'a mut Value<'src>) -> &'a mut Value<'src> {
+    match value {
+        Value::Secret(inner) => unwrap_secret_mut(inner),
+        other => other,
+    }
+}
+
+fn add<'src>(target: &mut Value<'src>, path: &[String], value: Value<'src>) -> Result<(), String> {
+    let Some((last, parent)) = path.split_last() else {
+        *target = value;
+        return Ok(());
+    };
+    match unwrap_secret_mut(get_mut(target, parent).ok_or("parent path not found")?) {
+        Value::Object(entries) => {
+            match entries.iter_mut().find(|(k, _)| k == last) {
+                Some((_, existing)) => *existing = value,
+                None => entries.push((Cow::Owned(last.clone()), value)),
+            }
+            Ok(())
+        }
+        Value::List(items) if last == "-" => {
+            items.push(value);
+            Ok(())
+        }
+        Value::List(items) => match index(last) {
+            Some(i) if i <= items.len() => {
+                items.insert(i, value);
+                Ok(())
+            }
+            _ => Err(format!("index {} is out of bounds", last)),
+        },
+        other => Err(format!("cannot add to {}", other.type_name())),
+    }
+}
+
+fn remove<'src>(target: &mut Value<'src>, path: &[String]) -> Result<Value<'src>, String> {
+    let Some((last, parent)) = path.split_last() else {
+        return Ok(std::mem::replace(target, Value::Null));
+    };
+    let parent = unwrap_secret_mut(get_mut(target, parent).ok_or("path not found")?);
+    match parent {
+        Value::Object(entries) => entries
+            .iter()
+            .position(|(k, _)| k == last)
+            .map(|pos| entries.remove(pos).1)
+            .ok_or_else(|| "path not found".to_string()),
+        Value::List(items) => match index(last) {
+            Some(i) if i < items.len() => Ok(items.remove(i)),
+            _ => Err(format!("index {} is out of bounds", last)),
+        },
+        _ => Err("path not found".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn v(json: serde_json::Value) -> Value<'static> {
+        Value::from_json(&json)
+    }
+
+    #[test]
+    fn test_merge_patch_strategic_lists() {
+        let base = v(json!({
+            "replicas": 1,
+            "labels": { "app": "web", "tier": "frontend" },
+            "containers": [
+                { "name": "web", "image": "nginx:1.25", "ports": [80] },
+                { "name": "sidecar", "image": "envoy" },
+            ],
+            "args": ["--a"],
+        }));
+        let patch = v(json!({
+            "replicas": 3,
+            "labels": { "tier": null },
+            "containers": [
+                { "name": "web", "image": "nginx:1.27" },
+                { "name": "metrics", "image": "exporter" },
+            ],
+            "args": ["--b"],
+        }));
+        assert_eq!(
+            merge_patch(base, &patch).to_json(),
+            json!({
+                "replicas": 3,
+                "labels": { "app": "web" },
+                "containers": [
+                    { "name": "web", "image": "nginx:1.27", "ports": [80] },
+                    { "name": "sidecar", "image": "envoy" },
+                    { "name": "metrics", "image": "exporter" },
+                ],
+                "args": ["--b"],
+            })
+        );
+    }
+
+    #[test]
+    fn test_merge_patch_keeps_secrets() {
+        let base = Value::Object(vec![(
+            Cow::Borrowed("password"),
+            Value::Secret(Box::new(Value::String(Cow::Borrowed("hunter2")))),
+        )]);
+        let merged = merge_patch(base, &v(json!({ "user": "admin" })));
+        let Value::Object(entries) = merged else {
+            panic!("expected an object");
+        };
+        assert!(entries[0].1.is_secret());
+        assert!(!entries[1].1.is_secret());
+    }
+
+    #[test]
+    fn test_merge_patch_with_unknowns() {
+        let base = Value::Object(vec![
+            (Cow::Borrowed("bucket"), Value::Unknown),
+            (Cow::Borrowed("tags"), Value::Unknown),
+            (Cow::Borrowed("size"), Value::Number(1i64.into())),
+        ]);
+        let merged = merge_patch(base, &v(json!({ "size": 2, "tags": { "env": "prod" } })));
+        let Value::Object(entries) = merged else {
+            panic!("expected an object");
+        };
+        assert_eq!(entries[0].1, Value::Unknown);
+        assert_eq!(entries[1].1, Value::Unknown);
+        assert_eq!(entries[2].1, Value::Number(2i64.into()));
+    }
+
+    #[test]
+    fn test_json_patch_operations() {
+        let doc = v(json!({ "spec": { "replicas": 1, "ports": [80] }, "a/b": 1 }));
+        let ops = v(json!([
+            { "op": "replace", "path": "/spec/replicas", "value": 3 },
+            { "op": "add", "path": "/spec/ports/-", "value": 443 },
+            { "op": "add", "path": "/spec/ports/0", "value": 22 },
+            { "op": "test", "path": "/spec/ports/2", "value": 443 },
+            { "op": "copy", "from": "/spec/replicas", "path": "/min" },
+            { "op": "move", "from": "/a~1b", "path": "/moved" },
+            { "op": "remove", "path": "/spec/ports/1" },
+        ]));
+        let Value::List(ops) = ops else {
+            unreachable!()
+        };
+        assert_eq!(
+            json_patch(doc.clone(), &ops).unwrap().to_json(),
+            json!({ "spec": { "replicas": 3, "ports": [22, 443] }, "min": 3, "moved": 1 })
+        );
+
+        let bad = v(json!([
+            { "op": "add", "path": "/x", "value": 1 },
+            { "op": "remove", "path": "/spec/missing" },
+        ]));
+        let Value::List(bad) = bad else {
+            unreachable!()
+        };
+        assert_eq!(
+            json_patch(doc, &bad).unwrap_err(),
+            (1, "path not found".to_string())
+        );
+    }
+}
@@ -1,4 +1,5 @@
-use crate::eval::value::Value;
+use crate::eval::value::{encode_base64, Archive, Asset, Number, Value};
+use crate::number::MAX_SAFE_INTEGER;
 use std::borrow::Cow;
 use std::collections::BTreeMap;
 
@@ -12,7 +13,7 @@ pub const ASSET_SIG: &str = "c44067f5952c0a294b673a41bacd8c17";
 pub const ARCHIVE_SIG: &str = "0def7320c3a5731c473e5ecbe6d01bc7";
 
 /// The special key used in protobuf structs to hold the type signature marker.
-const SIG_KEY: &str = "4dabf18193072939515e22adb298388d";
+pub const SIG_KEY: &str = "4dabf18193072939515e22adb298388d";
 
 /// Converts a `Value` into a `prost_types::Value` for gRPC transmission.
 pub fn value_to_protobuf(val: &Value<'_>) -> prost_types::Value {
@@ -21,8 +22,10 @@ pub fn value_to_protobuf(val: &Value<'_>) -> prost_types::Value {
     let kind = match val {
         Value::Null => Kind::NullValue(0),
         Value::Bool(b) => Kind::BoolValue(*b),
-        Value::Number(n) => Kind::NumberValue(*n),
+        Value::Number(n) => Kind::NumberValue(n.as_f64()),
         Value::String(s) => Kind::StringValue(s.to_string()),
+        // Providers take binary content as base64 strings
+        Value::Bytes(b) => Kind::StringValue(encode_base64(b)),
         Value::List(items) => {
             let values: Vec<prost_types::Value> = items.iter().map(value_to_protobuf).collect();
             Kind::ListValue(prost_types::ListValue { values })
@@ -48,87 +51,117 @@ pub fn value_to_protobuf(val: &Value<'_>) -> prost_types::Value {
         }
         Value::Unknown => Kind::StringValue(UNKNOWN_VALUE.to_string()),
         Value::Resource(r) => Kind::StringValue(format!("resource({})", r.0)),
-        Value::Asset(asset) => {
-            let mut fields = BTreeMap::new();
-            fields.insert(
-                SIG_KEY.to_string(),
-                prost_types::Value {
-                    kind: Some(Kind::StringValue(ASSET_SIG.to_string())),
-                },
-            );
-            match asset {
-                crate::eval::value::Asset::String(s) => {
-                    fields.insert(
-                        "text".to_string(),
-                        prost_types::Value {
-                            kind: Some(Kind::StringValue(s.to_string())),
-                        },
-                    );
-                }
-                crate::eval::value::Asset::File(s) => {
-                    fields.insert(
-                        "path".to_string(),
-                        prost_types::Value {
-                            kind: Some(Kind::StringValue(s.to_string())),
-                        },
-                    );
-                }
-                crate::eval::value::Asset::Remote(s) => {
-                    fields.insert(
-                        "uri".to_string(),
-                        prost_types::Value {
-                            kind: Some(Kind::StringValue(s.to_string())),
-                        },
-                    );
-                }
-            }
-            Kind::StructValue(prost_types::Struct { fields })
-        }
-        Value::Archive(archive) => {
-            let mut fields = BTreeMap::new();
-            fields.insert(
-                SIG_KEY.to_string(),
-                prost_types::Value {
-                    kind: Some(Kind::StringValue(ARCHIVE_SIG.to_string())),
-                },
-            );
-            match archive {
-                crate::eval::value::Archive::File(s) => {
-                    fields.insert(
-                        "path".to_string(),
-                        prost_types::Value {
-                            kind: Some(Kind::StringValue(s.to_string())),
-                        },
-                    );
-                }
-                crate::eval::value::Archive::Remote(s) => {
-                    fields.insert(
-                        "uri".to_string(),
-                        prost_types::Value {
-                            kind: Some(Kind::StringValue(s.to_string())),
-                        },
-                    );
-                }
-                crate::eval::value::Archive::Assets(entries) => {
-                    let assets: BTreeMap<String, prost_types::Value> = entries
-                        .iter()
-                        .map(|(k, v)| (k.to_string(), value_to_protobuf(v)))
-                        .collect();
-                    fields.insert(
-                        "assets".to_string(),
-                        prost_types::Value {
-                            kind: Some(Kind::StructValue(prost_types::Struct { fields: assets })),
-                        },
-                    );
-                }
-            }
-            Kind::StructValue(prost_types::Struct { fields })
-        }
+        Value::Asset(asset) => Kind::StructValue(encode_asset(asset)),
+        Value::Archive(archive) => Kind::StructValue(encode_archive(archive)),
     };
 
     prost_types::Value { kind: Some(kind) }
 }
 
+/// Converts a `Value` into an engine output value carrying `dependencies`.
+///
+/// Output values are structs tagged with `OUTPUT_SIG`:
+/// `{value?, secret?, dependencies}`. An unknown value omits `value`, and a
+/// top-level secret is unwrapped into `secret: true`. Used when the receiver
+/// accepts output values (remote components), so SDKs downstream see real
+/// Outputs with their dependencies instead of plain values.
+pub fn value_to_output_protobuf(val: &Value<'_>, dependencies: &[String]) -> prost_types::Value {
+    let (inner, secret) = match val {
+        Value::Secret(inner) => (inner.as_ref(), true),
+        other => (other, false),
+    };
+    let value = (!inner.is_unknown()).then(|| value_to_protobuf(inner));
+    output_value(value, secret, dependencies)
+}
+
+/// Converts a `Value` into a `prost_types::Value`, consuming it.
+///
+/// Produces the same encoding as [`value_to_protobuf`], but owned strings
+/// and keys are moved into the protobuf value instead of copied. Callbacks
+/// receive their inputs by value, and for large string-heavy payloads the
+/// copies dominate the conversion.
+pub fn value_into_protobuf(val: Value<'static>) -> prost_types::Value {
+    use prost_types::value::Kind;
+
+    let kind = match val {
+        Value::String(s) => Kind::StringValue(s.into_owned()),
+        Value::List(items) => Kind::ListValue(prost_types::ListValue {
+            values: items.into_iter().map(value_into_protobuf).collect(),
+        }),
+        Value::Object(entries) => Kind::StructValue(entries_into_struct(
+            entries.into_iter().map(|(k, v)| (k.into_owned(), v)),
+        )),
+        Value::Secret(inner) => Kind::StructValue(signed_struct(
+            SECRET_SIG,
+            [("value", value_into_protobuf(*inner))],
+        )),
+        other => return value_to_protobuf(&other),
+    };
+
+    prost_types::Value { kind: Some(kind) }
+}
+
+/// Converts `(key, value)` pairs into a `prost_types::Struct`, consuming
+/// them as [`value_into_protobuf`] does.
+pub fn entries_into_struct(
+    entries: impl IntoIterator<Item = (String, Value<'static>)>,
+) -> prost_types::Struct {
+    // Inserting directly avoids the intermediate vector `collect` sorts.
+    let mut fields = BTreeMap::new();
+    for (k, v) in entries {
+        fields.insert(k, value_into_protobuf(v));
+    }
+    prost_types::Struct { fields }
+}
+
+/// Converts a `Value` into an engine output value, consuming it as
+/// [`value_into_protobuf`] does. See [`value_to_output_protobuf`].
+pub fn value_into_output_protobuf(
+    val: Value<'static>,
+    dependencies: &[String],
+) -> prost_types::Value {
+    let (inner, secret) = match val {
+        Value::Secret(inner) => (*inner, true),
+        other => (other, false),
+    };
+    let value = (!inner.is_unknown()).then(|| value_into_protobuf(inner));
+    output_value(value, secret, dependencies)
+}
+
+/// Builds an output value struct: `{sig: OUTPUT_SIG, value?, secret?,
+/// dependencies}`.
+fn output_value(
+    value: Option<prost_types::Value>,
+    secret: bool,
+    dependencies: &[String],
+) -> prost_types::Value {
+    use prost_types::value::Kind;
+
+    let deps = prost_types::Value {
+        kind: Some(Kind::ListValue(prost_types::ListValue {
+            values: dependencies.iter().map(|d| string_value(d)).collect(),
+        })),
+    };
+
+    let mut fields = BTreeMap::new();
+    fields.insert(SIG_KEY.to_string(), string_value(OUTPUT_SIG));
+    if let Some(value) = value {
+        fields.insert("value".to_string(), value);
+    }
+    if secret {
+        fields.insert(
+            "secret".to_string(),
+            prost_types::Value {
+                kind: Some(Kind::BoolValue(true)),
+            },
+        );
+    }
+    fields.insert("dependencies".to_string(), deps);
+    prost_types::Value {
+        kind: Some(Kind::StructValue(prost_types::Struct { fields })),
+    }
+}
+
 /// Converts a `prost_types::Value` back into a `Value<'static>`.
 ///
 /// Consumes the protobuf value by value to avoid unnecessary clones —
@@ -144,7 +177,7 @@ pub fn protobuf_to_value(pv: prost_types::Value) -> Value<'static> {
     match kind {
         Kind::NullValue(_) => Value::Null,
         Kind::BoolValue(b) => Value::Bool(b),
-        Kind::NumberValue(n) => Value::Number(n),
+        Kind::NumberValue(n) => Value::Number(wire_number(n)),
         Kind::StringValue(s) => {
             if s == UNKNOWN_VALUE {
                 Value::Unknown
@@ -169,26 +202,8 @@ pub fn protobuf_to_value(pv: prost_types::Value) -> Value<'static> {
                             return Value::Secret(Box::new(Value::Null));
                         }
                         ASSET_SIG => {
-                            if let Some(text_val) = obj.fields.remove("text") {
-                                if let Some(Kind::StringValue(s)) = text_val.kind {
-                                    return Value::Asset(crate::eval::value::Asset::String(
-                                        Cow::Owned(s),
-                                    ));
-                                }
-                            }
-                            if let Some(path_val) = obj.fields.remove("path") {
-                                if let Some(Kind::StringValue(s)) = path_val.kind {
-                                    return Value::Asset(crate::eval::value::Asset::File(
-                                        Cow::Owned(s),
-                                    ));
-                                }
-                            }
-                            if let Some(uri_val) = obj.fields.remove("uri") {
-                                if let Some(Kind::StringValue(s)) = uri_val.kind {
-                                    return Value::Asset(crate::eval::value::Asset::Remote(
-                                        Cow::Owned(s),
-                                    ));
-                                }
+                            if let Some(asset) = decode_asset(&mut obj.fields) {
+                                return Value::Asset(asset);
                             }
                         }
                         OUTPUT_SIG => {
@@ -228,31 +243,8 @@ pub fn protobuf_to_value(pv: prost_types::Value) -> Value<'static> {
                             };
                         }
                         ARCHIVE_SIG => {
-                            if let Some(path_val) = obj.fields.remove("path") {
-                                if let Some(Kind::StringValue(s)) = path_val.kind {
-                                    return Value::Archive(crate::eval::value::Archive::File(
-                                        Cow::Owned(s),
-                                    ));
-                                }
-                            }
-                            if let Some(uri_val) = obj.fields.remove("uri") {
-                                if let Some(Kind::StringValue(s)) = uri_val.kind {
-                                    return Value::Archive(crate::eval::value::Archive::Remote(
-                                        Cow::Owned(s),
-                                    ));
-                                }
-                            }
-                            if let Some(assets_val) = obj.fields.remove("assets") {
-                                if let Some(Kind::StructValue(assets_obj)) = assets_val.kind {
-                                    let entries: Vec<_> = assets_obj
-                                        .fields
-                                        .into_iter()
-                                        .map(|(k, v)| (Cow::Owned(k), protobuf_to_value(v)))
-                                        .collect();
-                                    return Value::Archive(crate::eval::value::Archive::Assets(
-                                        entries,
-                                    ));
-                                }
+                            if let Some(archive) = decode_archive(&mut obj.fields) {
+                                return Value::Archive(archive);
                             }
                         }
                         _ => {}
@@ -271,6 +263,126 @@ pub fn protobuf_to_value(pv: prost_types::Value) -> Value<'static> {
     }
 }
 
+/// Decodes a protobuf number. The wire format only has doubles, so whole
+/// numbers in the range where doubles are exact come back as integers.
+fn wire_number(n: f64) -> Number {
+    if n.fract() == 0.0 && n.abs() <= MAX_SAFE_INTEGER as f64 {
+        Number::Int(n as i64)
+    } else {
+        Number::Float(n)
+    }
+}
+
+/// Builds a struct tagged with the given signature plus `fields`.
+fn signed_struct<const N: usize>(
+    sig: &str,
+    fields: [(&str, prost_types::Value); N],
+) -> prost_types::Struct {
+    let mut map = BTreeMap::new();
+    map.insert(SIG_KEY.to_string(), string_value(sig));
+    for (k, v) in fields {
+        map.insert(k.to_string(), v);
+    }
+    prost_types::Struct { fields: map }
+}
+
+fn string_value(s: &str) -> prost_types::Value {
+    prost_types::Value {
+        kind: Some(prost_types::value::Kind::StringValue(s.to_string())),
+    }
+}
+
+/// Encodes an asset as `{sig: ASSET_SIG, text | path | uri}`, plus `hash`
+/// for a file asset whose contents have been hashed.
+fn encode_asset(asset: &Asset<'_>) -> prost_types::Struct {
+    let (key, s) = match asset {
+        Asset::String(s) => ("text", s),
+        Asset::File(s, Some(hash)) => {
+            return signed_struct(
+                ASSET_SIG,
+                [("path", string_value(s)), ("hash", string_value(hash))],
+            )
+        }
+        Asset::File(s, None) => ("path", s),
+        Asset::Remote(s) => ("uri", s),
+    };
+    signed_struct(ASSET_SIG, [(key, string_value(s))])
+}
+
+/// Encodes an archive as `{sig: ARCHIVE_SIG, path | uri | assets}`.
+///
+/// The values of an `assets` map are themselves assets or archives and are
+/// encoded recursively.
+fn encode_archive(archive: &Archive<'_>) -> prost_types::Struct {
+    match archive {
+        Archive::File(s) => signed_struct(ARCHIVE_SIG, [("path", string_value(s))]),
+        Archive::Remote(s) => signed_struct(ARCHIVE_SIG, [("uri", string_value(s))]),
+        Archive::Assets(entries) => {
+            let assets: BTreeMap<String, prost_types::Value> = entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), value_to_protobuf(v)))
+                .collect();
+            let assets = prost_types::Value {
+                kind: Some(prost_types::value::Kind::StructValue(prost_types::Struct {
+                    fields: assets,
+                })),
+            };
+            signed_struct(ARCHIVE_SIG, [("assets", assets)])
+        }
+    }
+}
+
+/// Removes a string field, returning it only if it is non-empty.
+fn take_non_empty(fields: &mut BTreeMap<String, prost_types::Value>, key: &str) -> Option<String> {
+    match fields.remove(key)?.kind {
+        Some(prost_types::value::Kind::StringValue(s)) if !s.is_empty() => Some(s),
+        _ => None,
+    }
+}
+
+/// Decodes the fields of an asset struct.
+///
+/// The engine sends a `hash` alongside the content key; it is kept for file
+/// assets and dropped otherwise, as it is derived from the content. Empty
+/// content keys are ignored, except that an asset with only an empty `text`
+/// is an empty string asset.
+fn decode_asset(fields: &mut BTreeMap<String, prost_types::Value>) -> Option<Asset<'static>> {
+    let has_text = fields.contains_key("text");
+    if let Some(s) = take_non_empty(fields, "text") {
+        return Some(Asset::String(Cow::Owned(s)));
+    }
+    if let Some(s) = take_non_empty(fields, "path") {
+        return Some(Asset::File(Cow::Owned(s), take_non_empty(fields, "hash")));
+    }
+    if let Some(s) = take_non_empty(fields, "uri") {
+        return Some(Asset::Remote(Cow::Owned(s)));
+    }
+    has_text.then_some(Asset::String(Cow::Borrowed("")))
+}
+
+/// Decodes the fields of an archive struct. Nested assets and archives in an
+/// `assets` map are decoded recursively; `hash` is dropped as for assets.
+fn decode_archive(fields: &mut BTreeMap<String, prost_types::Value>) -> Option<Archive<'static>> {
+    if let Some(prost_types::Value {
+        kind: Some(prost_types::value::Kind::StructValue(assets)),
+    }) = fields.remove("assets")
+    {
+        let entries = assets
+            .fields
+            .into_iter()
+            .map(|(k, v)| (Cow::Owned(k), protobuf_to_value(v)))
+            .collect();
+        return Some(Archive::Assets(entries));
+    }
+    if let Some(s) = take_non_empty(fields, "path") {
+        return Some(Archive::File(Cow::Owned(s)));
+    }
+    if let Some(s) = take_non_empty(fields, "uri") {
+        return Some(Archive::Remote(Cow::Owned(s)));
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -280,6 +392,58 @@ mod tests {
         protobuf_to_value(pb)
     }
 
+    #[test]
+    fn test_owned_encoding_matches_borrowed() {
+        let values = vec![
+            Value::Null,
+            Value::Bool(true),
+            Value::Number(1.5.into()),
+            Value::String("plain".into()),
+            Value::Unknown,
+            Value::Resource(crate::eval::value::ResourceRef(3)),
+            Value::List(vec![Value::String("a".into()), Value::Unknown]),
+            Value::Object(vec![
+                (Cow::Borrowed("b"), Value::String(Cow::Owned("x".into()))),
+                (Cow::Owned("a".into()), Value::Number(2.0.into())),
+            ]),
+            Value::Secret(Box::new(Value::Object(vec![(
+                Cow::Borrowed("k"),
+                Value::Secret(Box::new(Value::Bool(false))),
+            )]))),
+            Value::Asset(Asset::File("./f.txt".into(), Some("abc".into()))),
+            Value::Archive(Archive::Assets(vec![(
+                Cow::Borrowed("index.html"),
+                Value::Asset(Asset::String("<html/>".into())),
+            )])),
+        ];
+        let deps = vec!["urn:a".to_string()];
+        for value in values {
+            assert_eq!(
+                value_into_protobuf(value.clone()),
+                value_to_protobuf(&value),
+                "{:?}",
+                value
+            );
+            let secret = Value::Secret(Box::new(value.clone()));
+            assert_eq!(
+                value_into_output_protobuf(secret.clone(), &deps),
+                value_to_output_protobuf(&secret, &deps),
+            );
+            assert_eq!(
+                value_into_output_protobuf(value.clone(), &deps),
+                value_to_output_protobuf(&value, &deps),
+            );
+        }
+    }
+
+    #[test]
+    fn test_bytes_marshal_as_base64() {
+        let bytes = Value::Bytes(vec![0x89, b'P', 0xff, 0x00]);
+        let expected = Value::String(Cow::Borrowed("iVD/AA=="));
+        assert_eq!(round_trip(bytes.clone()), expected);
+        assert_eq!(protobuf_to_value(value_into_protobuf(bytes)), expected);
+    }
+
     #[test]
     fn test_null_round_trip() {
         assert_eq!(round_trip(Value::Null), Value::Null);
@@ -293,8 +457,23 @@ mod tests {
 
     #[test]
     fn test_number_round_trip() {
-        assert_eq!(round_trip(Value::Number(42.0)), Value::Number(42.0));
-        assert_eq!(round_trip(Value::Number(2.75)), Value::Number(2.75));
+        assert_eq!(
+            round_trip(Value::Number(42.0.into())),
+            Value::Number(42.0.into())
+        );
+        assert_eq!(
+            round_trip(Value::Number(2.75.into())),
+            Value::Number(2.75.into())
+        );
+    }
+
+    #[test]
+    fn test_whole_numbers_decode_as_integers() {
+        assert_eq!(round_trip(Value::Number(Number::Int(7))).as_i64(), Some(7));
+        assert!(matches!(
+            round_trip(Value::Number(Number::Int(1 << 60))),
+            Value::Number(Number::Float(_))
+        ));
     }
 
     #[test]
@@ -309,7 +488,7 @@ mod tests {
     #[test]
     fn test_list_round_trip() {
         let v = Value::List(vec![
-            Value::Number(1.0),
+            Value::Number(1.0.into()),
             Value::String(Cow::Owned("two".to_string())),
             Value::Bool(true),
         ]);
@@ -317,7 +496,7 @@ mod tests {
         match &result {
             Value::List(items) => {
                 assert_eq!(items.len(), 3);
-                assert_eq!(items[0], Value::Number(1.0));
+                assert_eq!(items[0], Value::Number(1.0.into()));
             }
             _ => panic!("expected list"),
         }
@@ -360,13 +539,16 @@ mod tests {
     fn test_nested_object_round_trip() {
         let v = Value::Object(vec![(
             Cow::Owned("outer".to_string()),
-            Value::Object(vec![(Cow::Owned("inner".to_string()), Value::Number(99.0))]),
+            Value::Object(vec![(
+                Cow::Owned("inner".to_string()),
+                Value::Number(99.0.into()),
+            )]),
         )]);
         let result = round_trip(v);
         match &result {
             Value::Object(entries) => match &entries[0].1 {
                 Value::Object(inner) => {
-                    assert_eq!(inner[0].1, Value::Number(99.0));
+                    assert_eq!(inner[0].1, Value::Number(99.0.into()));
                 }
                 _ => panic!("expected inner object"),
             },
@@ -390,16 +572,23 @@ mod tests {
 
     #[test]
     fn test_asset_file_round_trip() {
-        let v = Value::Asset(crate::eval::value::Asset::File(Cow::Owned(
-            "/path/to/file".to_string(),
-        )));
+        let v = Value::Asset(crate::eval::value::Asset::File(
+            Cow::Owned("/path/to/file".to_string()),
+            None,
+        ));
         let result = round_trip(v);
         match &result {
-            Value::Asset(crate::eval::value::Asset::File(s)) => {
+            Value::Asset(crate::eval::value::Asset::File(s, _)) => {
                 assert_eq!(s.as_ref(), "/path/to/file");
             }
             _ => panic!("expected file asset"),
         }
+
+        let hashed = Value::Asset(Asset::File(
+            Cow::Borrowed("a.txt"),
+            Some("e3b0".to_string()),
+        ));
+        assert_eq!(round_trip(hashed.clone()), hashed);
     }
 
     #[test]
@@ -416,6 +605,131 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_remote_asset_and_archive_round_trip() {
+        let asset = Value::Asset(Asset::Remote(Cow::Borrowed("https://example.com/a.txt")));
+        assert_eq!(round_trip(asset.clone()), asset);
+        let archive = Value::Archive(Archive::Remote(Cow::Borrowed("https://example.com/a.zip")));
+        assert_eq!(round_trip(archive.clone()), archive);
+    }
+
+    #[test]
+    fn test_nested_asset_archive_round_trip() {
+        let v = Value::Archive(Archive::Assets(vec![
+            (
+                Cow::Borrowed("index.html"),
+                Value::Asset(Asset::String(Cow::Borrowed("<h1>hi</h1>"))),
+            ),
+            (
+                Cow::Borrowed("lib"),
+                Value::Archive(Archive::Assets(vec![(
+                    Cow::Borrowed("util.js"),
+                    Value::Asset(Asset::File(Cow::Borrowed("./util.js"), None)),
+                )])),
+            ),
+            (
+                Cow::Borrowed("vendor"),
+                Value::Archive(Archive::File(Cow::Borrowed("./vendor.tar.gz"))),
+            ),
+        ]));
+        assert_eq!(round_trip(v.clone()), v);
+    }
+
+    #[test]
+    fn test_asset_encoding_uses_sig_keys() {
+        use prost_types::value::Kind;
+        let pb = value_to_protobuf(&Value::Asset(Asset::File(Cow::Borrowed("a.txt"), None)));
+        let Some(Kind::StructValue(obj)) = pb.kind else {
+            panic!("expected struct");
+        };
+        assert_eq!(obj.fields.len(), 2);
+        assert_eq!(
+            obj.fields[SIG_KEY].kind,
+            Some(Kind::StringValue(ASSET_SIG.to_string()))
+        );
+        assert_eq!(
+            obj.fields["path"].kind,
+            Some(Kind::StringValue("a.txt".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_decode_engine_asset_with_hash() {
+        use prost_types::value::Kind;
+        let engine_asset = |sig: &str, fields: &[(&str, &str)]| {
+            let mut map = BTreeMap::new();
+            map.insert(SIG_KEY.to_string(), string_value(sig));
+            for (k, v) in fields {
+                map.insert(k.to_string(), string_value(v));
+            }
+            prost_types::Value {
+                kind: Some(Kind::StructValue(prost_types::Struct { fields: map })),
+            }
+        };
+        assert_eq!(
+            protobuf_to_value(engine_asset(
+                ASSET_SIG,
+                &[("hash", "abc123"), ("text", ""), ("path", "main.py")]
+            )),
+            Value::Asset(Asset::File(
+                Cow::Borrowed("main.py"),
+                Some("abc123".to_string())
+            ))
+        );
+        assert_eq!(
+            protobuf_to_value(engine_asset(ASSET_SIG, &[("hash", "e3b0"), ("text", "")])),
+            Value::Asset(Asset::String(Cow::Borrowed("")))
+        );
+        assert_eq!(
+            protobuf_to_value(engine_asset(
+                ARCHIVE_SIG,
+                &[
+                    ("hash", "def456"),
+                    ("path", ""),
+                    ("uri", "s3://bucket/code.zip")
+                ]
+            )),
+            Value::Archive(Archive::Remote(Cow::Borrowed("s3://bucket/code.zip")))
+        );
+    }
+
+    #[test]
+    fn test_output_value_encoding() {
+        use prost_types::value::Kind;
+        let deps = vec!["urn:pulumi:dev::proj::aws:s3/bucket:Bucket::b".to_string()];
+        let pb = value_to_output_protobuf(
+            &Value::Secret(Box::new(Value::String(Cow::Borrowed("s3cr3t")))),
+            &deps,
+        );
+        let Some(Kind::StructValue(ref obj)) = pb.kind else {
+            panic!("expected struct");
+        };
+        assert_eq!(
+            obj.fields[SIG_KEY].kind,
+            Some(Kind::StringValue(OUTPUT_SIG.to_string()))
+        );
+        assert_eq!(obj.fields["secret"].kind, Some(Kind::BoolValue(true)));
+        match &obj.fields["dependencies"].kind {
+            Some(Kind::ListValue(list)) => assert_eq!(list.values.len(), 1),
+            other => panic!("expected dependency list, got {:?}", other),
+        }
+        // Decoding drops dependencies but keeps the value and secretness.
+        assert_eq!(
+            protobuf_to_value(pb),
+            Value::Secret(Box::new(Value::String(Cow::Borrowed("s3cr3t"))))
+        );
+    }
+
+    #[test]
+    fn test_unknown_output_value_omits_value() {
+        let pb = value_to_output_protobuf(&Value::Unknown, &[]);
+        let Some(prost_types::value::Kind::StructValue(ref obj)) = pb.kind else {
+            panic!("expected struct");
+        };
+        assert!(!obj.fields.contains_key("value"));
+        assert_eq!(protobuf_to_value(pb), Value::Unknown);
+    }
+
     #[test]
     fn test_unknown_round_trip_is_stable() {
         // Verify Unknown survives multiple round trips
@@ -9,6 +9,7 @@
 
 use crate::ast::property::PropertyAccessor;
 use crate::diag::Diagnostics;
+use crate::eval::builtins::{eval_property_access, list_index};
 use crate::eval::value::Value;
 
 // ---------------------------------------------------------------------------
@@ -44,8 +45,9 @@ pub trait PropertyResolver<'src> {
 
 /// Zero-copy property resolver that returns references into the value tree.
 ///
-/// Returns `None` (without emitting diagnostics) if a `Secret` wrapper is
-/// encountered, signaling the caller to fall back to `OwnedResolver`.
+/// Returns `None` (without emitting diagnostics) if a `Secret` wrapper, a
+/// slice or a `*` projection is encountered, signaling the caller to fall
+/// back to `OwnedResolver`.
 pub struct BorrowedResolver<'src, 'a> {
     root: &'a Value<'src>,
 }
@@ -101,8 +103,9 @@ impl<'src, 'a> PropertyResolver<'src> for BorrowedResolver<'src, 'a> {
                 PropertyAccessor::IntSubscript(idx) => {
                     let i = *idx;
                     match current {
-                        Value::List(items) => {
-                            if i < 0 || (i as usize) >= items.len() {
+                        Value::List(items) => match list_index(i, items.len()) {
+                            Some(pos) => current = &items[pos],
+                            None => {
                                 diags.error(
                                     None,
                                     format!(
@@ -114,8 +117,7 @@ impl<'src, 'a> PropertyResolver<'src> for BorrowedResolver<'src, 'a> {
                                 );
                                 return None;
                             }
-                            current = &items[i as usize];
-                        }
+                        },
                         // Cannot resolve through secrets without cloning — signal fallback
                         Value::Secret(_) => return None,
                         Value::Null | Value::Unknown => return Some(current),
@@ -129,6 +131,12 @@ impl<'src, 'a> PropertyResolver<'src> for BorrowedResolver<'src, 'a> {
                         }
                     }
                 }
+                // Slices and projections build new values — signal fallback
+                PropertyAccessor::Slice(_, _) | PropertyAccessor::Wildcard => return None,
+                PropertyAccessor::PropertySubscript(inner) => {
+                    unresolved_key(inner, diags);
+                    return None;
+                }
             }
         }
 
@@ -221,8 +229,9 @@ fn resolve_owned<'src>(
             PropertyAccessor::IntSubscript(idx) => {
                 let i_val = *idx;
                 match current {
-                    Value::List(items) => {
-                        if i_val < 0 || (i_val as usize) >= items.len() {
+                    Value::List(items) => match list_index(i_val, items.len()) {
+                        Some(pos) => current = &items[pos],
+                        None => {
                             diags.error(
                                 None,
                                 format!(
@@ -234,8 +243,7 @@ fn resolve_owned<'src>(
                             );
                             return None;
                         }
-                        current = &items[i_val as usize];
-                    }
+                    },
                     Value::Secret(inner) => {
                         let result =
                             resolve_owned(inner, std::slice::from_ref(accessor), diags)?;
@@ -257,12 +265,29 @@ fn resolve_owned<'src>(
                     }
                 }
             }
+            PropertyAccessor::Slice(_, _) | PropertyAccessor::Wildcard => {
+                return eval_property_access(current, &accessors[i..], diags);
+            }
+            PropertyAccessor::PropertySubscript(inner) => {
+                unresolved_key(inner, diags);
+                return None;
+            }
         }
     }
 
     Some(current.clone())
 }
 
+/// Reports a nested `[a.b]` key that reached a resolver without first being
+/// resolved to a literal subscript by the evaluator.
+fn unresolved_key(inner: &crate::ast::property::PropertyAccess<'_>, diags: &mut Diagnostics) {
+    diags.error(
+        None,
+        format!("unresolved property access key [{}]", inner),
+        "nested keys must be resolved by the evaluator before property access",
+    );
+}
+
 // ---------------------------------------------------------------------------
 // Convenience: try-borrowed-then-owned resolution
 // ---------------------------------------------------------------------------
@@ -438,6 +463,37 @@ mod tests {
         }
     }
 
+    #[test]
+    fn resolve_property_negative_index_and_projection() {
+        let mut diags = Diagnostics::new();
+        let val = Value::List(vec![
+            Value::Object(vec![(Cow::Owned("id".into()), s("a"))]),
+            Value::Object(vec![(Cow::Owned("id".into()), s("b"))]),
+        ]);
+        let last = resolve_property(
+            &val,
+            &[
+                PropertyAccessor::IntSubscript(-1),
+                PropertyAccessor::Name(Cow::Borrowed("id")),
+            ],
+            &mut diags,
+        )
+        .unwrap();
+        assert_eq!(last.as_str(), Some("b"));
+
+        let ids = resolve_property(
+            &val,
+            &[
+                PropertyAccessor::Wildcard,
+                PropertyAccessor::Name(Cow::Borrowed("id")),
+            ],
+            &mut diags,
+        )
+        .unwrap();
+        assert_eq!(ids, Value::List(vec![s("a"), s("b")]));
+        assert!(!diags.has_errors());
+    }
+
     #[test]
     fn owned_index_oob() {
         let mut diags = Diagnostics::new();
@@ -487,7 +543,7 @@ mod tests {
     #[test]
     fn resolve_property_error_propagates() {
         let mut diags = Diagnostics::new();
-        let val = Value::Number(42.0);
+        let val = Value::Number(42.0.into());
         let result = resolve_property(
             &val,
             &[PropertyAccessor::Name(Cow::Borrowed("x"))],
@@ -58,6 +58,26 @@ impl Default for ResourceState {
     }
 }
 
+/// The instances registered for a resource entry with `count:` or `range:`,
+/// each named by its logical name, like `web[0]` or `web["east"]`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResourceGroup {
+    /// Instances by index, from `count:` or a `range:` list.
+    List(Vec<String>),
+    /// Instances by key, from a `range:` object.
+    Object(Vec<(String, String)>),
+}
+
+impl ResourceGroup {
+    /// The logical names of the instances, in order.
+    pub fn instances(&self) -> Vec<&str> {
+        match self {
+            ResourceGroup::List(names) => names.iter().map(String::as_str).collect(),
+            ResourceGroup::Object(entries) => entries.iter().map(|(_, n)| n.as_str()).collect(),
+        }
+    }
+}
+
 /// A resolved alias for a resource — either a URN string or a structured spec.
 #[derive(Debug, Clone, PartialEq)]
 pub enum ResolvedAlias {
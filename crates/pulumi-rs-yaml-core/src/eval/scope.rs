@@ -0,0 +1,95 @@
+//! Expression-local variable scopes.
+//!
+//! Builtins such as `fn::map` bind names (`${item}`, `${index}`) that are
+//! visible only while their body is evaluated. Bindings shadow config,
+//! variables, and resources of the same name.
+//!
+//! Like [`DepthGuard`](crate::eval::limits), scopes are tracked per thread:
+//! levels may be evaluated in parallel, but each expression tree is evaluated
+//! entirely on one thread. The file that declared the entry being evaluated
+//! is tracked the same way, for resolving relative paths.
+
+use std::cell::RefCell;
+
+use crate::eval::value::Value;
+
+thread_local! {
+    static SCOPES: RefCell<Vec<Vec<(String, Value<'static>)>>> = const { RefCell::new(Vec::new()) };
+    static SOURCE_FILE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// RAII guard for one scope of bindings on the current thread.
+pub(crate) struct ScopeGuard(());
+
+impl ScopeGuard {
+    /// Pushes a scope holding `bindings`; it is popped when the guard drops.
+    pub(crate) fn push(bindings: Vec<(String, Value<'static>)>) -> Self {
+        SCOPES.with(|s| s.borrow_mut().push(bindings));
+        ScopeGuard(())
+    }
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        SCOPES.with(|s| {
+            s.borrow_mut().pop();
+        });
+    }
+}
+
+/// RAII guard recording the file that declared the entry being evaluated on
+/// the current thread; the previous file is restored when the guard drops.
+pub(crate) struct SourceFileGuard(Option<String>);
+
+impl SourceFileGuard {
+    pub(crate) fn enter(file: Option<String>) -> Self {
+        SourceFileGuard(SOURCE_FILE.with(|f| f.replace(file)))
+    }
+}
+
+impl Drop for SourceFileGuard {
+    fn drop(&mut self) {
+        SOURCE_FILE.with(|f| *f.borrow_mut() = self.0.take());
+    }
+}
+
+/// Returns the file that declared the entry being evaluated on the current
+/// thread, if known.
+pub(crate) fn source_file() -> Option<String> {
+    SOURCE_FILE.with(|f| f.borrow().clone())
+}
+
+/// Looks up `name` in the scopes of the current thread, innermost first.
+pub(crate) fn lookup(name: &str) -> Option<Value<'static>> {
+    SCOPES.with(|s| {
+        s.borrow().iter().rev().find_map(|scope| {
+            scope
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.clone())
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_scope_shadowing_and_unwinding() {
+        assert!(lookup("item").is_none());
+        let outer = ScopeGuard::push(vec![
+            ("item".to_string(), Value::String(Cow::Borrowed("a"))),
+            ("index".to_string(), Value::Number(0.0.into())),
+        ]);
+        {
+            let _inner = ScopeGuard::push(vec![("item".to_string(), Value::Bool(true))]);
+            assert_eq!(lookup("item"), Some(Value::Bool(true)));
+            assert_eq!(lookup("index"), Some(Value::Number(0.0.into())));
+        }
+        assert_eq!(lookup("item"), Some(Value::String(Cow::Borrowed("a"))));
+        drop(outer);
+        assert!(lookup("index").is_none());
+    }
+}
@@ -0,0 +1,159 @@
+//! Stable `fn::uuid` and `fn::randomString` values.
+//!
+//! With [`Evaluator::stable_random`](super::evaluator::Evaluator::stable_random)
+//! set (the `stableRandom` runtime option), each call returns the value it
+//! returned in the previous deployment instead of a new one, like the
+//! `random` provider's resources but without declaring any. The language host
+//! stores the values in a secret stack output, [`OUTPUT_NAME`], and reads
+//! them back at the start of the next run with the engine's builtin
+//! [`READ_STACK_OUTPUTS`] invoke, so no resource is added to the stack.
+//! Values from the previous deployment that a run does not evaluate, e.g.
+//! with `--target`, are carried over.
+//!
+//! A call is identified by the entry it appears in and its position among the
+//! random calls evaluated for that entry, e.g. `dbPassword#0` or
+//! `outputs.token#1`. Renaming the entry or reordering its calls generates
+//! new values, and so does changing the length of a `fn::randomString`.
+//!
+//! Like [`scope`](super::scope), the entry being evaluated is tracked per
+//! thread.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::eval::value::Value;
+
+/// The stack output holding the values, as a secret map of call to value.
+pub const OUTPUT_NAME: &str = "pulumiYamlStableRandom";
+
+/// The builtin invoke returning a stack's outputs, the one `StackReference`
+/// reads through.
+pub const READ_STACK_OUTPUTS: &str = "pulumi:pulumi:readStackOutputs";
+
+thread_local! {
+    static ENTRY: RefCell<Option<(String, usize)>> = const { RefCell::new(None) };
+}
+
+/// RAII guard recording the entry being evaluated on the current thread; the
+/// previous entry is restored when the guard drops.
+pub(crate) struct EntryGuard(Option<(String, usize)>);
+
+impl EntryGuard {
+    pub(crate) fn enter(entry: String) -> Self {
+        EntryGuard(ENTRY.with(|e| e.replace(Some((entry, 0)))))
+    }
+}
+
+impl Drop for EntryGuard {
+    fn drop(&mut self) {
+        ENTRY.with(|e| *e.borrow_mut() = self.0.take());
+    }
+}
+
+/// Returns the key of the next random call in the entry being evaluated on
+/// the current thread, or `None` outside of an entry.
+pub(crate) fn next_key() -> Option<String> {
+    ENTRY.with(|e| {
+        let mut entry = e.borrow_mut();
+        let (name, calls) = entry.as_mut()?;
+        let key = format!("{}#{}", name, calls);
+        *calls += 1;
+        Some(key)
+    })
+}
+
+/// Extracts the stored values from the result of a [`READ_STACK_OUTPUTS`]
+/// invoke. Anything missing or malformed yields no values.
+pub fn values_from_stack_outputs(outputs: &HashMap<String, Value<'_>>) -> HashMap<String, String> {
+    let Some(Value::Object(stack_outputs)) = outputs.get("outputs").map(Value::unwrap_secret)
+    else {
+        return HashMap::new();
+    };
+    let Some((_, Value::Object(values))) = stack_outputs
+        .iter()
+        .find(|(k, _)| k == OUTPUT_NAME)
+        .map(|(k, v)| (k, v.unwrap_secret()))
+    else {
+        return HashMap::new();
+    };
+    values
+        .iter()
+        .filter_map(|(k, v)| Some((k.to_string(), v.unwrap_secret().as_str()?.to_string())))
+        .collect()
+}
+
+/// Returns the values to store: the `previous` ones, updated with the
+/// `current` ones this run evaluated.
+pub fn merge(
+    previous: Option<&HashMap<String, String>>,
+    current: BTreeMap<String, String>,
+) -> BTreeMap<String, String> {
+    let mut merged: BTreeMap<String, String> = previous
+        .into_iter()
+        .flatten()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    merged.extend(current);
+    merged
+}
+
+/// Returns the stack output storing `values`.
+pub fn to_output(values: &BTreeMap<String, String>) -> Value<'static> {
+    Value::Secret(Box::new(Value::Object(
+        values
+            .iter()
+            .map(|(k, v)| (Cow::Owned(k.clone()), Value::String(Cow::Owned(v.clone()))))
+            .collect(),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_follow_entries() {
+        assert_eq!(next_key(), None);
+        {
+            let _outer = EntryGuard::enter("password".to_string());
+            assert_eq!(next_key().as_deref(), Some("password#0"));
+            {
+                let _inner = EntryGuard::enter("outputs.token".to_string());
+                assert_eq!(next_key().as_deref(), Some("outputs.token#0"));
+            }
+            assert_eq!(next_key().as_deref(), Some("password#1"));
+        }
+        assert_eq!(next_key(), None);
+    }
+
+    #[test]
+    fn test_stack_outputs_round_trip() {
+        let values = BTreeMap::from([
+            ("password#0".to_string(), "s3cr3t".to_string()),
+            ("id#0".to_string(), "0d5c...".to_string()),
+        ]);
+        let outputs = HashMap::from([(
+            "outputs".to_string(),
+            Value::Object(vec![(Cow::Borrowed(OUTPUT_NAME), to_output(&values))]),
+        )]);
+        let read = values_from_stack_outputs(&outputs);
+        assert_eq!(read.len(), 2);
+        assert_eq!(read["password#0"], "s3cr3t");
+
+        assert!(values_from_stack_outputs(&HashMap::new()).is_empty());
+    }
+
+    #[test]
+    fn test_merge_keeps_values_not_evaluated() {
+        let previous = HashMap::from([
+            ("password#0".to_string(), "old".to_string()),
+            ("token#0".to_string(), "kept".to_string()),
+        ]);
+        let current = BTreeMap::from([("password#0".to_string(), "new".to_string())]);
+        let merged = merge(Some(&previous), current);
+        assert_eq!(merged["password#0"], "new");
+        assert_eq!(merged["token#0"], "kept");
+        assert_eq!(merge(None, BTreeMap::new()).len(), 0);
+    }
+}
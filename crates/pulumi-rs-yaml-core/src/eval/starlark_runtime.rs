@@ -331,14 +331,11 @@ fn value_to_starlark<'v>(val: &Value<'_>, heap: &'v starlark::values::Heap) -> S
     match val {
         Value::Null => StarlarkValue::new_none(),
         Value::Bool(b) => StarlarkValue::new_bool(*b),
-        Value::Number(n) => {
-            // If the number is an exact integer, allocate as i64 for starlark's int type
-            if n.fract() == 0.0 && n.is_finite() && *n >= i64::MIN as f64 && *n <= i64::MAX as f64 {
-                heap.alloc(*n as i64)
-            } else {
-                heap.alloc(*n)
-            }
-        }
+        // Exact integers become starlark ints
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => heap.alloc(i),
+            None => heap.alloc(n.as_f64()),
+        },
         Value::String(s) => heap.alloc_str(s.as_ref()).to_value(),
         Value::List(items) => {
             let converted: Vec<StarlarkValue<'v>> =
@@ -463,18 +460,18 @@ mod tests {
         let rt = StarlarkRuntime::compile(&funcs, &mut diags);
 
         let input = Value::List(vec![
-            Value::Number(1.0),
-            Value::Number(2.0),
-            Value::Number(3.0),
+            Value::Number(1.0.into()),
+            Value::Number(2.0.into()),
+            Value::Number(3.0.into()),
         ]);
         let result = rt.call("double", &input, &mut diags);
         assert!(!diags.has_errors());
         assert_eq!(
             result,
             Some(Value::List(vec![
-                Value::Number(2.0),
-                Value::Number(4.0),
-                Value::Number(6.0),
+                Value::Number(2.0.into()),
+                Value::Number(4.0.into()),
+                Value::Number(6.0.into()),
             ]))
         );
     }
@@ -546,7 +543,7 @@ mod tests {
         let rt = StarlarkRuntime::compile(&funcs, &mut diags);
         assert!(!diags.has_errors());
 
-        let input = Value::Number(42.0);
+        let input = Value::Number(42.0.into());
         let result = rt.call("divide", &input, &mut diags);
         assert!(diags.has_errors());
         assert!(result.is_none());
@@ -578,8 +575,8 @@ mod tests {
         let mut diags = Diagnostics::new();
         let rt = StarlarkRuntime::compile(&funcs, &mut diags);
 
-        let result = rt.call("identity", &Value::Number(42.0), &mut diags);
-        assert_eq!(result, Some(Value::Number(42.0)));
+        let result = rt.call("identity", &Value::Number(42.0.into()), &mut diags);
+        assert_eq!(result, Some(Value::Number(42.0.into())));
     }
 
     #[test]
@@ -603,7 +600,7 @@ mod tests {
         let rt = StarlarkRuntime::compile(&funcs, &mut diags);
 
         let input = Value::List(vec![
-            Value::Number(1.0),
+            Value::Number(1.0.into()),
             Value::String(Cow::Borrowed("two")),
             Value::Bool(true),
         ]);
@@ -612,7 +609,7 @@ mod tests {
         match result {
             Some(Value::List(items)) => {
                 assert_eq!(items.len(), 3);
-                assert_eq!(items[0], Value::Number(1.0));
+                assert_eq!(items[0], Value::Number(1.0.into()));
             }
             other => panic!("expected List, got {:?}", other),
         }
@@ -0,0 +1,168 @@
+//! Evaluation timing.
+//!
+//! The evaluator times every entry it evaluates and splits the wall time into
+//! time spent waiting on the engine (register, read and invoke RPCs) and the
+//! rest, which is expression evaluation. The resulting [`TimingReport`] is
+//! passed to the evaluator's [`ProgressSink`](super::evaluator::ProgressSink)
+//! once evaluation finishes, and with
+//! [`Evaluator::timing_summary`](super::evaluator::Evaluator::timing_summary)
+//! set it is also reported as a warning listing the slowest entries.
+
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+thread_local! {
+    static RPC_TIME: Cell<Duration> = const { Cell::new(Duration::ZERO) };
+}
+
+/// Runs an engine RPC, adding its wall time to the current thread's RPC time.
+pub(crate) fn time_rpc<T>(rpc: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = rpc();
+    RPC_TIME.with(|t| t.set(t.get() + start.elapsed()));
+    result
+}
+
+/// Returns and resets the current thread's RPC time.
+pub(crate) fn take_rpc_time() -> Duration {
+    RPC_TIME.with(|t| t.replace(Duration::ZERO))
+}
+
+/// The time spent evaluating one entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeTiming {
+    pub name: String,
+    /// `config`, `variable`, `invoke` (a variable whose value is an
+    /// `fn::invoke`), or `resource`.
+    pub kind: &'static str,
+    /// The topological level the entry was evaluated in.
+    pub level: usize,
+    /// Wall time.
+    pub total: Duration,
+    /// The part of `total` spent waiting on engine RPCs.
+    pub rpc: Duration,
+}
+
+impl NodeTiming {
+    /// The part of `total` spent evaluating expressions.
+    pub fn eval(&self) -> Duration {
+        self.total.saturating_sub(self.rpc)
+    }
+}
+
+/// Timings of one evaluation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TimingReport {
+    /// Wall time of each topological level, in order.
+    pub levels: Vec<Duration>,
+    /// Every evaluated entry, in the order they finished.
+    pub nodes: Vec<NodeTiming>,
+}
+
+impl TimingReport {
+    /// Wall time of all levels.
+    pub fn total(&self) -> Duration {
+        self.levels.iter().sum()
+    }
+
+    /// The `n` slowest resources and invokes, slowest first.
+    pub fn slowest(&self, n: usize) -> Vec<&NodeTiming> {
+        let mut nodes: Vec<&NodeTiming> = self
+            .nodes
+            .iter()
+            .filter(|node| matches!(node.kind, "resource" | "invoke"))
+            .collect();
+        nodes.sort_by(|a, b| b.total.cmp(&a.total).then_with(|| a.name.cmp(&b.name)));
+        nodes.truncate(n);
+        nodes
+    }
+
+    /// Renders the slowest level and the `n` slowest resources and invokes,
+    /// one per line.
+    pub fn render(&self, n: usize) -> String {
+        let mut out = format!(
+            "evaluated {} entries in {} levels in {}",
+            self.nodes.len(),
+            self.levels.len(),
+            millis(self.total())
+        );
+        let slowest_level = self
+            .levels
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(&a.0)));
+        if let Some((level, time)) = slowest_level {
+            let count = self.nodes.iter().filter(|n| n.level == level).count();
+            out.push_str(&format!(
+                "\n  slowest level: {} ({} entries) took {}",
+                level,
+                count,
+                millis(*time)
+            ));
+        }
+        for node in self.slowest(n) {
+            out.push_str(&format!(
+                "\n  {} {} (level {}): {} ({} eval, {} rpc)",
+                node.kind,
+                node.name,
+                node.level,
+                millis(node.total),
+                millis(node.eval()),
+                millis(node.rpc)
+            ));
+        }
+        out
+    }
+}
+
+fn millis(d: Duration) -> String {
+    format!("{:.1}ms", d.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, kind: &'static str, level: usize, total: u64, rpc: u64) -> NodeTiming {
+        NodeTiming {
+            name: name.to_string(),
+            kind,
+            level,
+            total: Duration::from_millis(total),
+            rpc: Duration::from_millis(rpc),
+        }
+    }
+
+    #[test]
+    fn test_render_slowest() {
+        let report = TimingReport {
+            levels: vec![Duration::from_millis(5), Duration::from_millis(120)],
+            nodes: vec![
+                node("region", "config", 0, 5, 0),
+                node("bucket", "resource", 1, 120, 100),
+                node("ami", "invoke", 1, 80, 75),
+                node("queue", "resource", 1, 10, 9),
+            ],
+        };
+        let names: Vec<&str> = report.slowest(2).iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, vec!["bucket", "ami"]);
+        assert_eq!(
+            report.render(2),
+            "evaluated 4 entries in 2 levels in 125.0ms\n  \
+             slowest level: 1 (3 entries) took 120.0ms\n  \
+             resource bucket (level 1): 120.0ms (20.0ms eval, 100.0ms rpc)\n  \
+             invoke ami (level 1): 80.0ms (5.0ms eval, 75.0ms rpc)"
+        );
+    }
+
+    #[test]
+    fn test_rpc_time_is_per_thread() {
+        take_rpc_time();
+        time_rpc(|| std::thread::sleep(Duration::from_millis(2)));
+        std::thread::spawn(|| assert_eq!(take_rpc_time(), Duration::ZERO))
+            .join()
+            .unwrap();
+        assert!(take_rpc_time() >= Duration::from_millis(2));
+        assert_eq!(take_rpc_time(), Duration::ZERO);
+    }
+}
@@ -1,6 +1,10 @@
 use std::borrow::Cow;
 use std::fmt;
 
+use base64::Engine;
+
+pub use crate::number::Number;
+
 /// Runtime value during evaluation. Replaces Go's `interface{}`.
 ///
 /// Uses `Cow<'src, str>` so values can borrow from source text or own data
@@ -9,8 +13,11 @@ use std::fmt;
 pub enum Value<'src> {
     Null,
     Bool(bool),
-    Number(f64),
+    Number(Number),
     String(Cow<'src, str>),
+    /// Raw bytes, such as a binary file read with `fn::readFileBinary`.
+    /// Marshaled as a base64 string.
+    Bytes(Vec<u8>),
     List(Vec<Value<'src>>),
     Object(Vec<(Cow<'src, str>, Value<'src>)>),
     Secret(Box<Value<'src>>),
@@ -32,7 +39,9 @@ pub struct ResourceRef(pub u32);
 #[derive(Debug, Clone, PartialEq)]
 pub enum Asset<'src> {
     String(Cow<'src, str>),
-    File(Cow<'src, str>),
+    /// A file path, with the SHA-256 of its contents once it has been read
+    /// (see [`crate::eval::assets`]).
+    File(Cow<'src, str>, Option<String>),
     Remote(Cow<'src, str>),
 }
 
@@ -51,6 +60,7 @@ impl fmt::Debug for Value<'_> {
             Value::Bool(b) => f.debug_tuple("Bool").field(b).finish(),
             Value::Number(n) => f.debug_tuple("Number").field(n).finish(),
             Value::String(s) => f.debug_tuple("String").field(s).finish(),
+            Value::Bytes(b) => write!(f, "Bytes({} bytes)", b.len()),
             Value::List(items) => f.debug_tuple("List").field(items).finish(),
             Value::Object(entries) => f.debug_tuple("Object").field(entries).finish(),
             Value::Secret(_) => write!(f, "Secret([REDACTED])"),
@@ -86,6 +96,14 @@ impl<'src> Value<'src> {
         }
     }
 
+    /// Tries to get the value as raw bytes.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+
     /// Tries to get the value as a bool.
     pub fn as_bool(&self) -> Option<bool> {
         match self {
@@ -97,7 +115,15 @@ impl<'src> Value<'src> {
     /// Tries to get the value as a f64.
     pub fn as_number(&self) -> Option<f64> {
         match self {
-            Value::Number(n) => Some(*n),
+            Value::Number(n) => Some(n.as_f64()),
+            _ => None,
+        }
+    }
+
+    /// Tries to get the value as an integer.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Number(n) => n.as_i64(),
             _ => None,
         }
     }
@@ -117,6 +143,7 @@ impl<'src> Value<'src> {
             Value::Bool(b) => Value::Bool(b),
             Value::Number(n) => Value::Number(n),
             Value::String(s) => Value::String(Cow::Owned(s.into_owned())),
+            Value::Bytes(b) => Value::Bytes(b),
             Value::List(items) => Value::List(items.into_iter().map(|v| v.into_owned()).collect()),
             Value::Object(entries) => Value::Object(
                 entries
@@ -128,7 +155,7 @@ impl<'src> Value<'src> {
             Value::Resource(r) => Value::Resource(r),
             Value::Asset(a) => Value::Asset(match a {
                 Asset::String(s) => Asset::String(Cow::Owned(s.into_owned())),
-                Asset::File(s) => Asset::File(Cow::Owned(s.into_owned())),
+                Asset::File(s, hash) => Asset::File(Cow::Owned(s.into_owned()), hash),
                 Asset::Remote(s) => Asset::Remote(Cow::Owned(s.into_owned())),
             }),
             Value::Archive(a) => Value::Archive(match a {
@@ -150,7 +177,7 @@ impl<'src> Value<'src> {
         match v {
             serde_json::Value::Null => Value::Null,
             serde_json::Value::Bool(b) => Value::Bool(*b),
-            serde_json::Value::Number(n) => Value::Number(n.as_f64().unwrap_or(0.0)),
+            serde_json::Value::Number(n) => Value::Number(Number::from_json(n)),
             serde_json::Value::String(s) => Value::String(Cow::Owned(s.clone())),
             serde_json::Value::Array(arr) => {
                 Value::List(arr.iter().map(Value::from_json).collect())
@@ -169,7 +196,7 @@ impl<'src> Value<'src> {
         match v {
             serde_json::Value::Null => Value::Null,
             serde_json::Value::Bool(b) => Value::Bool(b),
-            serde_json::Value::Number(n) => Value::Number(n.as_f64().unwrap_or(0.0)),
+            serde_json::Value::Number(n) => Value::Number(Number::from_json(&n)),
             serde_json::Value::String(s) => Value::String(Cow::Owned(s)),
             serde_json::Value::Array(arr) => {
                 Value::List(arr.into_iter().map(Value::from_json_owned).collect())
@@ -187,10 +214,12 @@ impl<'src> Value<'src> {
         match self {
             Value::Null => serde_json::Value::Null,
             Value::Bool(b) => serde_json::Value::Bool(*b),
-            Value::Number(n) => serde_json::Number::from_f64(*n)
+            Value::Number(n) => n
+                .to_json()
                 .map(serde_json::Value::Number)
                 .unwrap_or(serde_json::Value::Null),
             Value::String(s) => serde_json::Value::String(s.to_string()),
+            Value::Bytes(b) => serde_json::Value::String(encode_base64(b)),
             Value::List(items) => {
                 serde_json::Value::Array(items.iter().map(|v| v.to_json()).collect())
             }
@@ -214,6 +243,7 @@ impl<'src> Value<'src> {
             Value::Bool(_) => "bool",
             Value::Number(_) => "number",
             Value::String(_) => "string",
+            Value::Bytes(_) => "bytes",
             Value::List(_) => "list",
             Value::Object(_) => "object",
             Value::Secret(_) => "secret",
@@ -232,6 +262,7 @@ impl fmt::Display for Value<'_> {
             Value::Bool(b) => write!(f, "{}", b),
             Value::Number(n) => write!(f, "{}", n),
             Value::String(s) => write!(f, "{}", s),
+            Value::Bytes(b) => write!(f, "{}", encode_base64(b)),
             Value::List(items) => {
                 write!(f, "[")?;
                 for (i, item) in items.iter().enumerate() {
@@ -261,6 +292,12 @@ impl fmt::Display for Value<'_> {
     }
 }
 
+/// Encodes bytes as standard, padded base64: the form [`Value::Bytes`] takes
+/// wherever a value has to be text, such as JSON, interpolation, and gRPC.
+pub fn encode_base64(bytes: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -339,7 +376,7 @@ mod tests {
         // Ensure non-secret values still show correctly in Debug
         assert!(format!("{:?}", Value::Null).contains("Null"));
         assert!(format!("{:?}", Value::Bool(true)).contains("true"));
-        assert!(format!("{:?}", Value::Number(42.0)).contains("42"));
+        assert!(format!("{:?}", Value::Number(42.0.into())).contains("42"));
         assert!(format!("{:?}", Value::String(Cow::Borrowed("hi"))).contains("hi"));
         assert!(format!("{:?}", Value::Unknown).contains("Unknown"));
     }
@@ -348,7 +385,7 @@ mod tests {
     fn test_value_display() {
         assert_eq!(Value::Null.to_string(), "null");
         assert_eq!(Value::Bool(true).to_string(), "true");
-        assert_eq!(Value::Number(42.0).to_string(), "42");
+        assert_eq!(Value::Number(42.0.into()).to_string(), "42");
         assert_eq!(Value::String(Cow::Borrowed("hi")).to_string(), "hi");
         assert_eq!(Value::Unknown.to_string(), "[unknown]");
     }
@@ -0,0 +1,593 @@
+//! Source formatting for templates.
+//!
+//! [`format_template`] rewrites a template's source into one consistent
+//! style while keeping its comments and everything it does not normalize
+//! byte for byte:
+//!
+//! - invokes can all be written as `fn::invoke` long form, or as
+//!   `fn::<token>` shorthand where the long form has nothing but `function`
+//!   and `arguments`,
+//! - the properties of each resource can be sorted by name.
+//!
+//! The AST has no source positions, so the rewrites work on the
+//! [`crate::outline`] of the source. Invokes written in flow style are left
+//! as they are. Formatting is idempotent: formatting
+//! formatted source changes nothing.
+
+use crate::ast::parse::is_invoke_shorthand;
+use crate::outline::{indent_of, inline_value, key_of, Outline};
+
+/// How invokes are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InvokeStyle {
+    /// Leave each invoke as written.
+    #[default]
+    Preserve,
+    /// `fn::invoke` with `function` and `arguments`.
+    LongForm,
+    /// `fn::<token>` with the arguments as its value. Invokes with `options`
+    /// or `return` have no shorthand and stay in long form.
+    Shorthand,
+}
+
+/// How the properties of a resource are ordered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PropertyOrder {
+    /// Leave the properties in the order they are written.
+    #[default]
+    Declaration,
+    /// Sort the properties by name. Comments above a property move with it.
+    Alphabetical,
+}
+
+/// What [`format_template`] normalizes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FormatOptions {
+    pub invokes: InvokeStyle,
+    pub property_order: PropertyOrder,
+}
+
+/// Formats a template's source.
+pub fn format_template(source: &str, options: &FormatOptions) -> String {
+    let newline = if source.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut lines: Vec<String> = source.lines().map(str::to_string).collect();
+    if options.invokes != InvokeStyle::Preserve {
+        let step = indent_step(&lines);
+        // Each pass rewrites the outermost invokes, so nested invokes are
+        // reached by later passes.
+        loop {
+            let next = rewrite_invokes(&lines, options.invokes, step);
+            if next == lines {
+                break;
+            }
+            lines = next;
+        }
+    }
+    if options.property_order == PropertyOrder::Alphabetical {
+        lines = sort_properties(&lines);
+    }
+    let mut formatted = lines.join(newline);
+    if source.ends_with('\n') {
+        formatted.push_str(newline);
+    }
+    formatted
+}
+
+/// The indentation one level adds in the source, two spaces if it has no
+/// indented lines.
+fn indent_step(lines: &[String]) -> usize {
+    lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| indent_of(line))
+        .find(|&indent| indent > 0)
+        .unwrap_or(2)
+}
+
+/// A line holding a mapping key, possibly after list item dashes.
+struct KeyLine<'s> {
+    /// The indentation and dashes before the key.
+    prefix: &'s str,
+    /// The column the key starts at.
+    column: usize,
+    key: &'s str,
+    /// The text after the key's colon, trailing comment included.
+    rest: &'s str,
+    /// Whether the key starts a list item.
+    item: bool,
+}
+
+impl<'s> KeyLine<'s> {
+    fn parse(text: &'s str) -> Option<Self> {
+        let mut column = indent_of(text);
+        let mut item = false;
+        while let Some(after) = text[column..].strip_prefix("- ") {
+            column = text.len() - after.trim_start_matches(' ').len();
+            item = true;
+        }
+        let body = &text[column..];
+        let key = key_of(body)?;
+        let colon = body.find(": ").unwrap_or_else(|| body.trim_end().len() - 1);
+        Some(Self {
+            prefix: &text[..column],
+            column,
+            key,
+            rest: body[colon + 1..].trim(),
+            item,
+        })
+    }
+
+    /// Whether the value is a `|` or `>` block scalar.
+    fn starts_block_scalar(&self) -> bool {
+        self.rest.starts_with(['|', '>'])
+    }
+
+    /// The value and the trailing comment written after the key.
+    fn value_and_comment(&self) -> (&'s str, &'s str) {
+        if self.rest.starts_with('#') {
+            return ("", self.rest);
+        }
+        let value = inline_value(self.rest);
+        (value, self.rest[value.len()..].trim_start())
+    }
+}
+
+fn is_blank(text: &str) -> bool {
+    let trimmed = text.trim_start();
+    trimmed.is_empty() || trimmed.starts_with('#')
+}
+
+/// The end of the lines nested under the key on line `at`, excluding
+/// trailing blank lines.
+fn block_end(lines: &[String], at: usize, column: usize) -> usize {
+    let mut end = at + 1;
+    while end < lines.len() && (lines[end].trim().is_empty() || indent_of(&lines[end]) > column) {
+        end += 1;
+    }
+    while end > at + 1 && lines[end - 1].trim().is_empty() {
+        end -= 1;
+    }
+    end
+}
+
+/// Whether the key on line `at`, whose value ends before `end`, is the only
+/// key of its mapping, as an expression's key must be.
+fn is_only_key(lines: &[String], at: usize, end: usize, key: &KeyLine<'_>) -> bool {
+    let sibling = |text: &String| {
+        KeyLine::parse(text).is_some_and(|other| other.column == key.column && !other.item)
+    };
+    if let Some(next) = lines[end..].iter().find(|text| !is_blank(text)) {
+        if sibling(next) {
+            return false;
+        }
+    }
+    if key.item {
+        return true;
+    }
+    for text in lines[..at].iter().rev().filter(|text| !is_blank(text)) {
+        if KeyLine::parse(text).is_some_and(|other| other.column == key.column) {
+            return false;
+        }
+        if indent_of(text) < key.column {
+            break;
+        }
+    }
+    true
+}
+
+/// Rewrites the outermost invokes not yet in `style`.
+fn rewrite_invokes(lines: &[String], style: InvokeStyle, step: usize) -> Vec<String> {
+    let mut out = Vec::with_capacity(lines.len());
+    // The column a block scalar's content is indented past.
+    let mut block_scalar = None;
+    let mut n = 0;
+    while n < lines.len() {
+        let text = &lines[n];
+        if let Some(column) = block_scalar {
+            if text.trim().is_empty() || indent_of(text) > column {
+                out.push(text.clone());
+                n += 1;
+                continue;
+            }
+            block_scalar = None;
+        }
+        if let Some(key) = KeyLine::parse(text) {
+            let rewritten = match style {
+                InvokeStyle::Preserve => None,
+                InvokeStyle::LongForm => to_long_form(lines, n, &key, step),
+                InvokeStyle::Shorthand => to_shorthand(lines, n, &key),
+            };
+            if let Some((replacement, end)) = rewritten {
+                out.extend(replacement);
+                n = end;
+                continue;
+            }
+            if key.starts_block_scalar() {
+                block_scalar = Some(key.column);
+            }
+        }
+        out.push(text.clone());
+        n += 1;
+    }
+    out
+}
+
+/// Rewrites a `fn::<token>` shorthand on line `at` as `fn::invoke`,
+/// returning the new lines and the line after the invoke.
+fn to_long_form(
+    lines: &[String],
+    at: usize,
+    key: &KeyLine<'_>,
+    step: usize,
+) -> Option<(Vec<String>, usize)> {
+    if !is_invoke_shorthand(key.key) {
+        return None;
+    }
+    let end = block_end(lines, at, key.column);
+    if !is_only_key(lines, at, end, key) {
+        return None;
+    }
+    let (value, comment) = key.value_and_comment();
+    let pad = " ".repeat(key.column + step);
+    let mut out = vec![
+        with_comment(format!("{}fn::invoke:", key.prefix), comment),
+        format!("{}function: {}", pad, &key.key[4..]),
+    ];
+    if !value.is_empty() {
+        out.push(format!("{}arguments: {}", pad, value));
+    } else if end > at + 1 {
+        out.push(format!("{}arguments:", pad));
+    }
+    let shift = " ".repeat(step);
+    out.extend(lines[at + 1..end].iter().map(|line| {
+        if line.trim().is_empty() {
+            line.clone()
+        } else {
+            format!("{}{}", shift, line)
+        }
+    }));
+    Some((out, end))
+}
+
+/// Rewrites a `fn::invoke` on line `at` that has only `function` and
+/// `arguments` as `fn::<token>` shorthand, returning the new lines and the
+/// line after the invoke.
+fn to_shorthand(lines: &[String], at: usize, key: &KeyLine<'_>) -> Option<(Vec<String>, usize)> {
+    if key.key != "fn::invoke" {
+        return None;
+    }
+    let (value, comment) = key.value_and_comment();
+    if !value.is_empty() {
+        return None;
+    }
+    let end = block_end(lines, at, key.column);
+    if end == at + 1 || !is_only_key(lines, at, end, key) {
+        return None;
+    }
+
+    // The children must be a `function` and an optional `arguments`, with no
+    // comments between them that the shorthand would have nowhere to keep.
+    let children = lines[..end].iter().enumerate().skip(at + 1);
+    let child_column = indent_of(children.clone().find(|(_, text)| !is_blank(text))?.1);
+    let mut function = None;
+    let mut arguments = None;
+    for (n, text) in children {
+        if text.trim().is_empty() || indent_of(text) > child_column {
+            continue;
+        }
+        if indent_of(text) < child_column || text.trim_start().starts_with('#') {
+            return None;
+        }
+        let child = KeyLine::parse(text).filter(|child| !child.item)?;
+        match child.key {
+            "function" if function.is_none() => function = Some(child),
+            "arguments" if arguments.is_none() => arguments = Some((n, child)),
+            _ => return None,
+        }
+    }
+    let function = function?;
+    let (token, function_comment) = function.value_and_comment();
+    let token = token.trim_matches(|c| c == '"' || c == '\'');
+    let shorthand = format!("fn::{}", token);
+    if !function_comment.is_empty() || !is_invoke_shorthand(&shorthand) || token.contains(": ") {
+        return None;
+    }
+
+    let mut out = Vec::new();
+    match arguments {
+        None => out.push(with_comment(
+            format!("{}{}:", key.prefix, shorthand),
+            comment,
+        )),
+        Some((n, arguments)) => {
+            let (value, arguments_comment) = arguments.value_and_comment();
+            if !comment.is_empty() && !arguments_comment.is_empty() {
+                return None;
+            }
+            let comment = if comment.is_empty() {
+                arguments_comment
+            } else {
+                comment
+            };
+            let line = if value.is_empty() {
+                format!("{}{}:", key.prefix, shorthand)
+            } else {
+                format!("{}{}: {}", key.prefix, shorthand, value)
+            };
+            out.push(with_comment(line, comment));
+            let nested_end = block_end(lines, n, child_column);
+            let shift = child_column - key.column;
+            out.extend(lines[n + 1..nested_end].iter().map(|line| {
+                let remove = indent_of(line).min(shift);
+                line[remove..].to_string()
+            }));
+        }
+    }
+    Some((out, end))
+}
+
+fn with_comment(line: String, comment: &str) -> String {
+    if comment.is_empty() {
+        line
+    } else {
+        format!("{} {}", line, comment)
+    }
+}
+
+/// Sorts the properties of each resource by name, moving the comments and
+/// blank lines above a property with it.
+fn sort_properties(lines: &[String]) -> Vec<String> {
+    let source = lines.join("\n");
+    let outline = Outline::new(&source);
+    let mut sorted = lines.to_vec();
+    let top = outline.entries(None);
+    let Some(resources) = outline.find(&top, "resources") else {
+        return sorted;
+    };
+    for resource in outline.entries(Some(&resources)) {
+        let Some(properties) = outline.find(&outline.entries(Some(&resource)), "properties") else {
+            continue;
+        };
+        if !properties.value.is_empty() {
+            continue;
+        }
+        let mut start = properties.line + 1;
+        let mut blocks = Vec::new();
+        for property in outline.entries(Some(&properties)) {
+            blocks.push((property.key, start..property.last_line + 1));
+            start = property.last_line + 1;
+        }
+        blocks.sort_by_key(|(key, _)| *key);
+        let reordered: Vec<String> = blocks
+            .into_iter()
+            .flat_map(|(_, block)| lines[block].iter().cloned())
+            .collect();
+        sorted.splice(properties.line + 1..start, reordered);
+    }
+    sorted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::parse::parse_template;
+
+    fn options(invokes: InvokeStyle, property_order: PropertyOrder) -> FormatOptions {
+        FormatOptions {
+            invokes,
+            property_order,
+        }
+    }
+
+    /// Formats `source`, checking that formatting is idempotent and that the
+    /// result still parses.
+    fn format(source: &str, options: FormatOptions) -> String {
+        let formatted = format_template(source, &options);
+        assert_eq!(
+            format_template(&formatted, &options),
+            formatted,
+            "formatting is not idempotent:\n{}",
+            formatted
+        );
+        let (_, diags) = parse_template(&formatted, None);
+        assert!(!diags.has_errors(), "{}\n{}", formatted, diags);
+        formatted
+    }
+
+    /// The parsed variables of a template, ignoring how invokes are written.
+    /// The variables of `source`, leaving out where they are written.
+    fn variables_of(source: &str) -> String {
+        let (template, _) = parse_template(source, None);
+        let debug =
+            format!("{:?}", template.variables).replace("shorthand: true", "shorthand: false");
+        regex::Regex::new(r"span: Some\(Span \{[^}]*\}\)")
+            .unwrap()
+            .replace_all(&debug, "span: None")
+            .into_owned()
+    }
+
+    const SHORTHAND: &str = "\
+name: test
+runtime: yaml
+variables:
+  # The newest AMI.
+  ami:
+    fn::aws:ec2:getAmi:
+      owners:
+        - amazon
+      mostRecent: true
+  zones:
+    fn::aws:getAvailabilityZones: {} # every zone
+  caller:
+    fn::aws:getCallerIdentity:
+  nested:
+    fn::std:join:
+      separator: ','
+      input:
+        - fn::std:upper:
+            input: a
+  script:
+    fn::std:trim:
+      input: |
+        fn::not:an:invoke:
+          text
+";
+
+    #[test]
+    fn test_preserve_changes_nothing() {
+        assert_eq!(format(SHORTHAND, FormatOptions::default()), SHORTHAND);
+    }
+
+    #[test]
+    fn test_shorthand_to_long_form() {
+        let long = format(
+            SHORTHAND,
+            options(InvokeStyle::LongForm, PropertyOrder::Declaration),
+        );
+        assert_eq!(
+            long,
+            "\
+name: test
+runtime: yaml
+variables:
+  # The newest AMI.
+  ami:
+    fn::invoke:
+      function: aws:ec2:getAmi
+      arguments:
+        owners:
+          - amazon
+        mostRecent: true
+  zones:
+    fn::invoke: # every zone
+      function: aws:getAvailabilityZones
+      arguments: {}
+  caller:
+    fn::invoke:
+      function: aws:getCallerIdentity
+  nested:
+    fn::invoke:
+      function: std:join
+      arguments:
+        separator: ','
+        input:
+          - fn::invoke:
+              function: std:upper
+              arguments:
+                input: a
+  script:
+    fn::invoke:
+      function: std:trim
+      arguments:
+        input: |
+          fn::not:an:invoke:
+            text
+"
+        );
+        assert_eq!(variables_of(&long), variables_of(SHORTHAND));
+    }
+
+    #[test]
+    fn test_long_form_to_shorthand_round_trips() {
+        let shorthand_options = options(InvokeStyle::Shorthand, PropertyOrder::Declaration);
+        let long = format(
+            SHORTHAND,
+            options(InvokeStyle::LongForm, PropertyOrder::Declaration),
+        );
+        let short = format(&long, shorthand_options);
+        assert_eq!(short, SHORTHAND);
+        assert_eq!(format(SHORTHAND, shorthand_options), SHORTHAND);
+    }
+
+    #[test]
+    fn test_invokes_with_options_stay_long_form() {
+        let source = "\
+variables:
+  ami:
+    fn::invoke:
+      function: aws:ec2:getAmi
+      arguments:
+        owners: [amazon]
+      return: id
+  region:
+    fn::invoke:
+      # The provider's region.
+      function: aws:getRegion
+  pair:
+    fn::invoke:
+      function: aws:getRegion
+    other: true
+";
+        assert_eq!(
+            format(
+                source,
+                options(InvokeStyle::Shorthand, PropertyOrder::Declaration)
+            ),
+            source
+        );
+    }
+
+    #[test]
+    fn test_sort_properties() {
+        let source = "\
+resources:
+  bucket:
+    type: aws:s3:Bucket
+    properties:
+      # Tags go last.
+      tags:
+        env: dev
+      bucketPrefix: logs-
+      acl: private
+    options:
+      protect: true
+  other:
+    type: aws:s3:Bucket
+    properties: {}
+";
+        let sorted = format(
+            source,
+            options(InvokeStyle::Preserve, PropertyOrder::Alphabetical),
+        );
+        assert_eq!(
+            sorted,
+            "\
+resources:
+  bucket:
+    type: aws:s3:Bucket
+    properties:
+      acl: private
+      bucketPrefix: logs-
+      # Tags go last.
+      tags:
+        env: dev
+    options:
+      protect: true
+  other:
+    type: aws:s3:Bucket
+    properties: {}
+"
+        );
+        assert_eq!(
+            format(source, FormatOptions::default()),
+            source,
+            "declaration order keeps the properties as written"
+        );
+    }
+
+    #[test]
+    fn test_format_keeps_crlf() {
+        let source = "variables:\r\n  ami:\r\n    fn::aws:getAmi:\r\n      owners: [amazon]\r\n";
+        assert_eq!(
+            format(
+                source,
+                options(InvokeStyle::LongForm, PropertyOrder::Alphabetical)
+            ),
+            "variables:\r\n  ami:\r\n    fn::invoke:\r\n      function: aws:getAmi\r\n      arguments:\r\n        owners: [amazon]\r\n"
+        );
+    }
+}
@@ -4,13 +4,25 @@
 //! - `NoopPreprocessor`: zero-cost passthrough (returns `&'src str`)
 //! - `JinjaPreprocessor`: renders Jinja2 syntax via `minijinja`, returning
 //!   `Cow::Borrowed` when no Jinja syntax is detected (zero-copy fast path)
+//!
+//! Rendering, i.e. `JinjaPreprocessor` and `validate_jinja_syntax`, needs the
+//! `jinja` feature. The context types, the passthrough scanner, block
+//! stripping and rendered-YAML validation are always available.
 
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fmt;
+#[cfg(feature = "jinja")]
 use std::path::Path;
+#[cfg(feature = "jinja")]
 use std::sync::{Arc, Mutex};
 
+use crate::ast::template::DuplicateKeys;
+#[cfg(feature = "jinja")]
+use crate::paths;
+#[cfg(feature = "jinja")]
+use crate::vfs::{FsProvider, OsFs};
+
 // ---------------------------------------------------------------------------
 // GAT-based trait (B.1)
 // ---------------------------------------------------------------------------
@@ -117,6 +129,7 @@ impl RenderDiagnostic<'_> {
 // ---------------------------------------------------------------------------
 
 /// Classifies a minijinja error and returns a (kind, suggestion) pair.
+#[cfg(feature = "jinja")]
 fn classify_jinja_error(err: &minijinja::Error) -> (RenderErrorKind, Option<&'static str>) {
     let msg = err.to_string();
     if msg.contains("readFile:") {
@@ -196,6 +209,8 @@ pub struct JinjaContext<'cfg> {
     pub root_directory: &'cfg str,
     pub config: &'cfg HashMap<String, String>,
     pub project_dir: &'cfg str,
+    /// Arguments passed to the program (`RunRequest.args`).
+    pub args: &'cfg [String],
     pub undefined: UndefinedMode,
     /// Extra context variables passed through from the caller.
     /// Inserted into the Jinja context BEFORE built-in keys, so built-ins
@@ -208,6 +223,7 @@ pub struct JinjaContext<'cfg> {
 ///
 /// Extras are inserted FIRST, then built-in keys overwrite them.
 /// This prevents extra vars from overriding built-in Pulumi context.
+#[cfg(feature = "jinja")]
 fn build_minijinja_context(ctx: &JinjaContext<'_>) -> minijinja::Value {
     let mut map = std::collections::BTreeMap::<String, minijinja::Value>::new();
 
@@ -234,12 +250,17 @@ fn build_minijinja_context(ctx: &JinjaContext<'_>) -> minijinja::Value {
         "pulumi_root_directory".into(),
         minijinja::Value::from(ctx.root_directory),
     );
+    map.insert(
+        "pulumi_args".into(),
+        minijinja::Value::from(ctx.args.to_vec()),
+    );
     map.insert("config".into(), build_config_value(ctx.config));
     map.insert("env".into(), build_env_value());
 
     minijinja::Value::from_serialize(&map)
 }
 
+#[cfg(feature = "jinja")]
 fn build_config_value(config: &HashMap<String, String>) -> minijinja::Value {
     let map: std::collections::BTreeMap<String, minijinja::Value> = config
         .iter()
@@ -256,6 +277,7 @@ fn build_config_value(config: &HashMap<String, String>) -> minijinja::Value {
     minijinja::Value::from_serialize(&map)
 }
 
+#[cfg(feature = "jinja")]
 fn build_env_value() -> minijinja::Value {
     let env_vars: std::collections::BTreeMap<String, String> = std::env::vars()
         .filter(|(k, _)| k.starts_with("JINJA_VAR_"))
@@ -281,6 +303,7 @@ const KNOWN_ROOTS: &[&str] = &[
     "pulumi_cwd",
     "pulumi_organization",
     "pulumi_root_directory",
+    "pulumi_args",
 ];
 
 /// Roots that are dict-like objects (attribute access should be evaluated).
@@ -529,16 +552,32 @@ pub fn pre_escape_for_passthrough(source: &str) -> Cow<'_, str> {
 }
 
 /// Jinja preprocessor. Borrows its configuration context.
+#[cfg(feature = "jinja")]
 pub struct JinjaPreprocessor<'cfg> {
     context: &'cfg JinjaContext<'cfg>,
+    /// Where `readFile` and `{% import %}` / `{% include %}` read from.
+    fs: Arc<dyn FsProvider>,
 }
 
+#[cfg(feature = "jinja")]
 impl<'cfg> JinjaPreprocessor<'cfg> {
+    /// Creates a preprocessor that reads files from the host filesystem.
     pub fn new(context: &'cfg JinjaContext<'cfg>) -> Self {
-        Self { context }
+        Self {
+            context,
+            fs: Arc::new(OsFs),
+        }
+    }
+
+    /// Reads `readFile` arguments and imported templates through `fs`
+    /// instead of the host filesystem, e.g. a [`crate::vfs::RootedFs`].
+    pub fn with_fs(mut self, fs: Arc<dyn FsProvider>) -> Self {
+        self.fs = fs;
+        self
     }
 }
 
+#[cfg(feature = "jinja")]
 impl TemplatePreprocessor for JinjaPreprocessor<'_> {
     type Output<'src>
         = Cow<'src, str>
@@ -572,7 +611,12 @@ impl TemplatePreprocessor for JinjaPreprocessor<'_> {
         register_custom_filters(&mut env);
 
         let cache = Arc::new(Mutex::new(ReadFileCache::new()));
-        register_readfile_function(&mut env, self.context.project_dir, Arc::clone(&cache));
+        register_readfile_function(
+            &mut env,
+            self.context.project_dir,
+            Arc::clone(&self.fs),
+            Arc::clone(&cache),
+        );
 
         // Register filesystem template loader for {% import %} / {% include %}
         // Resolves paths relative to project_dir with path traversal protection.
@@ -580,6 +624,7 @@ impl TemplatePreprocessor for JinjaPreprocessor<'_> {
             &mut env,
             self.context.project_dir,
             self.context.root_directory,
+            Arc::clone(&self.fs),
         );
 
         env.add_template(filename, effective_source.as_ref())
@@ -608,7 +653,7 @@ impl TemplatePreprocessor for JinjaPreprocessor<'_> {
 }
 
 /// Quick check for Jinja syntax markers (no allocation).
-fn has_jinja_syntax(s: &str) -> bool {
+pub(crate) fn has_jinja_syntax(s: &str) -> bool {
     s.contains("{{") || s.contains("{%") || s.contains("{#")
 }
 
@@ -635,26 +680,37 @@ pub fn has_any_jinja_block_syntax(s: &str) -> bool {
 
 /// Strips lines containing Jinja block syntax (`{% %}`), preserving everything else.
 /// `{{ }}` expressions in quoted strings are untouched.
-/// Returns the stripped content with the original trailing newline preserved.
+/// Kept lines keep their own line endings, so CRLF sources stay CRLF, and
+/// the original trailing newline (or its absence) is preserved.
 pub fn strip_jinja_blocks(source: &str) -> String {
-    let result: Vec<&str> = source
-        .lines()
+    let mut result: String = source
+        .split_inclusive('\n')
         .filter(|line| {
             let trimmed = line.trim();
             !(trimmed.starts_with("{%") && trimmed.ends_with("%}"))
         })
         .collect();
-    let joined = result.join("\n");
-    if source.ends_with('\n') {
-        joined + "\n"
-    } else {
-        joined
+    if result.is_empty() && source.ends_with('\n') {
+        result.push_str(if source.ends_with("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        });
+    } else if !source.ends_with('\n') {
+        let eol = if result.ends_with("\r\n") {
+            2
+        } else {
+            usize::from(result.ends_with('\n'))
+        };
+        result.truncate(result.len() - eol);
     }
+    result
 }
 
 /// Validates Jinja syntax without rendering (no context needed).
 /// Catches unclosed blocks, invalid expressions, etc.
 /// Returns `Ok(())` if syntax is valid, or a `RenderDiagnostic` with rich error info.
+#[cfg(feature = "jinja")]
 pub fn validate_jinja_syntax<'src>(
     source: &'src str,
     filename: &str,
@@ -671,6 +727,7 @@ pub fn validate_jinja_syntax<'src>(
 }
 
 /// Converts a minijinja::Error into a RenderDiagnostic with zero-copy source reference.
+#[cfg(feature = "jinja")]
 fn build_render_diagnostic<'src>(
     source: &'src str,
     err: &minijinja::Error,
@@ -697,12 +754,32 @@ fn build_render_diagnostic<'src>(
 // ---------------------------------------------------------------------------
 
 /// Validates rendered YAML is parseable. Returns rich diagnostic on failure.
+///
+/// Repeated keys are accepted when the document opts in with
+/// `pulumi.duplicateKeys: warn`; the warnings are reported when the template
+/// is parsed.
 pub fn validate_rendered_yaml<'src>(
+    rendered: &'src str,
+    original: &'src str,
+    filename: &str,
+) -> Result<(), RenderDiagnostic<'src>> {
+    validate_rendered_yaml_with_duplicate_keys(rendered, original, filename, DuplicateKeys::Error)
+}
+
+/// Like [`validate_rendered_yaml`], with `duplicate_keys` as the policy when
+/// the document does not set `pulumi.duplicateKeys` itself.
+pub fn validate_rendered_yaml_with_duplicate_keys<'src>(
     rendered: &'src str,
     _original: &'src str,
     filename: &str,
+    duplicate_keys: DuplicateKeys,
 ) -> Result<(), RenderDiagnostic<'src>> {
-    if let Err(e) = serde_yaml::from_str::<serde_yaml::Value>(rendered) {
+    if let Err(e) = crate::ast::parse::load_yaml(
+        rendered,
+        None,
+        duplicate_keys,
+        &mut crate::diag::Diagnostics::new(),
+    ) {
         let line = e.location().map(|l| l.line()).unwrap_or(0) as u32;
         let col = e.location().map(|l| l.column()).unwrap_or(0) as u32;
         let rendered_line = rendered
@@ -732,10 +809,12 @@ pub fn validate_rendered_yaml<'src>(
 
 /// Cache of file contents read by `readFile()` during Jinja rendering.
 /// Each entry is indexed by a marker ID.
+#[cfg(feature = "jinja")]
 struct ReadFileCache {
     entries: Vec<String>,
 }
 
+#[cfg(feature = "jinja")]
 impl ReadFileCache {
     fn new() -> Self {
         Self {
@@ -755,17 +834,20 @@ impl ReadFileCache {
 }
 
 /// Constructs a NUL-delimited marker: `\x00RF:<id>\x00`
+#[cfg(feature = "jinja")]
 fn readfile_marker(id: usize) -> String {
     format!("\x00RF:{}\x00", id)
 }
 
 /// Extracts the ID from a marker string like `\x00RF:42\x00`.
+#[cfg(feature = "jinja")]
 fn parse_marker_id(s: &str) -> Option<usize> {
     let s = s.strip_prefix("\x00RF:")?.strip_suffix('\x00')?;
     s.parse().ok()
 }
 
 /// Returns true if the trimmed line contains only a single readFile marker.
+#[cfg(feature = "jinja")]
 fn is_single_marker(trimmed: &str) -> bool {
     trimmed.starts_with("\x00RF:")
         && trimmed.ends_with('\x00')
@@ -773,6 +855,7 @@ fn is_single_marker(trimmed: &str) -> bool {
 }
 
 /// Returns the leading whitespace of a line.
+#[cfg(feature = "jinja")]
 fn leading_whitespace(line: &str) -> &str {
     let trimmed = line.trim_start();
     &line[..line.len() - trimmed.len()]
@@ -780,6 +863,7 @@ fn leading_whitespace(line: &str) -> &str {
 
 /// Prepends `indent` to all non-empty lines of `content`.
 /// Trailing newline from the content is stripped to avoid double-newlines.
+#[cfg(feature = "jinja")]
 fn indent_content(content: &str, indent: &str) -> String {
     let content = content
         .strip_suffix("\r\n")
@@ -807,6 +891,7 @@ fn indent_content(content: &str, indent: &str) -> String {
 }
 
 /// Replaces all markers in a line with their file content (no indentation).
+#[cfg(feature = "jinja")]
 fn replace_inline_markers(line: &str, cache: &ReadFileCache) -> String {
     let mut result = String::new();
     let mut rest = line;
@@ -844,18 +929,21 @@ fn replace_inline_markers(line: &str, cache: &ReadFileCache) -> String {
 /// - **Fast path:** if no NUL bytes are present, returns the input as-is.
 /// - **Standalone markers** (only non-whitespace on the line) get auto-indented.
 /// - **Inline markers** get simple text replacement.
+#[cfg(feature = "jinja")]
 fn resolve_readfile_markers(rendered: &str, cache: &ReadFileCache) -> Option<String> {
     if !rendered.contains('\x00') {
         return None;
     }
 
     let mut result = String::with_capacity(rendered.len());
-    for (i, line) in rendered.lines().enumerate() {
-        if i > 0 {
-            result.push('\n');
-        }
+    for segment in rendered.split_inclusive('\n') {
+        let line = segment
+            .strip_suffix("\r\n")
+            .or_else(|| segment.strip_suffix('\n'))
+            .unwrap_or(segment);
+        let eol = &segment[line.len()..];
         if !line.contains('\x00') {
-            result.push_str(line);
+            result.push_str(segment);
             continue;
         }
 
@@ -870,9 +958,7 @@ fn resolve_readfile_markers(rendered: &str, cache: &ReadFileCache) -> Option<Str
         } else {
             result.push_str(&replace_inline_markers(line, cache));
         }
-    }
-    if rendered.ends_with('\n') {
-        result.push('\n');
+        result.push_str(eol);
     }
     Some(result)
 }
@@ -880,35 +966,40 @@ fn resolve_readfile_markers(rendered: &str, cache: &ReadFileCache) -> Option<Str
 /// Registers the `readFile(path)` function in the minijinja environment.
 ///
 /// Security: rejects absolute paths and path traversals that escape
-/// the project directory (e.g. `../../../etc/passwd`).
+/// the project directory (e.g. `../../../etc/passwd`). Files are read
+/// through `fs`, so a [`crate::vfs::RootedFs`] restricts them further.
+#[cfg(feature = "jinja")]
 fn register_readfile_function(
     env: &mut minijinja::Environment<'_>,
     project_dir: &str,
+    fs: Arc<dyn FsProvider>,
     cache: Arc<Mutex<ReadFileCache>>,
 ) {
     let project_dir = project_dir.to_string();
     env.add_function(
         "readFile",
         move |path: String| -> Result<String, minijinja::Error> {
-            // Reject absolute paths
-            if Path::new(&path).is_absolute() {
+            // Reject absolute paths, including Windows drive and UNC paths
+            if paths::is_absolute(&path) {
                 return Err(minijinja::Error::new(
                     minijinja::ErrorKind::InvalidOperation,
                     format!("readFile: absolute paths are not allowed: '{}'", path),
                 ));
             }
-            let project_canonical = Path::new(&project_dir).canonicalize().map_err(|e| {
+            let project_canonical = fs.canonicalize(Path::new(&project_dir)).map_err(|e| {
                 minijinja::Error::new(
                     minijinja::ErrorKind::InvalidOperation,
                     format!("readFile: failed to resolve project directory: {}", e),
                 )
             })?;
-            let resolved = project_canonical.join(&path).canonicalize().map_err(|e| {
-                minijinja::Error::new(
-                    minijinja::ErrorKind::InvalidOperation,
-                    format!("readFile: failed to resolve '{}': {}", path, e),
-                )
-            })?;
+            let resolved = fs
+                .canonicalize(&paths::resolve(&project_canonical, &path))
+                .map_err(|e| {
+                    minijinja::Error::new(
+                        minijinja::ErrorKind::InvalidOperation,
+                        format!("readFile: failed to resolve '{}': {}", path, e),
+                    )
+                })?;
             if !resolved.starts_with(&project_canonical) {
                 return Err(minijinja::Error::new(
                     minijinja::ErrorKind::InvalidOperation,
@@ -916,7 +1007,7 @@ fn register_readfile_function(
                 ));
             }
 
-            let content = std::fs::read_to_string(&resolved).map_err(|e| {
+            let content = fs.read_to_string(&resolved).map_err(|e| {
                 minijinja::Error::new(
                     minijinja::ErrorKind::InvalidOperation,
                     format!("readFile: failed to read '{}': {}", path, e),
@@ -941,11 +1032,15 @@ fn register_readfile_function(
 /// This supports the common pattern where shared templates live at the repo root.
 ///
 /// Only `.j2`, `.jinja`, `.jinja2`, `.yaml`, and `.yml` extensions are loaded
-/// to prevent arbitrary file reads. Absolute paths are rejected.
+/// to prevent arbitrary file reads. Absolute paths are rejected, and a
+/// template must resolve inside `project_dir` or `root_directory`. Templates
+/// are read through `fs`.
+#[cfg(feature = "jinja")]
 fn register_template_loader(
     env: &mut minijinja::Environment<'_>,
     project_dir: &str,
     root_directory: &str,
+    fs: Arc<dyn FsProvider>,
 ) {
     let base_dir = project_dir.to_string();
     let root_dir = if root_directory.is_empty() {
@@ -953,6 +1048,10 @@ fn register_template_loader(
     } else {
         root_directory.to_string()
     };
+    let allowed: Vec<_> = [&base_dir, &root_dir]
+        .into_iter()
+        .filter_map(|dir| fs.canonicalize(Path::new(dir)).ok())
+        .collect();
     env.set_loader(move |name: &str| {
         // Only allow template-like extensions
         let allowed_extensions = [".j2", ".jinja", ".jinja2", ".yaml", ".yml"];
@@ -960,39 +1059,40 @@ fn register_template_loader(
             return Ok(None);
         }
 
-        // Reject absolute paths
-        if Path::new(name).is_absolute() {
+        // Reject absolute paths, including Windows drive and UNC paths
+        if paths::is_absolute(name) {
             return Ok(None);
         }
 
-        // Try relative to project_dir first (handles local and .. paths)
-        let resolved = Path::new(&base_dir).join(name);
-        if let Ok(canonical) = resolved.canonicalize() {
-            if let Ok(content) = std::fs::read_to_string(&canonical) {
-                return Ok(Some(content));
+        let load = |base: &str, name: &str| -> Option<String> {
+            let canonical = fs
+                .canonicalize(&paths::resolve(Path::new(base), name))
+                .ok()?;
+            if !allowed.iter().any(|dir| canonical.starts_with(dir)) {
+                return None;
             }
+            fs.read_to_string(&canonical).ok()
+        };
+
+        // Try relative to project_dir first (handles local and .. paths)
+        if let Some(content) = load(&base_dir, name) {
+            return Ok(Some(content));
         }
 
         // Fall back to root_directory (handles shared templates at repo root)
         if root_dir != base_dir {
-            let resolved = Path::new(&root_dir).join(name);
-            if let Ok(canonical) = resolved.canonicalize() {
-                if let Ok(content) = std::fs::read_to_string(&canonical) {
-                    return Ok(Some(content));
-                }
+            if let Some(content) = load(&root_dir, name) {
+                return Ok(Some(content));
             }
         }
 
         // Also try the template name stripped of leading ../ against root_directory.
         // This handles the pattern where '../environment.j2' from a subdirectory
         // should find 'environment.j2' at the project root.
-        let stripped = name.trim_start_matches("../").trim_start_matches("..\\");
+        let stripped = paths::strip_parent_dirs(name);
         if stripped != name {
-            let resolved = Path::new(&root_dir).join(stripped);
-            if let Ok(canonical) = resolved.canonicalize() {
-                if let Ok(content) = std::fs::read_to_string(&canonical) {
-                    return Ok(Some(content));
-                }
+            if let Some(content) = load(&root_dir, stripped) {
+                return Ok(Some(content));
             }
         }
 
@@ -1004,6 +1104,7 @@ fn register_template_loader(
 // Custom Jinja Filters (B.7)
 // ---------------------------------------------------------------------------
 
+#[cfg(feature = "jinja")]
 fn register_custom_filters(env: &mut minijinja::Environment<'_>) {
     env.add_filter("to_json", |v: minijinja::Value| -> String {
         serde_json::to_string(&v).unwrap_or_default()
@@ -1068,7 +1169,7 @@ pub fn parse_template_with_preprocessor<P: TemplatePreprocessor>(
     (template, diags)
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "jinja"))]
 mod tests {
     use super::*;
 
@@ -1250,6 +1351,14 @@ mod tests {
         assert!(diag.message.contains("test.yaml"));
     }
 
+    #[test]
+    fn test_validate_rendered_yaml_duplicate_keys() {
+        let yaml = "name: a\nname: b\n";
+        assert!(validate_rendered_yaml(yaml, yaml, "test.yaml").is_err());
+        let yaml = "pulumi:\n  duplicateKeys: warn\nname: a\nname: b\n";
+        assert!(validate_rendered_yaml(yaml, yaml, "test.yaml").is_ok());
+    }
+
     // ---- NoopPreprocessor ----
 
     #[test]
@@ -1273,6 +1382,7 @@ mod tests {
             config: &config,
             project_dir: "",
             undefined: UndefinedMode::Strict,
+            args: &[],
             extra: &HashMap::new(),
         };
         let preprocessor = JinjaPreprocessor::new(&ctx);
@@ -1293,6 +1403,7 @@ mod tests {
             config: &config,
             project_dir: "",
             undefined: UndefinedMode::Strict,
+            args: &[],
             extra: &HashMap::new(),
         };
         let preprocessor = JinjaPreprocessor::new(&ctx);
@@ -1314,6 +1425,7 @@ mod tests {
             config: &config,
             project_dir: "",
             undefined: UndefinedMode::Strict,
+            args: &[],
             extra: &HashMap::new(),
         };
         let preprocessor = JinjaPreprocessor::new(&ctx);
@@ -1336,6 +1448,7 @@ mod tests {
             config: &config,
             project_dir: "",
             undefined: UndefinedMode::Strict,
+            args: &[],
             extra: &HashMap::new(),
         };
         let preprocessor = JinjaPreprocessor::new(&ctx);
@@ -1359,6 +1472,7 @@ mod tests {
             config: &config,
             project_dir: "",
             undefined: UndefinedMode::Strict,
+            args: &[],
             extra: &HashMap::new(),
         };
         let preprocessor = JinjaPreprocessor::new(&ctx);
@@ -1506,6 +1620,13 @@ mod tests {
         assert_eq!(stripped, "name: test\nruntime: yaml\n");
     }
 
+    #[test]
+    fn test_strip_jinja_blocks_preserves_crlf() {
+        let source = "name: test\r\n{% if x %}\r\nruntime: yaml\r\n{% endif %}\r\n";
+        let stripped = strip_jinja_blocks(source);
+        assert_eq!(stripped, "name: test\r\nruntime: yaml\r\n");
+    }
+
     #[test]
     fn test_single_line_set_and_use() {
         let source = "{% set prefix = \"test\" %}\nname: {{ prefix }}-bucket\nruntime: yaml\n";
@@ -1518,6 +1639,7 @@ mod tests {
             config: &std::collections::HashMap::new(),
             project_dir: "/tmp",
             undefined: UndefinedMode::Strict,
+            args: &[],
             extra: &HashMap::new(),
         };
         let preprocessor = JinjaPreprocessor::new(&ctx);
@@ -1540,6 +1662,7 @@ mod tests {
             config: &std::collections::HashMap::new(),
             project_dir: "/tmp",
             undefined: UndefinedMode::Strict,
+            args: &[],
             extra: &HashMap::new(),
         };
         let preprocessor = JinjaPreprocessor::new(&ctx);
@@ -1559,6 +1682,75 @@ mod tests {
 
     // ---- readFile marker helpers ----
 
+    #[test]
+    fn test_jinja_reads_files_through_fs_provider() {
+        let config = HashMap::new();
+        let extra = HashMap::new();
+        let ctx = JinjaContext {
+            project_name: "test",
+            stack_name: "dev",
+            cwd: "/project",
+            organization: "",
+            root_directory: "/",
+            config: &config,
+            project_dir: "/project",
+            undefined: UndefinedMode::Strict,
+            args: &[],
+            extra: &extra,
+        };
+        let memory = crate::vfs::MemoryFs::new()
+            .with_file("/project/motd.txt", "hello")
+            .with_file("/project/shared.j2", "region: us-west-2")
+            .with_file("/outside/secret.j2", "token: hunter2");
+        let fs: Arc<dyn FsProvider> = Arc::new(crate::vfs::RootedFs::new(memory, ["/project"]));
+        let preprocessor = JinjaPreprocessor::new(&ctx).with_fs(fs);
+
+        let result = preprocessor
+            .preprocess(
+                "motd: {{ readFile('motd.txt') }}\n{% include 'shared.j2' %}\n",
+                "Pulumi.yaml",
+            )
+            .unwrap();
+        assert!(result.contains("motd: hello"), "{}", result);
+        assert!(result.contains("region: us-west-2"), "{}", result);
+
+        // The root directory is outside the allowed roots, so the include
+        // cannot reach files next to the project.
+        let err = preprocessor
+            .preprocess("{% include '../outside/secret.j2' %}\n", "Pulumi.yaml")
+            .unwrap_err();
+        assert!(!err.to_string().contains("hunter2"), "{}", err);
+    }
+
+    #[test]
+    fn test_jinja_include_stays_inside_project() {
+        let config = HashMap::new();
+        let extra = HashMap::new();
+        let ctx = JinjaContext {
+            project_name: "test",
+            stack_name: "dev",
+            cwd: "/project",
+            organization: "",
+            root_directory: "",
+            config: &config,
+            project_dir: "/project",
+            undefined: UndefinedMode::Strict,
+            args: &[],
+            extra: &extra,
+        };
+        let memory = crate::vfs::MemoryFs::new()
+            .with_file("/project/shared.yaml", "region: us-west-2")
+            .with_file("/etc/secret.yaml", "token: hunter2");
+        let preprocessor = JinjaPreprocessor::new(&ctx).with_fs(Arc::new(memory));
+        let result = preprocessor
+            .preprocess("{% include 'shared.yaml' %}\n", "Pulumi.yaml")
+            .unwrap();
+        assert!(result.contains("region: us-west-2"), "{}", result);
+        assert!(preprocessor
+            .preprocess("{% include '../etc/secret.yaml' %}\n", "Pulumi.yaml")
+            .is_err());
+    }
+
     #[test]
     fn test_readfile_marker_format() {
         assert_eq!(readfile_marker(0), "\x00RF:0\x00");
@@ -1691,4 +1883,21 @@ mod tests {
         let result = resolve_readfile_markers(&input, &cache).unwrap();
         assert_eq!(result, "version: 1.2.3\n");
     }
+
+    #[test]
+    fn test_resolve_readfile_markers_preserves_crlf() {
+        let mut cache = ReadFileCache::new();
+        cache.add("line1\r\nline2\r\n".to_string());
+        cache.add("1.2.3\r\n".to_string());
+        let input = format!(
+            "script: |\r\n  {}\r\nversion: {}\r\n",
+            readfile_marker(0),
+            readfile_marker(1)
+        );
+        let result = resolve_readfile_markers(&input, &cache).unwrap();
+        assert_eq!(
+            result,
+            "script: |\r\n  line1\r\n  line2\r\nversion: 1.2.3\r\n"
+        );
+    }
 }
@@ -1,17 +1,36 @@
 pub mod ast;
+pub mod canonical;
+pub mod checkpoint;
 pub mod classify;
+#[cfg(feature = "completion")]
+pub mod code_actions;
+#[cfg(feature = "completion")]
 pub mod completion;
 pub mod config_types;
+pub mod dead_code;
 pub mod diag;
 pub mod eval;
+pub mod format;
 pub mod jinja;
+pub mod lockfile;
 pub mod multi_file;
+pub mod number;
+mod outline;
 pub mod packages;
+pub mod paths;
+#[cfg(feature = "pcl-gen")]
 pub mod pcl_gen;
+pub mod plan;
+pub mod remote_import;
+#[cfg(feature = "completion")]
+pub mod scaffold;
 pub mod schema;
 pub mod source;
 pub mod syntax;
+#[cfg(feature = "type-check")]
 pub mod type_check;
+pub mod version;
+pub mod vfs;
 
 /// Normalizes a gRPC address string for tonic connection.
 /// Ensures the address has an `http://` scheme unless it already has one
@@ -0,0 +1,495 @@
+//! The project lockfile, `pulumi-yaml.lock`.
+//!
+//! Records, per referenced package, the resolved provider version, its
+//! download server, and the SHA-256 checksums of the plugin binary per
+//! platform, plus the digest of every remote import:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "packages": {
+//!     "aws": {
+//!       "version": "6.52.0",
+//!       "checksums": { "linux-amd64": "3f1c…" }
+//!     }
+//!   },
+//!   "imports": { "https://example.com/network.yaml": "9f86…" }
+//! }
+//! ```
+//!
+//! When present, the language host pins unversioned packages to the locked
+//! versions and hands the checksums to the engine with `GetRequiredPackages`,
+//! so plugin installation is reproducible. A lockfile that no longer matches
+//! the program is an error rather than silently ignored.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ast::template::ImportDecl;
+use crate::packages::{installed_versions, pulumi_home, PackageDependency};
+use crate::remote_import::sha256_hex;
+use crate::version::VersionConstraint;
+
+/// File name of the lockfile, next to `Pulumi.yaml`.
+pub const LOCKFILE_NAME: &str = "pulumi-yaml.lock";
+
+/// Current lockfile format version.
+pub const LOCKFILE_VERSION: u32 = 1;
+
+/// Errors from reading or writing the lockfile.
+#[derive(Debug, thiserror::Error)]
+pub enum LockfileError {
+    #[error("{}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("{}: invalid lockfile: {source}", path.display())]
+    Parse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[error("{}: unsupported lockfile version {version}", path.display())]
+    UnsupportedVersion { path: PathBuf, version: u32 },
+}
+
+/// The contents of `pulumi-yaml.lock`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lockfile {
+    pub version: u32,
+    /// Locked packages, keyed by [`lock_key`].
+    #[serde(default)]
+    pub packages: BTreeMap<String, LockedPackage>,
+    /// Remote import URL → SHA-256 digest.
+    #[serde(default)]
+    pub imports: BTreeMap<String, String>,
+}
+
+/// A locked package.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockedPackage {
+    /// Resolved plugin version; empty if no version could be resolved.
+    #[serde(default)]
+    pub version: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub server: String,
+    /// Platform (`<os>-<arch>`, e.g. `linux-amd64`) → plugin SHA-256.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub checksums: BTreeMap<String, String>,
+}
+
+impl Default for Lockfile {
+    fn default() -> Self {
+        Self {
+            version: LOCKFILE_VERSION,
+            packages: BTreeMap::new(),
+            imports: BTreeMap::new(),
+        }
+    }
+}
+
+/// The key a package is locked under: the parameterized package name if the
+/// package is parameterized, else the plugin name.
+pub fn lock_key(pkg: &PackageDependency) -> &str {
+    pkg.parameterization
+        .as_ref()
+        .map_or(pkg.name.as_str(), |p| p.name.as_str())
+}
+
+/// Returns the current platform in Pulumi's `<os>-<arch>` notation.
+pub fn platform() -> String {
+    let os = match std::env::consts::OS {
+        "macos" => "darwin",
+        other => other,
+    };
+    let arch = match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        other => other,
+    };
+    format!("{}-{}", os, arch)
+}
+
+/// Returns the plugin cache directory, `$PULUMI_HOME/plugins`.
+pub fn plugin_dir() -> PathBuf {
+    pulumi_home().join("plugins")
+}
+
+/// Finds the installed resource plugin binary for `name`: the highest
+/// installed version satisfying the `version` constraint, or the highest
+/// installed version if `version` is empty.
+fn find_plugin(plugin_dir: &Path, name: &str, version: &str) -> Option<(String, PathBuf)> {
+    let constraint = match version {
+        "" => None,
+        _ => Some(VersionConstraint::parse(version).ok()?),
+    };
+    let binary = format!("pulumi-resource-{}{}", name, std::env::consts::EXE_SUFFIX);
+    installed_versions(plugin_dir, name)
+        .into_iter()
+        .filter(|v| constraint.as_ref().is_none_or(|c| c.matches(v)))
+        .map(|v| {
+            let path = plugin_dir
+                .join(format!("resource-{}-v{}", name, v))
+                .join(&binary);
+            (v, path)
+        })
+        .filter(|(_, path)| path.is_file())
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(v, path)| (v.to_string(), path))
+}
+
+/// Whether a locked version satisfies a package's version constraint.
+/// Versions that are not semver must match exactly.
+fn satisfies(constraint: &str, locked: &str) -> bool {
+    match (
+        VersionConstraint::parse(constraint),
+        semver::Version::parse(locked),
+    ) {
+        (Ok(c), Ok(v)) => c.matches(&v),
+        _ => constraint == locked,
+    }
+}
+
+/// Whether `version` is a requirement rather than an exact version.
+fn is_requirement(version: &str) -> bool {
+    !version.is_empty()
+        && matches!(
+            VersionConstraint::parse(version),
+            Ok(VersionConstraint::Range(_))
+        )
+}
+
+impl Lockfile {
+    /// Returns the lockfile path for a project directory.
+    pub fn path(project_dir: &Path) -> PathBuf {
+        project_dir.join(LOCKFILE_NAME)
+    }
+
+    /// Loads the lockfile of a project, or `None` if it has none.
+    pub fn load(project_dir: &Path) -> Result<Option<Self>, LockfileError> {
+        let path = Self::path(project_dir);
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(source) => return Err(LockfileError::Io { path, source }),
+        };
+        let lock: Lockfile =
+            serde_json::from_str(&contents).map_err(|source| LockfileError::Parse {
+                path: path.clone(),
+                source,
+            })?;
+        if lock.version != LOCKFILE_VERSION {
+            return Err(LockfileError::UnsupportedVersion {
+                path,
+                version: lock.version,
+            });
+        }
+        Ok(Some(lock))
+    }
+
+    /// Writes the lockfile into a project directory.
+    pub fn save(&self, project_dir: &Path) -> Result<(), LockfileError> {
+        let path = Self::path(project_dir);
+        let mut contents = serde_json::to_string_pretty(self).expect("lockfile serializes");
+        contents.push('\n');
+        std::fs::write(&path, contents).map_err(|source| LockfileError::Io { path, source })
+    }
+
+    /// Generates a lockfile for a program's packages and imports.
+    ///
+    /// Unversioned packages are locked to the highest version installed in
+    /// `plugin_dir`, and packages with a version requirement to the highest
+    /// installed version satisfying it (see [`VersionConstraint::resolve`]).
+    /// The checksum of each installed plugin binary is recorded for the
+    /// current platform; checksums for other platforms are carried over from
+    /// `previous` while the version is unchanged.
+    pub fn generate(
+        packages: &[PackageDependency],
+        imports: &[ImportDecl<'_>],
+        plugin_dir: &Path,
+        previous: Option<&Lockfile>,
+    ) -> Self {
+        let platform = platform();
+        let mut lock = Lockfile::default();
+        for pkg in packages {
+            let key = lock_key(pkg);
+            let installed = find_plugin(plugin_dir, &pkg.name, &pkg.version);
+            let locked = previous
+                .and_then(|p| p.packages.get(key))
+                .map(|p| p.version.clone())
+                .filter(|v| pkg.version.is_empty() || satisfies(&pkg.version, v));
+            let version = match (&installed, pkg.version.as_str()) {
+                (Some((version, _)), v) if v.is_empty() || is_requirement(v) => version.clone(),
+                (None, "") => locked.unwrap_or_default(),
+                (None, v) if is_requirement(v) => locked
+                    .or_else(|| {
+                        let constraint = VersionConstraint::parse(v).ok()?;
+                        Some(constraint.resolve([])?.to_string())
+                    })
+                    .unwrap_or_default(),
+                (_, v) => v.to_string(),
+            };
+            let mut checksums = previous
+                .and_then(|p| p.packages.get(key))
+                .filter(|p| p.version == version)
+                .map(|p| p.checksums.clone())
+                .unwrap_or_default();
+            // Parameterized packages share the base plugin's binary.
+            if let Some((_, path)) = installed.filter(|(v, _)| *v == version) {
+                if let Ok(bytes) = std::fs::read(&path) {
+                    checksums.insert(platform.clone(), sha256_hex(&bytes));
+                }
+            }
+            lock.packages.insert(
+                key.to_string(),
+                LockedPackage {
+                    version,
+                    server: pkg.download_url.clone(),
+                    checksums,
+                },
+            );
+        }
+        for import in imports {
+            lock.imports
+                .insert(import.url.to_string(), import.sha256.to_string());
+        }
+        lock
+    }
+
+    /// Returns how the program has drifted from the lockfile; empty if the
+    /// lockfile is up to date.
+    pub fn check(&self, packages: &[PackageDependency], imports: &[ImportDecl<'_>]) -> Vec<String> {
+        let mut problems = Vec::new();
+        for pkg in packages {
+            let key = lock_key(pkg);
+            match self.packages.get(key) {
+                None => problems.push(format!("package '{}' is not locked", key)),
+                Some(locked)
+                    if !pkg.version.is_empty() && !satisfies(&pkg.version, &locked.version) =>
+                {
+                    problems.push(format!(
+                        "package '{}' requires version {} but {} is locked",
+                        key, pkg.version, locked.version
+                    ))
+                }
+                Some(_) => {}
+            }
+        }
+        for key in self.packages.keys() {
+            if !packages.iter().any(|p| lock_key(p) == key) {
+                problems.push(format!("package '{}' is locked but no longer used", key));
+            }
+        }
+        for import in imports {
+            match self.imports.get(import.url.as_ref()) {
+                None => problems.push(format!("import {} is not locked", import.url)),
+                Some(digest) if *digest != import.sha256 => problems.push(format!(
+                    "import {} is pinned to sha256 {} but {} is locked",
+                    import.url, import.sha256, digest
+                )),
+                Some(_) => {}
+            }
+        }
+        for url in self.imports.keys() {
+            if !imports.iter().any(|i| i.url == url.as_str()) {
+                problems.push(format!("import {} is locked but no longer used", url));
+            }
+        }
+        problems
+    }
+
+    /// Fills in the version and download server of unversioned packages,
+    /// and replaces version requirements with the locked version.
+    pub fn pin(&self, packages: &mut [PackageDependency]) {
+        for pkg in packages {
+            let Some(locked) = self.packages.get(lock_key(pkg)) else {
+                continue;
+            };
+            if pkg.version.is_empty() || is_requirement(&pkg.version) {
+                pkg.version = locked.version.clone();
+            }
+            if pkg.download_url.is_empty() {
+                pkg.download_url = locked.server.clone();
+            }
+        }
+    }
+
+    /// Returns the locked plugin checksums of a package.
+    pub fn checksums(&self, pkg: &PackageDependency) -> Option<&BTreeMap<String, String>> {
+        self.packages
+            .get(lock_key(pkg))
+            .filter(|locked| locked.version == pkg.version)
+            .map(|locked| &locked.checksums)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    fn package(name: &str, version: &str) -> PackageDependency {
+        PackageDependency {
+            name: name.to_string(),
+            version: version.to_string(),
+            download_url: String::new(),
+            parameterization: None,
+        }
+    }
+
+    fn install_plugin(plugin_dir: &Path, name: &str, version: &str, contents: &str) {
+        let dir = plugin_dir.join(format!("resource-{}-v{}", name, version));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join(format!(
+                "pulumi-resource-{}{}",
+                name,
+                std::env::consts::EXE_SUFFIX
+            )),
+            contents,
+        )
+        .unwrap();
+    }
+
+    fn import(url: &'static str, sha256: &'static str) -> ImportDecl<'static> {
+        ImportDecl {
+            url: Cow::Borrowed(url),
+            sha256: Cow::Borrowed(sha256),
+        }
+    }
+
+    #[test]
+    fn test_generate_resolves_installed_plugins() {
+        let plugins = tempfile::tempdir().unwrap();
+        install_plugin(plugins.path(), "aws", "6.1.0", "aws-6.1.0");
+        install_plugin(plugins.path(), "aws", "6.10.0", "aws-6.10.0");
+        install_plugin(plugins.path(), "random", "4.16.0", "random");
+
+        let packages = [package("aws", ""), package("random", "4.16.0")];
+        let imports = [import("https://example.com/a.yaml", "abc")];
+        let lock = Lockfile::generate(&packages, &imports, plugins.path(), None);
+
+        let aws = &lock.packages["aws"];
+        assert_eq!(aws.version, "6.10.0");
+        assert_eq!(aws.checksums[&platform()], sha256_hex(b"aws-6.10.0"));
+        assert_eq!(lock.packages["random"].version, "4.16.0");
+        assert_eq!(lock.imports["https://example.com/a.yaml"], "abc");
+        assert!(lock.check(&packages, &imports).is_empty());
+    }
+
+    #[test]
+    fn test_generate_and_pin_resolve_version_requirements() {
+        let plugins = tempfile::tempdir().unwrap();
+        install_plugin(plugins.path(), "aws", "6.1.0", "aws-6.1.0");
+        install_plugin(plugins.path(), "aws", "6.9.0", "aws-6.9.0");
+        install_plugin(plugins.path(), "aws", "7.0.0", "aws-7.0.0");
+
+        let packages = [package("aws", "^6.1"), package("gcp", ">=7.2, <8")];
+        let lock = Lockfile::generate(&packages, &[], plugins.path(), None);
+        assert_eq!(lock.packages["aws"].version, "6.9.0");
+        assert_eq!(
+            lock.packages["aws"].checksums[&platform()],
+            sha256_hex(b"aws-6.9.0")
+        );
+        assert_eq!(lock.packages["gcp"].version, "7.2.0");
+        assert!(lock.check(&packages, &[]).is_empty());
+
+        let mut pinned = packages.to_vec();
+        lock.pin(&mut pinned);
+        assert_eq!(pinned[0].version, "6.9.0");
+        assert_eq!(pinned[1].version, "7.2.0");
+
+        assert_eq!(
+            lock.check(&[package("aws", "^7"), package("gcp", "7.2.0")], &[]),
+            vec!["package 'aws' requires version ^7 but 6.9.0 is locked"]
+        );
+    }
+
+    #[test]
+    fn test_generate_keeps_other_platform_checksums() {
+        let plugins = tempfile::tempdir().unwrap();
+        let mut previous = Lockfile::default();
+        previous.packages.insert(
+            "aws".to_string(),
+            LockedPackage {
+                version: "6.0.0".to_string(),
+                server: String::new(),
+                checksums: BTreeMap::from([("plan9-mips".to_string(), "ff".to_string())]),
+            },
+        );
+
+        let lock = Lockfile::generate(&[package("aws", "")], &[], plugins.path(), Some(&previous));
+        assert_eq!(lock.packages["aws"], previous.packages["aws"]);
+
+        // A version bump drops checksums for the old version.
+        let lock = Lockfile::generate(
+            &[package("aws", "6.1.0")],
+            &[],
+            plugins.path(),
+            Some(&previous),
+        );
+        assert!(lock.packages["aws"].checksums.is_empty());
+    }
+
+    #[test]
+    fn test_check_reports_drift() {
+        let plugins = tempfile::tempdir().unwrap();
+        let lock = Lockfile::generate(
+            &[package("aws", "6.0.0"), package("gcp", "7.0.0")],
+            &[import("https://example.com/a.yaml", "abc")],
+            plugins.path(),
+            None,
+        );
+        let problems = lock.check(
+            &[package("aws", "6.1.0"), package("random", "")],
+            &[import("https://example.com/a.yaml", "def")],
+        );
+        assert_eq!(
+            problems,
+            vec![
+                "package 'aws' requires version 6.1.0 but 6.0.0 is locked",
+                "package 'random' is not locked",
+                "package 'gcp' is locked but no longer used",
+                "import https://example.com/a.yaml is pinned to sha256 def but abc is locked",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pin_and_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(Lockfile::load(dir.path()).unwrap().is_none());
+
+        let mut lock = Lockfile::default();
+        lock.packages.insert(
+            "aws".to_string(),
+            LockedPackage {
+                version: "6.0.0".to_string(),
+                server: "https://example.com/plugins".to_string(),
+                checksums: BTreeMap::from([(platform(), "ab".to_string())]),
+            },
+        );
+        lock.save(dir.path()).unwrap();
+        let loaded = Lockfile::load(dir.path()).unwrap().unwrap();
+        assert_eq!(loaded, lock);
+
+        let mut packages = vec![package("aws", "")];
+        loaded.pin(&mut packages);
+        assert_eq!(packages[0].version, "6.0.0");
+        assert_eq!(packages[0].download_url, "https://example.com/plugins");
+        assert_eq!(loaded.checksums(&packages[0]).unwrap()[&platform()], "ab");
+    }
+
+    #[test]
+    fn test_load_rejects_unknown_version() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(Lockfile::path(dir.path()), r#"{"version": 99}"#).unwrap();
+        let err = Lockfile::load(dir.path()).unwrap_err();
+        assert!(matches!(
+            err,
+            LockfileError::UnsupportedVersion { version: 99, .. }
+        ));
+    }
+}
@@ -8,6 +8,7 @@
 //!
 //! - `Pulumi.yaml` is required (main file with metadata, config, resources, outputs)
 //! - `Pulumi.*.yaml` / `Pulumi.*.yml` are additional resource files
+//! - `Pulumi.vars.yaml` / `Pulumi.vars.yml` holds shared constants (see below)
 //! - Stack config files (`Pulumi.<stack>.yaml`) are handled by the CLI, not us
 //! - Files are sorted alphabetically for deterministic ordering
 //!
@@ -15,23 +16,72 @@
 //!
 //! | Field       | Main | Additional | Collision |
 //! |-------------|------|-----------|-----------|
-//! | name        | Req  | Forbidden | Error     |
-//! | runtime     | Req  | Forbidden | Error     |
+//! | name        | Req  | Forbidden | See below |
+//! | description | OK   | Forbidden | See below |
+//! | runtime     | Req  | Forbidden | See below |
 //! | config      | OK   | Forbidden | Error     |
 //! | resources   | OK   | OK        | Dup error |
 //! | variables   | OK   | OK        | Dup error |
 //! | outputs     | OK   | OK        | Dup error |
 //! | components  | OK   | OK        | Dup error |
+//! | imports     | OK   | Forbidden | Error     |
+//! | plugins     | OK   | Forbidden | Error     |
+//!
+//! The main file takes precedence for `name`, `description`, and `runtime`.
+//! An additional file repeating the main file's value gets a warning; a
+//! different value, or one the main file does not set, is an error naming
+//! both files. An additional file's `runtime` other than `yaml` is rejected
+//! as unsupported.
+//!
+//! # Shared Variables
+//!
+//! `Pulumi.vars.yaml` may only declare `variables:`. They are merged with the
+//! lowest precedence: a config entry, variable, or resource of the same name
+//! in any other file (or inherited through `extends:`) replaces the shared
+//! one. A `Pulumi.vars.yaml` with only `config:` is the stack config of a
+//! stack named `vars` and is skipped like any other.
+//!
+//! # Inheritance
+//!
+//! The main file may declare `extends: <path>` (relative to the file) to
+//! inherit from a base template, which may itself extend another. The base's
+//! config, variables, resources, outputs, components, Starlark functions,
+//! imports, and plugins are merged beneath the child:
+//!
+//! - an entry the child declares under the same name replaces the base entry
+//!   wholesale (there is no deep merge of properties);
+//! - `name` always comes from the child; `description`, `namespace`,
+//!   `runtime:`, and `pulumi:` settings come from the child if it sets them,
+//!   else the base;
+//! - inherited entries keep the base file in the source map, so relative
+//!   paths in their `fn::readFile`, `fn::fileAsset`, and similar calls are
+//!   resolved against the base file's directory (set the `runtime:` option
+//!   `projectRelativePaths: true` to resolve them against the project
+//!   directory instead).
+//!
+//! Remote fragments pinned in the main file's `imports:` block (see
+//! [`crate::remote_import`]) are merged as additional files, before the local
+//! ones, with the import URL as their file name.
 
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use crate::ast::parse::parse_template;
+use crate::ast::parse::parse_template_with_duplicate_keys;
 use crate::ast::template::*;
 use crate::diag::Diagnostics;
-use crate::jinja::{validate_rendered_yaml, JinjaContext, JinjaPreprocessor, TemplatePreprocessor};
+use crate::jinja::JinjaContext;
+#[cfg(feature = "jinja")]
+use crate::jinja::{
+    validate_rendered_yaml_with_duplicate_keys, JinjaPreprocessor, TemplatePreprocessor,
+};
+use crate::packages;
+use crate::paths;
+use crate::remote_import::{default_cache_dir, resolve_import};
+use crate::source::SourceArena;
+use crate::syntax::Span;
+use crate::vfs::{FsProvider, OsFs};
 
 /// The set of project files discovered in a directory.
 #[derive(Debug, Clone)]
@@ -40,17 +90,22 @@ pub struct ProjectFiles {
     pub main_file: PathBuf,
     /// Additional `Pulumi.*.yaml` files, sorted alphabetically.
     pub additional_files: Vec<PathBuf>,
+    /// The shared variables file, `Pulumi.vars.yaml`, if present.
+    pub vars_file: Option<PathBuf>,
 }
 
 impl ProjectFiles {
-    /// Returns an iterator over all files (main first, then additional).
+    /// Returns an iterator over all files (main first, then additional, then
+    /// the shared variables file).
     pub fn all_files(&self) -> impl Iterator<Item = &PathBuf> {
-        std::iter::once(&self.main_file).chain(self.additional_files.iter())
+        std::iter::once(&self.main_file)
+            .chain(self.additional_files.iter())
+            .chain(self.vars_file.iter())
     }
 
     /// Returns the total number of files.
     pub fn file_count(&self) -> usize {
-        1 + self.additional_files.len()
+        1 + self.additional_files.len() + usize::from(self.vars_file.is_some())
     }
 }
 
@@ -74,8 +129,21 @@ pub struct MergedTemplate {
     components: Vec<ComponentDecl<'static>>,
     /// Starlark function declarations (from main file only).
     starlark_functions: Vec<StarlarkFunctionDecl<'static>>,
+    /// Remote imports declared by the main file (already merged).
+    imports: Vec<ImportDecl<'static>>,
+    /// The main file's `runtime:` setting.
+    main_runtime: Option<RuntimeDecl<'static>>,
+    /// Provider plugins declared by the main file.
+    plugins: Vec<PluginDecl<'static>>,
+    /// The main file's name.
+    main_file: String,
+    /// The `extends:` bases of the main file, deepest first.
+    base_files: Vec<String>,
     /// Maps logical name → source filename for error reporting.
     source_map: Arc<HashMap<String, String>>,
+    /// The text of each parsed file, as rendered by Jinja. The spans of the
+    /// template's nodes point into these.
+    sources: Arc<SourceArena>,
 }
 
 impl MergedTemplate {
@@ -96,9 +164,23 @@ impl MergedTemplate {
             outputs: self.outputs.clone(),
             components: self.components.clone(),
             starlark_functions: self.starlark_functions.clone(),
+            imports: Vec::new(),
+            extends: None,
+            runtime: self.main_runtime.clone(),
+            plugins: self.plugins.clone(),
         }
     }
 
+    /// Returns the provider plugins declared by the main file.
+    pub fn plugins(&self) -> &[PluginDecl<'static>] {
+        &self.plugins
+    }
+
+    /// Returns the remote imports declared by the main file.
+    pub fn imports(&self) -> &[ImportDecl<'static>] {
+        &self.imports
+    }
+
     /// Returns the project name from the main file.
     pub fn name(&self) -> Option<&str> {
         self.main_name.as_deref()
@@ -124,6 +206,16 @@ impl MergedTemplate {
         &self.outputs
     }
 
+    /// Returns the main file's name.
+    pub fn main_file(&self) -> &str {
+        &self.main_file
+    }
+
+    /// Returns the `extends:` bases of the main file, deepest first.
+    pub fn base_files(&self) -> &[String] {
+        &self.base_files
+    }
+
     /// Returns the source file for a given logical name.
     pub fn source_file(&self, name: &str) -> Option<&str> {
         self.source_map.get(name).map(|s| s.as_str())
@@ -167,6 +259,12 @@ impl MergedTemplate {
         Arc::clone(&self.source_map)
     }
 
+    /// Returns the parsed files the spans of the template's nodes point
+    /// into. Empty when the template was not loaded by [`load_project`].
+    pub fn sources(&self) -> &Arc<SourceArena> {
+        &self.sources
+    }
+
     /// Returns the number of files that contributed to this merged template.
     pub fn file_count(&self) -> usize {
         let unique: std::collections::HashSet<&str> =
@@ -177,16 +275,27 @@ impl MergedTemplate {
 
 /// Discovers project files in a directory.
 ///
-/// Returns `Pulumi.yaml` as the main file and any `Pulumi.*.yaml`/`Pulumi.*.yml`
-/// as additional files. Additional files are sorted alphabetically.
+/// Returns `Pulumi.yaml` as the main file, `Pulumi.vars.yaml` as the shared
+/// variables file, and any other `Pulumi.*.yaml`/`Pulumi.*.yml` as additional
+/// files. Additional files are sorted by file name, ignoring case, so the
+/// order does not depend on the platform's directory listing or on
+/// case-insensitive filesystems.
 pub fn discover_project_files(directory: &Path) -> Result<ProjectFiles, String> {
+    discover_project_files_with_fs(directory, &OsFs)
+}
+
+/// Like [`discover_project_files`], listing the directory through `fs`.
+pub fn discover_project_files_with_fs(
+    directory: &Path,
+    fs: &dyn FsProvider,
+) -> Result<ProjectFiles, String> {
     // Look for main file
     let main_yaml = directory.join("Pulumi.yaml");
     let main_yml = directory.join("Pulumi.yml");
 
-    let main_file = if main_yaml.exists() {
+    let main_file = if fs.kind(&main_yaml).is_ok() {
         main_yaml
-    } else if main_yml.exists() {
+    } else if fs.kind(&main_yml).is_ok() {
         main_yml
     } else {
         return Err(format!("no Pulumi.yaml found in {}", directory.display()));
@@ -194,18 +303,13 @@ pub fn discover_project_files(directory: &Path) -> Result<ProjectFiles, String>
 
     // Discover additional files matching Pulumi.*.yaml or Pulumi.*.yml
     let mut additional_files = Vec::new();
+    let mut vars_file = None;
 
-    let entries = std::fs::read_dir(directory)
+    let entries = fs
+        .read_dir(directory)
         .map_err(|e| format!("failed to read directory {}: {}", directory.display(), e))?;
 
-    for entry in entries {
-        let entry = match entry {
-            Ok(e) => e,
-            Err(_) => continue,
-        };
-        let file_name = entry.file_name();
-        let name = file_name.to_string_lossy();
-
+    for name in entries {
         // Must match Pulumi.*.yaml or Pulumi.*.yml pattern
         if !name.starts_with("Pulumi.") {
             continue;
@@ -237,23 +341,129 @@ pub fn discover_project_files(directory: &Path) -> Result<ProjectFiles, String>
         }
 
         // Validate it's a regular file
-        let path = entry.path();
-        if !path.is_file() {
+        let path = directory.join(&name);
+        if !fs.is_file(&path) {
             continue;
         }
 
+        if middle == "vars" {
+            vars_file = Some(path);
+            continue;
+        }
         additional_files.push(path);
     }
 
-    // Sort alphabetically for deterministic ordering
-    additional_files.sort();
+    // Sort by name for deterministic ordering; ties (names differing only in
+    // case) fall back to byte order
+    additional_files.sort_by_cached_key(|path| {
+        let name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        (name.to_lowercase(), name)
+    });
 
     Ok(ProjectFiles {
         main_file,
         additional_files,
+        vars_file,
     })
 }
 
+/// Checks a main-file-only metadata field (`name`, `description`) repeated in
+/// an additional file. The main file takes precedence: a repeated identical
+/// value is only a warning, a different one is an error naming both files.
+fn check_main_only(
+    field: &str,
+    (main_path, main_value): (&str, Option<&str>),
+    (filename, value): (&str, Option<&str>),
+    diags: &mut Diagnostics,
+) {
+    let Some(value) = value else {
+        return;
+    };
+    match main_value {
+        Some(main_value) if main_value == value => diags.warning(
+            None,
+            format!(
+                "'{}' in {} repeats the value in {}",
+                field, filename, main_path
+            ),
+            format!(
+                "{} takes precedence; remove '{}' from {}",
+                main_path, field, filename
+            ),
+        ),
+        Some(main_value) => diags.error(
+            None,
+            format!(
+                "'{}' in {} ({:?}) conflicts with {} ({:?})",
+                field, filename, value, main_path, main_value
+            ),
+            format!(
+                "'{}' is only allowed in {}, which takes precedence; remove it from {}",
+                field, main_path, filename
+            ),
+        ),
+        None => diags.error(
+            None,
+            format!(
+                "'{}' is only allowed in {}, found in {}",
+                field, main_path, filename
+            ),
+            format!("move '{}' to {}", field, main_path),
+        ),
+    }
+}
+
+/// Checks a `runtime:` setting in an additional file. Only `yaml` projects
+/// can be merged; otherwise it is handled like [`check_main_only`].
+fn check_runtime(
+    (main_path, main_runtime): (&str, Option<&RuntimeDecl<'static>>),
+    (filename, runtime): (&str, Option<&RuntimeDecl<'static>>),
+    diags: &mut Diagnostics,
+) {
+    let Some(runtime) = runtime else {
+        return;
+    };
+    if runtime.name != "yaml" {
+        diags.error(
+            None,
+            format!(
+                "runtime '{}' in {} is not supported",
+                runtime.name, filename
+            ),
+            format!(
+                "additional project files are merged into the yaml program of {}; remove 'runtime' from {}",
+                main_path, filename
+            ),
+        );
+        return;
+    }
+    if main_runtime.is_some_and(|main| main.name == runtime.name && main.options != runtime.options)
+    {
+        diags.error(
+            None,
+            format!(
+                "'runtime' options in {} conflict with {}",
+                filename, main_path
+            ),
+            format!(
+                "'runtime' is only allowed in {}, which takes precedence; remove it from {}",
+                main_path, filename
+            ),
+        );
+        return;
+    }
+    check_main_only(
+        "runtime",
+        (main_path, main_runtime.map(|r| r.name.as_ref())),
+        (filename, Some(runtime.name.as_ref())),
+        diags,
+    );
+}
+
 /// Merge items from an additional file into the target collection, detecting name collisions.
 fn merge_section<T, F>(
     items: &[T],
@@ -292,6 +502,7 @@ fn merge_section<T, F>(
 ///
 /// Returns the merged template and any diagnostics (errors for collisions,
 /// forbidden fields in additional files, etc.).
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(main = main_path, additional = additional.len())))]
 pub fn merge_templates(
     main: TemplateDecl<'static>,
     main_path: &str,
@@ -307,6 +518,9 @@ pub fn merge_templates(
     let main_pulumi = main.pulumi;
     let main_config = main.config;
     let main_starlark = main.starlark_functions;
+    let main_imports = main.imports;
+    let main_runtime = main.runtime;
+    let main_plugins = main.plugins;
 
     // Move collections (main is consumed by value, no need to clone)
     let mut resources = main.resources;
@@ -342,42 +556,71 @@ pub fn merge_templates(
             continue;
         }
 
+        // Project metadata belongs to the main file, which takes precedence.
+        check_main_only(
+            "name",
+            (main_path, main_name.as_deref()),
+            (filename, template.name.as_deref()),
+            &mut diags,
+        );
+        check_main_only(
+            "description",
+            (main_path, main_description.as_deref()),
+            (filename, template.description.as_deref()),
+            &mut diags,
+        );
+        check_runtime(
+            (main_path, main_runtime.as_ref()),
+            (filename, template.runtime.as_ref()),
+            &mut diags,
+        );
+
         // Forbidden fields in additional files
-        if template.name.is_some() {
+        if !template.config.is_empty() {
+            diags.error(
+                None,
+                format!(
+                    "'config' is only allowed in {}, found in {}",
+                    main_path, filename
+                ),
+                "",
+            );
+        }
+        if !template.starlark_functions.is_empty() {
             diags.error(
                 None,
                 format!(
-                    "'name' is only allowed in {}, found in {}",
+                    "'starlark' is only allowed in {}, found in {}",
                     main_path, filename
                 ),
                 "",
             );
         }
-        if template.description.is_some() {
+        if !template.imports.is_empty() {
             diags.error(
                 None,
                 format!(
-                    "'description' is only allowed in {}, found in {}",
+                    "'imports' is only allowed in {}, found in {}",
                     main_path, filename
                 ),
                 "",
             );
         }
-        if !template.config.is_empty() {
+        if template.extends.is_some() {
             diags.error(
                 None,
                 format!(
-                    "'config' is only allowed in {}, found in {}",
+                    "'extends' is only allowed in {}, found in {}",
                     main_path, filename
                 ),
                 "",
             );
         }
-        if !template.starlark_functions.is_empty() {
+        if !template.plugins.is_empty() {
             diags.error(
                 None,
                 format!(
-                    "'starlark' is only allowed in {}, found in {}",
+                    "'plugins' is only allowed in {}, found in {}",
                     main_path, filename
                 ),
                 "",
@@ -423,6 +666,12 @@ pub fn merge_templates(
         );
     }
 
+    diags.extend(packages::check_version_constraints(
+        &resources,
+        &main_plugins,
+        Some(&source_map),
+    ));
+
     let merged = MergedTemplate {
         main_name,
         main_namespace,
@@ -434,25 +683,113 @@ pub fn merge_templates(
         outputs,
         components,
         starlark_functions: main_starlark,
+        imports: main_imports,
+        main_runtime,
+        plugins: main_plugins,
+        main_file: main_path.to_string(),
+        base_files: Vec::new(),
         source_map: Arc::new(source_map),
+        sources: Arc::default(),
     };
 
     (merged, diags)
 }
 
+/// Merges the `variables:` of the shared variables file `filename` beneath
+/// `merged`: a variable is dropped if a config entry, variable, or resource
+/// of the same name already exists. Any other section is an error.
+fn merge_vars(
+    merged: &mut MergedTemplate,
+    filename: &str,
+    template: TemplateDecl<'static>,
+) -> Diagnostics {
+    let mut diags = Diagnostics::new();
+    let is_stack_config = !template.config.is_empty() && template.variables.is_empty();
+    if is_stack_config {
+        return diags;
+    }
+
+    let sections = [
+        ("name", template.name.is_some()),
+        ("description", template.description.is_some()),
+        ("runtime", template.runtime.is_some()),
+        ("config", !template.config.is_empty()),
+        ("resources", !template.resources.is_empty()),
+        ("outputs", !template.outputs.is_empty()),
+        ("components", !template.components.is_empty()),
+        ("starlark", !template.starlark_functions.is_empty()),
+        ("imports", !template.imports.is_empty()),
+        ("extends", template.extends.is_some()),
+        ("plugins", !template.plugins.is_empty()),
+    ];
+    for (section, _) in sections.iter().filter(|(_, present)| *present) {
+        diags.error(
+            None,
+            format!(
+                "'{}' is not allowed in {}, which may only declare 'variables'",
+                section, filename
+            ),
+            "",
+        );
+    }
+
+    let taken: std::collections::HashSet<String> = merged
+        .config
+        .iter()
+        .map(|c| c.key.to_string())
+        .chain(merged.variables.iter().map(|v| v.key.to_string()))
+        .chain(merged.resources.iter().map(|r| r.logical_name.to_string()))
+        .collect();
+    let source_map = Arc::make_mut(&mut merged.source_map);
+    for variable in template.variables {
+        if taken.contains(variable.key.as_ref()) {
+            continue;
+        }
+        source_map.insert(variable.key.to_string(), filename.to_string());
+        merged.variables.push(variable);
+    }
+    diags
+}
+
 /// High-level entry point: discovers, optionally Jinja-preprocesses, parses,
 /// and merges all project files into a single `MergedTemplate`.
 ///
 /// If `jinja_ctx` is `Some`, Jinja `{{ }}` expressions are rendered per-file.
-/// If `None`, files are parsed as-is.
+/// If `None`, files are parsed as-is. Remote imports are cached in
+/// [`default_cache_dir`].
 pub fn load_project(
     directory: &Path,
     jinja_ctx: Option<&JinjaContext<'_>>,
 ) -> (MergedTemplate, Diagnostics) {
+    load_project_with_import_cache(directory, jinja_ctx, &default_cache_dir())
+}
+
+/// Like [`load_project`], with remote imports cached in `import_cache`.
+pub fn load_project_with_import_cache(
+    directory: &Path,
+    jinja_ctx: Option<&JinjaContext<'_>>,
+    import_cache: &Path,
+) -> (MergedTemplate, Diagnostics) {
+    load_project_with_fs(directory, jinja_ctx, import_cache, Arc::new(OsFs))
+}
+
+/// Like [`load_project_with_import_cache`], reading project files and
+/// `extends:` bases through `fs`. Jinja's `readFile` and template imports
+/// read through `fs` too. The remote import cache is always on the host
+/// filesystem.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(dir = %directory.display())))]
+pub fn load_project_with_fs(
+    directory: &Path,
+    jinja_ctx: Option<&JinjaContext<'_>>,
+    import_cache: &Path,
+    fs: Arc<dyn FsProvider>,
+) -> (MergedTemplate, Diagnostics) {
+    let fs = &fs;
     let mut diags = Diagnostics::new();
+    let mut sources = SourceArena::new();
 
     // 1. Discover files
-    let project_files = match discover_project_files(directory) {
+    let project_files = match discover_project_files_with_fs(directory, fs.as_ref()) {
         Ok(files) => files,
         Err(e) => {
             diags.error(None, e, "");
@@ -467,7 +804,13 @@ pub fn load_project(
                 outputs: Vec::new(),
                 components: Vec::new(),
                 starlark_functions: Vec::new(),
+                imports: Vec::new(),
+                main_runtime: None,
+                plugins: Vec::new(),
+                main_file: String::new(),
+                base_files: Vec::new(),
                 source_map: Arc::new(HashMap::new()),
+                sources: Arc::default(),
             };
             return (empty, diags);
         }
@@ -480,30 +823,17 @@ pub fn load_project(
         .and_then(|n| n.to_str())
         .unwrap_or("Pulumi.yaml")
         .to_string();
-    let main_template =
-        match load_and_parse_file(&project_files.main_file, &main_filename, jinja_ctx) {
-            Ok((template, file_diags)) => {
-                diags.extend(file_diags);
-                if diags.has_errors() {
-                    let empty = MergedTemplate {
-                        main_name: None,
-                        main_namespace: None,
-                        main_description: None,
-                        main_pulumi: PulumiDecl::default(),
-                        config: Vec::new(),
-                        resources: Vec::new(),
-                        variables: Vec::new(),
-                        outputs: Vec::new(),
-                        components: Vec::new(),
-                        starlark_functions: Vec::new(),
-                        source_map: Arc::new(HashMap::new()),
-                    };
-                    return (empty, diags);
-                }
-                template
-            }
-            Err(e) => {
-                diags.error(None, e, "");
+    let main_template = match load_and_parse_file(
+        &project_files.main_file,
+        &main_filename,
+        jinja_ctx,
+        fs,
+        &mut sources,
+        DuplicateKeys::Error,
+    ) {
+        Ok((template, file_diags)) => {
+            diags.extend(file_diags);
+            if diags.has_errors() {
                 let empty = MergedTemplate {
                     main_name: None,
                     main_namespace: None,
@@ -515,21 +845,107 @@ pub fn load_project(
                     outputs: Vec::new(),
                     components: Vec::new(),
                     starlark_functions: Vec::new(),
+                    imports: Vec::new(),
+                    main_runtime: None,
+                    plugins: Vec::new(),
+                    main_file: String::new(),
+                    base_files: Vec::new(),
                     source_map: Arc::new(HashMap::new()),
+                    sources: Arc::default(),
                 };
                 return (empty, diags);
             }
-        };
+            template
+        }
+        Err(e) => {
+            diags.error(None, e, "");
+            let empty = MergedTemplate {
+                main_name: None,
+                main_namespace: None,
+                main_description: None,
+                main_pulumi: PulumiDecl::default(),
+                config: Vec::new(),
+                resources: Vec::new(),
+                variables: Vec::new(),
+                outputs: Vec::new(),
+                components: Vec::new(),
+                starlark_functions: Vec::new(),
+                imports: Vec::new(),
+                main_runtime: None,
+                plugins: Vec::new(),
+                main_file: String::new(),
+                base_files: Vec::new(),
+                source_map: Arc::new(HashMap::new()),
+                sources: Arc::default(),
+            };
+            return (empty, diags);
+        }
+    };
 
-    // 3. Parse additional files
+    // 3. Merge the `extends:` chain beneath the main file
+    let mut inherited = HashMap::new();
+    let mut chain = Vec::new();
+    let main_template = match apply_extends(
+        main_template,
+        &main_filename,
+        &project_files.main_file,
+        jinja_ctx,
+        fs,
+        &mut sources,
+        &mut chain,
+        &mut inherited,
+    ) {
+        Ok((template, base_diags)) => {
+            diags.extend(base_diags);
+            template
+        }
+        Err(e) => {
+            diags.error(None, e, "");
+            TemplateDecl::new()
+        }
+    };
+
+    // The main file's `duplicateKeys` policy applies to the whole project.
+    let duplicate_keys = main_template.pulumi.duplicate_keys;
+
+    // 4. Resolve and parse remote imports
     let mut additional = Vec::new();
+    for import in &main_template.imports {
+        let filename = import.url.to_string();
+        let parsed = resolve_import(import, import_cache)
+            .map_err(|e| e.to_string())
+            .and_then(|source| {
+                parse_source(
+                    source,
+                    &filename,
+                    jinja_ctx,
+                    fs,
+                    &mut sources,
+                    duplicate_keys,
+                )
+            });
+        match parsed {
+            Ok((template, file_diags)) => {
+                diags.extend(file_diags);
+                if diags.has_errors() {
+                    continue;
+                }
+                additional.push((filename, template));
+            }
+            Err(e) => {
+                diags.error(None, e, "");
+            }
+        }
+    }
+
+    // 5. Parse additional files
     for path in &project_files.additional_files {
         let filename = path
             .file_name()
             .and_then(|n| n.to_str())
             .unwrap_or("Pulumi.yaml")
             .to_string();
-        match load_and_parse_file(path, &filename, jinja_ctx) {
+        match load_and_parse_file(path, &filename, jinja_ctx, fs, &mut sources, duplicate_keys) {
             Ok((template, file_diags)) => {
                 diags.extend(file_diags);
                 if diags.has_errors() {
@@ -555,62 +971,300 @@ pub fn load_project(
             outputs: Vec::new(),
             components: Vec::new(),
             starlark_functions: Vec::new(),
+            imports: Vec::new(),
+            main_runtime: None,
+            plugins: Vec::new(),
+            main_file: String::new(),
+            base_files: Vec::new(),
             source_map: Arc::new(HashMap::new()),
+            sources: Arc::default(),
         };
         return (empty, diags);
     }
 
-    // 4. Merge
-    let (merged, merge_diags) = merge_templates(main_template, &main_filename, additional);
+    // 6. Merge
+    let (mut merged, merge_diags) = merge_templates(main_template, &main_filename, additional);
     diags.extend(merge_diags);
+    merged.base_files = chain.into_iter().rev().map(|(_, label)| label).collect();
+    if !inherited.is_empty() {
+        let source_map = Arc::make_mut(&mut merged.source_map);
+        for (name, base_file) in inherited {
+            if source_map.get(&name) == Some(&main_filename) {
+                source_map.insert(name, base_file);
+            }
+        }
+    }
+
+    // 7. Merge shared variables beneath everything else
+    if let Some(ref path) = project_files.vars_file {
+        let filename = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("Pulumi.vars.yaml")
+            .to_string();
+        match load_and_parse_file(path, &filename, jinja_ctx, fs, &mut sources, duplicate_keys) {
+            Ok((template, file_diags)) => {
+                let failed = file_diags.has_errors();
+                diags.extend(file_diags);
+                if !failed {
+                    diags.extend(merge_vars(&mut merged, &filename, template));
+                }
+            }
+            Err(e) => diags.error(None, format!("{}: {}", filename, e), ""),
+        }
+    }
+    merged.sources = Arc::new(sources);
 
     (merged, diags)
 }
 
-/// Loads a single file, optionally applies Jinja preprocessing, parses it.
+/// Merges the `extends:` chain of `template` (loaded from `path`, labelled
+/// `label` in diagnostics) beneath it.
+///
+/// `chain` collects the canonical path and label of each base on the chain,
+/// nearest first, and is used for cycle detection. Names of entries inherited from a base are recorded in
+/// `inherited` with the base's label.
+#[allow(clippy::too_many_arguments)]
+fn apply_extends(
+    template: TemplateDecl<'static>,
+    label: &str,
+    path: &Path,
+    jinja_ctx: Option<&JinjaContext<'_>>,
+    fs: &Arc<dyn FsProvider>,
+    sources: &mut SourceArena,
+    chain: &mut Vec<(PathBuf, String)>,
+    inherited: &mut HashMap<String, String>,
+) -> Result<(TemplateDecl<'static>, Diagnostics), String> {
+    let Some(extends) = template.extends.clone() else {
+        return Ok((template, Diagnostics::new()));
+    };
+    let base_path = paths::resolve(path.parent().unwrap_or(Path::new(".")), &extends);
+    let canonical = fs
+        .canonicalize(&base_path)
+        .map_err(|e| format!("{}: cannot read base template {}: {}", label, extends, e))?;
+    if chain.iter().any(|(p, _)| *p == canonical) {
+        return Err(format!("{}: 'extends' cycle through {}", label, extends));
+    }
+
+    let base_label = match Path::new(label).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => {
+            format!("{}/{}", dir.display(), extends)
+        }
+        _ => extends.to_string(),
+    };
+    chain.push((canonical, base_label.clone()));
+    let (base, mut diags) = load_and_parse_file(
+        &base_path,
+        &base_label,
+        jinja_ctx,
+        fs,
+        sources,
+        DuplicateKeys::Error,
+    )?;
+    if diags.has_errors() {
+        return Ok((template, diags));
+    }
+    let (base, base_diags) = apply_extends(
+        base,
+        &base_label,
+        &base_path,
+        jinja_ctx,
+        fs,
+        sources,
+        chain,
+        inherited,
+    )?;
+    diags.extend(base_diags);
+
+    Ok((merge_beneath(base, template, &base_label, inherited), diags))
+}
+
+/// Overlays `child` entries on `base` entries with the same key.
+fn overlay<T>(
+    base: Vec<T>,
+    child: Vec<T>,
+    key: impl Fn(&T) -> &str,
+    base_label: &str,
+    inherited: Option<&mut HashMap<String, String>>,
+) -> Vec<T> {
+    let child_keys: std::collections::HashSet<String> =
+        child.iter().map(|c| key(c).to_string()).collect();
+    let kept: Vec<T> = base
+        .into_iter()
+        .filter(|b| !child_keys.contains(key(b)))
+        .collect();
+    if let Some(inherited) = inherited {
+        for k in &child_keys {
+            inherited.remove(k);
+        }
+        for b in &kept {
+            inherited
+                .entry(key(b).to_string())
+                .or_insert_with(|| base_label.to_string());
+        }
+    }
+    kept.into_iter().chain(child).collect()
+}
+
+/// Merges `base` beneath `child` following the inheritance rules.
+fn merge_beneath(
+    base: TemplateDecl<'static>,
+    child: TemplateDecl<'static>,
+    base_label: &str,
+    inherited: &mut HashMap<String, String>,
+) -> TemplateDecl<'static> {
+    TemplateDecl {
+        meta: child.meta,
+        name: child.name,
+        namespace: child.namespace.or(base.namespace),
+        description: child.description.or(base.description),
+        pulumi: if child.pulumi.has_settings() {
+            child.pulumi
+        } else {
+            base.pulumi
+        },
+        config: overlay(base.config, child.config, |c| &c.key, base_label, None),
+        variables: overlay(
+            base.variables,
+            child.variables,
+            |v| &v.key,
+            base_label,
+            Some(inherited),
+        ),
+        resources: overlay(
+            base.resources,
+            child.resources,
+            |r| &r.logical_name,
+            base_label,
+            Some(inherited),
+        ),
+        outputs: overlay(
+            base.outputs,
+            child.outputs,
+            |o| &o.key,
+            base_label,
+            Some(inherited),
+        ),
+        components: overlay(
+            base.components,
+            child.components,
+            |c| &c.key,
+            base_label,
+            Some(inherited),
+        ),
+        starlark_functions: overlay(
+            base.starlark_functions,
+            child.starlark_functions,
+            |f| &f.name,
+            base_label,
+            None,
+        ),
+        imports: overlay(base.imports, child.imports, |i| &i.url, base_label, None),
+        extends: None,
+        runtime: child.runtime.or(base.runtime),
+        plugins: overlay(base.plugins, child.plugins, |p| &p.name, base_label, None),
+    }
+}
+
+/// Loads a single file from `fs`, optionally applies Jinja preprocessing,
+/// parses it.
 fn load_and_parse_file(
     path: &Path,
     filename: &str,
     jinja_ctx: Option<&JinjaContext<'_>>,
+    fs: &Arc<dyn FsProvider>,
+    sources: &mut SourceArena,
+    duplicate_keys: DuplicateKeys,
 ) -> Result<(TemplateDecl<'static>, Diagnostics), String> {
-    let source = std::fs::read_to_string(path)
+    let source = fs
+        .read_to_string(path)
         .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    parse_source(source, filename, jinja_ctx, fs, sources, duplicate_keys)
+}
 
+/// Optionally applies Jinja preprocessing to `source`, then parses it.
+/// Jinja reads the files it references through `fs`. The parsed text is
+/// added to `sources`, which the template's spans point into. A file that
+/// does not set `pulumi.duplicateKeys` uses `duplicate_keys`.
+fn parse_source(
+    source: String,
+    filename: &str,
+    jinja_ctx: Option<&JinjaContext<'_>>,
+    fs: &Arc<dyn FsProvider>,
+    sources: &mut SourceArena,
+    duplicate_keys: DuplicateKeys,
+) -> Result<(TemplateDecl<'static>, Diagnostics), String> {
     let mut diags = Diagnostics::new();
 
-    // Apply Jinja preprocessing if context is available
-    let effective_source = if let Some(ctx) = jinja_ctx {
-        let preprocessor = JinjaPreprocessor::new(ctx);
-        let rendered = match preprocessor.preprocess(&source, filename) {
-            Ok(cow) => cow.into_owned(),
-            Err(diag) => {
-                return Err(format!(
-                    "Jinja preprocessing failed for {}: {}",
-                    filename,
-                    diag.format_rich(filename)
-                ));
-            }
-        };
+    let effective_source = match jinja_ctx {
+        Some(ctx) => render_jinja(source, filename, ctx, fs, duplicate_keys)?,
+        None => source,
+    };
 
-        // Validate rendered YAML (only when Jinja was applied)
-        if let Err(diag) = validate_rendered_yaml(&rendered, &source, filename) {
+    // Parse
+    let len = effective_source.len() as u32;
+    let file = sources.add_file(filename.to_string(), effective_source);
+    let (template, parse_diags) = parse_template_with_duplicate_keys(
+        sources.text(file),
+        Some(Span::new(file, 0, len)),
+        duplicate_keys,
+    );
+    diags.extend(parse_diags);
+
+    Ok((template, diags))
+}
+
+/// Renders the Jinja syntax of `source` and checks the result is YAML.
+#[cfg(feature = "jinja")]
+fn render_jinja(
+    source: String,
+    filename: &str,
+    ctx: &JinjaContext<'_>,
+    fs: &Arc<dyn FsProvider>,
+    duplicate_keys: DuplicateKeys,
+) -> Result<String, String> {
+    let preprocessor = JinjaPreprocessor::new(ctx).with_fs(Arc::clone(fs));
+    let rendered = match preprocessor.preprocess(&source, filename) {
+        Ok(cow) => cow.into_owned(),
+        Err(diag) => {
             return Err(format!(
-                "YAML validation failed for {}: {}",
+                "Jinja preprocessing failed for {}: {}",
                 filename,
                 diag.format_rich(filename)
             ));
         }
-
-        rendered
-    } else {
-        source
     };
 
-    // Parse
-    let (template, parse_diags) = parse_template(&effective_source, None);
-    diags.extend(parse_diags);
+    if let Err(diag) =
+        validate_rendered_yaml_with_duplicate_keys(&rendered, &source, filename, duplicate_keys)
+    {
+        return Err(format!(
+            "YAML validation failed for {}: {}",
+            filename,
+            diag.format_rich(filename)
+        ));
+    }
 
-    Ok((template, diags))
+    Ok(rendered)
+}
+
+/// Without the `jinja` feature, files using Jinja syntax cannot be loaded;
+/// all others are parsed as they are.
+#[cfg(not(feature = "jinja"))]
+fn render_jinja(
+    source: String,
+    filename: &str,
+    _ctx: &JinjaContext<'_>,
+    _fs: &Arc<dyn FsProvider>,
+    _duplicate_keys: DuplicateKeys,
+) -> Result<String, String> {
+    if crate::jinja::has_jinja_syntax(&source) {
+        return Err(format!(
+            "{} uses Jinja syntax, but this build was compiled without the `jinja` feature",
+            filename
+        ));
+    }
+    Ok(source)
 }
 
 /// Loads just the raw file contents for all project files.
@@ -637,13 +1291,17 @@ pub fn load_project_sources(directory: &Path) -> Result<Vec<(String, String)>, S
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ast::parse::parse_template;
+    #[cfg(feature = "jinja")]
     use crate::jinja::UndefinedMode;
     use std::fs;
 
     fn make_temp_project(files: &[(&str, &str)]) -> tempfile::TempDir {
         let dir = tempfile::tempdir().unwrap();
         for (name, content) in files {
-            fs::write(dir.path().join(name), content).unwrap();
+            let path = dir.path().join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
         }
         dir
     }
@@ -683,6 +1341,30 @@ mod tests {
         assert_eq!(names, vec!["Pulumi.buckets.yaml", "Pulumi.tables.yaml"]);
     }
 
+    #[test]
+    fn test_discover_sorts_ignoring_case() {
+        let dir = make_temp_project(&[
+            ("Pulumi.yaml", "name: test\nruntime: yaml\n"),
+            ("Pulumi.Zones.yaml", "resources: {}\n"),
+            ("Pulumi.apps.yaml", "resources: {}\n"),
+            ("Pulumi.Buckets.yml", "resources: {}\n"),
+        ]);
+        let files = discover_project_files(dir.path()).unwrap();
+        let names: Vec<String> = files
+            .additional_files
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "Pulumi.apps.yaml",
+                "Pulumi.Buckets.yml",
+                "Pulumi.Zones.yaml"
+            ]
+        );
+    }
+
     #[test]
     fn test_discover_requires_pulumi_yaml() {
         let dir = make_temp_project(&[(
@@ -782,6 +1464,48 @@ mod tests {
         assert!(errors[0].summary.contains("Pulumi.extra.yaml"));
     }
 
+    #[test]
+    fn test_merge_conflicting_version_constraints_error() {
+        let main_src = r#"
+name: test
+runtime: yaml
+plugins:
+  providers:
+    - name: aws
+      version: ^6.1
+resources:
+  bucket:
+    type: aws:s3:Bucket
+    options:
+      version: 6.4.0
+"#;
+        let extra_src = r#"
+resources:
+  queue:
+    type: aws:sqs:Queue
+    options:
+      version: ~6.4
+  table:
+    type: aws:dynamodb:Table
+    options:
+      version: 7.0.0
+"#;
+        let (main_template, _) = parse_template(main_src, None);
+        let (extra_template, _) = parse_template(extra_src, None);
+        let (_, diags) = merge_templates(
+            main_template,
+            "Pulumi.yaml",
+            vec![("Pulumi.data.yaml".to_string(), extra_template)],
+        );
+        let errors: Vec<_> = diags.iter().filter(|d| d.is_error()).collect();
+        assert_eq!(errors.len(), 1, "{}", diags);
+        assert_eq!(
+            errors[0].summary,
+            "package 'aws' has conflicting version constraints: '^6.1' (plugin 'aws') \
+             and '7.0.0' (resource 'table' in Pulumi.data.yaml)"
+        );
+    }
+
     #[test]
     fn test_merge_config_in_extra_file_error() {
         // Extra file with config AND resources → error (not a stack config file)
@@ -965,6 +1689,77 @@ mod tests {
     }
 
     #[test]
+    fn test_load_project_applies_duplicate_keys_policy_to_all_files() {
+        let dir = make_temp_project(&[
+            (
+                "Pulumi.yaml",
+                "name: test\nruntime: yaml\npulumi:\n  duplicateKeys: warn\n",
+            ),
+            (
+                "Pulumi.buckets.yaml",
+                "resources:\n  bucket:\n    type: test:Bucket\n    type: test:Other\n",
+            ),
+        ]);
+        let (merged, diags) = load_project(dir.path(), None);
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        assert_eq!(merged.resources.len(), 1);
+        assert!(
+            diags
+                .iter()
+                .any(|d| d.summary
+                    == "duplicate key 'resources.bucket.type'; keeping the last value"),
+            "{}",
+            diags
+        );
+
+        let dir = make_temp_project(&[
+            ("Pulumi.yaml", "name: test\nruntime: yaml\n"),
+            (
+                "Pulumi.buckets.yaml",
+                "resources:\n  bucket:\n    type: test:Bucket\n    type: test:Other\n",
+            ),
+        ]);
+        let (_, diags) = load_project(dir.path(), None);
+        assert!(diags.has_errors());
+    }
+
+    #[test]
+    fn test_load_project_from_memory_fs() {
+        let fs = crate::vfs::MemoryFs::new()
+            .with_file(
+                "/project/Pulumi.yaml",
+                "name: test\nruntime: yaml\nextends: ../base/Pulumi.yaml\n",
+            )
+            .with_file(
+                "/project/Pulumi.buckets.yaml",
+                "resources:\n  bucket:\n    type: test:Bucket\n",
+            )
+            .with_file(
+                "/base/Pulumi.yaml",
+                "name: base\nruntime: yaml\nvariables:\n  region: us-west-2\n",
+            );
+        let cache = Path::new("/cache");
+        let (merged, diags) =
+            load_project_with_fs(Path::new("/project"), None, cache, Arc::new(fs.clone()));
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        assert_eq!(merged.resources.len(), 1);
+        assert_eq!(merged.variables.len(), 1);
+
+        // A rooted filesystem refuses the base outside the project.
+        let rooted = crate::vfs::RootedFs::new(fs, ["/project"]);
+        let (_, diags) = load_project_with_fs(Path::new("/project"), None, cache, Arc::new(rooted));
+        assert!(diags.has_errors());
+        assert!(
+            diags
+                .to_string()
+                .contains("outside the allowed directories"),
+            "{}",
+            diags
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "jinja")]
     fn test_load_project_with_jinja() {
         let dir = make_temp_project(&[
             ("Pulumi.yaml", "name: test\nruntime: yaml\n"),
@@ -980,6 +1775,7 @@ mod tests {
             config: &config,
             project_dir: dir.path().to_str().unwrap(),
             undefined: UndefinedMode::Strict,
+            args: &[],
             extra: &HashMap::new(),
         };
         let (merged, diags) = load_project(dir.path(), Some(&ctx));
@@ -1057,4 +1853,456 @@ mod tests {
             "Pulumi.yaml"
         );
     }
+
+    fn cache_fragment(cache: &Path, contents: &str) -> String {
+        let digest = crate::remote_import::sha256_hex(contents.as_bytes());
+        let dir = cache.join("sha256");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(format!("{}.yaml", digest)), contents).unwrap();
+        digest
+    }
+
+    #[test]
+    fn test_load_project_merges_cached_import() {
+        let cache = tempfile::tempdir().unwrap();
+        let digest = cache_fragment(
+            cache.path(),
+            "resources:\n  shared:\n    type: test:index:Shared\n",
+        );
+        let main = format!(
+            "name: test\nruntime: yaml\nimports:\n  - url: https://fragments.invalid/shared.yaml\n    sha256: {}\nresources:\n  local:\n    type: test:index:Local\n    properties:\n      dep: ${{shared.id}}\n",
+            digest
+        );
+        let dir = make_temp_project(&[("Pulumi.yaml", &main)]);
+
+        let (merged, diags) = load_project_with_import_cache(dir.path(), None, cache.path());
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        assert_eq!(merged.resource_names(), vec!["local", "shared"]);
+        assert_eq!(
+            merged.source_file("shared"),
+            Some("https://fragments.invalid/shared.yaml")
+        );
+    }
+
+    #[test]
+    fn test_load_project_rejects_unverified_import() {
+        let cache = tempfile::tempdir().unwrap();
+        let main = format!(
+            "name: test\nruntime: yaml\nimports:\n  - url: https://fragments.invalid/shared.yaml\n    sha256: {}\n",
+            "0".repeat(64)
+        );
+        let dir = make_temp_project(&[("Pulumi.yaml", &main)]);
+
+        let (_, diags) = load_project_with_import_cache(dir.path(), None, cache.path());
+        assert!(diags.has_errors());
+        assert!(diags.to_string().contains("fragments.invalid"), "{}", diags);
+    }
+
+    #[test]
+    fn test_merge_imports_in_extra_file_error() {
+        let cache = tempfile::tempdir().unwrap();
+        let digest = cache_fragment(cache.path(), "variables:\n  x: 1\n");
+        let extra = format!(
+            "imports:\n  - url: https://fragments.invalid/x.yaml\n    sha256: {}\nvariables:\n  y: 2\n",
+            digest
+        );
+        let dir = make_temp_project(&[
+            ("Pulumi.yaml", "name: test\nruntime: yaml\n"),
+            ("Pulumi.extra.yaml", &extra),
+        ]);
+
+        let (_, diags) = load_project_with_import_cache(dir.path(), None, cache.path());
+        assert!(diags.has_errors());
+        assert!(
+            diags.to_string().contains("'imports' is only allowed"),
+            "{}",
+            diags
+        );
+    }
+
+    #[test]
+    fn test_load_project_extends_base() {
+        let dir = make_temp_project(&[
+            (
+                "base/Pulumi.base.yaml",
+                "name: base
+config:
+  region:
+    default: us-east-1
+  size:
+    default: small
+variables:
+  prefix: org
+resources:
+  logs:
+    type: test:index:Bucket
+  network:
+    type: test:index:Network
+",
+            ),
+            (
+                "app/Pulumi.yaml",
+                "name: app
+runtime: yaml
+extends: ../base/Pulumi.base.yaml
+config:
+  size:
+    default: large
+resources:
+  network:
+    type: test:index:CustomNetwork
+  app:
+    type: test:index:App
+",
+            ),
+        ]);
+
+        let (merged, diags) = load_project(&dir.path().join("app"), None);
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        assert_eq!(merged.name(), Some("app"));
+        assert_eq!(merged.resource_names(), vec!["logs", "network", "app"]);
+        let network = &merged.resources()[1];
+        assert_eq!(network.resource.type_, "test:index:CustomNetwork");
+
+        let config: Vec<&str> = merged.config().iter().map(|c| c.key.as_ref()).collect();
+        assert_eq!(config, vec!["region", "size"]);
+        assert!(matches!(
+            &merged.config()[1].param.default,
+            Some(crate::ast::expr::Expr::String(_, s)) if s == "large"
+        ));
+
+        assert_eq!(merged.source_file("logs"), Some("../base/Pulumi.base.yaml"));
+        assert_eq!(
+            merged.source_file("prefix"),
+            Some("../base/Pulumi.base.yaml")
+        );
+        assert_eq!(merged.source_file("network"), Some("Pulumi.yaml"));
+        assert_eq!(merged.source_file("app"), Some("Pulumi.yaml"));
+    }
+
+    #[test]
+    fn test_load_project_shared_vars() {
+        let dir = make_temp_project(&[
+            (
+                "Pulumi.yaml",
+                "name: app
+runtime: yaml
+config:
+  region:
+    default: eu-west-1
+variables:
+  owner: app-team
+resources:
+  bucket:
+    type: test:index:Bucket
+",
+            ),
+            (
+                "Pulumi.vars.yaml",
+                "variables:
+  owner: platform
+  region: us-east-1
+  bucket: shadowed
+  costCenter: cc-42
+",
+            ),
+        ]);
+
+        let files = discover_project_files(dir.path()).unwrap();
+        assert!(files.additional_files.is_empty());
+        assert!(files.vars_file.is_some());
+        assert_eq!(files.file_count(), 2);
+
+        let (merged, diags) = load_project(dir.path(), None);
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        let variables: Vec<&str> = merged.variables().iter().map(|v| v.key.as_ref()).collect();
+        assert_eq!(variables, vec!["owner", "costCenter"]);
+        assert_eq!(merged.source_file("owner"), Some("Pulumi.yaml"));
+        assert_eq!(merged.source_file("costCenter"), Some("Pulumi.vars.yaml"));
+    }
+
+    #[test]
+    fn test_load_project_shared_vars_only_variables() {
+        let dir = make_temp_project(&[
+            ("Pulumi.yaml", "name: app\nruntime: yaml\n"),
+            (
+                "Pulumi.vars.yaml",
+                "variables:\n  a: 1\nresources:\n  r:\n    type: test:index:R\n",
+            ),
+        ]);
+        let (_, diags) = load_project(dir.path(), None);
+        assert!(diags.to_string().contains(
+            "'resources' is not allowed in Pulumi.vars.yaml, which may only declare 'variables'"
+        ));
+
+        // The stack config of a stack named `vars` is skipped
+        let dir = make_temp_project(&[
+            ("Pulumi.yaml", "name: app\nruntime: yaml\n"),
+            ("Pulumi.vars.yaml", "config:\n  app:size: large\n"),
+        ]);
+        let (merged, diags) = load_project(dir.path(), None);
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        assert!(merged.variables().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "jinja")]
+    fn test_load_project_windows_paths_and_crlf() {
+        let app = format!(
+            "name: app\r\nruntime: yaml\r\nextends: ..{0}base{0}Pulumi.base.yaml\r\n",
+            std::path::MAIN_SEPARATOR
+        );
+        let dir = make_temp_project(&[
+            (
+                "base/Pulumi.base.yaml",
+                "name: base\r\nresources:\r\n  logs:\r\n    type: test:index:Bucket\r\n",
+            ),
+            ("app/Pulumi.yaml", &app),
+            (
+                "app/Pulumi.buckets.yaml",
+                "resources:\r\n{% for i in range(2) %}\r\n  bucket{{ i }}:\r\n    type: test:index:Bucket\r\n{% endfor %}\r\n",
+            ),
+        ]);
+        let config = HashMap::new();
+        let project_dir = dir.path().join("app");
+        let ctx = JinjaContext {
+            project_name: "app",
+            stack_name: "dev",
+            cwd: "/tmp",
+            organization: "",
+            root_directory: "",
+            config: &config,
+            project_dir: project_dir.to_str().unwrap(),
+            undefined: UndefinedMode::Strict,
+            args: &[],
+            extra: &HashMap::new(),
+        };
+        let (merged, diags) = load_project(&project_dir, Some(&ctx));
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        assert_eq!(merged.resource_names(), vec!["logs", "bucket0", "bucket1"]);
+    }
+
+    #[test]
+    fn test_load_project_extends_chain() {
+        let dir = make_temp_project(&[
+            (
+                "org/Pulumi.org.yaml",
+                "variables:
+  owner: platform
+  tier: shared
+",
+            ),
+            (
+                "team/Pulumi.team.yaml",
+                "extends: ../org/Pulumi.org.yaml
+variables:
+  tier: team
+",
+            ),
+            (
+                "app/Pulumi.yaml",
+                "name: app
+runtime: yaml
+extends: ../team/Pulumi.team.yaml
+",
+            ),
+        ]);
+
+        let (merged, diags) = load_project(&dir.path().join("app"), None);
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        assert_eq!(merged.variable_count(), 2);
+        assert_eq!(
+            merged.source_file("owner"),
+            Some("../team/../org/Pulumi.org.yaml")
+        );
+        assert_eq!(merged.source_file("tier"), Some("../team/Pulumi.team.yaml"));
+        assert_eq!(
+            merged.base_files(),
+            ["../team/../org/Pulumi.org.yaml", "../team/Pulumi.team.yaml"]
+        );
+        assert_eq!(merged.main_file(), "Pulumi.yaml");
+    }
+
+    #[test]
+    fn test_load_project_extends_cycle_error() {
+        let dir = make_temp_project(&[
+            (
+                "a/Pulumi.a.yaml",
+                "extends: ../b/Pulumi.b.yaml
+",
+            ),
+            (
+                "b/Pulumi.b.yaml",
+                "extends: ../a/Pulumi.a.yaml
+",
+            ),
+            (
+                "app/Pulumi.yaml",
+                "name: app
+runtime: yaml
+extends: ../a/Pulumi.a.yaml
+",
+            ),
+        ]);
+
+        let (_, diags) = load_project(&dir.path().join("app"), None);
+        assert!(diags.has_errors());
+        assert!(diags.to_string().contains("'extends' cycle"), "{}", diags);
+    }
+
+    #[test]
+    fn test_merge_extends_in_extra_file_error() {
+        let dir = make_temp_project(&[
+            (
+                "base/Pulumi.base.yaml",
+                "variables:
+  x: 1
+",
+            ),
+            (
+                "Pulumi.yaml",
+                "name: test
+runtime: yaml
+",
+            ),
+            (
+                "Pulumi.extra.yaml",
+                "extends: base/Pulumi.base.yaml
+variables:
+  y: 2
+",
+            ),
+        ]);
+
+        let (_, diags) = load_project(dir.path(), None);
+        assert!(diags.has_errors());
+        assert!(
+            diags.to_string().contains("'extends' is only allowed"),
+            "{}",
+            diags
+        );
+    }
+
+    #[test]
+    fn test_merge_runtime_and_plugins_in_extra_file_error() {
+        let dir = make_temp_project(&[
+            (
+                "Pulumi.yaml",
+                "name: test\nruntime: yaml\nplugins:\n  providers:\n    - name: aws\n      path: ./bin\n",
+            ),
+            (
+                "Pulumi.extra.yaml",
+                "runtime: yaml\nplugins:\n  providers:\n    - name: gcp\n      path: ./bin\nvariables:\n  y: 2\n",
+            ),
+        ]);
+
+        let (merged, diags) = load_project(dir.path(), None);
+        let errors = diags.to_string();
+        assert!(
+            errors.contains("'runtime' in Pulumi.extra.yaml repeats the value in Pulumi.yaml"),
+            "{}",
+            errors
+        );
+        assert!(errors.contains("'plugins' is only allowed"), "{}", errors);
+        assert_eq!(merged.plugins().len(), 1);
+        assert_eq!(merged.as_template_decl().runtime.unwrap().name, "yaml");
+    }
+
+    #[test]
+    fn test_merge_repeated_metadata_warns() {
+        let (main_template, _) = parse_template(
+            "name: test
+description: demo
+runtime: yaml
+",
+            None,
+        );
+        let (extra_template, _) = parse_template(
+            "name: test
+description: demo
+runtime: yaml
+variables:
+  y: 2
+",
+            None,
+        );
+
+        let (merged, diags) = merge_templates(
+            main_template,
+            "Pulumi.yaml",
+            vec![("Pulumi.extra.yaml".to_string(), extra_template)],
+        );
+        assert!(!diags.has_errors(), "errors: {}", diags);
+        let warnings: Vec<_> = diags.iter().filter(|d| !d.is_error()).collect();
+        assert_eq!(warnings.len(), 3);
+        assert!(warnings[0].detail.contains("Pulumi.yaml takes precedence"));
+        assert_eq!(merged.variables.len(), 1);
+    }
+
+    #[test]
+    fn test_merge_conflicting_metadata_error() {
+        let (main_template, _) = parse_template(
+            "name: test
+description: demo
+runtime: yaml
+",
+            None,
+        );
+        let (extra_template, _) = parse_template(
+            "description: other
+runtime:
+  name: yaml
+  options:
+    compiler: jinja
+variables:
+  y: 2
+",
+            None,
+        );
+
+        let (_, diags) = merge_templates(
+            main_template,
+            "Pulumi.yaml",
+            vec![("Pulumi.extra.yaml".to_string(), extra_template)],
+        );
+        let errors: Vec<_> = diags.iter().filter(|d| d.is_error()).collect();
+        assert_eq!(errors.len(), 2, "{}", diags);
+        assert_eq!(
+            errors[0].summary,
+            "'description' in Pulumi.extra.yaml (\"other\") conflicts with Pulumi.yaml (\"demo\")"
+        );
+        assert_eq!(
+            errors[1].summary,
+            "'runtime' options in Pulumi.extra.yaml conflict with Pulumi.yaml"
+        );
+    }
+
+    #[test]
+    fn test_merge_unsupported_runtime_error() {
+        let (main_template, _) = parse_template(
+            "name: test
+runtime: yaml
+",
+            None,
+        );
+        let (extra_template, _) = parse_template(
+            "runtime: nodejs
+variables:
+  y: 2
+",
+            None,
+        );
+
+        let (_, diags) = merge_templates(
+            main_template,
+            "Pulumi.yaml",
+            vec![("Pulumi.extra.yaml".to_string(), extra_template)],
+        );
+        let errors: Vec<_> = diags.iter().filter(|d| d.is_error()).collect();
+        assert_eq!(errors.len(), 1, "{}", diags);
+        assert_eq!(
+            errors[0].summary,
+            "runtime 'nodejs' in Pulumi.extra.yaml is not supported"
+        );
+    }
 }
@@ -0,0 +1,261 @@
+//! Numbers in templates and values.
+//!
+//! YAML and JSON integers are kept as `i64` rather than converted to `f64`,
+//! so account IDs, epoch nanoseconds, and other integers above 2^53
+//! survive parsing, builtins, and JSON output exactly. Anything with a
+//! fraction or exponent, and integers outside the `i64` range, are floats.
+//!
+//! Integers and floats compare by numeric value, so `1` equals `1.0`.
+//! Arithmetic stays in integers while it fits and falls back to floats on
+//! overflow. The engine's protobuf values carry only doubles, so integers
+//! sent to the engine beyond 2^53 are rounded there (see
+//! [`crate::eval::protobuf`]).
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Add;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Largest integer up to which every integer is exactly representable as
+/// an `f64`.
+pub const MAX_SAFE_INTEGER: i64 = (1 << 53) - 1;
+
+/// An integer or floating-point number.
+#[derive(Clone, Copy)]
+pub enum Number {
+    Int(i64),
+    Float(f64),
+}
+
+impl Number {
+    /// Returns the number as an `f64`, rounding integers beyond 2^53.
+    pub fn as_f64(self) -> f64 {
+        match self {
+            Number::Int(i) => i as f64,
+            Number::Float(f) => f,
+        }
+    }
+
+    /// Returns the number as an `i64` if it is an integer: an `Int`, or a
+    /// `Float` with no fraction within the `i64` range.
+    pub fn as_i64(self) -> Option<i64> {
+        match self {
+            Number::Int(i) => Some(i),
+            Number::Float(f) => float_to_i64(f),
+        }
+    }
+
+    /// Returns true if this is an `Int`.
+    pub fn is_int(self) -> bool {
+        matches!(self, Number::Int(_))
+    }
+
+    /// Converts a YAML number, keeping integers that fit in an `i64`.
+    pub fn from_yaml(n: &serde_yaml::Number) -> Self {
+        match n.as_i64() {
+            Some(i) => Number::Int(i),
+            None => Number::Float(n.as_f64().unwrap_or(0.0)),
+        }
+    }
+
+    /// Converts a JSON number, keeping integers that fit in an `i64`.
+    pub fn from_json(n: &serde_json::Number) -> Self {
+        match n.as_i64() {
+            Some(i) => Number::Int(i),
+            None => Number::Float(n.as_f64().unwrap_or(0.0)),
+        }
+    }
+
+    /// Returns the JSON form of the number, or `None` for NaN and
+    /// infinities.
+    pub fn to_json(self) -> Option<serde_json::Number> {
+        match self {
+            Number::Int(i) => Some(i.into()),
+            Number::Float(f) => serde_json::Number::from_f64(f),
+        }
+    }
+}
+
+/// Adds in integers unless either side is a float or the sum overflows.
+impl Add for Number {
+    type Output = Number;
+
+    fn add(self, other: Number) -> Number {
+        match (self, other) {
+            (Number::Int(a), Number::Int(b)) => a
+                .checked_add(b)
+                .map_or(Number::Float(a as f64 + b as f64), Number::Int),
+            _ => Number::Float(self.as_f64() + other.as_f64()),
+        }
+    }
+}
+
+/// Converts a float with no fraction to an `i64`, if it is in range.
+fn float_to_i64(f: f64) -> Option<i64> {
+    // 2^63 is exact as an f64; i64::MAX is not.
+    (f.fract() == 0.0 && f >= -(2f64.powi(63)) && f < 2f64.powi(63)).then_some(f as i64)
+}
+
+impl From<f64> for Number {
+    fn from(f: f64) -> Self {
+        Number::Float(f)
+    }
+}
+
+impl From<i64> for Number {
+    fn from(i: i64) -> Self {
+        Number::Int(i)
+    }
+}
+
+impl From<usize> for Number {
+    fn from(n: usize) -> Self {
+        i64::try_from(n).map_or(Number::Float(n as f64), Number::Int)
+    }
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        match (*self, *other) {
+            (Number::Int(a), Number::Int(b)) => a == b,
+            (Number::Float(a), Number::Float(b)) => a == b,
+            (Number::Int(i), Number::Float(f)) | (Number::Float(f), Number::Int(i)) => {
+                float_to_i64(f) == Some(i)
+            }
+        }
+    }
+}
+
+impl PartialEq<f64> for Number {
+    fn eq(&self, other: &f64) -> bool {
+        *self == Number::Float(*other)
+    }
+}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (*self, *other) {
+            (Number::Int(a), Number::Int(b)) => Some(a.cmp(&b)),
+            (Number::Float(a), Number::Float(b)) => a.partial_cmp(&b),
+            (Number::Int(i), Number::Float(f)) => match float_to_i64(f) {
+                Some(j) => Some(i.cmp(&j)),
+                None => (i as f64).partial_cmp(&f),
+            },
+            (Number::Float(_), Number::Int(_)) => other.partial_cmp(self).map(Ordering::reverse),
+        }
+    }
+}
+
+impl PartialOrd<f64> for Number {
+    fn partial_cmp(&self, other: &f64) -> Option<Ordering> {
+        self.partial_cmp(&Number::Float(*other))
+    }
+}
+
+impl fmt::Debug for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::Int(i) => fmt::Debug::fmt(i, f),
+            Number::Float(x) => fmt::Debug::fmt(x, f),
+        }
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::Int(i) => fmt::Display::fmt(i, f),
+            Number::Float(x) => fmt::Display::fmt(x, f),
+        }
+    }
+}
+
+impl Serialize for Number {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            Number::Int(i) => serializer.serialize_i64(i),
+            Number::Float(f) => serializer.serialize_f64(f),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Number {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = Number;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a number")
+            }
+
+            fn visit_i64<E>(self, i: i64) -> Result<Number, E> {
+                Ok(Number::Int(i))
+            }
+
+            fn visit_u64<E>(self, u: u64) -> Result<Number, E> {
+                Ok(i64::try_from(u).map_or(Number::Float(u as f64), Number::Int))
+            }
+
+            fn visit_f64<E>(self, f: f64) -> Result<Number, E> {
+                Ok(Number::Float(f))
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_large_integers_are_exact() {
+        let n = Number::from_json(&serde_json::from_str("9007199254740993").unwrap());
+        assert_eq!(n.as_i64(), Some(9_007_199_254_740_993));
+        assert_eq!(n.to_string(), "9007199254740993");
+        assert_eq!(n.to_json().unwrap().to_string(), "9007199254740993");
+        // The f64 nearest to it is 2^53, a different integer.
+        assert_ne!(n, Number::Float(9_007_199_254_740_993f64));
+    }
+
+    #[test]
+    fn test_mixed_comparison() {
+        assert_eq!(Number::Int(1), Number::Float(1.0));
+        assert_ne!(Number::Int(1), Number::Float(1.5));
+        assert!(Number::Int(1) < Number::Float(1.5));
+        assert!(Number::Int(i64::MAX) > Number::Int(i64::MAX - 1));
+    }
+
+    #[test]
+    fn test_add_overflows_to_float() {
+        assert_eq!(Number::Int(2) + Number::Int(3), Number::Int(5));
+        assert!((Number::Int(2) + Number::Int(3)).is_int());
+        assert!(!(Number::Int(i64::MAX) + Number::Int(1)).is_int());
+        assert!(!(Number::Int(1) + Number::Float(0.5)).is_int());
+    }
+
+    #[test]
+    fn test_from_yaml() {
+        let n: serde_yaml::Number = serde_yaml::from_str("1234567890123456789").unwrap();
+        assert_eq!(
+            Number::from_yaml(&n),
+            Number::Int(1_234_567_890_123_456_789)
+        );
+        let n: serde_yaml::Number = serde_yaml::from_str("1.5").unwrap();
+        assert_eq!(Number::from_yaml(&n), Number::Float(1.5));
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        for n in [Number::Int(i64::MIN), Number::Int(42), Number::Float(0.25)] {
+            let json = serde_json::to_string(&n).unwrap();
+            let back: Number = serde_json::from_str(&json).unwrap();
+            assert_eq!(back, n);
+            assert_eq!(back.is_int(), n.is_int());
+        }
+    }
+}
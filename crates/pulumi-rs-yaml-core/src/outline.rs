@@ -0,0 +1,118 @@
+//! A line-based outline of a template's source.
+//!
+//! The AST has no source positions, so the features that edit source text
+//! ([`crate::format`], and the IDE support in `completion` and
+//! `code_actions`) find mapping keys and their values from the lines and
+//! indentation of the source instead. Parts of a template written in flow
+//! style are not outlined.
+
+/// The mapping keys of a template's source, found by indentation.
+#[cfg_attr(not(feature = "completion"), allow(dead_code))]
+pub(crate) struct Outline<'s> {
+    pub(crate) lines: Vec<&'s str>,
+    pub(crate) newline: &'static str,
+}
+
+/// A mapping key in the source and the lines its value spans.
+#[derive(Clone, Copy)]
+#[cfg_attr(not(feature = "completion"), allow(dead_code))]
+pub(crate) struct Entry<'s> {
+    pub(crate) key: &'s str,
+    pub(crate) line: usize,
+    pub(crate) indent: usize,
+    /// The last line of the value, which is `line` for inline values.
+    pub(crate) last_line: usize,
+    /// The column just after the key's colon.
+    pub(crate) colon_end: usize,
+    /// The value written on the key's line, without a trailing comment.
+    pub(crate) value: &'s str,
+    pub(crate) value_start: usize,
+}
+
+impl<'s> Outline<'s> {
+    pub(crate) fn new(source: &'s str) -> Self {
+        Self {
+            lines: source.lines().collect(),
+            newline: if source.contains("\r\n") {
+                "\r\n"
+            } else {
+                "\n"
+            },
+        }
+    }
+
+    /// The keys directly under `parent`, or the top-level keys.
+    pub(crate) fn entries(&self, parent: Option<&Entry<'s>>) -> Vec<Entry<'s>> {
+        let range = match parent {
+            Some(parent) => parent.line + 1..parent.last_line + 1,
+            None => 0..self.lines.len(),
+        };
+        let mut child_indent = None;
+        let mut entries: Vec<Entry<'s>> = Vec::new();
+        for n in range {
+            let text = self.lines[n];
+            let trimmed = text.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let indent = indent_of(text);
+            match key_of(text) {
+                Some(key) if *child_indent.get_or_insert(indent) == indent => {
+                    entries.push(Entry::new(text, key, n));
+                }
+                _ => {
+                    if let Some(last) = entries.last_mut() {
+                        last.last_line = n;
+                    }
+                }
+            }
+        }
+        entries
+    }
+
+    pub(crate) fn find(&self, entries: &[Entry<'s>], key: &str) -> Option<Entry<'s>> {
+        entries.iter().find(|e| e.key == key).copied()
+    }
+}
+
+impl<'s> Entry<'s> {
+    pub(crate) fn new(text: &'s str, key: &'s str, line: usize) -> Self {
+        let indent = indent_of(text);
+        let trimmed = text[indent..].trim_end();
+        let colon = indent + trimmed.find(": ").unwrap_or(trimmed.len() - 1);
+        let rest = &text[colon + 1..];
+        let value_start = colon + 1 + (rest.len() - rest.trim_start().len());
+        Self {
+            key,
+            line,
+            indent,
+            last_line: line,
+            colon_end: text[..colon + 1].chars().count(),
+            value: inline_value(&text[value_start..]),
+            value_start: text[..value_start].chars().count(),
+        }
+    }
+}
+
+/// The text of an inline value, up to a trailing comment.
+pub(crate) fn inline_value(text: &str) -> &str {
+    let end = match text.chars().next() {
+        Some(quote @ ('"' | '\'')) => text[1..].find(quote).map_or(text.len(), |i| i + 2),
+        _ => text.find(" #").unwrap_or(text.len()),
+    };
+    text[..end].trim_end()
+}
+
+/// The mapping key a line starts, if any.
+pub(crate) fn key_of(text: &str) -> Option<&str> {
+    let trimmed = text.trim_start();
+    let end = trimmed
+        .find(": ")
+        .or_else(|| trimmed.trim_end().strip_suffix(':').map(|key| key.len()))?;
+    let key = trimmed[..end].trim_matches(|c| c == '"' || c == '\'');
+    Some(key).filter(|k| !k.is_empty() && !k.starts_with(['-', '#']))
+}
+
+pub(crate) fn indent_of(text: &str) -> usize {
+    text.len() - text.trim_start_matches(' ').len()
+}
@@ -1,10 +1,12 @@
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
 
 use crate::ast::expr::Expr;
 use crate::ast::template::*;
 use crate::ast::visitor::{walk_expr, InvokeInfo, InvokePackageCollector};
+use crate::diag::Diagnostics;
+use crate::version::VersionConstraint;
 
 // Static YAML keys allocated once, used for package lock parsing.
 static KEY_PKG_DECL_VERSION: LazyLock<serde_yaml::Value> =
@@ -55,6 +57,18 @@ pub struct PackageDependency {
     pub parameterization: Option<ParameterizationDecl>,
 }
 
+/// Returns the Pulumi home directory: `$PULUMI_HOME`, else `~/.pulumi`.
+pub fn pulumi_home() -> PathBuf {
+    if let Some(home) = std::env::var_os("PULUMI_HOME") {
+        return PathBuf::from(home);
+    }
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+        .join(".pulumi")
+}
+
 /// Searches a directory recursively for package lock `.yaml` files.
 ///
 /// Lock files are YAML files that parse as a `PackageDecl` with a valid
@@ -239,8 +253,11 @@ pub fn get_referenced_packages(
         scan_expr_for_invokes(&entry.value, &mut package_map);
     }
 
-    // Scan invoke expressions in resource properties
+    // Scan invoke expressions in resource locals and properties
     for entry in &template.resources {
+        for local in &entry.resource.locals {
+            scan_expr_for_invokes(&local.value, &mut package_map);
+        }
         match &entry.resource.properties {
             ResourceProperties::Map(props) => {
                 for prop in props {
@@ -263,6 +280,7 @@ pub fn get_referenced_packages(
 
     // Sort deterministically
     let mut packages: Vec<PackageDependency> = package_map.into_values().collect();
+    apply_plugins(&mut packages, &template.plugins);
     packages.sort_by(|a, b| {
         a.name
             .cmp(&b.name)
@@ -273,6 +291,144 @@ pub fn get_referenced_packages(
     packages
 }
 
+/// Checks that the version constraints each package is given, by resource
+/// `options.version` and `plugins: providers:` entries, can all be
+/// satisfied by one version. Reports an error naming both declarations for
+/// each constraint incompatible with an earlier one. Constraints that do not
+/// parse are skipped; the parser reports them.
+pub fn check_version_constraints(
+    resources: &[ResourceEntry<'_>],
+    plugins: &[PluginDecl<'_>],
+    source_map: Option<&HashMap<String, String>>,
+) -> Diagnostics {
+    let mut diags = Diagnostics::new();
+    let mut seen: HashMap<&str, Vec<(VersionConstraint, &str, String)>> = HashMap::new();
+    let declared = plugins
+        .iter()
+        .filter_map(|p| {
+            let version = p.version.as_deref()?;
+            Some((p.name.as_ref(), version, format!("plugin '{}'", p.name)))
+        })
+        .chain(resources.iter().filter_map(|r| {
+            let version = r.resource.options.version.as_deref()?;
+            let name = r.logical_name.as_ref();
+            let site = match source_map.and_then(|sm| sm.get(name)) {
+                Some(file) => format!("resource '{}' in {}", name, file),
+                None => format!("resource '{}'", name),
+            };
+            Some((resolve_pkg_name(r.resource.type_.as_ref()), version, site))
+        }));
+    for (pkg, text, site) in declared {
+        let Ok(constraint) = VersionConstraint::parse(text) else {
+            continue;
+        };
+        let earlier = seen.entry(pkg).or_default();
+        if let Some((_, other_text, other_site)) = earlier
+            .iter()
+            .find(|(c, _, _)| !c.is_compatible(&constraint))
+        {
+            diags.error(
+                None,
+                format!(
+                    "package '{}' has conflicting version constraints: '{}' ({}) and '{}' ({})",
+                    pkg, other_text, other_site, text, site
+                ),
+                "",
+            );
+            continue;
+        }
+        earlier.push((constraint, text, site));
+    }
+    diags
+}
+
+/// Resolves the version requirements of `packages` to concrete versions
+/// (see [`VersionConstraint::resolve`]), preferring the plugins installed
+/// in `plugin_dir`. A requirement that resolves to nothing leaves the
+/// version unset. Exact versions and invalid constraints are left as is.
+pub fn resolve_versions(packages: &mut [PackageDependency], plugin_dir: &Path) {
+    for pkg in packages.iter_mut().filter(|p| !p.version.is_empty()) {
+        let Ok(VersionConstraint::Range(req)) = VersionConstraint::parse(&pkg.version) else {
+            continue;
+        };
+        let installed = installed_versions(plugin_dir, &pkg.name);
+        pkg.version = VersionConstraint::Range(req)
+            .resolve(&installed)
+            .map(|v| v.to_string())
+            .unwrap_or_default();
+    }
+}
+
+/// Maps each package name to the version it resolved to, for
+/// [`Evaluator::package_versions`](crate::eval::evaluator::Evaluator::package_versions).
+/// Two packages of one name that resolved to different versions, e.g. two
+/// parameterizations of one plugin, are an error: a resource requiring that
+/// package could be sent with either.
+pub fn package_versions(packages: &[PackageDependency]) -> Result<HashMap<String, String>, String> {
+    let mut versions: HashMap<String, String> = HashMap::new();
+    for pkg in packages {
+        match versions.get(&pkg.name) {
+            Some(version) if *version != pkg.version => {
+                return Err(format!(
+                    "package '{}' resolved to conflicting versions '{}' and '{}'",
+                    pkg.name, version, pkg.version
+                ));
+            }
+            Some(_) => {}
+            None => {
+                versions.insert(pkg.name.clone(), pkg.version.clone());
+            }
+        }
+    }
+    Ok(versions)
+}
+
+/// Returns the versions of the resource plugin `name` installed in
+/// `plugin_dir`.
+pub fn installed_versions(plugin_dir: &Path, name: &str) -> Vec<semver::Version> {
+    let prefix = format!("resource-{}-v", name);
+    let Ok(entries) = std::fs::read_dir(plugin_dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let dir_name = entry.file_name().to_string_lossy().into_owned();
+            semver::Version::parse(dir_name.strip_prefix(&prefix)?).ok()
+        })
+        .collect()
+}
+
+/// Applies the project's `plugins: providers:` entries to `packages`.
+///
+/// A plugin's `version` and `server` fill in what the program leaves unset.
+/// A plugin with a local `path` is loaded by the engine from that path, so
+/// its package is reported without a download server.
+pub fn apply_plugins(packages: &mut [PackageDependency], plugins: &[PluginDecl<'_>]) {
+    for pkg in packages {
+        let Some(plugin) = plugins.iter().find(|p| p.name == pkg.name) else {
+            continue;
+        };
+        if let Some(version) = &plugin.version {
+            if pkg.version.is_empty() {
+                pkg.version = version.to_string();
+            }
+        }
+        if plugin.path.is_some() {
+            pkg.download_url.clear();
+        } else if let Some(server) = &plugin.server {
+            if pkg.download_url.is_empty() {
+                pkg.download_url = server.to_string();
+            }
+        }
+    }
+}
+
+/// Returns true if `plugins` loads the package `name` from a local path.
+pub fn is_local_plugin(plugins: &[PluginDecl<'_>], name: &str) -> bool {
+    plugins.iter().any(|p| p.name == name && p.path.is_some())
+}
+
 /// Adds a package to the map, merging version/download_url if already present.
 fn accept_package(
     map: &mut HashMap<String, PackageDependency>,
@@ -381,6 +537,70 @@ pub fn canonicalize_type_token(type_name: &str) -> String {
     }
 }
 
+/// Looks up resource type tokens in the schemas of installed plugins, such as
+/// a [`crate::schema::SchemaStore`].
+pub trait TypeTokenResolver {
+    /// Returns the canonical token that `token` names, or `None` if the
+    /// resolver does not know it or it is ambiguous.
+    fn resolve_type_token(&self, token: &str) -> Option<String>;
+
+    /// Whether the resolver has the schema of package `pkg`.
+    fn has_package(&self, pkg: &str) -> bool;
+}
+
+/// A type token resolved by [`canonicalize_type_token_with`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedTypeToken {
+    pub token: String,
+    /// Whether the token was guessed by [`canonicalize_type_token`] although
+    /// the resolver has the package's schema, so it likely names no type.
+    pub heuristic: bool,
+}
+
+/// Like [`canonicalize_type_token`], but asks `resolver` first, so short
+/// tokens such as `aws:Bucket` resolve to the module the schema declares the
+/// type in rather than the guessed `aws:index/bucket:Bucket`.
+pub fn canonicalize_type_token_with(
+    type_name: &str,
+    resolver: &dyn TypeTokenResolver,
+) -> ResolvedTypeToken {
+    match resolver.resolve_type_token(type_name) {
+        Some(token) => ResolvedTypeToken {
+            token,
+            heuristic: false,
+        },
+        None => ResolvedTypeToken {
+            token: canonicalize_type_token(type_name),
+            heuristic: resolver.has_package(resolve_pkg_name(type_name)),
+        },
+    }
+}
+
+/// Resolves the type token a resource is written with to the token it is
+/// registered with: the token of a component declared in the project (one
+/// of `local_components`), else the type `resolver` knows, else the
+/// [`canonicalize_type_token`] guess.
+pub fn resolve_type_token(
+    type_name: &str,
+    local_components: &HashSet<String>,
+    resolver: Option<&dyn TypeTokenResolver>,
+) -> ResolvedTypeToken {
+    let component = crate::schema::expand_component_token(type_name);
+    if local_components.contains(component.as_ref()) {
+        return ResolvedTypeToken {
+            token: component.into_owned(),
+            heuristic: false,
+        };
+    }
+    match resolver {
+        Some(resolver) => canonicalize_type_token_with(type_name, resolver),
+        None => ResolvedTypeToken {
+            token: canonicalize_type_token(type_name),
+            heuristic: false,
+        },
+    }
+}
+
 /// Collapses a canonical type token to its shortest display form.
 ///
 /// This is a partial inverse of `canonicalize_type_token()`:
@@ -556,6 +776,47 @@ resources:
         assert_eq!(packages[0].name, "aws");
     }
 
+    #[test]
+    fn test_get_referenced_packages_applies_plugins() {
+        use crate::ast::parse::parse_template;
+
+        let source = r#"
+name: test
+runtime: yaml
+plugins:
+  providers:
+    - name: aws
+      path: ../pulumi-aws/bin
+    - name: acme
+      version: 1.2.0
+      server: https://plugins.example.com
+    - name: random
+      version: 4.0.0
+resources:
+  bucket:
+    type: aws:s3:Bucket
+    options:
+      pluginDownloadURL: https://ignored.example.com
+  widget:
+    type: acme:index:Widget
+  pet:
+    type: random:RandomPet
+    options:
+      version: 4.16.0
+"#;
+        let (template, _) = parse_template(source, None);
+        let packages = get_referenced_packages(&template, &[]);
+        let find = |name: &str| packages.iter().find(|p| p.name == name).unwrap();
+
+        assert_eq!(find("aws").download_url, "");
+        assert!(is_local_plugin(&template.plugins, "aws"));
+        assert_eq!(find("acme").version, "1.2.0");
+        assert_eq!(find("acme").download_url, "https://plugins.example.com");
+        // The program's own version wins over the plugin's.
+        assert_eq!(find("random").version, "4.16.0");
+        assert!(!is_local_plugin(&template.plugins, "random"));
+    }
+
     #[test]
     fn test_get_referenced_packages_skips_pulumi() {
         use crate::ast::parse::parse_template;
@@ -782,4 +1043,22 @@ resources:
         assert_eq!(packages[0].name, "aws");
         assert_eq!(packages[0].version, "5.0.0");
     }
+
+    #[test]
+    fn test_package_versions_reports_conflicts() {
+        let dep = |name: &str, version: &str| PackageDependency {
+            name: name.to_string(),
+            version: version.to_string(),
+            download_url: String::new(),
+            parameterization: None,
+        };
+        let versions =
+            package_versions(&[dep("aws", "6.1.0"), dep("gcp", ""), dep("aws", "6.1.0")]).unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions["aws"], "6.1.0");
+        assert_eq!(
+            package_versions(&[dep("aws", "6.1.0"), dep("aws", "6.2.0")]).unwrap_err(),
+            "package 'aws' resolved to conflicting versions '6.1.0' and '6.2.0'"
+        );
+    }
 }
@@ -0,0 +1,171 @@
+//! Paths written in templates.
+//!
+//! Templates use `/` as the separator on every platform. On Windows these
+//! helpers also accept `\` and treat drive letters and UNC shares as
+//! absolute, so a template written there reads the same way. On Unix those
+//! characters are legal in file names (`a:b.txt`, `a\b.txt`) and keep their
+//! meaning.
+
+use std::path::{Path, PathBuf};
+
+/// Characters that separate path components on the host.
+#[cfg(windows)]
+const SEPARATORS: [char; 2] = ['/', '\\'];
+#[cfg(not(windows))]
+const SEPARATORS: [char; 1] = ['/'];
+
+/// Returns true if `path` is absolute: it starts with a separator, or, on
+/// Windows, with a drive letter such as `C:\x` or `C:/x`.
+///
+/// On Windows a drive-relative path like `C:x` is also treated as absolute,
+/// since it never names a file under the project directory.
+pub fn is_absolute(path: &str) -> bool {
+    if path.starts_with(SEPARATORS) {
+        return true;
+    }
+    let mut chars = path.chars();
+    cfg!(windows)
+        && matches!(
+            (chars.next(), chars.next()),
+            (Some(drive), Some(':')) if drive.is_ascii_alphabetic()
+        )
+}
+
+/// Splits a relative template path into components. Empty and `.`
+/// components are dropped; `..` is kept.
+pub fn components(path: &str) -> impl Iterator<Item = &str> {
+    path.split(SEPARATORS)
+        .filter(|c| !c.is_empty() && *c != ".")
+}
+
+/// Resolves a template path against `base`.
+///
+/// Absolute paths are returned as written. Relative paths are joined to
+/// `base` component by component, so on Windows `scripts\setup.sh` and
+/// `scripts/setup.sh` name the same file.
+pub fn resolve(base: &Path, path: &str) -> PathBuf {
+    if is_absolute(path) {
+        return PathBuf::from(path);
+    }
+    let mut resolved = base.to_path_buf();
+    resolved.extend(components(path));
+    resolved
+}
+
+/// Returns the directory part of a template path, or `None` if the path has
+/// no directory.
+pub fn parent(path: &str) -> Option<&str> {
+    let end = path.rfind(SEPARATORS)?;
+    Some(&path[..end]).filter(|dir| !dir.is_empty())
+}
+
+/// Strips leading `..` components.
+pub fn strip_parent_dirs(path: &str) -> &str {
+    let mut rest = path;
+    while let Some(stripped) = rest
+        .strip_prefix("..")
+        .and_then(|r| r.strip_prefix(SEPARATORS))
+    {
+        rest = stripped;
+    }
+    rest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_absolute() {
+        for path in ["/etc/hosts", "//server/share/file.txt"] {
+            assert!(is_absolute(path), "{}", path);
+        }
+        for path in ["file.txt", "./file.txt", "../file.txt", "1:2", ""] {
+            assert!(!is_absolute(path), "{}", path);
+        }
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_is_absolute_windows() {
+        for path in [
+            "\\Windows",
+            "C:\\Users\\me",
+            "c:/users/me",
+            "D:relative",
+            "\\\\server\\share\\file.txt",
+        ] {
+            assert!(is_absolute(path), "{}", path);
+        }
+        for path in ["..\\file.txt", "dir\\file.txt"] {
+            assert!(!is_absolute(path), "{}", path);
+        }
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_unix_names_keep_their_meaning() {
+        for path in ["a:b.txt", "C:\\Users", "\\Windows"] {
+            assert!(!is_absolute(path), "{}", path);
+        }
+        let parts: Vec<&str> = components("dir/a\\b.txt").collect();
+        assert_eq!(parts, vec!["dir", "a\\b.txt"]);
+        assert_eq!(
+            resolve(Path::new("project"), "a:b.txt"),
+            Path::new("project").join("a:b.txt")
+        );
+        assert_eq!(parent("a\\b.txt"), None);
+        assert_eq!(strip_parent_dirs("..\\env.j2"), "..\\env.j2");
+    }
+
+    #[test]
+    fn test_components() {
+        let parts: Vec<&str> = components("./scripts/nested//setup.sh").collect();
+        assert_eq!(parts, vec!["scripts", "nested", "setup.sh"]);
+        let parts: Vec<&str> = components("../shared/./motd.txt").collect();
+        assert_eq!(parts, vec!["..", "shared", "motd.txt"]);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_components_accept_both_separators() {
+        let parts: Vec<&str> = components(".\\scripts/nested\\\\setup.sh").collect();
+        assert_eq!(parts, vec!["scripts", "nested", "setup.sh"]);
+        let base = Path::new("project");
+        assert_eq!(
+            resolve(base, "scripts\\setup.sh"),
+            resolve(base, "scripts/setup.sh")
+        );
+        assert_eq!(
+            resolve(base, "C:/data/a.txt"),
+            PathBuf::from("C:/data/a.txt")
+        );
+        assert_eq!(parent("..\\base\\Pulumi.base.yaml"), Some("..\\base"));
+        assert_eq!(strip_parent_dirs("../..\\env.j2"), "env.j2");
+    }
+
+    #[test]
+    fn test_resolve() {
+        let base = Path::new("project");
+        assert_eq!(
+            resolve(base, "scripts/setup.sh"),
+            Path::new("project").join("scripts").join("setup.sh")
+        );
+        assert_eq!(resolve(base, "/data/a.txt"), PathBuf::from("/data/a.txt"));
+    }
+
+    #[test]
+    fn test_parent() {
+        assert_eq!(parent("../base/Pulumi.base.yaml"), Some("../base"));
+        assert_eq!(parent("base/Pulumi.yaml"), Some("base"));
+        assert_eq!(parent("Pulumi.yaml"), None);
+    }
+
+    #[test]
+    fn test_strip_parent_dirs() {
+        assert_eq!(strip_parent_dirs("../../env.j2"), "env.j2");
+        assert_eq!(strip_parent_dirs("env.j2"), "env.j2");
+        assert_eq!(strip_parent_dirs("../shared/../env.j2"), "shared/../env.j2");
+        assert_eq!(strip_parent_dirs("..env.j2"), "..env.j2");
+    }
+}
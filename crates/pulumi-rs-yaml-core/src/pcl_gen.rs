@@ -333,14 +333,18 @@ impl PclToYamlGenerator {
             }
             hcl::Expression::Parenthesis(inner) => self.expr_to_yaml(inner),
             hcl::Expression::Conditional(cond) => {
-                // Conditionals don't have a direct YAML equivalent
-                self.diags.warning(
-                    None,
-                    "conditional expression cannot be directly represented in YAML".to_string(),
-                    "manual conversion needed",
+                // cond ? a : b → fn::if: [cond, a, b]
+                let args = vec![
+                    self.expr_to_yaml(&cond.cond_expr),
+                    self.expr_to_yaml(&cond.true_expr),
+                    self.expr_to_yaml(&cond.false_expr),
+                ];
+                let mut map = serde_yaml::Mapping::new();
+                map.insert(
+                    serde_yaml::Value::String("fn::if".to_string()),
+                    serde_yaml::Value::Sequence(args),
                 );
-                // Best effort: emit as the true branch
-                self.expr_to_yaml(&cond.true_expr)
+                serde_yaml::Value::Mapping(map)
             }
             _ => {
                 self.diags.warning(
@@ -1665,18 +1669,20 @@ resource myRes "test:mod:Res" { name = "test" }
         assert_eq!(yaml, "");
     }
 
-    // ─── Conditional expression warning ─────────────────────
+    // ─── Conditional expression ─────────────────────────────
 
     #[test]
-    fn test_conditional_expression_warning() {
+    fn test_conditional_expression() {
         let (yaml, diags) = gen(r#"
 resource myRes "test:mod:Res" {
     value = true ? "yes" : "no"
 }
 "#);
-        let has = diags.iter().any(|d| d.summary.contains("conditional"));
-        assert!(has, "expected conditional warning");
-        assert!(yaml.contains("value: yes"), "got:\n{}", yaml);
+        assert!(!diags.has_errors());
+        assert!(diags.is_empty(), "{}", diags);
+        assert!(yaml.contains("fn::if:"), "got:\n{}", yaml);
+        assert!(yaml.contains("- yes"), "got:\n{}", yaml);
+        assert!(yaml.contains("- no"), "got:\n{}", yaml);
     }
 
     // ─── Parenthesized expression ───────────────────────────
@@ -0,0 +1,934 @@
+//! Execution plans: the serializable form of a loaded project.
+//!
+//! An [`ExecutionPlan`] lists every config, variable, and resource node in
+//! dependency order, together with the topological levels, the dependency
+//! graph, and the source map. Expressions are encoded as JSON objects with a
+//! `"t"` type discriminator (see [`expr_to_json`]), so a plan can be executed
+//! by a host that does not link this crate, such as the Python SDK binding.
+//!
+//! The JSON shape is stable: fields are only ever added, and
+//! [`PLAN_FORMAT_VERSION`] is bumped on incompatible changes.
+
+use std::collections::{BTreeMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value as Json};
+
+use crate::ast::expr::{Expr, InvokeExpr, InvokeOptions};
+use crate::ast::interpolation::InterpolationPart;
+use crate::ast::property::{PropertyAccess, PropertyAccessor};
+use crate::ast::template::{OutputEntry, ResourceOptionsDecl, ResourceProperties, ResourceRange};
+use crate::ast::visitor::{walk_expr, AllRefsCollector};
+use crate::diag::Diagnostics;
+use crate::eval::{fold, graph};
+use crate::multi_file::MergedTemplate;
+use crate::number::Number;
+use crate::packages::{canonicalize_type_token, resolve_type_token, TypeTokenResolver};
+use crate::schema::component_type_tokens;
+
+/// Version of the plan JSON format.
+///
+/// Version 2 made [`ResourceNode::resource_name`] an expression rather than
+/// a string.
+pub const PLAN_FORMAT_VERSION: u32 = 2;
+
+/// A project ready for execution.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionPlan {
+    pub version: u32,
+    pub project_name: String,
+    /// Nodes in topological order.
+    pub nodes: Vec<PlanNode>,
+    pub outputs: Vec<PlanOutput>,
+    /// Logical name → source file.
+    pub source_map: BTreeMap<String, String>,
+    pub diagnostics: Vec<PlanDiagnostic>,
+    /// Node names grouped by topological level; nodes within a level are
+    /// independent of each other.
+    pub levels: Vec<Vec<String>>,
+    /// Node name → names of the nodes it depends on.
+    pub dependencies: BTreeMap<String, Vec<String>>,
+    /// Dependencies between entries declared in different files.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cross_file_edges: Vec<CrossFileEdge>,
+}
+
+/// A config, variable, or resource node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum PlanNode {
+    Config(ConfigNode),
+    Variable(VariableNode),
+    Resource(ResourceNode),
+}
+
+impl PlanNode {
+    /// Returns the node's logical name.
+    pub fn name(&self) -> &str {
+        match self {
+            PlanNode::Config(n) => &n.name,
+            PlanNode::Variable(n) => &n.name,
+            PlanNode::Resource(n) => &n.name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigNode {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+    pub secret: Option<bool>,
+    pub level: usize,
+    pub default: Option<Json>,
+    pub value: Option<Json>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VariableNode {
+    pub name: String,
+    pub value: Json,
+    pub level: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceNode {
+    pub name: String,
+    /// Canonical type token.
+    pub type_token: String,
+    pub level: usize,
+    /// Explicit physical name expression, if set.
+    pub resource_name: Option<Json>,
+    /// Always false in a plan built without schemas; hosts refine it.
+    pub is_component: bool,
+    /// A list of `{"k", "v"}` entries, or a single expression.
+    pub properties: Json,
+    pub options: Json,
+    /// Populated by hosts that have schemas.
+    pub output_properties: Vec<String>,
+    /// Populated by hosts that have schemas.
+    pub property_types: BTreeMap<String, Json>,
+    pub get: Option<GetNode>,
+    /// Resource-scoped `locals:`, in evaluation order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub locals: Vec<PlanProperty>,
+    /// The `count:` expression, for a resource registering that many
+    /// instances.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<Json>,
+    /// The `range:` expression, for a resource registering one instance per
+    /// element.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<Json>,
+}
+
+/// The `get` block of a resource read.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GetNode {
+    pub id: Json,
+    pub state: Vec<PlanProperty>,
+}
+
+/// A key/expression pair.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanProperty {
+    pub k: String,
+    pub v: Json,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanOutput {
+    pub name: String,
+    pub value: Json,
+}
+
+/// A dependency of a node or output on an entry declared in another file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrossFileEdge {
+    pub from: String,
+    pub from_file: String,
+    pub to: String,
+    pub to_file: String,
+    /// Why the edge is suspicious, if it is: a file depending on a file that
+    /// is layered on top of it, or two peer files depending on each other.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanDiagnostic {
+    pub message: String,
+    pub detail: String,
+    pub is_error: bool,
+    pub severity: String,
+}
+
+fn plan_diagnostics(diags: &Diagnostics) -> impl Iterator<Item = PlanDiagnostic> + '_ {
+    diags.iter().map(|d| PlanDiagnostic {
+        message: d.summary.clone(),
+        detail: d.detail.clone(),
+        is_error: d.is_error(),
+        severity: if d.is_error() { "error" } else { "warning" }.to_string(),
+    })
+}
+
+impl ExecutionPlan {
+    /// Builds the plan for a loaded project. Pure builtin calls over literals
+    /// are folded first (see [`fold`]).
+    ///
+    /// `load_diags` are the diagnostics from loading the project; they are
+    /// carried into the plan. Returns the graph diagnostics if the dependency
+    /// graph is invalid.
+    pub fn build(merged: &MergedTemplate, load_diags: &Diagnostics) -> Result<Self, Diagnostics> {
+        Self::build_with_resolver(merged, load_diags, None)
+    }
+
+    /// Like [`ExecutionPlan::build`], resolving resource type tokens with
+    /// `resolver` as the evaluator does (see
+    /// [`resolve_type_token`](crate::packages::resolve_type_token)).
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn build_with_resolver(
+        merged: &MergedTemplate,
+        load_diags: &Diagnostics,
+        resolver: Option<&dyn TypeTokenResolver>,
+    ) -> Result<Self, Diagnostics> {
+        let mut template = merged.as_template_decl();
+        fold::fold_template(&mut template);
+
+        let (sort_result, sort_diags) =
+            graph::topological_sort_with_deps(&template, Some(merged.source_map()));
+        if sort_diags.has_errors() {
+            return Err(sort_diags);
+        }
+        let levels = graph::topological_levels(&sort_result.order, &sort_result.deps);
+        let level_of: BTreeMap<&str, usize> = levels
+            .iter()
+            .enumerate()
+            .flat_map(|(i, names)| names.iter().map(move |n| (n.as_str(), i)))
+            .collect();
+
+        let local_components = component_type_tokens(&template);
+        let mut nodes = Vec::new();
+        for name in &sort_result.order {
+            let level = level_of.get(name.as_str()).copied().unwrap_or(0);
+            if let Some(cfg) = template.config.iter().find(|c| c.key == *name) {
+                nodes.push(PlanNode::Config(ConfigNode {
+                    name: cfg.key.to_string(),
+                    type_: cfg.param.type_.as_deref().map(str::to_string),
+                    secret: cfg.param.secret,
+                    level,
+                    default: cfg.param.default.as_ref().map(expr_to_json),
+                    value: cfg.param.value.as_ref().map(expr_to_json),
+                }));
+            } else if let Some(var) = template.variables.iter().find(|v| v.key == *name) {
+                nodes.push(PlanNode::Variable(VariableNode {
+                    name: var.key.to_string(),
+                    value: expr_to_json(&var.value),
+                    level,
+                }));
+            } else if let Some(res) = template.resources.iter().find(|r| r.logical_name == *name) {
+                let decl = &res.resource;
+                nodes.push(PlanNode::Resource(ResourceNode {
+                    name: res.logical_name.to_string(),
+                    type_token: resolve_type_token(&decl.type_, &local_components, resolver).token,
+                    level,
+                    resource_name: decl.name.as_ref().map(expr_to_json),
+                    is_component: false,
+                    properties: resource_properties_to_json(&decl.properties),
+                    options: resource_options_to_json(&decl.options),
+                    output_properties: Vec::new(),
+                    property_types: BTreeMap::new(),
+                    get: decl.get.as_ref().map(|get| GetNode {
+                        id: expr_to_json(&get.id),
+                        state: get
+                            .state
+                            .iter()
+                            .map(|e| PlanProperty {
+                                k: e.key.to_string(),
+                                v: expr_to_json(&e.value),
+                            })
+                            .collect(),
+                    }),
+                    locals: decl
+                        .locals
+                        .iter()
+                        .map(|l| PlanProperty {
+                            k: l.key.to_string(),
+                            v: expr_to_json(&l.value),
+                        })
+                        .collect(),
+                    count: match &decl.range {
+                        Some(ResourceRange::Count(expr)) => Some(expr_to_json(expr)),
+                        _ => None,
+                    },
+                    range: match &decl.range {
+                        Some(ResourceRange::Items(expr)) => Some(expr_to_json(expr)),
+                        _ => None,
+                    },
+                }));
+            }
+            // The `pulumi` settings node and unknown names are not executed.
+        }
+
+        let outputs = template
+            .outputs
+            .iter()
+            .map(|o| PlanOutput {
+                name: o.key.to_string(),
+                value: expr_to_json(&o.value),
+            })
+            .collect();
+
+        let dependencies: BTreeMap<String, Vec<String>> = sort_result
+            .deps
+            .iter()
+            .map(|(name, deps)| {
+                let mut deps: Vec<String> = deps.iter().cloned().collect();
+                deps.sort();
+                (name.clone(), deps)
+            })
+            .collect();
+        let cross_file_edges = cross_file_edges(merged, &dependencies, &template.outputs);
+
+        Ok(ExecutionPlan {
+            version: PLAN_FORMAT_VERSION,
+            project_name: merged.name().unwrap_or("unknown").to_string(),
+            nodes,
+            outputs,
+            source_map: merged
+                .source_map()
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+            diagnostics: plan_diagnostics(load_diags)
+                .chain(plan_diagnostics(&sort_diags))
+                .collect(),
+            levels,
+            dependencies,
+            cross_file_edges,
+        })
+    }
+}
+
+/// Collects the dependencies of nodes and outputs on entries declared in
+/// other files, sorted by file and name.
+///
+/// Files are layered: `extends:` bases (deepest first), then the main file,
+/// then the additional files and remote imports, which are peers. An edge
+/// from a lower layer to a higher one — e.g. the main file depending on an
+/// additional file — is flagged, as are two peer files depending on each
+/// other.
+fn cross_file_edges(
+    merged: &MergedTemplate,
+    dependencies: &BTreeMap<String, Vec<String>>,
+    outputs: &[OutputEntry<'_>],
+) -> Vec<CrossFileEdge> {
+    let file_of = |name: &str| merged.source_file(name);
+    let bases = merged.base_files();
+    let layer = |file: &str| {
+        bases
+            .iter()
+            .position(|b| b == file)
+            .unwrap_or(if file == merged.main_file() {
+                bases.len()
+            } else {
+                bases.len() + 1
+            })
+    };
+
+    let output_deps = outputs.iter().map(|o| {
+        let mut refs = HashSet::new();
+        walk_expr(&o.value, &AllRefsCollector, &mut refs);
+        let mut refs: Vec<String> = refs.into_iter().map(str::to_string).collect();
+        refs.sort();
+        (o.key.to_string(), refs)
+    });
+    let mut edges: Vec<CrossFileEdge> = dependencies
+        .iter()
+        .map(|(name, deps)| (name.clone(), deps.clone()))
+        .chain(output_deps)
+        .flat_map(|(from, deps)| {
+            let from_file = file_of(&from).map(str::to_string);
+            deps.into_iter().filter_map(move |to| {
+                let from_file = from_file.clone()?;
+                let to_file = file_of(&to)?.to_string();
+                (from_file != to_file).then(|| CrossFileEdge {
+                    from: from.clone(),
+                    from_file,
+                    to,
+                    to_file,
+                    warning: None,
+                })
+            })
+        })
+        .collect();
+
+    let pairs: HashSet<(String, String)> = edges
+        .iter()
+        .map(|e| (e.from_file.clone(), e.to_file.clone()))
+        .collect();
+    for edge in &mut edges {
+        edge.warning = if layer(&edge.from_file) < layer(&edge.to_file) {
+            Some(format!(
+                "{} depends on {}, which is layered on top of it; move '{}' into {} or '{}' out of it",
+                edge.from_file, edge.to_file, edge.to, edge.from_file, edge.from
+            ))
+        } else if layer(&edge.from_file) == layer(&edge.to_file)
+            && pairs.contains(&(edge.to_file.clone(), edge.from_file.clone()))
+        {
+            Some(format!(
+                "{} and {} depend on each other",
+                edge.from_file, edge.to_file
+            ))
+        } else {
+            None
+        };
+    }
+    edges.sort_by(|a, b| {
+        (&a.from_file, &a.from, &a.to_file, &a.to).cmp(&(&b.from_file, &b.from, &b.to_file, &b.to))
+    });
+    edges
+}
+
+// =============================================================================
+// Expression encoding
+// =============================================================================
+
+/// Encodes an expression tree as JSON with a `"t"` type discriminator.
+pub fn expr_to_json(expr: &Expr<'_>) -> Json {
+    match expr {
+        Expr::Null(_) => json!({ "t": "null" }),
+        Expr::Bool(_, b) => json!({ "t": "bool", "v": b }),
+        Expr::Number(_, n) => json!({ "t": "number", "v": number(*n) }),
+        Expr::String(_, s) => json!({ "t": "string", "v": s }),
+        Expr::Symbol(_, access) => json!({ "t": "sym", "a": access_to_json(access) }),
+        Expr::Interpolate(_, parts) => json!({
+            "t": "interp",
+            "parts": parts.iter().map(interp_part_to_json).collect::<Vec<_>>(),
+        }),
+        Expr::List(_, items) => json!({
+            "t": "list",
+            "items": items.iter().map(expr_to_json).collect::<Vec<_>>(),
+        }),
+        Expr::Object(_, entries) => json!({
+            "t": "obj",
+            "entries": entries
+                .iter()
+                .map(|e| json!({ "k": expr_to_json(&e.key), "v": expr_to_json(&e.value) }))
+                .collect::<Vec<_>>(),
+        }),
+        Expr::Invoke(_, inv) => invoke_to_json(inv),
+        Expr::Join(_, sep, vals) => json!({
+            "t": "join",
+            "sep": expr_to_json(sep),
+            "vals": expr_to_json(vals),
+        }),
+        Expr::Select(_, idx, vals) => json!({
+            "t": "select",
+            "idx": expr_to_json(idx),
+            "vals": expr_to_json(vals),
+        }),
+        Expr::Lookup(_, map, key, default) => json!({
+            "t": "lookup",
+            "map": expr_to_json(map),
+            "key": expr_to_json(key),
+            "default": default.as_deref().map(expr_to_json),
+        }),
+        Expr::Env(_, name, default) => json!({
+            "t": "env",
+            "name": expr_to_json(name),
+            "default": default.as_deref().map(expr_to_json),
+        }),
+        Expr::Split(_, sep, src) => json!({
+            "t": "split",
+            "sep": expr_to_json(sep),
+            "src": expr_to_json(src),
+        }),
+        Expr::JsonPatch(_, target, ops) => json!({
+            "t": "jsonPatch",
+            "target": expr_to_json(target),
+            "ops": expr_to_json(ops),
+        }),
+        Expr::TemplateFile(_, path, args) => json!({
+            "t": "templateFile",
+            "path": expr_to_json(path),
+            "args": expr_to_json(args),
+        }),
+        Expr::ValidateJson(_, value, schema) => json!({
+            "t": "validateJson",
+            "value": expr_to_json(value),
+            "schema": expr_to_json(schema),
+        }),
+        Expr::Assert(_, condition, message) => json!({
+            "t": "assert",
+            "condition": expr_to_json(condition),
+            "message": expr_to_json(message),
+        }),
+        Expr::Map(_, list, body) => json!({
+            "t": "map",
+            "list": expr_to_json(list),
+            "body": expr_to_json(body),
+        }),
+        Expr::Filter(_, list, body) => json!({
+            "t": "filter",
+            "list": expr_to_json(list),
+            "body": expr_to_json(body),
+        }),
+        Expr::Foreach(_, foreach) => json!({
+            "t": "foreach",
+            "list": expr_to_json(&foreach.list),
+            "as": foreach.var,
+            "index": foreach.index,
+            "body": expr_to_json(&foreach.body),
+        }),
+        Expr::If(_, condition, then, otherwise) => json!({
+            "t": "if",
+            "condition": expr_to_json(condition),
+            "then": expr_to_json(then),
+            "else": expr_to_json(otherwise),
+        }),
+        Expr::Substring(_, src, start, len) => json!({
+            "t": "substring",
+            "src": expr_to_json(src),
+            "start": expr_to_json(start),
+            "len": expr_to_json(len),
+        }),
+        Expr::Replace(_, src, search, replacement) => json!({
+            "t": "replace",
+            "src": expr_to_json(src),
+            "search": expr_to_json(search),
+            "replacement": expr_to_json(replacement),
+        }),
+        Expr::Equals(_, left, right) => comparison("equals", left, right),
+        Expr::LessThan(_, left, right) => comparison("lessThan", left, right),
+        Expr::LessThanOrEqual(_, left, right) => comparison("lessThanOrEqual", left, right),
+        Expr::GreaterThan(_, left, right) => comparison("greaterThan", left, right),
+        Expr::GreaterThanOrEqual(_, left, right) => comparison("greaterThanOrEqual", left, right),
+        Expr::StartsWith(_, src, prefix) => json!({
+            "t": "startsWith",
+            "src": expr_to_json(src),
+            "prefix": expr_to_json(prefix),
+        }),
+        Expr::Format(_, format, args) => json!({
+            "t": "format",
+            "format": expr_to_json(format),
+            "args": expr_to_json(args),
+        }),
+        Expr::CidrSubnet(_, prefix, newbits, netnum) => json!({
+            "t": "cidrSubnet",
+            "prefix": expr_to_json(prefix),
+            "newbits": expr_to_json(newbits),
+            "netnum": expr_to_json(netnum),
+        }),
+        Expr::CidrHost(_, prefix, hostnum) => json!({
+            "t": "cidrHost",
+            "prefix": expr_to_json(prefix),
+            "hostnum": expr_to_json(hostnum),
+        }),
+        // Single-arg builtins
+        Expr::ToJson(_, a) => single_arg("toJSON", a),
+        Expr::ParseJson(_, a) => single_arg("parseJSON", a),
+        Expr::ParseYaml(_, a) => single_arg("parseYAML", a),
+        Expr::ToBase64(_, a) => single_arg("toBase64", a),
+        Expr::FromBase64(_, a) => single_arg("fromBase64", a),
+        Expr::Secret(_, a) => single_arg("secret", a),
+        Expr::Encrypted(_, a) => single_arg("encrypted", a),
+        Expr::ReadFile(_, a) => single_arg("readFile", a),
+        Expr::ReadFileBinary(_, a) => single_arg("readFileBinary", a),
+        Expr::Merge(_, a) => single_arg("merge", a),
+        Expr::Abs(_, a) => single_arg("abs", a),
+        Expr::Floor(_, a) => single_arg("floor", a),
+        Expr::Ceil(_, a) => single_arg("ceil", a),
+        Expr::Max(_, a) => single_arg("max", a),
+        Expr::Min(_, a) => single_arg("min", a),
+        Expr::FormatDuration(_, a) => single_arg("formatDuration", a),
+        Expr::FormatBytes(_, a) => single_arg("formatBytes", a),
+        Expr::StringLen(_, a) => single_arg("stringLen", a),
+        Expr::Sort(_, a) => single_arg("sort", a),
+        Expr::Unique(_, a) => single_arg("unique", a),
+        Expr::Length(_, a) => single_arg("length", a),
+        Expr::Coalesce(_, a) => single_arg("coalesce", a),
+        Expr::Not(_, a) => single_arg("not", a),
+        Expr::And(_, a) => single_arg("and", a),
+        Expr::Or(_, a) => single_arg("or", a),
+        Expr::ToUpper(_, a) => single_arg("toUpper", a),
+        Expr::ToLower(_, a) => single_arg("toLower", a),
+        Expr::Trim(_, a) => single_arg("trim", a),
+        Expr::CidrNetmask(_, a) => single_arg("cidrNetmask", a),
+        Expr::TimeUtc(_, a) => single_arg("timeUtc", a),
+        Expr::TimeUnix(_, a) => single_arg("timeUnix", a),
+        Expr::Uuid(_, a) => single_arg("uuid", a),
+        Expr::RandomString(_, a) => single_arg("randomString", a),
+        Expr::DateFormat(_, a) => single_arg("dateFormat", a),
+        // Assets/Archives
+        Expr::StringAsset(_, a) => single_arg("stringAsset", a),
+        Expr::FileAsset(_, a) => single_arg("fileAsset", a),
+        Expr::RemoteAsset(_, a) => single_arg("remoteAsset", a),
+        Expr::FileArchive(_, a) => single_arg("fileArchive", a),
+        Expr::RemoteArchive(_, a) => single_arg("remoteArchive", a),
+        Expr::AssetArchive(_, entries) => json!({
+            "t": "assetArchive",
+            "entries": entries
+                .iter()
+                .map(|(k, v)| json!({ "k": k, "v": expr_to_json(v) }))
+                .collect::<Vec<_>>(),
+        }),
+        Expr::Starlark(_, call) => json!({
+            "t": "starlark",
+            "invoke": call.invoke,
+            "input": expr_to_json(&call.input),
+        }),
+        Expr::Open(_, provider, inputs) => json!({
+            "t": "open",
+            "provider": provider,
+            "arg": expr_to_json(inputs),
+        }),
+    }
+}
+
+/// Integral numbers are encoded as JSON integers.
+fn number(n: Number) -> Json {
+    match n.as_i64() {
+        Some(i) => json!(i),
+        None => json!(n.as_f64()),
+    }
+}
+
+fn single_arg(tag: &str, arg: &Expr<'_>) -> Json {
+    json!({ "t": tag, "arg": expr_to_json(arg) })
+}
+
+fn comparison(tag: &str, left: &Expr<'_>, right: &Expr<'_>) -> Json {
+    json!({ "t": tag, "left": expr_to_json(left), "right": expr_to_json(right) })
+}
+
+fn accessor_to_json(acc: &PropertyAccessor<'_>) -> Json {
+    match acc {
+        PropertyAccessor::Name(n) => json!({ "t": "name", "v": n }),
+        PropertyAccessor::StringSubscript(s) => json!({ "t": "str_sub", "v": s }),
+        PropertyAccessor::IntSubscript(i) => json!({ "t": "int_sub", "v": i }),
+        PropertyAccessor::Slice(start, end) => {
+            json!({ "t": "slice", "start": start, "end": end })
+        }
+        PropertyAccessor::Wildcard => json!({ "t": "wildcard" }),
+        PropertyAccessor::PropertySubscript(inner) => {
+            json!({ "t": "access_sub", "v": access_to_json(inner) })
+        }
+    }
+}
+
+fn access_to_json(access: &PropertyAccess<'_>) -> Json {
+    Json::Array(access.accessors.iter().map(accessor_to_json).collect())
+}
+
+fn interp_part_to_json(part: &InterpolationPart<'_>) -> Json {
+    json!({ "text": part.text, "a": part.value.as_ref().map(access_to_json) })
+}
+
+fn invoke_to_json(inv: &InvokeExpr<'_>) -> Json {
+    json!({
+        "t": "invoke",
+        "tok": canonicalize_type_token(&inv.token),
+        "args": inv.call_args.as_deref().map(expr_to_json),
+        "ret": inv.return_,
+        "opts": invoke_options_to_json(&inv.call_opts),
+    })
+}
+
+fn invoke_options_to_json(opts: &InvokeOptions<'_>) -> Json {
+    let mut map = Map::new();
+    if let Some(p) = &opts.parent {
+        map.insert("parent".into(), expr_to_json(p));
+    }
+    if let Some(p) = &opts.provider {
+        map.insert("provider".into(), expr_to_json(p));
+    }
+    if let Some(d) = &opts.depends_on {
+        map.insert("dependsOn".into(), expr_to_json(d));
+    }
+    if let Some(v) = &opts.version {
+        map.insert("version".into(), json!(v));
+    }
+    if let Some(u) = &opts.plugin_download_url {
+        map.insert("pluginDownloadURL".into(), json!(u));
+    }
+    Json::Object(map)
+}
+
+/// Encodes resource options; only options that are set are present.
+pub fn resource_options_to_json(opts: &ResourceOptionsDecl<'_>) -> Json {
+    let mut map = Map::new();
+    let mut expr = |key: &str, e: &Option<Expr<'_>>| {
+        if let Some(e) = e {
+            map.insert(key.into(), expr_to_json(e));
+        }
+    };
+    expr("dependsOn", &opts.depends_on);
+    expr("parent", &opts.parent);
+    expr("provider", &opts.provider);
+    expr("providers", &opts.providers);
+    expr("aliases", &opts.aliases);
+    expr("protect", &opts.protect);
+    expr("replaceWith", &opts.replace_with);
+    expr("deletedWith", &opts.deleted_with);
+    if let Some(b) = opts.delete_before_replace {
+        map.insert("deleteBeforeReplace".into(), json!(b));
+    }
+    if let Some(ic) = &opts.ignore_changes {
+        map.insert("ignoreChanges".into(), json!(ic));
+    }
+    if let Some(imp) = &opts.import {
+        map.insert("import".into(), json!(imp));
+    }
+    if let Some(v) = &opts.version {
+        map.insert("version".into(), json!(v));
+    }
+    if let Some(u) = &opts.plugin_download_url {
+        map.insert("pluginDownloadURL".into(), json!(u));
+    }
+    if let Some(aso) = &opts.additional_secret_outputs {
+        map.insert("additionalSecretOutputs".into(), json!(aso));
+    }
+    if let Some(ct) = &opts.custom_timeouts {
+        let mut timeouts = Map::new();
+        for (key, value) in [
+            ("create", &ct.create),
+            ("update", &ct.update),
+            ("delete", &ct.delete),
+        ] {
+            if let Some(v) = value {
+                timeouts.insert(key.into(), json!(v));
+            }
+        }
+        map.insert("customTimeouts".into(), Json::Object(timeouts));
+    }
+    if let Some(roc) = &opts.replace_on_changes {
+        map.insert("replaceOnChanges".into(), json!(roc));
+    }
+    if let Some(b) = opts.retain_on_delete {
+        map.insert("retainOnDelete".into(), json!(b));
+    }
+    if let Some(hd) = &opts.hide_diffs {
+        map.insert("hideDiffs".into(), json!(hd));
+    }
+    Json::Object(map)
+}
+
+/// Encodes resource properties as a list of `{"k", "v"}` entries, or as a
+/// single expression when the whole property map is an expression.
+pub fn resource_properties_to_json(props: &ResourceProperties<'_>) -> Json {
+    match props {
+        ResourceProperties::Map(entries) => Json::Array(
+            entries
+                .iter()
+                .map(|e| json!({ "k": e.key, "v": expr_to_json(&e.value) }))
+                .collect(),
+        ),
+        ResourceProperties::Expr(expr) => expr_to_json(expr),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::parse::parse_template;
+
+    fn expr(source: &str) -> Json {
+        let yaml = format!("variables:\n  v:\n{}", source);
+        let (template, diags) = parse_template(&yaml, None);
+        assert!(!diags.has_errors(), "{}", diags);
+        expr_to_json(&template.variables[0].value)
+    }
+
+    #[test]
+    fn test_expr_encoding() {
+        assert_eq!(expr("    42"), json!({ "t": "number", "v": 42 }));
+        assert_eq!(expr("    1.5"), json!({ "t": "number", "v": 1.5 }));
+        assert_eq!(
+            expr("    ${res.items[0]}"),
+            json!({
+                "t": "sym",
+                "a": [
+                    { "t": "name", "v": "res" },
+                    { "t": "name", "v": "items" },
+                    { "t": "int_sub", "v": 0 },
+                ],
+            })
+        );
+        assert_eq!(
+            expr("    fn::invoke:\n      function: aws:index:getRegion\n      return: name"),
+            json!({
+                "t": "invoke",
+                "tok": "aws:index/getRegion:getRegion",
+                "args": null,
+                "ret": "name",
+                "opts": {},
+            })
+        );
+    }
+
+    #[test]
+    fn test_plan_round_trips_through_json() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Pulumi.yaml"),
+            r#"
+name: plan-test
+runtime: yaml
+config:
+  prefix:
+    type: string
+    default: app
+variables:
+  bucketName: ${prefix}-bucket
+resources:
+  bucket:
+    type: aws:s3:Bucket
+    properties:
+      bucket: ${bucketName}
+      tags:
+        fn::toJSON: { team: infra }
+    options:
+      protect: true
+outputs:
+  name: ${bucket.bucket}
+"#,
+        )
+        .unwrap();
+        let (merged, diags) = crate::multi_file::load_project(dir.path(), None);
+        let plan = ExecutionPlan::build(&merged, &diags).unwrap();
+
+        assert_eq!(plan.project_name, "plan-test");
+        let names: Vec<&str> = plan.nodes.iter().map(|n| n.name()).collect();
+        assert_eq!(names, ["prefix", "bucketName", "bucket"]);
+        assert_eq!(plan.levels.len(), 3);
+        assert_eq!(plan.dependencies["bucket"], ["bucketName"]);
+
+        let PlanNode::Resource(bucket) = &plan.nodes[2] else {
+            panic!("expected a resource node");
+        };
+        assert_eq!(bucket.type_token, "aws:s3/bucket:Bucket");
+        assert_eq!(
+            bucket.options,
+            json!({ "protect": { "t": "bool", "v": true } })
+        );
+        // The literal fn::toJSON call is folded.
+        assert_eq!(
+            bucket.properties[1],
+            json!({ "k": "tags", "v": { "t": "string", "v": "{\"team\":\"infra\"}" } })
+        );
+
+        let encoded = serde_json::to_string(&plan).unwrap();
+        let decoded: ExecutionPlan = serde_json::from_str(&encoded).unwrap();
+        assert_eq!(decoded, plan);
+        let value: Json = serde_json::from_str(&encoded).unwrap();
+        assert_eq!(value["nodes"][0]["kind"], "config");
+        assert!(value.get("cross_file_edges").is_none());
+    }
+
+    #[test]
+    fn test_cross_file_edges() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, src: &str| std::fs::write(dir.path().join(name), src).unwrap();
+        write(
+            "Pulumi.yaml",
+            "name: edges\nruntime: yaml\nvariables:\n  region: us-east-1\noutputs:\n  url: ${site.url}\n",
+        );
+        write(
+            "Pulumi.a.yaml",
+            "resources:\n  logs:\n    type: test:Bucket\n  site:\n    type: test:Site\n    properties:\n      region: ${region}\n      log: ${logName}\n",
+        );
+        write(
+            "Pulumi.b.yaml",
+            "variables:\n  logName: ${logs.name}/site\n",
+        );
+        let (merged, diags) = crate::multi_file::load_project(dir.path(), None);
+        assert!(!diags.has_errors(), "{}", diags);
+        let plan = ExecutionPlan::build(&merged, &diags).unwrap();
+
+        let edges: Vec<(&str, &str, bool)> = plan
+            .cross_file_edges
+            .iter()
+            .map(|e| (e.from.as_str(), e.to.as_str(), e.warning.is_some()))
+            .collect();
+        assert_eq!(
+            edges,
+            [
+                ("site", "logName", true),
+                ("site", "region", false),
+                ("logName", "logs", true),
+                ("url", "site", true),
+            ]
+        );
+        let url = &plan.cross_file_edges[3];
+        assert_eq!(url.from_file, "Pulumi.yaml");
+        assert_eq!(url.to_file, "Pulumi.a.yaml");
+        assert!(url
+            .warning
+            .as_deref()
+            .unwrap()
+            .starts_with("Pulumi.yaml depends on Pulumi.a.yaml, which is layered on top of it"));
+        assert_eq!(
+            plan.cross_file_edges[0].warning.as_deref(),
+            Some("Pulumi.a.yaml and Pulumi.b.yaml depend on each other")
+        );
+    }
+
+    #[test]
+    fn test_build_resolves_type_tokens() {
+        use crate::schema::{PackageSchema, ResourceTypeInfo, SchemaStore};
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Pulumi.yaml"),
+            r#"name: shop
+runtime: yaml
+components:
+  Site:
+    resources:
+      logs:
+        type: aws:Bucket
+resources:
+  logs:
+    type: aws:Bucket
+  site:
+    type: shop:Site
+"#,
+        )
+        .unwrap();
+        let (merged, diags) = crate::multi_file::load_project(dir.path(), None);
+        assert!(!diags.has_errors(), "{}", diags);
+        let type_tokens = |plan: &ExecutionPlan| -> Vec<String> {
+            plan.nodes
+                .iter()
+                .filter_map(|n| match n {
+                    PlanNode::Resource(r) => Some(r.type_token.clone()),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let plan = ExecutionPlan::build(&merged, &diags).unwrap();
+        assert_eq!(
+            type_tokens(&plan),
+            ["aws:index/bucket:Bucket", "shop:index:Site"]
+        );
+
+        let mut store = SchemaStore::new();
+        store.insert(PackageSchema {
+            name: "aws".to_string(),
+            resources: [(
+                "aws:s3/bucket:Bucket".to_string(),
+                ResourceTypeInfo::default(),
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        });
+        let plan = ExecutionPlan::build_with_resolver(&merged, &diags, Some(&store)).unwrap();
+        assert_eq!(
+            type_tokens(&plan),
+            ["aws:s3/bucket:Bucket", "shop:index:Site"]
+        );
+    }
+}
@@ -0,0 +1,392 @@
+//! Remote template fragments (`imports:`).
+//!
+//! `Pulumi.yaml` may pin YAML fragments hosted elsewhere:
+//!
+//! ```yaml
+//! imports:
+//!   - url: https://example.com/fragments/network.yaml
+//!     sha256: 9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08
+//!   - url: oci://ghcr.io/acme/fragments:v1
+//!     sha256: 2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae
+//! ```
+//!
+//! Each fragment is fetched once, verified against its SHA-256, and stored in
+//! a content-addressed cache (`<cache>/sha256/<digest>.yaml`); later loads are
+//! served from the cache without network access. Verified fragments are merged
+//! like additional `Pulumi.*.yaml` files.
+//!
+//! An `oci://<registry>/<repository>:<tag>` (or `@sha256:<digest>`) reference
+//! resolves to the first layer of the artifact's manifest, so a fragment pushed
+//! with `oras push <ref> network.yaml` can be imported directly. Anonymous
+//! bearer-token authentication is supported; private registries are not.
+//!
+//! Fetching requires the `remote-imports` feature.
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::ast::template::ImportDecl;
+use crate::packages::pulumi_home;
+
+/// Environment variable overriding the import cache directory.
+pub const IMPORT_CACHE_ENV: &str = "PULUMI_YAML_IMPORT_CACHE";
+
+/// Upper bound on the size of a fetched fragment.
+#[cfg_attr(not(feature = "remote-imports"), allow(dead_code))]
+const MAX_FRAGMENT_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Errors from resolving a remote import.
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error("failed to fetch {url}: {reason}")]
+    Fetch { url: String, reason: String },
+    #[error("integrity check failed for {url}: expected sha256 {expected}, got {actual}")]
+    Integrity {
+        url: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("{url} is not valid UTF-8")]
+    Encoding { url: String },
+    #[error("cannot fetch {url}: remote imports are not enabled in this build")]
+    Unsupported { url: String },
+    #[error("import cache {}: {source}", path.display())]
+    Cache {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+/// Returns the import cache directory: `$PULUMI_YAML_IMPORT_CACHE`, else
+/// `$PULUMI_HOME/yaml-imports`, else `~/.pulumi/yaml-imports`.
+pub fn default_cache_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os(IMPORT_CACHE_ENV) {
+        return PathBuf::from(dir);
+    }
+    pulumi_home().join("yaml-imports")
+}
+
+/// Returns the lowercase hex SHA-256 of `bytes`.
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, b| {
+            let _ = write!(hex, "{:02x}", b);
+            hex
+        })
+}
+
+/// Returns the verified contents of `import`, from `cache_dir` if present,
+/// otherwise fetched and added to the cache.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(url = %import.url)))]
+pub fn resolve_import(import: &ImportDecl<'_>, cache_dir: &Path) -> Result<String, ImportError> {
+    let path = cache_dir
+        .join("sha256")
+        .join(format!("{}.yaml", import.sha256));
+
+    let bytes = match std::fs::read(&path) {
+        // A corrupted cache entry is discarded and fetched again.
+        Ok(bytes) if sha256_hex(&bytes) == import.sha256 => bytes,
+        _ => {
+            let bytes = fetch(&import.url)?;
+            let actual = sha256_hex(&bytes);
+            if actual != import.sha256 {
+                return Err(ImportError::Integrity {
+                    url: import.url.to_string(),
+                    expected: import.sha256.to_string(),
+                    actual,
+                });
+            }
+            store(&path, &bytes)?;
+            bytes
+        }
+    };
+
+    String::from_utf8(bytes).map_err(|_| ImportError::Encoding {
+        url: import.url.to_string(),
+    })
+}
+
+/// Writes a cache entry atomically so concurrent loads never see a partial file.
+fn store(path: &Path, bytes: &[u8]) -> Result<(), ImportError> {
+    let cache_err = |source| ImportError::Cache {
+        path: path.to_path_buf(),
+        source,
+    };
+    let dir = path.parent().expect("cache entries live in a directory");
+    std::fs::create_dir_all(dir).map_err(cache_err)?;
+    let tmp = dir.join(format!(".{}.tmp", std::process::id()));
+    std::fs::write(&tmp, bytes).map_err(cache_err)?;
+    std::fs::rename(&tmp, path).map_err(cache_err)
+}
+
+#[cfg(not(feature = "remote-imports"))]
+fn fetch(url: &str) -> Result<Vec<u8>, ImportError> {
+    Err(ImportError::Unsupported {
+        url: url.to_string(),
+    })
+}
+
+#[cfg(feature = "remote-imports")]
+fn fetch(url: &str) -> Result<Vec<u8>, ImportError> {
+    let result = match url.strip_prefix("oci://") {
+        Some(reference) => oci::fetch(reference),
+        None => http::get(url, &[]).map_err(|e| e.to_string()),
+    };
+    result.map_err(|reason| ImportError::Fetch {
+        url: url.to_string(),
+        reason,
+    })
+}
+
+#[cfg(feature = "remote-imports")]
+mod http {
+    use std::io::Read;
+
+    use super::MAX_FRAGMENT_BYTES;
+
+    /// GETs `url` and returns the body, or the failed response.
+    pub fn get(url: &str, headers: &[(&str, &str)]) -> Result<Vec<u8>, Box<ureq::Error>> {
+        let mut request = ureq::get(url);
+        for (name, value) in headers {
+            request = request.set(name, value);
+        }
+        let response = request.call().map_err(Box::new)?;
+        let mut body = Vec::new();
+        response
+            .into_reader()
+            .take(MAX_FRAGMENT_BYTES + 1)
+            .read_to_end(&mut body)
+            .map_err(|e| Box::new(ureq::Error::from(e)))?;
+        if body.len() as u64 > MAX_FRAGMENT_BYTES {
+            return Err(Box::new(ureq::Error::from(std::io::Error::other(format!(
+                "response exceeds {} bytes",
+                MAX_FRAGMENT_BYTES
+            )))));
+        }
+        Ok(body)
+    }
+}
+
+#[cfg(feature = "remote-imports")]
+mod oci {
+    use super::http;
+
+    const MANIFEST_TYPES: &str = "application/vnd.oci.image.manifest.v1+json, \
+                                  application/vnd.docker.distribution.manifest.v2+json";
+
+    /// A parsed `<registry>/<repository>(:<tag>|@<digest>)` reference.
+    #[derive(Debug, PartialEq)]
+    pub struct Reference<'a> {
+        pub registry: &'a str,
+        pub repository: &'a str,
+        pub reference: &'a str,
+    }
+
+    pub fn parse_reference(s: &str) -> Result<Reference<'_>, String> {
+        let (registry, rest) = s
+            .split_once('/')
+            .ok_or_else(|| format!("invalid OCI reference {:?}: missing repository", s))?;
+        let (repository, reference) = match rest.split_once('@') {
+            Some((repo, digest)) => (repo, digest),
+            // The tag separator is the last ':' after the last '/'.
+            None => match rest.rfind(':').filter(|&i| !rest[i..].contains('/')) {
+                Some(i) => (&rest[..i], &rest[i + 1..]),
+                None => (rest, "latest"),
+            },
+        };
+        if repository.is_empty() || reference.is_empty() {
+            return Err(format!("invalid OCI reference {:?}", s));
+        }
+        Ok(Reference {
+            registry,
+            repository,
+            reference,
+        })
+    }
+
+    /// Parses `Bearer realm="...",service="...",scope="..."` into the token URL.
+    pub fn token_url(challenge: &str) -> Option<String> {
+        let params = challenge.strip_prefix("Bearer ")?;
+        let mut realm = None;
+        let mut query = Vec::new();
+        for param in params.split(',') {
+            let (key, value) = param.trim().split_once('=')?;
+            let value = value.trim_matches('"');
+            match key {
+                "realm" => realm = Some(value),
+                "service" | "scope" => query.push(format!("{}={}", key, value)),
+                _ => {}
+            }
+        }
+        let realm = realm?;
+        if query.is_empty() {
+            Some(realm.to_string())
+        } else {
+            Some(format!("{}?{}", realm, query.join("&")))
+        }
+    }
+
+    /// GETs a registry URL, answering one anonymous bearer-token challenge.
+    fn registry_get(
+        url: &str,
+        accept: &str,
+        token: &mut Option<String>,
+    ) -> Result<Vec<u8>, String> {
+        let auth = token.as_ref().map(|t| format!("Bearer {}", t));
+        let mut headers = vec![("Accept", accept)];
+        if let Some(auth) = &auth {
+            headers.push(("Authorization", auth));
+        }
+        match http::get(url, &headers) {
+            Ok(body) => Ok(body),
+            Err(e) => match *e {
+                ureq::Error::Status(401, response) if token.is_none() => {
+                    let challenge = response.header("www-authenticate").unwrap_or_default();
+                    let token_url = token_url(challenge)
+                        .ok_or_else(|| format!("unsupported registry auth {:?}", challenge))?;
+                    let body = http::get(&token_url, &[]).map_err(|e| e.to_string())?;
+                    let json: serde_json::Value =
+                        serde_json::from_slice(&body).map_err(|e| e.to_string())?;
+                    let value = json
+                        .get("token")
+                        .or_else(|| json.get("access_token"))
+                        .and_then(|t| t.as_str())
+                        .ok_or("registry token response has no token")?;
+                    *token = Some(value.to_string());
+                    registry_get(url, accept, token)
+                }
+                other => Err(other.to_string()),
+            },
+        }
+    }
+
+    /// Fetches the first layer of the artifact at `reference`.
+    pub fn fetch(reference: &str) -> Result<Vec<u8>, String> {
+        let r = parse_reference(reference)?;
+        let base = format!("https://{}/v2/{}", r.registry, r.repository);
+        let mut token = None;
+        let manifest = registry_get(
+            &format!("{}/manifests/{}", base, r.reference),
+            MANIFEST_TYPES,
+            &mut token,
+        )?;
+        let manifest: serde_json::Value =
+            serde_json::from_slice(&manifest).map_err(|e| format!("invalid manifest: {}", e))?;
+        let digest = manifest
+            .pointer("/layers/0/digest")
+            .and_then(|d| d.as_str())
+            .ok_or("manifest has no layers")?;
+        registry_get(
+            &format!("{}/blobs/{}", base, digest),
+            "application/octet-stream",
+            &mut token,
+        )
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_parse_reference() {
+            assert_eq!(
+                parse_reference("ghcr.io/acme/fragments:v1").unwrap(),
+                Reference {
+                    registry: "ghcr.io",
+                    repository: "acme/fragments",
+                    reference: "v1",
+                }
+            );
+            assert_eq!(
+                parse_reference("localhost:5000/frag").unwrap(),
+                Reference {
+                    registry: "localhost:5000",
+                    repository: "frag",
+                    reference: "latest",
+                }
+            );
+            assert_eq!(
+                parse_reference("ghcr.io/acme/frag@sha256:abcd")
+                    .unwrap()
+                    .reference,
+                "sha256:abcd"
+            );
+            assert!(parse_reference("ghcr.io").is_err());
+        }
+
+        #[test]
+        fn test_token_url() {
+            assert_eq!(
+                token_url(
+                    r#"Bearer realm="https://ghcr.io/token",service="ghcr.io",scope="repository:acme/frag:pull""#
+                )
+                .unwrap(),
+                "https://ghcr.io/token?service=ghcr.io&scope=repository:acme/frag:pull"
+            );
+            assert!(token_url("Basic realm=\"x\"").is_none());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    const FRAGMENT: &str = "resources:\n  shared:\n    type: test:index:Shared\n";
+
+    fn import(sha256: String) -> ImportDecl<'static> {
+        ImportDecl {
+            url: Cow::Borrowed("https://fragments.invalid/shared.yaml"),
+            sha256: Cow::Owned(sha256),
+        }
+    }
+
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(
+            sha256_hex(b"test"),
+            "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+        );
+    }
+
+    #[test]
+    fn test_resolve_from_cache() {
+        let cache = tempfile::tempdir().unwrap();
+        let digest = sha256_hex(FRAGMENT.as_bytes());
+        store(
+            &cache.path().join("sha256").join(format!("{}.yaml", digest)),
+            FRAGMENT.as_bytes(),
+        )
+        .unwrap();
+
+        let contents = resolve_import(&import(digest), cache.path()).unwrap();
+        assert_eq!(contents, FRAGMENT);
+    }
+
+    #[test]
+    fn test_corrupted_cache_entry_is_refetched() {
+        let cache = tempfile::tempdir().unwrap();
+        let digest = sha256_hex(FRAGMENT.as_bytes());
+        store(
+            &cache.path().join("sha256").join(format!("{}.yaml", digest)),
+            b"tampered",
+        )
+        .unwrap();
+
+        // The host does not resolve, so the refetch fails instead of
+        // returning the tampered contents.
+        let err = resolve_import(&import(digest), cache.path()).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                ImportError::Fetch { .. } | ImportError::Unsupported { .. }
+            ),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,721 @@
+//! New projects from built-in archetypes.
+//!
+//! [`scaffold`] generates the files of a new YAML project for one of the
+//! [`Archetype`]s on one of the supported [`Cloud`]s, with its config,
+//! variables, resources, and outputs already wired together. When a schema
+//! store is given, required properties the archetype does not set are added
+//! with placeholder values and type tokens are made canonical, using the
+//! same fixes as [`crate::code_actions`], so the project type checks
+//! against the provider versions the host has.
+
+use crate::code_actions::{apply_edits, code_actions, CodeActionKind};
+use crate::schema::SchemaStore;
+
+/// What a new project deploys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Archetype {
+    /// A bucket serving a static website.
+    StaticSite,
+    /// A public container running a given image.
+    ContainerService,
+    /// A serverless function behind an HTTP endpoint.
+    ServerlessApi,
+}
+
+impl Archetype {
+    pub const ALL: [Archetype; 3] = [
+        Archetype::StaticSite,
+        Archetype::ContainerService,
+        Archetype::ServerlessApi,
+    ];
+
+    /// The name the archetype is chosen by, like `static-site`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Archetype::StaticSite => "static-site",
+            Archetype::ContainerService => "container-service",
+            Archetype::ServerlessApi => "serverless-api",
+        }
+    }
+
+    /// Finds an archetype by name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.name() == name)
+    }
+
+    /// A one-line description of what the archetype deploys.
+    pub fn description(self) -> &'static str {
+        match self {
+            Archetype::StaticSite => "A static website served from a storage bucket",
+            Archetype::ContainerService => "A container image running as a public web service",
+            Archetype::ServerlessApi => "A serverless function behind an HTTP endpoint",
+        }
+    }
+}
+
+/// The cloud a new project deploys to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Cloud {
+    #[default]
+    Aws,
+    Gcp,
+    Azure,
+}
+
+impl Cloud {
+    pub const ALL: [Cloud; 3] = [Cloud::Aws, Cloud::Gcp, Cloud::Azure];
+
+    /// The name the cloud is chosen by, which is also the name of its
+    /// provider package: `aws`, `gcp`, or `azure`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Cloud::Aws => "aws",
+            Cloud::Gcp => "gcp",
+            Cloud::Azure => "azure",
+        }
+    }
+
+    /// Finds a cloud by name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.name() == name)
+    }
+}
+
+/// What [`scaffold`] generates.
+#[derive(Debug, Clone, Copy)]
+pub struct ScaffoldOptions<'a> {
+    /// The project name.
+    pub name: &'a str,
+    pub archetype: Archetype,
+    pub cloud: Cloud,
+    /// Provider schemas to complete the resources from.
+    pub schema: Option<&'a SchemaStore>,
+}
+
+/// A file of a new project, at a `/`-separated path relative to the project
+/// directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScaffoldFile {
+    pub path: String,
+    pub contents: String,
+}
+
+/// Generates the files of a new project, `Pulumi.yaml` first.
+pub fn scaffold(options: &ScaffoldOptions<'_>) -> Result<Vec<ScaffoldFile>, String> {
+    check_project_name(options.name)?;
+    let (template, files) = archetype_files(options.archetype, options.cloud);
+    let mut project = template
+        .replace("{{name}}", options.name)
+        .replace("{{description}}", options.archetype.description());
+    if let Some(store) = options.schema {
+        project = complete_from_schema(store, project);
+    }
+
+    let mut scaffold = vec![ScaffoldFile {
+        path: "Pulumi.yaml".to_string(),
+        contents: project,
+    }];
+    scaffold.extend(files.iter().map(|(path, contents)| ScaffoldFile {
+        path: path.to_string(),
+        contents: contents.to_string(),
+    }));
+    Ok(scaffold)
+}
+
+/// Checks a project name the way the Pulumi CLI does: letters, digits,
+/// `-`, `_` and `.`, at most 100 characters.
+fn check_project_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("the project name must not be empty".to_string());
+    }
+    if name.len() > 100 {
+        return Err("the project name must be at most 100 characters".to_string());
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err(format!(
+            "invalid project name '{}': use letters, digits, '-', '_' and '.'",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// Applies the schema fixes that complete a template — canonical type
+/// tokens and required properties — one at a time, since each changes the
+/// lines the next is placed by.
+fn complete_from_schema(store: &SchemaStore, mut source: String) -> String {
+    while let Some(action) = code_actions(store, &source).into_iter().find(|a| {
+        matches!(
+            a.kind,
+            CodeActionKind::CanonicalTypeToken | CodeActionKind::AddRequiredProperty
+        )
+    }) {
+        source = apply_edits(&source, &action.edits);
+    }
+    source
+}
+
+/// The `Pulumi.yaml` template of an archetype and the other files it needs.
+fn archetype_files(
+    archetype: Archetype,
+    cloud: Cloud,
+) -> (&'static str, &'static [(&'static str, &'static str)]) {
+    match (archetype, cloud) {
+        (Archetype::StaticSite, Cloud::Aws) => (AWS_STATIC_SITE, SITE_FILES),
+        (Archetype::StaticSite, Cloud::Gcp) => (GCP_STATIC_SITE, SITE_FILES),
+        (Archetype::StaticSite, Cloud::Azure) => (AZURE_STATIC_SITE, SITE_FILES),
+        (Archetype::ContainerService, Cloud::Aws) => (AWS_CONTAINER_SERVICE, &[]),
+        (Archetype::ContainerService, Cloud::Gcp) => (GCP_CONTAINER_SERVICE, &[]),
+        (Archetype::ContainerService, Cloud::Azure) => (AZURE_CONTAINER_SERVICE, &[]),
+        (Archetype::ServerlessApi, Cloud::Aws) => (AWS_SERVERLESS_API, AWS_FUNCTION_FILES),
+        (Archetype::ServerlessApi, Cloud::Gcp) => (GCP_SERVERLESS_API, GCP_FUNCTION_FILES),
+        (Archetype::ServerlessApi, Cloud::Azure) => (AZURE_SERVERLESS_API, AZURE_FUNCTION_FILES),
+    }
+}
+
+const SITE_FILES: &[(&str, &str)] = &[(
+    "www/index.html",
+    "<!DOCTYPE html>\n<html>\n  <head><title>Hello</title></head>\n  <body><h1>Hello, world!</h1></body>\n</html>\n",
+)];
+
+const AWS_FUNCTION_FILES: &[(&str, &str)] = &[(
+    "app/index.js",
+    "exports.handler = async () => ({\n  statusCode: 200,\n  body: \"Hello, world!\",\n});\n",
+)];
+
+const GCP_FUNCTION_FILES: &[(&str, &str)] = &[
+    (
+        "app/index.js",
+        "exports.handler = (req, res) => {\n  res.send(\"Hello, world!\");\n};\n",
+    ),
+    (
+        "app/package.json",
+        "{\n  \"name\": \"app\",\n  \"main\": \"index.js\"\n}\n",
+    ),
+];
+
+const AZURE_FUNCTION_FILES: &[(&str, &str)] = &[
+    (
+        "app/host.json",
+        "{\n  \"version\": \"2.0\"\n}\n",
+    ),
+    (
+        "app/hello/function.json",
+        "{\n  \"bindings\": [\n    { \"type\": \"httpTrigger\", \"direction\": \"in\", \"name\": \"req\", \"authLevel\": \"anonymous\", \"methods\": [\"get\"] },\n    { \"type\": \"http\", \"direction\": \"out\", \"name\": \"res\" }\n  ]\n}\n",
+    ),
+    (
+        "app/hello/index.js",
+        "module.exports = async (context) => {\n  context.res = { body: \"Hello, world!\" };\n};\n",
+    ),
+];
+
+const AWS_STATIC_SITE: &str = r#"name: {{name}}
+description: {{description}}
+runtime: yaml
+config:
+  indexDocument:
+    type: string
+    default: index.html
+resources:
+  bucket:
+    type: aws:s3:BucketV2
+  website:
+    type: aws:s3:BucketWebsiteConfigurationV2
+    properties:
+      bucket: ${bucket.id}
+      indexDocument:
+        suffix: ${indexDocument}
+  ownershipControls:
+    type: aws:s3:BucketOwnershipControls
+    properties:
+      bucket: ${bucket.id}
+      rule:
+        objectOwnership: ObjectWriter
+  publicAccessBlock:
+    type: aws:s3:BucketPublicAccessBlock
+    properties:
+      bucket: ${bucket.id}
+      blockPublicAcls: false
+  indexPage:
+    type: aws:s3:BucketObject
+    properties:
+      bucket: ${bucket.id}
+      key: ${indexDocument}
+      source:
+        fn::fileAsset: ./www/index.html
+      contentType: text/html
+      acl: public-read
+    options:
+      dependsOn:
+        - ${ownershipControls}
+        - ${publicAccessBlock}
+outputs:
+  url: http://${website.websiteEndpoint}
+"#;
+
+const GCP_STATIC_SITE: &str = r#"name: {{name}}
+description: {{description}}
+runtime: yaml
+config:
+  location:
+    type: string
+    default: US
+  indexDocument:
+    type: string
+    default: index.html
+resources:
+  bucket:
+    type: gcp:storage:Bucket
+    properties:
+      location: ${location}
+      uniformBucketLevelAccess: true
+      website:
+        mainPageSuffix: ${indexDocument}
+  publicRead:
+    type: gcp:storage:BucketIAMMember
+    properties:
+      bucket: ${bucket.name}
+      role: roles/storage.objectViewer
+      member: allUsers
+  indexPage:
+    type: gcp:storage:BucketObject
+    properties:
+      bucket: ${bucket.name}
+      name: ${indexDocument}
+      source:
+        fn::fileAsset: ./www/index.html
+      contentType: text/html
+outputs:
+  url: https://storage.googleapis.com/${bucket.name}/${indexDocument}
+"#;
+
+const AZURE_STATIC_SITE: &str = r#"name: {{name}}
+description: {{description}}
+runtime: yaml
+config:
+  location:
+    type: string
+    default: westus2
+  indexDocument:
+    type: string
+    default: index.html
+resources:
+  resourceGroup:
+    type: azure-native:resources:ResourceGroup
+    properties:
+      location: ${location}
+  account:
+    type: azure-native:storage:StorageAccount
+    properties:
+      resourceGroupName: ${resourceGroup.name}
+      location: ${location}
+      kind: StorageV2
+      sku:
+        name: Standard_LRS
+  website:
+    type: azure-native:storage:StorageAccountStaticWebsite
+    properties:
+      resourceGroupName: ${resourceGroup.name}
+      accountName: ${account.name}
+      indexDocument: ${indexDocument}
+  indexPage:
+    type: azure-native:storage:Blob
+    properties:
+      resourceGroupName: ${resourceGroup.name}
+      accountName: ${account.name}
+      containerName: ${website.containerName}
+      blobName: ${indexDocument}
+      source:
+        fn::fileAsset: ./www/index.html
+      contentType: text/html
+outputs:
+  url: ${account.primaryEndpoints.web}
+"#;
+
+const AWS_CONTAINER_SERVICE: &str = r#"name: {{name}}
+description: {{description}}
+runtime: yaml
+config:
+  image:
+    type: string
+    default: public.ecr.aws/aws-containers/hello-app-runner:latest
+  port:
+    type: integer
+    default: 8000
+resources:
+  service:
+    type: aws:apprunner:Service
+    properties:
+      serviceName: ${pulumi.stack}-service
+      sourceConfiguration:
+        autoDeploymentsEnabled: false
+        imageRepository:
+          imageIdentifier: ${image}
+          imageRepositoryType: ECR_PUBLIC
+          imageConfiguration:
+            port: ${port}
+outputs:
+  url: https://${service.serviceUrl}
+"#;
+
+const GCP_CONTAINER_SERVICE: &str = r#"name: {{name}}
+description: {{description}}
+runtime: yaml
+config:
+  location:
+    type: string
+    default: us-central1
+  image:
+    type: string
+    default: us-docker.pkg.dev/cloudrun/container/hello
+resources:
+  service:
+    type: gcp:cloudrunv2:Service
+    properties:
+      location: ${location}
+      ingress: INGRESS_TRAFFIC_ALL
+      template:
+        containers:
+          - image: ${image}
+  invoker:
+    type: gcp:cloudrunv2:ServiceIamMember
+    properties:
+      name: ${service.name}
+      location: ${location}
+      role: roles/run.invoker
+      member: allUsers
+outputs:
+  url: ${service.uri}
+"#;
+
+const AZURE_CONTAINER_SERVICE: &str = r#"name: {{name}}
+description: {{description}}
+runtime: yaml
+config:
+  location:
+    type: string
+    default: westus2
+  image:
+    type: string
+    default: mcr.microsoft.com/azuredocs/aci-helloworld
+  port:
+    type: integer
+    default: 80
+resources:
+  resourceGroup:
+    type: azure-native:resources:ResourceGroup
+    properties:
+      location: ${location}
+  containerGroup:
+    type: azure-native:containerinstance:ContainerGroup
+    properties:
+      resourceGroupName: ${resourceGroup.name}
+      location: ${location}
+      osType: Linux
+      restartPolicy: Always
+      containers:
+        - name: app
+          image: ${image}
+          ports:
+            - port: ${port}
+          resources:
+            requests:
+              cpu: 1
+              memoryInGB: 1.5
+      ipAddress:
+        type: Public
+        ports:
+          - port: ${port}
+            protocol: TCP
+outputs:
+  url: http://${containerGroup.ipAddress.ip}:${port}
+"#;
+
+const AWS_SERVERLESS_API: &str = r#"name: {{name}}
+description: {{description}}
+runtime: yaml
+resources:
+  role:
+    type: aws:iam:Role
+    properties:
+      assumeRolePolicy:
+        fn::toJSON:
+          Version: '2012-10-17'
+          Statement:
+            - Action: sts:AssumeRole
+              Effect: Allow
+              Principal:
+                Service: lambda.amazonaws.com
+  logging:
+    type: aws:iam:RolePolicyAttachment
+    properties:
+      role: ${role.name}
+      policyArn: arn:aws:iam::aws:policy/service-role/AWSLambdaBasicExecutionRole
+  handler:
+    type: aws:lambda:Function
+    properties:
+      role: ${role.arn}
+      runtime: nodejs20.x
+      handler: index.handler
+      code:
+        fn::fileArchive: ./app
+  api:
+    type: aws:apigatewayv2:Api
+    properties:
+      protocolType: HTTP
+      target: ${handler.arn}
+  invokePermission:
+    type: aws:lambda:Permission
+    properties:
+      action: lambda:InvokeFunction
+      function: ${handler.name}
+      principal: apigateway.amazonaws.com
+      sourceArn: ${api.executionArn}/*/*
+outputs:
+  url: ${api.apiEndpoint}
+"#;
+
+const GCP_SERVERLESS_API: &str = r#"name: {{name}}
+description: {{description}}
+runtime: yaml
+config:
+  location:
+    type: string
+    default: us-central1
+resources:
+  sourceBucket:
+    type: gcp:storage:Bucket
+    properties:
+      location: ${location}
+      uniformBucketLevelAccess: true
+  sourceArchive:
+    type: gcp:storage:BucketObject
+    properties:
+      bucket: ${sourceBucket.name}
+      name: source.zip
+      source:
+        fn::fileArchive: ./app
+  function:
+    type: gcp:cloudfunctionsv2:Function
+    properties:
+      location: ${location}
+      buildConfig:
+        runtime: nodejs20
+        entryPoint: handler
+        source:
+          storageSource:
+            bucket: ${sourceBucket.name}
+            object: ${sourceArchive.name}
+      serviceConfig:
+        availableMemory: 256M
+  invoker:
+    type: gcp:cloudrunv2:ServiceIamMember
+    properties:
+      name: ${function.name}
+      location: ${location}
+      role: roles/run.invoker
+      member: allUsers
+outputs:
+  url: ${function.url}
+"#;
+
+const AZURE_SERVERLESS_API: &str = r#"name: {{name}}
+description: {{description}}
+runtime: yaml
+config:
+  location:
+    type: string
+    default: westus2
+variables:
+  keys:
+    fn::invoke:
+      function: azure-native:storage:listStorageAccountKeys
+      arguments:
+        resourceGroupName: ${resourceGroup.name}
+        accountName: ${account.name}
+  packageSas:
+    fn::invoke:
+      function: azure-native:storage:listStorageAccountServiceSAS
+      arguments:
+        resourceGroupName: ${resourceGroup.name}
+        accountName: ${account.name}
+        protocols: https
+        sharedAccessStartTime: '2024-01-01'
+        sharedAccessExpiryTime: '2034-01-01'
+        resource: c
+        permissions: r
+        canonicalizedResource: /blob/${account.name}/${container.name}
+resources:
+  resourceGroup:
+    type: azure-native:resources:ResourceGroup
+    properties:
+      location: ${location}
+  account:
+    type: azure-native:storage:StorageAccount
+    properties:
+      resourceGroupName: ${resourceGroup.name}
+      location: ${location}
+      kind: StorageV2
+      sku:
+        name: Standard_LRS
+  container:
+    type: azure-native:storage:BlobContainer
+    properties:
+      resourceGroupName: ${resourceGroup.name}
+      accountName: ${account.name}
+      publicAccess: None
+  package:
+    type: azure-native:storage:Blob
+    properties:
+      resourceGroupName: ${resourceGroup.name}
+      accountName: ${account.name}
+      containerName: ${container.name}
+      source:
+        fn::fileArchive: ./app
+  plan:
+    type: azure-native:web:AppServicePlan
+    properties:
+      resourceGroupName: ${resourceGroup.name}
+      location: ${location}
+      kind: linux
+      reserved: true
+      sku:
+        name: Y1
+        tier: Dynamic
+  app:
+    type: azure-native:web:WebApp
+    properties:
+      resourceGroupName: ${resourceGroup.name}
+      location: ${location}
+      kind: functionapp,linux
+      serverFarmId: ${plan.id}
+      siteConfig:
+        linuxFxVersion: Node|20
+        appSettings:
+          - name: FUNCTIONS_WORKER_RUNTIME
+            value: node
+          - name: FUNCTIONS_EXTENSION_VERSION
+            value: ~4
+          - name: AzureWebJobsStorage
+            value:
+              fn::secret: DefaultEndpointsProtocol=https;AccountName=${account.name};AccountKey=${keys.keys[0].value}
+          - name: WEBSITE_RUN_FROM_PACKAGE
+            value:
+              fn::secret: ${package.url}?${packageSas.serviceSasToken}
+outputs:
+  url: https://${app.defaultHostName}/api/hello
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::parse::parse_template;
+    use crate::eval::graph::topological_sort;
+    use crate::schema::{PackageSchema, PropertyInfo, ResourceTypeInfo, SchemaPropertyType};
+
+    fn options(archetype: Archetype, cloud: Cloud) -> ScaffoldOptions<'static> {
+        ScaffoldOptions {
+            name: "my-app",
+            archetype,
+            cloud,
+            schema: None,
+        }
+    }
+
+    #[test]
+    fn test_every_archetype_parses() {
+        for archetype in Archetype::ALL {
+            for cloud in Cloud::ALL {
+                let files = scaffold(&options(archetype, cloud)).unwrap();
+                assert_eq!(files[0].path, "Pulumi.yaml");
+                let (template, diags) = parse_template(&files[0].contents, None);
+                let what = format!("{} on {}", archetype.name(), cloud.name());
+                assert!(!diags.has_errors(), "{}: {}", what, diags);
+                assert_eq!(template.name.as_deref(), Some("my-app"), "{}", what);
+                let (_, diags) = topological_sort(&template);
+                assert!(!diags.has_errors(), "{}: {}", what, diags);
+                assert!(
+                    template
+                        .resources
+                        .iter()
+                        .all(|r| r.resource.type_.starts_with(cloud.name())),
+                    "{}",
+                    what
+                );
+                // Assets the template reads are generated with it.
+                for path in ["./www/index.html", "./app"] {
+                    if files[0].contents.contains(path) {
+                        let path = &path[2..];
+                        assert!(
+                            files.iter().any(|f| f.path.starts_with(path)),
+                            "{}: {} is not generated",
+                            what,
+                            path
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(
+            Archetype::from_name("serverless-api"),
+            Some(Archetype::ServerlessApi)
+        );
+        assert_eq!(Archetype::from_name("lambda"), None);
+        assert_eq!(Cloud::from_name("gcp"), Some(Cloud::Gcp));
+        let mut bad = options(Archetype::StaticSite, Cloud::Aws);
+        for name in ["", "my app", "app/one"] {
+            bad.name = name;
+            assert!(scaffold(&bad).is_err(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn test_schema_completes_resources() {
+        let required = PropertyInfo {
+            type_: SchemaPropertyType::String,
+            secret: false,
+            const_value: None,
+            required: true,
+            enum_values: Vec::new(),
+        };
+        let mut service = ResourceTypeInfo::default();
+        service.input_properties.insert("serviceName".to_string());
+        service.input_properties.insert("instanceRole".to_string());
+        service.required_inputs.insert("serviceName".to_string());
+        service.required_inputs.insert("instanceRole".to_string());
+        service
+            .input_property_types
+            .insert("instanceRole".to_string(), required);
+        let mut schema = PackageSchema {
+            name: "aws".to_string(),
+            ..Default::default()
+        };
+        schema
+            .resources
+            .insert("aws:apprunner/service:Service".to_string(), service);
+        let mut store = SchemaStore::new();
+        store.insert(schema);
+
+        let mut options = options(Archetype::ContainerService, Cloud::Aws);
+        options.schema = Some(&store);
+        let project = &scaffold(&options).unwrap()[0].contents;
+        assert!(
+            project.contains("    type: aws:apprunner/service:Service\n"),
+            "{}",
+            project
+        );
+        assert!(
+            project.contains("      instanceRole: \"\"\n"),
+            "{}",
+            project
+        );
+        assert_eq!(project.matches("serviceName:").count(), 1, "{}", project);
+    }
+}
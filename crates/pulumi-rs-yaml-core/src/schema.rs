@@ -7,6 +7,12 @@
 //! - Fill `Value::Unknown` for output-only properties during preview
 //! - Auto-add `additional_secret_outputs` from schema
 //! - Auto-add `aliases` from schema
+//!
+//! Reading schemas, i.e. [`parse_schema_json`] and the `GetSchema` request
+//! helpers, and generating the schema of a template's components need the
+//! `schema` feature. Without it a [`SchemaStore`] can still be filled in
+//! code or with [`SchemaStore::load`], and everything that consumes one
+//! works as usual.
 
 use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
@@ -55,6 +61,20 @@ pub struct PropertyInfo {
     pub const_value: Option<serde_json::Value>,
     /// Whether this property is required.
     pub required: bool,
+    /// Allowed values, when the property's type is an enum.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub enum_values: Vec<EnumValue>,
+}
+
+/// One allowed value of an enum type.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnumValue {
+    pub value: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Set when the value is deprecated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecation_message: Option<String>,
 }
 
 /// Metadata extracted from a provider schema for a single resource type.
@@ -82,6 +102,9 @@ pub struct ResourceTypeInfo {
     pub required_inputs: HashSet<String>,
     /// Typed input property metadata (distinct from property_types which merges both).
     pub input_property_types: HashMap<String, PropertyInfo>,
+    /// The shortest YAML example in the resource's description, if it has any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub example: Option<String>,
 }
 
 /// Metadata extracted from a provider schema for a single function.
@@ -180,7 +203,9 @@ impl SchemaStore {
     ///
     /// 1. Direct lookup (already canonical)
     /// 2. Try heuristic canonicalization
-    /// 3. Search aliases in matching package
+    /// 3. Try all expansions
+    /// 4. Search aliases in matching package
+    /// 5. Search the package for the only type with that name
     pub fn resolve_resource_token<'a>(&'a self, token: &'a str) -> Option<Cow<'a, str>> {
         // 1. Direct lookup
         if self.lookup_resource(token).is_some() {
@@ -203,22 +228,44 @@ impl SchemaStore {
 
         // 4. Search aliases in matching package
         let pkg_name = token.split(':').next()?;
-        if let Some(schema) = self.packages.get(pkg_name) {
-            for (canonical_token, info) in &schema.resources {
-                for alias in &info.aliases {
-                    if alias == token {
-                        return Some(Cow::Borrowed(canonical_token.as_str()));
-                    }
-                    // Also try canonical form of alias
-                    let canonical_alias = crate::packages::canonicalize_type_token(alias);
-                    if canonical_alias == canonical {
-                        return Some(Cow::Borrowed(canonical_token.as_str()));
-                    }
+        let schema = self.packages.get(pkg_name)?;
+        for (canonical_token, info) in &schema.resources {
+            for alias in &info.aliases {
+                if alias == token {
+                    return Some(Cow::Borrowed(canonical_token.as_str()));
+                }
+                // Also try canonical form of alias
+                let canonical_alias = crate::packages::canonicalize_type_token(alias);
+                if canonical_alias == canonical {
+                    return Some(Cow::Borrowed(canonical_token.as_str()));
                 }
             }
         }
 
-        None
+        // 5. Search by type name: `aws:Bucket` names the only `Bucket` in
+        //    the package, and `aws:s3:Bucket` the only one in module `s3`.
+        let parts: Vec<&str> = token.split(':').collect();
+        let (module, name) = match parts.as_slice() {
+            [_, name] => (None, *name),
+            [_, module, name] => (Some(*module), *name),
+            _ => return None,
+        };
+        let mut matches = schema.resources.keys().filter(|t| {
+            let mut t_parts = t.splitn(3, ':').skip(1);
+            let t_module = t_parts.next().unwrap_or("");
+            t_parts.next() == Some(name)
+                && module.is_none_or(|m| t_module.split('/').next() == Some(m))
+        });
+        let found = matches.next()?;
+        match matches.next() {
+            Some(_) => None,
+            None => Some(Cow::Borrowed(found.as_str())),
+        }
+    }
+
+    /// Whether the store has the schema of package `pkg`.
+    pub fn has_package(&self, pkg: &str) -> bool {
+        self.packages.contains_key(pkg)
     }
 
     /// Resolve a function token to its canonical form using schema knowledge.
@@ -264,7 +311,62 @@ impl SchemaStore {
     }
 }
 
+/// Returns the shortest ```` ```yaml ```` code block of a schema description.
+/// Descriptions list an example per scenario, each in every language, so the
+/// shortest YAML one is the closest to a minimal valid resource.
+#[cfg(feature = "schema")]
+fn shortest_yaml_example(description: &str) -> Option<String> {
+    let mut shortest: Option<&str> = None;
+    let mut rest = description;
+    while let Some(start) = rest.find("```yaml\n") {
+        let body = &rest[start + "```yaml\n".len()..];
+        let Some(end) = body.find("```") else {
+            break;
+        };
+        let example = body[..end].trim_end();
+        if !example.is_empty() && shortest.is_none_or(|s| example.len() < s.len()) {
+            shortest = Some(example);
+        }
+        rest = &body[end + 3..];
+    }
+    shortest.map(|s| format!("{}\n", s))
+}
+
+/// Returns the Pulumi Registry page documenting a resource type, like
+/// `https://www.pulumi.com/registry/packages/aws/api-docs/s3/bucket/` for
+/// `aws:s3/bucket:Bucket` or `aws:s3:Bucket`. Returns `None` for tokens of
+/// the built-in `pulumi` package and malformed tokens.
+pub fn registry_docs_url(type_token: &str) -> Option<String> {
+    let mut parts = type_token.split(':');
+    let (Some(package), Some(module), Some(name), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return None;
+    };
+    if package.is_empty() || package == "pulumi" || name.is_empty() {
+        return None;
+    }
+    // Canonical modules end with the type name in camel case, which the
+    // registry leaves out of the path.
+    let module = match module.rsplit_once('/') {
+        Some((module, last)) if last.eq_ignore_ascii_case(name) => module,
+        _ => module,
+    };
+    let mut url = format!(
+        "https://www.pulumi.com/registry/packages/{}/api-docs/",
+        package
+    );
+    if !module.is_empty() && module != "index" {
+        url.push_str(&module.to_ascii_lowercase());
+        url.push('/');
+    }
+    url.push_str(&name.to_ascii_lowercase());
+    url.push('/');
+    Some(url)
+}
+
 /// Parse a property type from a schema property definition.
+#[cfg(feature = "schema")]
 fn parse_property_type(prop: &serde_json::Value) -> SchemaPropertyType {
     // Check $ref for asset/archive types
     if let Some(ref_str) = prop.get("$ref").and_then(|v| v.as_str()) {
@@ -293,6 +395,57 @@ fn parse_property_type(prop: &serde_json::Value) -> SchemaPropertyType {
     }
 }
 
+/// Collects the allowed values of an enum-typed property: a `$ref` to an
+/// enum in the schema's `types`, a `oneOf` including one, or an inline
+/// `enum` list.
+#[cfg(feature = "schema")]
+fn parse_enum_values(
+    prop: &serde_json::Value,
+    types: Option<&serde_json::Value>,
+) -> Vec<EnumValue> {
+    if let Some(values) = prop.get("enum").and_then(|v| v.as_array()) {
+        return values.iter().map(enum_value).collect();
+    }
+    if let Some(token) = prop
+        .get("$ref")
+        .and_then(|v| v.as_str())
+        .and_then(|r| r.strip_prefix("#/types/"))
+    {
+        return types
+            .and_then(|t| t.get(token))
+            .and_then(|t| t.get("enum"))
+            .and_then(|v| v.as_array())
+            .map(|values| values.iter().map(enum_value).collect())
+            .unwrap_or_default();
+    }
+    prop.get("oneOf")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .map(|alt| parse_enum_values(alt, types))
+        .find(|values| !values.is_empty())
+        .unwrap_or_default()
+}
+
+/// Reads one enum value, either `{ value, description, deprecationMessage }`
+/// as in Pulumi schema `types`, or a bare JSON Schema value.
+#[cfg(feature = "schema")]
+fn enum_value(v: &serde_json::Value) -> EnumValue {
+    let text = |key: &str| v.get(key).and_then(|s| s.as_str()).map(str::to_string);
+    match v.get("value") {
+        Some(value) if v.is_object() => EnumValue {
+            value: value.clone(),
+            description: text("description"),
+            deprecation_message: text("deprecationMessage"),
+        },
+        _ => EnumValue {
+            value: v.clone(),
+            description: None,
+            deprecation_message: None,
+        },
+    }
+}
+
 /// Parse provider schema JSON bytes into a `PackageSchema`.
 ///
 /// Only extracts resource metadata (property names, secrets, aliases, types).
@@ -312,6 +465,8 @@ fn parse_property_type(prop: &serde_json::Value) -> SchemaPropertyType {
 ///   }
 /// }
 /// ```
+#[cfg(feature = "schema")]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(bytes = json_bytes.len())))]
 pub fn parse_schema_json(json_bytes: &[u8]) -> Result<PackageSchema, String> {
     let root: serde_json::Value =
         serde_json::from_slice(json_bytes).map_err(|e| format!("invalid JSON: {}", e))?;
@@ -327,6 +482,7 @@ pub fn parse_schema_json(json_bytes: &[u8]) -> Result<PackageSchema, String> {
         .unwrap_or("")
         .to_string();
 
+    let types = root.get("types");
     let mut resources = HashMap::new();
 
     if let Some(res_map) = root.get("resources").and_then(|v| v.as_object()) {
@@ -355,6 +511,7 @@ pub fn parse_schema_json(json_bytes: &[u8]) -> Result<PackageSchema, String> {
                             secret,
                             const_value,
                             required: false, // set later from "required" array
+                            enum_values: parse_enum_values(prop_def, types),
                         },
                     );
                 }
@@ -397,6 +554,7 @@ pub fn parse_schema_json(json_bytes: &[u8]) -> Result<PackageSchema, String> {
                         let is_required = input_required_set.contains(prop_name);
                         let prop_type = parse_property_type(prop_def);
                         let const_value = prop_def.get("const").cloned();
+                        let enum_values = parse_enum_values(prop_def, types);
 
                         info.input_property_types.insert(
                             prop_name.clone(),
@@ -405,6 +563,7 @@ pub fn parse_schema_json(json_bytes: &[u8]) -> Result<PackageSchema, String> {
                                 secret,
                                 const_value: const_value.clone(),
                                 required: is_required,
+                                enum_values: enum_values.clone(),
                             },
                         );
 
@@ -421,6 +580,7 @@ pub fn parse_schema_json(json_bytes: &[u8]) -> Result<PackageSchema, String> {
                                     secret,
                                     const_value,
                                     required: is_required,
+                                    enum_values,
                                 },
                             );
                         }
@@ -450,6 +610,11 @@ pub fn parse_schema_json(json_bytes: &[u8]) -> Result<PackageSchema, String> {
                 .and_then(|v| v.as_bool())
                 .unwrap_or(false);
 
+            info.example = res_def
+                .get("description")
+                .and_then(|v| v.as_str())
+                .and_then(shortest_yaml_example);
+
             resources.insert(token.clone(), info);
         }
     }
@@ -490,6 +655,7 @@ pub fn parse_schema_json(json_bytes: &[u8]) -> Result<PackageSchema, String> {
                                 secret,
                                 const_value: None,
                                 required: is_required,
+                                enum_values: parse_enum_values(prop_def, types),
                             },
                         );
                     }
@@ -512,6 +678,7 @@ pub fn parse_schema_json(json_bytes: &[u8]) -> Result<PackageSchema, String> {
                                 secret,
                                 const_value: None,
                                 required: false,
+                                enum_values: Vec::new(),
                             },
                         );
                     }
@@ -530,20 +697,52 @@ pub fn parse_schema_json(json_bytes: &[u8]) -> Result<PackageSchema, String> {
     })
 }
 
+/// Returns the package name the components of `template` are published under.
+pub fn component_package<'a>(template: &'a crate::ast::template::TemplateDecl<'_>) -> &'a str {
+    template.name.as_deref().unwrap_or("yaml-components")
+}
+
+/// Returns the type token of component `name` of package `pkg`.
+pub fn component_type_token(pkg: &str, name: &str) -> String {
+    format!("{}:index:{}", pkg, name)
+}
+
+/// Returns the type tokens of the components `template` declares.
+pub fn component_type_tokens(template: &crate::ast::template::TemplateDecl<'_>) -> HashSet<String> {
+    let pkg = component_package(template);
+    template
+        .components
+        .iter()
+        .map(|c| component_type_token(pkg, &c.key))
+        .collect()
+}
+
+/// Expands the short form `pkg:Name` of a component type token to
+/// `pkg:index:Name`. Other tokens are returned unchanged.
+pub fn expand_component_token(type_token: &str) -> Cow<'_, str> {
+    match type_token.split_once(':') {
+        Some((pkg, name)) if !name.contains(':') => Cow::Owned(component_type_token(pkg, name)),
+        _ => Cow::Borrowed(type_token),
+    }
+}
+
 /// Generates a Pulumi package schema JSON from component declarations in a template.
 ///
 /// Each component becomes a resource with `isComponent: true`, with input and
-/// output properties extracted from the component declaration.
+/// output properties extracted from the component declaration. Descriptions
+/// and literal scalar defaults of inputs are carried over so generated SDKs
+/// document them.
+#[cfg(feature = "schema")]
 pub fn generate_component_schema(
     template: &crate::ast::template::TemplateDecl<'_>,
 ) -> serde_json::Value {
-    let pkg_name = template.name.as_deref().unwrap_or("yaml-components");
+    let pkg_name = component_package(template);
 
     let mut resources = serde_json::Map::new();
 
     for comp in &template.components {
         let comp_name = &comp.key;
-        let component_type = format!("{}:index:{}", pkg_name, comp_name);
+        let component_type = component_type_token(pkg_name, comp_name);
 
         let mut input_properties = serde_json::Map::new();
         let mut required_inputs = Vec::new();
@@ -584,6 +783,12 @@ pub fn generate_component_schema(
             if input.param.secret == Some(true) {
                 prop.insert("secret".into(), true.into());
             }
+            if let Some(ref description) = input.param.description {
+                prop.insert("description".into(), description.to_string().into());
+            }
+            if let Some(default) = input.param.default.as_ref().and_then(schema_default) {
+                prop.insert("default".into(), default);
+            }
 
             // If no default, it's required
             if input.param.default.is_none() {
@@ -603,6 +808,9 @@ pub fn generate_component_schema(
 
         let mut resource_spec = serde_json::Map::new();
         resource_spec.insert("isComponent".into(), true.into());
+        if let Some(ref description) = comp.component.description {
+            resource_spec.insert("description".into(), description.to_string().into());
+        }
         resource_spec.insert("inputProperties".into(), input_properties.into());
         resource_spec.insert("properties".into(), output_properties.into());
         if !required_inputs.is_empty() {
@@ -619,9 +827,27 @@ pub fn generate_component_schema(
     })
 }
 
+/// Converts a component input default to a schema `default`. The schema only
+/// allows scalar defaults, so anything but a literal string, number or bool
+/// is left to the component body.
+#[cfg(feature = "schema")]
+fn schema_default(expr: &crate::ast::expr::Expr<'_>) -> Option<serde_json::Value> {
+    use crate::ast::expr::Expr;
+    match expr {
+        Expr::String(_, s) => Some(s.to_string().into()),
+        Expr::Bool(_, b) => Some((*b).into()),
+        Expr::Number(_, n) => match n.as_i64() {
+            Some(i) => Some(i.into()),
+            None => n.to_json().map(Into::into),
+        },
+        _ => None,
+    }
+}
+
 /// Builds a `GetSchemaRequest` for the given package dependency, including
 /// any parameterization. Invalid base64 in the parameterization value is
 /// reported as a warning and treated as an empty byte slice.
+#[cfg(feature = "schema")]
 pub fn build_schema_request(
     pkg: &crate::packages::PackageDependency,
 ) -> pulumi_rs_yaml_proto::codegen::GetSchemaRequest {
@@ -655,6 +881,8 @@ pub fn build_schema_request(
 ///
 /// Returns an error string on parse failure; the caller is responsible
 /// for deciding how to report it (typically as a warning).
+#[cfg(feature = "schema")]
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(package = pkg_name)))]
 pub fn process_schema_response(
     store: &mut SchemaStore,
     pkg_name: &str,
@@ -666,7 +894,17 @@ pub fn process_schema_response(
     Ok(())
 }
 
-#[cfg(test)]
+impl crate::packages::TypeTokenResolver for SchemaStore {
+    fn resolve_type_token(&self, token: &str) -> Option<String> {
+        self.resolve_resource_token(token).map(Cow::into_owned)
+    }
+
+    fn has_package(&self, pkg: &str) -> bool {
+        SchemaStore::has_package(self, pkg)
+    }
+}
+
+#[cfg(all(test, feature = "schema"))]
 mod tests {
     use super::*;
 
@@ -1027,6 +1265,53 @@ mod tests {
         assert!(!custom.is_component);
     }
 
+    #[test]
+    fn test_registry_docs_url() {
+        for (token, url) in [
+            ("aws:s3/bucket:Bucket", Some("aws/api-docs/s3/bucket/")),
+            ("aws:s3:BucketV2", Some("aws/api-docs/s3/bucketv2/")),
+            (
+                "random:index/randomPet:RandomPet",
+                Some("random/api-docs/randompet/"),
+            ),
+            (
+                "kubernetes:apps/v1:Deployment",
+                Some("kubernetes/api-docs/apps/v1/deployment/"),
+            ),
+            (
+                "azure-native:storage:StorageAccount",
+                Some("azure-native/api-docs/storage/storageaccount/"),
+            ),
+            ("pulumi:providers:aws", None),
+            ("aws:Bucket", None),
+        ] {
+            let expected =
+                url.map(|url| format!("https://www.pulumi.com/registry/packages/{}", url));
+            assert_eq!(registry_docs_url(token), expected, "{}", token);
+        }
+    }
+
+    #[test]
+    fn test_parse_resource_example() {
+        let json = serde_json::json!({
+            "name": "test",
+            "resources": {
+                "test:index/bucket:Bucket": {
+                    "description": "A bucket.\n\n{{% examples %}}\n## Example Usage\n{{% example %}}\n### Logging\n\n```typescript\nnew test.Bucket(\"b\");\n```\n```yaml\nresources:\n  b:\n    type: test:Bucket\n    properties:\n      logging: true\n```\n{{% /example %}}\n{{% example %}}\n### Basic\n\n```yaml\nresources:\n  b:\n    type: test:Bucket\n```\n{{% /example %}}\n{{% /examples %}}"
+                },
+                "test:index/queue:Queue": { "description": "A queue." }
+            }
+        });
+        let schema = parse_schema_json(json.to_string().as_bytes()).unwrap();
+        assert_eq!(
+            schema.resources["test:index/bucket:Bucket"]
+                .example
+                .as_deref(),
+            Some("resources:\n  b:\n    type: test:Bucket\n")
+        );
+        assert_eq!(schema.resources["test:index/queue:Queue"].example, None);
+    }
+
     #[test]
     fn test_store_is_component() {
         let mut store = SchemaStore::new();
@@ -1100,6 +1385,49 @@ mod tests {
         assert_eq!(ver_info.const_value, Some(serde_json::json!(2)));
     }
 
+    #[test]
+    fn test_parse_enum_values() {
+        let json = br##"{
+            "name": "test",
+            "version": "1.0.0",
+            "types": {
+                "test:index:Acl": {
+                    "type": "string",
+                    "enum": [
+                        { "value": "private", "description": "Owner only." },
+                        { "value": "public-read" },
+                        { "value": "authenticated", "deprecationMessage": "Use private." }
+                    ]
+                }
+            },
+            "resources": {
+                "test:index/res:Res": {
+                    "inputProperties": {
+                        "acl": { "$ref": "#/types/test:index:Acl" },
+                        "size": {
+                            "oneOf": [{ "type": "string" }, { "type": "integer", "enum": [1, 2] }]
+                        },
+                        "name": { "type": "string" }
+                    }
+                }
+            }
+        }"##;
+
+        let schema = parse_schema_json(json).unwrap();
+        let info = schema.resources.get("test:index/res:Res").unwrap();
+        let acl = &info.input_property_types["acl"].enum_values;
+        assert_eq!(acl.len(), 3);
+        assert_eq!(acl[0].value, serde_json::json!("private"));
+        assert_eq!(acl[0].description.as_deref(), Some("Owner only."));
+        assert_eq!(acl[2].deprecation_message.as_deref(), Some("Use private."));
+        let size = &info.input_property_types["size"].enum_values;
+        assert_eq!(
+            size.iter().map(|v| v.value.clone()).collect::<Vec<_>>(),
+            vec![serde_json::json!(1), serde_json::json!(2)]
+        );
+        assert!(info.input_property_types["name"].enum_values.is_empty());
+    }
+
     #[test]
     fn test_parse_required_inputs() {
         let json = br#"{
@@ -1266,6 +1594,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_resolve_resource_token_by_type_name() {
+        let mut store = SchemaStore::new();
+        let json = br#"{
+            "name": "aws",
+            "version": "6.0.0",
+            "resources": {
+                "aws:s3/bucket:Bucket": {},
+                "aws:ec2/instance:Instance": {},
+                "aws:lightsail/instance:Instance": {}
+            }
+        }"#;
+        store.insert(parse_schema_json(json).unwrap());
+
+        assert_eq!(
+            store.resolve_resource_token("aws:Bucket").as_deref(),
+            Some("aws:s3/bucket:Bucket")
+        );
+        assert_eq!(
+            store.resolve_resource_token("aws:ec2:Instance").as_deref(),
+            Some("aws:ec2/instance:Instance")
+        );
+        // Ambiguous without a module.
+        assert!(store.resolve_resource_token("aws:Instance").is_none());
+
+        let resolved = crate::packages::canonicalize_type_token_with("aws:Bucket", &store);
+        assert_eq!(resolved.token, "aws:s3/bucket:Bucket");
+        assert!(!resolved.heuristic);
+        let resolved = crate::packages::canonicalize_type_token_with("aws:Instance", &store);
+        assert_eq!(resolved.token, "aws:index/instance:Instance");
+        assert!(resolved.heuristic);
+        let resolved = crate::packages::canonicalize_type_token_with("gcp:Bucket", &store);
+        assert!(!resolved.heuristic);
+    }
+
     #[test]
     fn test_resolve_resource_token_not_found() {
         let store = SchemaStore::new();
@@ -1,19 +1,23 @@
+use serde::{Deserialize, Serialize};
+
 /// Owns the source text for all YAML files in a program.
 ///
 /// All `&'src str` references in the AST and evaluation layers borrow from a `SourceArena`.
 /// This enforces the lifetime invariant: source outlives AST outlives evaluation.
+#[derive(Debug)]
 pub struct SourceArena {
     files: Vec<SourceFile>,
 }
 
 /// A single source file with its name and contents.
+#[derive(Debug)]
 pub struct SourceFile {
     name: String,
     text: String,
 }
 
 /// Index into `SourceArena::files`.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct FileId(pub u32);
 
 impl SourceArena {
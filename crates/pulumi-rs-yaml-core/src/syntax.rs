@@ -1,11 +1,12 @@
 use crate::source::FileId;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 /// A byte-offset span within a source file.
 ///
 /// Spans are cheap to copy and compare. They reference positions
 /// within the source text owned by `SourceArena`.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Span {
     pub file: FileId,
     pub start: u32,
@@ -90,8 +91,9 @@ impl LineIndex {
 /// Metadata that can be attached to any AST node.
 ///
 /// Contains an optional span for source location tracking.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct ExprMeta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub span: Option<Span>,
 }
 
@@ -3,11 +3,12 @@
 //! Validates resource properties, required inputs, invoke arguments,
 //! and property access chains against provider schemas.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::ast::expr::Expr;
 use crate::ast::property::{PropertyAccess, PropertyAccessor};
 use crate::ast::template::*;
+use crate::ast::visitor::{walk_expr, InvokeCollector};
 use crate::diag::Diagnostics;
 use crate::packages::canonicalize_type_token;
 use crate::schema::{SchemaPropertyType, SchemaStore};
@@ -40,6 +41,7 @@ pub enum InferredType {
 /// Type-checks a template against provider schemas.
 ///
 /// Returns diagnostics (warnings for unknown properties, errors for missing required inputs).
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
 pub fn type_check(
     template: &TemplateDecl<'_>,
     schema_store: &SchemaStore,
@@ -49,6 +51,7 @@ pub fn type_check(
         schema_store,
         source_map,
         resource_types: HashMap::new(),
+        ranged: HashSet::new(),
         diags: Diagnostics::new(),
     };
 
@@ -64,6 +67,8 @@ struct TypeChecker<'a> {
     source_map: Option<&'a HashMap<String, String>>,
     /// Maps resource logical name → canonical type token.
     resource_types: HashMap<String, String>,
+    /// Resources with `count:` or `range:`, referenced by instance.
+    ranged: HashSet<String>,
     diags: Diagnostics,
 }
 
@@ -78,6 +83,9 @@ impl TypeChecker<'_> {
                 .unwrap_or_else(|| canonicalize_type_token(&entry.resource.type_));
             self.resource_types
                 .insert(entry.logical_name.to_string(), canonical);
+            if entry.resource.range.is_some() {
+                self.ranged.insert(entry.logical_name.to_string());
+            }
         }
 
         // Second pass: validate each resource
@@ -85,8 +93,9 @@ impl TypeChecker<'_> {
             self.check_resource(entry);
         }
 
-        // Validate invoke expressions in variables
-        for entry in &template.variables {
+        // Validate invoke expressions in variables and resource locals
+        let locals = template.resources.iter().flat_map(|r| &r.resource.locals);
+        for entry in template.variables.iter().chain(locals) {
             self.check_expr_invokes(&entry.value);
         }
 
@@ -204,59 +213,10 @@ impl TypeChecker<'_> {
     }
 
     fn check_expr_invokes(&mut self, expr: &Expr<'_>) {
-        match expr {
-            Expr::Invoke(_, invoke) => {
-                self.check_invoke(invoke);
-            }
-            Expr::List(_, items) => {
-                for item in items {
-                    self.check_expr_invokes(item);
-                }
-            }
-            Expr::Object(_, entries) => {
-                for entry in entries {
-                    self.check_expr_invokes(&entry.key);
-                    self.check_expr_invokes(&entry.value);
-                }
-            }
-            Expr::Join(_, a, b) | Expr::Select(_, a, b) | Expr::Split(_, a, b) => {
-                self.check_expr_invokes(a);
-                self.check_expr_invokes(b);
-            }
-            Expr::ToJson(_, inner)
-            | Expr::ToBase64(_, inner)
-            | Expr::FromBase64(_, inner)
-            | Expr::Secret(_, inner)
-            | Expr::ReadFile(_, inner)
-            | Expr::Abs(_, inner)
-            | Expr::Floor(_, inner)
-            | Expr::Ceil(_, inner)
-            | Expr::Max(_, inner)
-            | Expr::Min(_, inner)
-            | Expr::StringLen(_, inner)
-            | Expr::TimeUtc(_, inner)
-            | Expr::TimeUnix(_, inner)
-            | Expr::Uuid(_, inner)
-            | Expr::RandomString(_, inner)
-            | Expr::DateFormat(_, inner)
-            | Expr::StringAsset(_, inner)
-            | Expr::FileAsset(_, inner)
-            | Expr::RemoteAsset(_, inner)
-            | Expr::FileArchive(_, inner)
-            | Expr::RemoteArchive(_, inner) => {
-                self.check_expr_invokes(inner);
-            }
-            Expr::Substring(_, a, b, c) => {
-                self.check_expr_invokes(a);
-                self.check_expr_invokes(b);
-                self.check_expr_invokes(c);
-            }
-            Expr::AssetArchive(_, entries) => {
-                for (_, v) in entries {
-                    self.check_expr_invokes(v);
-                }
-            }
-            _ => {}
+        let mut invokes = Vec::new();
+        walk_expr(expr, &InvokeCollector, &mut invokes);
+        for invoke in invokes {
+            self.check_invoke(invoke);
         }
     }
 
@@ -269,7 +229,13 @@ impl TypeChecker<'_> {
 
         let func_info = match self.schema_store.lookup_function(&canonical) {
             Some(info) => info,
-            None => return, // Unknown function — skip validation
+            None => {
+                // Unknown function — skip validation
+                if invoke.shorthand {
+                    self.check_ambiguous_shorthand(invoke, &canonical);
+                }
+                return;
+            }
         };
 
         // Check arguments
@@ -361,13 +327,43 @@ impl TypeChecker<'_> {
         }
     }
 
+    /// Warns when `fn::<token>` shorthand names no function in a loaded
+    /// package schema. Such a call may be a misspelled provider function or
+    /// collide with a future builtin, so the long `fn::invoke` form is
+    /// suggested. Packages without a loaded schema are not checked.
+    fn check_ambiguous_shorthand(
+        &mut self,
+        invoke: &crate::ast::expr::InvokeExpr<'_>,
+        canonical: &str,
+    ) {
+        let pkg = canonical.split(':').next().unwrap_or_default();
+        let Some(schema) = self.schema_store.packages().get(pkg) else {
+            return;
+        };
+        let suggestion = find_closest_match_map(canonical, &schema.functions);
+        let mut detail = format!("package '{}' has no function '{}'", pkg, canonical);
+        if let Some(ref s) = suggestion {
+            detail.push_str(&format!("; did you mean '{}'?", s));
+        }
+        detail.push_str(&format!(
+            "\nto call a provider function explicitly, use the long form:\n  \
+             fn::invoke:\n    function: {}\n    arguments: {{...}}",
+            suggestion.as_deref().unwrap_or(&invoke.token)
+        ));
+        self.diags.warning(
+            None,
+            format!("ambiguous invoke shorthand 'fn::{}'", invoke.token),
+            detail,
+        );
+    }
+
     /// Infers the type of an expression (shallow).
     fn infer_type(&self, expr: &Expr<'_>) -> InferredType {
         match expr {
             Expr::Null(_) => InferredType::Null,
             Expr::Bool(_, _) => InferredType::Bool,
             Expr::Number(_, n) => {
-                if *n == n.trunc() && n.is_finite() {
+                if n.as_i64().is_some() {
                     InferredType::Integer
                 } else {
                     InferredType::Number
@@ -398,17 +394,72 @@ impl TypeChecker<'_> {
             Expr::Symbol(_, access) => self.infer_access_type(access),
             Expr::Invoke(_, _) => InferredType::Any,
             Expr::Join(_, _, _) => InferredType::String,
-            Expr::Select(_, _, _) => InferredType::Any,
+            Expr::Select(_, _, _) | Expr::Lookup(_, _, _, _) => InferredType::Any,
             Expr::Split(_, _, _) => InferredType::Array(Box::new(InferredType::String)),
+            Expr::ValidateJson(_, value, _) => self.infer_type(value),
+            Expr::Assert(_, _, _) => InferredType::Bool,
+            Expr::Merge(_, _) | Expr::JsonPatch(_, _, _) => InferredType::Any,
+            Expr::Map(_, _, body) => InferredType::Array(Box::new(self.infer_type(body))),
+            Expr::Foreach(_, foreach) => {
+                InferredType::Array(Box::new(self.infer_type(&foreach.body)))
+            }
+            Expr::Filter(_, list, _) => match self.infer_type(list) {
+                array @ InferredType::Array(_) => array,
+                _ => InferredType::Array(Box::new(InferredType::Any)),
+            },
             Expr::ToJson(_, _) => InferredType::String,
+            Expr::ParseJson(_, _) | Expr::ParseYaml(_, _) => InferredType::Any,
             Expr::ToBase64(_, _) => InferredType::String,
-            Expr::FromBase64(_, _) => InferredType::String,
+            Expr::FromBase64(_, _) | Expr::Encrypted(_, _) => InferredType::String,
             Expr::Secret(_, inner) => self.infer_type(inner),
-            Expr::ReadFile(_, _) => InferredType::String,
+            Expr::ReadFile(_, _) | Expr::TemplateFile(_, _, _) => InferredType::String,
+            Expr::ReadFileBinary(_, _) => InferredType::Any,
+            Expr::Env(_, _, None) => InferredType::String,
+            Expr::Env(_, _, Some(_)) => InferredType::Any,
             Expr::Abs(_, _) | Expr::Floor(_, _) | Expr::Ceil(_, _) => InferredType::Number,
             Expr::Max(_, _) | Expr::Min(_, _) => InferredType::Number,
-            Expr::StringLen(_, _) => InferredType::Integer,
-            Expr::Substring(_, _, _, _) => InferredType::String,
+            Expr::FormatDuration(_, _) | Expr::FormatBytes(_, _) => InferredType::String,
+            Expr::StringLen(_, _) | Expr::Length(_, _) => InferredType::Integer,
+            Expr::Sort(_, inner) | Expr::Unique(_, inner) => self.infer_type(inner),
+            Expr::Coalesce(_, inner) => match inner.as_ref() {
+                Expr::List(_, items) => {
+                    let mut types = items
+                        .iter()
+                        .filter(|item| !matches!(item, Expr::Null(_)))
+                        .map(|item| self.infer_type(item));
+                    match types.next() {
+                        Some(first) if types.all(|t| t == first) => first,
+                        _ => InferredType::Any,
+                    }
+                }
+                _ => match self.infer_type(inner) {
+                    InferredType::Array(elem) => *elem,
+                    _ => InferredType::Any,
+                },
+            },
+            Expr::Substring(_, _, _, _) | Expr::Replace(_, _, _, _) => InferredType::String,
+            Expr::ToUpper(_, _) | Expr::ToLower(_, _) | Expr::Trim(_, _) => InferredType::String,
+            Expr::Format(_, _, _) => InferredType::String,
+            Expr::CidrSubnet(_, _, _, _) | Expr::CidrHost(_, _, _) | Expr::CidrNetmask(_, _) => {
+                InferredType::String
+            }
+            Expr::StartsWith(_, _, _) => InferredType::Bool,
+            Expr::Equals(_, _, _)
+            | Expr::Not(_, _)
+            | Expr::And(_, _)
+            | Expr::Or(_, _)
+            | Expr::LessThan(_, _, _)
+            | Expr::LessThanOrEqual(_, _, _)
+            | Expr::GreaterThan(_, _, _)
+            | Expr::GreaterThanOrEqual(_, _, _) => InferredType::Bool,
+            Expr::If(_, _, then, otherwise) => {
+                let then = self.infer_type(then);
+                if then == self.infer_type(otherwise) {
+                    then
+                } else {
+                    InferredType::Any
+                }
+            }
             Expr::TimeUtc(_, _) | Expr::DateFormat(_, _) => InferredType::String,
             Expr::TimeUnix(_, _) => InferredType::Number,
             Expr::Uuid(_, _) | Expr::RandomString(_, _) => InferredType::String,
@@ -417,7 +468,7 @@ impl TypeChecker<'_> {
             }
             Expr::FileArchive(_, _) | Expr::RemoteArchive(_, _) => InferredType::Archive,
             Expr::AssetArchive(_, _) => InferredType::Archive,
-            Expr::Starlark(_, _) => InferredType::Any,
+            Expr::Starlark(_, _) | Expr::Open(_, _, _) => InferredType::Any,
         }
     }
 
@@ -432,12 +483,21 @@ impl TypeChecker<'_> {
 
         // Check if it's a resource reference
         if let Some(canonical_token) = self.resource_types.get(&root) {
-            if access.accessors.len() == 1 {
+            let mut rest = &access.accessors[1..];
+            // The instances of a resource with `count:` or `range:` are
+            // reached through a subscript.
+            if self.ranged.contains(&root) {
+                match rest.split_first() {
+                    Some((_, instance)) => rest = instance,
+                    None => return InferredType::Any,
+                }
+            }
+            if rest.is_empty() {
                 return InferredType::Resource(canonical_token.clone());
             }
             // Try to resolve property type from schema
             if let Some(info) = self.schema_store.lookup_resource(canonical_token) {
-                if let Some(PropertyAccessor::Name(prop_name)) = access.accessors.get(1) {
+                if let Some(PropertyAccessor::Name(prop_name)) = rest.first() {
                     if let Some(prop_info) = info.property_types.get(prop_name.as_ref()) {
                         return schema_type_to_inferred(&prop_info.type_);
                     }
@@ -621,6 +681,7 @@ mod tests {
                 secret: false,
                 const_value: None,
                 required: is_required,
+                enum_values: Vec::new(),
             };
             info.input_property_types
                 .insert(name.to_string(), prop_info.clone());
@@ -926,6 +987,7 @@ variables:
                 secret: false,
                 const_value: None,
                 required: true,
+                enum_values: Vec::new(),
             },
         );
         func.required_inputs.insert("owners".to_string());
@@ -936,6 +998,7 @@ variables:
                 secret: false,
                 const_value: None,
                 required: false,
+                enum_values: Vec::new(),
             },
         );
 
@@ -984,6 +1047,7 @@ variables:
                 secret: false,
                 const_value: None,
                 required: true,
+                enum_values: Vec::new(),
             },
         );
         func.inputs.insert(
@@ -993,6 +1057,7 @@ variables:
                 secret: false,
                 const_value: None,
                 required: false,
+                enum_values: Vec::new(),
             },
         );
         func.required_inputs.insert("owners".to_string());
@@ -1003,6 +1068,7 @@ variables:
                 secret: false,
                 const_value: None,
                 required: false,
+                enum_values: Vec::new(),
             },
         );
 
@@ -1031,6 +1097,103 @@ variables:
         );
     }
 
+    fn make_store_with_function(token: &str) -> SchemaStore {
+        let mut store = SchemaStore::new();
+        store.insert(PackageSchema {
+            name: token.split(':').next().unwrap().to_string(),
+            version: "1.0.0".to_string(),
+            resources: HashMap::new(),
+            functions: [(token.to_string(), FunctionTypeInfo::default())]
+                .into_iter()
+                .collect(),
+        });
+        store
+    }
+
+    #[test]
+    fn test_type_check_shorthand_prefers_schema_function() {
+        let yaml = r#"
+name: test
+runtime: yaml
+variables:
+  ami:
+    fn::aws:ec2:getAmi: {}
+"#;
+        let (template, _) = parse_template(yaml, None);
+        let store = make_store_with_function("aws:ec2/getAmi:getAmi");
+        let result = type_check(&template, &store, None);
+        assert!(
+            !result
+                .diagnostics
+                .iter()
+                .any(|d| d.summary.contains("ambiguous")),
+            "schema-known shorthand should not warn: {:?}",
+            result
+                .diagnostics
+                .iter()
+                .map(|d| &d.summary)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_type_check_ambiguous_shorthand_suggests_long_form() {
+        let yaml = r#"
+name: test
+runtime: yaml
+variables:
+  ami:
+    fn::aws:ec2:getAmy: {}
+"#;
+        let (template, _) = parse_template(yaml, None);
+        let store = make_store_with_function("aws:ec2/getAmi:getAmi");
+        let result = type_check(&template, &store, None);
+        let diag = result
+            .diagnostics
+            .iter()
+            .find(|d| {
+                d.summary
+                    .contains("ambiguous invoke shorthand 'fn::aws:ec2:getAmy'")
+            })
+            .expect("expected ambiguity warning");
+        assert!(!diag.is_error());
+        assert!(diag
+            .detail
+            .contains("did you mean 'aws:ec2/getAmi:getAmi'?"));
+        assert!(diag
+            .detail
+            .contains("fn::invoke:\n    function: aws:ec2/getAmi:getAmi"));
+    }
+
+    #[test]
+    fn test_type_check_shorthand_without_schema_not_checked() {
+        let yaml = r#"
+name: test
+runtime: yaml
+variables:
+  a:
+    fn::gcp:compute:getImage: {}
+  b:
+    fn::invoke:
+      function: aws:ec2:getAmy
+"#;
+        let (template, _) = parse_template(yaml, None);
+        let store = make_store_with_function("aws:ec2/getAmi:getAmi");
+        let result = type_check(&template, &store, None);
+        assert!(
+            !result
+                .diagnostics
+                .iter()
+                .any(|d| d.summary.contains("ambiguous")),
+            "only shorthand calls into loaded schemas are checked: {:?}",
+            result
+                .diagnostics
+                .iter()
+                .map(|d| &d.summary)
+                .collect::<Vec<_>>()
+        );
+    }
+
     #[test]
     fn test_type_check_invoke_unknown_return() {
         let yaml = r#"
@@ -1054,6 +1217,7 @@ variables:
                 secret: false,
                 const_value: None,
                 required: true,
+                enum_values: Vec::new(),
             },
         );
         func.required_inputs.insert("owners".to_string());
@@ -1064,6 +1228,7 @@ variables:
                 secret: false,
                 const_value: None,
                 required: false,
+                enum_values: Vec::new(),
             },
         );
 
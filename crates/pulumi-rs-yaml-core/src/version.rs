@@ -0,0 +1,201 @@
+//! Provider version constraints.
+//!
+//! A resource's `options.version` and a `plugins: providers:` entry's
+//! `version` are semver constraints. A bare version such as `4.16.0` (or
+//! `v4.16.0`) pins that exact version; anything else is a requirement in
+//! Cargo syntax, e.g. `^4.1`, `~4.16`, or `>=4.0.0, <5.0.0`. A partial
+//! version needs an operator: Cargo reads a bare `4.16` as `^4.16`, which a
+//! template author may well have meant as a pin. The engine only
+//! accepts exact versions, so requirements are resolved to one during plugin
+//! resolution: the highest installed plugin that satisfies them, else the
+//! lowest version they name.
+
+use std::fmt;
+
+use semver::{Comparator, Op, Version, VersionReq};
+
+/// Errors from parsing a version constraint.
+#[derive(Debug, thiserror::Error)]
+pub enum VersionError {
+    #[error("invalid version constraint '{text}': {source}")]
+    Syntax { text: String, source: semver::Error },
+    /// A partial version without an operator, such as `4.16`.
+    #[error(
+        "ambiguous version constraint '{text}': write ^{part} for a requirement, or a full version to pin one"
+    )]
+    Partial { text: String, part: String },
+}
+
+/// A parsed provider version constraint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionConstraint {
+    /// Exactly this version.
+    Exact(Version),
+    /// Any version satisfying the requirement.
+    Range(VersionReq),
+}
+
+impl VersionConstraint {
+    /// Parses a constraint as written in a template.
+    pub fn parse(text: &str) -> Result<Self, VersionError> {
+        let trimmed = text.trim();
+        if let Ok(version) = Version::parse(trimmed.strip_prefix('v').unwrap_or(trimmed)) {
+            return Ok(VersionConstraint::Exact(version));
+        }
+        let req = VersionReq::parse(trimmed).map_err(|source| VersionError::Syntax {
+            text: text.to_string(),
+            source,
+        })?;
+        if let Some(part) = trimmed.split(',').map(str::trim).find(|part| {
+            part.starts_with(|c: char| c.is_ascii_digit()) && !part.contains(['*', 'x', 'X'])
+        }) {
+            return Err(VersionError::Partial {
+                text: text.to_string(),
+                part: part.to_string(),
+            });
+        }
+        Ok(VersionConstraint::Range(req))
+    }
+
+    /// Whether `version` satisfies the constraint.
+    pub fn matches(&self, version: &Version) -> bool {
+        match self {
+            VersionConstraint::Exact(exact) => exact == version,
+            VersionConstraint::Range(req) => req.matches(version),
+        }
+    }
+
+    /// Whether some version satisfies both constraints.
+    ///
+    /// Only the versions the constraints name are tried (plus the next one
+    /// above each exclusive lower bound, and `0.0.0`). Two constraints with
+    /// a common version always share one of these unless they only overlap
+    /// in pre-releases.
+    pub fn is_compatible(&self, other: &VersionConstraint) -> bool {
+        let mut candidates = self.candidates();
+        candidates.extend(other.candidates());
+        candidates.push(Version::new(0, 0, 0));
+        candidates
+            .iter()
+            .any(|v| self.matches(v) && other.matches(v))
+    }
+
+    /// Picks the version to use: the highest of `installed` that satisfies
+    /// the constraint, else the lowest version the constraint names that
+    /// satisfies it. `None` if neither exists, e.g. for `<5`.
+    pub fn resolve<'a>(&self, installed: impl IntoIterator<Item = &'a Version>) -> Option<Version> {
+        if let VersionConstraint::Exact(exact) = self {
+            return Some(exact.clone());
+        }
+        installed
+            .into_iter()
+            .filter(|v| self.matches(v))
+            .max()
+            .cloned()
+            .or_else(|| {
+                self.candidates()
+                    .into_iter()
+                    .filter(|v| self.matches(v))
+                    .min()
+            })
+    }
+
+    /// The versions named by the constraint's comparators.
+    fn candidates(&self) -> Vec<Version> {
+        match self {
+            VersionConstraint::Exact(exact) => vec![exact.clone()],
+            VersionConstraint::Range(req) => req
+                .comparators
+                .iter()
+                .flat_map(comparator_versions)
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for VersionConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VersionConstraint::Exact(version) => version.fmt(f),
+            VersionConstraint::Range(req) => req.fmt(f),
+        }
+    }
+}
+
+/// The version a comparator names, and for `>` the next version above it.
+fn comparator_versions(c: &Comparator) -> Vec<Version> {
+    let mut base = Version::new(c.major, c.minor.unwrap_or(0), c.patch.unwrap_or(0));
+    base.pre = c.pre.clone();
+    if c.op != Op::Greater {
+        return vec![base];
+    }
+    let next = match (c.minor, c.patch) {
+        (Some(_), Some(patch)) => Version::new(c.major, base.minor, patch + 1),
+        (Some(minor), None) => Version::new(c.major, minor + 1, 0),
+        _ => Version::new(c.major + 1, 0, 0),
+    };
+    vec![base, next]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> VersionConstraint {
+        VersionConstraint::parse(text).unwrap()
+    }
+
+    fn v(text: &str) -> Version {
+        Version::parse(text).unwrap()
+    }
+
+    #[test]
+    fn test_parse_exact_and_ranges() {
+        assert_eq!(parse("4.16.0"), VersionConstraint::Exact(v("4.16.0")));
+        assert_eq!(
+            parse("v6.0.0-alpha.1"),
+            VersionConstraint::Exact(v("6.0.0-alpha.1"))
+        );
+        assert!(matches!(parse("^4.1"), VersionConstraint::Range(_)));
+        assert_eq!(parse(">=4.0.0, <5.0.0").to_string(), ">=4.0.0, <5.0.0");
+
+        let err = VersionConstraint::parse("latest").unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("invalid version constraint 'latest': "));
+        assert!(VersionConstraint::parse("4.x.y").is_err());
+
+        // Cargo would read these as ^4.16, which may not be what was meant
+        let err = VersionConstraint::parse("4.16").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ambiguous version constraint '4.16': write ^4.16 for a requirement, or a full version to pin one"
+        );
+        assert!(matches!(
+            VersionConstraint::parse(">=4.0.0, 5"),
+            Err(VersionError::Partial { part, .. }) if part == "5"
+        ));
+        assert!(matches!(parse("4.*"), VersionConstraint::Range(_)));
+        assert!(matches!(parse("=4.16"), VersionConstraint::Range(_)));
+    }
+
+    #[test]
+    fn test_compatibility() {
+        assert!(parse("4.16.0").is_compatible(&parse("^4.1")));
+        assert!(parse("^4.1").is_compatible(&parse("~4.2")));
+        assert!(parse(">4.0.0").is_compatible(&parse("<4.0.5")));
+        assert!(parse("<5").is_compatible(&parse("<4")));
+        assert!(!parse("5.0.0").is_compatible(&parse("6.0.0")));
+        assert!(!parse("^5").is_compatible(&parse(">=6.0.0")));
+        assert!(!parse("6.1.0").is_compatible(&parse("~6.0")));
+    }
+
+    #[test]
+    fn test_resolve() {
+        let installed = [v("4.1.0"), v("4.9.2"), v("5.0.0")];
+        assert_eq!(parse("^4.1").resolve(&installed), Some(v("4.9.2")));
+        assert_eq!(parse("4.0.3").resolve(&installed), Some(v("4.0.3")));
+        assert_eq!(parse(">=6, <7").resolve(&installed), Some(v("6.0.0")));
+        assert_eq!(parse("<4").resolve(&installed), None);
+    }
+}
@@ -0,0 +1,297 @@
+//! Filesystem access for project loading and file-reading builtins.
+//!
+//! Project discovery, `extends:`, `fn::readFile`, `fn::templateFile`,
+//! `fn::validateJson` schema files, and the file asset checks all read
+//! through an [`FsProvider`], so hosts decide what a template can see:
+//!
+//! - [`OsFs`] reads the real filesystem and is the default.
+//! - [`MemoryFs`] serves files from memory, for tests and for WASM builds
+//!   without a filesystem.
+//! - [`RootedFs`] wraps another provider and refuses paths outside a set of
+//!   allowed directories, for sandboxed hosts.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// What a path names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Dir,
+}
+
+/// A filesystem that templates are loaded from and builtins read.
+pub trait FsProvider: Send + Sync {
+    /// Reads the whole file at `path`.
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Returns what `path` names, or an error if nothing does.
+    fn kind(&self, path: &Path) -> io::Result<EntryKind>;
+
+    /// Lists the names of the entries in the directory at `path`.
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<String>>;
+
+    /// Returns the canonical form of `path`, which must exist. Two paths
+    /// naming the same file have the same canonical form.
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+
+    /// Reads the whole file at `path` as UTF-8.
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Returns true if `path` names a file.
+    fn is_file(&self, path: &Path) -> bool {
+        matches!(self.kind(path), Ok(EntryKind::File))
+    }
+}
+
+/// The host filesystem.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsFs;
+
+impl FsProvider for OsFs {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        std::fs::read(path)
+    }
+
+    fn kind(&self, path: &Path) -> io::Result<EntryKind> {
+        let metadata = std::fs::metadata(path)?;
+        Ok(if metadata.is_dir() {
+            EntryKind::Dir
+        } else {
+            EntryKind::File
+        })
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<String>> {
+        std::fs::read_dir(path)?
+            .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+            .collect()
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        std::fs::canonicalize(path)
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+}
+
+/// Files held in memory. Directories exist implicitly as the ancestors of
+/// the files added. Paths are normalized lexically, so `a/./b` and
+/// `a/c/../b` name the same file; there are no symlinks.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryFs {
+    files: BTreeMap<PathBuf, Vec<u8>>,
+}
+
+impl MemoryFs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds or replaces the file at `path`.
+    pub fn insert(&mut self, path: impl AsRef<Path>, contents: impl Into<Vec<u8>>) {
+        self.files.insert(normalize(path.as_ref()), contents.into());
+    }
+
+    /// Adds or replaces the file at `path`, returning `self`.
+    pub fn with_file(mut self, path: impl AsRef<Path>, contents: impl Into<Vec<u8>>) -> Self {
+        self.insert(path, contents);
+        self
+    }
+}
+
+impl FsProvider for MemoryFs {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files
+            .get(&normalize(path))
+            .cloned()
+            .ok_or_else(|| not_found(path))
+    }
+
+    fn kind(&self, path: &Path) -> io::Result<EntryKind> {
+        let path = normalize(path);
+        if self.files.contains_key(&path) {
+            Ok(EntryKind::File)
+        } else if self.files.keys().any(|file| file.starts_with(&path)) {
+            Ok(EntryKind::Dir)
+        } else {
+            Err(not_found(&path))
+        }
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<String>> {
+        let dir = normalize(path);
+        let mut names: Vec<String> = self
+            .files
+            .keys()
+            .filter_map(|file| file.strip_prefix(&dir).ok())
+            .filter_map(|rest| rest.components().next())
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        if names.is_empty() {
+            return Err(not_found(&dir));
+        }
+        names.dedup();
+        Ok(names)
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        self.kind(path)?;
+        Ok(normalize(path))
+    }
+}
+
+/// Restricts another provider to paths inside a set of root directories.
+///
+/// Each path is canonicalized by the inner provider before it is checked,
+/// so `..` components and symlinks cannot leave the roots. Paths outside
+/// them fail with [`io::ErrorKind::PermissionDenied`].
+#[derive(Debug, Clone)]
+pub struct RootedFs<F = OsFs> {
+    inner: F,
+    roots: Vec<PathBuf>,
+}
+
+impl<F: FsProvider> RootedFs<F> {
+    /// Allows `roots` on `inner`. Roots that do not exist are dropped.
+    pub fn new(inner: F, roots: impl IntoIterator<Item = impl AsRef<Path>>) -> Self {
+        let roots = roots
+            .into_iter()
+            .filter_map(|root| inner.canonicalize(root.as_ref()).ok())
+            .collect();
+        Self { inner, roots }
+    }
+
+    /// The canonical allowed roots.
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    fn check(&self, path: &Path) -> io::Result<PathBuf> {
+        let canonical = self.inner.canonicalize(path)?;
+        if self.roots.iter().any(|root| canonical.starts_with(root)) {
+            Ok(canonical)
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("{} is outside the allowed directories", path.display()),
+            ))
+        }
+    }
+}
+
+impl<F: FsProvider> FsProvider for RootedFs<F> {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.inner.read(&self.check(path)?)
+    }
+
+    fn kind(&self, path: &Path) -> io::Result<EntryKind> {
+        self.inner.kind(&self.check(path)?)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<String>> {
+        self.inner.read_dir(&self.check(path)?)
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        self.check(path)
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        self.inner.read_to_string(&self.check(path)?)
+    }
+}
+
+/// Removes `.` components and resolves `..` against the preceding component.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push("..");
+                }
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{} does not exist", path.display()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory() -> MemoryFs {
+        MemoryFs::new()
+            .with_file("/project/Pulumi.yaml", "name: test\n")
+            .with_file("/project/scripts/setup.sh", "echo hi\n")
+            .with_file("/secrets/key", "hunter2")
+    }
+
+    #[test]
+    fn test_memory_fs_reads_and_lists() {
+        let fs = memory();
+        assert_eq!(
+            fs.read_to_string(Path::new("/project/scripts/../Pulumi.yaml"))
+                .unwrap(),
+            "name: test\n"
+        );
+        assert_eq!(
+            fs.kind(Path::new("/project/scripts")).unwrap(),
+            EntryKind::Dir
+        );
+        assert!(fs.is_file(Path::new("/project/./scripts/setup.sh")));
+        assert_eq!(
+            fs.read_dir(Path::new("/project")).unwrap(),
+            vec!["Pulumi.yaml", "scripts"]
+        );
+        let err = fs.read(Path::new("/project/missing")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_rooted_fs_refuses_paths_outside_roots() {
+        let fs = RootedFs::new(memory(), ["/project"]);
+        assert!(fs.read(Path::new("/project/scripts/setup.sh")).is_ok());
+        for path in ["/secrets/key", "/project/../secrets/key"] {
+            let err = fs.read(Path::new(path)).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::PermissionDenied, "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_rooted_fs_follows_symlinks_before_checking() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("key"), "hunter2").unwrap();
+        std::fs::write(root.path().join("motd.txt"), "hello").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(outside.path().join("key"), root.path().join("link")).unwrap();
+
+        let fs = RootedFs::new(OsFs, [root.path()]);
+        assert_eq!(
+            fs.read_to_string(&root.path().join("motd.txt")).unwrap(),
+            "hello"
+        );
+        #[cfg(unix)]
+        assert_eq!(
+            fs.read(&root.path().join("link")).unwrap_err().kind(),
+            io::ErrorKind::PermissionDenied
+        );
+    }
+}
@@ -31,7 +31,7 @@ fn eval_with_mock_and_config(
     if parse_diags.has_errors() {
         panic!("parse errors: {}", parse_diags);
     }
-    let template: &'static _ = Box::leak(Box::new(template));
+    let template = &template;
     let eval = Evaluator::with_callback(
         "test".to_string(),
         "dev".to_string(),
@@ -71,7 +71,7 @@ fn eval_multifile(
     }
 
     let template = merged.as_template_decl();
-    let template: &'static _ = Box::leak(Box::new(template));
+    let template = &template;
 
     let eval = Evaluator::with_callback(
         "test".to_string(),
@@ -1976,6 +1976,7 @@ resources:
 /// B13: Multi-file multi-SQL with readFile() — SQL loaded from .sql files via Jinja.
 /// Uses temp directory with actual .sql files on disk + Jinja preprocessor.
 #[test]
+#[cfg(feature = "jinja")]
 fn test_gcpx_dbt_multi_sql_readfile() {
     use pulumi_rs_yaml_core::jinja::{JinjaContext, UndefinedMode};
     use pulumi_rs_yaml_core::multi_file::load_project;
@@ -2065,6 +2066,7 @@ outputs:
         config: &config,
         project_dir: dir.path().to_str().unwrap(),
         undefined: UndefinedMode::Strict,
+        args: &[],
         extra: &HashMap::new(),
     };
 
@@ -2074,7 +2076,7 @@ outputs:
 
     // Now evaluate with mock callback
     let template = merged.as_template_decl();
-    let template: &'static _ = Box::leak(Box::new(template));
+    let template = &template;
 
     // ctdMacro (c) before dbtProject (d) alphabetically
     let mock = MockCallback::with_register_responses(vec![
@@ -2275,7 +2277,7 @@ outputs:
 
     let (template, parse_diags) = parse_template(&source, None);
     assert!(!parse_diags.has_errors(), "parse errors: {}", parse_diags);
-    let template: &'static _ = Box::leak(Box::new(template));
+    let template = &template;
 
     let eval = Evaluator::with_callback(
         "test".to_string(),
@@ -4,30 +4,41 @@
 
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
 
 use pulumi_rs_yaml_core::ast::parse::parse_template;
 use pulumi_rs_yaml_core::eval::callback::{InvokeResponse, RegisterResponse};
-use pulumi_rs_yaml_core::eval::evaluator::Evaluator;
+use pulumi_rs_yaml_core::eval::evaluator::{EvalMode, Evaluator, PlannedResource, ProgressSink};
+use pulumi_rs_yaml_core::eval::hooks::RegisteredResource;
 use pulumi_rs_yaml_core::eval::mock::MockCallback;
+use pulumi_rs_yaml_core::eval::timing::TimingReport;
 use pulumi_rs_yaml_core::eval::value::{Archive, Asset, Value};
+use pulumi_rs_yaml_core::source::SourceArena;
+use pulumi_rs_yaml_core::syntax::Span;
 
 /// Helper to create an evaluator with a mock callback.
-///
-/// Uses `Box::leak` to give the template a `'static` lifetime, which is fine
-/// for tests since the process exits after each test anyway.
 fn eval_with_mock(source: &str, mock: MockCallback) -> (Evaluator<'static, MockCallback>, bool) {
+    eval_with_mock_in(source, mock, Path::new("/tmp"))
+}
+
+/// Like `eval_with_mock`, with `dir` as the project directory.
+fn eval_with_mock_in(
+    source: &str,
+    mock: MockCallback,
+    dir: &Path,
+) -> (Evaluator<'static, MockCallback>, bool) {
     let (template, parse_diags) = parse_template(source, None);
     if parse_diags.has_errors() {
         panic!("parse errors: {}", parse_diags);
     }
 
-    // Leak the template so it lives for 'static — acceptable in tests.
-    let template: &'static _ = Box::leak(Box::new(template));
+    let template = &template;
 
     let eval = Evaluator::with_callback(
         "test".to_string(),
         "dev".to_string(),
-        "/tmp".to_string(),
+        dir.display().to_string(),
         false,
         mock,
     );
@@ -49,7 +60,7 @@ fn eval_with_mock_and_config(
         panic!("parse errors: {}", parse_diags);
     }
 
-    let template: &'static _ = Box::leak(Box::new(template));
+    let template = &template;
 
     let eval = Evaluator::with_callback(
         "test".to_string(),
@@ -143,6 +154,74 @@ resources:
     assert_eq!(regs[1].type_token, "aws:s3/bucketObject:BucketObject");
 }
 
+#[test]
+fn test_computed_resource_names() {
+    let source = r#"
+name: test
+runtime: yaml
+variables:
+  prefix: acme
+resources:
+  vpc:
+    type: aws:ec2:Vpc
+    properties:
+      region: us-west-2
+  subnet:
+    type: aws:ec2:Subnet
+    name: ${prefix}-${vpc.region}-subnet
+  logs:
+    type: aws:s3:Bucket
+    name: ${prefix}-logs
+"#;
+    let (eval, has_errors) = eval_with_mock(source, MockCallback::new());
+    assert!(!has_errors, "errors: {}", eval.diags_display());
+
+    let regs = eval.callback().registrations();
+    let subnet = regs
+        .iter()
+        .find(|r| r.type_token == "aws:ec2/subnet:Subnet")
+        .unwrap();
+    assert_eq!(subnet.name, "acme-us-west-2-subnet");
+    // The name's reference to vpc makes it a dependency
+    let vpc_urn = eval.get_resource("vpc").unwrap().urn;
+    assert_eq!(subnet.options.depends_on, vec![vpc_urn]);
+    assert!(regs.iter().any(|r| r.name == "acme-logs"));
+}
+
+#[test]
+fn test_unknown_resource_name_is_previewed_as_logical_name() {
+    let source = r#"
+name: test
+runtime: yaml
+resources:
+  vpc:
+    type: aws:ec2:Vpc
+  subnet:
+    type: aws:ec2:Subnet
+    name: ${vpc.arn}-subnet
+"#;
+    let (template, _) = parse_template(source, None);
+    let mock = MockCallback::with_register_responses(vec![RegisterResponse {
+        urn: "urn:pulumi:test::test::aws:ec2/vpc:Vpc::vpc".to_string(),
+        id: String::new(),
+        outputs: HashMap::from([("arn".to_string(), Value::Unknown)]),
+        stables: Vec::new(),
+    }]);
+    let eval = Evaluator::with_callback(
+        "test".to_string(),
+        "dev".to_string(),
+        "/tmp".to_string(),
+        true,
+        mock,
+    );
+    eval.evaluate_template(&template, &HashMap::new(), &[]);
+    assert!(!eval.has_errors(), "errors: {}", eval.diags_display());
+    assert_eq!(eval.callback().registrations()[1].name, "subnet");
+    assert!(eval
+        .diags_display()
+        .contains("the name of resource 'subnet' is not known until the update"));
+}
+
 #[test]
 fn test_config_resolution_with_resource() {
     let source = r#"
@@ -290,6 +369,155 @@ resources:
     );
 }
 
+fn page(items: &[&str], next: Option<&str>) -> InvokeResponse {
+    let mut return_values = HashMap::new();
+    return_values.insert(
+        "zones".to_string(),
+        Value::List(
+            items
+                .iter()
+                .map(|i| Value::String(Cow::Owned(i.to_string())))
+                .collect(),
+        ),
+    );
+    if let Some(next) = next {
+        return_values.insert(
+            "nextMarker".to_string(),
+            Value::String(Cow::Owned(next.to_string())),
+        );
+    }
+    InvokeResponse {
+        return_values,
+        failures: Vec::new(),
+    }
+}
+
+#[test]
+fn test_invoke_paginate_concatenates_pages() {
+    let source = r#"
+name: test
+runtime: yaml
+variables:
+  zones:
+    fn::invoke:
+      function: aws:route53:listZones
+      arguments:
+        maxItems: 2
+      options:
+        paginate:
+          itemsField: zones
+          tokenField: nextMarker
+          tokenArgument: marker
+outputs:
+  zones: ${zones}
+"#;
+    let mock = MockCallback::with_invoke_responses(vec![
+        page(&["a", "b"], Some("p2")),
+        page(&["c", "d"], Some("p3")),
+        page(&["e"], Some("")),
+    ]);
+    let (eval, has_errors) = eval_with_mock(source, mock);
+    assert!(!has_errors, "errors: {}", eval.diags_display());
+
+    let invocations = eval.callback().invocations();
+    assert_eq!(invocations.len(), 3);
+    assert!(!invocations[0].args.contains_key("marker"));
+    assert_eq!(
+        invocations[2].args.get("marker").and_then(|v| v.as_str()),
+        Some("p3")
+    );
+    assert!(invocations[2].args.contains_key("maxItems"));
+
+    let zones = eval.take_outputs().remove("zones").unwrap();
+    let zones: Vec<_> = match zones {
+        Value::List(items) => items
+            .iter()
+            .map(|v| v.as_str().unwrap().to_string())
+            .collect(),
+        other => panic!("expected list, got {:?}", other),
+    };
+    assert_eq!(zones, ["a", "b", "c", "d", "e"]);
+}
+
+#[test]
+fn test_invoke_paginate_stops_on_repeated_token() {
+    let source = r#"
+name: test
+runtime: yaml
+variables:
+  zones:
+    fn::invoke:
+      function: aws:route53:listZones
+      options:
+        paginate:
+          itemsField: zones
+          tokenField: nextMarker
+outputs:
+  zones: ${zones}
+"#;
+    let mock = MockCallback::with_invoke_responses(vec![
+        page(&["a"], Some("p2")),
+        page(&["b"], Some("p2")),
+    ]);
+    let (eval, has_errors) = eval_with_mock(source, mock);
+    assert!(has_errors);
+    assert_eq!(eval.callback().invocations().len(), 2);
+    assert!(eval
+        .diags_display()
+        .contains("returned page token 'p2' twice"));
+}
+
+#[test]
+fn test_invoke_paginate_limits_pages_and_redacts_secret_tokens() {
+    let source = r#"
+name: test
+runtime: yaml
+variables:
+  zones:
+    fn::invoke:
+      function: aws:route53:listZones
+      options:
+        paginate:
+          itemsField: zones
+          tokenField: nextMarker
+"#;
+    let (template, _) = parse_template(source, None);
+    let mock = MockCallback::with_invoke_responses(vec![
+        page(&["a"], Some("p2")),
+        page(&["b"], Some("p3")),
+        page(&["c"], Some("")),
+    ]);
+    let mut eval =
+        Evaluator::with_callback("test".into(), "dev".into(), "/tmp".into(), false, mock);
+    eval.limits.max_pages = 2;
+    eval.evaluate_template(&template, &HashMap::new(), &[]);
+    assert_eq!(eval.callback().invocations().len(), 2);
+    assert!(
+        eval.diags_display().contains("returned more than 2 pages"),
+        "{}",
+        eval.diags_display()
+    );
+
+    let secret_page = |item: &str| {
+        let mut response = page(&[item], None);
+        response.return_values.insert(
+            "nextMarker".to_string(),
+            Value::Secret(Box::new(Value::String(Cow::Borrowed("hunter2")))),
+        );
+        response
+    };
+    let mock = MockCallback::with_invoke_responses(vec![secret_page("a"), secret_page("b")]);
+    let (eval, has_errors) = eval_with_mock(source, mock);
+    assert!(has_errors);
+    let errors = eval.diags_display();
+    assert!(
+        errors.contains("returned a secret page token twice"),
+        "{}",
+        errors
+    );
+    assert!(!errors.contains("hunter2"), "{}", errors);
+}
+
 #[test]
 fn test_invoke_without_return() {
     let source = r#"
@@ -764,6 +992,124 @@ variables:
     assert!(has_errors, "should report invoke failures");
 }
 
+#[test]
+fn test_rich_check_failures_name_template_argument() {
+    let source = r#"
+name: test
+runtime: yaml
+variables:
+  result:
+    fn::invoke:
+      function: aws:ec2:getAmi
+      arguments:
+        filters:
+          - name: owner-alias
+            values: [amazon]
+      return: id
+"#;
+    let (template, _) = parse_template(source, None);
+    let template = &template;
+    let evaluate = |rich: bool| {
+        let mock = MockCallback::with_invoke_responses(vec![InvokeResponse {
+            return_values: HashMap::new(),
+            failures: vec![
+                (
+                    "filters[0].values[0]".to_string(),
+                    "unknown owner".to_string(),
+                ),
+                ("filters[0].region".to_string(), "required".to_string()),
+                ("mostRecent".to_string(), "required".to_string()),
+            ],
+        }]);
+        let mut eval = Evaluator::with_callback(
+            "test".to_string(),
+            "dev".to_string(),
+            "/tmp".to_string(),
+            false,
+            mock,
+        );
+        eval.rich_check_failures = rich;
+        eval.evaluate_template(template, &HashMap::new(), &[]);
+        eval.diags_display()
+    };
+
+    let flat = evaluate(false);
+    assert!(
+        flat.contains("invoke aws:ec2/getAmi:getAmi failed on property 'filters[0].values[0]'"),
+        "{}",
+        flat
+    );
+
+    let rich = evaluate(true);
+    assert!(
+        rich.contains(
+            "invoke aws:ec2/getAmi:getAmi rejected argument 'filters[0].values[0]': unknown owner; \
+             the value is set by 'filters[0].values[0]' in the invoke arguments"
+        ),
+        "{}",
+        rich
+    );
+    assert!(
+        rich.contains("the closest argument is 'filters[0]'"),
+        "{}",
+        rich
+    );
+    assert!(
+        rich.contains("'mostRecent' is not set in the invoke arguments"),
+        "{}",
+        rich
+    );
+}
+
+#[test]
+fn test_rich_check_failures_locate_resource_property() {
+    let source = r#"name: test
+runtime: yaml
+resources:
+  site:
+    type: aws:s3:Bucket
+    properties:
+      tags:
+        env: 1
+"#;
+    let mut arena = SourceArena::new();
+    let file = arena.add_file("Pulumi.yaml".to_string(), source.to_string());
+    let (template, diags) = parse_template(source, Some(Span::new(file, 0, source.len() as u32)));
+    assert!(!diags.has_errors(), "{}", diags);
+    let template = &template;
+
+    let mock = MockCallback::new();
+    mock.fail_registration(
+        "site",
+        "aws:s3/bucket:Bucket resource 'site': property tags.env value {1} has a problem: must be a string",
+    );
+    let mut eval = Evaluator::with_callback(
+        "test".to_string(),
+        "dev".to_string(),
+        "/tmp".to_string(),
+        false,
+        mock,
+    );
+    eval.rich_check_failures = true;
+    eval.sources = Some(Arc::new(arena));
+    eval.evaluate_template(template, &HashMap::new(), &[]);
+
+    let messages = eval.diag_error_messages();
+    assert_eq!(messages.len(), 1, "{:?}", messages);
+    assert!(
+        messages[0].starts_with(
+            "Pulumi.yaml:8:14: resource 'site' rejected property 'tags.env': must be a string"
+        ),
+        "{}",
+        messages[0]
+    );
+    assert!(
+        messages[0].contains("the value is set by 'tags.env' in the resource properties"),
+        "{}",
+        messages[0]
+    );
+}
+
 #[test]
 fn test_undefined_variable_reference() {
     let source = r#"
@@ -864,6 +1210,129 @@ outputs:
     );
 }
 
+#[test]
+fn test_config_default_references_config_and_pulumi() {
+    let source = r#"
+name: test
+runtime: yaml
+config:
+  bucketName:
+    type: string
+    default: ${prefix}-logs
+  prefix:
+    type: string
+    default: ${pulumi.project}-${pulumi.stack}
+outputs:
+  result: ${bucketName}
+"#;
+    let result = |raw_config| {
+        let (eval, has_errors) =
+            eval_with_mock_and_config(source, MockCallback::new(), raw_config, &[]);
+        assert!(!has_errors, "errors: {}", eval.diags_display());
+        eval.get_output("result")
+            .and_then(|v| v.as_str().map(|s| s.to_string()))
+    };
+
+    assert_eq!(result(HashMap::new()).as_deref(), Some("test-dev-logs"));
+    let raw_config = HashMap::from([("test:prefix".to_string(), "acme".to_string())]);
+    assert_eq!(result(raw_config).as_deref(), Some("acme-logs"));
+}
+
+#[test]
+fn test_config_duration_and_bytes_types() {
+    let source = r#"
+name: test
+runtime: yaml
+config:
+  timeout:
+    type: Duration
+    default: 5m
+  volumeSize:
+    type: Bytes
+resources:
+  fn:
+    type: aws:lambda:Function
+    properties:
+      timeout: ${timeout}
+      description:
+        fn::formatBytes: ${volumeSize}
+outputs:
+  timeout:
+    fn::formatDuration: ${timeout}
+"#;
+    let raw_config = HashMap::from([("test:volumeSize".to_string(), "10Gi".to_string())]);
+    let (eval, has_errors) =
+        eval_with_mock_and_config(source, MockCallback::new(), raw_config, &[]);
+    assert!(!has_errors, "errors: {}", eval.diags_display());
+
+    let regs = eval.callback().registrations();
+    assert_eq!(
+        regs[0].inputs.get("timeout"),
+        Some(&Value::Number(300.0.into()))
+    );
+    assert_eq!(
+        regs[0].inputs.get("description").and_then(|v| v.as_str()),
+        Some("10Gi")
+    );
+    assert_eq!(
+        eval.get_output("timeout")
+            .and_then(|v| v.as_str().map(str::to_string)),
+        Some("5m".to_string())
+    );
+
+    let raw_config = HashMap::from([("test:volumeSize".to_string(), "10 GB".to_string())]);
+    let (eval, has_errors) =
+        eval_with_mock_and_config(source, MockCallback::new(), raw_config, &[]);
+    assert!(has_errors);
+    assert!(eval
+        .diags_display()
+        .contains("config value '10 GB' is not a valid byte size"));
+}
+
+#[test]
+fn test_snippets_expand_into_resources() {
+    let source = r#"
+name: test
+runtime: yaml
+snippets:
+  bucket:
+    params:
+      app: {}
+      versioned: { default: false }
+    body:
+      type: aws:s3:Bucket
+      properties:
+        bucket: ${params.app}-assets
+        versioning:
+          enabled: ${params.versioned}
+resources:
+  web:
+    fn::snippet: { name: bucket, params: { app: web, versioned: true } }
+  api:
+    fn::snippet: { name: bucket, params: { app: api } }
+outputs:
+  name: ${web.bucket}
+"#;
+    let (eval, has_errors) = eval_with_mock(source, MockCallback::new());
+    assert!(!has_errors, "errors: {}", eval.diags_display());
+
+    let regs = eval.callback().registrations();
+    assert_eq!(regs.len(), 2);
+    let web = regs.iter().find(|r| r.name == "web").unwrap();
+    assert_eq!(
+        web.inputs.get("bucket").and_then(|v| v.as_str()),
+        Some("web-assets")
+    );
+    let api = regs.iter().find(|r| r.name == "api").unwrap();
+    match api.inputs.get("versioning") {
+        Some(Value::Object(entries)) => {
+            assert_eq!(entries[0].0, "enabled");
+            assert_eq!(entries[0].1, Value::Bool(false));
+        }
+        other => panic!("expected versioning object, got {:?}", other),
+    }
+}
+
 #[test]
 fn test_config_type_mismatch_error() {
     let source = r#"
@@ -1035,6 +1504,46 @@ resources:
     assert_eq!(regs[0].options.version, "5.0.0");
 }
 
+#[test]
+fn test_resource_options_version_requirement_uses_resolved_version() {
+    let source = r#"
+name: test
+runtime: yaml
+resources:
+  bucket:
+    type: aws:s3:Bucket
+    options:
+      version: ^5.1
+  zone:
+    type: gcp:dns:ManagedZone
+    options:
+      version: ~7.2
+"#;
+    let (template, _) = parse_template(source, None);
+    let template = &template;
+    let mut eval = Evaluator::with_callback(
+        "test".to_string(),
+        "dev".to_string(),
+        "/tmp".to_string(),
+        false,
+        MockCallback::new(),
+    );
+    eval.package_versions = HashMap::from([("aws".to_string(), "5.3.0".to_string())]);
+    eval.evaluate_template(template, &HashMap::new(), &[]);
+    assert!(!eval.has_errors(), "{}", eval.diags_display());
+
+    let regs = eval.callback().registrations();
+    let version = |name: &str| {
+        regs.iter()
+            .find(|r| r.name == name)
+            .map(|r| r.options.version.clone())
+            .unwrap()
+    };
+    assert_eq!(version("bucket"), "5.3.0");
+    // Unresolved requirements are left to the engine's default version.
+    assert_eq!(version("zone"), "");
+}
+
 #[test]
 fn test_resource_options_custom_timeouts() {
     let source = r#"
@@ -1195,19 +1704,27 @@ outputs:
     let json_str = json_string.as_str();
     let parsed: serde_json::Value = serde_json::from_str(json_str).unwrap();
     assert_eq!(parsed["key"], "value");
-    assert_eq!(parsed["num"], serde_json::json!(42.0));
+    assert_eq!(parsed["num"], serde_json::json!(42));
 }
 
 #[test]
-fn test_builtin_to_base64() {
+fn test_large_integer_literals_are_exact() {
     let source = r#"
 name: test
 runtime: yaml
+resources:
+  bucket:
+    type: test:index:Bucket
+    properties:
+      accountId: 123456789012345678
 variables:
-  encoded:
-    fn::toBase64: "Hello, World!"
+  nanos: 9007199254740993
+  jsonStr:
+    fn::toJSON:
+      nanos: ${nanos}
 outputs:
-  result: ${encoded}
+  json: ${jsonStr}
+  text: "at ${nanos}"
 "#;
 
     let mock = MockCallback::new();
@@ -1215,8 +1732,45 @@ outputs:
     assert!(!has_errors, "errors: {}", eval.diags_display());
 
     assert_eq!(
-        eval.get_output("result")
-            .and_then(|v| v.as_str().map(|s| s.to_string()))
+        eval.get_variable("nanos").and_then(|v| v.as_i64()),
+        Some(9_007_199_254_740_993)
+    );
+    assert_eq!(
+        eval.get_output("json")
+            .and_then(|v| v.as_str().map(String::from)),
+        Some(r#"{"nanos":9007199254740993}"#.to_string())
+    );
+    assert_eq!(
+        eval.get_output("text")
+            .and_then(|v| v.as_str().map(String::from)),
+        Some("at 9007199254740993".to_string())
+    );
+    let regs = eval.callback().registrations();
+    assert_eq!(
+        regs[0].inputs.get("accountId").and_then(|v| v.as_i64()),
+        Some(123_456_789_012_345_678)
+    );
+}
+
+#[test]
+fn test_builtin_to_base64() {
+    let source = r#"
+name: test
+runtime: yaml
+variables:
+  encoded:
+    fn::toBase64: "Hello, World!"
+outputs:
+  result: ${encoded}
+"#;
+
+    let mock = MockCallback::new();
+    let (eval, has_errors) = eval_with_mock(source, mock);
+    assert!(!has_errors, "errors: {}", eval.diags_display());
+
+    assert_eq!(
+        eval.get_output("result")
+            .and_then(|v| v.as_str().map(|s| s.to_string()))
             .as_deref(),
         Some("SGVsbG8sIFdvcmxkIQ==")
     );
@@ -1267,6 +1821,38 @@ outputs:
     assert_eq!(val.unwrap_secret().as_str(), Some("my-secret-value"));
 }
 
+#[test]
+fn test_parse_yaml_config_file_with_property_access() {
+    let source = r#"
+name: test
+runtime: yaml
+variables:
+  settings:
+    fn::parseYAML:
+      fn::readFile: ./settings.yaml
+  limits:
+    fn::parseJSON: '{"maxSize": 10}'
+outputs:
+  port: ${settings.db.port}
+  firstHost: ${settings.db.hosts[0]}
+  maxSize: ${limits.maxSize}
+"#;
+
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("settings.yaml"),
+        "db:\n  port: 5432\n  hosts: [primary, replica]\n",
+    )
+    .unwrap();
+    let (eval, has_errors) = eval_with_mock_in(source, MockCallback::new(), dir.path());
+    assert!(!has_errors, "errors: {}", eval.diags_display());
+
+    let outputs = eval.take_outputs();
+    assert_eq!(outputs["port"].as_number(), Some(5432.0));
+    assert_eq!(outputs["firstHost"].as_str(), Some("primary"));
+    assert_eq!(outputs["maxSize"].as_number(), Some(10.0));
+}
+
 #[test]
 fn test_file_asset_in_resource() {
     let source = r#"
@@ -1280,19 +1866,64 @@ resources:
         fn::fileAsset: ./index.html
 "#;
 
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("index.html"), "hello").unwrap();
     let mock = MockCallback::new();
-    let (eval, has_errors) = eval_with_mock(source, mock);
+    let (eval, has_errors) = eval_with_mock_in(source, mock, dir.path());
     assert!(!has_errors, "errors: {}", eval.diags_display());
 
     let regs = eval.callback().registrations();
     match regs[0].inputs.get("source") {
-        Some(Value::Asset(Asset::File(path))) => {
+        Some(Value::Asset(Asset::File(path, hash))) => {
             assert_eq!(path.as_ref(), "./index.html");
+            assert_eq!(
+                hash.as_deref(),
+                Some("2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824")
+            );
         }
         other => panic!("expected file asset, got {:?}", other),
     }
 }
 
+#[test]
+fn test_missing_file_assets_name_resource_and_property() {
+    let source = r#"
+name: test
+runtime: yaml
+resources:
+  func:
+    type: aws:lambda:Function
+    properties:
+      code:
+        fn::fileArchive: ./lambda.zip
+      layers:
+        - fn::fileAsset: ./layer.txt
+"#;
+
+    let dir = tempfile::tempdir().unwrap();
+    let (eval, has_errors) = eval_with_mock_in(source, MockCallback::new(), dir.path());
+    assert!(has_errors);
+    assert!(eval.callback().registrations().is_empty());
+    assert_eq!(
+        eval.diag_errors(),
+        [
+            "resource 'func' property 'code': fileArchive './lambda.zip' does not exist",
+            "resource 'func' property 'layers[0]': fileAsset './layer.txt' does not exist",
+        ]
+    );
+
+    // Previews do not touch the filesystem.
+    let (template, _) = parse_template(source, None);
+    let eval = Evaluator::new(
+        "test".to_string(),
+        "dev".to_string(),
+        dir.path().display().to_string(),
+        true,
+    );
+    eval.evaluate_template(&template, &HashMap::new(), &[]);
+    assert!(!eval.has_errors(), "errors: {}", eval.diags_display());
+}
+
 #[test]
 fn test_string_asset_in_resource() {
     let source = r#"
@@ -1332,8 +1963,10 @@ resources:
         fn::fileArchive: ./lambda.zip
 "#;
 
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("lambda.zip"), "PK").unwrap();
     let mock = MockCallback::new();
-    let (eval, has_errors) = eval_with_mock(source, mock);
+    let (eval, has_errors) = eval_with_mock_in(source, mock, dir.path());
     assert!(!has_errors, "errors: {}", eval.diags_display());
 
     let regs = eval.callback().registrations();
@@ -1960,7 +2593,7 @@ outputs:
     let mock = MockCallback::new();
     let (template, parse_diags) = parse_template(source, None);
     assert!(!parse_diags.has_errors());
-    let template: &'static _ = Box::leak(Box::new(template));
+    let template = &template;
 
     let mut eval = Evaluator::with_callback(
         "test".to_string(),
@@ -2010,7 +2643,7 @@ outputs:
     let mock = MockCallback::new();
     let (template, parse_diags) = parse_template(source, None);
     assert!(!parse_diags.has_errors());
-    let template: &'static _ = Box::leak(Box::new(template));
+    let template = &template;
 
     let mut eval = Evaluator::with_callback(
         "test".to_string(),
@@ -2037,18 +2670,18 @@ outputs:
 use pulumi_rs_yaml_core::schema::{PackageSchema, ResourceTypeInfo, SchemaStore};
 
 /// Helper to create an evaluator with schema store and mock callback.
-fn eval_with_schema(
+fn eval_with_schema<'s>(
     source: &str,
     mock: MockCallback,
-    schema_store: Option<SchemaStore>,
+    schema_store: Option<&'s SchemaStore>,
     dry_run: bool,
-) -> (Evaluator<'static, MockCallback>, bool) {
+) -> (Evaluator<'s, MockCallback>, bool) {
     let (template, parse_diags) = parse_template(source, None);
     if parse_diags.has_errors() {
         panic!("parse errors: {}", parse_diags);
     }
 
-    let template: &'static _ = Box::leak(Box::new(template));
+    let template = &template;
 
     let mut eval = Evaluator::with_callback(
         "test".to_string(),
@@ -2057,7 +2690,7 @@ fn eval_with_schema(
         dry_run,
         mock,
     );
-    eval.schema_store = schema_store.map(|s| &*Box::leak(Box::new(s)) as &'static SchemaStore);
+    eval.schema_store = schema_store;
     let raw_config = HashMap::new();
     eval.evaluate_template(template, &raw_config, &[]);
     let has_errors = eval.has_errors();
@@ -2107,7 +2740,7 @@ resources:
     let mock = MockCallback::new();
     let store = make_bucket_schema();
 
-    let (eval, has_errors) = eval_with_schema(source, mock, Some(store), true);
+    let (eval, has_errors) = eval_with_schema(source, mock, Some(&store), true);
     assert!(!has_errors, "errors: {}", eval.diags_display());
 
     let state = eval.get_resource("myBucket").unwrap();
@@ -2124,6 +2757,41 @@ resources:
     assert!(state.outputs.contains_key("bucketName"));
 }
 
+#[test]
+fn test_eval_with_schema_resolves_short_type_tokens() {
+    let source = r#"
+name: test
+runtime: yaml
+resources:
+  short:
+    type: aws:Bucket
+  unknown:
+    type: aws:Queue
+"#;
+    let mock = MockCallback::new();
+    let store = make_bucket_schema();
+
+    let (eval, has_errors) = eval_with_schema(source, mock, Some(&store), false);
+    assert!(!has_errors, "errors: {}", eval.diags_display());
+
+    let regs = eval.callback().registrations();
+    let type_of = |name: &str| {
+        regs.iter()
+            .find(|r| r.name == name)
+            .map(|r| r.type_token.clone())
+            .unwrap()
+    };
+    assert_eq!(type_of("short"), "aws:s3/bucket:Bucket");
+    assert_eq!(type_of("unknown"), "aws:index/queue:Queue");
+    assert_eq!(
+        eval.diag_warnings(),
+        vec![
+            "resource 'unknown': type 'aws:Queue' is not in the aws schema; \
+             guessed 'aws:index/queue:Queue'"
+        ]
+    );
+}
+
 #[test]
 fn test_eval_with_schema_adds_secret_outputs() {
     let source = r#"
@@ -2138,7 +2806,7 @@ resources:
     let mock = MockCallback::new();
     let store = make_bucket_schema();
 
-    let (eval, _) = eval_with_schema(source, mock, Some(store), false);
+    let (eval, _) = eval_with_schema(source, mock, Some(&store), false);
 
     let regs = eval.callback().registrations();
     assert_eq!(regs.len(), 1);
@@ -2165,7 +2833,7 @@ resources:
     let mock = MockCallback::new();
     let store = make_bucket_schema();
 
-    let (eval, _) = eval_with_schema(source, mock, Some(store), false);
+    let (eval, _) = eval_with_schema(source, mock, Some(&store), false);
 
     let regs = eval.callback().registrations();
     assert_eq!(regs.len(), 1);
@@ -2229,7 +2897,7 @@ resources:
     let mock = MockCallback::new();
     let store = make_bucket_schema();
 
-    let (eval, _) = eval_with_schema(source, mock, Some(store), false);
+    let (eval, _) = eval_with_schema(source, mock, Some(&store), false);
 
     let regs = eval.callback().registrations();
     assert_eq!(regs.len(), 1);
@@ -2301,7 +2969,7 @@ resources:
     let mock = MockCallback::new();
     let store = make_secret_input_schema();
 
-    let (eval, has_errors) = eval_with_schema(source, mock, Some(store), false);
+    let (eval, has_errors) = eval_with_schema(source, mock, Some(&store), false);
     assert!(!has_errors, "errors: {}", eval.diags_display());
 
     let regs = eval.callback().registrations();
@@ -2352,7 +3020,7 @@ resources:
     let mock = MockCallback::new();
     let store = make_secret_input_schema();
 
-    let (eval, has_errors) = eval_with_schema(source, mock, Some(store), false);
+    let (eval, has_errors) = eval_with_schema(source, mock, Some(&store), false);
     assert!(!has_errors, "errors: {}", eval.diags_display());
 
     let regs = eval.callback().registrations();
@@ -2543,7 +3211,7 @@ resources:
     let mut store = SchemaStore::new();
     store.insert(schema);
 
-    let (eval, has_errors) = eval_with_schema(source, mock, Some(store), false);
+    let (eval, has_errors) = eval_with_schema(source, mock, Some(&store), false);
     assert!(!has_errors, "errors: {}", eval.diags_display());
 
     let regs = eval.callback().registrations();
@@ -2610,6 +3278,7 @@ resources:
             secret: false,
             const_value: Some(serde_json::Value::String("ConstantKind".to_string())),
             required: false,
+            enum_values: Vec::new(),
         },
     );
     info.property_types.insert(
@@ -2619,6 +3288,7 @@ resources:
             secret: false,
             const_value: None,
             required: false,
+            enum_values: Vec::new(),
         },
     );
     let schema = pulumi_rs_yaml_core::schema::PackageSchema {
@@ -2632,7 +3302,7 @@ resources:
     let mut store = SchemaStore::new();
     store.insert(schema);
 
-    let (eval, has_errors) = eval_with_schema(source, mock, Some(store), false);
+    let (eval, has_errors) = eval_with_schema(source, mock, Some(&store), false);
     assert!(!has_errors, "errors: {}", eval.diags_display());
 
     let regs = eval.callback().registrations();
@@ -2682,6 +3352,7 @@ resources:
             secret: false,
             const_value: Some(serde_json::Value::String("ConstantKind".to_string())),
             required: false,
+            enum_values: Vec::new(),
         },
     );
     let schema = pulumi_rs_yaml_core::schema::PackageSchema {
@@ -2695,7 +3366,7 @@ resources:
     let mut store = SchemaStore::new();
     store.insert(schema);
 
-    let (eval, has_errors) = eval_with_schema(source, mock, Some(store), false);
+    let (eval, has_errors) = eval_with_schema(source, mock, Some(&store), false);
     assert!(!has_errors, "errors: {}", eval.diags_display());
 
     let regs = eval.callback().registrations();
@@ -3120,6 +3791,218 @@ resources:
     assert!(has_errors, "docker:Image should be blocked");
 }
 
+#[test]
+fn test_blocklist_docker_image_allowed_with_provider_v4() {
+    let source = r#"
+name: test
+runtime: yaml
+resources:
+  pinned:
+    type: docker:image:Image
+    properties:
+      imageName: pinned
+    options:
+      version: 4.5.0
+  resolved:
+    type: docker:Image
+    properties:
+      imageName: resolved
+"#;
+    let (template, parse_diags) = parse_template(source, None);
+    assert!(!parse_diags.has_errors(), "{}", parse_diags);
+    let mut eval = Evaluator::with_callback(
+        "test".to_string(),
+        "dev".to_string(),
+        "/tmp".to_string(),
+        false,
+        MockCallback::new(),
+    );
+    eval.package_versions
+        .insert("docker".to_string(), "4.1.0".to_string());
+    eval.evaluate_template(&template, &HashMap::new(), &[]);
+    assert!(!eval.has_errors(), "{}", eval.diags_display());
+    assert!(eval.has_resource("pinned"));
+    assert!(eval.has_resource("resolved"));
+}
+
+#[test]
+fn test_blocklist_host_overrides() {
+    let source = r#"
+name: test
+runtime: yaml
+resources:
+  config:
+    type: kubernetes:yaml:ConfigFile
+    properties:
+      file: manifest.yaml
+  key:
+    type: aws:iam:AccessKey
+    properties:
+      user: deployer
+"#;
+    let (template, parse_diags) = parse_template(source, None);
+    assert!(!parse_diags.has_errors(), "{}", parse_diags);
+    let mut eval = Evaluator::with_callback(
+        "test".to_string(),
+        "dev".to_string(),
+        "/tmp".to_string(),
+        false,
+        MockCallback::new(),
+    );
+    eval.continue_on_error = true;
+    eval.blocklist.allow("kubernetes:yaml:ConfigFile");
+    eval.blocklist.block("aws:iam:AccessKey");
+    eval.evaluate_template(&template, &HashMap::new(), &[]);
+    assert!(eval.has_resource("config"));
+    assert!(!eval.has_resource("key"));
+    assert!(eval
+        .diags_display()
+        .contains("aws:iam:AccessKey is blocked by this host's configuration"));
+}
+
+#[test]
+fn test_stable_random_reuses_previous_values() {
+    let source = r#"
+name: test
+runtime: yaml
+variables:
+  password:
+    fn::randomString: 8
+  token:
+    fn::randomString: 12
+  id:
+    fn::uuid: {}
+outputs:
+  suffix:
+    fn::randomString: 4
+"#;
+    let (template, parse_diags) = parse_template(source, None);
+    assert!(!parse_diags.has_errors(), "{}", parse_diags);
+    let mut eval = Evaluator::with_callback(
+        "test".to_string(),
+        "dev".to_string(),
+        "/tmp".to_string(),
+        false,
+        MockCallback::new(),
+    );
+    eval.stable_random = Some(HashMap::from([
+        ("password#0".to_string(), "abcdefgh".to_string()),
+        // The length changed, so a new token is generated
+        ("token#0".to_string(), "short".to_string()),
+    ]));
+    eval.evaluate_template(&template, &HashMap::new(), &[]);
+    assert!(!eval.has_errors(), "errors: {}", eval.diags_display());
+
+    assert_eq!(
+        eval.get_variable("password"),
+        Some(Value::String(Cow::Borrowed("abcdefgh")))
+    );
+    let token = eval.get_variable("token").unwrap();
+    assert_eq!(token.as_str().map(str::len), Some(12));
+
+    let values = eval.random_values();
+    let keys: Vec<&str> = values.keys().map(String::as_str).collect();
+    assert_eq!(
+        keys,
+        vec!["id#0", "outputs.suffix#0", "password#0", "token#0"]
+    );
+    assert_eq!(Some(values["token#0"].as_str()), token.as_str());
+    assert_eq!(
+        eval.get_output("suffix").as_ref().and_then(|v| v.as_str()),
+        Some(values["outputs.suffix#0"].as_str())
+    );
+
+    // Without the option nothing is recorded
+    let (eval, _) = eval_with_mock(source, MockCallback::new());
+    assert!(eval.random_values().is_empty());
+}
+
+/// Records progress events as strings.
+struct RecordingProgress(std::sync::Arc<std::sync::Mutex<Vec<String>>>);
+
+impl ProgressSink for RecordingProgress {
+    fn on_level_start(&mut self, level: usize, count: usize) {
+        self.0
+            .lock()
+            .unwrap()
+            .push(format!("level {} ({})", level, count));
+    }
+
+    fn on_resource_done(&mut self, name: &str) {
+        self.0.lock().unwrap().push(format!("done {}", name));
+    }
+
+    fn on_timing_report(&mut self, report: &TimingReport) {
+        let nodes: Vec<String> = report
+            .nodes
+            .iter()
+            .map(|n| format!("{} {}@{}", n.kind, n.name, n.level))
+            .collect();
+        self.0
+            .lock()
+            .unwrap()
+            .push(format!("report {}", nodes.join(", ")));
+    }
+}
+
+#[test]
+fn test_timing_report_and_summary() {
+    let source = r#"
+name: test
+runtime: yaml
+variables:
+  ami:
+    fn::invoke:
+      function: aws:ec2:getAmi
+      arguments:
+        owners: [amazon]
+resources:
+  server:
+    type: aws:ec2:Instance
+    properties:
+      ami: ${ami.id}
+"#;
+    let (template, parse_diags) = parse_template(source, None);
+    assert!(!parse_diags.has_errors(), "{}", parse_diags);
+    let mock = MockCallback::with_invoke_responses(vec![InvokeResponse {
+        return_values: HashMap::from([("id".to_string(), Value::String(Cow::Borrowed("ami-123")))]),
+        failures: Vec::new(),
+    }]);
+    let mut eval = Evaluator::with_callback(
+        "test".to_string(),
+        "dev".to_string(),
+        "/tmp".to_string(),
+        false,
+        mock,
+    );
+    let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    eval.progress = std::sync::Mutex::new(Box::new(RecordingProgress(events.clone())));
+    eval.timing_summary = Some(5);
+    eval.evaluate_template(&template, &HashMap::new(), &[]);
+    assert!(!eval.has_errors(), "errors: {}", eval.diags_display());
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![
+            "level 0 (2)",
+            "level 1 (1)",
+            "done server",
+            "report invoke ami@0, resource server@1",
+        ]
+    );
+    let report = eval.timing_report();
+    assert_eq!(report.levels.len(), 2);
+    assert!(report.nodes.iter().all(|n| n.rpc <= n.total));
+
+    let warnings = eval.diag_warnings();
+    let summary = warnings
+        .iter()
+        .find(|w| w.starts_with("evaluated 2 entries in 2 levels"))
+        .unwrap_or_else(|| panic!("no timing summary in {:?}", warnings));
+    assert!(summary.contains("\n  resource server (level 1): "));
+    assert!(summary.contains("\n  invoke ami (level 0): "));
+}
+
 #[test]
 fn test_blocklist_allowed_type_passes() {
     let source = r#"
@@ -3199,7 +4082,10 @@ variables:
 "#;
     let (eval, has_errors) = eval_with_mock(source, MockCallback::new());
     assert!(!has_errors);
-    assert_eq!(eval.get_variable("result").unwrap(), Value::Number(42.0));
+    assert_eq!(
+        eval.get_variable("result").unwrap(),
+        Value::Number(42.0.into())
+    );
 }
 
 #[test]
@@ -3213,7 +4099,10 @@ variables:
 "#;
     let (eval, has_errors) = eval_with_mock(source, MockCallback::new());
     assert!(!has_errors);
-    assert_eq!(eval.get_variable("result").unwrap(), Value::Number(42.0));
+    assert_eq!(
+        eval.get_variable("result").unwrap(),
+        Value::Number(42.0.into())
+    );
 }
 
 #[test]
@@ -3227,7 +4116,10 @@ variables:
 "#;
     let (eval, has_errors) = eval_with_mock(source, MockCallback::new());
     assert!(!has_errors);
-    assert_eq!(eval.get_variable("result").unwrap(), Value::Number(3.0));
+    assert_eq!(
+        eval.get_variable("result").unwrap(),
+        Value::Number(3.0.into())
+    );
 }
 
 #[test]
@@ -3241,7 +4133,10 @@ variables:
 "#;
     let (eval, has_errors) = eval_with_mock(source, MockCallback::new());
     assert!(!has_errors);
-    assert_eq!(eval.get_variable("result").unwrap(), Value::Number(4.0));
+    assert_eq!(
+        eval.get_variable("result").unwrap(),
+        Value::Number(4.0.into())
+    );
 }
 
 #[test]
@@ -3258,7 +4153,10 @@ variables:
 "#;
     let (eval, has_errors) = eval_with_mock(source, MockCallback::new());
     assert!(!has_errors);
-    assert_eq!(eval.get_variable("result").unwrap(), Value::Number(5.0));
+    assert_eq!(
+        eval.get_variable("result").unwrap(),
+        Value::Number(5.0.into())
+    );
 }
 
 #[test]
@@ -3275,7 +4173,10 @@ variables:
 "#;
     let (eval, has_errors) = eval_with_mock(source, MockCallback::new());
     assert!(!has_errors);
-    assert_eq!(eval.get_variable("result").unwrap(), Value::Number(2.0));
+    assert_eq!(
+        eval.get_variable("result").unwrap(),
+        Value::Number(2.0.into())
+    );
 }
 
 #[test]
@@ -3344,7 +4245,7 @@ resources:
     );
 
     let template = merged.as_template_decl();
-    let template: &'static _ = Box::leak(Box::new(template));
+    let template = &template;
 
     let eval = Evaluator::with_callback(
         "test".to_string(),
@@ -3813,6 +4714,7 @@ fn test_protobuf_resource_sig() {
 // ============================================================
 
 #[test]
+#[cfg(feature = "schema")]
 fn test_generate_component_schema() {
     use pulumi_rs_yaml_core::ast::template::*;
     use pulumi_rs_yaml_core::schema::generate_component_schema;
@@ -3851,6 +4753,10 @@ fn test_generate_component_schema() {
             },
         }],
         starlark_functions: Vec::new(),
+        imports: Vec::new(),
+        extends: None,
+        runtime: None,
+        plugins: Vec::new(),
     };
 
     let schema = generate_component_schema(&template);
@@ -3886,7 +4792,7 @@ resources:
     let mock = MockCallback::new();
     let (template, parse_diags) = parse_template(source, None);
     assert!(!parse_diags.has_errors());
-    let template: &'static _ = Box::leak(Box::new(template));
+    let template = &template;
 
     let mut eval = Evaluator::with_callback(
         "test".to_string(),
@@ -3939,32 +4845,34 @@ resources:
 }
 
 #[test]
-fn test_read_resource_with_state() {
+fn test_read_resource_propagates_secrets_and_dependencies() {
     let source = r#"
 runtime: yaml
 resources:
-  myBucket:
-    type: aws:s3:Bucket
-    properties:
-      bucketName: existing-bucket
-      region: us-west-2
+  vpc:
+    type: aws:ec2:Vpc
+  db:
+    type: aws:rds:Instance
     get:
-      id: bucket-456
-      state:
-        tags:
-          env: prod
+      id: db-1
+    options:
+      dependsOn:
+        - ${vpc}
+      additionalSecretOutputs:
+        - password
 "#;
     let read_resp = RegisterResponse {
-        urn: "urn:pulumi:test::test::aws:s3/bucket:Bucket::myBucket".to_string(),
-        id: "bucket-456".to_string(),
+        urn: "urn:pulumi:test::test::aws:rds/instance:Instance::db".to_string(),
+        id: "db-1".to_string(),
         outputs: {
             let mut m = HashMap::new();
             m.insert(
-                "tags".to_string(),
-                Value::Object(vec![(
-                    Cow::Owned("env".to_string()),
-                    Value::String(Cow::Owned("prod".to_string())),
-                )]),
+                "password".to_string(),
+                Value::String(Cow::Owned("hunter2".to_string())),
+            );
+            m.insert(
+                "endpoint".to_string(),
+                Value::String(Cow::Owned("db.example.com".to_string())),
             );
             m
         },
@@ -3976,31 +4884,108 @@ resources:
 
     let reads = eval.callback().reads();
     assert_eq!(reads.len(), 1);
-    assert_eq!(reads[0].id, "bucket-456");
-    // The state/properties are passed as inputs to read_resource
-    assert!(!reads[0].inputs.is_empty());
+    assert_eq!(reads[0].depends_on.len(), 1);
+    assert!(reads[0].depends_on[0].contains("vpc"));
+    assert_eq!(
+        reads[0].additional_secret_outputs,
+        vec!["password".to_string()]
+    );
 
-    let regs = eval.callback().registrations();
-    assert_eq!(regs.len(), 0);
+    let db = eval.get_resource("db").unwrap();
+    assert!(db.outputs["password"].is_secret());
+    assert!(!db.outputs["endpoint"].is_secret());
 }
 
 #[test]
-fn test_read_resource_no_state() {
+fn test_additional_secret_outputs_are_secret_stack_outputs() {
     let source = r#"
 runtime: yaml
 resources:
-  myBucket:
-    type: aws:s3:Bucket
-    get:
-      id: bucket-789
+  db:
+    type: aws:rds:Instance
+    properties:
+      password: hunter2
+      endpoint: db.example.com
+    options:
+      additionalSecretOutputs:
+        - password
+outputs:
+  password: ${db.password}
+  endpoint: ${db.endpoint}
 "#;
-    let mock = MockCallback::new();
-    let (eval, has_errors) = eval_with_mock(source, mock);
+    // The mock echoes inputs back in plaintext, like an engine that does not
+    // apply additionalSecretOutputs to the response.
+    let (eval, has_errors) = eval_with_mock(source, MockCallback::new());
     assert!(!has_errors, "errors: {}", eval.diags_display());
 
-    let reads = eval.callback().reads();
-    assert_eq!(reads.len(), 1);
-    assert_eq!(reads[0].id, "bucket-789");
+    let outputs = eval.take_outputs();
+    assert!(outputs["password"].is_secret());
+    assert!(!outputs["endpoint"].is_secret());
+}
+
+#[test]
+fn test_read_resource_with_state() {
+    let source = r#"
+runtime: yaml
+resources:
+  myBucket:
+    type: aws:s3:Bucket
+    properties:
+      bucketName: existing-bucket
+      region: us-west-2
+    get:
+      id: bucket-456
+      state:
+        tags:
+          env: prod
+"#;
+    let read_resp = RegisterResponse {
+        urn: "urn:pulumi:test::test::aws:s3/bucket:Bucket::myBucket".to_string(),
+        id: "bucket-456".to_string(),
+        outputs: {
+            let mut m = HashMap::new();
+            m.insert(
+                "tags".to_string(),
+                Value::Object(vec![(
+                    Cow::Owned("env".to_string()),
+                    Value::String(Cow::Owned("prod".to_string())),
+                )]),
+            );
+            m
+        },
+        stables: Vec::new(),
+    };
+    let mock = MockCallback::with_read_responses(vec![read_resp]);
+    let (eval, has_errors) = eval_with_mock(source, mock);
+    assert!(!has_errors, "errors: {}", eval.diags_display());
+
+    let reads = eval.callback().reads();
+    assert_eq!(reads.len(), 1);
+    assert_eq!(reads[0].id, "bucket-456");
+    // The state/properties are passed as inputs to read_resource
+    assert!(!reads[0].inputs.is_empty());
+
+    let regs = eval.callback().registrations();
+    assert_eq!(regs.len(), 0);
+}
+
+#[test]
+fn test_read_resource_no_state() {
+    let source = r#"
+runtime: yaml
+resources:
+  myBucket:
+    type: aws:s3:Bucket
+    get:
+      id: bucket-789
+"#;
+    let mock = MockCallback::new();
+    let (eval, has_errors) = eval_with_mock(source, mock);
+    assert!(!has_errors, "errors: {}", eval.diags_display());
+
+    let reads = eval.callback().reads();
+    assert_eq!(reads.len(), 1);
+    assert_eq!(reads[0].id, "bucket-789");
     assert!(
         reads[0].inputs.is_empty(),
         "no properties means empty inputs"
@@ -4263,10 +5248,12 @@ resources:
           index.js:
             fn::stringAsset: "exports.handler = () => {}"
           config.json:
-            fn::fileAsset: /tmp/config.json
+            fn::fileAsset: ./config.json
 "#;
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("config.json"), "{}").unwrap();
     let mock = MockCallback::new();
-    let (eval, has_errors) = eval_with_mock(source, mock);
+    let (eval, has_errors) = eval_with_mock_in(source, mock, dir.path());
     assert!(!has_errors, "errors: {}", eval.diags_display());
 
     let regs = eval.callback().registrations();
@@ -5094,7 +6081,7 @@ outputs:
     let mock = MockCallback::new();
     let store = make_bucket_schema();
 
-    let (eval, has_errors) = eval_with_schema(source, mock, Some(store), true);
+    let (eval, has_errors) = eval_with_schema(source, mock, Some(&store), true);
     assert!(!has_errors, "errors: {}", eval.diags_display());
 
     // In preview, output-only properties should be Unknown
@@ -5127,7 +6114,7 @@ resources:
     let store = make_bucket_schema();
 
     // dry_run = false: should NOT inject Unknown for output-only properties
-    let (eval, has_errors) = eval_with_schema(source, mock, Some(store), false);
+    let (eval, has_errors) = eval_with_schema(source, mock, Some(&store), false);
     assert!(!has_errors, "errors: {}", eval.diags_display());
 
     let state = eval.get_resource("myBucket").unwrap();
@@ -5169,7 +6156,7 @@ outputs:
     let store = make_bucket_schema();
 
     // In preview, bucket.arn is Unknown → invoke arg is Unknown → result propagates
-    let (eval, has_errors) = eval_with_schema(source, mock, Some(store), true);
+    let (eval, has_errors) = eval_with_schema(source, mock, Some(&store), true);
     assert!(!has_errors, "errors: {}", eval.diags_display());
 
     let policy = eval.get_output("policy").unwrap();
@@ -5281,6 +6268,34 @@ fn test_dollar_dollar_is_literal() {
     // $$ in YAML values is kept as-is (not an escape mechanism)
     let source = r#"
 runtime: yaml
+outputs:
+  price: "$$100"
+  mixed: "$$100 for ${pulumi.stack}"
+"#;
+    let mock = MockCallback::new();
+    let (eval, has_errors) = eval_with_mock(source, mock);
+    assert!(!has_errors, "errors: {}", eval.diags_display());
+
+    assert_eq!(
+        eval.get_output("price")
+            .and_then(|v| v.as_str().map(|s| s.to_string()))
+            .as_deref(),
+        Some("$$100"),
+        "$$ is kept literally"
+    );
+    assert_eq!(
+        eval.get_output("mixed")
+            .and_then(|v| v.as_str().map(|s| s.to_string()))
+            .as_deref(),
+        Some("$$100 for dev")
+    );
+}
+
+#[test]
+fn test_dollar_dollar_escapes_interpolation() {
+    // $${...} escapes the interpolation and yields a literal ${...}
+    let source = r#"
+runtime: yaml
 outputs:
   literal: "$${something}"
 "#;
@@ -5292,8 +6307,742 @@ outputs:
         eval.get_output("literal")
             .and_then(|v| v.as_str().map(|s| s.to_string()))
             .as_deref(),
-        Some("$${something}"),
-        "$$ is kept literally"
+        Some("${something}"),
+        "$${{...}} is an escaped interpolation"
+    );
+}
+
+#[test]
+fn test_property_access_negative_slice_and_projection() {
+    let source = r#"
+runtime: yaml
+variables:
+  zones:
+    - us-east-1a
+    - us-east-1b
+    - us-east-1c
+  buckets:
+    logs:
+      id: logs-bucket
+    assets:
+      id: assets-bucket
+outputs:
+  lastZone: ${zones[-1]}
+  firstTwo: ${zones[:2]}
+  bucketIds: ${buckets.*.id}
+"#;
+    let mock = MockCallback::new();
+    let (eval, has_errors) = eval_with_mock(source, mock);
+    assert!(!has_errors, "errors: {}", eval.diags_display());
+
+    assert_eq!(
+        eval.get_output("lastZone")
+            .and_then(|v| v.as_str().map(|s| s.to_string()))
+            .as_deref(),
+        Some("us-east-1c")
+    );
+    assert_eq!(
+        eval.get_output("firstTwo"),
+        Some(Value::List(vec![
+            Value::String("us-east-1a".into()),
+            Value::String("us-east-1b".into()),
+        ]))
+    );
+    assert_eq!(
+        eval.get_output("bucketIds"),
+        Some(Value::List(vec![
+            Value::String("logs-bucket".into()),
+            Value::String("assets-bucket".into()),
+        ]))
+    );
+}
+
+#[test]
+fn test_select_object_and_lookup_default() {
+    let source = r#"
+runtime: yaml
+variables:
+  amis:
+    us-east-1: ami-east
+    us-west-2: ami-west
+  selected:
+    fn::select: [us-east-1, "${amis}"]
+  fallback:
+    fn::lookup: ["${amis}", eu-west-1, ami-default]
+  found:
+    fn::lookup: ["${amis}", us-west-2]
+outputs:
+  selected: ${selected}
+  fallback: ${fallback}
+  found: ${found}
+"#;
+    let mock = MockCallback::new();
+    let (eval, has_errors) = eval_with_mock(source, mock);
+    assert!(!has_errors, "errors: {}", eval.diags_display());
+
+    for (key, expected) in [
+        ("selected", "ami-east"),
+        ("fallback", "ami-default"),
+        ("found", "ami-west"),
+    ] {
+        assert_eq!(
+            eval.get_output(key)
+                .and_then(|v| v.as_str().map(|s| s.to_string()))
+                .as_deref(),
+            Some(expected),
+            "output {}",
+            key
+        );
+    }
+}
+
+#[test]
+fn test_lookup_missing_key_without_default_errors() {
+    let source = r#"
+runtime: yaml
+variables:
+  amis:
+    us-east-1: ami-east
+outputs:
+  ami:
+    fn::lookup: ["${amis}", eu-west-1]
+"#;
+    let mock = MockCallback::new();
+    let (eval, has_errors) = eval_with_mock(source, mock);
+    assert!(has_errors);
+    assert!(eval.diags_display().contains("not found in fn::lookup map"));
+}
+
+#[test]
+fn test_host_provided_builtin_extension() {
+    let source = r#"
+runtime: yaml
+variables:
+  name: My Service
+  slug:
+    fn::acme:slugify: ${name}
+  secretSlug:
+    fn::acme:slugify:
+      fn::secret: Top Secret
+outputs:
+  slug: ${slug}
+  secretSlug: ${secretSlug}
+"#;
+    let (template, parse_diags) = parse_template(source, None);
+    assert!(!parse_diags.has_errors(), "{}", parse_diags);
+    let template = &template;
+
+    let mut eval = Evaluator::with_callback(
+        "test".to_string(),
+        "dev".to_string(),
+        "/tmp".to_string(),
+        false,
+        MockCallback::new(),
+    );
+    eval.register_builtin("acme:slugify", |v| match v {
+        Value::String(s) => Ok(Value::String(Cow::Owned(
+            s.to_lowercase().replace(' ', "-"),
+        ))),
+        other => Err(format!("expected a string, got {}", other.type_name())),
+    })
+    .unwrap();
+    eval.evaluate_template(template, &HashMap::new(), &[]);
+    assert!(!eval.has_errors(), "errors: {}", eval.diags_display());
+
+    assert_eq!(
+        eval.get_output("slug")
+            .and_then(|v| v.as_str().map(|s| s.to_string()))
+            .as_deref(),
+        Some("my-service")
+    );
+    assert_eq!(
+        eval.get_output("secretSlug"),
+        Some(Value::Secret(Box::new(Value::String("top-secret".into()))))
+    );
+    // Extension calls never reach the engine
+    assert!(eval.callback().invocations().is_empty());
+}
+
+#[test]
+fn test_unknown_builtin_in_registered_namespace_errors() {
+    let source = r#"
+runtime: yaml
+outputs:
+  out:
+    fn::acme:missing: x
+"#;
+    let (template, _) = parse_template(source, None);
+    let template = &template;
+    let mut eval = Evaluator::with_callback(
+        "test".to_string(),
+        "dev".to_string(),
+        "/tmp".to_string(),
+        false,
+        MockCallback::new(),
+    );
+    eval.register_builtin("acme:slugify", |v| Ok(v.clone()))
+        .unwrap();
+    eval.evaluate_template(template, &HashMap::new(), &[]);
+    assert!(eval.has_errors());
+    assert!(eval
+        .diags_display()
+        .contains("unknown builtin fn::acme:missing"));
+}
+
+#[test]
+fn test_open_config_is_resolved_by_host() {
+    let source = r#"
+name: test
+runtime: yaml
+config:
+  roleArn:
+    default: arn:aws:iam::123456789012:role/deploy
+  aws:
+    fn::open::aws-login:
+      oidc:
+        roleArn: ${roleArn}
+  region:
+    type: string
+    default:
+      fn::open::aws-parameters:
+        name: /deploy/region
+outputs:
+  accessKeyId: ${aws.creds.accessKeyId}
+  region: ${region}
+"#;
+    let (template, parse_diags) = parse_template(source, None);
+    assert!(!parse_diags.has_errors(), "{}", parse_diags);
+    let template = &template;
+
+    let opened = Arc::new(Mutex::new(Vec::new()));
+    let mut eval = Evaluator::with_callback(
+        "test".to_string(),
+        "dev".to_string(),
+        "/tmp".to_string(),
+        false,
+        MockCallback::new(),
+    );
+    let calls = Arc::clone(&opened);
+    eval.set_open_resolver(move |provider: &str, inputs: &Value<'static>| {
+        calls
+            .lock()
+            .unwrap()
+            .push((provider.to_string(), inputs.to_json()));
+        match provider {
+            "aws-login" => Ok(Value::Object(vec![(
+                Cow::Borrowed("creds"),
+                Value::Object(vec![(
+                    Cow::Borrowed("accessKeyId"),
+                    Value::Secret(Box::new(Value::String("AKIA".into()))),
+                )]),
+            )])),
+            other => Err(format!("unsupported provider {}", other)),
+        }
+    });
+
+    // The stack's config takes precedence, so aws-parameters is never opened.
+    let config = HashMap::from([("test:region".to_string(), "us-west-2".to_string())]);
+    eval.evaluate_template(template, &config, &[]);
+    assert!(!eval.has_errors(), "errors: {}", eval.diags_display());
+
+    assert_eq!(
+        eval.get_output("accessKeyId"),
+        Some(Value::Secret(Box::new(Value::String("AKIA".into()))))
+    );
+    assert_eq!(
+        opened.lock().unwrap().as_slice(),
+        &[(
+            "aws-login".to_string(),
+            serde_json::json!({"oidc": {"roleArn": "arn:aws:iam::123456789012:role/deploy"}})
+        )]
+    );
+}
+
+#[test]
+fn test_open_without_resolver_errors() {
+    let source = r#"
+name: test
+runtime: yaml
+config:
+  aws:
+    fn::open::aws-login:
+      oidc: {}
+"#;
+    let (template, _) = parse_template(source, None);
+    let eval = Evaluator::new("test".into(), "dev".into(), "/tmp".into(), false);
+    eval.evaluate_template(&template, &HashMap::new(), &[]);
+    assert!(eval
+        .diags_display()
+        .contains("fn::open::aws-login cannot be opened"));
+}
+
+#[test]
+fn test_output_hooks_transform_outputs_before_references() {
+    let source = r#"
+runtime: yaml
+resources:
+  bucket:
+    type: aws:s3:Bucket
+    properties:
+      BucketName: logs
+  policy:
+    type: aws:s3:BucketPolicy
+    properties:
+      bucket: ${bucket.bucketName}
+      arn: ${bucket.arn}
+outputs:
+  arn: ${bucket.arn}
+"#;
+    let (template, _) = parse_template(source, None);
+    let template = &template;
+    let mut eval = Evaluator::with_callback(
+        "test".to_string(),
+        "dev".to_string(),
+        "/tmp".to_string(),
+        false,
+        MockCallback::new(),
+    );
+    // Normalize property names to camelCase, then derive a computed field.
+    eval.add_output_hook(
+        |_: &RegisteredResource<'_>, outputs: &mut HashMap<String, Value<'static>>| {
+            *outputs = std::mem::take(outputs)
+                .into_iter()
+                .map(|(k, v)| {
+                    let mut chars = k.chars();
+                    let first = chars.next().map(|c| c.to_ascii_lowercase());
+                    (first.into_iter().chain(chars).collect(), v)
+                })
+                .collect();
+            Ok(())
+        },
+    );
+    eval.add_output_hook(
+        |resource: &RegisteredResource<'_>, outputs: &mut HashMap<String, Value<'static>>| {
+            if resource.type_token.starts_with("aws:s3") {
+                let name = outputs
+                    .get("bucketName")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or(resource.logical_name)
+                    .to_string();
+                outputs.insert(
+                    "arn".to_string(),
+                    Value::String(Cow::Owned(format!("arn:aws:s3:::{}", name))),
+                );
+            }
+            Ok(())
+        },
+    );
+    eval.evaluate_template(template, &HashMap::new(), &[]);
+    assert!(!eval.has_errors(), "errors: {}", eval.diags_display());
+
+    assert_eq!(
+        eval.get_output("arn"),
+        Some(Value::String("arn:aws:s3:::logs".into()))
+    );
+    let regs = eval.callback().registrations();
+    let policy = regs.iter().find(|r| r.name == "policy").unwrap();
+    assert_eq!(policy.inputs["bucket"], Value::String("logs".into()));
+    assert_eq!(
+        policy.inputs["arn"],
+        Value::String("arn:aws:s3:::logs".into())
+    );
+}
+
+#[test]
+fn test_output_hook_error_fails_resource() {
+    let source = r#"
+runtime: yaml
+resources:
+  bucket:
+    type: aws:s3:Bucket
+outputs:
+  id: ${bucket.id}
+"#;
+    let (template, _) = parse_template(source, None);
+    let template = &template;
+    let mut eval = Evaluator::with_callback(
+        "test".to_string(),
+        "dev".to_string(),
+        "/tmp".to_string(),
+        false,
+        MockCallback::new(),
+    );
+    eval.add_output_hook(
+        |_: &RegisteredResource<'_>, _: &mut HashMap<String, Value<'static>>| {
+            Err("unsupported output model".to_string())
+        },
+    );
+    eval.evaluate_template(template, &HashMap::new(), &[]);
+    assert!(eval.has_errors());
+    assert!(eval
+        .diags_display()
+        .contains("output hook failed for resource 'bucket': unsupported output model"));
+    assert!(eval.get_resource("bucket").is_none());
+}
+
+#[test]
+fn test_duplicate_registration_names_both_call_sites() {
+    let source = r#"
+runtime: yaml
+resources:
+  logs:
+    type: aws:s3:Bucket
+    properties:
+      acl: private
+  archive:
+    type: aws:s3:Bucket
+    name: logs
+  other:
+    type: aws:s3:Bucket
+    name: logs
+    options:
+      parent: ${logs}
+"#;
+    let evaluator = || {
+        let mut eval = Evaluator::with_callback(
+            "test".to_string(),
+            "dev".to_string(),
+            "/tmp".to_string(),
+            false,
+            MockCallback::new(),
+        );
+        eval.check_duplicate_registrations = true;
+        eval
+    };
+    let mut arena = SourceArena::new();
+    let file = arena.add_file("storage.yaml".to_string(), source.to_string());
+    let (template, _) = parse_template(source, Some(Span::new(file, 0, source.len() as u32)));
+    let template = &template;
+    let mut eval = evaluator();
+    let source_map: HashMap<String, String> = [
+        ("logs".to_string(), "Pulumi.yaml".to_string()),
+        ("archive".to_string(), "storage.yaml".to_string()),
+        ("other".to_string(), "storage.yaml".to_string()),
+    ]
+    .into_iter()
+    .collect();
+    eval.source_map = Some(std::sync::Arc::new(source_map));
+    eval.sources = Some(Arc::new(arena));
+    eval.evaluate_template(template, &HashMap::new(), &[]);
+    assert!(eval.has_errors());
+    assert_eq!(
+        eval.diag_error_messages(),
+        vec![
+            "storage.yaml:4:3: duplicate registration of aws:s3/bucket:Bucket 'logs': resource \
+             'archive' in storage.yaml and resource 'logs' in Pulumi.yaml both register it\n\
+             resource 'archive' in storage.yaml is declared at storage.yaml:8:3; resources \
+             with the same parent need distinct `name`s"
+        ]
+    );
+    assert!(eval.get_resource("logs").is_none());
+    assert_eq!(eval.callback().registrations().len(), 1);
+
+    // A child with the same name under a different parent is distinct.
+    let eval = evaluator();
+    let (template, _) = parse_template(&source.replace("    name: logs\n  other", "  other"), None);
+    let template = &template;
+    eval.evaluate_template(template, &HashMap::new(), &[]);
+    assert!(!eval.has_errors(), "{}", eval.diags_display());
+
+    // Children of two parents of the same type share a URN.
+    let eval = evaluator();
+    let (template, _) = parse_template(
+        r#"
+runtime: yaml
+resources:
+  a:
+    type: test:index:Group
+  b:
+    type: test:index:Group
+  first:
+    type: aws:s3:Bucket
+    name: shared
+    options:
+      parent: ${a}
+  second:
+    type: aws:s3:Bucket
+    name: shared
+    options:
+      parent: ${b}
+"#,
+        None,
+    );
+    let template = &template;
+    eval.evaluate_template(template, &HashMap::new(), &[]);
+    assert!(
+        eval.diags_display()
+            .contains("duplicate registration of aws:s3/bucket:Bucket 'shared'"),
+        "{}",
+        eval.diags_display()
+    );
+
+    // The check is off by default, leaving duplicates to the engine.
+    let eval = Evaluator::with_callback(
+        "test".to_string(),
+        "dev".to_string(),
+        "/tmp".to_string(),
+        false,
+        MockCallback::new(),
+    );
+    let (template, _) = parse_template(source, None);
+    let template = &template;
+    eval.evaluate_template(template, &HashMap::new(), &[]);
+    assert!(!eval.has_errors(), "{}", eval.diags_display());
+}
+
+#[test]
+fn test_continue_on_error_reports_independent_failures() {
+    let source = r#"
+runtime: yaml
+variables:
+  decoded:
+    fn::fromBase64: "not base64!"
+  picked:
+    fn::select: [5, [a, b]]
+  label: ${decoded}-logs
+resources:
+  bucket:
+    type: aws:s3:Bucket
+  logs:
+    type: aws:s3:Bucket
+    properties:
+      tags:
+        label: ${label}
+outputs:
+  bucketId: ${bucket.id}
+  logsId: ${logs.id}
+"#;
+    let (template, _) = parse_template(source, None);
+    let template = &template;
+    let evaluate = |continue_on_error: bool| {
+        let mut eval = Evaluator::with_callback(
+            "test".to_string(),
+            "dev".to_string(),
+            "/tmp".to_string(),
+            false,
+            MockCallback::new(),
+        );
+        eval.continue_on_error = continue_on_error;
+        eval.evaluate_template(template, &HashMap::new(), &[]);
+        eval
+    };
+
+    let eval = evaluate(false);
+    assert_eq!(eval.diag_errors().len(), 1, "{}", eval.diags_display());
+    assert!(eval.take_outputs().is_empty());
+
+    let eval = evaluate(true);
+    let errors = eval.diag_errors();
+    assert_eq!(errors.len(), 2, "{}", eval.diags_display());
+    assert!(errors.iter().any(|e| e.contains("fromBase64")));
+    assert!(errors.iter().any(|e| e.contains("out-of-bounds")));
+    assert!(eval.get_resource("bucket").is_some());
+    assert!(eval.get_resource("logs").is_none());
+    let names: Vec<_> = eval
+        .callback()
+        .registrations()
+        .into_iter()
+        .map(|r| r.name)
+        .collect();
+    assert_eq!(names, vec!["bucket".to_string()]);
+    let outputs = eval.take_outputs();
+    assert!(outputs.contains_key("bucketId"));
+    assert!(!outputs.contains_key("logsId"));
+}
+
+#[test]
+fn test_targets_evaluate_only_dependency_closure() {
+    let source = r#"
+runtime: yaml
+resources:
+  vpc:
+    type: aws:ec2:Vpc
+  subnet:
+    type: aws:ec2:Subnet
+    properties:
+      vpcId: ${vpc.id}
+  bucket:
+    type: aws:s3:Bucket
+    name: app-data
+  unrelated:
+    type: aws:s3:Bucket
+  queues:
+    type: aws:sqs:Queue
+    range: [a, b]
+  topics:
+    type: aws:sns:Topic
+    range: [a, b]
+outputs:
+  subnetId: ${subnet.id}
+  unrelatedId: ${unrelated.id}
+"#;
+    let (template, _) = parse_template(source, None);
+    let template = &template;
+    let mut eval = Evaluator::with_callback(
+        "test".to_string(),
+        "dev".to_string(),
+        "/tmp".to_string(),
+        false,
+        MockCallback::new(),
+    );
+    eval.targets = Some(vec![
+        "subnet".to_string(),
+        "urn:pulumi:dev::test::aws:s3/bucket:Bucket::app-data".to_string(),
+        "urn:pulumi:dev::test::pulumi:pulumi:Stack$aws:sqs/queue:Queue::queues-1".to_string(),
+        // Another stack's resource of the same name and type.
+        "urn:pulumi:prod::test::aws:sns/topic:Topic::topics-0".to_string(),
+        "missing".to_string(),
+    ]);
+    eval.evaluate_template(template, &HashMap::new(), &[]);
+    assert!(!eval.has_errors(), "{}", eval.diags_display());
+    assert!(eval
+        .diag_warnings()
+        .iter()
+        .any(|w| w == "target 'missing' does not match any entry"));
+
+    let mut names: Vec<_> = eval
+        .callback()
+        .registrations()
+        .into_iter()
+        .map(|r| r.name)
+        .collect();
+    names.sort();
+    assert_eq!(
+        names,
+        vec!["app-data", "queues-0", "queues-1", "subnet", "vpc"]
+    );
+    assert!(eval.diag_warnings().iter().any(|w| {
+        w
+        == "target 'urn:pulumi:prod::test::aws:sns/topic:Topic::topics-0' does not match any entry"
+    }));
+
+    // Outputs of untargeted entries are left out instead of overwritten
+    // with unknowns.
+    let outputs = eval.take_outputs();
+    assert!(!matches!(outputs["subnetId"], Value::Unknown));
+    assert!(!outputs.contains_key("unrelatedId"));
+}
+
+#[test]
+fn test_refresh_mode_reads_without_registering() {
+    let source = r#"
+runtime: yaml
+resources:
+  existing:
+    type: aws:s3:Bucket
+    get:
+      id: bucket-123
+  logs:
+    type: aws:s3:Bucket
+    properties:
+      source: ${existing.id}
+outputs:
+  existingId: ${existing.id}
+  logsArn: ${logs.arn}
+"#;
+    let (template, _) = parse_template(source, None);
+    let template = &template;
+    let mut eval = Evaluator::with_callback(
+        "test".to_string(),
+        "dev".to_string(),
+        "/tmp".to_string(),
+        false,
+        MockCallback::new(),
+    );
+    eval.mode = EvalMode::Refresh;
+    eval.evaluate_template(template, &HashMap::new(), &[]);
+    assert!(!eval.has_errors(), "{}", eval.diags_display());
+    assert_eq!(eval.callback().reads().len(), 1);
+    assert!(eval.callback().registrations().is_empty());
+    let outputs = eval.take_outputs();
+    assert_ne!(outputs["existingId"], Value::Unknown);
+    assert_eq!(outputs["logsArn"], Value::Unknown);
+}
+
+#[test]
+fn test_destroy_plan_lists_resources_in_deletion_order() {
+    let source = r#"
+runtime: yaml
+variables:
+  vpcId: ${vpc.id}
+resources:
+  vpc:
+    type: aws:ec2:Vpc
+  subnet:
+    type: aws:ec2:Subnet
+    name: private-subnet
+    properties:
+      vpcId: ${vpcId}
+      cidrBlock:
+        fn::invoke:
+          function: aws:ec2:getCidr
+          return: cidr
+"#;
+    let (template, _) = parse_template(source, None);
+    let template = &template;
+    let mut eval = Evaluator::with_callback(
+        "test".to_string(),
+        "dev".to_string(),
+        "/tmp".to_string(),
+        false,
+        MockCallback::new(),
+    );
+    eval.mode = EvalMode::DestroyPlan;
+    eval.evaluate_template(template, &HashMap::new(), &[]);
+    assert!(!eval.has_errors(), "{}", eval.diags_display());
+    assert!(eval.callback().invocations().is_empty());
+    assert!(eval.callback().registrations().is_empty());
+    assert_eq!(
+        eval.destroy_plan(),
+        vec![
+            PlannedResource {
+                logical_name: "subnet".to_string(),
+                type_token: "aws:ec2/subnet:Subnet".to_string(),
+                name: "private-subnet".to_string(),
+                dependencies: vec!["vpc".to_string()],
+            },
+            PlannedResource {
+                logical_name: "vpc".to_string(),
+                type_token: "aws:ec2/vpc:Vpc".to_string(),
+                name: "vpc".to_string(),
+                dependencies: vec![],
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_nested_property_access_key() {
+    let source = r#"
+runtime: yaml
+variables:
+  region: us-west-2
+  amis:
+    us-east-1: ami-east
+    us-west-2: ami-west
+  sizes:
+    - small
+    - large
+  which: 1
+outputs:
+  ami: ${amis[region]}
+  label: "size-${sizes[which]}"
+"#;
+    let mock = MockCallback::new();
+    let (eval, has_errors) = eval_with_mock(source, mock);
+    assert!(!has_errors, "errors: {}", eval.diags_display());
+
+    assert_eq!(
+        eval.get_output("ami")
+            .and_then(|v| v.as_str().map(|s| s.to_string()))
+            .as_deref(),
+        Some("ami-west")
+    );
+    assert_eq!(
+        eval.get_output("label")
+            .and_then(|v| v.as_str().map(|s| s.to_string()))
+            .as_deref(),
+        Some("size-large")
     );
 }
 
@@ -5373,6 +7122,7 @@ outputs:
 }
 
 #[test]
+#[cfg(feature = "type-check")]
 fn test_schema_property_not_exist_diagnostic() {
     use pulumi_rs_yaml_core::type_check::type_check;
 
@@ -5451,7 +7201,7 @@ fn eval_with_mock_parallel(
     if parse_diags.has_errors() {
         panic!("parse errors: {}", parse_diags);
     }
-    let template: &'static _ = Box::leak(Box::new(template));
+    let template = &template;
     let mut eval = Evaluator::with_callback(
         "test".to_string(),
         "dev".to_string(),
@@ -1,5 +1,7 @@
 //! Integration tests for the Jinja2 template pre-processing module.
 
+#![cfg(feature = "jinja")]
+
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fs;
@@ -23,6 +25,7 @@ fn make_context(config: &HashMap<String, String>) -> JinjaContext<'_> {
         config,
         project_dir: "/home/user/project",
         undefined: UndefinedMode::Strict,
+        args: &[],
         extra: &EMPTY_EXTRA,
     }
 }
@@ -333,7 +336,7 @@ outputs:
     assert!(!diags.has_errors(), "parse errors: {}", diags);
 
     // Evaluate with mock
-    let template: &'static _ = Box::leak(Box::new(template));
+    let template = &template;
     let eval = Evaluator::with_callback(
         "test-project".to_string(),
         "dev".to_string(),
@@ -377,9 +380,14 @@ stack: {{ pulumi_stack }}
 cwd: {{ pulumi_cwd }}
 org: {{ pulumi_organization }}
 root: {{ pulumi_root_directory }}
+args: {{ pulumi_args | join(",") }}
 "#;
     let config = HashMap::new();
-    let ctx = make_context(&config);
+    let args = ["--verbose".to_string(), "blue".to_string()];
+    let ctx = JinjaContext {
+        args: &args,
+        ..make_context(&config)
+    };
     let preprocessor = JinjaPreprocessor::new(&ctx);
     let result = preprocessor.preprocess(source, "Pulumi.yaml").unwrap();
     assert!(result.contains("proj: test-project"), "got: {}", result);
@@ -391,6 +399,7 @@ root: {{ pulumi_root_directory }}
         "got: {}",
         result
     );
+    assert!(result.contains("args: --verbose,blue"), "got: {}", result);
 }
 
 #[test]
@@ -760,6 +769,7 @@ outputs:
         config: &config,
         project_dir: "/tmp",
         undefined: UndefinedMode::Strict,
+        args: &[],
         extra: &HashMap::new(),
     };
     let preprocessor = JinjaPreprocessor::new(&ctx);
@@ -770,7 +780,7 @@ outputs:
     assert!(!diags.has_errors(), "parse errors: {}", diags);
 
     // Phase 4: Evaluate with mock
-    let template: &'static _ = Box::leak(Box::new(template));
+    let template = &template;
     let eval = Evaluator::with_callback(
         "exec-test".to_string(),
         "dev".to_string(),
@@ -848,7 +858,7 @@ outputs:
     // Parse and evaluate
     let (template, diags) = parse_template(rendered.as_ref(), None);
     assert!(!diags.has_errors(), "parse errors: {}", diags);
-    let template: &'static _ = Box::leak(Box::new(template));
+    let template = &template;
     let eval = Evaluator::with_callback(
         "test-project".to_string(),
         "dev".to_string(),
@@ -910,9 +920,7 @@ fn make_readfile_context<'a>(
     dir: &'a tempfile::TempDir,
     config: &'a HashMap<String, String>,
 ) -> JinjaContext<'a> {
-    // We leak the string so it lives long enough; tests are short-lived
-    let project_dir: &'static str =
-        Box::leak(dir.path().to_str().unwrap().to_string().into_boxed_str());
+    let project_dir = dir.path().to_str().unwrap();
     JinjaContext {
         project_name: "test-project",
         stack_name: "dev",
@@ -922,6 +930,7 @@ fn make_readfile_context<'a>(
         config,
         project_dir,
         undefined: UndefinedMode::Strict,
+        args: &[],
         extra: &EMPTY_EXTRA,
     }
 }
@@ -1219,7 +1228,7 @@ outputs:
     assert!(!diags.has_errors(), "parse errors: {}", diags);
 
     // Evaluate with mock
-    let template: &'static _ = Box::leak(Box::new(template));
+    let template = &template;
     let eval = Evaluator::with_callback(
         "test-project".to_string(),
         "dev".to_string(),
@@ -1482,6 +1491,7 @@ resources:
         config: &config,
         project_dir: "/tmp",
         undefined: UndefinedMode::Passthrough,
+        args: &[],
         extra: &HashMap::new(),
     };
     let preprocessor = JinjaPreprocessor::new(&ctx);
@@ -1508,6 +1518,7 @@ fn test_passthrough_config_typo_still_errors() {
         config: &config,
         project_dir: "/tmp",
         undefined: UndefinedMode::Passthrough,
+        args: &[],
         extra: &HashMap::new(),
     };
     let preprocessor = JinjaPreprocessor::new(&ctx);
@@ -1533,6 +1544,7 @@ fn test_passthrough_unknown_passes_through() {
         config: &config,
         project_dir: "/tmp",
         undefined: UndefinedMode::Passthrough,
+        args: &[],
         extra: &HashMap::new(),
     };
     let preprocessor = JinjaPreprocessor::new(&ctx);
@@ -1557,6 +1569,7 @@ fn test_passthrough_readfile_still_works() {
         config: &config,
         project_dir: dir.path().to_str().unwrap(),
         undefined: UndefinedMode::Passthrough,
+        args: &[],
         extra: &HashMap::new(),
     };
     let preprocessor = JinjaPreprocessor::new(&ctx);
@@ -1586,6 +1599,7 @@ fn test_passthrough_for_loop_with_unknown_vars() {
         config: &config,
         project_dir: "/tmp",
         undefined: UndefinedMode::Passthrough,
+        args: &[],
         extra: &HashMap::new(),
     };
     let preprocessor = JinjaPreprocessor::new(&ctx);
@@ -1613,6 +1627,7 @@ fn test_strict_mode_unchanged() {
         config: &config,
         project_dir: "/tmp",
         undefined: UndefinedMode::Strict,
+        args: &[],
         extra: &HashMap::new(),
     };
     let preprocessor = JinjaPreprocessor::new(&ctx);
@@ -1632,8 +1647,7 @@ fn test_import_local_template() {
     let main_source = "{% import 'vars.j2' as v %}\nname: {{ v.app_name }}\n";
     std::fs::write(dir.path().join("vars.j2"), "{% set app_name = 'myapp' %}\n").unwrap();
 
-    let project_dir: &'static str =
-        Box::leak(dir.path().to_str().unwrap().to_string().into_boxed_str());
+    let project_dir = dir.path().to_str().unwrap();
     let config = HashMap::new();
     let ctx = JinjaContext {
         project_name: "test",
@@ -1644,6 +1658,7 @@ fn test_import_local_template() {
         config: &config,
         project_dir,
         undefined: UndefinedMode::Strict,
+        args: &[],
         extra: &EMPTY_EXTRA,
     };
     let preprocessor = JinjaPreprocessor::new(&ctx);
@@ -1672,9 +1687,8 @@ fn test_import_parent_relative_template() {
 
     let main_source = "{% import '../environment.j2' as env %}\nlocation: {{ env.location }}\n";
 
-    let project_dir: &'static str = Box::leak(sub.to_str().unwrap().to_string().into_boxed_str());
-    let root_dir: &'static str =
-        Box::leak(root.path().to_str().unwrap().to_string().into_boxed_str());
+    let project_dir = sub.to_str().unwrap();
+    let root_dir = root.path().to_str().unwrap();
     let config = HashMap::new();
     let ctx = JinjaContext {
         project_name: "test",
@@ -1685,6 +1699,7 @@ fn test_import_parent_relative_template() {
         config: &config,
         project_dir,
         undefined: UndefinedMode::Strict,
+        args: &[],
         extra: &EMPTY_EXTRA,
     };
     let preprocessor = JinjaPreprocessor::new(&ctx);
@@ -1724,9 +1739,8 @@ bucket: {{ b.name }}
 {% endfor %}
 ";
 
-    let project_dir: &'static str = Box::leak(sub.to_str().unwrap().to_string().into_boxed_str());
-    let root_dir: &'static str =
-        Box::leak(root.path().to_str().unwrap().to_string().into_boxed_str());
+    let project_dir = sub.to_str().unwrap();
+    let root_dir = root.path().to_str().unwrap();
     let config = HashMap::new();
     let ctx = JinjaContext {
         project_name: "test",
@@ -1737,6 +1751,7 @@ bucket: {{ b.name }}
         config: &config,
         project_dir,
         undefined: UndefinedMode::Strict,
+        args: &[],
         extra: &EMPTY_EXTRA,
     };
     let preprocessor = JinjaPreprocessor::new(&ctx);
@@ -1764,8 +1779,7 @@ fn test_import_rejects_absolute_path() {
         abs_template.display()
     );
 
-    let project_dir: &'static str =
-        Box::leak(dir.path().to_str().unwrap().to_string().into_boxed_str());
+    let project_dir = dir.path().to_str().unwrap();
     let config = HashMap::new();
     let ctx = JinjaContext {
         project_name: "test",
@@ -1776,6 +1790,7 @@ fn test_import_rejects_absolute_path() {
         config: &config,
         project_dir,
         undefined: UndefinedMode::Strict,
+        args: &[],
         extra: &EMPTY_EXTRA,
     };
     let preprocessor = JinjaPreprocessor::new(&ctx);
@@ -1790,8 +1805,7 @@ fn test_import_rejects_non_template_extension() {
 
     let main_source = "{% include 'secret.txt' %}\n";
 
-    let project_dir: &'static str =
-        Box::leak(dir.path().to_str().unwrap().to_string().into_boxed_str());
+    let project_dir = dir.path().to_str().unwrap();
     let config = HashMap::new();
     let ctx = JinjaContext {
         project_name: "test",
@@ -1802,6 +1816,7 @@ fn test_import_rejects_non_template_extension() {
         config: &config,
         project_dir,
         undefined: UndefinedMode::Strict,
+        args: &[],
         extra: &EMPTY_EXTRA,
     };
     let preprocessor = JinjaPreprocessor::new(&ctx);
@@ -1870,9 +1885,8 @@ resources:
   {% endfor %}
 "#;
 
-    let project_dir: &'static str = Box::leak(sub.to_str().unwrap().to_string().into_boxed_str());
-    let root_dir: &'static str =
-        Box::leak(root.path().to_str().unwrap().to_string().into_boxed_str());
+    let project_dir = sub.to_str().unwrap();
+    let root_dir = root.path().to_str().unwrap();
     let config = HashMap::new();
     let ctx = JinjaContext {
         project_name: "cheese-storage-buckets-1",
@@ -1883,6 +1897,7 @@ resources:
         config: &config,
         project_dir,
         undefined: UndefinedMode::Strict,
+        args: &[],
         extra: &EMPTY_EXTRA,
     };
     let preprocessor = JinjaPreprocessor::new(&ctx);
@@ -3,6 +3,8 @@
 //! Tests cover: file discovery, merge rules, collision detection,
 //! cross-file references, DAG ordering, Jinja + multi-file interactions.
 
+#![cfg(feature = "jinja")]
+
 use std::collections::HashMap;
 use std::fs;
 
@@ -338,7 +340,7 @@ resources:
     assert!(!merge_diags.has_errors());
 
     let template = merged.as_template_decl();
-    let template: &'static _ = Box::leak(Box::new(template));
+    let template = &template;
 
     let mock = MockCallback::new();
     let eval = Evaluator::with_callback(
@@ -381,6 +383,7 @@ fn test_jinja_expressions_in_multi_file() {
         config: &config,
         project_dir: dir.path().to_str().unwrap(),
         undefined: UndefinedMode::Strict,
+        args: &[],
         extra: &HashMap::new(),
     };
 
@@ -410,6 +413,7 @@ fn test_jinja_per_file_isolation() {
         config: &config,
         project_dir: dir.path().to_str().unwrap(),
         undefined: UndefinedMode::Strict,
+        args: &[],
         extra: &HashMap::new(),
     };
 
@@ -439,6 +443,7 @@ fn test_jinja_error_in_one_file_reports_correct_filename() {
         config: &config,
         project_dir: dir.path().to_str().unwrap(),
         undefined: UndefinedMode::Strict,
+        args: &[],
         extra: &HashMap::new(),
     };
 
@@ -464,6 +469,7 @@ fn test_jinja_for_loop_generates_resources_cross_file_deps() {
         config: &config,
         project_dir: "/tmp",
         undefined: UndefinedMode::Strict,
+        args: &[],
         extra: &HashMap::new(),
     };
     let preprocessor = JinjaPreprocessor::new(&ctx);
@@ -532,6 +538,7 @@ fn test_jinja_filters_in_multi_file_properties() {
         config: &config,
         project_dir: dir.path().to_str().unwrap(),
         undefined: UndefinedMode::Strict,
+        args: &[],
         extra: &HashMap::new(),
     };
 
@@ -552,6 +559,7 @@ fn test_multi_file_jinja_full_pipeline() {
         config: &config,
         project_dir: "/tmp",
         undefined: UndefinedMode::Strict,
+        args: &[],
         extra: &HashMap::new(),
     };
     let preprocessor = JinjaPreprocessor::new(&ctx);
@@ -598,7 +606,7 @@ fn test_multi_file_jinja_full_pipeline() {
     assert_eq!(merged.resource_count(), 3);
 
     let template = merged.as_template_decl();
-    let template: &'static _ = Box::leak(Box::new(template));
+    let template = &template;
 
     let mock = MockCallback::new();
     let eval = Evaluator::with_callback(
@@ -1010,6 +1018,7 @@ fn test_readfile_in_main_yaml() {
         config: &config,
         project_dir: dir.path().to_str().unwrap(),
         undefined: UndefinedMode::Strict,
+        args: &[],
         extra: &HashMap::new(),
     };
 
@@ -1036,6 +1045,7 @@ fn test_readfile_in_satellite_yaml() {
         config: &config,
         project_dir: dir.path().to_str().unwrap(),
         undefined: UndefinedMode::Strict,
+        args: &[],
         extra: &HashMap::new(),
     };
 
@@ -1063,6 +1073,7 @@ fn test_readfile_in_multiple_files() {
         config: &config,
         project_dir: dir.path().to_str().unwrap(),
         undefined: UndefinedMode::Strict,
+        args: &[],
         extra: &HashMap::new(),
     };
 
@@ -1089,6 +1100,7 @@ fn test_readfile_cross_file_with_evaluation() {
         config: &config,
         project_dir: dir.path().to_str().unwrap(),
         undefined: UndefinedMode::Strict,
+        args: &[],
         extra: &HashMap::new(),
     };
 
@@ -1096,7 +1108,7 @@ fn test_readfile_cross_file_with_evaluation() {
     assert!(!diags.has_errors(), "errors: {}", diags);
 
     let template = merged.as_template_decl();
-    let template: &'static _ = Box::leak(Box::new(template));
+    let template = &template;
 
     let mock = MockCallback::new();
     let eval = Evaluator::with_callback(
@@ -13,7 +13,7 @@ fn eval_with_noop(source: &str) -> (Evaluator<'static, NoopCallback>, bool) {
     if parse_diags.has_errors() {
         panic!("parse errors: {}", parse_diags);
     }
-    let template: &'static _ = Box::leak(Box::new(template));
+    let template = &template;
     let eval = Evaluator::new(
         "test".to_string(),
         "dev".to_string(),
@@ -31,7 +31,7 @@ fn eval_with_mock(source: &str, mock: MockCallback) -> (Evaluator<'static, MockC
     if parse_diags.has_errors() {
         panic!("parse errors: {}", parse_diags);
     }
-    let template: &'static _ = Box::leak(Box::new(template));
+    let template = &template;
     let eval = Evaluator::with_callback(
         "test".to_string(),
         "dev".to_string(),
@@ -92,7 +92,7 @@ variables:
     let (eval, has_errors) = eval_with_noop(source);
     assert!(!has_errors, "errors: {}", eval.diags_display());
     let val = eval.get_variable("result").unwrap();
-    assert_eq!(val, Value::Number(42.0));
+    assert_eq!(val, Value::Number(42.0.into()));
 }
 
 #[test]
@@ -145,8 +145,8 @@ variables:
     match val {
         Value::List(items) => {
             assert_eq!(items.len(), 2);
-            assert_eq!(items[0], Value::Number(3000.0));
-            assert_eq!(items[1], Value::Number(8080.0));
+            assert_eq!(items[0], Value::Number(3000.0.into()));
+            assert_eq!(items[1], Value::Number(8080.0.into()));
         }
         _ => panic!("expected list, got {:?}", val),
     }
@@ -219,7 +219,7 @@ variables:
     let (eval, has_errors) = eval_with_noop(source);
     assert!(!has_errors, "errors: {}", eval.diags_display());
     let val = eval.get_variable("total").unwrap();
-    assert_eq!(val, Value::Number(60.0));
+    assert_eq!(val, Value::Number(60.0.into()));
 }
 
 #[test]
@@ -669,7 +669,7 @@ variables:
     let (eval, has_errors) = eval_with_noop(source);
     assert!(!has_errors, "errors: {}", eval.diags_display());
     let val = eval.get_variable("result").unwrap();
-    assert_eq!(val, Value::Number(1000000.0));
+    assert_eq!(val, Value::Number(1000000.0.into()));
 }
 
 // =========================================================================
@@ -838,7 +838,7 @@ variables:
     assert!(!has_errors, "errors: {}", eval.diags_display());
     let val = eval.get_variable("result").unwrap();
     match val {
-        Value::Number(n) => assert!((n - 3.14159).abs() < 0.0001, "got {}", n),
+        Value::Number(n) => assert!((n.as_f64() - 3.14159).abs() < 0.0001, "got {}", n),
         _ => panic!("expected Number, got {:?}", val),
     }
 }
@@ -863,7 +863,7 @@ variables:
     let (eval, has_errors) = eval_with_noop(source);
     assert!(!has_errors, "errors: {}", eval.diags_display());
     let val = eval.get_variable("result").unwrap();
-    assert_eq!(val, Value::Number(-2.5));
+    assert_eq!(val, Value::Number((-2.5).into()));
 }
 
 #[test]
@@ -889,7 +889,7 @@ variables:
     let (eval, has_errors) = eval_with_noop(source);
     assert!(!has_errors, "errors: {}", eval.diags_display());
     let val = eval.get_variable("result").unwrap();
-    assert_eq!(val, Value::Number(100000.0));
+    assert_eq!(val, Value::Number(100000.0.into()));
 }
 
 #[test]
@@ -915,7 +915,7 @@ variables:
     match val {
         Value::List(items) => {
             assert_eq!(items.len(), 3);
-            assert_eq!(items[0], Value::Number(1.0));
+            assert_eq!(items[0], Value::Number(1.0.into()));
             assert_eq!(items[1], Value::String("hello".to_string().into()));
             assert_eq!(items[2], Value::Bool(true));
         }
@@ -950,7 +950,7 @@ variables:
             // Check pi is a number, not a string
             let pi_entry = entries.iter().find(|(k, _)| k.as_ref() == "pi");
             assert!(
-                matches!(pi_entry, Some((_, Value::Number(n))) if (*n - 3.14).abs() < 0.01),
+                matches!(pi_entry, Some((_, Value::Number(n))) if (n.as_f64() - 3.14).abs() < 0.01),
                 "pi should be Number(3.14), got {:?}",
                 pi_entry
             );
@@ -0,0 +1,174 @@
+//! gRPC channel setup shared by the engine, monitor, and schema loader clients.
+//!
+//! Long previews can sit idle on a connection for minutes while providers
+//! work; proxies and load balancers then close the "idle" connection and the
+//! next RPC fails. Channels built here send HTTP/2 keepalive pings while
+//! requests are in flight, which keeps long provider calls alive without
+//! pinging an idle connection often enough to trip the server's
+//! `too_many_pings` GOAWAY.
+//!
+//! Idempotent calls (feature queries, package registration) are retried with
+//! bounded exponential backoff when the transport reports `Unavailable`;
+//! tonic re-establishes the underlying connection on the next request, so a
+//! retry is a reconnect. Calls with side effects — resource registration,
+//! invokes, logs — are sent exactly once: `Unavailable` does not prove the
+//! server never saw the request, and a retry could register or invoke twice.
+//!
+//! Since idle connections are not pinged, one a proxy dropped while idle is
+//! only noticed by the next call. Before a call with side effects on a
+//! connection idle for longer than [`IdleProbe::after`], the client sends a
+//! cheap idempotent probe and reconnects if it fails or goes unanswered, so
+//! the call itself goes out on a live connection.
+
+use std::future::Future;
+use std::time::Duration;
+
+use tonic::transport::{Channel, Endpoint};
+use tonic::{Code, Status};
+
+/// Interval between HTTP/2 keepalive pings.
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
+/// How long to wait for a ping acknowledgement before closing the connection.
+const KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(20);
+/// TCP-level keepalive for the underlying socket.
+const TCP_KEEPALIVE: Duration = Duration::from_secs(60);
+
+/// Connects to `url` with keepalive enabled for active connections.
+pub async fn connect(url: String) -> Result<Channel, tonic::transport::Error> {
+    Endpoint::from_shared(url)?
+        .http2_keep_alive_interval(KEEPALIVE_INTERVAL)
+        .keep_alive_timeout(KEEPALIVE_TIMEOUT)
+        .keep_alive_while_idle(false)
+        .tcp_keepalive(Some(TCP_KEEPALIVE))
+        .connect()
+        .await
+}
+
+/// Bounded exponential backoff for retrying idempotent RPCs after a dropped
+/// connection.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled after each failed attempt.
+    pub initial_backoff: Duration,
+    /// Upper bound on the delay between attempts.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Runs `call` until it succeeds, fails with a non-retryable status, or
+    /// the attempt budget is exhausted. `call` is invoked once per attempt and
+    /// must build a fresh request each time.
+    pub async fn run<T, F, Fut>(&self, mut call: F) -> Result<T, Status>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Status>>,
+    {
+        let mut backoff = self.initial_backoff;
+        let mut attempt = 1;
+        loop {
+            match call().await {
+                Err(status) if is_retryable(&status) && attempt < self.max_attempts => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(attempt, error = %status, "engine connection lost, retrying");
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(self.max_backoff);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// When to check a connection before sending a call with side effects.
+#[derive(Debug, Clone, Copy)]
+pub struct IdleProbe {
+    /// How long a connection may go unused before it is probed.
+    pub after: Duration,
+    /// How long the probe may take before the connection is presumed dead.
+    pub timeout: Duration,
+}
+
+impl Default for IdleProbe {
+    fn default() -> Self {
+        Self {
+            after: KEEPALIVE_INTERVAL,
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// `Unavailable` is what tonic reports when the connection was closed or
+/// could not be re-established. The request may or may not have reached the
+/// server, so only idempotent calls are run under a [`RetryPolicy`].
+fn is_retryable(status: &Status) -> bool {
+    status.code() == Code::Unavailable
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn fast_policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_recovers_after_unavailable() {
+        let calls = AtomicU32::new(0);
+        let result = fast_policy(5)
+            .run(|| async {
+                if calls.fetch_add(1, Ordering::SeqCst) < 2 {
+                    Err(Status::unavailable("connection reset"))
+                } else {
+                    Ok("done")
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), "done");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_is_bounded() {
+        let calls = AtomicU32::new(0);
+        let result: Result<(), Status> = fast_policy(3)
+            .run(|| async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(Status::unavailable("proxy closed the connection"))
+            })
+            .await;
+        assert_eq!(result.unwrap_err().code(), Code::Unavailable);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_other_errors_are_not_retried() {
+        let calls = AtomicU32::new(0);
+        let result: Result<(), Status> = fast_policy(5)
+            .run(|| async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(Status::invalid_argument("bad request"))
+            })
+            .await;
+        assert_eq!(result.unwrap_err().code(), Code::InvalidArgument);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
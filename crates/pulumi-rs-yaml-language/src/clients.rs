@@ -1,22 +1,45 @@
 //! Tonic gRPC client wrappers for the Pulumi engine and resource monitor.
 
 use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::{Mutex, OnceLock, RwLock};
+use std::time::Instant;
 
 use pulumi_rs_yaml_core::eval::callback::{InvokeResponse, RegisterResponse, ResourceCallback};
 use pulumi_rs_yaml_core::eval::context::EngineError;
-use pulumi_rs_yaml_core::eval::protobuf::{protobuf_to_value, value_to_protobuf};
+use pulumi_rs_yaml_core::eval::protobuf::{
+    entries_into_struct, protobuf_to_value, value_into_output_protobuf,
+};
 use pulumi_rs_yaml_core::eval::resource::ResolvedResourceOptions;
 use pulumi_rs_yaml_core::eval::value::Value;
 
 use pulumi_rs_yaml_proto::pulumirpc;
 use tokio::runtime::Handle;
 
+use crate::channel::{self, IdleProbe, RetryPolicy};
+use crate::rpc_metrics::{self, RpcObservation};
+
+type MonitorClient =
+    pulumirpc::resource_monitor_client::ResourceMonitorClient<tonic::transport::Channel>;
+type EngineClient = pulumirpc::engine_client::EngineClient<tonic::transport::Channel>;
+
 /// Wraps a tonic `ResourceMonitorClient` with synchronous methods
 /// suitable for use as a `ResourceCallback`.
 pub struct GrpcCallback {
-    monitor: pulumirpc::resource_monitor_client::ResourceMonitorClient<tonic::transport::Channel>,
-    engine: pulumirpc::engine_client::EngineClient<tonic::transport::Channel>,
+    /// The clients are replaced when an idle connection turns out dead.
+    monitor: RwLock<MonitorClient>,
+    engine: RwLock<EngineClient>,
+    monitor_url: String,
+    engine_url: String,
     handle: Handle,
+    /// Retries idempotent RPCs that fail because the connection was dropped.
+    retry: RetryPolicy,
+    /// Checks an idle connection before an RPC with side effects.
+    idle_probe: IdleProbe,
+    /// When the monitor or engine last answered an RPC.
+    last_answer: Mutex<Instant>,
+    /// Whether the monitor accepts output values (queried on first use).
+    output_values: OnceLock<bool>,
 }
 
 /// Runs a future to completion on the tokio runtime, allowing synchronous
@@ -33,33 +56,156 @@ fn block_on<F: std::future::Future>(handle: &Handle, f: F) -> F::Output {
 
 impl GrpcCallback {
     /// Creates a new GrpcCallback by connecting to the given addresses.
+    ///
+    /// Both channels use HTTP/2 keepalive, idempotent RPCs are retried with
+    /// bounded backoff if the connection is lost, and an idle connection is
+    /// checked before an RPC with side effects (see [`channel`]).
     pub async fn connect(monitor_address: &str, engine_address: &str) -> Result<Self, EngineError> {
         let monitor_url = pulumi_rs_yaml_core::normalize_grpc_address(monitor_address);
         let engine_url = pulumi_rs_yaml_core::normalize_grpc_address(engine_address);
-
-        // Raise tonic's default 4 MiB message cap — large resource registrations
-        // / reads can exceed it. See core::MAX_GRPC_MESSAGE_BYTES.
-        let max = pulumi_rs_yaml_core::MAX_GRPC_MESSAGE_BYTES;
-        let monitor =
-            pulumirpc::resource_monitor_client::ResourceMonitorClient::connect(monitor_url)
-                .await
-                .map_err(|e| EngineError::Grpc(format!("failed to connect to monitor: {}", e)))?
-                .max_decoding_message_size(max)
-                .max_encoding_message_size(max);
-
-        let engine = pulumirpc::engine_client::EngineClient::connect(engine_url)
-            .await
-            .map_err(|e| EngineError::Grpc(format!("failed to connect to engine: {}", e)))?
-            .max_decoding_message_size(max)
-            .max_encoding_message_size(max);
+        let (monitor, engine) = connect_clients(&monitor_url, &engine_url).await?;
 
         Ok(Self {
-            monitor,
-            engine,
+            monitor: RwLock::new(monitor),
+            engine: RwLock::new(engine),
+            monitor_url,
+            engine_url,
             handle: Handle::current(),
+            retry: RetryPolicy::default(),
+            idle_probe: IdleProbe::default(),
+            last_answer: Mutex::new(Instant::now()),
+            output_values: OnceLock::new(),
         })
     }
 
+    fn monitor(&self) -> MonitorClient {
+        self.monitor.read().unwrap().clone()
+    }
+
+    fn engine(&self) -> EngineClient {
+        self.engine.read().unwrap().clone()
+    }
+
+    /// Records that an RPC was answered, so the connection is known to be
+    /// live.
+    fn answered<Resp>(&self, result: &Result<tonic::Response<Resp>, tonic::Status>) {
+        if result.is_ok() {
+            *self.last_answer.lock().unwrap() = Instant::now();
+        }
+    }
+
+    /// Probes the monitor if it has not answered for longer than the idle
+    /// probe allows, and reconnects both clients if the probe fails or times
+    /// out. A proxy may have dropped the idle connection without closing it,
+    /// and an RPC with side effects sent on it could not be retried.
+    async fn ensure_connected(&self) {
+        if self.last_answer.lock().unwrap().elapsed() < self.idle_probe.after {
+            return;
+        }
+        let mut monitor = self.monitor();
+        let probe = monitor.supports_feature(pulumirpc::SupportsFeatureRequest {
+            id: "secrets".to_string(),
+        });
+        match tokio::time::timeout(self.idle_probe.timeout, probe).await {
+            Ok(result @ Ok(_)) => return self.answered(&result),
+            Ok(Err(status)) if status.code() != tonic::Code::Unavailable => return,
+            _ => {}
+        }
+        #[cfg(feature = "tracing")]
+        tracing::warn!("idle engine connection is not answering, reconnecting");
+        match connect_clients(&self.monitor_url, &self.engine_url).await {
+            Ok((monitor, engine)) => {
+                *self.monitor.write().unwrap() = monitor;
+                *self.engine.write().unwrap() = engine;
+            }
+            Err(_e) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(error = %_e, "reconnecting to the engine failed");
+            }
+        }
+    }
+
+    /// Runs an idempotent RPC under the retry policy and records its metrics
+    /// as `method` (see [`rpc_metrics`]).
+    ///
+    /// Every attempt but the last gets a copy of `req`; the last one takes
+    /// `req` itself, so a policy of one attempt never copies it.
+    async fn observe_idempotent<Req, Resp, F, Fut>(
+        &self,
+        method: &'static str,
+        req: Req,
+        mut call: F,
+    ) -> Result<tonic::Response<Resp>, tonic::Status>
+    where
+        Req: prost::Message + Clone,
+        Resp: prost::Message,
+        F: FnMut(Req) -> Fut,
+        Fut: Future<Output = Result<tonic::Response<Resp>, tonic::Status>>,
+    {
+        let started = Instant::now();
+        let request_bytes = req.encoded_len();
+        let max_attempts = self.retry.max_attempts;
+        let mut req = Some(req);
+        let mut attempts = 0;
+        let result = self
+            .retry
+            .run(|| {
+                attempts += 1;
+                let req = if attempts < max_attempts {
+                    req.clone()
+                } else {
+                    req.take()
+                };
+                call(req.expect("the retry policy stops after its last attempt"))
+            })
+            .await;
+        self.answered(&result);
+        rpc_metrics::record(&RpcObservation {
+            method,
+            elapsed: started.elapsed(),
+            attempts,
+            request_bytes,
+            response: match &result {
+                Ok(resp) => Ok(resp.get_ref().encoded_len()),
+                Err(status) => Err(status.code()),
+            },
+        });
+        result
+    }
+
+    /// Sends an RPC with side effects exactly once, moving `req` into the
+    /// call, and records its metrics as `method`. An idle connection is
+    /// checked first (see [`GrpcCallback::ensure_connected`]).
+    async fn observe_once<Req, Resp, F, Fut>(
+        &self,
+        method: &'static str,
+        req: Req,
+        call: F,
+    ) -> Result<tonic::Response<Resp>, tonic::Status>
+    where
+        Req: prost::Message,
+        Resp: prost::Message,
+        F: FnOnce(Req) -> Fut,
+        Fut: Future<Output = Result<tonic::Response<Resp>, tonic::Status>>,
+    {
+        self.ensure_connected().await;
+        let started = Instant::now();
+        let request_bytes = req.encoded_len();
+        let result = call(req).await;
+        self.answered(&result);
+        rpc_metrics::record(&RpcObservation {
+            method,
+            elapsed: started.elapsed(),
+            attempts: 1,
+            request_bytes,
+            response: match &result {
+                Ok(resp) => Ok(resp.get_ref().encoded_len()),
+                Err(status) => Err(status.code()),
+            },
+        });
+        result
+    }
+
     /// Registers a package with the engine and returns a package reference UUID.
     pub fn register_package(
         &self,
@@ -82,10 +228,12 @@ impl GrpcCallback {
             parameterization: param,
         };
 
-        let mut monitor = self.monitor.clone();
         block_on(&self.handle, async {
-            let resp = monitor
-                .register_package(req)
+            let resp = self
+                .observe_idempotent("register_package", req, |req| {
+                    let mut monitor = self.monitor();
+                    async move { monitor.register_package(req).await }
+                })
                 .await
                 .map_err(|e| {
                     let pkg_id = if version.is_empty() {
@@ -108,13 +256,23 @@ impl GrpcCallback {
         let req = pulumirpc::SupportsFeatureRequest {
             id: feature_id.to_string(),
         };
-        let mut monitor = self.monitor.clone();
-        match block_on(&self.handle, async { monitor.supports_feature(req).await }) {
+        let call = self.observe_idempotent("supports_feature", req, |req| {
+            let mut monitor = self.monitor();
+            async move { monitor.supports_feature(req).await }
+        });
+        match block_on(&self.handle, call) {
             Ok(resp) => resp.into_inner().has_support,
             Err(_) => false,
         }
     }
 
+    /// Returns true if the monitor supports the `outputValues` feature.
+    fn supports_output_values(&self) -> bool {
+        *self
+            .output_values
+            .get_or_init(|| self.supports_feature("outputValues"))
+    }
+
     /// Logs a message to the engine.
     pub fn log_to_engine(
         &self,
@@ -131,12 +289,13 @@ impl GrpcCallback {
             stream_id,
             ephemeral,
         };
-        let mut engine = self.engine.clone();
         block_on(&self.handle, async {
-            engine
-                .log(req)
-                .await
-                .map_err(|e| EngineError::Grpc(format!("log failed: {}", e)))?;
+            self.observe_once("log", req, |req| {
+                let mut engine = self.engine();
+                async move { engine.log(req).await }
+            })
+            .await
+            .map_err(|e| EngineError::Grpc(format!("log failed: {}", e)))?;
             Ok(())
         })
     }
@@ -152,8 +311,13 @@ impl ResourceCallback for GrpcCallback {
         inputs: HashMap<String, Value<'static>>,
         options: ResolvedResourceOptions,
     ) -> Result<RegisterResponse, EngineError> {
-        // Convert inputs to protobuf struct
-        let object = values_to_struct(&inputs);
+        // Convert inputs to protobuf struct. Remote components get inputs as
+        // output values so their SDK sees real Outputs with dependencies.
+        let object = if remote && self.supports_output_values() {
+            values_to_output_struct(inputs, &options.property_dependencies)
+        } else {
+            values_to_struct(inputs)
+        };
 
         // Convert property dependencies
         let property_dependencies: HashMap<
@@ -258,12 +422,20 @@ impl ResourceCallback for GrpcCallback {
             hide_diffs: options.hide_diffs.clone(),
         };
 
-        let mut monitor = self.monitor.clone();
         block_on(&self.handle, async {
-            let resp = monitor
-                .register_resource(req)
+            let resp = self
+                .observe_once("register_resource", req, |req| {
+                    let mut monitor = self.monitor();
+                    async move { monitor.register_resource(req).await }
+                })
                 .await
-                .map_err(|e| EngineError::Registration(format!("register {} failed: {}", name, e)))?
+                .map_err(|e| {
+                    EngineError::Registration(format!(
+                        "register {} failed: {}",
+                        name,
+                        describe_status(&e)
+                    ))
+                })?
                 .into_inner();
 
             let outputs = struct_to_values(resp.object);
@@ -282,49 +454,66 @@ impl ResourceCallback for GrpcCallback {
         type_token: &str,
         name: &str,
         id: &str,
-        parent_urn: &str,
         inputs: HashMap<String, Value<'static>>,
-        provider_ref: &str,
-        version: &str,
+        options: ResolvedResourceOptions,
     ) -> Result<RegisterResponse, EngineError> {
-        let properties = values_to_struct(&inputs);
+        let properties = values_to_struct(inputs);
+
+        // ReadResource has no per-property dependency map, so fold property
+        // dependencies into the resource-level list.
+        let mut dependencies = options.depends_on.clone();
+        for urns in options.property_dependencies.values() {
+            for urn in urns {
+                if !dependencies.contains(urn) {
+                    dependencies.push(urn.clone());
+                }
+            }
+        }
 
         let req = pulumirpc::ReadResourceRequest {
             r#type: type_token.to_string(),
             name: name.to_string(),
             id: id.to_string(),
-            parent: parent_urn.to_string(),
+            parent: options.parent_urn.unwrap_or_default(),
             properties: Some(properties),
-            dependencies: Vec::new(),
-            provider: provider_ref.to_string(),
-            version: version.to_string(),
+            dependencies,
+            provider: options.provider_ref.unwrap_or_default(),
+            version: options.version,
             accept_secrets: true,
-            additional_secret_outputs: Vec::new(),
+            additional_secret_outputs: options.additional_secret_outputs.clone(),
             accept_resources: true,
-            plugin_download_url: String::new(),
+            plugin_download_url: options.plugin_download_url,
             plugin_checksums: HashMap::new(),
             source_position: None,
             stack_trace: None,
             parent_stack_trace_handle: String::new(),
-            package_ref: String::new(),
+            package_ref: options.package_ref,
         };
 
-        let mut monitor = self.monitor.clone();
         block_on(&self.handle, async {
-            let resp = monitor
-                .read_resource(req)
+            let resp = self
+                .observe_once("read_resource", req, |req| {
+                    let mut monitor = self.monitor();
+                    async move { monitor.read_resource(req).await }
+                })
                 .await
-                .map_err(|e| EngineError::Grpc(format!("read resource failed: {}", e)))?
+                .map_err(|e| {
+                    EngineError::Grpc(format!("read resource failed: {}", describe_status(&e)))
+                })?
                 .into_inner();
 
+            // Secret signatures in the response become Value::Secret here;
+            // outputs the engine returned in plaintext are re-wrapped below.
             let outputs = struct_to_values(resp.properties);
 
-            Ok(RegisterResponse {
+            let mut resp = RegisterResponse {
                 urn: resp.urn,
                 id: id.to_string(),
                 outputs,
                 stables: Vec::new(),
-            })
+            };
+            resp.mark_secret_outputs(&options.additional_secret_outputs);
+            Ok(resp)
         })
     }
 
@@ -337,7 +526,7 @@ impl ResourceCallback for GrpcCallback {
         _parent: &str,
         _depends_on: &[String],
     ) -> Result<InvokeResponse, EngineError> {
-        let args_struct = values_to_struct(&args);
+        let args_struct = values_to_struct(args);
 
         let req = pulumirpc::ResourceInvokeRequest {
             tok: token.to_string(),
@@ -353,12 +542,16 @@ impl ResourceCallback for GrpcCallback {
             parent_stack_trace_handle: String::new(),
         };
 
-        let mut monitor = self.monitor.clone();
         block_on(&self.handle, async {
-            let resp = monitor
-                .invoke(req)
+            let resp = self
+                .observe_once("invoke", req, |req| {
+                    let mut monitor = self.monitor();
+                    async move { monitor.invoke(req).await }
+                })
                 .await
-                .map_err(|e| EngineError::Invoke(format!("invoke {} failed: {}", token, e)))?
+                .map_err(|e| {
+                    EngineError::Invoke(format!("invoke {} failed: {}", token, describe_status(&e)))
+                })?
                 .into_inner();
 
             let return_values = struct_to_values(resp.r#return);
@@ -380,19 +573,22 @@ impl ResourceCallback for GrpcCallback {
         urn: &str,
         outputs: HashMap<String, Value<'static>>,
     ) -> Result<(), EngineError> {
-        let outputs_struct = values_to_struct(&outputs);
+        let outputs_struct = values_to_struct(outputs);
 
         let req = pulumirpc::RegisterResourceOutputsRequest {
             urn: urn.to_string(),
             outputs: Some(outputs_struct),
         };
 
-        let mut monitor = self.monitor.clone();
         block_on(&self.handle, async {
-            monitor
-                .register_resource_outputs(req)
-                .await
-                .map_err(|e| EngineError::Grpc(format!("register outputs failed: {}", e)))?;
+            self.observe_once("register_resource_outputs", req, |req| {
+                let mut monitor = self.monitor();
+                async move { monitor.register_resource_outputs(req).await }
+            })
+            .await
+            .map_err(|e| {
+                EngineError::Grpc(format!("register outputs failed: {}", describe_status(&e)))
+            })?;
             Ok(())
         })
     }
@@ -402,11 +598,69 @@ impl ResourceCallback for GrpcCallback {
     }
 }
 
+/// Connects the monitor and engine clients.
+async fn connect_clients(
+    monitor_url: &str,
+    engine_url: &str,
+) -> Result<(MonitorClient, EngineClient), EngineError> {
+    // Raise tonic's default 4 MiB message cap — large resource registrations
+    // / reads can exceed it. See core::MAX_GRPC_MESSAGE_BYTES.
+    let max = pulumi_rs_yaml_core::MAX_GRPC_MESSAGE_BYTES;
+    let monitor_channel = channel::connect(monitor_url.to_string())
+        .await
+        .map_err(|e| EngineError::Grpc(format!("failed to connect to monitor: {}", e)))?;
+    let monitor = MonitorClient::new(monitor_channel)
+        .max_decoding_message_size(max)
+        .max_encoding_message_size(max);
+
+    let engine_channel = channel::connect(engine_url.to_string())
+        .await
+        .map_err(|e| EngineError::Grpc(format!("failed to connect to engine: {}", e)))?;
+    let engine = EngineClient::new(engine_channel)
+        .max_decoding_message_size(max)
+        .max_encoding_message_size(max);
+    Ok((monitor, engine))
+}
+
+/// Formats an RPC error, explaining message-size rejections that tonic and
+/// the engine otherwise report as bare transport errors.
+fn describe_status(status: &tonic::Status) -> String {
+    match status.code() {
+        tonic::Code::ResourceExhausted | tonic::Code::OutOfRange
+            if status.message().contains("message") =>
+        {
+            format!(
+                "{} (the request exceeds the gRPC message size limit; \
+                 pass large file contents as assets such as fn::fileAsset \
+                 instead of inlining them with fn::readFile)",
+                status
+            )
+        }
+        _ => status.to_string(),
+    }
+}
+
 /// Converts a HashMap of Values to a protobuf Struct.
-fn values_to_struct(values: &HashMap<String, Value<'static>>) -> prost_types::Struct {
+///
+/// Consumes the map so that strings and nested values are moved rather
+/// than cloned.
+fn values_to_struct(values: HashMap<String, Value<'static>>) -> prost_types::Struct {
+    entries_into_struct(values)
+}
+
+/// Converts a HashMap of Values to a protobuf Struct of output values, each
+/// carrying the URNs its property depends on.
+fn values_to_output_struct(
+    values: HashMap<String, Value<'static>>,
+    dependencies: &HashMap<String, Vec<String>>,
+) -> prost_types::Struct {
     let fields: BTreeMap<String, prost_types::Value> = values
-        .iter()
-        .map(|(k, v)| (k.clone(), value_to_protobuf(v)))
+        .into_iter()
+        .map(|(k, v)| {
+            let deps = dependencies.get(&k).map(Vec::as_slice).unwrap_or_default();
+            let v = value_into_output_protobuf(v, deps);
+            (k, v)
+        })
         .collect();
     prost_types::Struct { fields }
 }
@@ -425,3 +679,100 @@ fn struct_to_values(s: Option<prost_types::Struct>) -> HashMap<String, Value<'st
         None => HashMap::new(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulumi_rs_yaml_mock_engine::MockEngine;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// A TCP proxy in front of `upstream` that can stop forwarding on every
+    /// open connection without closing it, like a load balancer that
+    /// silently drops idle connections. New connections are forwarded.
+    struct Proxy {
+        address: String,
+        stalled: Arc<Mutex<Arc<AtomicBool>>>,
+    }
+
+    impl Proxy {
+        async fn start(upstream: String) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let address = listener.local_addr().unwrap().to_string();
+            let stalled = Arc::new(Mutex::new(Arc::new(AtomicBool::new(false))));
+            let current = Arc::clone(&stalled);
+            tokio::spawn(async move {
+                while let Ok((mut client, _)) = listener.accept().await {
+                    let upstream = upstream.clone();
+                    let stalled = Arc::clone(&current.lock().unwrap());
+                    tokio::spawn(async move {
+                        let mut server = TcpStream::connect(upstream).await.unwrap();
+                        tokio::select! {
+                            _ = tokio::io::copy_bidirectional(&mut client, &mut server) => {}
+                            _ = async {
+                                while !stalled.load(Ordering::SeqCst) {
+                                    tokio::time::sleep(Duration::from_millis(5)).await;
+                                }
+                            } => std::future::pending::<()>().await,
+                        }
+                    });
+                }
+            });
+            Self { address, stalled }
+        }
+
+        /// Stops forwarding on the connections open so far.
+        fn stall_connections(&self) {
+            let mut stalled = self.stalled.lock().unwrap();
+            stalled.store(true, Ordering::SeqCst);
+            *stalled = Arc::new(AtomicBool::new(false));
+        }
+    }
+
+    fn register(callback: &GrpcCallback, name: &str) -> Result<RegisterResponse, EngineError> {
+        callback.register_resource(
+            "test:index:Bucket",
+            name,
+            true,
+            false,
+            HashMap::new(),
+            ResolvedResourceOptions::default(),
+        )
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_register_after_idle_connection_dropped() {
+        let engine = MockEngine::start().await;
+        let monitor = Proxy::start(engine.monitor_address.clone()).await;
+        let mut callback = GrpcCallback::connect(&monitor.address, &engine.engine_address)
+            .await
+            .unwrap();
+        callback.idle_probe = IdleProbe {
+            after: Duration::from_millis(100),
+            timeout: Duration::from_millis(200),
+        };
+        let callback = Arc::new(callback);
+        register(&callback, "before").unwrap();
+
+        monitor.stall_connections();
+        tokio::time::sleep(Duration::from_millis(150)).await;
+
+        // Without the probe the registration would wait on the dead
+        // connection until the in-flight keepalive gives up, then fail.
+        let after = tokio::task::spawn_blocking(move || register(&callback, "after"));
+        tokio::time::timeout(Duration::from_secs(10), after)
+            .await
+            .expect("the registration waited on the dropped connection")
+            .unwrap()
+            .unwrap();
+        let names: Vec<String> = engine
+            .recorded()
+            .registrations
+            .iter()
+            .map(|r| r.name.clone())
+            .collect();
+        assert_eq!(names, vec!["before", "after"]);
+    }
+}
@@ -5,15 +5,28 @@
 //! this provider via `RunPlugin`. The Pulumi engine then calls `Construct`
 //! on this provider for each component instantiation.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
 use tonic::{Request, Response, Status};
 
 use pulumi_rs_yaml_core::ast::template::TemplateDecl;
+use pulumi_rs_yaml_core::diag::Diagnostics;
 use pulumi_rs_yaml_core::eval::callback::ResourceCallback;
-use pulumi_rs_yaml_core::eval::evaluator::Evaluator;
-use pulumi_rs_yaml_core::eval::protobuf::protobuf_to_value;
+use pulumi_rs_yaml_core::eval::context::EvaluatorContext;
+use pulumi_rs_yaml_core::eval::factory::EvaluatorFactory;
+use pulumi_rs_yaml_core::eval::graph::{
+    check_component_cycles, check_component_interface, check_component_names,
+};
+use pulumi_rs_yaml_core::eval::protobuf::{
+    protobuf_to_value, value_to_output_protobuf, value_to_protobuf,
+};
+use pulumi_rs_yaml_core::eval::resource::ResolvedResourceOptions;
 use pulumi_rs_yaml_core::eval::value::Value;
+use pulumi_rs_yaml_core::multi_file;
+use pulumi_rs_yaml_core::remote_import::sha256_hex;
+use pulumi_rs_yaml_core::schema::{component_type_tokens, generate_component_schema};
 use pulumi_rs_yaml_proto::pulumirpc;
 
 use crate::clients::GrpcCallback;
@@ -24,16 +37,23 @@ pub struct ComponentProvider {
     pub engine_address: String,
     /// The monitor address for creating inner gRPC callbacks.
     pub monitor_address: String,
-    /// The template containing component declarations (leaked to 'static).
-    pub template: &'static TemplateDecl<'static>,
-    /// The JSON-encoded schema for this package.
-    pub schema_json: String,
+    /// The template containing component declarations.
+    pub template: Arc<TemplateDecl<'static>>,
+    /// The schema for this package, served by `GetSchema`.
+    pub schema: ComponentSchema,
     /// Project name for evaluator context.
     pub project: String,
     /// Stack name for evaluator context.
     pub stack: String,
     /// Whether we're in preview mode.
     pub dry_run: bool,
+    /// The directory file paths in component bodies are relative to.
+    pub program_directory: String,
+    /// Creates the evaluator of each `Construct` call, reusing the state
+    /// of earlier ones.
+    pub evaluators: EvaluatorFactory<'static>,
+    /// URNs of the components whose bodies are being evaluated.
+    pub constructing: Mutex<HashSet<String>>,
 }
 
 #[tonic::async_trait]
@@ -58,9 +78,8 @@ impl pulumirpc::resource_provider_server::ResourceProvider for ComponentProvider
         &self,
         _request: Request<pulumirpc::GetSchemaRequest>,
     ) -> Result<Response<pulumirpc::GetSchemaResponse>, Status> {
-        Ok(Response::new(pulumirpc::GetSchemaResponse {
-            schema: self.schema_json.clone(),
-        }))
+        let schema = self.schema.get().map_err(Status::internal)?;
+        Ok(Response::new(pulumirpc::GetSchemaResponse { schema }))
     }
 
     async fn configure(
@@ -102,12 +121,29 @@ impl pulumirpc::resource_provider_server::ResourceProvider for ComponentProvider
                 ))
             })?;
 
+        let mut decl_diags = Diagnostics::new();
+        check_component_names(component, &mut decl_diags);
+        check_component_interface(component, &mut decl_diags);
+        check_component_cycles(&self.template, &mut decl_diags);
+        if decl_diags.has_errors() {
+            return Err(Status::invalid_argument(decl_diags.to_string()));
+        }
+        check_parent_chain(&req.r#type, &req.parent, &self.constructing.lock().unwrap())?;
+
         // Connect gRPC clients for inner resource registration
         let callback = GrpcCallback::connect(&self.monitor_address, &self.engine_address)
             .await
             .map_err(|e| Status::internal(format!("failed to connect: {}", e)))?;
 
         // Register the component resource itself (custom=false, remote=false)
+        // with the options the caller gave it.
+        let options = ResolvedResourceOptions {
+            parent_urn: Some(req.parent.clone()).filter(|p| !p.is_empty()),
+            depends_on: req.dependencies.clone(),
+            protect: req.protect.unwrap_or(false),
+            providers: req.providers.clone(),
+            ..Default::default()
+        };
         let comp_resp = callback
             .register_resource(
                 &req.r#type,
@@ -115,11 +151,12 @@ impl pulumirpc::resource_provider_server::ResourceProvider for ComponentProvider
                 false,
                 false,
                 HashMap::new(),
-                Default::default(),
+                options,
             )
             .map_err(|e| Status::internal(format!("failed to register component: {}", e)))?;
 
         let component_urn = comp_resp.urn.clone();
+        let _constructing = Constructing::start(&self.constructing, &component_urn);
 
         // Build a synthetic TemplateDecl from the component's body
         let synthetic = TemplateDecl {
@@ -134,31 +171,50 @@ impl pulumirpc::resource_provider_server::ResourceProvider for ComponentProvider
             outputs: component.component.outputs.clone(),
             components: Vec::new(),
             starlark_functions: Vec::new(),
+            imports: Vec::new(),
+            extends: None,
+            runtime: None,
+            plugins: Vec::new(),
         };
 
-        // Leak the synthetic template so it has 'static lifetime
-        let synthetic: &'static _ = Box::leak(Box::new(synthetic));
-
-        // Create evaluator for the component body
-        let mut eval = Evaluator::with_callback(
-            self.project.clone(),
-            self.stack.clone(),
-            std::env::current_dir()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string(),
-            self.dry_run,
-            callback,
-        );
-
-        // Set component parent so inner resources inherit this component as parent
+        // Create evaluator for the component body, in the caller's project
+        // and stack
+        let project = if req.project.is_empty() {
+            self.project.clone()
+        } else {
+            req.project.clone()
+        };
+        let stack = if req.stack.is_empty() {
+            self.stack.clone()
+        } else {
+            req.stack.clone()
+        };
+        let context = EvaluatorContext::builder(project.clone(), stack)
+            .cwd(self.program_directory.clone())
+            .root_directory(self.program_directory.clone())
+            .organization(req.organization.clone())
+            .dry_run(self.dry_run || req.dry_run)
+            .build()
+            .map_err(|e| Status::internal(e.to_string()))?;
+        let mut eval = self.evaluators.create(&context, callback);
+
+        // Inner resources are children of the component and inherit its
+        // providers, dependencies and protection
         eval.component_parent_urn = Some(component_urn.clone());
+        eval.component_providers = req.providers.clone();
+        eval.component_depends_on = req.dependencies.clone();
+        eval.component_protect = req.protect.unwrap_or(false);
+
+        // Components of this template used in the body are registered as
+        // remote components, which the engine constructs through this
+        // provider in turn
+        eval.local_components = component_type_tokens(&self.template);
 
         // Convert construct inputs to raw config strings for the evaluator
-        let raw_config = convert_construct_inputs(&req);
+        let raw_config = convert_construct_inputs(&req, &project);
 
         // Evaluate the component body
-        eval.evaluate_template(synthetic, &raw_config, &[]);
+        eval.evaluate_template(&synthetic, &raw_config, &req.config_secret_keys);
 
         if eval.has_errors() {
             let errors = eval.diag_errors();
@@ -182,23 +238,51 @@ impl pulumirpc::resource_provider_server::ResourceProvider for ComponentProvider
                 Status::internal(format!("failed to register component outputs: {}", e))
             })?;
 
-        // Convert outputs to protobuf
+        // Convert outputs to protobuf. Every output depends on the component
+        // itself and on the inner resources it references; callers that accept
+        // output values get those dependencies inline, others through
+        // `state_dependencies`.
+        let mut output_deps = eval.output_dependencies(&synthetic);
+        self.evaluators.recycle(eval);
+        let output_deps: HashMap<String, Vec<String>> = output_values
+            .keys()
+            .map(|k| {
+                let mut urns = vec![component_urn.clone()];
+                urns.extend(output_deps.remove(k).unwrap_or_default());
+                (k.clone(), urns)
+            })
+            .collect();
         let state_fields: std::collections::BTreeMap<String, prost_types::Value> = output_values
             .iter()
             .map(|(k, v)| {
-                (
-                    k.clone(),
-                    pulumi_rs_yaml_core::eval::protobuf::value_to_protobuf(v),
-                )
+                let pv = if req.accepts_output_values {
+                    value_to_output_protobuf(v, &output_deps[k])
+                } else {
+                    value_to_protobuf(v)
+                };
+                (k.clone(), pv)
             })
             .collect();
+        let state_dependencies = if req.accepts_output_values {
+            HashMap::new()
+        } else {
+            output_deps
+                .into_iter()
+                .map(|(k, urns)| {
+                    (
+                        k,
+                        pulumirpc::construct_response::PropertyDependencies { urns },
+                    )
+                })
+                .collect()
+        };
 
         Ok(Response::new(pulumirpc::ConstructResponse {
             urn: component_urn,
             state: Some(prost_types::Struct {
                 fields: state_fields,
             }),
-            state_dependencies: HashMap::new(),
+            state_dependencies,
         }))
     }
 
@@ -314,11 +398,135 @@ impl pulumirpc::resource_provider_server::ResourceProvider for ComponentProvider
     }
 }
 
+/// The JSON package schema generated from a program's component
+/// declarations.
+///
+/// The schema is cached together with a hash of the project files and
+/// regenerated when any of them changes, so a long-running provider serves
+/// the schema of the template as it is on disk.
+pub struct ComponentSchema {
+    directory: PathBuf,
+    cached: Mutex<Option<(String, String)>>,
+}
+
+impl ComponentSchema {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// Returns the schema JSON, regenerating it if the project files changed
+    /// since the last call.
+    pub fn get(&self) -> Result<String, String> {
+        let sources = multi_file::load_project_sources(&self.directory)?;
+        let mut contents = Vec::new();
+        for (filename, source) in &sources {
+            contents.extend_from_slice(filename.as_bytes());
+            contents.push(0);
+            contents.extend_from_slice(source.as_bytes());
+            contents.push(0);
+        }
+        let fingerprint = sha256_hex(&contents);
+
+        let mut cached = self.cached.lock().unwrap();
+        if let Some((hash, schema)) = cached.as_ref() {
+            if *hash == fingerprint {
+                return Ok(schema.clone());
+            }
+        }
+
+        let (merged, load_diags) = multi_file::load_project(&self.directory, None);
+        if let Some(diag) = load_diags.iter().find(|d| d.is_error()) {
+            return Err(format!(
+                "failed to load component template: {}",
+                diag.summary
+            ));
+        }
+        let schema = generate_component_schema(&merged.as_template_decl());
+        let schema = serde_json::to_string(&schema)
+            .map_err(|e| format!("schema serialization failed: {}", e))?;
+        *cached = Some((fingerprint, schema.clone()));
+        Ok(schema)
+    }
+}
+
+/// Rejects constructing a component of type `type_token` from inside the body
+/// of an instance of the same type that is still being evaluated. The types
+/// of a resource's ancestors are part of its URN
+/// (`urn:pulumi:stack::project::Outer$Inner::name`), so this catches
+/// instantiation cycles that reach the provider at runtime, such as ones
+/// through components of other packages. A finished instance may be the
+/// parent of another instance of its type.
+fn check_parent_chain(
+    type_token: &str,
+    parent: &str,
+    constructing: &HashSet<String>,
+) -> Result<(), Status> {
+    let Some(qualified_type) = parent.split("::").nth(2) else {
+        return Ok(());
+    };
+    let inside_body = constructing.iter().any(|urn| {
+        let Some(outer) = urn.split("::").nth(2) else {
+            return false;
+        };
+        outer.rsplit('$').next() == Some(type_token)
+            && (urn == parent
+                || qualified_type
+                    .strip_prefix(outer)
+                    .is_some_and(|rest| rest.starts_with('$')))
+    });
+    if !inside_body {
+        return Ok(());
+    }
+    let chain: Vec<&str> = qualified_type.split('$').collect();
+    match chain.iter().position(|t| *t == type_token) {
+        Some(start) => Err(Status::invalid_argument(format!(
+            "component {} instantiates itself: {} -> {}",
+            type_token,
+            chain[start..].join(" -> "),
+            type_token
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Marks a component as being constructed until dropped.
+struct Constructing<'a> {
+    set: &'a Mutex<HashSet<String>>,
+    urn: String,
+}
+
+impl<'a> Constructing<'a> {
+    fn start(set: &'a Mutex<HashSet<String>>, urn: &str) -> Self {
+        set.lock().unwrap().insert(urn.to_string());
+        Constructing {
+            set,
+            urn: urn.to_string(),
+        }
+    }
+}
+
+impl Drop for Constructing<'_> {
+    fn drop(&mut self) {
+        self.set.lock().unwrap().remove(&self.urn);
+    }
+}
+
 /// Converts ConstructRequest inputs to raw config strings for the evaluator.
-fn convert_construct_inputs(req: &pulumirpc::ConstructRequest) -> HashMap<String, String> {
-    let mut config = HashMap::new();
+///
+/// The stack config is included so inputs the caller did not pass fall back
+/// to it. Inputs are keyed by `<project>:<name>`, which takes precedence over
+/// the bare key during config resolution.
+fn convert_construct_inputs(
+    req: &pulumirpc::ConstructRequest,
+    project: &str,
+) -> HashMap<String, String> {
+    let mut config = req.config.clone();
     if let Some(ref inputs) = req.inputs {
         for (k, v) in &inputs.fields {
+            let k = &format!("{}:{}", project, k);
             let eval_val = protobuf_to_value(v.clone());
             match &eval_val {
                 Value::String(s) => {
@@ -339,3 +547,250 @@ fn convert_construct_inputs(req: &pulumirpc::ConstructRequest) -> HashMap<String
     }
     config
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMPONENT: &str = r#"
+name: pkg
+runtime: yaml
+components:
+  Bucket:
+    description: A storage bucket.
+    inputs:
+      prefix:
+        type: string
+        description: Name prefix for the bucket.
+        default: logs
+      replicas:
+        type: integer
+        default: 2
+    outputs:
+      name: ${prefix}
+"#;
+
+    #[test]
+    fn test_component_schema_regenerates_on_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Pulumi.yaml");
+        std::fs::write(&path, COMPONENT).unwrap();
+
+        let cache = ComponentSchema::new(dir.path());
+        let schema: serde_json::Value = serde_json::from_str(&cache.get().unwrap()).unwrap();
+        let bucket = &schema["resources"]["pkg:index:Bucket"];
+        assert_eq!(bucket["description"], "A storage bucket.");
+        let prefix = &bucket["inputProperties"]["prefix"];
+        assert_eq!(prefix["description"], "Name prefix for the bucket.");
+        assert_eq!(prefix["default"], "logs");
+        assert_eq!(bucket["inputProperties"]["replicas"]["default"], 2);
+        assert!(bucket.get("requiredInputs").is_none());
+
+        // Unchanged files are served from the cache.
+        let cached = cache.cached.lock().unwrap().clone();
+        cache.get().unwrap();
+        assert_eq!(*cache.cached.lock().unwrap(), cached);
+
+        std::fs::write(&path, COMPONENT.replace("Bucket:", "Queue:")).unwrap();
+        let schema: serde_json::Value = serde_json::from_str(&cache.get().unwrap()).unwrap();
+        let resources = schema["resources"].as_object().unwrap();
+        assert!(resources.contains_key("pkg:index:Queue"));
+        assert!(!resources.contains_key("pkg:index:Bucket"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_construct_propagates_options_and_output_dependencies() {
+        use pulumi_rs_yaml_proto::pulumirpc::resource_provider_server::ResourceProvider;
+
+        let source = r#"
+name: pkg
+runtime: yaml
+components:
+  Site:
+    inputs:
+      region:
+        type: string
+      index:
+        type: string
+    variables:
+      bucketName: ${bucket.bucket}
+    resources:
+      bucket:
+        type: aws:s3:Bucket
+        properties:
+          bucket: site-${region}
+      page:
+        type: aws:s3:BucketObject
+        properties:
+          key: ${index}
+    outputs:
+      bucketName: ${bucketName}
+      region: ${region}
+"#;
+        let (template, diags) = pulumi_rs_yaml_core::ast::parse::parse_template(source, None);
+        assert!(!diags.has_errors());
+        let engine = pulumi_rs_yaml_mock_engine::MockEngine::start().await;
+        let provider = ComponentProvider {
+            engine_address: engine.engine_address.clone(),
+            monitor_address: engine.monitor_address.clone(),
+            template: Arc::new(template),
+            schema: ComponentSchema::new("."),
+            project: String::new(),
+            stack: String::new(),
+            dry_run: false,
+            program_directory: ".".to_string(),
+            evaluators: EvaluatorFactory::new(),
+            constructing: Default::default(),
+        };
+
+        let provider_ref = "urn:pulumi:dev::proj::pulumi:providers:aws::east::east-id".to_string();
+        let dependency = "urn:pulumi:dev::proj::test:index:Network::net".to_string();
+        let request = pulumirpc::ConstructRequest {
+            project: "proj".to_string(),
+            stack: "dev".to_string(),
+            r#type: "pkg:index:Site".to_string(),
+            name: "site".to_string(),
+            config: HashMap::from([("proj:region".to_string(), "us-east-1".to_string())]),
+            inputs: Some(prost_types::Struct {
+                fields: [(
+                    "index".to_string(),
+                    value_to_protobuf(&Value::String("index.html".into())),
+                )]
+                .into(),
+            }),
+            providers: HashMap::from([("aws".to_string(), provider_ref.clone())]),
+            dependencies: vec![dependency.clone()],
+            protect: Some(true),
+            ..Default::default()
+        };
+        let response = provider
+            .construct(Request::new(request))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let component_urn = "urn:pulumi:dev::proj::pkg:index:Site::site";
+        assert_eq!(response.urn, component_urn);
+        let recorded = engine.recorded();
+        let site = &recorded.registrations[0];
+        assert_eq!(site.providers["aws"], provider_ref);
+        assert_eq!(site.protect, Some(true));
+        assert_eq!(site.dependencies, vec![dependency.clone()]);
+
+        let bucket = recorded
+            .registrations
+            .iter()
+            .find(|r| r.name == "bucket")
+            .unwrap();
+        assert_eq!(bucket.parent, component_urn);
+        assert_eq!(bucket.provider, provider_ref);
+        assert_eq!(bucket.protect, Some(true));
+        assert!(bucket.dependencies.contains(&dependency));
+        let region = bucket.object.as_ref().unwrap().fields["bucket"].clone();
+        assert_eq!(
+            protobuf_to_value(region),
+            Value::String("site-us-east-1".into())
+        );
+
+        let deps = &response.state_dependencies;
+        assert_eq!(
+            deps["bucketName"].urns,
+            vec![
+                component_urn.to_string(),
+                "urn:pulumi:dev::proj::aws:s3/bucket:Bucket::bucket".to_string()
+            ]
+        );
+        assert_eq!(deps["region"].urns, vec![component_urn.to_string()]);
+    }
+
+    #[test]
+    fn test_check_parent_chain() {
+        let site = "urn:pulumi:dev::proj::pulumi:pulumi:Stack$pkg:index:Site::a";
+        let page = "urn:pulumi:dev::proj::pulumi:pulumi:Stack$pkg:index:Site$pkg:index:Page::p";
+        let idle = HashSet::new();
+        assert!(check_parent_chain("pkg:index:Site", "", &idle).is_ok());
+        // Another instance of the same component may be the parent
+        assert!(check_parent_chain("pkg:index:Site", site, &idle).is_ok());
+        assert!(check_parent_chain("pkg:index:Site", page, &idle).is_ok());
+
+        let constructing = HashSet::from([site.to_string()]);
+        assert!(check_parent_chain("pkg:index:Bucket", page, &constructing).is_ok());
+        let other = "urn:pulumi:dev::proj::pulumi:pulumi:Stack$pkg:index:Site::b";
+        assert!(check_parent_chain("pkg:index:Site", other, &constructing).is_ok());
+        let err = check_parent_chain("pkg:index:Site", site, &constructing).unwrap_err();
+        assert_eq!(
+            err.message(),
+            "component pkg:index:Site instantiates itself: pkg:index:Site -> pkg:index:Site"
+        );
+        let err = check_parent_chain("pkg:index:Site", page, &constructing).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+        assert_eq!(
+            err.message(),
+            "component pkg:index:Site instantiates itself: pkg:index:Site -> pkg:index:Page -> pkg:index:Site"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_construct_registers_nested_components_as_remote() {
+        use pulumi_rs_yaml_proto::pulumirpc::resource_provider_server::ResourceProvider;
+
+        let source = r#"
+name: pkg
+runtime: yaml
+components:
+  Site:
+    resources:
+      assets:
+        type: pkg:Bucket
+      bucket:
+        type: aws:s3:Bucket
+    outputs:
+      assetsUrn: ${assets.urn}
+  Bucket:
+    resources:
+      bucket:
+        type: aws:s3:Bucket
+"#;
+        let (template, diags) = pulumi_rs_yaml_core::ast::parse::parse_template(source, None);
+        assert!(!diags.has_errors());
+        let engine = pulumi_rs_yaml_mock_engine::MockEngine::start().await;
+        let provider = ComponentProvider {
+            engine_address: engine.engine_address.clone(),
+            monitor_address: engine.monitor_address.clone(),
+            template: Arc::new(template),
+            schema: ComponentSchema::new("."),
+            project: String::new(),
+            stack: String::new(),
+            dry_run: false,
+            program_directory: ".".to_string(),
+            evaluators: EvaluatorFactory::new(),
+            constructing: Default::default(),
+        };
+
+        let request = pulumirpc::ConstructRequest {
+            project: "proj".to_string(),
+            stack: "dev".to_string(),
+            r#type: "pkg:index:Site".to_string(),
+            name: "site".to_string(),
+            ..Default::default()
+        };
+        provider.construct(Request::new(request)).await.unwrap();
+
+        let recorded = engine.recorded();
+        let find = |name: &str| {
+            recorded
+                .registrations
+                .iter()
+                .find(|r| r.name == name)
+                .unwrap()
+        };
+        let assets = find("assets");
+        assert_eq!(assets.r#type, "pkg:index:Bucket");
+        assert!(assets.remote);
+        assert!(!assets.custom);
+        assert_eq!(assets.parent, "urn:pulumi:dev::proj::pkg:index:Site::site");
+        let bucket = find("bucket");
+        assert!(!bucket.remote);
+        assert!(bucket.custom);
+    }
+}
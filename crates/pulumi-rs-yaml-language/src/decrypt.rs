@@ -0,0 +1,133 @@
+//! Decrypts `fn::encrypted` values through Pulumi Cloud.
+//!
+//! The ciphertext is the one `pulumi config set --secret` stores for a stack
+//! using the Pulumi Cloud secrets provider, so it is decrypted by the same
+//! stack decryption endpoint the CLI uses for stack config. Stacks using a
+//! passphrase or cloud KMS secrets provider cannot decrypt through it.
+//!
+//! The engine gives language hosts no way to decrypt with the stack's
+//! secrets manager — it only passes them stack config already decrypted —
+//! so the host asks the backend directly. The backend and access token are
+//! found once per run, as for ESC (see [`crate::esc`]), and each distinct
+//! ciphertext is decrypted once: values repeated across `range` instances
+//! or component instances reuse the cached plaintext.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use base64::Engine;
+use pulumi_rs_yaml_core::eval::encrypted::Decrypter;
+use pulumi_rs_yaml_core::packages::pulumi_home;
+
+use crate::esc::{read_json, request_error, Login};
+
+/// Decrypts values with the secrets provider of one Pulumi Cloud stack.
+pub struct CloudDecrypter {
+    organization: String,
+    project: String,
+    stack: String,
+    /// The backend login, discovered on first use.
+    login: OnceLock<Result<Login, String>>,
+    /// Keeps the connection to the backend open between values.
+    agent: ureq::Agent,
+    /// Plaintexts by trimmed ciphertext.
+    plaintexts: Mutex<HashMap<String, String>>,
+}
+
+impl CloudDecrypter {
+    pub fn new(organization: &str, project: &str, stack: &str) -> Self {
+        Self {
+            organization: organization.to_string(),
+            project: project.to_string(),
+            stack: stack.to_string(),
+            login: OnceLock::new(),
+            agent: ureq::Agent::new(),
+            plaintexts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn url(&self, backend: &str) -> String {
+        format!(
+            "{}/api/stacks/{}/{}/{}/decrypt",
+            backend, self.organization, self.project, self.stack
+        )
+    }
+}
+
+impl Decrypter for CloudDecrypter {
+    fn decrypt(&self, ciphertext: &str) -> Result<String, String> {
+        if self.organization.is_empty() {
+            return Err("the stack has no organization to decrypt it in".to_string());
+        }
+        let ciphertext = ciphertext.trim();
+        if let Some(plaintext) = self.plaintexts.lock().unwrap().get(ciphertext) {
+            return Ok(plaintext.clone());
+        }
+        let login = self
+            .login
+            .get_or_init(|| {
+                Login::discover(&pulumi_home().join("credentials.json"), "fn::encrypted")
+            })
+            .as_ref()
+            .map_err(Clone::clone)?;
+        let response = self
+            .agent
+            .post(&self.url(&login.backend))
+            .set("Authorization", &format!("token {}", login.token))
+            .set("Accept", "application/vnd.pulumi+8")
+            .set("Content-Type", "application/json")
+            .send_string(&serde_json::json!({ "ciphertext": ciphertext }).to_string())
+            .map_err(request_error)?;
+        let plaintext = plaintext(&read_json(response)?)?;
+        self.plaintexts
+            .lock()
+            .unwrap()
+            .insert(ciphertext.to_string(), plaintext.clone());
+        Ok(plaintext)
+    }
+}
+
+/// Decodes the base64 plaintext of a decryption response.
+fn plaintext(response: &serde_json::Value) -> Result<String, String> {
+    let encoded = response["plaintext"]
+        .as_str()
+        .ok_or("Pulumi Cloud returned no plaintext")?;
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| format!("invalid plaintext from Pulumi Cloud: {}", e))?;
+    String::from_utf8(bytes).map_err(|_| "the decrypted value is not UTF-8".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_names_the_stack() {
+        let decrypter = CloudDecrypter::new("acme", "web", "prod");
+        assert_eq!(
+            decrypter.url("https://api.pulumi.com"),
+            "https://api.pulumi.com/api/stacks/acme/web/prod/decrypt"
+        );
+    }
+
+    #[test]
+    fn test_decrypted_values_are_cached() {
+        let decrypter = CloudDecrypter::new("acme", "web", "prod");
+        decrypter
+            .plaintexts
+            .lock()
+            .unwrap()
+            .insert("AAAB".to_string(), "hunter2".to_string());
+        // Served from the cache without discovering a login.
+        assert_eq!(decrypter.decrypt(" AAAB\n").unwrap(), "hunter2");
+        assert!(decrypter.login.get().is_none());
+    }
+
+    #[test]
+    fn test_plaintext_is_base64_decoded() {
+        let response = serde_json::json!({ "plaintext": "aHVudGVyMg==" });
+        assert_eq!(plaintext(&response).unwrap(), "hunter2");
+        assert!(plaintext(&serde_json::json!({})).is_err());
+    }
+}
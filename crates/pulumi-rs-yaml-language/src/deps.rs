@@ -0,0 +1,93 @@
+//! The `deps` subcommand.
+//!
+//! `pulumi-language-yaml deps [--strict] [<project-dir>]` prints the
+//! dependencies between entries declared in different project files (default:
+//! the current directory), with a warning under each suspicious one. With
+//! `--strict` any warning fails the command.
+
+use std::path::Path;
+
+use pulumi_rs_yaml_core::multi_file;
+use pulumi_rs_yaml_core::plan::{CrossFileEdge, ExecutionPlan};
+
+/// Runs the `deps` subcommand and returns the process exit code.
+pub fn run_deps(args: &[String]) -> i32 {
+    let strict = args.iter().any(|a| a == "--strict");
+    let dir = args
+        .iter()
+        .find(|a| !a.starts_with("--"))
+        .map_or(".", |a| a.as_str());
+    match cross_file_edges(Path::new(dir)) {
+        Ok(edges) => {
+            print!("{}", render(&edges));
+            let warnings = edges.iter().filter(|e| e.warning.is_some()).count();
+            if strict && warnings > 0 {
+                eprintln!("error: {} suspicious cross-file dependencies", warnings);
+                1
+            } else {
+                0
+            }
+        }
+        Err(message) => {
+            eprintln!("error: {}", message);
+            1
+        }
+    }
+}
+
+fn cross_file_edges(dir: &Path) -> Result<Vec<CrossFileEdge>, String> {
+    let (merged, diags) = multi_file::load_project(dir, None);
+    if diags.has_errors() {
+        return Err(format!("failed to load project: {}", diags));
+    }
+    let plan = ExecutionPlan::build(&merged, &diags)
+        .map_err(|diags| format!("invalid dependency graph: {}", diags))?;
+    Ok(plan.cross_file_edges)
+}
+
+/// Renders one `file:name -> file:name` line per edge.
+fn render(edges: &[CrossFileEdge]) -> String {
+    if edges.is_empty() {
+        return "no cross-file dependencies\n".to_string();
+    }
+    let mut out = String::new();
+    for edge in edges {
+        out.push_str(&format!(
+            "{}:{} -> {}:{}\n",
+            edge.from_file, edge.from, edge.to_file, edge.to
+        ));
+        if let Some(warning) = &edge.warning {
+            out.push_str(&format!("  warning: {}\n", warning));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_cross_file_edges() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Pulumi.yaml"),
+            "name: deps-test\nruntime: yaml\noutputs:\n  id: ${bucket.id}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("Pulumi.storage.yaml"),
+            "resources:\n  bucket:\n    type: aws:s3:Bucket\n",
+        )
+        .unwrap();
+
+        let edges = cross_file_edges(dir.path()).unwrap();
+        assert_eq!(
+            render(&edges),
+            "Pulumi.yaml:id -> Pulumi.storage.yaml:bucket\n  \
+             warning: Pulumi.yaml depends on Pulumi.storage.yaml, which is layered on top of it; \
+             move 'bucket' into Pulumi.yaml or 'id' out of it\n"
+        );
+        assert_eq!(render(&[]), "no cross-file dependencies\n");
+    }
+}
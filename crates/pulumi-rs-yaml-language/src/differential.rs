@@ -0,0 +1,292 @@
+//! Differential testing against the Go `pulumi-language-yaml`.
+//!
+//! Opt-in: set `PULUMI_YAML_DIFFERENTIAL=1` and run
+//! `cargo test -p pulumi-rs-yaml-language differential`. Every program in the
+//! golden corpus (`pulumi-rs-yaml-testing/corpus`) is previewed twice, once by
+//! this language host and once by the Go host, each against its own recording
+//! [`MockEngine`]. The two registration streams are normalized and diffed, and
+//! the result is written as a Markdown compatibility report to
+//! `PULUMI_YAML_DIFF_REPORT` (default `target/differential-report.md`).
+//!
+//! The Go host is taken from `PULUMI_YAML_GO_HOST`, or else the first
+//! `pulumi-language-yaml` on `PATH` (the one bundled with the Pulumi CLI).
+//! Differences are reported, not failed on; the test fails only if a host
+//! cannot run at all.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use pulumi_rs_yaml_core::eval::context::EvaluatorContext;
+use pulumi_rs_yaml_core::eval::protobuf::protobuf_to_value;
+use pulumi_rs_yaml_mock_engine::{MockEngine, Recorded};
+use pulumi_rs_yaml_proto::pulumirpc;
+use pulumi_rs_yaml_testing::{corpus_dir, corpus_entries, render_value};
+
+use crate::exec::tests::TempCwd;
+use crate::runner;
+
+/// Enables the differential test.
+const ENABLE_ENV: &str = "PULUMI_YAML_DIFFERENTIAL";
+/// Path of the Go language host, overriding the `PATH` lookup.
+const GO_HOST_ENV: &str = "PULUMI_YAML_GO_HOST";
+/// Output path of the compatibility report.
+const REPORT_ENV: &str = "PULUMI_YAML_DIFF_REPORT";
+
+/// Features both hosts are told the monitor supports, so secrets and
+/// resource references are marshaled the same way.
+const FEATURES: &[&str] = &["secrets", "resourceReferences"];
+
+/// A registration stream reduced to comparable lines, keyed by
+/// `<type> <name>`. Stack outputs are keyed by `outputs <urn>`.
+type Stream = BTreeMap<String, Vec<String>>;
+
+fn find_go_host() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(GO_HOST_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let exe = format!("pulumi-language-yaml{}", std::env::consts::EXE_SUFFIX);
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(&exe))
+        .find(|p| p.is_file())
+}
+
+fn normalize(recorded: &Recorded) -> Stream {
+    let mut stream = Stream::new();
+    for req in &recorded.registrations {
+        let mut lines = vec![format!("custom: {}", req.custom)];
+        if !req.parent.is_empty() {
+            lines.push(format!("parent: {}", req.parent));
+        }
+        if !req.provider.is_empty() {
+            lines.push(format!("provider: {}", req.provider));
+        }
+        if let Some(object) = &req.object {
+            let inputs: BTreeMap<_, _> = object.fields.iter().collect();
+            for (k, v) in inputs {
+                lines.push(format!(
+                    "input {}: {}",
+                    k,
+                    render_value(&protobuf_to_value(v.clone()))
+                ));
+            }
+        }
+        let mut deps = req.dependencies.clone();
+        deps.sort();
+        if !deps.is_empty() {
+            lines.push(format!("dependsOn: {}", deps.join(", ")));
+        }
+        let mut secrets = req.additional_secret_outputs.clone();
+        secrets.sort();
+        if !secrets.is_empty() {
+            lines.push(format!("additionalSecretOutputs: {}", secrets.join(", ")));
+        }
+        stream.insert(format!("{} {}", req.r#type, req.name), lines);
+    }
+    for req in &recorded.outputs {
+        let outputs: BTreeMap<_, _> = req
+            .outputs
+            .iter()
+            .flat_map(|s| s.fields.iter())
+            .map(|(k, v)| (k, render_value(&protobuf_to_value(v.clone()))))
+            .collect();
+        let lines = outputs
+            .into_iter()
+            .map(|(k, v)| format!("{}: {}", k, v))
+            .collect();
+        stream.insert(format!("outputs {}", req.urn), lines);
+    }
+    stream
+}
+
+/// Returns human-readable differences between the two streams.
+fn diff(ours: &Stream, theirs: &Stream) -> Vec<String> {
+    let mut out = Vec::new();
+    for (key, lines) in ours {
+        match theirs.get(key) {
+            None => out.push(format!("only in rust: `{}`", key)),
+            Some(other) => {
+                for line in lines.iter().filter(|l| !other.contains(l)) {
+                    out.push(format!("`{}`: rust has `{}`", key, line));
+                }
+                for line in other.iter().filter(|l| !lines.contains(l)) {
+                    out.push(format!("`{}`: go has `{}`", key, line));
+                }
+            }
+        }
+    }
+    for key in theirs.keys().filter(|k| !ours.contains_key(*k)) {
+        out.push(format!("only in go: `{}`", key));
+    }
+    out
+}
+
+async fn run_ours(dir: &Path, project: &str) -> Result<Stream, String> {
+    let engine = MockEngine::start_with_features(FEATURES).await;
+    let program_directory = dir.to_str().ok_or("non-UTF-8 corpus path")?;
+    let _cwd = TempCwd::new(dir);
+    let context = EvaluatorContext::builder(project, "dev")
+        .cwd(program_directory)
+        .root_directory(program_directory)
+        .organization("")
+        .dry_run(true)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let result = runner::run(
+        &context,
+        &engine.monitor_address,
+        &engine.engine_address,
+        &Default::default(),
+        &[],
+        program_directory,
+        None,
+        0,
+        &Default::default(),
+    )
+    .await;
+    if !result.error.is_empty() {
+        return Err(result.error);
+    }
+    let stream = normalize(&engine.recorded());
+    Ok(stream)
+}
+
+async fn run_go(host: &Path, dir: &Path, project: &str) -> Result<Stream, String> {
+    let engine = MockEngine::start_with_features(FEATURES).await;
+    let mut child = Command::new(host)
+        .arg(&engine.engine_address)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|e| format!("failed to start {}: {}", host.display(), e))?;
+
+    // The host prints its port on the first line of stdout.
+    let stdout = child.stdout.take().ok_or("no stdout")?;
+    let mut port = String::new();
+    tokio::task::block_in_place(|| BufReader::new(stdout).read_line(&mut port))
+        .map_err(|e| format!("failed to read port: {}", e))?;
+
+    let result = async {
+        let url = format!("http://127.0.0.1:{}", port.trim());
+        let mut client = pulumirpc::language_runtime_client::LanguageRuntimeClient::connect(url)
+            .await
+            .map_err(|e| format!("failed to connect: {}", e))?;
+        let program_directory = dir.to_string_lossy().into_owned();
+        let resp = client
+            .run(pulumirpc::RunRequest {
+                project: project.to_string(),
+                stack: "dev".to_string(),
+                pwd: program_directory.clone(),
+                dry_run: true,
+                monitor_address: engine.monitor_address.clone(),
+                info: Some(pulumirpc::ProgramInfo {
+                    root_directory: program_directory.clone(),
+                    program_directory,
+                    entry_point: ".".to_string(),
+                    options: None,
+                }),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| e.message().to_string())?
+            .into_inner();
+        if !resp.error.is_empty() {
+            return Err(resp.error);
+        }
+        Ok(normalize(&engine.recorded()))
+    }
+    .await;
+    let _ = child.kill();
+    let _ = child.wait();
+    result
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_differential_against_go_host() {
+    if std::env::var(ENABLE_ENV).is_err() {
+        return;
+    }
+    let Some(host) = find_go_host() else {
+        eprintln!("differential: no Go pulumi-language-yaml found, skipping");
+        return;
+    };
+
+    let mut report = format!(
+        "# Differential report\n\nGo host: `{}`\n\n| Program | Result |\n|---|---|\n",
+        host.display()
+    );
+    let mut details = String::new();
+    let mut host_failures = Vec::new();
+    for name in corpus_entries().unwrap() {
+        let dir = corpus_dir().join(&name);
+        let ours = run_ours(&dir, &name).await;
+        let theirs = run_go(&host, &dir, &name).await;
+        let status = match (&ours, &theirs) {
+            (Ok(ours), Ok(theirs)) => {
+                let diffs = diff(ours, theirs);
+                if diffs.is_empty() {
+                    "identical".to_string()
+                } else {
+                    details.push_str(&format!("\n## {}\n\n", name));
+                    for d in &diffs {
+                        details.push_str(&format!("- {}\n", d));
+                    }
+                    format!("{} difference(s)", diffs.len())
+                }
+            }
+            (Err(e), _) => {
+                host_failures.push(format!("{} (rust): {}", name, e));
+                format!("rust failed: {}", e)
+            }
+            (_, Err(e)) => format!("go failed: {}", e.replace('\n', " ")),
+        };
+        report.push_str(&format!("| {} | {} |\n", name, status));
+    }
+    report.push_str(&details);
+
+    let path = std::env::var_os(REPORT_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("../../target/differential-report.md")
+        });
+    std::fs::write(&path, &report).unwrap();
+    eprintln!("differential: report written to {}", path.display());
+
+    assert!(host_failures.is_empty(), "{:#?}", host_failures);
+}
+
+fn stream(entries: &[(&str, &[&str])]) -> Stream {
+    entries
+        .iter()
+        .map(|(k, lines)| (k.to_string(), lines.iter().map(|l| l.to_string()).collect()))
+        .collect()
+}
+
+#[test]
+fn test_diff_streams() {
+    let ours = stream(&[
+        (
+            "aws:s3/bucket:Bucket b",
+            &["custom: true", "input acl: \"private\""],
+        ),
+        ("random:index/randomPet:RandomPet p", &["custom: true"]),
+    ]);
+    let theirs = stream(&[
+        (
+            "aws:s3/bucket:Bucket b",
+            &["custom: true", "input acl: \"public\""],
+        ),
+        ("kubernetes:core/v1:Service s", &["custom: true"]),
+    ]);
+    assert_eq!(
+        diff(&ours, &theirs),
+        vec![
+            "`aws:s3/bucket:Bucket b`: rust has `input acl: \"private\"`",
+            "`aws:s3/bucket:Bucket b`: go has `input acl: \"public\"`",
+            "only in rust: `random:index/randomPet:RandomPet p`",
+            "only in go: `kubernetes:core/v1:Service s`",
+        ]
+    );
+    assert!(diff(&ours, &ours).is_empty());
+}
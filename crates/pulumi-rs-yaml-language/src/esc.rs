@@ -0,0 +1,339 @@
+//! Opens `fn::open::<provider>` calls through Pulumi Cloud.
+//!
+//! Each call is opened as an anonymous ESC environment in the stack's
+//! organization whose only value is the call, the same way the CLI opens
+//! environment definitions written inline in stack config. The provider runs
+//! in Pulumi Cloud, so every provider ESC supports works unchanged, with its
+//! secret outputs kept secret.
+//!
+//! The backend and access token are those of the CLI: `PULUMI_BACKEND_URL`
+//! and `PULUMI_ACCESS_TOKEN`, falling back to the current login in
+//! `~/.pulumi/credentials.json`. Nothing is read until the first call, and
+//! the login is found once per run. Each distinct call is opened once:
+//! calls repeated across `range` instances or component instances reuse the
+//! first result instead of opening another environment.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+use pulumi_rs_yaml_core::eval::esc::OpenResolver;
+use pulumi_rs_yaml_core::eval::value::Value;
+use pulumi_rs_yaml_core::packages::pulumi_home;
+
+/// Backend used when neither `PULUMI_BACKEND_URL` nor a login selects one.
+const DEFAULT_BACKEND: &str = "https://api.pulumi.com";
+
+/// How long the anonymous environments stay open.
+const OPEN_DURATION: &str = "2h";
+
+/// The definition key the call is opened under.
+const VALUE_KEY: &str = "value";
+
+/// Opens provider functions in `organization` on the current Pulumi Cloud
+/// backend.
+pub struct CloudOpenResolver {
+    organization: String,
+    /// The backend login, discovered on first use.
+    login: OnceLock<Result<Login, String>>,
+    /// Keeps the connection to the backend open between calls.
+    agent: ureq::Agent,
+    /// Opened values by environment definition.
+    opened: Mutex<HashMap<String, Value<'static>>>,
+}
+
+impl CloudOpenResolver {
+    pub fn new(organization: &str) -> Self {
+        Self {
+            organization: organization.to_string(),
+            login: OnceLock::new(),
+            agent: ureq::Agent::new(),
+            opened: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl OpenResolver for CloudOpenResolver {
+    fn open(&self, provider: &str, inputs: &Value<'static>) -> Result<Value<'static>, String> {
+        if self.organization.is_empty() {
+            return Err("the stack has no organization to open it in".to_string());
+        }
+        let definition = definition(provider, inputs)?;
+        if let Some(value) = self.opened.lock().unwrap().get(&definition) {
+            return Ok(value.clone());
+        }
+        let login = self
+            .login
+            .get_or_init(|| Login::discover(&pulumi_home().join("credentials.json"), "ESC"))
+            .as_ref()
+            .map_err(Clone::clone)?;
+
+        let base = format!(
+            "{}/api/esc/environments/{}/yaml/open",
+            login.backend, self.organization
+        );
+        let authorization = format!("token {}", login.token);
+        let opened = self
+            .agent
+            .post(&base)
+            .query("duration", OPEN_DURATION)
+            .set("Authorization", &authorization)
+            .set("Accept", "application/vnd.pulumi+8")
+            .set("Content-Type", "application/x-yaml")
+            .send_string(&definition)
+            .map_err(request_error)?;
+        let opened: serde_json::Value = read_json(opened)?;
+        let id = opened["id"]
+            .as_str()
+            .ok_or("Pulumi Cloud returned no open session id")?;
+
+        let environment = self
+            .agent
+            .get(&format!("{}/{}", base, id))
+            .set("Authorization", &authorization)
+            .set("Accept", "application/vnd.pulumi+8")
+            .call()
+            .map_err(request_error)?;
+        let environment: serde_json::Value = read_json(environment)?;
+        let value = environment["properties"]
+            .get(VALUE_KEY)
+            .ok_or("the opened environment has no value")?;
+        let value = esc_value(value);
+        self.opened
+            .lock()
+            .unwrap()
+            .insert(definition, value.clone());
+        Ok(value)
+    }
+}
+
+/// The backend and access token requests are made with.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Login {
+    pub(crate) backend: String,
+    pub(crate) token: String,
+}
+
+impl Login {
+    /// Selects the backend and token like the CLI does, reading the
+    /// credentials file only when the environment does not supply both.
+    /// `feature` names what needs Pulumi Cloud in the error for a
+    /// self-managed backend.
+    pub(crate) fn discover(credentials: &Path, feature: &str) -> Result<Login, String> {
+        Self::discover_with(credentials, feature, |name| std::env::var(name).ok())
+    }
+
+    /// [`Login::discover`] with `env` looking up environment variables.
+    fn discover_with(
+        credentials: &Path,
+        feature: &str,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Login, String> {
+        let env = |name: &str| env(name).filter(|v| !v.is_empty());
+        let stored: serde_json::Value = std::fs::read_to_string(credentials)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+
+        let backend = env("PULUMI_BACKEND_URL")
+            .or_else(|| stored["current"].as_str().map(str::to_string))
+            .unwrap_or_else(|| DEFAULT_BACKEND.to_string());
+        if !backend.starts_with("https://") && !backend.starts_with("http://") {
+            return Err(format!(
+                "{} requires a Pulumi Cloud backend, but the stack is stored in {}",
+                feature, backend
+            ));
+        }
+        let token = env("PULUMI_ACCESS_TOKEN")
+            .or_else(|| {
+                stored["accessTokens"][backend.as_str()]
+                    .as_str()
+                    .map(str::to_string)
+            })
+            .ok_or_else(|| {
+                format!(
+                    "not logged in to {}: set PULUMI_ACCESS_TOKEN or run `pulumi login`",
+                    backend
+                )
+            })?;
+        Ok(Login {
+            backend: api_url(&backend),
+            token,
+        })
+    }
+}
+
+/// Maps a console URL (`https://app.pulumi.com`) to its API URL.
+fn api_url(backend: &str) -> String {
+    backend
+        .trim_end_matches('/')
+        .replacen("://app.", "://api.", 1)
+}
+
+/// The anonymous environment holding the call.
+fn definition(provider: &str, inputs: &Value<'static>) -> Result<String, String> {
+    let call = serde_json::json!({ format!("fn::open::{}", provider): inputs.to_json() });
+    let definition = serde_json::json!({ "values": { VALUE_KEY: call } });
+    serde_yaml::to_string(&definition).map_err(|e| e.to_string())
+}
+
+/// Converts an ESC value (`{value, secret, unknown}`, with objects and
+/// arrays of nested ESC values) to an evaluator value.
+fn esc_value(v: &serde_json::Value) -> Value<'static> {
+    if v["unknown"].as_bool() == Some(true) {
+        return Value::Unknown;
+    }
+    let value = match &v["value"] {
+        serde_json::Value::Array(items) => Value::List(items.iter().map(esc_value).collect()),
+        serde_json::Value::Object(props) => Value::Object(
+            props
+                .iter()
+                .map(|(k, p)| (Cow::Owned(k.clone()), esc_value(p)))
+                .collect(),
+        ),
+        other => Value::from_json(other),
+    };
+    if v["secret"].as_bool() == Some(true) {
+        Value::Secret(Box::new(value))
+    } else {
+        value
+    }
+}
+
+pub(crate) fn read_json(response: ureq::Response) -> Result<serde_json::Value, String> {
+    let body = response.into_string().map_err(|e| e.to_string())?;
+    serde_json::from_str(&body).map_err(|e| format!("invalid response from Pulumi Cloud: {}", e))
+}
+
+/// Describes a failed request, including ESC's diagnostics for an invalid
+/// definition.
+pub(crate) fn request_error(err: ureq::Error) -> String {
+    match err {
+        ureq::Error::Status(code, response) => {
+            let body = response.into_string().unwrap_or_default();
+            let parsed: serde_json::Value = serde_json::from_str(&body).unwrap_or_default();
+            let diagnostics: Vec<&str> = parsed["diagnostics"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|d| d["summary"].as_str())
+                .collect();
+            if !diagnostics.is_empty() {
+                diagnostics.join("; ")
+            } else if let Some(message) = parsed["message"].as_str() {
+                format!("{} ({})", message, code)
+            } else {
+                format!("Pulumi Cloud returned {}", code)
+            }
+        }
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_definition_wraps_the_call() {
+        let inputs = Value::Object(vec![(
+            Cow::Borrowed("oidc"),
+            Value::Object(vec![(
+                Cow::Borrowed("roleArn"),
+                Value::Secret(Box::new(Value::String("arn:aws:iam::1:role/x".into()))),
+            )]),
+        )]);
+        let yaml: serde_json::Value =
+            serde_yaml::from_str(&definition("aws-login", &inputs).unwrap()).unwrap();
+        assert_eq!(
+            yaml,
+            serde_json::json!({"values": {"value": {"fn::open::aws-login": {
+                "oidc": {"roleArn": "arn:aws:iam::1:role/x"}
+            }}}})
+        );
+    }
+
+    #[test]
+    fn test_esc_values_keep_secrets() {
+        let value = esc_value(&serde_json::json!({
+            "value": {
+                "region": {"value": "us-west-2"},
+                "keys": {"value": [{"value": "AKIA", "secret": true}]},
+                "pending": {"unknown": true},
+            }
+        }));
+        let Value::Object(props) = value else {
+            panic!("expected an object");
+        };
+        let prop = |name: &str| &props.iter().find(|(k, _)| k == name).unwrap().1;
+        assert_eq!(prop("region"), &Value::String("us-west-2".into()));
+        assert_eq!(
+            prop("keys"),
+            &Value::List(vec![Value::Secret(Box::new(Value::String("AKIA".into())))])
+        );
+        assert_eq!(prop("pending"), &Value::Unknown);
+    }
+
+    #[test]
+    fn test_login_from_credentials() {
+        let dir = tempfile::tempdir().unwrap();
+        let credentials = dir.path().join("credentials.json");
+        std::fs::write(
+            &credentials,
+            r#"{"current": "https://app.pulumi.com/", "accessTokens": {"https://app.pulumi.com/": "pul-123"}}"#,
+        )
+        .unwrap();
+        let no_env = |_: &str| None;
+        assert_eq!(
+            Login::discover_with(&credentials, "ESC", no_env).unwrap(),
+            Login {
+                backend: "https://api.pulumi.com".to_string(),
+                token: "pul-123".to_string(),
+            }
+        );
+        std::fs::write(&credentials, r#"{"current": "file://~"}"#).unwrap();
+        assert!(Login::discover_with(&credentials, "ESC", no_env)
+            .unwrap_err()
+            .contains("ESC requires a Pulumi Cloud backend"));
+    }
+
+    #[test]
+    fn test_login_from_environment() {
+        let dir = tempfile::tempdir().unwrap();
+        let credentials = dir.path().join("credentials.json");
+        std::fs::write(&credentials, r#"{"current": "file://~"}"#).unwrap();
+        let env = |name: &str| match name {
+            "PULUMI_BACKEND_URL" => Some("https://app.example.com".to_string()),
+            "PULUMI_ACCESS_TOKEN" => Some("pul-456".to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            Login::discover_with(&credentials, "ESC", env).unwrap(),
+            Login {
+                backend: "https://api.example.com".to_string(),
+                token: "pul-456".to_string(),
+            }
+        );
+        let empty = |_: &str| Some(String::new());
+        assert!(Login::discover_with(&credentials, "ESC", empty)
+            .unwrap_err()
+            .contains("ESC requires a Pulumi Cloud backend"));
+    }
+
+    #[test]
+    fn test_opened_values_are_cached() {
+        let resolver = CloudOpenResolver::new("acme");
+        let inputs = Value::Object(vec![(Cow::Borrowed("region"), Value::String("eu".into()))]);
+        resolver.opened.lock().unwrap().insert(
+            definition("aws-login", &inputs).unwrap(),
+            Value::String("opened".into()),
+        );
+        // Served from the cache without discovering a login.
+        assert_eq!(
+            resolver.open("aws-login", &inputs).unwrap(),
+            Value::String("opened".into())
+        );
+        assert!(resolver.login.get().is_none());
+    }
+}
@@ -213,22 +213,22 @@ fn cleanup_all(modified_files: &[(PathBuf, String)], temp_dir: &Path) {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
 
     // Tests that change the working directory must run serially (not in parallel)
-    // because CWD is process-global state.
+    // because CWD is process-global state. Shared with the runner tests.
     use std::sync::Mutex;
     static CWD_LOCK: Mutex<()> = Mutex::new(());
 
     /// RAII guard that changes to a temp directory and restores on drop.
-    struct TempCwd {
+    pub(crate) struct TempCwd {
         original: PathBuf,
         _guard: std::sync::MutexGuard<'static, ()>,
     }
 
     impl TempCwd {
-        fn new(target: &Path) -> Self {
+        pub(crate) fn new(target: &Path) -> Self {
             // Recover from poisoned mutex (previous test may have panicked while
             // holding the lock). The data behind the lock is just `()`, so it's
             // always safe to continue.
@@ -0,0 +1,132 @@
+//! The `impact` subcommand.
+//!
+//! `pulumi-language-yaml impact [--ancestors] <name> [<project-dir>]` prints
+//! the config, variables and resources re-evaluated when `name` changes
+//! (default: the current directory). With `--ancestors` it prints what
+//! `name` depends on instead.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use pulumi_rs_yaml_core::eval::graph;
+use pulumi_rs_yaml_core::multi_file;
+
+/// Runs the `impact` subcommand and returns the process exit code.
+pub fn run_impact(args: &[String]) -> i32 {
+    let ancestors = args.iter().any(|a| a == "--ancestors");
+    let mut positional = args.iter().filter(|a| !a.starts_with("--"));
+    let Some(name) = positional.next() else {
+        eprintln!("usage: pulumi-language-yaml impact [--ancestors] <name> [<project-dir>]");
+        return 1;
+    };
+    let dir = positional.next().map_or(".", |a| a.as_str());
+    match impact(Path::new(dir), name, ancestors) {
+        Ok(out) => {
+            print!("{}", out);
+            0
+        }
+        Err(message) => {
+            eprintln!("error: {}", message);
+            1
+        }
+    }
+}
+
+/// Renders the nodes affected by (or, with `ancestors`, feeding into) `name`,
+/// one `kind name` line each.
+fn impact(dir: &Path, name: &str, ancestors: bool) -> Result<String, String> {
+    let (merged, diags) = multi_file::load_project(dir, None);
+    if diags.has_errors() {
+        return Err(format!("failed to load project: {}", diags));
+    }
+    let template = merged.as_template_decl();
+    let mut kinds: HashMap<&str, &str> = HashMap::new();
+    kinds.insert("pulumi", "pulumi");
+    for entry in &template.config {
+        kinds.insert(entry.key.as_ref(), "config");
+    }
+    for entry in &template.variables {
+        kinds.insert(entry.key.as_ref(), "variable");
+    }
+    for entry in &template.resources {
+        kinds.insert(entry.logical_name.as_ref(), "resource");
+    }
+    if !kinds.contains_key(name) {
+        return Err(format!(
+            "'{}' is not a config value, variable or resource of this project",
+            name
+        ));
+    }
+
+    let (result, diags) = graph::topological_sort_with_deps(&template, Some(merged.source_map()));
+    if diags.has_errors() {
+        return Err(format!("invalid dependency graph: {}", diags));
+    }
+    let (nodes, header, none) = if ancestors {
+        (
+            graph::ancestors(&result.deps, name),
+            format!("'{}' depends on:\n", name),
+            format!("'{}' depends on nothing\n", name),
+        )
+    } else {
+        (
+            graph::descendants(&result.deps, name),
+            format!("changing '{}' re-evaluates:\n", name),
+            format!("nothing depends on '{}'\n", name),
+        )
+    };
+    if nodes.is_empty() {
+        return Ok(none);
+    }
+    let mut out = header;
+    for node in &nodes {
+        let kind = kinds.get(node.as_str()).copied().unwrap_or("missing");
+        out.push_str(&format!("  {} {}\n", kind, node));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_impact_of_config_change() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Pulumi.yaml"),
+            r#"name: impact-test
+runtime: yaml
+config:
+  region:
+    default: us-east-1
+variables:
+  prefix: app-${region}
+resources:
+  bucket:
+    type: aws:s3:Bucket
+    properties:
+      bucket: ${prefix}
+  queue:
+    type: aws:sqs:Queue
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            impact(dir.path(), "region", false).unwrap(),
+            "changing 'region' re-evaluates:\n  resource bucket\n  variable prefix\n"
+        );
+        assert_eq!(
+            impact(dir.path(), "bucket", true).unwrap(),
+            "'bucket' depends on:\n  variable prefix\n  config region\n"
+        );
+        assert_eq!(
+            impact(dir.path(), "queue", false).unwrap(),
+            "nothing depends on 'queue'\n"
+        );
+        assert!(impact(dir.path(), "nope", false)
+            .unwrap_err()
+            .contains("'nope' is not a config value"));
+    }
+}
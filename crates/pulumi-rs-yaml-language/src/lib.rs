@@ -0,0 +1,28 @@
+//! The Pulumi YAML language host.
+//!
+//! [`server::YamlLanguageHost`] implements the engine's `LanguageRuntime`
+//! gRPC service. The `pulumi-language-yaml` binary serves it to an engine
+//! that started it as a plugin; it can also be served in-process, with the
+//! engine attaching to it as `pulumi up --client <address>`.
+
+// `Run` reports a panic in the program task as an internal error, which
+// needs the panic to unwind back to the server.
+#[cfg(panic = "abort")]
+compile_error!("the language host must be built with panic = \"unwind\"");
+
+mod channel;
+mod clients;
+mod component_provider;
+#[cfg(feature = "esc")]
+mod decrypt;
+#[cfg(test)]
+mod differential;
+#[cfg(feature = "esc")]
+mod esc;
+pub mod exec;
+pub mod lock;
+pub mod rpc_metrics;
+mod runner;
+mod schema_loader;
+pub mod server;
+mod template_loader;
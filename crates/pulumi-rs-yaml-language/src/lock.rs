@@ -0,0 +1,205 @@
+//! The `lock` subcommand and lockfile-aware package resolution.
+//!
+//! `pulumi-language-yaml lock [--check] [<project-dir>]` writes
+//! `pulumi-yaml.lock` for the project (default: the current directory), or
+//! with `--check` verifies that it is up to date.
+
+use std::path::Path;
+
+use pulumi_rs_yaml_core::lockfile::{lock_key, plugin_dir, Lockfile, LOCKFILE_NAME};
+use pulumi_rs_yaml_core::multi_file::{self, MergedTemplate};
+use pulumi_rs_yaml_core::packages::{self, PackageDependency};
+
+/// Returns the packages a loaded program references, pinned to the
+/// project's lockfile if it has one, together with the lockfile. Version
+/// requirements the lockfile does not pin are resolved against the
+/// installed plugins.
+///
+/// Packages loaded from a local plugin path (`plugins: providers:`) are
+/// neither checked against nor pinned to the lockfile, and the returned
+/// lockfile has no entries for them.
+///
+/// Fails if the lockfile is unreadable or out of date.
+pub fn locked_packages(
+    dir: &Path,
+    merged: &MergedTemplate,
+) -> Result<(Vec<PackageDependency>, Option<Lockfile>), String> {
+    let (mut packages, local) = referenced_packages(dir, merged);
+    let mut lock = Lockfile::load(dir).map_err(|e| e.to_string())?;
+    if let Some(lock) = &mut lock {
+        lock.packages
+            .retain(|name, _| !packages::is_local_plugin(merged.plugins(), name));
+        let problems = lock.check(&packages, merged.imports());
+        if !problems.is_empty() {
+            return Err(format!(
+                "{} is out of date (run `pulumi-language-yaml lock` to update it):\n  {}",
+                LOCKFILE_NAME,
+                problems.join("\n  ")
+            ));
+        }
+        lock.pin(&mut packages);
+    }
+    packages::resolve_versions(&mut packages, &plugin_dir());
+    packages.extend(local);
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok((packages, lock))
+}
+
+/// Returns the packages a loaded program references, split into those the
+/// lockfile governs and those loaded from a local plugin path.
+fn referenced_packages(
+    dir: &Path,
+    merged: &MergedTemplate,
+) -> (Vec<PackageDependency>, Vec<PackageDependency>) {
+    let template = merged.as_template_decl();
+    packages::get_referenced_packages(&template, &packages::search_package_decls(dir))
+        .into_iter()
+        .partition(|pkg| !packages::is_local_plugin(merged.plugins(), lock_key(pkg)))
+}
+
+/// Runs the `lock` subcommand and returns the process exit code.
+pub fn run_lock(args: &[String]) -> i32 {
+    let check = args.iter().any(|a| a == "--check");
+    let dir = args
+        .iter()
+        .find(|a| !a.starts_with("--"))
+        .map_or(".", |a| a.as_str());
+    match lock(Path::new(dir), check) {
+        Ok(message) => {
+            println!("{}", message);
+            0
+        }
+        Err(message) => {
+            eprintln!("error: {}", message);
+            1
+        }
+    }
+}
+
+fn lock(dir: &Path, check: bool) -> Result<String, String> {
+    let (merged, diags) = multi_file::load_project(dir, None);
+    if diags.has_errors() {
+        return Err(format!("failed to load project: {}", diags));
+    }
+
+    if check {
+        return match locked_packages(dir, &merged)? {
+            (_, Some(_)) => Ok(format!("{} is up to date", LOCKFILE_NAME)),
+            (_, None) => Err(format!("no {} in {}", LOCKFILE_NAME, dir.display())),
+        };
+    }
+
+    let (packages, _) = referenced_packages(dir, &merged);
+    let previous = Lockfile::load(dir).ok().flatten();
+    let lock = Lockfile::generate(
+        &packages,
+        merged.imports(),
+        &plugin_dir(),
+        previous.as_ref(),
+    );
+    lock.save(dir).map_err(|e| e.to_string())?;
+    Ok(format!("wrote {}", Lockfile::path(dir).display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: &str = r#"
+name: lock-test
+runtime: yaml
+resources:
+  bucket:
+    type: aws:s3:Bucket
+  pet:
+    type: random:RandomPet
+    options:
+      version: 4.16.0
+"#;
+
+    fn project(source: &str) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("Pulumi.yaml"), source).unwrap();
+        dir
+    }
+
+    fn load(dir: &Path) -> MergedTemplate {
+        let (merged, diags) = multi_file::load_project(dir, None);
+        assert!(!diags.has_errors(), "{}", diags);
+        merged
+    }
+
+    #[test]
+    fn test_lock_then_check() {
+        let dir = project(PROGRAM);
+        assert!(lock(dir.path(), true).is_err(), "no lockfile yet");
+
+        lock(dir.path(), false).unwrap();
+        let locked = Lockfile::load(dir.path()).unwrap().unwrap();
+        assert_eq!(locked.packages["random"].version, "4.16.0");
+        assert!(locked.packages.contains_key("aws"));
+        assert!(lock(dir.path(), true).is_ok());
+
+        // Bumping a version without re-locking is drift.
+        std::fs::write(
+            dir.path().join("Pulumi.yaml"),
+            PROGRAM.replace("4.16.0", "4.17.0"),
+        )
+        .unwrap();
+        let err = lock(dir.path(), true).unwrap_err();
+        assert!(
+            err.contains("package 'random' requires version 4.17.0 but 4.16.0 is locked"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_locked_packages_pins_versions() {
+        let dir = project(PROGRAM);
+        std::fs::write(
+            Lockfile::path(dir.path()),
+            r#"{
+  "version": 1,
+  "packages": {
+    "aws": { "version": "6.52.0", "checksums": { "linux-amd64": "ab" } },
+    "random": { "version": "4.16.0" }
+  }
+}"#,
+        )
+        .unwrap();
+
+        let (packages, lock) = locked_packages(dir.path(), &load(dir.path())).unwrap();
+        let aws = packages.iter().find(|p| p.name == "aws").unwrap();
+        assert_eq!(aws.version, "6.52.0");
+        assert_eq!(lock.unwrap().checksums(aws).unwrap()["linux-amd64"], "ab");
+    }
+
+    #[test]
+    fn test_local_plugins_are_not_locked() {
+        let program = PROGRAM.replace(
+            "runtime: yaml\n",
+            "runtime: yaml\nplugins:\n  providers:\n    - name: aws\n      path: ./bin\n",
+        );
+        let dir = project(&program);
+        lock(dir.path(), false).unwrap();
+        let locked = Lockfile::load(dir.path()).unwrap().unwrap();
+        assert!(!locked.packages.contains_key("aws"));
+        assert!(locked.packages.contains_key("random"));
+
+        // A stale entry for the local plugin is ignored.
+        let mut stale = locked.clone();
+        stale.packages.insert(
+            "aws".to_string(),
+            serde_json::from_str(
+                r#"{ "version": "6.52.0", "checksums": { "linux-amd64": "ab" } }"#,
+            )
+            .unwrap(),
+        );
+        stale.save(dir.path()).unwrap();
+        let (packages, lock) = locked_packages(dir.path(), &load(dir.path())).unwrap();
+        let aws = packages.iter().find(|p| p.name == "aws").unwrap();
+        assert_eq!(aws.version, "");
+        assert!(lock.unwrap().checksums(aws).is_none());
+    }
+}
@@ -1,22 +1,101 @@
-mod clients;
-mod component_provider;
-pub(crate) mod exec;
-mod runner;
-mod schema_loader;
-mod server;
-mod template_loader;
+mod deps;
+mod impact;
+mod new;
+mod plan;
+mod unused;
 
 use std::net::SocketAddr;
 
+use pulumi_rs_yaml_language::server::YamlLanguageHost;
+use pulumi_rs_yaml_language::{exec, lock, rpc_metrics};
 use pulumi_rs_yaml_proto::pulumirpc;
 use tonic::transport::Server;
 
-use server::YamlLanguageHost;
+/// Environment variable holding the log filter directives
+/// (e.g. `PULUMI_YAML_LOG=pulumi_rs_yaml_core=debug`).
+#[cfg(feature = "tracing")]
+const LOG_ENV: &str = "PULUMI_YAML_LOG";
+
+/// Environment variable that starts the host in debug mode on a fixed port,
+/// like `--port`.
+const DEBUG_PORT_ENV: &str = "PULUMI_YAML_DEBUG_PORT";
+
+/// Installs a stderr log subscriber when `PULUMI_YAML_LOG` is set, or at
+/// `debug` level in debug mode.
+///
+/// Stdout carries the port handshake with the engine, so logs always go to
+/// stderr. Without either no subscriber is installed.
+#[cfg(feature = "tracing")]
+fn init_tracing(debug: bool) {
+    let filter = match tracing_subscriber::EnvFilter::try_from_env(LOG_ENV) {
+        Ok(filter) => filter,
+        Err(_) if debug => tracing_subscriber::EnvFilter::new("debug"),
+        Err(_) => return,
+    };
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .try_init();
+}
+
+/// Arguments the engine starts the language host with.
+#[derive(Debug, Default, PartialEq)]
+struct HostArgs {
+    /// The engine's gRPC address (the last non-flag argument).
+    engine_address: String,
+    /// The project root directory from `--root`.
+    root: Option<String>,
+    /// The fixed port to listen on from `--port`, for debug mode.
+    port: Option<u16>,
+}
+
+/// Parses `[--tracing <endpoint>] [--root <dir>] [--port <port>] [<engine_address>]`.
+///
+/// The engine passes flags in Go style, so `-root=<dir>`, `--root=<dir>`,
+/// and `-root <dir>` are accepted as well. Unknown flags are ignored.
+fn parse_host_args(args: &[String]) -> HostArgs {
+    let mut parsed = HostArgs::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let Some(flag) = arg.strip_prefix("--").or_else(|| arg.strip_prefix('-')) else {
+            parsed.engine_address = arg.clone();
+            continue;
+        };
+        let (name, inline_value) = match flag.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (flag, None),
+        };
+        if !matches!(name, "root" | "tracing" | "port") {
+            continue;
+        }
+        let value = inline_value.or_else(|| iter.next().cloned());
+        match name {
+            "root" => parsed.root = value.filter(|v| !v.is_empty()),
+            "port" => parsed.port = value.and_then(|v| v.parse().ok()),
+            _ => {}
+        }
+    }
+    parsed
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args: Vec<String> = std::env::args().collect();
 
+    let mut host_args = parse_host_args(&args[1..]);
+    if host_args.port.is_none() {
+        host_args.port = std::env::var(DEBUG_PORT_ENV)
+            .ok()
+            .and_then(|p| p.parse().ok());
+    }
+
+    // Debug mode: the host is started by hand (e.g. under a debugger) on a
+    // fixed port and the engine attaches to it. The engine address then
+    // arrives in the handshake, so it may be omitted here.
+    let debug = host_args.port.is_some();
+    #[cfg(feature = "tracing")]
+    init_tracing(debug);
+
     // Check for exec subcommand: pulumi-language-yaml exec -- <command> [args...]
     if args.len() > 1 && args[1] == "exec" {
         let dash_pos = args.iter().position(|a| a == "--");
@@ -32,37 +111,63 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
-    // Parse arguments: the last non-flag argument is the engine address
-    let mut engine_address = String::new();
-    let mut i = 1;
-    while i < args.len() {
-        let arg = &args[i];
-        if arg == "--tracing" || arg == "--root" {
-            // Skip flag and its value
-            i += 2;
-            continue;
-        }
-        if arg.starts_with("--") {
-            i += 1;
-            continue;
-        }
-        // Non-flag argument: engine address
-        engine_address = arg.clone();
-        i += 1;
+    // Check for lock subcommand: pulumi-language-yaml lock [--check] [<project-dir>]
+    if args.len() > 1 && args[1] == "lock" {
+        std::process::exit(lock::run_lock(&args[2..]));
+    }
+
+    // Check for deps subcommand: pulumi-language-yaml deps [--strict] [<project-dir>]
+    if args.len() > 1 && args[1] == "deps" {
+        std::process::exit(deps::run_deps(&args[2..]));
     }
 
-    if engine_address.is_empty() {
-        eprintln!("usage: pulumi-language-yaml [--tracing <endpoint>] <engine_address>");
+    // Check for impact subcommand: pulumi-language-yaml impact [--ancestors] <name> [<project-dir>]
+    if args.len() > 1 && args[1] == "impact" {
+        std::process::exit(impact::run_impact(&args[2..]));
+    }
+
+    // Check for new subcommand: pulumi-language-yaml new <archetype> [--cloud <cloud>] [<project-dir>]
+    if args.len() > 1 && args[1] == "new" {
+        std::process::exit(new::run_new(&args[2..]));
+    }
+
+    // Check for plan subcommand: pulumi-language-yaml plan [--stack <stack>] [<project-dir>]
+    if args.len() > 1 && args[1] == "plan" {
+        std::process::exit(plan::run_plan(&args[2..]));
+    }
+
+    // Check for unused subcommand: pulumi-language-yaml unused [--strict] [<project-dir>]
+    if args.len() > 1 && args[1] == "unused" {
+        std::process::exit(unused::run_unused(&args[2..]));
+    }
+
+    if host_args.engine_address.is_empty() && !debug {
+        eprintln!(
+            "usage: pulumi-language-yaml [--tracing <endpoint>] [--root <dir>] [--port <port>] <engine_address>"
+        );
         std::process::exit(1);
     }
 
+    if let Err(e) = rpc_metrics::install() {
+        eprintln!("warning: {}", e);
+    }
+
     // Create the language host
-    let host = YamlLanguageHost::new(engine_address);
+    let host = YamlLanguageHost::new(host_args.engine_address);
+    if let Some(root) = host_args.root {
+        host.set_root_directory(root);
+    }
 
-    // Bind to a random port on localhost
-    let addr: SocketAddr = "127.0.0.1:0".parse()?;
+    // Bind to the debug port, or a random port on localhost
+    let addr = SocketAddr::from(([127, 0, 0, 1], host_args.port.unwrap_or(0)));
     let listener = tokio::net::TcpListener::bind(addr).await?;
     let local_addr = listener.local_addr()?;
+    if debug {
+        eprintln!(
+            "pulumi-language-yaml: debug mode, waiting for the engine on {}",
+            local_addr
+        );
+    }
 
     // Print the port to stdout so the Pulumi engine can connect
     println!("{}", local_addr.port());
@@ -77,3 +182,51 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> HostArgs {
+        parse_host_args(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_parse_host_args() {
+        let expected = HostArgs {
+            engine_address: "127.0.0.1:4000".to_string(),
+            root: Some("/work/app".to_string()),
+            port: None,
+        };
+        assert_eq!(parse(&["--root", "/work/app", "127.0.0.1:4000"]), expected);
+        assert_eq!(
+            parse(&["-tracing=http://t", "-root=/work/app", "127.0.0.1:4000"]),
+            expected
+        );
+        assert_eq!(
+            parse(&[
+                "--tracing",
+                "http://t",
+                "127.0.0.1:4000",
+                "--root=/work/app"
+            ]),
+            expected
+        );
+        assert_eq!(
+            parse(&["--unknown", "127.0.0.1:4000"]),
+            HostArgs {
+                engine_address: "127.0.0.1:4000".to_string(),
+                root: None,
+                port: None,
+            }
+        );
+        assert_eq!(
+            parse(&["-port=5005"]),
+            HostArgs {
+                port: Some(5005),
+                ..Default::default()
+            }
+        );
+        assert_eq!(parse(&["--port", "not-a-port"]).port, None);
+    }
+}
@@ -0,0 +1,192 @@
+//! The `new` subcommand.
+//!
+//! `pulumi-language-yaml new <archetype> [--cloud <cloud>] [--name <name>]
+//! [--schema <file>] [<project-dir>]` writes a new project from one of the
+//! built-in archetypes (see [`pulumi_rs_yaml_core::scaffold`]) to the
+//! project directory (default: the current directory). The project name
+//! defaults to the directory's name. `--schema` takes a provider schema, as
+//! printed by `pulumi package get-schema`, to complete the resources from.
+
+use std::path::{Path, PathBuf};
+
+use pulumi_rs_yaml_core::scaffold::{self, Archetype, Cloud, ScaffoldOptions};
+use pulumi_rs_yaml_core::schema::{self, SchemaStore};
+
+const USAGE: &str = "usage: pulumi-language-yaml new <archetype> [--cloud aws|gcp|azure] [--name <name>] [--schema <file>] [<project-dir>]";
+
+/// Runs the `new` subcommand and returns the process exit code.
+pub fn run_new(args: &[String]) -> i32 {
+    match parse_args(args).and_then(|args| new(&args)) {
+        Ok(written) => {
+            for path in written {
+                println!("created {}", path.display());
+            }
+            0
+        }
+        Err(message) => {
+            eprintln!("error: {}", message);
+            1
+        }
+    }
+}
+
+#[derive(Debug)]
+struct NewArgs {
+    archetype: Archetype,
+    cloud: Cloud,
+    name: Option<String>,
+    schema: Option<PathBuf>,
+    dir: PathBuf,
+}
+
+fn parse_args(args: &[String]) -> Result<NewArgs, String> {
+    let mut cloud = Cloud::default();
+    let mut name = None;
+    let mut schema = None;
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().cloned().ok_or_else(|| USAGE.to_string());
+        match arg.as_str() {
+            "--cloud" => {
+                let value = value()?;
+                cloud = Cloud::from_name(&value)
+                    .ok_or_else(|| format!("unknown cloud '{}' (aws, gcp or azure)", value))?;
+            }
+            "--name" => name = Some(value()?),
+            "--schema" => schema = Some(PathBuf::from(value()?)),
+            _ if arg.starts_with("--") => return Err(USAGE.to_string()),
+            _ => positional.push(arg.as_str()),
+        }
+    }
+    let (archetype, dir) = match positional.as_slice() {
+        [archetype] => (*archetype, "."),
+        [archetype, dir] => (*archetype, *dir),
+        _ => return Err(USAGE.to_string()),
+    };
+    let archetype = Archetype::from_name(archetype).ok_or_else(|| {
+        let names: Vec<&str> = Archetype::ALL.iter().map(|a| a.name()).collect();
+        format!(
+            "unknown archetype '{}' (one of: {})",
+            archetype,
+            names.join(", ")
+        )
+    })?;
+    Ok(NewArgs {
+        archetype,
+        cloud,
+        name,
+        schema,
+        dir: PathBuf::from(dir),
+    })
+}
+
+/// Writes the project and returns the paths of the files written.
+fn new(args: &NewArgs) -> Result<Vec<PathBuf>, String> {
+    if args.dir.join("Pulumi.yaml").exists() {
+        return Err(format!(
+            "{} already contains a Pulumi.yaml",
+            args.dir.display()
+        ));
+    }
+    let name = match &args.name {
+        Some(name) => name.clone(),
+        None => dir_name(&args.dir)?,
+    };
+    let store = match &args.schema {
+        Some(path) => Some(load_schemas(std::slice::from_ref(path))?),
+        None => None,
+    };
+    let files = scaffold::scaffold(&ScaffoldOptions {
+        name: &name,
+        archetype: args.archetype,
+        cloud: args.cloud,
+        schema: store.as_ref(),
+    })?;
+
+    let mut written = Vec::new();
+    for file in files {
+        let path = args.dir.join(&file.path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("failed to create {}: {}", parent.display(), e))?;
+        }
+        std::fs::write(&path, file.contents)
+            .map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// The name of the project directory, for the default project name.
+fn dir_name(dir: &Path) -> Result<String, String> {
+    let dir = std::path::absolute(dir).map_err(|e| e.to_string())?;
+    dir.components()
+        .filter_map(|c| match c {
+            std::path::Component::Normal(name) => name.to_str(),
+            _ => None,
+        })
+        .next_back()
+        .map(str::to_string)
+        .ok_or_else(|| "cannot name the project after its directory; use --name".to_string())
+}
+
+/// Reads provider schema files, as printed by `pulumi package get-schema`,
+/// into one store.
+pub(crate) fn load_schemas(paths: &[PathBuf]) -> Result<SchemaStore, String> {
+    let mut store = SchemaStore::new();
+    for path in paths {
+        let bytes =
+            std::fs::read(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        let package = schema::parse_schema_json(&bytes)
+            .map_err(|e| format!("invalid schema {}: {}", path.display(), e))?;
+        store.insert(package);
+    }
+    Ok(store)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_new_writes_project() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("site");
+        let dir_arg = dir.to_str().unwrap();
+        let parsed = parse_args(&args(&["static-site", "--cloud", "gcp", dir_arg])).unwrap();
+        let written = new(&parsed).unwrap();
+        assert_eq!(
+            written,
+            vec![dir.join("Pulumi.yaml"), dir.join("www/index.html")]
+        );
+        let project = std::fs::read_to_string(dir.join("Pulumi.yaml")).unwrap();
+        assert!(project.starts_with("name: site\n"), "{}", project);
+        assert!(project.contains("type: gcp:storage:Bucket"));
+
+        let err = new(&parsed).unwrap_err();
+        assert!(err.contains("already contains a Pulumi.yaml"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let parsed = parse_args(&args(&["serverless-api", "--name", "api"])).unwrap();
+        assert_eq!(parsed.archetype, Archetype::ServerlessApi);
+        assert_eq!(parsed.cloud, Cloud::Aws);
+        assert_eq!(parsed.name.as_deref(), Some("api"));
+        assert_eq!(parsed.dir, PathBuf::from("."));
+
+        assert!(parse_args(&args(&[])).is_err());
+        assert!(parse_args(&args(&["static-site", "--cloud"])).is_err());
+        assert!(parse_args(&args(&["static-site", "--cloud", "oci"]))
+            .unwrap_err()
+            .contains("unknown cloud"));
+        assert!(parse_args(&args(&["website"]))
+            .unwrap_err()
+            .contains("unknown archetype"));
+    }
+}
@@ -0,0 +1,302 @@
+//! The `plan` subcommand.
+//!
+//! `pulumi-language-yaml plan [--stack <stack>]... [--config <key>=<value>]...
+//! [--schema <file>]... [<project-dir>]` prints the canonical plan of the
+//! project (default: the current directory), see
+//! [`pulumi_rs_yaml_core::canonical`]. With `--stack`, config is read from
+//! the stack's `Pulumi.<stack>.yaml`; values stored there encrypted
+//! (`secure:`) are redacted. `--config` values are applied on top. Given
+//! several stacks, their plans are rendered concurrently and printed as one
+//! YAML document each, in order.
+//!
+//! The command runs without the engine, so it cannot fetch provider schemas.
+//! `--schema` takes provider schemas, as printed by `pulumi package
+//! get-schema`, which are loaded once and shared by every stack's plan.
+//! Type tokens are then resolved against them and secret resource inputs
+//! redacted; without them type tokens are canonicalized from their form
+//! alone and only config secrets are redacted.
+
+use std::path::{Path, PathBuf};
+
+use pulumi_rs_yaml_core::canonical::{self, CanonicalOptions};
+use pulumi_rs_yaml_core::eval::config::RawConfig;
+use pulumi_rs_yaml_core::multi_file;
+use pulumi_rs_yaml_core::schema::SchemaStore;
+
+const USAGE: &str = "usage: pulumi-language-yaml plan [--stack <stack>]... [--config <key>=<value>]... [--schema <file>]... [<project-dir>]";
+
+/// Runs the `plan` subcommand and returns the process exit code.
+pub fn run_plan(args: &[String]) -> i32 {
+    let mut stacks = Vec::new();
+    let mut overrides = Vec::new();
+    let mut schemas = Vec::new();
+    let mut dir = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--stack" => match args.next() {
+                Some(stack) => stacks.push(stack.as_str()),
+                None => {
+                    eprintln!("{}", USAGE);
+                    return 1;
+                }
+            },
+            "--config" => match args.next().and_then(|kv| kv.split_once('=')) {
+                Some((key, value)) => overrides.push((key.to_string(), value.to_string())),
+                None => {
+                    eprintln!("{}", USAGE);
+                    return 1;
+                }
+            },
+            "--schema" => match args.next() {
+                Some(path) => schemas.push(PathBuf::from(path)),
+                None => {
+                    eprintln!("{}", USAGE);
+                    return 1;
+                }
+            },
+            _ if arg.starts_with("--") || dir.is_some() => {
+                eprintln!("{}", USAGE);
+                return 1;
+            }
+            _ => dir = Some(arg.as_str()),
+        }
+    }
+    let schema = if schemas.is_empty() {
+        None
+    } else {
+        match crate::new::load_schemas(&schemas) {
+            Ok(store) => Some(store),
+            Err(message) => {
+                eprintln!("error: {}", message);
+                return 1;
+            }
+        }
+    };
+    match plan(
+        Path::new(dir.unwrap_or(".")),
+        &stacks,
+        overrides,
+        schema.as_ref(),
+    ) {
+        Ok(out) => {
+            print!("{}", out);
+            0
+        }
+        Err(message) => {
+            eprintln!("error: {}", message);
+            1
+        }
+    }
+}
+
+fn plan(
+    dir: &Path,
+    stacks: &[&str],
+    overrides: Vec<(String, String)>,
+    schema: Option<&SchemaStore>,
+) -> Result<String, String> {
+    let (merged, diags) = multi_file::load_project(dir, None);
+    if diags.has_errors() {
+        return Err(format!("failed to load project: {}", diags));
+    }
+    let project = merged.name().unwrap_or("unknown");
+    let mut configs = Vec::new();
+    for stack in stacks
+        .iter()
+        .map(Some)
+        .chain(stacks.is_empty().then_some(None))
+    {
+        let (mut config, mut secret_keys) = match stack {
+            Some(stack) => stack_config(&dir.join(format!("Pulumi.{}.yaml", stack)))?,
+            None => (RawConfig::new(), Vec::new()),
+        };
+        for (key, value) in &overrides {
+            let key = if key.contains(':') {
+                key.clone()
+            } else {
+                format!("{}:{}", project, key)
+            };
+            secret_keys.retain(|k| *k != key);
+            config.insert(key, value.clone());
+        }
+        configs.push((stack.copied(), config, secret_keys));
+    }
+    let options: Vec<CanonicalOptions> = configs
+        .iter()
+        .map(|(stack, config, secret_keys)| CanonicalOptions {
+            stack: *stack,
+            config: Some(config),
+            secret_keys,
+            schema,
+        })
+        .collect();
+
+    let plans = canonical::canonical_plans(&merged, &options).map_err(|diags| diags.to_string())?;
+    if let [plan] = plans.as_slice() {
+        return plan.clone().map_err(|diags| diags.to_string());
+    }
+    let mut out = String::new();
+    for (plan, stack) in plans.into_iter().zip(stacks) {
+        let plan = plan.map_err(|diags| format!("stack {}: {}", stack, diags))?;
+        out.push_str("---\n");
+        out.push_str(&plan);
+    }
+    Ok(out)
+}
+
+/// Reads the `config:` of a stack settings file, if it exists, returning
+/// the values and the keys of the encrypted ones. Structured values are
+/// passed on as JSON, as the CLI passes them to the program.
+fn stack_config(path: &Path) -> Result<(RawConfig, Vec<String>), String> {
+    let mut config = RawConfig::new();
+    let mut secret_keys = Vec::new();
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((config, secret_keys)),
+        Err(e) => return Err(format!("failed to read {}: {}", path.display(), e)),
+    };
+    let settings: serde_json::Value = serde_yaml::from_str(&source)
+        .map_err(|e| format!("failed to parse {}: {}", path.display(), e))?;
+    let Some(values) = settings.get("config").and_then(|c| c.as_object()) else {
+        return Ok((config, secret_keys));
+    };
+    for (key, value) in values {
+        let raw = match value {
+            serde_json::Value::String(s) => s.clone(),
+            serde_json::Value::Object(o) if o.contains_key("secure") => {
+                secret_keys.push(key.clone());
+                String::new()
+            }
+            other => other.to_string(),
+        };
+        config.insert(key.clone(), raw);
+    }
+    Ok((config, secret_keys))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_reads_stack_config() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Pulumi.yaml"),
+            r#"name: plan-test
+runtime: yaml
+config:
+  region:
+    type: string
+  token:
+    type: string
+resources:
+  bucket:
+    type: aws:s3:Bucket
+    properties:
+      bucket: logs-${region}
+      tags:
+        token: ${token}
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("Pulumi.prod.yaml"),
+            "config:\n  plan-test:region: us-east-1\n  plan-test:token:\n    secure: v1:abc\n",
+        )
+        .unwrap();
+
+        let out = plan(dir.path(), &["prod"], Vec::new(), None).unwrap();
+        assert!(out.contains("      bucket: logs-us-east-1\n"), "{}", out);
+        assert!(out.contains("  token: '[secret]'\n"), "{}", out);
+        assert!(out.contains("        token: ${token}\n"), "{}", out);
+        assert!(!out.contains("v1:abc"), "{}", out);
+
+        let overrides = vec![("region".to_string(), "eu-west-1".to_string())];
+        let out = plan(dir.path(), &["prod"], overrides.clone(), None).unwrap();
+        assert!(out.contains("      bucket: logs-eu-west-1\n"), "{}", out);
+
+        let out = plan(dir.path(), &[], Vec::new(), None).unwrap();
+        assert!(out.contains("      bucket: logs-${region}\n"), "{}", out);
+
+        std::fs::write(
+            dir.path().join("Pulumi.dev.yaml"),
+            "config:\n  plan-test:region: us-west-2\n",
+        )
+        .unwrap();
+        let out = plan(dir.path(), &["dev", "prod"], Vec::new(), None).unwrap();
+        let docs: Vec<&str> = out.split("---\n").skip(1).collect();
+        assert_eq!(docs.len(), 2, "{}", out);
+        assert!(
+            docs[0].contains("      bucket: logs-us-west-2\n"),
+            "{}",
+            out
+        );
+        assert!(docs[0].contains("stack: dev\n"), "{}", out);
+        assert!(
+            docs[1].contains("      bucket: logs-us-east-1\n"),
+            "{}",
+            out
+        );
+        assert!(docs[1].contains("stack: prod\n"), "{}", out);
+
+        // Overrides apply to every stack.
+        let out = plan(dir.path(), &["dev", "prod"], overrides, None).unwrap();
+        assert_eq!(
+            out.matches("      bucket: logs-eu-west-1\n").count(),
+            2,
+            "{}",
+            out
+        );
+    }
+
+    #[test]
+    fn test_plan_uses_schemas() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Pulumi.yaml"),
+            r#"name: plan-test
+runtime: yaml
+resources:
+  db:
+    type: aws:rds:Instance
+    properties:
+      password: hunter2
+"#,
+        )
+        .unwrap();
+        let schema = dir.path().join("aws.json");
+        std::fs::write(
+            &schema,
+            r#"{
+  "name": "aws",
+  "resources": {
+    "aws:rds/instance:Instance": {
+      "inputProperties": { "password": { "type": "string", "secret": true } }
+    }
+  }
+}"#,
+        )
+        .unwrap();
+
+        let out = plan(dir.path(), &[], Vec::new(), None).unwrap();
+        assert!(out.contains("      password: hunter2\n"), "{}", out);
+
+        let store = crate::new::load_schemas(&[schema]).unwrap();
+        let out = plan(dir.path(), &["dev", "prod"], Vec::new(), Some(&store)).unwrap();
+        assert_eq!(
+            out.matches("    type: aws:rds/instance:Instance\n").count(),
+            2,
+            "{}",
+            out
+        );
+        assert_eq!(
+            out.matches("      password: '[secret]'\n").count(),
+            2,
+            "{}",
+            out
+        );
+        assert!(!out.contains("hunter2"), "{}", out);
+    }
+}
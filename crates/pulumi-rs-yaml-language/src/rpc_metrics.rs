@@ -0,0 +1,123 @@
+//! Per-RPC metrics for the engine and monitor clients.
+//!
+//! With the `metrics` feature, every RPC made by [`GrpcCallback`] records,
+//! labeled by `method`:
+//!
+//! - `pulumi_yaml_rpc_calls_total` (also labeled by gRPC `status`)
+//! - `pulumi_yaml_rpc_retries_total`
+//! - `pulumi_yaml_rpc_duration_seconds`, including retries and backoff
+//! - `pulumi_yaml_rpc_request_bytes` and `pulumi_yaml_rpc_response_bytes`
+//!
+//! Setting `PULUMI_YAML_METRICS_ADDR` (e.g. `127.0.0.1:9464`) serves them in
+//! Prometheus text format on that address for the lifetime of the language
+//! host. Without the feature, recording compiles to nothing.
+//!
+//! [`GrpcCallback`]: crate::clients::GrpcCallback
+
+use std::time::Duration;
+
+/// Environment variable holding the address of the metrics endpoint.
+pub const METRICS_ADDR_ENV: &str = "PULUMI_YAML_METRICS_ADDR";
+
+/// The outcome of one RPC, across all of its attempts.
+#[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+pub struct RpcObservation {
+    pub method: &'static str,
+    pub elapsed: Duration,
+    pub attempts: u32,
+    pub request_bytes: usize,
+    /// The encoded response size, or the status code the call failed with.
+    pub response: Result<usize, tonic::Code>,
+}
+
+/// Records an RPC.
+#[cfg(feature = "metrics")]
+pub fn record(obs: &RpcObservation) {
+    let status = match obs.response {
+        Ok(_) => tonic::Code::Ok,
+        Err(code) => code,
+    };
+    metrics::counter!(
+        "pulumi_yaml_rpc_calls_total",
+        "method" => obs.method,
+        "status" => format!("{:?}", status),
+    )
+    .increment(1);
+    if obs.attempts > 1 {
+        metrics::counter!("pulumi_yaml_rpc_retries_total", "method" => obs.method)
+            .increment(u64::from(obs.attempts - 1));
+    }
+    metrics::histogram!("pulumi_yaml_rpc_duration_seconds", "method" => obs.method)
+        .record(obs.elapsed.as_secs_f64());
+    metrics::histogram!("pulumi_yaml_rpc_request_bytes", "method" => obs.method)
+        .record(obs.request_bytes as f64);
+    if let Ok(bytes) = obs.response {
+        metrics::histogram!("pulumi_yaml_rpc_response_bytes", "method" => obs.method)
+            .record(bytes as f64);
+    }
+}
+
+/// Records an RPC.
+#[cfg(not(feature = "metrics"))]
+pub fn record(_obs: &RpcObservation) {}
+
+/// Starts the metrics endpoint if `PULUMI_YAML_METRICS_ADDR` is set. Must be
+/// called from within the tokio runtime.
+pub fn install() -> Result<(), String> {
+    let Ok(addr) = std::env::var(METRICS_ADDR_ENV) else {
+        return Ok(());
+    };
+    #[cfg(feature = "metrics")]
+    {
+        let addr: std::net::SocketAddr = addr
+            .parse()
+            .map_err(|e| format!("invalid {} '{}': {}", METRICS_ADDR_ENV, addr, e))?;
+        metrics_exporter_prometheus::PrometheusBuilder::new()
+            .with_http_listener(addr)
+            .install()
+            .map_err(|e| format!("failed to start metrics endpoint on {}: {}", addr, e))
+    }
+    #[cfg(not(feature = "metrics"))]
+    Err(format!(
+        "{} is set to '{}' but this build has no `metrics` feature",
+        METRICS_ADDR_ENV, addr
+    ))
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_renders_prometheus_metrics() {
+        let recorder = metrics_exporter_prometheus::PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        metrics::with_local_recorder(&recorder, || {
+            record(&RpcObservation {
+                method: "register_resource",
+                elapsed: Duration::from_millis(12),
+                attempts: 3,
+                request_bytes: 512,
+                response: Ok(128),
+            });
+            record(&RpcObservation {
+                method: "invoke",
+                elapsed: Duration::from_millis(4),
+                attempts: 1,
+                request_bytes: 64,
+                response: Err(tonic::Code::NotFound),
+            });
+        });
+
+        let rendered = handle.render();
+        assert!(rendered
+            .contains(r#"pulumi_yaml_rpc_calls_total{method="register_resource",status="Ok"} 1"#));
+        assert!(rendered
+            .contains(r#"pulumi_yaml_rpc_calls_total{method="invoke",status="NotFound"} 1"#));
+        assert!(rendered.contains(r#"pulumi_yaml_rpc_retries_total{method="register_resource"} 2"#));
+        assert!(!rendered.contains(r#"pulumi_yaml_rpc_retries_total{method="invoke"}"#));
+        assert!(rendered
+            .contains(r#"pulumi_yaml_rpc_request_bytes_sum{method="register_resource"} 512"#));
+        assert!(!rendered.contains(r#"pulumi_yaml_rpc_response_bytes_sum{method="invoke"}"#));
+    }
+}
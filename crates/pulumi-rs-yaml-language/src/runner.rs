@@ -2,127 +2,276 @@
 //! the evaluator and the Pulumi engine.
 
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use pulumi_rs_yaml_core::ast::parse::parse_template;
+use pulumi_rs_yaml_core::ast::parse::{parse_template, parse_template_with_duplicate_keys};
+use pulumi_rs_yaml_core::diag::FileTable;
+use pulumi_rs_yaml_core::eval::cache::EvalCache;
 use pulumi_rs_yaml_core::eval::callback::ResourceCallback;
-use pulumi_rs_yaml_core::eval::evaluator::Evaluator;
+use pulumi_rs_yaml_core::eval::context::{EngineError, EvaluatorContext};
+use pulumi_rs_yaml_core::eval::evaluator::{EvalMode, Evaluator};
+use pulumi_rs_yaml_core::eval::extensions::BuiltinRegistry;
+use pulumi_rs_yaml_core::eval::fold;
+use pulumi_rs_yaml_core::eval::limits::EvalLimits;
+use pulumi_rs_yaml_core::eval::stable_random;
 use pulumi_rs_yaml_core::eval::value::Value;
 use pulumi_rs_yaml_core::jinja::{
-    validate_rendered_yaml, JinjaContext, JinjaPreprocessor, TemplatePreprocessor, UndefinedMode,
+    validate_rendered_yaml, validate_rendered_yaml_with_duplicate_keys, JinjaContext,
+    JinjaPreprocessor, TemplatePreprocessor, UndefinedMode,
 };
+use pulumi_rs_yaml_core::lockfile;
 use pulumi_rs_yaml_core::multi_file;
 use pulumi_rs_yaml_core::packages;
+use pulumi_rs_yaml_core::remote_import::default_cache_dir;
+use pulumi_rs_yaml_core::vfs::{FsProvider, OsFs, RootedFs};
 
 use crate::clients::GrpcCallback;
 use crate::schema_loader::SchemaLoader;
 
-/// Result of running a YAML program.
+/// Environment variable holding a comma-separated list of logical names or
+/// URNs. When set, only those entries and their dependencies are evaluated,
+/// and stack outputs depending on other entries are not updated. The URN of
+/// one `range` instance selects the whole entry.
+pub const TARGETS_ENV: &str = "PULUMI_YAML_TARGETS";
+
+/// Environment variables holding comma-separated resource types to block or
+/// allow, in addition to the template's `blockedTypes` and `allowedTypes`
+/// runtime options.
+pub const BLOCKED_TYPES_ENV: &str = "PULUMI_YAML_BLOCKED_TYPES";
+pub const ALLOWED_TYPES_ENV: &str = "PULUMI_YAML_ALLOWED_TYPES";
+
+/// Environment variable selecting the evaluation mode: `update` (the
+/// default), `refresh`, or `destroy-plan`. See [`EvalMode`].
+pub const EVAL_MODE_ENV: &str = "PULUMI_YAML_EVAL_MODE";
+
+/// Environment variable holding how many of the slowest resources and
+/// invokes to list in a timing summary after evaluation. The
+/// `timingSummary: true` runtime option lists [`DEFAULT_TIMING_SUMMARY`].
+pub const TIMING_SUMMARY_ENV: &str = "PULUMI_YAML_TIMING_SUMMARY";
+const DEFAULT_TIMING_SUMMARY: usize = 10;
+
+/// Environment variable holding a comma-separated list of extra directories
+/// templates may read. When set, project loading and the file-reading
+/// builtins are confined to the program directory, the project root, and
+/// these directories.
+pub const FS_ROOTS_ENV: &str = "PULUMI_YAML_FS_ROOTS";
+
+/// Environment variable holding the file that caches the values of pure
+/// variables between runs, so repeated previews only evaluate the ones whose
+/// inputs changed. The `evalCache: true` runtime option caches them in
+/// `$PULUMI_HOME/yaml-eval-cache/<project>-<stack>.json`.
+pub const EVAL_CACHE_ENV: &str = "PULUMI_YAML_EVAL_CACHE";
+
+/// Environment variable that, set to `true` or `1`, runs programs in strict
+/// mode: templates cannot read the host's environment, so `fn::env` fails
+/// even when the `allowEnv` runtime option is set.
+pub const STRICT_ENV: &str = "PULUMI_YAML_STRICT";
+
+/// Returns the filesystem templates read through: the host filesystem, or
+/// one rooted at the project directories when [`FS_ROOTS_ENV`] is set.
+fn template_fs(context: &EvaluatorContext, program_directory: &str) -> Arc<dyn FsProvider> {
+    if std::env::var_os(FS_ROOTS_ENV).is_none() {
+        return Arc::new(OsFs);
+    }
+    let roots = [
+        program_directory.to_string(),
+        context.root_directory.clone(),
+    ]
+    .into_iter()
+    .filter(|root| !root.is_empty())
+    .chain(env_list(FS_ROOTS_ENV));
+    Arc::new(RootedFs::new(OsFs, roots))
+}
+
+/// Reads a comma-separated list from environment variable `name`.
+fn env_list(name: &str) -> Vec<String> {
+    std::env::var(name)
+        .map(|list| {
+            list.split(',')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Whether [`STRICT_ENV`] enables strict mode.
+fn strict_mode() -> bool {
+    matches!(std::env::var(STRICT_ENV).as_deref(), Ok("true" | "1"))
+}
+
+/// Reads the evaluation mode from [`EVAL_MODE_ENV`].
+fn eval_mode_from_env() -> Result<EvalMode, String> {
+    match std::env::var(EVAL_MODE_ENV).as_deref() {
+        Err(_) | Ok("") | Ok("update") => Ok(EvalMode::Update),
+        Ok("refresh") => Ok(EvalMode::Refresh),
+        Ok("destroy-plan") => Ok(EvalMode::DestroyPlan),
+        Ok(other) => Err(format!(
+            "invalid {} '{}': expected update, refresh, or destroy-plan",
+            EVAL_MODE_ENV, other
+        )),
+    }
+}
+
+/// Result of running a YAML program, as the `RunResponse` reports it.
+///
+/// Errors in the program are reported as diagnostics when they are found, so
+/// a run they end is a [`RunResult::bail`]: the engine aborts without
+/// printing anything more. `error` is set only when the host itself failed,
+/// e.g. it could not reach the engine; the engine prints it.
+#[derive(Debug)]
 pub struct RunResult {
     pub error: String,
     pub bail: bool,
 }
 
+impl RunResult {
+    /// The program ran to completion.
+    pub fn ok() -> Self {
+        RunResult {
+            error: String::new(),
+            bail: false,
+        }
+    }
+
+    /// The program failed, and its errors have been reported.
+    pub fn bail() -> Self {
+        RunResult {
+            error: String::new(),
+            bail: true,
+        }
+    }
+
+    /// The host failed for a reason of its own.
+    pub fn internal(error: impl Into<String>) -> Self {
+        RunResult {
+            error: error.into(),
+            bail: false,
+        }
+    }
+
+    /// Whether the host failed, rather than the program.
+    pub fn is_internal(&self) -> bool {
+        !self.error.is_empty()
+    }
+}
+
+/// Reports errors of the program to the engine, which shows them to the
+/// user. An error the engine cannot be told about goes to stderr instead,
+/// so each is shown once.
+fn report_errors(callback: &GrpcCallback, errors: &[String]) {
+    for msg in errors {
+        if callback.log_to_engine(3, msg, "", 0, false).is_err() {
+            eprintln!("error: {}", msg);
+        }
+    }
+}
+
 /// Runs a YAML program by connecting to the monitor/engine and evaluating the template.
+///
+/// `context` supplies the `pulumi` variable and the Jinja context alike; its
+/// working directory is normally `program_directory`. The process's own
+/// working directory and environment are not changed. `extensions` are the
+/// host's builtins; their namespaces are never registered as packages.
 #[allow(clippy::too_many_arguments)]
 pub async fn run(
-    project: &str,
-    stack: &str,
-    pwd: &str,
+    context: &EvaluatorContext,
     monitor_address: &str,
     engine_address: &str,
     config: &HashMap<String, String>,
     config_secret_keys: &[String],
-    dry_run: bool,
     program_directory: &str,
-    organization: &str,
     loader_target: Option<&str>,
     parallel: i32,
+    extensions: &BuiltinRegistry,
 ) -> RunResult {
-    // 1. Change working directory to program directory (matching Go behavior)
-    if !program_directory.is_empty() {
-        if let Err(e) = std::env::set_current_dir(program_directory) {
-            eprintln!(
-                "warning: could not change to program directory {}: {}",
-                program_directory, e
-            );
+    let mode = match eval_mode_from_env() {
+        Ok(mode) => mode,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            return RunResult::bail();
         }
-    }
+    };
 
-    // Set environment variables for organization context
-    if !organization.is_empty() {
-        std::env::set_var("PULUMI_ORGANIZATION", organization);
-    }
+    // 1. Unlike the Go host, the working directory and environment are left
+    //    alone: every path below is resolved against `program_directory`, so
+    //    hosts served in-process can run programs side by side.
 
-    // 2. Build Jinja context for preprocessing
+    // 2. Build Jinja context for preprocessing. It sees the same directories
+    //    and arguments as the `pulumi` variable in the evaluator.
     let undefined_mode = match std::env::var("PULUMI_YAML_JINJA_UNDEFINED").as_deref() {
         Ok("passthrough") => UndefinedMode::Passthrough,
         _ => UndefinedMode::Strict,
     };
     let empty_extra = HashMap::new();
-    let jinja_ctx = JinjaContext {
-        project_name: project,
-        stack_name: stack,
-        cwd: pwd,
-        organization,
-        root_directory: program_directory,
-        config,
-        project_dir: program_directory,
-        undefined: undefined_mode,
-        extra: &empty_extra,
-    };
+    let jinja_ctx = context.jinja_context(config, program_directory, undefined_mode, &empty_extra);
+    let fs = template_fs(context, program_directory);
 
     // 3. Load template(s) — multi-file or single-file with Jinja source override
+    let mut sources = None;
     let (template, source_map) =
         if let Ok(jinja_source_dir) = std::env::var(crate::exec::JINJA_SOURCE_ENV) {
             // Exec wrapper is active: read original Jinja sources from temp directory
             // and load/preprocess/merge them
-            match load_from_jinja_source(&jinja_source_dir, program_directory, &jinja_ctx) {
+            match load_from_jinja_source(&jinja_source_dir, program_directory, &jinja_ctx, &fs) {
                 Ok((t, sm)) => (t, sm),
                 Err(e) => {
-                    return RunResult {
-                        error: format!("failed to load template: {}", e),
-                        bail: true,
-                    };
+                    eprintln!("error: failed to load template: {}", e);
+                    return RunResult::bail();
                 }
             }
         } else {
             // Normal mode: discover and load all Pulumi.*.yaml files
             let dir = Path::new(program_directory);
-            let (merged, load_diags) = multi_file::load_project(dir, Some(&jinja_ctx));
+            let (merged, load_diags) = multi_file::load_project_with_fs(
+                dir,
+                Some(&jinja_ctx),
+                &default_cache_dir(),
+                Arc::clone(&fs),
+            );
             if load_diags.has_errors() {
+                let mut files = FileTable::new(merged.sources());
                 for diag in load_diags.iter() {
-                    if diag.is_error() {
-                        eprintln!("error: {}", diag.summary);
+                    if !diag.is_error() {
+                        continue;
+                    }
+                    match diag.span.filter(|span| files.contains(span.file)) {
+                        Some(span) => {
+                            eprintln!("{}: error: {}", files.format_span(span), diag.summary)
+                        }
+                        None => eprintln!("error: {}", diag.summary),
                     }
                 }
-                return RunResult {
-                    error: "failed to load template".to_string(),
-                    bail: true,
-                };
+                return RunResult::bail();
             }
+            sources = Some(Arc::clone(merged.sources()));
             let sm = merged.source_map_arc();
             (merged.as_template_decl(), sm)
         };
 
-    // Leak the template to give it 'static lifetime
-    // This is acceptable since the process runs once per evaluation
-    let template: &'static _ = Box::leak(Box::new(template));
+    // Pre-evaluate pure builtin calls over literals
+    let mut template = template;
+    fold::fold_template(&mut template);
+
+    let template = &template;
 
     // 4. Connect gRPC clients
     let callback = match GrpcCallback::connect(monitor_address, engine_address).await {
         Ok(cb) => cb,
-        Err(e) => {
-            return RunResult {
-                error: format!("failed to connect: {}", e),
-                bail: false,
-            };
-        }
+        Err(e) => return RunResult::internal(format!("failed to connect: {}", e)),
     };
 
     // 5. Discover referenced packages (shared between schema loading and package registration)
     let lock_packages = packages::search_package_decls(Path::new(program_directory));
-    let referenced_pkgs = packages::get_referenced_packages(template, &lock_packages);
+    let mut referenced_pkgs = packages::get_referenced_packages(template, &lock_packages);
+    extensions.retain_plugin_packages(&mut referenced_pkgs);
+    // Resolve version requirements the same way `GetRequiredPackages` does.
+    if let Ok(Some(lock)) = lockfile::Lockfile::load(Path::new(program_directory)) {
+        lock.pin(&mut referenced_pkgs);
+    }
+    packages::resolve_versions(&mut referenced_pkgs, &lockfile::plugin_dir());
 
     // 6. Load schemas from provider packages (if loader_target is available)
     let schema_store = if let Some(addr) = loader_target {
@@ -166,22 +315,108 @@ pub async fn run(
         }
     }
 
+    let package_versions = match packages::package_versions(&referenced_pkgs) {
+        Ok(versions) => versions,
+        Err(e) => {
+            report_errors(&callback, &[e]);
+            return RunResult::bail();
+        }
+    };
+
     // 8. Create evaluator
-    let mut eval = Evaluator::with_callback(
-        project.to_string(),
-        stack.to_string(),
-        pwd.to_string(),
-        dry_run,
-        callback,
-    );
-    eval.organization = organization.to_string();
-    eval.root_directory = program_directory.to_string();
+    let mut eval = Evaluator::from_context(context, callback);
     eval.schema_store = schema_store.as_ref();
     eval.package_refs = package_refs;
+    eval.package_versions = package_versions;
     eval.parallel = parallel;
+    eval.extensions = extensions.clone();
+    eval.limits = EvalLimits::from_env();
+    eval.project_relative_paths = template
+        .runtime
+        .as_ref()
+        .and_then(|r| r.bool_option("projectRelativePaths"))
+        .unwrap_or(false);
+    eval.continue_on_error = template
+        .runtime
+        .as_ref()
+        .and_then(|r| r.bool_option("continueOnError"))
+        .unwrap_or(false);
+    eval.rich_check_failures = template
+        .runtime
+        .as_ref()
+        .and_then(|r| r.bool_option("richCheckFailures"))
+        .unwrap_or(false);
+    eval.check_duplicate_registrations = template
+        .runtime
+        .as_ref()
+        .and_then(|r| r.bool_option("checkDuplicateRegistrations"))
+        .unwrap_or(false);
+    eval.allow_env = !strict_mode()
+        && template
+            .runtime
+            .as_ref()
+            .and_then(|r| r.bool_option("allowEnv"))
+            .unwrap_or(false);
+    let runtime_types = |key: &str| {
+        template
+            .runtime
+            .as_ref()
+            .and_then(|r| r.string_list_option(key))
+            .unwrap_or_default()
+    };
+    for allowed in runtime_types("allowedTypes")
+        .into_iter()
+        .chain(env_list(ALLOWED_TYPES_ENV))
+    {
+        eval.blocklist.allow(&allowed);
+    }
+    for blocked in runtime_types("blockedTypes")
+        .into_iter()
+        .chain(env_list(BLOCKED_TYPES_ENV))
+    {
+        eval.blocklist.block(&blocked);
+    }
+    eval.targets = std::env::var_os(TARGETS_ENV).map(|_| env_list(TARGETS_ENV));
+    eval.timing_summary = std::env::var(TIMING_SUMMARY_ENV)
+        .ok()
+        .and_then(|n| n.trim().parse().ok())
+        .or_else(|| {
+            template
+                .runtime
+                .as_ref()
+                .and_then(|r| r.bool_option("timingSummary"))
+                .filter(|&on| on)
+                .map(|_| DEFAULT_TIMING_SUMMARY)
+        });
+    eval.eval_cache = std::env::var_os(EVAL_CACHE_ENV)
+        .map(PathBuf::from)
+        .or_else(|| {
+            template
+                .runtime
+                .as_ref()
+                .and_then(|r| r.bool_option("evalCache"))
+                .filter(|&on| on)
+                .map(|_| {
+                    packages::pulumi_home()
+                        .join("yaml-eval-cache")
+                        .join(format!("{}-{}.json", context.project, context.stack))
+                })
+        })
+        .map(|path| Arc::new(EvalCache::on_disk(path)));
+    #[cfg(feature = "esc")]
+    eval.set_open_resolver(crate::esc::CloudOpenResolver::new(&context.organization));
+    #[cfg(feature = "esc")]
+    eval.set_decrypter(crate::decrypt::CloudDecrypter::new(
+        &context.organization,
+        &context.project,
+        &context.stack,
+    ));
+    eval.mode = mode;
+    eval.fs = fs;
     if !source_map.is_empty() {
         eval.source_map = Some(std::sync::Arc::clone(&source_map));
     }
+    eval.sources = sources;
 
     // 8b. Type-check template against schemas (warnings only, non-blocking)
     if let Some(store) = eval.schema_store {
@@ -199,8 +434,25 @@ pub async fn run(
         }
     }
 
+    // A destroy plan is reported to the engine; nothing is registered.
+    if mode == EvalMode::DestroyPlan {
+        eval.evaluate_template(template, config, config_secret_keys);
+        if eval.has_errors() {
+            report_errors(eval.callback(), &eval.diag_errors());
+            return RunResult::bail();
+        }
+        for resource in eval.destroy_plan() {
+            let mut msg = format!("delete {} '{}'", resource.type_token, resource.name);
+            if !resource.dependencies.is_empty() {
+                msg.push_str(&format!(" before {}", resource.dependencies.join(", ")));
+            }
+            eval.callback().log(1, &msg);
+        }
+        return RunResult::ok();
+    }
+
     // 9. Register root stack resource
-    let stack_name_full = format!("{}-{}", project, stack);
+    let stack_name_full = format!("{}-{}", context.project, context.stack);
     let stack_type = "pulumi:pulumi:Stack";
 
     match eval.callback().register_resource(
@@ -214,27 +466,36 @@ pub async fn run(
         Ok(resp) => {
             eval.stack_urn = Some(resp.urn);
         }
-        Err(e) => {
-            return RunResult {
-                error: format!("failed to register stack: {}", e),
-                bail: false,
-            };
+        Err(e) => return RunResult::internal(format!("failed to register stack: {}", e)),
+    }
+
+    // 9b. Stable random values come back from the previous deployment
+    if template
+        .runtime
+        .as_ref()
+        .and_then(|r| r.bool_option("stableRandom"))
+        .unwrap_or(false)
+    {
+        match read_stable_random(&eval, context) {
+            Ok(values) => eval.stable_random = Some(values),
+            Err(e) => {
+                return RunResult::internal(format!("failed to read stable random values: {}", e))
+            }
         }
     }
 
     // 10. Evaluate the template
     eval.evaluate_template(template, config, config_secret_keys);
+    if let Some(cache) = &eval.eval_cache {
+        if let Err(e) = cache.save() {
+            eprintln!("warning: failed to save the evaluation cache: {}", e);
+        }
+    }
 
     // 11. Check for errors
     if eval.has_errors() {
-        // Collect error messages
-        let errors = eval.diag_errors();
-
-        // Write errors to stderr and log to engine
-        for msg in &errors {
-            eprintln!("error: {}", msg);
-            eval.callback().log(3, msg);
-        }
+        // Report error messages, with details such as docs links
+        report_errors(eval.callback(), &eval.diag_error_messages());
 
         // Register empty outputs for the stack
         let stack_urn = eval.stack_urn.clone();
@@ -242,11 +503,8 @@ pub async fn run(
             let _ = eval.callback().register_outputs(&urn, HashMap::new());
         }
 
-        // Return with bail=true to signal program abort (matching Go)
-        return RunResult {
-            error: String::new(),
-            bail: true,
-        };
+        // Bail to signal program abort (matching Go)
+        return RunResult::bail();
     }
 
     // 12. Log warnings to stderr and engine
@@ -256,38 +514,73 @@ pub async fn run(
         eval.callback().log(2, msg);
     }
 
-    // 13. Register stack outputs
-    let stack_urn = eval.stack_urn.clone();
+    // 13. Register stack outputs. A refresh leaves the recorded ones alone.
+    let stack_urn = eval.stack_urn.clone().filter(|_| mode != EvalMode::Refresh);
     if let Some(urn) = stack_urn {
-        let outputs: HashMap<String, Value<'static>> = eval
+        let mut outputs: HashMap<String, Value<'static>> = eval
             .take_outputs()
             .into_iter()
             .map(|(k, v)| (k, v.into_owned()))
             .collect();
+        let random_values = stable_random::merge(eval.stable_random.as_ref(), eval.random_values());
+        if eval.stable_random.is_some() && !random_values.is_empty() {
+            outputs.insert(
+                stable_random::OUTPUT_NAME.to_string(),
+                stable_random::to_output(&random_values),
+            );
+        }
 
         if let Err(e) = eval.callback().register_outputs(&urn, outputs) {
-            return RunResult {
-                error: format!("failed to register stack outputs: {}", e),
-                bail: false,
-            };
+            return RunResult::internal(format!("failed to register stack outputs: {}", e));
         }
     }
 
-    RunResult {
-        error: String::new(),
-        bail: false,
+    RunResult::ok()
+}
+
+/// Reads the values stable `fn::uuid` and `fn::randomString` calls returned
+/// in the previous deployment, from the outputs of this very stack. A stack
+/// that was never deployed has none.
+fn read_stable_random<C: ResourceCallback>(
+    eval: &Evaluator<'_, C>,
+    context: &EvaluatorContext,
+) -> Result<HashMap<String, String>, EngineError> {
+    let stack = if context.organization.is_empty() {
+        format!("{}/{}", context.project, context.stack)
+    } else {
+        format!(
+            "{}/{}/{}",
+            context.organization, context.project, context.stack
+        )
+    };
+    let args = HashMap::from([("name".to_string(), Value::String(stack.into()))]);
+    let resp = eval.callback().invoke(
+        stable_random::READ_STACK_OUTPUTS,
+        args,
+        "",
+        "",
+        eval.stack_urn.as_deref().unwrap_or_default(),
+        &[],
+    )?;
+    if let Some((property, reason)) = resp.failures.first() {
+        return Err(EngineError::Invoke(format!("{}: {}", property, reason)));
     }
+    Ok(stable_random::values_from_stack_outputs(
+        &resp.return_values,
+    ))
 }
 
 /// Loads templates from the Jinja source temp directory (exec wrapper mode).
 ///
 /// When the exec wrapper is active, original Jinja sources are stored in a temp
 /// directory. This function reads them, preprocesses with Jinja, parses, and
-/// merges into a single template.
+/// merges into a single template. Jinja reads the files it references
+/// through `fs`.
 fn load_from_jinja_source(
     jinja_source: &str,
     program_directory: &str,
     jinja_ctx: &JinjaContext<'_>,
+    fs: &Arc<dyn FsProvider>,
 ) -> Result<
     (
         pulumi_rs_yaml_core::ast::template::TemplateDecl<'static>,
@@ -301,7 +594,7 @@ fn load_from_jinja_source(
         // Multi-file mode: temp directory contains *.original files
         let dir = Path::new(program_directory);
         let project_files = multi_file::discover_project_files(dir)?;
-        let preprocessor = JinjaPreprocessor::new(jinja_ctx);
+        let preprocessor = JinjaPreprocessor::new(jinja_ctx).with_fs(Arc::clone(fs));
 
         // Read originals from temp dir, preprocess, parse
         let main_filename = project_files
@@ -339,6 +632,7 @@ fn load_from_jinja_source(
         if main_diags.has_errors() {
             return Err("failed to parse main template".to_string());
         }
+        let duplicate_keys = main_template.pulumi.duplicate_keys;
 
         let mut additional = Vec::new();
         for path in &project_files.additional_files {
@@ -361,11 +655,17 @@ fn load_from_jinja_source(
                 .preprocess(&source, &filename)
                 .map_err(|e| format!("Jinja error in {}: {}", filename, e))?;
 
-            if let Err(diag) = validate_rendered_yaml(rendered.as_ref(), &source, &filename) {
+            if let Err(diag) = validate_rendered_yaml_with_duplicate_keys(
+                rendered.as_ref(),
+                &source,
+                &filename,
+                duplicate_keys,
+            ) {
                 return Err(diag.format_rich(&filename));
             }
 
-            let (template, parse_diags) = parse_template(rendered.as_ref(), None);
+            let (template, parse_diags) =
+                parse_template_with_duplicate_keys(rendered.as_ref(), None, duplicate_keys);
             if parse_diags.has_errors() {
                 return Err(format!("failed to parse {}", filename));
             }
@@ -390,7 +690,7 @@ fn load_from_jinja_source(
         let source = std::fs::read_to_string(jinja_source)
             .map_err(|e| format!("failed to read Jinja source from {}: {}", jinja_source, e))?;
 
-        let preprocessor = JinjaPreprocessor::new(jinja_ctx);
+        let preprocessor = JinjaPreprocessor::new(jinja_ctx).with_fs(Arc::clone(fs));
         let rendered = preprocessor
             .preprocess(&source, "Pulumi.yaml")
             .map_err(|e| format!("Jinja error: {}", e))?;
@@ -410,3 +710,412 @@ fn load_from_jinja_source(
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::tests::TempCwd;
+    use pulumi_rs_yaml_core::eval::protobuf::{protobuf_to_value, value_to_protobuf};
+    use pulumi_rs_yaml_mock_engine::{Call, MockEngine};
+
+    const PROGRAM: &str = r#"
+name: proj
+runtime: yaml
+config:
+  password:
+    type: string
+    secret: true
+resources:
+  db:
+    type: test:index:Database
+    properties:
+      adminPassword: ${password}
+      token: plain-token
+      endpoint: db.example.com
+    options:
+      additionalSecretOutputs:
+        - token
+outputs:
+  endpoint: ${db.endpoint}
+  password: ${password}
+  token: ${db.token}
+  wrapped:
+    fn::secret: hidden
+  nested:
+    plain: ok
+    secret: ${password}
+"#;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_stack_outputs_carry_secret_markers() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("Pulumi.yaml"), PROGRAM).unwrap();
+        let program_directory = dir.path().to_str().unwrap();
+        let _cwd = TempCwd::new(dir.path());
+
+        let engine = MockEngine::start().await;
+        let mut config = HashMap::new();
+        config.insert("proj:password".to_string(), "hunter2".to_string());
+        let result = run(
+            &EvaluatorContext::builder("proj", "dev")
+                .cwd(program_directory)
+                .root_directory(program_directory)
+                .organization("")
+                .build()
+                .unwrap(),
+            &engine.monitor_address,
+            &engine.engine_address,
+            &config,
+            &[],
+            program_directory,
+            None,
+            0,
+            &Default::default(),
+        )
+        .await;
+        assert!(!result.bail, "{}", result.error);
+        assert!(result.error.is_empty(), "{}", result.error);
+
+        let recorded = engine.recorded();
+        let db = recorded
+            .registrations
+            .iter()
+            .find(|r| r.name == "db")
+            .unwrap();
+        assert_eq!(db.additional_secret_outputs, vec!["token".to_string()]);
+
+        assert_eq!(recorded.outputs.len(), 1);
+        let request = &recorded.outputs[0];
+        assert_eq!(
+            request.urn,
+            "urn:pulumi:dev::proj::pulumi:pulumi:Stack::proj-dev"
+        );
+
+        let fields = &request.outputs.as_ref().unwrap().fields;
+        let output = |key: &str| protobuf_to_value(fields[key].clone());
+        assert_eq!(output("endpoint"), Value::String("db.example.com".into()));
+        assert!(output("password").is_secret());
+        assert!(output("token").is_secret());
+        assert!(output("wrapped").is_secret());
+
+        let Value::Object(nested) = output("nested") else {
+            panic!("nested output should be an object");
+        };
+        let nested: HashMap<_, _> = nested.into_iter().collect();
+        assert!(!nested["plain"].is_secret());
+        assert!(nested["secret"].is_secret());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_pulumi_variable_matches_jinja_context() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Pulumi.yaml"),
+            r#"
+name: proj
+runtime: yaml
+outputs:
+  args: ${pulumi.args}
+  jinjaArgs: "{{ pulumi_args | join(',') }}"
+  organization: ${pulumi.organization}
+  jinjaOrganization: "{{ pulumi_organization }}"
+  rootDirectory: ${pulumi.rootDirectory}
+  jinjaRootDirectory: "{{ pulumi_root_directory }}"
+  cwd: ${pulumi.cwd}
+  jinjaCwd: "{{ pulumi_cwd }}"
+"#,
+        )
+        .unwrap();
+        let program_directory = dir.path().to_str().unwrap();
+        let _cwd = TempCwd::new(dir.path());
+
+        let engine = MockEngine::start().await;
+        let args = vec!["--verbose".to_string(), "blue".to_string()];
+        let result = run(
+            &EvaluatorContext::builder("proj", "dev")
+                .cwd(program_directory)
+                .root_directory("/workspace")
+                .organization("acme")
+                .args(args.clone())
+                .dry_run(true)
+                .build()
+                .unwrap(),
+            &engine.monitor_address,
+            &engine.engine_address,
+            &HashMap::new(),
+            &[],
+            program_directory,
+            None,
+            0,
+            &Default::default(),
+        )
+        .await;
+        assert!(result.error.is_empty(), "{}", result.error);
+
+        let recorded = engine.recorded();
+        let fields = &recorded.outputs[0].outputs.as_ref().unwrap().fields;
+        let output = |key: &str| protobuf_to_value(fields[key].clone());
+        assert_eq!(
+            output("args"),
+            Value::List(vec![
+                Value::String("--verbose".into()),
+                Value::String("blue".into())
+            ])
+        );
+        assert_eq!(output("jinjaArgs"), Value::String("--verbose,blue".into()));
+        assert_eq!(output("organization"), Value::String("acme".into()));
+        assert_eq!(output("jinjaOrganization"), output("organization"));
+        assert_eq!(output("rootDirectory"), Value::String("/workspace".into()));
+        assert_eq!(output("jinjaRootDirectory"), output("rootDirectory"));
+        assert_eq!(output("cwd"), Value::String(program_directory.into()));
+        assert_eq!(output("jinjaCwd"), output("cwd"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_stable_random_round_trips_through_stack_outputs() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Pulumi.yaml"),
+            r#"
+name: proj
+runtime:
+  name: yaml
+  options:
+    stableRandom: true
+variables:
+  password:
+    fn::randomString: 16
+"#,
+        )
+        .unwrap();
+        let _cwd = TempCwd::new(dir.path());
+
+        let engine = MockEngine::start().await;
+        let result = run_in(dir.path(), &engine).await;
+        assert!(result.error.is_empty(), "{}", result.error);
+
+        let recorded = engine.recorded();
+        assert!(recorded.reads.is_empty());
+        let read = recorded
+            .invokes
+            .iter()
+            .find(|i| i.tok == stable_random::READ_STACK_OUTPUTS)
+            .unwrap();
+        let stack = protobuf_to_value(read.args.as_ref().unwrap().fields["name"].clone());
+        assert_eq!(stack, Value::String("proj/dev".into()));
+
+        let fields = &recorded.outputs[0].outputs.as_ref().unwrap().fields;
+        let stored = protobuf_to_value(fields[stable_random::OUTPUT_NAME].clone());
+        assert!(stored.is_secret());
+        let Value::Object(values) = stored.unwrap_secret() else {
+            panic!("stable random output should be an object");
+        };
+        assert_eq!(values.len(), 1);
+        assert_eq!(values[0].0, "password#0");
+        assert_eq!(values[0].1.as_str().map(str::len), Some(16));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_stable_random_keeps_previous_values() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Pulumi.yaml"),
+            r#"
+name: proj
+runtime:
+  name: yaml
+  options:
+    stableRandom: true
+variables:
+  password:
+    fn::randomString: 8
+"#,
+        )
+        .unwrap();
+        let _cwd = TempCwd::new(dir.path());
+
+        // The previous deployment also stored a value for a call this run
+        // does not evaluate.
+        let engine = MockEngine::builder()
+            .on_invoke(|_| {
+                let previous = Value::Object(vec![(
+                    stable_random::OUTPUT_NAME.into(),
+                    Value::Object(vec![
+                        ("password#0".into(), Value::String("previous".into())),
+                        ("token#0".into(), Value::String("kept".into())),
+                    ]),
+                )]);
+                let mut result = prost_types::Struct::default();
+                result
+                    .fields
+                    .insert("outputs".to_string(), value_to_protobuf(&previous));
+                Ok(result)
+            })
+            .start()
+            .await;
+        let result = run_in(dir.path(), &engine).await;
+        assert!(result.error.is_empty(), "{}", result.error);
+
+        let recorded = engine.recorded();
+        let fields = &recorded.outputs[0].outputs.as_ref().unwrap().fields;
+        let stored = protobuf_to_value(fields[stable_random::OUTPUT_NAME].clone());
+        let Value::Object(values) = stored.unwrap_secret() else {
+            panic!("stable random output should be an object");
+        };
+        let value = |key: &str| {
+            values
+                .iter()
+                .find(|(k, _)| k == key)
+                .and_then(|(_, v)| v.as_str())
+        };
+        assert_eq!(value("password#0"), Some("previous"));
+        assert_eq!(value("token#0"), Some("kept"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_stable_random_read_failure_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Pulumi.yaml"),
+            "name: proj\nruntime:\n  name: yaml\n  options:\n    stableRandom: true\n",
+        )
+        .unwrap();
+        let _cwd = TempCwd::new(dir.path());
+
+        let engine = MockEngine::builder()
+            .fail(
+                Call::Invoke,
+                stable_random::READ_STACK_OUTPUTS,
+                tonic::Status::internal("backend unavailable"),
+            )
+            .start()
+            .await;
+        let result = run_in(dir.path(), &engine).await;
+        assert!(result.is_internal());
+        assert!(
+            result.error.contains("backend unavailable"),
+            "{}",
+            result.error
+        );
+        assert!(engine.recorded().outputs.is_empty());
+    }
+
+    async fn run_in(dir: &Path, engine: &MockEngine) -> RunResult {
+        let program_directory = dir.to_str().unwrap();
+        run(
+            &EvaluatorContext::builder("proj", "dev")
+                .cwd(program_directory)
+                .root_directory(program_directory)
+                .organization("")
+                .build()
+                .unwrap(),
+            &engine.monitor_address,
+            &engine.engine_address,
+            &HashMap::new(),
+            &[],
+            program_directory,
+            None,
+            0,
+            &Default::default(),
+        )
+        .await
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_program_errors_bail_after_logging_once() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Pulumi.yaml"),
+            r#"
+name: proj
+runtime: yaml
+variables:
+  check:
+    fn::assert:
+      condition: false
+      message: replicas must be positive
+"#,
+        )
+        .unwrap();
+        let _cwd = TempCwd::new(dir.path());
+
+        let engine = MockEngine::start().await;
+        let result = run_in(dir.path(), &engine).await;
+        assert!(result.bail, "the failed assertion should abort the run");
+        assert!(!result.is_internal(), "{}", result.error);
+
+        let recorded = engine.recorded();
+        let errors: Vec<_> = recorded
+            .logs
+            .iter()
+            .filter(|log| log.severity == 3)
+            .collect();
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].message.contains("replicas must be positive"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_invoke_results_and_registration_faults() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Pulumi.yaml"),
+            r#"
+name: proj
+runtime: yaml
+variables:
+  zone:
+    fn::invoke:
+      function: test:index:getZone
+      arguments:
+        region: us-east-1
+      return: name
+resources:
+  db:
+    type: test:index:Database
+    properties:
+      zone: ${zone}
+  cache:
+    type: test:index:Cache
+    properties:
+      zone: ${db.zone}
+"#,
+        )
+        .unwrap();
+        let _cwd = TempCwd::new(dir.path());
+
+        let engine = MockEngine::builder()
+            .latency(std::time::Duration::from_millis(5))
+            .on_invoke(|req| {
+                let mut result = req.args.clone().unwrap_or_default();
+                result.fields.insert(
+                    "name".to_string(),
+                    value_to_protobuf(&Value::String("us-east-1a".into())),
+                );
+                Ok(result)
+            })
+            .fail(
+                Call::RegisterResource,
+                "cache",
+                tonic::Status::unavailable("provider crashed"),
+            )
+            .start()
+            .await;
+        let result = run_in(dir.path(), &engine).await;
+        assert!(result.bail, "the failed registration should abort the run");
+
+        let recorded = engine.recorded();
+        assert!(recorded.logs.iter().any(|log| log.severity == 3
+            && log.message.contains("'cache'")
+            && log.message.contains("provider crashed")));
+        assert_eq!(recorded.invokes.len(), 1);
+        assert_eq!(recorded.invokes[0].tok, "test:index/getZone:getZone");
+        let db = recorded
+            .registrations
+            .iter()
+            .find(|r| r.name == "db")
+            .unwrap();
+        let zone = db.object.as_ref().unwrap().fields["zone"].clone();
+        assert_eq!(protobuf_to_value(zone), Value::String("us-east-1a".into()));
+        assert!(recorded.registrations.iter().any(|r| r.name == "cache"));
+    }
+}
@@ -22,9 +22,10 @@ impl SchemaLoader {
         // Provider schemas (e.g. gcp classic, ~56 MB) exceed tonic's default
         // 4 MiB decode cap — raise it so GetSchema succeeds and type checking /
         // preview fidelity work. See core::MAX_GRPC_MESSAGE_BYTES.
-        let client = codegen::loader_client::LoaderClient::connect(url)
+        let channel = crate::channel::connect(url)
             .await
-            .map_err(|e| format!("failed to connect to schema loader: {}", e))?
+            .map_err(|e| format!("failed to connect to schema loader: {}", e))?;
+        let client = codegen::loader_client::LoaderClient::new(channel)
             .max_decoding_message_size(pulumi_rs_yaml_core::MAX_GRPC_MESSAGE_BYTES)
             .max_encoding_message_size(pulumi_rs_yaml_core::MAX_GRPC_MESSAGE_BYTES);
         Ok(Self {
@@ -37,6 +38,7 @@ impl SchemaLoader {
     ///
     /// Uses `block_in_place` to run async calls synchronously, matching
     /// the pattern in `clients.rs` for `GrpcCallback`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(packages = packages.len())))]
     pub fn fetch_and_build_store(mut self, packages: &[PackageDependency]) -> SchemaStore {
         let mut store = SchemaStore::new();
 
@@ -3,48 +3,120 @@
 use std::collections::HashMap;
 use std::path::Path;
 use std::pin::Pin;
+use std::sync::{Arc, RwLock};
 
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request, Response, Status};
 
+use pulumi_rs_yaml_core::eval::context::EvaluatorContext;
+use pulumi_rs_yaml_core::eval::extensions::BuiltinRegistry;
+use pulumi_rs_yaml_core::lockfile::Lockfile;
 use pulumi_rs_yaml_core::multi_file;
 use pulumi_rs_yaml_core::packages;
 use pulumi_rs_yaml_proto::pulumirpc;
 
-use crate::runner;
+use crate::lock;
+use crate::runner::{self, RunResult};
 
 /// The YAML language host implementation.
 pub struct YamlLanguageHost {
-    /// Address of the Pulumi engine gRPC server.
-    pub engine_address: String,
+    /// Address of the Pulumi engine gRPC server: from the command line,
+    /// replaced by the one sent in the handshake.
+    engine_address: RwLock<String>,
+    /// The program directory to fall back to when a request carries none:
+    /// from `--root`, replaced by the one sent in the handshake.
+    root_directory: RwLock<Option<String>>,
+    /// Host-provided `fn::<namespace>:<name>` builtins, available to every
+    /// program the host runs.
+    extensions: BuiltinRegistry,
 }
 
 impl YamlLanguageHost {
     pub fn new(engine_address: String) -> Self {
-        Self { engine_address }
+        Self {
+            engine_address: RwLock::new(engine_address),
+            root_directory: RwLock::new(None),
+            extensions: BuiltinRegistry::new(),
+        }
+    }
+
+    /// Makes the builtins in `extensions` available to programs. Their
+    /// namespaces are not reported as required packages.
+    pub fn with_extensions(mut self, extensions: BuiltinRegistry) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    /// Returns the address of the engine.
+    fn engine_address(&self) -> String {
+        self.engine_address.read().unwrap().clone()
+    }
+
+    /// Returns the engine address and program arguments for a `Run` request.
+    ///
+    /// An engine attaching to a host it did not start (`pulumi up --client`)
+    /// passes its address as the first program argument instead.
+    fn run_engine_address(&self, args: &[String]) -> (String, Vec<String>) {
+        let address = self.engine_address();
+        match args.split_first() {
+            Some((first, rest)) if address.is_empty() => (first.clone(), rest.to_vec()),
+            _ => (address, args.to_vec()),
+        }
+    }
+
+    /// Sets the directory used for requests that carry no program directory.
+    pub fn set_root_directory(&self, dir: String) {
+        *self.root_directory.write().unwrap() = Some(dir);
+    }
+
+    /// Returns the program directory for a request.
+    ///
+    /// Prefers the request's `ProgramInfo`: its program directory, resolved
+    /// against its root directory if relative, else the root directory. Then
+    /// falls back to the directory from `--root` or the handshake, and
+    /// finally to `fallback` (the request's working directory, if any).
+    fn program_directory(&self, info: Option<&pulumirpc::ProgramInfo>, fallback: &str) -> String {
+        if let Some(info) = info {
+            match (
+                info.program_directory.as_str(),
+                info.root_directory.as_str(),
+            ) {
+                ("", "") => {}
+                ("", root) => return root.to_string(),
+                (program, root) => return Path::new(root).join(program).display().to_string(),
+            }
+        }
+        self.root_directory
+            .read()
+            .unwrap()
+            .clone()
+            .unwrap_or_else(|| fallback.to_string())
     }
 
     /// Loads all template files from a program directory and extracts referenced packages.
     ///
     /// Scans all `Pulumi.*.yaml` files for resource types to determine required packages.
+    /// If the project has a lockfile, packages are pinned to it and the lockfile is
+    /// returned for its plugin checksums.
     #[allow(clippy::result_large_err)]
     fn load_and_get_packages(
         &self,
         program_directory: &str,
-    ) -> Result<Vec<packages::PackageDependency>, Status> {
+    ) -> Result<(Vec<packages::PackageDependency>, Option<Lockfile>), Status> {
         let dir = Path::new(program_directory);
 
         // Load and merge all project files (without Jinja preprocessing for package discovery)
         let (merged, load_diags) = multi_file::load_project(dir, None);
         if load_diags.has_errors() {
             // Swallow errors to allow project config to evaluate
-            return Ok(Vec::new());
+            return Ok((Vec::new(), None));
         }
 
-        let template = merged.as_template_decl();
-        let lock_packages = packages::search_package_decls(dir);
-        Ok(packages::get_referenced_packages(&template, &lock_packages))
+        let (mut packages, lockfile) =
+            lock::locked_packages(dir, &merged).map_err(Status::failed_precondition)?;
+        self.extensions.retain_plugin_packages(&mut packages);
+        Ok((packages, lockfile))
     }
 }
 
@@ -71,13 +143,9 @@ impl pulumirpc::language_runtime_server::LanguageRuntime for YamlLanguageHost {
         request: Request<pulumirpc::GetRequiredPackagesRequest>,
     ) -> Result<Response<pulumirpc::GetRequiredPackagesResponse>, Status> {
         let req = request.into_inner();
-        let program_dir = req
-            .info
-            .as_ref()
-            .map(|i| i.program_directory.as_str())
-            .unwrap_or("");
+        let program_dir = self.program_directory(req.info.as_ref(), "");
 
-        let packages = self.load_and_get_packages(program_dir)?;
+        let (packages, lockfile) = self.load_and_get_packages(&program_dir)?;
 
         let proto_packages: Vec<pulumirpc::PackageDependency> = packages
             .iter()
@@ -96,7 +164,18 @@ impl pulumirpc::language_runtime_server::LanguageRuntime for YamlLanguageHost {
                     kind: "resource".to_string(),
                     version: pkg.version.clone(),
                     server: pkg.download_url.clone(),
-                    checksums: HashMap::new(),
+                    checksums: lockfile
+                        .as_ref()
+                        .and_then(|lock| lock.checksums(pkg))
+                        .map(|checksums| {
+                            checksums
+                                .iter()
+                                .filter_map(|(platform, hex)| {
+                                    Some((platform.clone(), hex_decode(hex)?))
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default(),
                     parameterization,
                 }
             })
@@ -113,38 +192,60 @@ impl pulumirpc::language_runtime_server::LanguageRuntime for YamlLanguageHost {
     ) -> Result<Response<pulumirpc::RunResponse>, Status> {
         let req = request.into_inner();
 
-        let program_dir = req
-            .info
-            .as_ref()
-            .map(|i| i.program_directory.as_str())
-            .unwrap_or(&req.pwd);
+        let (engine_address, args) = self.run_engine_address(&req.args);
+        let program_dir = self.program_directory(req.info.as_ref(), &req.pwd);
+        let root_dir = match req.info.as_ref() {
+            Some(info) if !info.root_directory.is_empty() => info.root_directory.clone(),
+            _ => program_dir.clone(),
+        };
 
-        let loader_target = if req.loader_target.is_empty() {
-            None
+        // File paths in the program are relative to the program directory,
+        // not to the directory the host was started in.
+        let cwd = if program_dir.is_empty() {
+            req.pwd.clone()
         } else {
-            Some(req.loader_target.as_str())
+            program_dir.clone()
+        };
+        let context = EvaluatorContext::builder(&req.project, &req.stack)
+            .cwd(cwd)
+            .root_directory(root_dir)
+            .organization(&req.organization)
+            .args(args)
+            .dry_run(req.dry_run)
+            .build()
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        // The program runs on a task of its own, so that a panic in the host
+        // fails this run with an internal error instead of the connection.
+        let extensions = self.extensions.clone();
+        let task = tokio::spawn(async move {
+            let loader_target = Some(req.loader_target.as_str()).filter(|t| !t.is_empty());
+            runner::run(
+                &context,
+                &req.monitor_address,
+                &engine_address,
+                &req.config,
+                &req.config_secret_keys,
+                &program_dir,
+                loader_target,
+                req.parallel,
+                &extensions,
+            )
+            .await
+        });
+        let result = match task.await {
+            Ok(result) => result,
+            Err(e) => match e.try_into_panic() {
+                Ok(payload) => RunResult::internal(format!(
+                    "the language host panicked: {}",
+                    panic_message(payload.as_ref())
+                )),
+                Err(e) => RunResult::internal(e.to_string()),
+            },
         };
 
-        let result = runner::run(
-            &req.project,
-            &req.stack,
-            &req.pwd,
-            &req.monitor_address,
-            &self.engine_address,
-            &req.config,
-            &req.config_secret_keys,
-            req.dry_run,
-            program_dir,
-            &req.organization,
-            loader_target,
-            req.parallel,
-        )
-        .await;
-
-        Ok(Response::new(pulumirpc::RunResponse {
-            error: result.error,
-            bail: result.bail,
-        }))
+        let request_id = uuid::Uuid::new_v4().to_string();
+        Ok(Response::new(run_response(result, &request_id)))
     }
 
     async fn get_plugin_info(
@@ -160,7 +261,8 @@ impl pulumirpc::language_runtime_server::LanguageRuntime for YamlLanguageHost {
         &self,
         _request: Request<pulumirpc::InstallDependenciesRequest>,
     ) -> Result<Response<Self::InstallDependenciesStream>, Status> {
-        // YAML has no dependencies to install.
+        // YAML has no dependencies to install; plugins are installed by the
+        // engine from GetRequiredPackages, pinned by the lockfile.
         // Send an empty stream that completes immediately.
         let (tx, rx) = mpsc::channel(1);
         drop(tx); // Close immediately
@@ -192,13 +294,9 @@ impl pulumirpc::language_runtime_server::LanguageRuntime for YamlLanguageHost {
         request: Request<pulumirpc::GetProgramDependenciesRequest>,
     ) -> Result<Response<pulumirpc::GetProgramDependenciesResponse>, Status> {
         let req = request.into_inner();
-        let program_dir = req
-            .info
-            .as_ref()
-            .map(|i| i.program_directory.as_str())
-            .unwrap_or("");
+        let program_dir = self.program_directory(req.info.as_ref(), "");
 
-        let packages = self.load_and_get_packages(program_dir)?;
+        let (packages, _) = self.load_and_get_packages(&program_dir)?;
 
         let deps: Vec<pulumirpc::DependencyInfo> = packages
             .iter()
@@ -219,11 +317,7 @@ impl pulumirpc::language_runtime_server::LanguageRuntime for YamlLanguageHost {
     ) -> Result<Response<Self::RunPluginStream>, Status> {
         let req = request.into_inner();
 
-        let program_directory = req
-            .info
-            .as_ref()
-            .map(|i| i.program_directory.clone())
-            .unwrap_or_default();
+        let program_directory = self.program_directory(req.info.as_ref(), "");
 
         if program_directory.is_empty() {
             return Err(Status::invalid_argument(
@@ -247,26 +341,26 @@ impl pulumirpc::language_runtime_server::LanguageRuntime for YamlLanguageHost {
             ));
         }
 
-        // Generate schema JSON from component declarations
-        let schema = pulumi_rs_yaml_core::schema::generate_component_schema(&template);
-        let schema_json = serde_json::to_string(&schema)
-            .map_err(|e| Status::internal(format!("schema serialization failed: {}", e)))?;
-
-        // Leak the template for 'static lifetime (process-scoped)
-        let template: &'static _ = Box::leak(Box::new(template));
+        // Generate the schema up front so a broken template fails RunPlugin
+        // rather than the first GetSchema call.
+        let schema = crate::component_provider::ComponentSchema::new(&program_directory);
+        schema.get().map_err(Status::internal)?;
 
         // Determine monitor address from env (set by engine before calling RunPlugin)
         let monitor_address = std::env::var("PULUMI_MONITOR_ADDRESS").unwrap_or_default();
 
         // Create the component provider
         let provider = crate::component_provider::ComponentProvider {
-            engine_address: self.engine_address.clone(),
+            engine_address: self.engine_address(),
             monitor_address,
-            template,
-            schema_json,
+            template: Arc::new(template),
+            schema,
             project: String::new(),
             stack: String::new(),
             dry_run: false,
+            program_directory: program_directory.clone(),
+            evaluators: pulumi_rs_yaml_core::eval::factory::EvaluatorFactory::new(),
+            constructing: Default::default(),
         };
 
         // Spawn a gRPC server for the component provider on a random port
@@ -627,8 +721,19 @@ impl pulumirpc::language_runtime_server::LanguageRuntime for YamlLanguageHost {
 
     async fn handshake(
         &self,
-        _request: Request<pulumirpc::LanguageHandshakeRequest>,
+        request: Request<pulumirpc::LanguageHandshakeRequest>,
     ) -> Result<Response<pulumirpc::LanguageHandshakeResponse>, Status> {
+        let req = request.into_inner();
+        if !req.engine_address.is_empty() {
+            *self.engine_address.write().unwrap() = req.engine_address;
+        }
+        if let Some(dir) = req
+            .program_directory
+            .or(req.root_directory)
+            .filter(|d| !d.is_empty())
+        {
+            self.set_root_directory(dir);
+        }
         Ok(Response::new(pulumirpc::LanguageHandshakeResponse {}))
     }
 
@@ -648,6 +753,50 @@ impl pulumirpc::language_runtime_server::LanguageRuntime for YamlLanguageHost {
     }
 }
 
+/// Decodes a hex string, such as a lockfile checksum, to bytes. `None` if it
+/// is not valid hex.
+fn hex_decode(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Builds the response to a `Run` request. Program errors have already been
+/// reported, so only a failure of the host itself carries a message: it
+/// names `request_id`, which is logged to stderr with it, so that a report
+/// can be matched with the host's logs.
+fn run_response(result: RunResult, request_id: &str) -> pulumirpc::RunResponse {
+    if !result.is_internal() {
+        return pulumirpc::RunResponse {
+            error: String::new(),
+            bail: result.bail,
+        };
+    }
+    eprintln!("internal error (request {}): {}", request_id, result.error);
+    pulumirpc::RunResponse {
+        error: format!(
+            "internal error in the YAML language host (request {}): {}",
+            request_id, result.error
+        ),
+        bail: false,
+    }
+}
+
+/// Returns the message a panic was raised with.
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
 /// Decodes a base64 string to bytes, returning empty on failure.
 fn base64_decode_or_empty(s: &str) -> Vec<u8> {
     use base64::Engine;
@@ -655,3 +804,140 @@ fn base64_decode_or_empty(s: &str) -> Vec<u8> {
         .decode(s)
         .unwrap_or_default()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_program_directory_resolution() {
+        let host = YamlLanguageHost::new("127.0.0.1:1".to_string());
+        let info = |root: &str, program: &str| pulumirpc::ProgramInfo {
+            root_directory: root.to_string(),
+            program_directory: program.to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(host.program_directory(None, "/pwd"), "/pwd");
+        host.set_root_directory("/work/app".to_string());
+        assert_eq!(host.program_directory(None, "/pwd"), "/work/app");
+        assert_eq!(
+            host.program_directory(Some(&info("", "")), "/pwd"),
+            "/work/app"
+        );
+        assert_eq!(
+            host.program_directory(Some(&info("/proj", "")), "/pwd"),
+            "/proj"
+        );
+        assert_eq!(
+            host.program_directory(Some(&info("/proj", "/proj/infra")), "/pwd"),
+            "/proj/infra"
+        );
+        assert_eq!(
+            host.program_directory(Some(&info("/proj", "infra")), "/pwd"),
+            "/proj/infra"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handshake_sets_engine_address() {
+        use pulumirpc::language_runtime_server::LanguageRuntime;
+
+        // In debug mode the host starts without an engine address.
+        let host = YamlLanguageHost::new(String::new());
+        host.handshake(Request::new(pulumirpc::LanguageHandshakeRequest {
+            engine_address: "127.0.0.1:4000".to_string(),
+            root_directory: Some("/work/app".to_string()),
+            ..Default::default()
+        }))
+        .await
+        .unwrap();
+        assert_eq!(host.engine_address(), "127.0.0.1:4000");
+        assert_eq!(host.program_directory(None, ""), "/work/app");
+    }
+
+    #[test]
+    fn test_run_response_separates_bail_from_internal_errors() {
+        let response = run_response(RunResult::bail(), "req-1");
+        assert!(response.bail);
+        assert!(response.error.is_empty());
+
+        let response = run_response(RunResult::ok(), "req-1");
+        assert!(!response.bail);
+        assert!(response.error.is_empty());
+
+        let response = run_response(RunResult::internal("failed to connect"), "req-1");
+        assert!(!response.bail);
+        assert_eq!(
+            response.error,
+            "internal error in the YAML language host (request req-1): failed to connect"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_panic_message() {
+        let task = tokio::spawn(async { panic!("evaluator state corrupted") });
+        let payload = task.await.unwrap_err().into_panic();
+        assert_eq!(panic_message(payload.as_ref()), "evaluator state corrupted");
+    }
+
+    #[tokio::test]
+    async fn test_required_packages_skip_extension_namespaces() {
+        use pulumi_rs_yaml_proto::pulumirpc::language_runtime_server::LanguageRuntime;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Pulumi.yaml"),
+            "name: test\nruntime: yaml\nvariables:\n  slug:\n    fn::myns:foo: hello\n  \
+             ami:\n    fn::invoke:\n      function: aws:ec2:getAmi\n",
+        )
+        .unwrap();
+        let mut extensions = BuiltinRegistry::new();
+        extensions.register("myns:foo", |v| Ok(v.clone())).unwrap();
+        let host = YamlLanguageHost::new(String::new()).with_extensions(extensions);
+        let response = host
+            .get_required_packages(Request::new(pulumirpc::GetRequiredPackagesRequest {
+                info: Some(pulumirpc::ProgramInfo {
+                    root_directory: dir.path().display().to_string(),
+                    ..Default::default()
+                }),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let names: Vec<&str> = response.packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["aws"]);
+    }
+
+    #[test]
+    fn test_release_profile_unwinds() {
+        // Tests always unwind; the release profile must too, or a panic in
+        // `run` aborts the host before it can be reported.
+        let manifest = include_str!("../../../Cargo.toml");
+        assert!(
+            !manifest.lines().any(|line| {
+                let line = line.split('#').next().unwrap_or("").replace(' ', "");
+                line == "panic=\"abort\""
+            }),
+            "the workspace profiles must not set panic = \"abort\""
+        );
+    }
+
+    #[test]
+    fn test_run_engine_address_from_args() {
+        let args = vec!["127.0.0.1:4000".to_string(), "--verbose".to_string()];
+
+        // Served in-process, the host learns the engine from the arguments.
+        let host = YamlLanguageHost::new(String::new());
+        assert_eq!(
+            host.run_engine_address(&args),
+            ("127.0.0.1:4000".to_string(), vec!["--verbose".to_string()])
+        );
+
+        let host = YamlLanguageHost::new("127.0.0.1:5000".to_string());
+        assert_eq!(
+            host.run_engine_address(&args),
+            ("127.0.0.1:5000".to_string(), args.clone())
+        );
+    }
+}
@@ -0,0 +1,129 @@
+//! The `unused` subcommand.
+//!
+//! `pulumi-language-yaml unused [--strict] [<project-dir>]` prints the
+//! variables and resources of the project (default: the current directory)
+//! that no output, resource registration, or component output depends on,
+//! see [`pulumi_rs_yaml_core::dead_code`]. With `--strict` any unused entry
+//! fails the command.
+
+use std::path::Path;
+
+use pulumi_rs_yaml_core::dead_code::{self, DeadEntry};
+use pulumi_rs_yaml_core::eval::graph;
+use pulumi_rs_yaml_core::multi_file;
+
+/// Runs the `unused` subcommand and returns the process exit code.
+pub fn run_unused(args: &[String]) -> i32 {
+    let strict = args.iter().any(|a| a == "--strict");
+    let dir = args
+        .iter()
+        .find(|a| !a.starts_with("--"))
+        .map_or(".", |a| a.as_str());
+    match unused(Path::new(dir)) {
+        Ok((out, count)) => {
+            print!("{}", out);
+            if strict && count > 0 {
+                eprintln!("error: {} unused variables and resources", count);
+                1
+            } else {
+                0
+            }
+        }
+        Err(message) => {
+            eprintln!("error: {}", message);
+            1
+        }
+    }
+}
+
+/// Renders the unused entries of the project, one `kind name` line each,
+/// and returns how many there are.
+fn unused(dir: &Path) -> Result<(String, usize), String> {
+    let (merged, diags) = multi_file::load_project(dir, None);
+    if diags.has_errors() {
+        return Err(format!("failed to load project: {}", diags));
+    }
+    let template = merged.as_template_decl();
+    let (_, diags) = graph::topological_sort_with_deps(&template, Some(merged.source_map()));
+    if diags.has_errors() {
+        return Err(format!("invalid dependency graph: {}", diags));
+    }
+    let dead = dead_code::find_dead_code(&template);
+    Ok((
+        render(&dead, |name| merged.source_map().get(name)),
+        dead.len(),
+    ))
+}
+
+/// Renders one line per entry, with the file declaring top-level entries of
+/// a multi-file project.
+fn render<'a>(dead: &[DeadEntry], file_of: impl Fn(&str) -> Option<&'a String>) -> String {
+    if dead.is_empty() {
+        return "no unused variables or resources\n".to_string();
+    }
+    let mut out = String::from("unused (no output or registration depends on them):\n");
+    for entry in dead {
+        out.push_str("  ");
+        if let Some(component) = &entry.component {
+            out.push_str(&format!("component {}: ", component));
+        }
+        out.push_str(&format!("{} {}", entry.kind, entry.name));
+        if let Some(reason) = entry.reason {
+            out.push_str(&format!(", {}", reason));
+        }
+        if entry.component.is_none() {
+            if let Some(file) = file_of(&entry.name) {
+                out.push_str(&format!(" ({})", file));
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_unused_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Pulumi.yaml"),
+            r#"name: unused-test
+runtime: yaml
+variables:
+  prefix: app
+  legacy: ${prefix}-legacy
+resources:
+  bucket:
+    type: aws:s3:Bucket
+    properties:
+      bucket: ${prefix}-data
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("Pulumi.legacy.yaml"),
+            "resources:\n  old:\n    type: aws:s3:Bucket\n    get:\n      id: old-bucket\n",
+        )
+        .unwrap();
+
+        let (out, count) = unused(dir.path()).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(
+            out,
+            "unused (no output or registration depends on them):\n  variable legacy (Pulumi.yaml)\n  resource old, read with get: (Pulumi.legacy.yaml)\n"
+        );
+
+        std::fs::write(
+            dir.path().join("Pulumi.legacy.yaml"),
+            "outputs:\n  legacyName: ${legacy}\n",
+        )
+        .unwrap();
+        assert_eq!(
+            unused(dir.path()).unwrap(),
+            ("no unused variables or resources\n".to_string(), 0)
+        );
+    }
+}
@@ -363,6 +363,7 @@ fn generate_package_lock(dir: &std::path::Path, schema_json: &str) -> Result<(),
 /// Verify the error message format for register_package when version is empty.
 /// The format should NOT include a trailing `@` when version is absent.
 #[test]
+#[allow(clippy::const_is_empty)]
 fn test_register_package_error_format_empty_version() {
     // Simulate the error formatting logic from clients.rs
     let name = "gcpx";
@@ -388,6 +389,7 @@ fn test_register_package_error_format_empty_version() {
 
 /// Verify the error message format includes version when present.
 #[test]
+#[allow(clippy::const_is_empty)]
 fn test_register_package_error_format_with_version() {
     let name = "aws";
     let version = "6.0.0";
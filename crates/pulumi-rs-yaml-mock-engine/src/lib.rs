@@ -0,0 +1,418 @@
+//! In-process Pulumi engine and resource monitor for end-to-end tests.
+//!
+//! [`MockEngine`] serves both gRPC services on loopback ports, so a language
+//! host under test talks to it over the same tonic path it uses against a
+//! real `pulumi up`. Every monitor and engine request is recorded so tests
+//! can assert on the exact payloads the host sends.
+//!
+//! By default resource registrations and reads echo their inputs back as
+//! outputs and invokes echo their arguments. [`MockEngineBuilder`] replaces
+//! those responses, adds latency to every monitor call, and injects faults
+//! into calls for a given type token or resource name:
+//!
+//! ```no_run
+//! # async fn example() {
+//! use std::time::Duration;
+//! use pulumi_rs_yaml_mock_engine::{Call, MockEngine};
+//!
+//! let engine = MockEngine::builder()
+//!     .features(&["secrets"])
+//!     .latency(Duration::from_millis(20))
+//!     .fail(Call::RegisterResource, "aws:s3/bucket:Bucket", tonic::Status::unavailable("down"))
+//!     .start()
+//!     .await;
+//! # }
+//! ```
+
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use pulumi_rs_yaml_proto::pulumirpc;
+use tonic::{Request, Response, Status};
+
+/// Requests recorded by the mock monitor and engine.
+#[derive(Debug, Default)]
+pub struct Recorded {
+    pub registrations: Vec<pulumirpc::RegisterResourceRequest>,
+    pub outputs: Vec<pulumirpc::RegisterResourceOutputsRequest>,
+    pub reads: Vec<pulumirpc::ReadResourceRequest>,
+    pub invokes: Vec<pulumirpc::ResourceInvokeRequest>,
+    pub packages: Vec<pulumirpc::RegisterPackageRequest>,
+    pub logs: Vec<pulumirpc::LogRequest>,
+}
+
+/// A monitor call that faults can be injected into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Call {
+    RegisterResource,
+    ReadResource,
+    Invoke,
+    RegisterPackage,
+}
+
+/// Computes the outputs of a resource registration.
+pub type RegisterHandler = Arc<
+    dyn Fn(&pulumirpc::RegisterResourceRequest) -> Result<prost_types::Struct, Status>
+        + Send
+        + Sync,
+>;
+
+/// Computes the return value of an invoke.
+pub type InvokeHandler = Arc<
+    dyn Fn(&pulumirpc::ResourceInvokeRequest) -> Result<prost_types::Struct, Status> + Send + Sync,
+>;
+
+/// A fault injected into every `call` whose type token, invoke token, package
+/// name, or resource name equals `target`.
+#[derive(Debug, Clone)]
+struct Fault {
+    call: Call,
+    target: String,
+    status: Status,
+}
+
+/// How the mock monitor answers requests.
+#[derive(Clone, Default)]
+struct Behavior {
+    features: Vec<String>,
+    latency: Duration,
+    on_register: Option<RegisterHandler>,
+    on_invoke: Option<InvokeHandler>,
+    faults: Vec<Fault>,
+}
+
+impl Behavior {
+    /// Waits for the configured latency, then returns the injected fault for
+    /// the call, if any.
+    async fn enter(&self, call: Call, targets: &[&str]) -> Result<(), Status> {
+        if !self.latency.is_zero() {
+            tokio::time::sleep(self.latency).await;
+        }
+        match self
+            .faults
+            .iter()
+            .find(|f| f.call == call && targets.contains(&f.target.as_str()))
+        {
+            Some(fault) => Err(fault.status.clone()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Configures a [`MockEngine`] before starting it.
+#[derive(Default)]
+pub struct MockEngineBuilder {
+    behavior: Behavior,
+}
+
+impl MockEngineBuilder {
+    /// Makes the monitor report support for the given `SupportsFeature` ids.
+    pub fn features(mut self, features: &[&str]) -> Self {
+        self.behavior.features = features.iter().map(|f| f.to_string()).collect();
+        self
+    }
+
+    /// Delays every monitor call by `latency`.
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.behavior.latency = latency;
+        self
+    }
+
+    /// Computes registration outputs with `handler` instead of echoing the
+    /// inputs.
+    pub fn on_register(
+        mut self,
+        handler: impl Fn(&pulumirpc::RegisterResourceRequest) -> Result<prost_types::Struct, Status>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        self.behavior.on_register = Some(Arc::new(handler));
+        self
+    }
+
+    /// Computes invoke results with `handler` instead of echoing the
+    /// arguments.
+    pub fn on_invoke(
+        mut self,
+        handler: impl Fn(&pulumirpc::ResourceInvokeRequest) -> Result<prost_types::Struct, Status>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        self.behavior.on_invoke = Some(Arc::new(handler));
+        self
+    }
+
+    /// Fails every `call` for `target` with `status`. `target` is matched
+    /// against the type token and the resource name of registrations and
+    /// reads, the token of invokes, and the name of packages.
+    pub fn fail(mut self, call: Call, target: impl Into<String>, status: Status) -> Self {
+        self.behavior.faults.push(Fault {
+            call,
+            target: target.into(),
+            status,
+        });
+        self
+    }
+
+    /// Starts the monitor and engine servers on ephemeral ports.
+    pub async fn start(self) -> MockEngine {
+        let recorded = Arc::new(Mutex::new(Recorded::default()));
+        let monitor = pulumirpc::resource_monitor_server::ResourceMonitorServer::new(Monitor {
+            recorded: Arc::clone(&recorded),
+            behavior: self.behavior,
+        });
+        let engine = pulumirpc::engine_server::EngineServer::new(Engine {
+            recorded: Arc::clone(&recorded),
+        });
+
+        let monitor_address = serve(tonic::transport::Server::builder().add_service(monitor)).await;
+        let engine_address = serve(tonic::transport::Server::builder().add_service(engine)).await;
+        MockEngine {
+            monitor_address: monitor_address.to_string(),
+            engine_address: engine_address.to_string(),
+            recorded,
+        }
+    }
+}
+
+/// A running mock engine.
+pub struct MockEngine {
+    pub monitor_address: String,
+    pub engine_address: String,
+    recorded: Arc<Mutex<Recorded>>,
+}
+
+impl MockEngine {
+    /// Returns a builder for a mock engine with custom behavior.
+    pub fn builder() -> MockEngineBuilder {
+        MockEngineBuilder::default()
+    }
+
+    /// Starts the monitor and engine servers on ephemeral ports.
+    pub async fn start() -> Self {
+        Self::builder().start().await
+    }
+
+    /// Like [`MockEngine::start`], but the monitor reports support for the
+    /// given `SupportsFeature` ids.
+    pub async fn start_with_features(features: &[&str]) -> Self {
+        Self::builder().features(features).start().await
+    }
+
+    /// Returns the requests recorded so far.
+    pub fn recorded(&self) -> std::sync::MutexGuard<'_, Recorded> {
+        self.recorded.lock().unwrap()
+    }
+}
+
+async fn serve(router: tonic::transport::server::Router) -> SocketAddr {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = tokio_stream::wrappers::TcpListenerStream::new(listener);
+    tokio::spawn(router.serve_with_incoming(incoming));
+    addr
+}
+
+fn urn(type_token: &str, name: &str) -> String {
+    format!("urn:pulumi:dev::proj::{}::{}", type_token, name)
+}
+
+struct Monitor {
+    recorded: Arc<Mutex<Recorded>>,
+    behavior: Behavior,
+}
+
+#[tonic::async_trait]
+impl pulumirpc::resource_monitor_server::ResourceMonitor for Monitor {
+    async fn supports_feature(
+        &self,
+        request: Request<pulumirpc::SupportsFeatureRequest>,
+    ) -> Result<Response<pulumirpc::SupportsFeatureResponse>, Status> {
+        let id = request.into_inner().id;
+        Ok(Response::new(pulumirpc::SupportsFeatureResponse {
+            has_support: self.behavior.features.contains(&id),
+        }))
+    }
+
+    async fn invoke(
+        &self,
+        request: Request<pulumirpc::ResourceInvokeRequest>,
+    ) -> Result<Response<pulumirpc::InvokeResponse>, Status> {
+        let req = request.into_inner();
+        self.recorded.lock().unwrap().invokes.push(req.clone());
+        self.behavior.enter(Call::Invoke, &[&req.tok]).await?;
+        let result = match &self.behavior.on_invoke {
+            Some(handler) => handler(&req)?,
+            None => req.args.clone().unwrap_or_default(),
+        };
+        Ok(Response::new(pulumirpc::InvokeResponse {
+            r#return: Some(result),
+            failures: Vec::new(),
+        }))
+    }
+
+    async fn call(
+        &self,
+        _request: Request<pulumirpc::ResourceCallRequest>,
+    ) -> Result<Response<pulumirpc::CallResponse>, Status> {
+        Err(Status::unimplemented("call"))
+    }
+
+    /// Echoes the properties back, like a provider whose resource state
+    /// matches the given inputs.
+    async fn read_resource(
+        &self,
+        request: Request<pulumirpc::ReadResourceRequest>,
+    ) -> Result<Response<pulumirpc::ReadResourceResponse>, Status> {
+        let req = request.into_inner();
+        self.recorded.lock().unwrap().reads.push(req.clone());
+        self.behavior
+            .enter(Call::ReadResource, &[&req.r#type, &req.name])
+            .await?;
+        Ok(Response::new(pulumirpc::ReadResourceResponse {
+            urn: urn(&req.r#type, &req.name),
+            properties: req.properties,
+        }))
+    }
+
+    /// Echoes the inputs back as outputs, like a provider with no computed
+    /// properties, unless a registration handler is set.
+    async fn register_resource(
+        &self,
+        request: Request<pulumirpc::RegisterResourceRequest>,
+    ) -> Result<Response<pulumirpc::RegisterResourceResponse>, Status> {
+        let req = request.into_inner();
+        self.recorded
+            .lock()
+            .unwrap()
+            .registrations
+            .push(req.clone());
+        self.behavior
+            .enter(Call::RegisterResource, &[&req.r#type, &req.name])
+            .await?;
+        let id = if req.custom {
+            format!("{}-id", req.name)
+        } else {
+            String::new()
+        };
+        let object = match &self.behavior.on_register {
+            Some(handler) => Some(handler(&req)?),
+            None => req.object.clone(),
+        };
+        Ok(Response::new(pulumirpc::RegisterResourceResponse {
+            urn: urn(&req.r#type, &req.name),
+            id,
+            object,
+            ..Default::default()
+        }))
+    }
+
+    async fn register_resource_outputs(
+        &self,
+        request: Request<pulumirpc::RegisterResourceOutputsRequest>,
+    ) -> Result<Response<()>, Status> {
+        self.recorded
+            .lock()
+            .unwrap()
+            .outputs
+            .push(request.into_inner());
+        Ok(Response::new(()))
+    }
+
+    async fn register_stack_transform(
+        &self,
+        _request: Request<pulumirpc::Callback>,
+    ) -> Result<Response<()>, Status> {
+        Err(Status::unimplemented("register_stack_transform"))
+    }
+
+    async fn register_stack_invoke_transform(
+        &self,
+        _request: Request<pulumirpc::Callback>,
+    ) -> Result<Response<()>, Status> {
+        Err(Status::unimplemented("register_stack_invoke_transform"))
+    }
+
+    async fn register_resource_hook(
+        &self,
+        _request: Request<pulumirpc::RegisterResourceHookRequest>,
+    ) -> Result<Response<()>, Status> {
+        Err(Status::unimplemented("register_resource_hook"))
+    }
+
+    async fn register_error_hook(
+        &self,
+        _request: Request<pulumirpc::RegisterErrorHookRequest>,
+    ) -> Result<Response<()>, Status> {
+        Err(Status::unimplemented("register_error_hook"))
+    }
+
+    /// Hands out `<name>@<version>` as the package reference.
+    async fn register_package(
+        &self,
+        request: Request<pulumirpc::RegisterPackageRequest>,
+    ) -> Result<Response<pulumirpc::RegisterPackageResponse>, Status> {
+        let req = request.into_inner();
+        self.recorded.lock().unwrap().packages.push(req.clone());
+        self.behavior
+            .enter(Call::RegisterPackage, &[&req.name])
+            .await?;
+        Ok(Response::new(pulumirpc::RegisterPackageResponse {
+            r#ref: format!("{}@{}", req.name, req.version),
+        }))
+    }
+
+    async fn signal_and_wait_for_shutdown(
+        &self,
+        _request: Request<()>,
+    ) -> Result<Response<()>, Status> {
+        Ok(Response::new(()))
+    }
+}
+
+struct Engine {
+    recorded: Arc<Mutex<Recorded>>,
+}
+
+#[tonic::async_trait]
+impl pulumirpc::engine_server::Engine for Engine {
+    async fn log(&self, request: Request<pulumirpc::LogRequest>) -> Result<Response<()>, Status> {
+        self.recorded
+            .lock()
+            .unwrap()
+            .logs
+            .push(request.into_inner());
+        Ok(Response::new(()))
+    }
+
+    async fn get_root_resource(
+        &self,
+        _request: Request<pulumirpc::GetRootResourceRequest>,
+    ) -> Result<Response<pulumirpc::GetRootResourceResponse>, Status> {
+        Ok(Response::new(Default::default()))
+    }
+
+    async fn set_root_resource(
+        &self,
+        _request: Request<pulumirpc::SetRootResourceRequest>,
+    ) -> Result<Response<pulumirpc::SetRootResourceResponse>, Status> {
+        Ok(Response::new(Default::default()))
+    }
+
+    async fn start_debugging(
+        &self,
+        _request: Request<pulumirpc::StartDebuggingRequest>,
+    ) -> Result<Response<()>, Status> {
+        Ok(Response::new(()))
+    }
+
+    async fn require_pulumi_version(
+        &self,
+        _request: Request<pulumirpc::RequirePulumiVersionRequest>,
+    ) -> Result<Response<pulumirpc::RequirePulumiVersionResponse>, Status> {
+        Ok(Response::new(Default::default()))
+    }
+}
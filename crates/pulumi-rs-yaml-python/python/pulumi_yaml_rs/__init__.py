@@ -8,6 +8,8 @@ from pulumi_yaml_rs._native import (
     preprocess_jinja,
     evaluate_builtin,
     create_execution_plan,
+    graph_descendants,
+    graph_ancestors,
 )
 from pulumi_yaml_rs._find_binary import find_language_binary, find_converter_binary
 
@@ -21,6 +23,8 @@ __all__ = [
     "preprocess_jinja",
     "evaluate_builtin",
     "create_execution_plan",
+    "graph_descendants",
+    "graph_ancestors",
     "find_language_binary",
     "find_converter_binary",
 ]
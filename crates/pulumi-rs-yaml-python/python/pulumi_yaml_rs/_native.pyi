@@ -9,3 +9,5 @@ def validate_jinja(source: str, filename: str) -> None: ...
 def preprocess_jinja(source: str, filename: str, context: dict[str, Any]) -> str: ...
 def evaluate_builtin(name: str, args: Any) -> Any: ...
 def create_execution_plan(project_dir: str, jinja_context: Optional[dict[str, Any]] = None) -> dict[str, Any]: ...
+def graph_descendants(project_dir: str, name: str) -> list[str]: ...
+def graph_ancestors(project_dir: str, name: str) -> list[str]: ...
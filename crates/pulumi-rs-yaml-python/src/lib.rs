@@ -1,19 +1,19 @@
 mod convert;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 use pyo3::types::PyDict;
 
+use pulumi_rs_yaml_core::code_actions::{CodeActionKind, Range};
+use pulumi_rs_yaml_core::completion::CompletionKind;
 use pulumi_rs_yaml_core::diag::Diagnostics;
 use pulumi_rs_yaml_core::eval::builtins;
 use pulumi_rs_yaml_core::eval::value::Value;
+use pulumi_rs_yaml_core::vfs::OsFs;
 
-use convert::{
-    expr_to_py, py_dict_to_string_map, py_to_value, resource_options_to_py,
-    resource_properties_to_py, value_to_py,
-};
+use convert::{json_to_py, py_dict_to_string_map, py_to_value, value_to_py};
 
 /// Parse a YAML template string and return its structure as a Python dict.
 #[pyfunction]
@@ -100,6 +100,13 @@ fn discover_project_files(py: Python<'_>, dir: &str) -> PyResult<Py<PyAny>> {
         .map(|p| p.to_string_lossy().into_owned())
         .collect();
     dict.set_item("additional_files", additional)?;
+    dict.set_item(
+        "vars_file",
+        discovery
+            .vars_file
+            .as_ref()
+            .map(|p| p.to_string_lossy().into_owned()),
+    )?;
     dict.set_item("file_count", discovery.file_count())?;
 
     Ok(dict.into_any().unbind())
@@ -166,6 +173,7 @@ fn preprocess_jinja(source: &str, filename: &str, context: &Bound<'_, PyDict>) -
         config: &config,
         project_dir: &project_dir,
         undefined: pulumi_rs_yaml_core::jinja::UndefinedMode::Strict,
+        args: &[],
         extra: &extra,
     };
 
@@ -193,6 +201,44 @@ fn evaluate_builtin(py: Python<'_>, name: &str, args: Py<PyAny>) -> PyResult<Py<
         "ceil" => builtins::eval_ceil(&arg_val, &mut diags),
         "max" => builtins::eval_max(&arg_val, &mut diags),
         "min" => builtins::eval_min(&arg_val, &mut diags),
+        "formatDuration" => builtins::eval_format_duration(&arg_val, &mut diags),
+        "formatBytes" => builtins::eval_format_bytes(&arg_val, &mut diags),
+        // List
+        "sort" => builtins::eval_sort(&arg_val, &mut diags),
+        "unique" => builtins::eval_unique(&arg_val, &mut diags),
+        "length" => builtins::eval_length(&arg_val, &mut diags),
+        "coalesce" => builtins::eval_coalesce(&arg_val, &mut diags),
+        // Comparison and boolean
+        "equals" => match &arg_val {
+            Value::List(items) if items.len() == 2 => {
+                Some(builtins::eval_equals(&items[0], &items[1]))
+            }
+            _ => {
+                return Err(PyValueError::new_err(
+                    "equals expects a list of [left, right]",
+                ));
+            }
+        },
+        "not" => builtins::eval_not(&arg_val, &mut diags),
+        "and" => builtins::eval_and(&arg_val, &mut diags),
+        "or" => builtins::eval_or(&arg_val, &mut diags),
+        "lessThan" | "lessThanOrEqual" | "greaterThan" | "greaterThanOrEqual" => {
+            let compare = match name {
+                "lessThan" => builtins::eval_less_than,
+                "lessThanOrEqual" => builtins::eval_less_than_or_equal,
+                "greaterThan" => builtins::eval_greater_than,
+                _ => builtins::eval_greater_than_or_equal,
+            };
+            match &arg_val {
+                Value::List(items) if items.len() == 2 => compare(&items[0], &items[1], &mut diags),
+                _ => {
+                    return Err(PyValueError::new_err(format!(
+                        "{} expects a list of [left, right]",
+                        name
+                    )));
+                }
+            }
+        }
         // String
         "stringLen" => builtins::eval_string_len(&arg_val, &mut diags),
         "substring" => match &arg_val {
@@ -205,6 +251,61 @@ fn evaluate_builtin(py: Python<'_>, name: &str, args: Py<PyAny>) -> PyResult<Py<
                 ));
             }
         },
+        "toUpper" => builtins::eval_to_upper(&arg_val, &mut diags),
+        "toLower" => builtins::eval_to_lower(&arg_val, &mut diags),
+        "trim" => builtins::eval_trim(&arg_val, &mut diags),
+        "replace" => match &arg_val {
+            Value::List(items) if items.len() == 3 => {
+                builtins::eval_replace(&items[0], &items[1], &items[2], &mut diags)
+            }
+            _ => {
+                return Err(PyValueError::new_err(
+                    "replace expects a list of [source, search, replacement]",
+                ));
+            }
+        },
+        "startsWith" => match &arg_val {
+            Value::List(items) if items.len() == 2 => {
+                builtins::eval_starts_with(&items[0], &items[1], &mut diags)
+            }
+            _ => {
+                return Err(PyValueError::new_err(
+                    "startsWith expects a list of [source, prefix]",
+                ));
+            }
+        },
+        "format" => match &arg_val {
+            Value::List(items) if items.len() == 2 => {
+                builtins::eval_format(&items[0], &items[1], &mut diags)
+            }
+            _ => {
+                return Err(PyValueError::new_err(
+                    "format expects a list of [format, arguments]",
+                ));
+            }
+        },
+        // Network
+        "cidrSubnet" => match &arg_val {
+            Value::List(items) if items.len() == 3 => {
+                builtins::eval_cidr_subnet(&items[0], &items[1], &items[2], &mut diags)
+            }
+            _ => {
+                return Err(PyValueError::new_err(
+                    "cidrSubnet expects a list of [prefix, newbits, netnum]",
+                ));
+            }
+        },
+        "cidrHost" => match &arg_val {
+            Value::List(items) if items.len() == 2 => {
+                builtins::eval_cidr_host(&items[0], &items[1], &mut diags)
+            }
+            _ => {
+                return Err(PyValueError::new_err(
+                    "cidrHost expects a list of [prefix, hostnum]",
+                ));
+            }
+        },
+        "cidrNetmask" => builtins::eval_cidr_netmask(&arg_val, &mut diags),
         // Existing string builtins
         "join" => match &arg_val {
             Value::List(items) if items.len() == 2 => {
@@ -236,7 +337,62 @@ fn evaluate_builtin(py: Python<'_>, name: &str, args: Py<PyAny>) -> PyResult<Py<
                 ));
             }
         },
+        "lookup" => match &arg_val {
+            Value::List(items) if items.len() == 2 || items.len() == 3 => {
+                builtins::eval_lookup(&items[0], &items[1], items.get(2).cloned(), &mut diags)
+            }
+            _ => {
+                return Err(PyValueError::new_err(
+                    "lookup expects a list of [map, key] or [map, key, default]",
+                ));
+            }
+        },
+        "merge" => builtins::eval_merge(&arg_val, &mut diags),
+        "jsonPatch" => match &arg_val {
+            Value::List(items) if items.len() == 2 => {
+                builtins::eval_json_patch(&items[0], &items[1], &mut diags)
+            }
+            _ => {
+                return Err(PyValueError::new_err(
+                    "jsonPatch expects a list of [target, operations]",
+                ));
+            }
+        },
+        "templateFile" => match &arg_val {
+            Value::List(items) if items.len() == 2 => {
+                builtins::eval_template_file(&items[0], &items[1], ".", &OsFs, &mut diags)
+            }
+            _ => {
+                return Err(PyValueError::new_err(
+                    "templateFile expects a list of [path, arguments]",
+                ));
+            }
+        },
+        "validateJson" => match arg_val {
+            Value::List(mut items) if items.len() == 2 => {
+                let schema = items.pop().unwrap();
+                let value = items.pop().unwrap();
+                builtins::eval_validate_json(value, &schema, ".", &OsFs, &mut diags)
+            }
+            _ => {
+                return Err(PyValueError::new_err(
+                    "validateJson expects a list of [value, schema]",
+                ));
+            }
+        },
+        "assert" => match &arg_val {
+            Value::List(items) if items.len() == 2 => {
+                builtins::eval_assert(&items[0], &items[1], None, &mut diags)
+            }
+            _ => {
+                return Err(PyValueError::new_err(
+                    "assert expects a list of [condition, message]",
+                ));
+            }
+        },
         "toJSON" => builtins::eval_to_json(&arg_val, &mut diags),
+        "parseJSON" => builtins::eval_parse_json(&arg_val, &mut diags),
+        "parseYAML" => builtins::eval_parse_yaml(&arg_val, &mut diags),
         "toBase64" => builtins::eval_to_base64(&arg_val, &mut diags),
         "fromBase64" => builtins::eval_from_base64(&arg_val, &mut diags),
         "secret" => Some(builtins::eval_secret(arg_val.clone())),
@@ -268,10 +424,14 @@ fn evaluate_builtin(py: Python<'_>, name: &str, args: Py<PyAny>) -> PyResult<Py<
 
 /// Create an execution plan from a YAML project directory.
 ///
-/// Pipeline: discover files → Jinja preprocess → parse → merge → validate DAG →
-/// canonicalize types → serialize expression trees as Python dicts.
+/// Pipeline: discover files → Jinja preprocess → parse → merge, then
+/// `pulumi_rs_yaml_core::plan::ExecutionPlan::build` (constant-fold → validate
+/// DAG → canonicalize types → encode expression trees).
 ///
-/// Returns a dict: { project_name, nodes: [...], outputs: [...], source_map, diagnostics }
+/// Returns the plan's JSON form as a dict: { version, project_name, nodes: [...],
+/// outputs: [...], source_map, diagnostics, levels, dependencies, cross_file_edges }.
+/// `cross_file_edges` lists dependencies between entries in different files
+/// (each `{ from, from_file, to, to_file, warning? }`) and is omitted if empty.
 #[pyfunction]
 #[pyo3(signature = (project_dir, jinja_context=None))]
 fn create_execution_plan(
@@ -330,17 +490,21 @@ fn create_execution_plan(
             .unwrap_or_else(|| "unknown".to_string())
     };
 
-    let jinja_ctx = pulumi_rs_yaml_core::jinja::JinjaContext {
-        project_name: &project_name_owned,
-        stack_name: &stack_name,
-        cwd: &cwd,
-        organization: &organization,
-        root_directory: &root_directory,
-        config: &config_map,
+    let context = pulumi_rs_yaml_core::eval::context::EvaluatorContext::builder(
+        project_name_owned,
+        stack_name,
+    )
+    .cwd(cwd)
+    .root_directory(root_directory)
+    .organization(organization)
+    .build()
+    .map_err(|e| PyValueError::new_err(e.to_string()))?;
+    let jinja_ctx = context.jinja_context(
+        &config_map,
         project_dir,
-        undefined: pulumi_rs_yaml_core::jinja::UndefinedMode::Strict,
-        extra: &extra_map,
-    };
+        pulumi_rs_yaml_core::jinja::UndefinedMode::Strict,
+        &extra_map,
+    );
 
     // Reload with Jinja preprocessing (handles both {{ }} and {% %} via full rendering)
     let jinja_opt = if jinja_context.is_some() {
@@ -356,11 +520,41 @@ fn create_execution_plan(
         )));
     }
 
-    let project_name = merged.name().unwrap_or("unknown").to_string();
+    let plan = pulumi_rs_yaml_core::plan::ExecutionPlan::build(&merged, &load_diags).map_err(
+        |sort_diags| PyValueError::new_err(format!("DAG validation failed: {}", sort_diags)),
+    )?;
+    let plan = serde_json::to_value(&plan)
+        .map_err(|e| PyValueError::new_err(format!("Failed to serialize plan: {}", e)))?;
+    json_to_py(py, &plan)
+}
 
-    // Validate DAG (topological sort with dep graph for level computation)
+/// Return the nodes that transitively depend on `name` in a project: the
+/// config, variables and resources re-evaluated when it changes. Sorted.
+#[pyfunction]
+fn graph_descendants(project_dir: &str, name: &str) -> PyResult<Vec<String>> {
+    let deps = project_deps(project_dir, name)?;
+    Ok(pulumi_rs_yaml_core::eval::graph::descendants(&deps, name))
+}
+
+/// Return the nodes `name` transitively depends on in a project. Sorted.
+#[pyfunction]
+fn graph_ancestors(project_dir: &str, name: &str) -> PyResult<Vec<String>> {
+    let deps = project_deps(project_dir, name)?;
+    Ok(pulumi_rs_yaml_core::eval::graph::ancestors(&deps, name))
+}
+
+/// Load a project and return its dependency graph, checking `name` is a node of it.
+fn project_deps(project_dir: &str, name: &str) -> PyResult<HashMap<String, HashSet<String>>> {
+    let path = std::path::Path::new(project_dir);
+    let (merged, load_diags) = pulumi_rs_yaml_core::multi_file::load_project(path, None);
+    if load_diags.has_errors() {
+        return Err(PyValueError::new_err(format!(
+            "Failed to load project: {}",
+            load_diags
+        )));
+    }
     let template = merged.as_template_decl();
-    let (sort_result, sort_diags) = pulumi_rs_yaml_core::eval::graph::topological_sort_with_deps(
+    let (result, sort_diags) = pulumi_rs_yaml_core::eval::graph::topological_sort_with_deps(
         &template,
         Some(merged.source_map()),
     );
@@ -370,181 +564,13 @@ fn create_execution_plan(
             sort_diags
         )));
     }
-    let order = &sort_result.order;
-
-    // Compute topological levels for parallel evaluation
-    let levels = pulumi_rs_yaml_core::eval::graph::topological_levels(order, &sort_result.deps);
-    let mut node_level_map: std::collections::HashMap<String, usize> =
-        std::collections::HashMap::new();
-    for (level_idx, level_nodes) in levels.iter().enumerate() {
-        for name in level_nodes {
-            node_level_map.insert(name.clone(), level_idx);
-        }
-    }
-
-    // Build lookup maps: name → kind + data
-    let mut config_map_by_name: HashMap<
-        &str,
-        &pulumi_rs_yaml_core::ast::template::ConfigEntry<'_>,
-    > = HashMap::new();
-    for entry in &template.config {
-        config_map_by_name.insert(entry.key.as_ref(), entry);
-    }
-    let mut var_map: HashMap<&str, &pulumi_rs_yaml_core::ast::template::VariableEntry<'_>> =
-        HashMap::new();
-    for entry in &template.variables {
-        var_map.insert(entry.key.as_ref(), entry);
-    }
-    let mut res_map: HashMap<&str, &pulumi_rs_yaml_core::ast::template::ResourceEntry<'_>> =
-        HashMap::new();
-    for entry in &template.resources {
-        res_map.insert(entry.logical_name.as_ref(), entry);
-    }
-
-    // Walk order and serialize each node
-    let mut nodes: Vec<Py<PyAny>> = Vec::new();
-    for name in order {
-        let name_str = name.as_str();
-
-        if name_str == "pulumi" {
-            // Skip the pulumi settings node
-            continue;
-        }
-
-        let level = node_level_map.get(name_str).copied().unwrap_or(0);
-
-        if let Some(cfg) = config_map_by_name.get(name_str) {
-            let node = PyDict::new(py);
-            node.set_item("kind", "config")?;
-            node.set_item("name", cfg.key.as_ref())?;
-            node.set_item("type", cfg.param.type_.as_deref())?;
-            node.set_item("secret", cfg.param.secret)?;
-            node.set_item("level", level)?;
-            if let Some(ref def) = cfg.param.default {
-                node.set_item("default", expr_to_py(py, def)?)?;
-            } else {
-                node.set_item("default", py.None())?;
-            }
-            if let Some(ref val) = cfg.param.value {
-                node.set_item("value", expr_to_py(py, val)?)?;
-            } else {
-                node.set_item("value", py.None())?;
-            }
-            nodes.push(node.into_any().unbind());
-        } else if let Some(var) = var_map.get(name_str) {
-            let node = PyDict::new(py);
-            node.set_item("kind", "variable")?;
-            node.set_item("name", var.key.as_ref())?;
-            node.set_item("value", expr_to_py(py, &var.value)?)?;
-            node.set_item("level", level)?;
-            nodes.push(node.into_any().unbind());
-        } else if let Some(res) = res_map.get(name_str) {
-            let node = PyDict::new(py);
-            node.set_item("kind", "resource")?;
-            node.set_item("name", res.logical_name.as_ref())?;
-            let canonical =
-                pulumi_rs_yaml_core::packages::canonicalize_type_token(res.resource.type_.as_ref());
-            node.set_item("type_token", &canonical)?;
-            node.set_item("level", level)?;
-
-            // Include explicit resource name if set (for physical name override)
-            if let Some(ref physical_name) = res.resource.name {
-                node.set_item("resource_name", physical_name.as_ref())?;
-            } else {
-                node.set_item("resource_name", py.None())?;
-            }
-
-            // Include component detection hint (will be refined when schema is available)
-            let is_component = false; // Schema not available at plan time; set by Python SDK
-            node.set_item("is_component", is_component)?;
-
-            node.set_item(
-                "properties",
-                resource_properties_to_py(py, &res.resource.properties)?,
-            )?;
-            node.set_item(
-                "options",
-                resource_options_to_py(py, &res.resource.options)?,
-            )?;
-            // Add empty output_properties and property_types (populated when schema available)
-            let empty_list: Vec<String> = Vec::new();
-            node.set_item("output_properties", empty_list)?;
-            node.set_item("property_types", PyDict::new(py))?;
-
-            if let Some(ref get) = res.resource.get {
-                let get_dict = PyDict::new(py);
-                get_dict.set_item("id", expr_to_py(py, &get.id)?)?;
-                let state_entries: Vec<Py<PyAny>> = get
-                    .state
-                    .iter()
-                    .map(|e| {
-                        let d = PyDict::new(py);
-                        d.set_item("k", e.key.as_ref())?;
-                        d.set_item("v", expr_to_py(py, &e.value)?)?;
-                        Ok(d.into_any().unbind())
-                    })
-                    .collect::<PyResult<_>>()?;
-                get_dict.set_item("state", pyo3::types::PyList::new(py, &state_entries)?)?;
-                node.set_item("get", get_dict)?;
-            } else {
-                node.set_item("get", py.None())?;
-            }
-            nodes.push(node.into_any().unbind());
-        }
-        // else: skip unknown nodes
-    }
-
-    // Serialize outputs
-    let py_outputs: Vec<Py<PyAny>> = template
-        .outputs
-        .iter()
-        .map(|o| {
-            let d = PyDict::new(py);
-            d.set_item("name", o.key.as_ref())?;
-            d.set_item("value", expr_to_py(py, &o.value)?)?;
-            Ok(d.into_any().unbind())
-        })
-        .collect::<PyResult<_>>()?;
-
-    // Build source_map dict
-    let py_source_map = PyDict::new(py);
-    for (name, file) in merged.source_map() {
-        py_source_map.set_item(name.as_str(), file.as_str())?;
-    }
-
-    // Build diagnostics
-    let mut all_diags = Diagnostics::new();
-    all_diags.extend(load_diags);
-    all_diags.extend(sort_diags);
-    let py_diags = diags_to_py(py, &all_diags)?;
-
-    // Build levels list (list of list of node names per level)
-    let py_levels: Vec<Py<PyAny>> = levels
-        .iter()
-        .map(|level_names| {
-            let py_names: Vec<&str> = level_names.iter().map(|s| s.as_str()).collect();
-            Ok(pyo3::types::PyList::new(py, &py_names)?.into_any().unbind())
-        })
-        .collect::<PyResult<_>>()?;
-
-    // Return the plan dict
-    let plan = PyDict::new(py);
-    plan.set_item("project_name", &project_name)?;
-    plan.set_item("nodes", pyo3::types::PyList::new(py, &nodes)?)?;
-    plan.set_item("outputs", pyo3::types::PyList::new(py, &py_outputs)?)?;
-    plan.set_item("source_map", py_source_map)?;
-    plan.set_item("diagnostics", py_diags)?;
-    plan.set_item("levels", pyo3::types::PyList::new(py, &py_levels)?)?;
-
-    // Add dependency graph
-    let deps_dict = PyDict::new(py);
-    for (name, dep_set) in &sort_result.deps {
-        let dep_list: Vec<&str> = dep_set.iter().map(|s| s.as_str()).collect();
-        deps_dict.set_item(name.as_str(), dep_list)?;
+    if !result.deps.contains_key(name) {
+        return Err(PyValueError::new_err(format!(
+            "'{}' is not a config value, variable or resource of this project",
+            name
+        )));
     }
-    plan.set_item("dependencies", deps_dict)?;
-
-    Ok(plan.into_any().unbind())
+    Ok(result.deps)
 }
 
 /// Convert diagnostics to a Python list of dicts.
@@ -693,7 +719,7 @@ fn complete_properties(
         .iter()
         .map(|item| {
             let dict = PyDict::new(py);
-            dict.set_item("name", item.name).ok();
+            dict.set_item("name", item.name.as_ref()).ok();
             dict.set_item("type", item.type_label).ok();
             dict.set_item("required", item.required).ok();
             dict.set_item("secret", item.secret).ok();
@@ -705,6 +731,192 @@ fn complete_properties(
     Ok(py_list.into_any().unbind())
 }
 
+/// Get completion items for the cursor in a template's source.
+///
+/// `line` and `column` are zero-based. Under a resource's `options:` the
+/// items are option keys; under `properties:` they are property names, or
+/// the allowed values of an enum-typed property after its key. Returns a
+/// list of dicts with keys: name, type, kind, required, secret,
+/// description, deprecated.
+#[pyfunction]
+#[pyo3(signature = (source, line, column, schema_dir=None))]
+fn complete_at(
+    py: Python<'_>,
+    source: &str,
+    line: usize,
+    column: usize,
+    schema_dir: Option<&str>,
+) -> PyResult<Py<PyAny>> {
+    let schema_store = match schema_dir {
+        Some(sd) => pulumi_rs_yaml_core::schema::SchemaStore::load(std::path::Path::new(sd))
+            .map_err(|e| PyValueError::new_err(format!("Failed to load schema: {}", e)))?,
+        None => pulumi_rs_yaml_core::schema::SchemaStore::new(),
+    };
+
+    let items = pulumi_rs_yaml_core::completion::complete_at(&schema_store, source, line, column);
+    let results: Vec<Py<PyAny>> = items
+        .iter()
+        .map(|item| {
+            let kind = match item.kind {
+                CompletionKind::Property => "property",
+                CompletionKind::Option => "option",
+                CompletionKind::EnumValue => "enumValue",
+            };
+            let dict = PyDict::new(py);
+            dict.set_item("name", item.name.as_ref()).ok();
+            dict.set_item("type", item.type_label).ok();
+            dict.set_item("kind", kind).ok();
+            dict.set_item("required", item.required).ok();
+            dict.set_item("secret", item.secret).ok();
+            dict.set_item("description", item.description).ok();
+            dict.set_item("deprecated", item.deprecation_message).ok();
+            dict.into_any().unbind()
+        })
+        .collect();
+
+    let py_list = pyo3::types::PyList::new(py, &results)?;
+    Ok(py_list.into_any().unbind())
+}
+
+/// Get quick fixes for the problems in a template's source.
+///
+/// Returns a list of dicts with keys: title, kind, severity, message,
+/// detail, range, edits. Ranges are dicts of zero-based `start` and `end`
+/// positions, each with `line` and `character`; each edit has a `range` and
+/// the `new_text` to replace it with. Fixes needing schema information are
+/// only found when `schema_dir` is provided.
+#[pyfunction]
+#[pyo3(signature = (source, schema_dir=None))]
+fn code_actions(py: Python<'_>, source: &str, schema_dir: Option<&str>) -> PyResult<Py<PyAny>> {
+    let schema_store = match schema_dir {
+        Some(sd) => pulumi_rs_yaml_core::schema::SchemaStore::load(std::path::Path::new(sd))
+            .map_err(|e| PyValueError::new_err(format!("Failed to load schema: {}", e)))?,
+        None => pulumi_rs_yaml_core::schema::SchemaStore::new(),
+    };
+
+    let range_to_py = |range: &Range| -> PyResult<Py<PyAny>> {
+        let dict = PyDict::new(py);
+        for (key, position) in [("start", range.start), ("end", range.end)] {
+            let p = PyDict::new(py);
+            p.set_item("line", position.line)?;
+            p.set_item("character", position.character)?;
+            dict.set_item(key, p)?;
+        }
+        Ok(dict.into_any().unbind())
+    };
+
+    let actions = pulumi_rs_yaml_core::code_actions::code_actions(&schema_store, source);
+    let mut results: Vec<Py<PyAny>> = Vec::with_capacity(actions.len());
+    for action in &actions {
+        let kind = match action.kind {
+            CodeActionKind::AddRequiredProperty => "addRequiredProperty",
+            CodeActionKind::CanonicalTypeToken => "canonicalTypeToken",
+            CodeActionKind::WrapInSecret => "wrapInSecret",
+            CodeActionKind::AddDependsOn => "addDependsOn",
+        };
+        let severity = if action.diagnostic.is_error() {
+            "error"
+        } else {
+            "warning"
+        };
+        let mut edits: Vec<Py<PyAny>> = Vec::with_capacity(action.edits.len());
+        for edit in &action.edits {
+            let e = PyDict::new(py);
+            e.set_item("range", range_to_py(&edit.range)?)?;
+            e.set_item("new_text", &edit.new_text)?;
+            edits.push(e.into_any().unbind());
+        }
+        let dict = PyDict::new(py);
+        dict.set_item("title", &action.title)?;
+        dict.set_item("kind", kind)?;
+        dict.set_item("severity", severity)?;
+        dict.set_item("message", &action.diagnostic.summary)?;
+        dict.set_item("detail", &action.diagnostic.detail)?;
+        dict.set_item("range", range_to_py(&action.range)?)?;
+        dict.set_item("edits", edits)?;
+        results.push(dict.into_any().unbind());
+    }
+
+    let py_list = pyo3::types::PyList::new(py, &results)?;
+    Ok(py_list.into_any().unbind())
+}
+
+/// Format a template's source, keeping its comments.
+///
+/// `invokes` is "preserve", "long" (`fn::invoke` long form), or "shorthand"
+/// (`fn::<token>`); `property_order` is "declaration" or "alphabetical".
+#[pyfunction]
+#[pyo3(signature = (source, invokes="preserve", property_order="declaration"))]
+fn format_template(source: &str, invokes: &str, property_order: &str) -> PyResult<String> {
+    use pulumi_rs_yaml_core::format::{FormatOptions, InvokeStyle, PropertyOrder};
+
+    let invokes = match invokes {
+        "preserve" => InvokeStyle::Preserve,
+        "long" => InvokeStyle::LongForm,
+        "shorthand" => InvokeStyle::Shorthand,
+        other => {
+            return Err(PyValueError::new_err(format!(
+                "invokes must be 'preserve', 'long', or 'shorthand', not '{}'",
+                other
+            )))
+        }
+    };
+    let property_order = match property_order {
+        "declaration" => PropertyOrder::Declaration,
+        "alphabetical" => PropertyOrder::Alphabetical,
+        other => {
+            return Err(PyValueError::new_err(format!(
+                "property_order must be 'declaration' or 'alphabetical', not '{}'",
+                other
+            )))
+        }
+    };
+    Ok(pulumi_rs_yaml_core::format::format_template(
+        source,
+        &FormatOptions {
+            invokes,
+            property_order,
+        },
+    ))
+}
+
+/// Generate the files of a new project from a built-in archetype.
+///
+/// `archetype` is "static-site", "container-service", or "serverless-api";
+/// `cloud` is "aws", "gcp", or "azure". Returns a list of `(path, contents)`
+/// tuples, `Pulumi.yaml` first. Required properties are completed from the
+/// schema when `schema_dir` is provided.
+#[pyfunction]
+#[pyo3(signature = (name, archetype, cloud="aws", schema_dir=None))]
+fn scaffold_project(
+    name: &str,
+    archetype: &str,
+    cloud: &str,
+    schema_dir: Option<&str>,
+) -> PyResult<Vec<(String, String)>> {
+    use pulumi_rs_yaml_core::scaffold::{Archetype, Cloud, ScaffoldOptions};
+
+    let archetype = Archetype::from_name(archetype)
+        .ok_or_else(|| PyValueError::new_err(format!("unknown archetype '{}'", archetype)))?;
+    let cloud = Cloud::from_name(cloud)
+        .ok_or_else(|| PyValueError::new_err(format!("unknown cloud '{}'", cloud)))?;
+    let schema_store = match schema_dir {
+        Some(sd) => Some(
+            pulumi_rs_yaml_core::schema::SchemaStore::load(std::path::Path::new(sd))
+                .map_err(|e| PyValueError::new_err(format!("Failed to load schema: {}", e)))?,
+        ),
+        None => None,
+    };
+    let files = pulumi_rs_yaml_core::scaffold::scaffold(&ScaffoldOptions {
+        name,
+        archetype,
+        cloud,
+        schema: schema_store.as_ref(),
+    })
+    .map_err(PyValueError::new_err)?;
+    Ok(files.into_iter().map(|f| (f.path, f.contents)).collect())
+}
+
 /// Get schema metadata for a resource type.
 ///
 /// Returns a dict with keys: required, secret, aliases, is_component, properties.
@@ -791,9 +1003,15 @@ fn _native(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(preprocess_jinja, m)?)?;
     m.add_function(wrap_pyfunction!(evaluate_builtin, m)?)?;
     m.add_function(wrap_pyfunction!(create_execution_plan, m)?)?;
+    m.add_function(wrap_pyfunction!(graph_descendants, m)?)?;
+    m.add_function(wrap_pyfunction!(graph_ancestors, m)?)?;
     m.add_function(wrap_pyfunction!(validate_and_classify, m)?)?;
     m.add_function(wrap_pyfunction!(type_check_project, m)?)?;
     m.add_function(wrap_pyfunction!(complete_properties, m)?)?;
+    m.add_function(wrap_pyfunction!(complete_at, m)?)?;
+    m.add_function(wrap_pyfunction!(code_actions, m)?)?;
+    m.add_function(wrap_pyfunction!(format_template, m)?)?;
+    m.add_function(wrap_pyfunction!(scaffold_project, m)?)?;
     m.add_function(wrap_pyfunction!(get_resource_schema, m)?)?;
     Ok(())
 }
@@ -15,6 +15,14 @@ def acceptance_dir():
     return p
 
 
+@pytest.fixture
+def conformance_dir():
+    """Path to the generated conformance fixtures (one project per builtin/option)."""
+    p = Path(__file__).resolve().parent.parent.parent / "pulumi-rs-yaml-testing" / "conformance"
+    assert p.is_dir(), f"conformance dir not found: {p}"
+    return p
+
+
 @pytest.fixture
 def tmp_project(tmp_path):
     """Factory that creates temp directories with Pulumi.yaml content.
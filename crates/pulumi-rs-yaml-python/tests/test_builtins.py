@@ -27,6 +27,10 @@ class TestMathBuiltins:
     def test_min(self):
         assert evaluate_builtin("min", [1, 5, 3]) == 1
 
+    def test_format_duration_and_bytes(self):
+        assert evaluate_builtin("formatDuration", 5400) == "1h30m"
+        assert evaluate_builtin("formatBytes", 5368709120) == "5Gi"
+
 
 class TestStringBuiltins:
     def test_join(self):
@@ -41,6 +45,61 @@ class TestStringBuiltins:
         result = evaluate_builtin("select", [1, ["a", "b", "c"]])
         assert result == "b"
 
+    def test_select_object_key(self):
+        result = evaluate_builtin("select", ["b", {"a": 1, "b": 2}])
+        assert result == 2
+
+    def test_lookup_default(self):
+        assert evaluate_builtin("lookup", [{"a": 1}, "a", 0]) == 1
+        assert evaluate_builtin("lookup", [{"a": 1}, "z", 0]) == 0
+
+    def test_merge(self):
+        result = evaluate_builtin("merge", [{"a": 1, "b": {"c": 2}}, {"b": {"c": None, "d": 3}}])
+        assert result == {"a": 1, "b": {"d": 3}}
+
+    def test_json_patch(self):
+        ops = [{"op": "add", "path": "/tags/-", "value": "prod"}]
+        assert evaluate_builtin("jsonPatch", [{"tags": ["web"]}, ops]) == {"tags": ["web", "prod"]}
+        with pytest.raises(ValueError, match="operation 0 failed"):
+            evaluate_builtin("jsonPatch", [{}, [{"op": "remove", "path": "/x"}]])
+
+    def test_template_file(self, tmp_path):
+        path = tmp_path / "policy.json"
+        path.write_text('{"Resource": "${bucket}/*"}')
+        result = evaluate_builtin("templateFile", [str(path), {"bucket": "arn:aws:s3:::logs"}])
+        assert result == '{"Resource": "arn:aws:s3:::logs/*"}'
+
+    def test_validate_json(self):
+        schema = {"type": "object", "required": ["name"]}
+        assert evaluate_builtin("validateJson", [{"name": "a"}, schema]) == {"name": "a"}
+        with pytest.raises(ValueError, match="missing required property"):
+            evaluate_builtin("validateJson", [{}, schema])
+
+    def test_assert(self):
+        assert evaluate_builtin("assert", [True, "unused"]) is True
+        with pytest.raises(ValueError, match="assertion failed: 2 subnets for 3 zones"):
+            evaluate_builtin("assert", [False, "2 subnets for 3 zones"])
+
+    def test_list_builtins(self):
+        assert evaluate_builtin("sort", ["b", "c", "a"]) == ["a", "b", "c"]
+        assert evaluate_builtin("unique", ["a", "b", "a"]) == ["a", "b"]
+        assert evaluate_builtin("length", ["a", "b"]) == 2
+        with pytest.raises(ValueError, match="must be a list or an object"):
+            evaluate_builtin("length", "abc")
+        assert evaluate_builtin("coalesce", [None, "", "t3.micro", "t3.large"]) == "t3.micro"
+        assert evaluate_builtin("coalesce", [None, ""]) is None
+
+    def test_comparison_builtins(self):
+        assert evaluate_builtin("equals", ["prod", "prod"]) is True
+        assert evaluate_builtin("equals", [{"a": 1, "b": 2}, {"b": 2, "a": 1}]) is True
+        assert evaluate_builtin("not", True) is False
+        assert evaluate_builtin("and", [True, False]) is False
+        assert evaluate_builtin("or", [False, True]) is True
+        assert evaluate_builtin("lessThan", [1, 2]) is True
+        assert evaluate_builtin("greaterThanOrEqual", [1, 2]) is False
+        with pytest.raises(ValueError, match="must be a boolean"):
+            evaluate_builtin("not", "yes")
+
     def test_string_len(self):
         result = evaluate_builtin("stringLen", "hello")
         assert result == 5
@@ -49,17 +108,53 @@ class TestStringBuiltins:
         result = evaluate_builtin("substring", ["hello world", 6, 5])
         assert result == "world"
 
+    def test_case_and_trim(self):
+        assert evaluate_builtin("toUpper", "My-App") == "MY-APP"
+        assert evaluate_builtin("toLower", "My-App") == "my-app"
+        assert evaluate_builtin("trim", "  web \n") == "web"
+
+    def test_replace(self):
+        assert evaluate_builtin("replace", ["a.b.c", ".", "-"]) == "a-b-c"
+        with pytest.raises(ValueError, match="must not be empty"):
+            evaluate_builtin("replace", ["abc", "", "-"])
+
+    def test_starts_with(self):
+        assert evaluate_builtin("startsWith", ["prod-east", "prod"]) is True
+        assert evaluate_builtin("startsWith", ["dev", "prod"]) is False
+
+    def test_format(self):
+        result = evaluate_builtin("format", ["arn:aws:s3:::{0}/{1}", ["logs", 2.0]])
+        assert result == "arn:aws:s3:::logs/2"
+        with pytest.raises(ValueError, match="out of range"):
+            evaluate_builtin("format", ["{1}", ["a"]])
+
+    def test_cidr(self):
+        assert evaluate_builtin("cidrSubnet", ["10.0.0.0/16", 8, 3]) == "10.0.3.0/24"
+        assert evaluate_builtin("cidrHost", ["10.0.3.0/24", -2]) == "10.0.3.254"
+        assert evaluate_builtin("cidrNetmask", "10.0.0.0/16") == "255.255.0.0"
+        with pytest.raises(ValueError, match="does not fit in 8 bits"):
+            evaluate_builtin("cidrSubnet", ["10.0.0.0/16", 8, 256])
+
     def test_to_json(self):
         result = evaluate_builtin("toJSON", {"a": 1})
         parsed = json.loads(result)
         assert parsed == {"a": 1}
 
+    def test_parse_json_and_yaml(self):
+        assert evaluate_builtin("parseJSON", '{"a": [1, 2]}') == {"a": [1, 2]}
+        assert evaluate_builtin("parseYAML", "a:\n  - 1\n  - 2\n") == {"a": [1, 2]}
+        with pytest.raises(ValueError, match="invalid JSON"):
+            evaluate_builtin("parseJSON", "{")
+
 
 class TestEncodingBuiltins:
     def test_to_base64(self):
         result = evaluate_builtin("toBase64", "hello")
         assert result == "aGVsbG8="
 
+    def test_to_base64_bytes(self):
+        assert evaluate_builtin("toBase64", b"\x89PNG\xff") == "iVBOR/8="
+
     def test_from_base64(self):
         result = evaluate_builtin("fromBase64", "aGVsbG8=")
         assert result == "hello"
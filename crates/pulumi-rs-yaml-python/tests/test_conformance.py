@@ -0,0 +1,33 @@
+"""Loads the generated conformance fixtures of the pulumi-rs-yaml-testing crate.
+
+Regenerate them with `cargo run -p pulumi-rs-yaml-testing --bin gen-conformance`.
+"""
+
+from pulumi_yaml_rs import create_execution_plan, load_project
+
+
+def conformance_projects(conformance_dir):
+    return [d for d in sorted(conformance_dir.iterdir()) if (d / "Pulumi.yaml").exists()]
+
+
+class TestConformanceFixtures:
+    def test_every_fixture_loads(self, conformance_dir):
+        projects = conformance_projects(conformance_dir)
+        assert projects, "no conformance fixtures"
+        for d in projects:
+            result = load_project(str(d))
+            assert not result["has_errors"], f"{d.name}: {result}"
+
+    def test_every_fixture_plans(self, conformance_dir):
+        for d in conformance_projects(conformance_dir):
+            plan = create_execution_plan(str(d))
+            assert "nodes" in plan, f"Missing nodes for {d.name}"
+            assert "levels" in plan, f"Missing levels for {d.name}"
+
+    def test_option_fixtures_declare_target(self, conformance_dir):
+        for d in conformance_projects(conformance_dir):
+            if not d.name.startswith("option"):
+                continue
+            plan = create_execution_plan(str(d))
+            resources = [n["name"] for n in plan["nodes"] if n["kind"] == "resource"]
+            assert "target" in resources, f"{d.name}: {resources}"
@@ -1,7 +1,7 @@
 """Tests for create_execution_plan() — DAG-based execution planning."""
 
 import pytest
-from pulumi_yaml_rs import create_execution_plan
+from pulumi_yaml_rs import create_execution_plan, graph_ancestors, graph_descendants
 
 
 class TestPlanBasicStructure:
@@ -61,6 +61,27 @@ class TestPlanNodes:
         assert var_nodes[0]["name"] == "encoded"
         assert var_nodes[0]["value"] is not None
 
+    def test_plan_folds_literal_builtins(self, tmp_project):
+        d = tmp_project("""\
+            name: fold-plan
+            runtime: yaml
+            config:
+              env:
+                type: string
+            variables:
+              encoded:
+                fn::toBase64: hello
+              joined:
+                fn::join: ["-", [a, b]]
+              withRef:
+                fn::join: ["-", [a, "${env}"]]
+        """)
+        plan = create_execution_plan(d)
+        values = {n["name"]: n["value"] for n in plan["nodes"] if n["kind"] == "variable"}
+        assert values["encoded"] == {"t": "string", "v": "aGVsbG8="}
+        assert values["joined"] == {"t": "string", "v": "a-b"}
+        assert values["withRef"]["t"] != "string"
+
     def test_plan_resource_nodes(self, tmp_project):
         d = tmp_project("""\
             name: res-plan
@@ -216,6 +237,29 @@ resources:
         assert "storageBucket" in source_map
         assert "Pulumi.storage.yaml" in source_map["storageBucket"]
 
+    def test_plan_cross_file_edges(self, tmp_project):
+        d = tmp_project(
+            """\
+            name: edges-plan
+            runtime: yaml
+            outputs:
+              bucketName: ${storageBucket.name}
+            """,
+            extras={
+                "Pulumi.storage.yaml": """\
+resources:
+  storageBucket:
+    type: gcp:storage:Bucket
+"""
+            },
+        )
+        plan = create_execution_plan(d)
+        [edge] = plan["cross_file_edges"]
+        assert edge["from"] == "bucketName"
+        assert edge["from_file"] == "Pulumi.yaml"
+        assert edge["to_file"] == "Pulumi.storage.yaml"
+        assert "layered on top of it" in edge["warning"]
+
 
 class TestPlanJinja:
     def test_plan_with_jinja_context(self, tmp_project, jinja_context):
@@ -239,3 +283,32 @@ class TestPlanErrors:
     def test_plan_missing_dir_error(self):
         with pytest.raises(ValueError):
             create_execution_plan("/nonexistent/path/to/project")
+
+
+class TestGraphImpact:
+    def test_descendants_and_ancestors(self, tmp_project):
+        d = tmp_project("""\
+            name: impact
+            runtime: yaml
+            config:
+              region:
+                default: us-east-1
+            variables:
+              prefix: app-${region}
+            resources:
+              bucket:
+                type: gcp:storage:Bucket
+                properties:
+                  name: ${prefix}
+        """)
+        assert graph_descendants(d, "region") == ["bucket", "prefix"]
+        assert graph_ancestors(d, "bucket") == ["prefix", "region"]
+        assert graph_descendants(d, "bucket") == []
+
+    def test_unknown_name(self, tmp_project):
+        d = tmp_project("""\
+            name: impact
+            runtime: yaml
+        """)
+        with pytest.raises(ValueError, match="not a config value"):
+            graph_descendants(d, "nope")
@@ -0,0 +1,22 @@
+//! Writes the conformance fixtures.
+//!
+//! Usage: `gen-conformance [DIR]`, where `DIR` defaults to the crate's
+//! `conformance/` directory. The directory is replaced.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use pulumi_rs_yaml_testing::conformance::{conformance_dir, fixtures, write_fixtures};
+
+fn main() -> ExitCode {
+    let dir = std::env::args_os()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(conformance_dir);
+    if let Err(e) = write_fixtures(&dir) {
+        eprintln!("error: writing {}: {}", dir.display(), e);
+        return ExitCode::FAILURE;
+    }
+    println!("wrote {} fixtures to {}", fixtures().len(), dir.display());
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,631 @@
+//! Generated conformance fixtures.
+//!
+//! Where the corpus holds hand-written programs, these are produced by
+//! [`fixtures`]: one program per expression form (every `Expr` variant, each
+//! builtin with representative arguments) and one per resource option, plus a
+//! resource that sets every option at once. They are checked in under
+//! `conformance/` so that the Python SDK's tests can load them, and run
+//! through [`check_project`](crate::check_project) against snapshots in
+//! `snapshots/conformance/` like corpus entries.
+//!
+//! Regenerate them with
+//! `cargo run -p pulumi-rs-yaml-testing --bin gen-conformance` after adding a
+//! builtin or option here, then accept the new snapshots with
+//! `UPDATE_SNAPSHOTS=1`.
+//!
+//! Builtins whose results change between runs (time and random values) are
+//! wrapped in `fn::stringLen` so that their snapshots are stable.
+
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value as Json};
+use serde_yaml::{Mapping, Value as Yaml};
+
+/// One generated program and the files it reads.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fixture {
+    /// The directory name, e.g. `expr-join` or `option-protect`.
+    pub name: String,
+    /// Relative path and contents of each file, `Pulumi.yaml` first.
+    pub files: Vec<(String, String)>,
+}
+
+/// The `t` tags of [`expr_to_json`](pulumi_rs_yaml_core::plan::expr_to_json),
+/// one per `Expr` variant, that the fixtures must cover between them.
+pub const EXPR_TAGS: &[&str] = &[
+    "null",
+    "bool",
+    "number",
+    "string",
+    "interp",
+    "sym",
+    "list",
+    "obj",
+    "invoke",
+    "join",
+    "select",
+    "lookup",
+    "split",
+    "toJSON",
+    "parseJSON",
+    "parseYAML",
+    "toBase64",
+    "fromBase64",
+    "secret",
+    "readFile",
+    "readFileBinary",
+    "env",
+    "validateJson",
+    "assert",
+    "templateFile",
+    "if",
+    "map",
+    "filter",
+    "foreach",
+    "merge",
+    "jsonPatch",
+    "sort",
+    "unique",
+    "length",
+    "coalesce",
+    "equals",
+    "not",
+    "and",
+    "or",
+    "lessThan",
+    "lessThanOrEqual",
+    "greaterThan",
+    "greaterThanOrEqual",
+    "abs",
+    "floor",
+    "ceil",
+    "max",
+    "min",
+    "formatDuration",
+    "formatBytes",
+    "stringLen",
+    "substring",
+    "toUpper",
+    "toLower",
+    "trim",
+    "replace",
+    "startsWith",
+    "format",
+    "cidrSubnet",
+    "cidrHost",
+    "cidrNetmask",
+    "timeUtc",
+    "timeUnix",
+    "uuid",
+    "randomString",
+    "dateFormat",
+    "stringAsset",
+    "fileAsset",
+    "remoteAsset",
+    "fileArchive",
+    "remoteArchive",
+    "assetArchive",
+    "open",
+    "encrypted",
+    "starlark",
+];
+
+/// Directory containing the generated fixtures.
+pub fn conformance_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("conformance")
+}
+
+/// Returns every fixture, sorted by name.
+pub fn fixtures() -> Vec<Fixture> {
+    let mut fixtures: Vec<Fixture> = expr_cases()
+        .into_iter()
+        .map(ExprCase::into_fixture)
+        .chain(option_fixtures())
+        .collect();
+    fixtures.sort_by(|a, b| a.name.cmp(&b.name));
+    fixtures
+}
+
+/// Replaces the contents of `dir` with the generated fixtures.
+pub fn write_fixtures(dir: &Path) -> std::io::Result<()> {
+    if dir.exists() {
+        std::fs::remove_dir_all(dir)?;
+    }
+    for fixture in fixtures() {
+        for (path, contents) in &fixture.files {
+            let path = dir.join(&fixture.name).join(path);
+            std::fs::create_dir_all(path.parent().expect("fixture files are in a directory"))?;
+            std::fs::write(path, contents)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the names of fixtures whose files in `dir` differ from the
+/// generated ones, including missing fixtures and stray directories.
+pub fn stale_fixtures(dir: &Path) -> Vec<String> {
+    let fixtures = fixtures();
+    let mut stale: Vec<String> = fixtures
+        .iter()
+        .filter(|f| {
+            count_files(&dir.join(&f.name)) != f.files.len()
+                || f.files.iter().any(|(path, contents)| {
+                    std::fs::read_to_string(dir.join(&f.name).join(path))
+                        .ok()
+                        .as_ref()
+                        != Some(contents)
+                })
+        })
+        .map(|f| f.name.clone())
+        .collect();
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !fixtures.iter().any(|f| f.name == name) {
+                stale.push(name);
+            }
+        }
+    }
+    stale.sort();
+    stale
+}
+
+/// Counts the files under `dir`, recursively.
+fn count_files(dir: &Path) -> usize {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| {
+            if entry.path().is_dir() {
+                count_files(&entry.path())
+            } else {
+                1
+            }
+        })
+        .sum()
+}
+
+/// A program evaluating one expression into the `value` output.
+struct ExprCase {
+    name: &'static str,
+    value: Json,
+    /// Variables the expression refers to.
+    variables: Vec<(&'static str, Json)>,
+    /// Additional top-level sections.
+    sections: Vec<(&'static str, Json)>,
+    /// Files the expression reads.
+    files: Vec<(&'static str, &'static str)>,
+}
+
+impl ExprCase {
+    fn new(name: &'static str, value: Json) -> Self {
+        ExprCase {
+            name,
+            value,
+            variables: Vec::new(),
+            sections: Vec::new(),
+            files: Vec::new(),
+        }
+    }
+
+    fn variable(mut self, name: &'static str, value: Json) -> Self {
+        self.variables.push((name, value));
+        self
+    }
+
+    fn section(mut self, name: &'static str, value: Json) -> Self {
+        self.sections.push((name, value));
+        self
+    }
+
+    fn file(mut self, path: &'static str, contents: &'static str) -> Self {
+        self.files.push((path, contents));
+        self
+    }
+
+    fn into_fixture(self) -> Fixture {
+        let name = format!("expr-{}", self.name);
+        let mut variables = Mapping::new();
+        for (k, v) in self.variables {
+            variables.insert(k.into(), yaml(v));
+        }
+        variables.insert("value".into(), yaml(self.value));
+        let mut sections: Vec<(&str, Yaml)> = self
+            .sections
+            .into_iter()
+            .map(|(k, v)| (k, yaml(v)))
+            .collect();
+        sections.push(("variables", Yaml::Mapping(variables)));
+        sections.push(("outputs", yaml(json!({ "value": "${value}" }))));
+
+        let mut files = vec![("Pulumi.yaml".to_string(), program(&name, sections))];
+        files.extend(
+            self.files
+                .into_iter()
+                .map(|(path, contents)| (path.to_string(), contents.to_string())),
+        );
+        Fixture { name, files }
+    }
+}
+
+fn expr_cases() -> Vec<ExprCase> {
+    let string_len = |inner: Json| json!({ "fn::stringLen": inner });
+    vec![
+        // Literals and references
+        ExprCase::new("null", Json::Null),
+        ExprCase::new("bool", json!(true)),
+        ExprCase::new("number", json!(42.5)),
+        ExprCase::new("string", json!("hello")),
+        ExprCase::new("list", json!(["a", 1, false])),
+        ExprCase::new("object", json!({ "name": "web", "port": 8080 })),
+        ExprCase::new("symbol", json!("${input.items[1]}"))
+            .variable("input", json!({ "items": ["a", "b"] })),
+        ExprCase::new("interpolate", json!("${prefix}-${pulumi.stack}"))
+            .variable("prefix", json!("app")),
+        // Invokes
+        ExprCase::new(
+            "invoke",
+            json!({ "fn::invoke": {
+                "function": "test:index:getValue",
+                "arguments": { "name": "x" },
+            }}),
+        ),
+        ExprCase::new(
+            "invoke-shorthand",
+            json!({ "fn::test:index:getValue": { "name": "x" } }),
+        ),
+        // String and encoding builtins
+        ExprCase::new("join", json!({ "fn::join": ["-", ["a", "b", "c"]] })),
+        ExprCase::new("split", json!({ "fn::split": [",", "a,b,c"] })),
+        ExprCase::new("select", json!({ "fn::select": [1, ["a", "b", "c"]] })),
+        // The branch not taken would fail if it were evaluated.
+        ExprCase::new(
+            "if",
+            json!({ "fn::if": [
+                { "fn::select": [0, [true]] },
+                "yes",
+                { "fn::readFile": "./missing.txt" },
+            ]}),
+        ),
+        ExprCase::new(
+            "lookup",
+            json!({ "fn::lookup": [{ "a": 1 }, "b", "fallback"] }),
+        ),
+        ExprCase::new("to-json", json!({ "fn::toJSON": { "a": [1, 2] } })),
+        ExprCase::new("parse-json", json!({ "fn::parseJSON": "{\"a\": [1, 2]}" })),
+        ExprCase::new(
+            "parse-yaml",
+            json!({ "fn::parseYAML": { "fn::readFile": "./config.yaml" } }),
+        )
+        .file("config.yaml", "db:\n  port: 5432\n"),
+        ExprCase::new("to-base64", json!({ "fn::toBase64": "hello" })),
+        ExprCase::new("from-base64", json!({ "fn::fromBase64": "aGVsbG8=" })),
+        ExprCase::new("secret", json!({ "fn::secret": "s3cr3t" })),
+        ExprCase::new("string-len", json!({ "fn::stringLen": "hello" })),
+        ExprCase::new(
+            "substring",
+            json!({ "fn::substring": ["hello world", 6, 5] }),
+        ),
+        ExprCase::new("to-upper", json!({ "fn::toUpper": "hello" })),
+        ExprCase::new("to-lower", json!({ "fn::toLower": "HELLO" })),
+        ExprCase::new("trim", json!({ "fn::trim": "  hello  " })),
+        ExprCase::new("replace", json!({ "fn::replace": ["a.b.c", ".", "-"] })),
+        ExprCase::new(
+            "starts-with",
+            json!({ "fn::startsWith": ["hello world", "hello"] }),
+        ),
+        ExprCase::new(
+            "format",
+            json!({ "fn::format": ["{0}-{1}-{0}", ["web", 3]] }),
+        ),
+        // Network
+        ExprCase::new(
+            "cidr-subnet",
+            json!({ "fn::cidrSubnet": ["10.0.0.0/16", 8, 2] }),
+        ),
+        ExprCase::new("cidr-host", json!({ "fn::cidrHost": ["10.0.2.0/24", -1] })),
+        ExprCase::new("cidr-netmask", json!({ "fn::cidrNetmask": "10.0.0.0/20" })),
+        // Files
+        ExprCase::new("read-file", json!({ "fn::readFile": "./data.txt" }))
+            .file("data.txt", "file contents\n"),
+        ExprCase::new(
+            "read-file-binary",
+            json!({ "fn::toBase64": { "fn::readFileBinary": "./data.bin" } }),
+        )
+        .file("data.bin", "binary\n"),
+        // Hosts must opt in to fn::env, which the conformance run does not.
+        ExprCase::new(
+            "env",
+            json!({ "fn::env": ["PULUMI_YAML_CONFORMANCE_UNSET", "fallback"] }),
+        ),
+        ExprCase::new(
+            "template-file",
+            json!({ "fn::templateFile": {
+                "path": "./greeting.txt",
+                "arguments": { "name": "world" },
+            }}),
+        )
+        .file("greeting.txt", "Hello, ${name}!\n"),
+        ExprCase::new(
+            "validate-json",
+            json!({ "fn::validateJson": {
+                "value": { "port": 8080 },
+                "schema": {
+                    "type": "object",
+                    "properties": { "port": { "type": "integer" } },
+                    "required": ["port"],
+                },
+            }}),
+        ),
+        ExprCase::new(
+            "assert",
+            json!({ "fn::assert": {
+                "condition": { "fn::startsWith": ["prod-east", "prod"] },
+                "message": "expected a production region",
+            }}),
+        ),
+        // Lists and objects
+        ExprCase::new(
+            "map",
+            json!({ "fn::map": [["a", "b"], "${index}-${item}"] }),
+        ),
+        ExprCase::new(
+            "filter",
+            json!({ "fn::filter": [
+                [{ "name": "a", "enabled": true }, { "name": "b", "enabled": false }],
+                "${item.enabled}",
+            ]}),
+        ),
+        ExprCase::new(
+            "foreach",
+            json!({ "fn::foreach": {
+                "list": ["a", "b"],
+                "as": "zone",
+                "index": "i",
+                "body": { "fn::foreach": {
+                    "list": [80, 443],
+                    "as": "port",
+                    "body": "${zone}-${i}:${port}",
+                }},
+            }}),
+        ),
+        ExprCase::new(
+            "merge",
+            json!({ "fn::merge": [{ "a": 1, "b": { "c": 2 } }, { "b": { "d": 3 } }] }),
+        ),
+        ExprCase::new(
+            "json-patch",
+            json!({ "fn::jsonPatch": [
+                { "a": 1 },
+                [{ "op": "add", "path": "/b", "value": 2 }],
+            ]}),
+        ),
+        ExprCase::new(
+            "sort",
+            json!({ "fn::sort": ["us-east-1c", "us-east-1a", "us-east-1b"] }),
+        ),
+        ExprCase::new("unique", json!({ "fn::unique": ["web", "db", "web"] })),
+        ExprCase::new("length", json!({ "fn::length": [1, 2, 3] })),
+        ExprCase::new(
+            "coalesce",
+            json!({ "fn::coalesce": [null, "", "t3.micro", "t3.large"] }),
+        ),
+        // Comparison and boolean
+        ExprCase::new("equals", json!({ "fn::equals": [{ "a": 1 }, { "a": 1 }] })),
+        ExprCase::new("not", json!({ "fn::not": false })),
+        ExprCase::new("and", json!({ "fn::and": [true, false] })),
+        ExprCase::new("or", json!({ "fn::or": [false, true] })),
+        ExprCase::new("less-than", json!({ "fn::lessThan": [1, 2] })),
+        ExprCase::new(
+            "less-than-or-equal",
+            json!({ "fn::lessThanOrEqual": [2, 2] }),
+        ),
+        ExprCase::new("greater-than", json!({ "fn::greaterThan": [1, 2] })),
+        ExprCase::new(
+            "greater-than-or-equal",
+            json!({ "fn::greaterThanOrEqual": [2, 2] }),
+        ),
+        // Math and formatting
+        ExprCase::new("abs", json!({ "fn::abs": -3 })),
+        ExprCase::new("floor", json!({ "fn::floor": 2.7 })),
+        ExprCase::new("ceil", json!({ "fn::ceil": 2.2 })),
+        ExprCase::new("max", json!({ "fn::max": [1, 5, 3] })),
+        ExprCase::new("min", json!({ "fn::min": [4, 2, 8] })),
+        ExprCase::new("format-duration", json!({ "fn::formatDuration": 5400 })),
+        ExprCase::new("format-bytes", json!({ "fn::formatBytes": 5368709120_u64 })),
+        // Time and random values
+        ExprCase::new("time-utc", string_len(json!({ "fn::timeUtc": {} }))),
+        ExprCase::new(
+            "time-unix",
+            string_len(json!({ "fn::toJSON": { "fn::timeUnix": {} } })),
+        ),
+        ExprCase::new(
+            "date-format",
+            string_len(json!({ "fn::dateFormat": "%Y-%m" })),
+        ),
+        ExprCase::new("uuid", string_len(json!({ "fn::uuid": {} }))),
+        ExprCase::new(
+            "random-string",
+            string_len(json!({ "fn::randomString": 16 })),
+        ),
+        // Assets and archives
+        ExprCase::new("string-asset", json!({ "fn::stringAsset": "<h1>hi</h1>" })),
+        ExprCase::new("file-asset", json!({ "fn::fileAsset": "./index.html" }))
+            .file("index.html", "<h1>hi</h1>\n"),
+        ExprCase::new(
+            "remote-asset",
+            json!({ "fn::remoteAsset": "https://example.com/index.html" }),
+        ),
+        ExprCase::new("file-archive", json!({ "fn::fileArchive": "./site" }))
+            .file("site/index.html", "<h1>hi</h1>\n"),
+        ExprCase::new(
+            "remote-archive",
+            json!({ "fn::remoteArchive": "https://example.com/site.zip" }),
+        ),
+        ExprCase::new(
+            "asset-archive",
+            json!({ "fn::assetArchive": {
+                "index.html": { "fn::stringAsset": "<h1>hi</h1>" },
+                "nested": { "fn::remoteArchive": "https://example.com/site.zip" },
+            }}),
+        ),
+        // Host integrations
+        ExprCase::new(
+            "open",
+            json!({ "fn::open::test-provider": { "name": "x" } }),
+        ),
+        ExprCase::new(
+            "encrypted",
+            json!({ "fn::encrypted": "AAABAKsg2Tq2vrNmjpIHb3Pw8JGe0Iw1CpoUWvuNpA==" }),
+        ),
+        ExprCase::new(
+            "starlark",
+            json!({ "fn::starlark": { "invoke": "shout", "input": "hello" } }),
+        )
+        .section(
+            "starlark",
+            json!({ "functions": { "shout": {
+                "script": "def shout(s):\n    return s.upper() + \"!\"\n",
+            }}}),
+        ),
+    ]
+}
+
+/// A resource option and the value each fixture sets it to.
+fn resource_options() -> Vec<(&'static str, Json)> {
+    vec![
+        ("additionalSecretOutputs", json!(["arn"])),
+        (
+            "aliases",
+            json!(["urn:pulumi:dev::conformance::test:index:Thing::old"]),
+        ),
+        (
+            "customTimeouts",
+            json!({ "create": "5m", "update": "10m", "delete": "15m" }),
+        ),
+        ("deleteBeforeReplace", json!(true)),
+        ("deletedWith", json!("${dependency}")),
+        ("dependsOn", json!(["${dependency}"])),
+        ("hideDiffs", json!(["tags"])),
+        ("ignoreChanges", json!(["tags"])),
+        ("import", json!("thing-1234")),
+        ("parent", json!("${dependency}")),
+        ("pluginDownloadURL", json!("https://example.com/plugins")),
+        ("protect", json!(true)),
+        ("provider", json!("${provider}")),
+        ("providers", json!({ "test": "${provider}" })),
+        ("replaceOnChanges", json!(["name"])),
+        ("replaceWith", json!(["${dependency}"])),
+        ("retainOnDelete", json!(true)),
+        ("version", json!("1.2.3")),
+    ]
+}
+
+/// One fixture per resource option, and one setting them all.
+fn option_fixtures() -> Vec<Fixture> {
+    let options = resource_options();
+    let mut fixtures: Vec<Fixture> = options
+        .iter()
+        .map(|(option, value)| {
+            let name = format!("option-{}", kebab(option));
+            option_fixture(name, json!({ *option: value }))
+        })
+        .collect();
+    let all: serde_json::Map<String, Json> = options
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+    fixtures.push(option_fixture("options-all".to_string(), Json::Object(all)));
+    fixtures
+}
+
+fn option_fixture(name: String, options: Json) -> Fixture {
+    let mut target = Mapping::new();
+    target.insert("type".into(), "test:index:Thing".into());
+    target.insert(
+        "properties".into(),
+        yaml(json!({ "name": "target", "tags": { "env": "dev" } })),
+    );
+    target.insert("options".into(), yaml(options));
+    let mut resources = Mapping::new();
+    resources.insert(
+        "provider".into(),
+        yaml(json!({ "type": "pulumi:providers:test" })),
+    );
+    resources.insert(
+        "dependency".into(),
+        yaml(json!({ "type": "test:index:Thing" })),
+    );
+    resources.insert("target".into(), Yaml::Mapping(target));
+    let source = program(
+        &name,
+        vec![
+            ("resources", Yaml::Mapping(resources)),
+            ("outputs", yaml(json!({ "urn": "${target.urn}" }))),
+        ],
+    );
+    Fixture {
+        name,
+        files: vec![("Pulumi.yaml".to_string(), source)],
+    }
+}
+
+/// Renders a `Pulumi.yaml` with `sections` after the project header.
+fn program(name: &str, sections: Vec<(&str, Yaml)>) -> String {
+    let mut doc = Mapping::new();
+    doc.insert("name".into(), format!("conformance-{}", name).into());
+    doc.insert("runtime".into(), "yaml".into());
+    for (key, value) in sections {
+        doc.insert(key.into(), value);
+    }
+    let body = serde_yaml::to_string(&doc).expect("fixtures serialize");
+    format!(
+        "# Generated by `cargo run -p pulumi-rs-yaml-testing --bin gen-conformance`.\n{}",
+        body
+    )
+}
+
+fn yaml(value: Json) -> Yaml {
+    serde_yaml::to_value(value).expect("JSON converts to YAML")
+}
+
+/// `pluginDownloadURL` -> `plugin-download-url`.
+fn kebab(name: &str) -> String {
+    let mut out = String::new();
+    let mut prev_upper = false;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            if !out.is_empty() && !prev_upper {
+                out.push('-');
+            }
+            out.push(c.to_ascii_lowercase());
+            prev_upper = true;
+        } else {
+            out.push(c);
+            prev_upper = false;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kebab() {
+        assert_eq!(kebab("dependsOn"), "depends-on");
+        assert_eq!(kebab("pluginDownloadURL"), "plugin-download-url");
+        assert_eq!(kebab("import"), "import");
+    }
+
+    #[test]
+    fn test_fixture_names_are_unique() {
+        let fixtures = fixtures();
+        let mut names: Vec<&str> = fixtures.iter().map(|f| f.name.as_str()).collect();
+        names.dedup();
+        assert_eq!(names.len(), fixtures.len());
+    }
+}
@@ -0,0 +1,313 @@
+//! Golden-corpus regression harness for pulumi-rs-yaml.
+//!
+//! `corpus/` holds real-world YAML programs in the shape of the
+//! `pulumi/examples` repository, one project directory per entry. Each entry
+//! is loaded, type-checked, and evaluated against a [`MockCallback`]; the
+//! result is rendered as a deterministic text [`Report`] and compared with the
+//! checked-in snapshot in `snapshots/<entry>.snap`.
+//!
+//! Any change in parser, type-checker, or evaluator diagnostics, in the
+//! resources a program registers, or in its stack outputs shows up as a
+//! snapshot diff. The serialized [`ExecutionPlan`] of each entry is pinned the
+//! same way in `snapshots/plans/<entry>.json`. To accept intended changes, re-run the tests with
+//! `UPDATE_SNAPSHOTS=1` and review the diff.
+//!
+//! The generated programs of [`conformance`] go through the same pipeline,
+//! with snapshots in `snapshots/conformance/`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use pulumi_rs_yaml_core::diag::Diagnostics;
+use pulumi_rs_yaml_core::eval::evaluator::Evaluator;
+use pulumi_rs_yaml_core::eval::mock::MockCallback;
+use pulumi_rs_yaml_core::eval::resource::{ResolvedAlias, ResolvedResourceOptions};
+use pulumi_rs_yaml_core::eval::value::{Archive, Asset, Value};
+use pulumi_rs_yaml_core::multi_file;
+use pulumi_rs_yaml_core::plan::ExecutionPlan;
+use pulumi_rs_yaml_core::schema::SchemaStore;
+use pulumi_rs_yaml_core::type_check::type_check;
+
+pub mod conformance;
+
+/// Environment variable that makes the corpus test rewrite snapshots.
+pub const UPDATE_SNAPSHOTS_ENV: &str = "UPDATE_SNAPSHOTS";
+
+/// Directory containing the corpus entries.
+pub fn corpus_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("corpus")
+}
+
+/// Directory containing the snapshots.
+pub fn snapshot_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("snapshots")
+}
+
+/// Directory containing the conformance fixture snapshots.
+pub fn conformance_snapshot_dir() -> PathBuf {
+    snapshot_dir().join("conformance")
+}
+
+/// Directory containing the execution plan snapshots.
+pub fn plan_snapshot_dir() -> PathBuf {
+    snapshot_dir().join("plans")
+}
+
+/// Returns the names of all corpus entries, sorted.
+pub fn corpus_entries() -> std::io::Result<Vec<String>> {
+    let mut names = Vec::new();
+    for entry in std::fs::read_dir(corpus_dir())? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// The observable result of running one corpus entry through the pipeline.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub parse: Vec<String>,
+    pub type_check: Vec<String>,
+    pub evaluate: Vec<String>,
+    /// Rendered registrations, keyed by logical name.
+    pub resources: BTreeMap<String, String>,
+    /// Rendered stack outputs.
+    pub outputs: BTreeMap<String, String>,
+}
+
+impl Report {
+    /// Renders the report in the snapshot format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        section(&mut out, "parse", &self.parse);
+        section(&mut out, "type-check", &self.type_check);
+        section(&mut out, "evaluate", &self.evaluate);
+        let resources: Vec<String> = self.resources.values().cloned().collect();
+        section(&mut out, "resources", &resources);
+        let outputs: Vec<String> = self
+            .outputs
+            .iter()
+            .map(|(k, v)| format!("{}: {}", k, v))
+            .collect();
+        section(&mut out, "outputs", &outputs);
+        out
+    }
+}
+
+fn section(out: &mut String, title: &str, lines: &[String]) {
+    let _ = writeln!(out, "# {}", title);
+    if lines.is_empty() {
+        out.push_str("(none)\n");
+    }
+    for line in lines {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.push('\n');
+}
+
+/// Loads, type-checks, and mock-evaluates the project in `dir`.
+///
+/// Type checking runs against an empty schema store, since provider plugins
+/// are not available in CI; it still covers structural and builtin checks.
+pub fn check_project(dir: &Path) -> Report {
+    let mut report = Report::default();
+
+    let (merged, load_diags) = multi_file::load_project(dir, None);
+    report.parse = diag_lines(&load_diags);
+    if load_diags.has_errors() {
+        return report;
+    }
+
+    let template = &merged.as_template_decl();
+    let store = SchemaStore::new();
+    report.type_check = diag_lines(&type_check(template, &store, None).diagnostics);
+
+    let eval = Evaluator::with_callback(
+        "corpus".to_string(),
+        "dev".to_string(),
+        dir.display().to_string(),
+        false,
+        MockCallback::new(),
+    );
+    eval.evaluate_template(template, &HashMap::new(), &[]);
+    // Sorted so the snapshot does not depend on evaluation order.
+    let mut evaluate: Vec<String> = eval
+        .diag_errors()
+        .into_iter()
+        .map(|s| format!("error: {}", s))
+        .chain(
+            eval.diag_warnings()
+                .into_iter()
+                .map(|s| format!("warning: {}", s)),
+        )
+        .collect();
+    evaluate.sort();
+    report.evaluate = evaluate;
+
+    for reg in eval.callback().registrations() {
+        let mut line = format!("{} {}", reg.type_token, reg.name);
+        if !reg.custom {
+            line.push_str(" (component)");
+        }
+        let inputs: BTreeMap<_, _> = reg
+            .inputs
+            .iter()
+            .map(|(k, v)| (k.clone(), render_value(v)))
+            .collect();
+        for (k, v) in inputs {
+            let _ = write!(line, "\n  {}: {}", k, v);
+        }
+        for opt in render_options(&reg.options) {
+            let _ = write!(line, "\n  [{}]", opt);
+        }
+        report.resources.insert(reg.name.clone(), line);
+    }
+
+    report.outputs = eval
+        .take_outputs()
+        .iter()
+        .map(|(k, v)| (k.clone(), render_value(v)))
+        .collect();
+    report
+}
+
+/// Builds the execution plan of the project in `dir` and renders it as
+/// pretty-printed JSON, or the load/graph diagnostics if it cannot be built.
+pub fn plan_json(dir: &Path) -> String {
+    let (merged, load_diags) = multi_file::load_project(dir, None);
+    if load_diags.has_errors() {
+        return diag_lines(&load_diags).join("\n");
+    }
+    match ExecutionPlan::build(&merged, &load_diags) {
+        Ok(plan) => serde_json::to_string_pretty(&plan).expect("plan serializes") + "\n",
+        Err(diags) => diag_lines(&diags).join("\n"),
+    }
+}
+
+fn diag_lines(diags: &Diagnostics) -> Vec<String> {
+    diags.iter().map(|d| d.to_string()).collect()
+}
+
+/// Renders a value as compact JSON, making secrets, unknowns, resources,
+/// assets, and archives visible.
+pub fn render_value(value: &Value<'_>) -> String {
+    to_json(value).to_string()
+}
+
+fn to_json(value: &Value<'_>) -> serde_json::Value {
+    use serde_json::{json, Value as Json};
+    match value {
+        Value::Secret(inner) => json!({ "<secret>": to_json(inner) }),
+        Value::Unknown => Json::String("<unknown>".into()),
+        Value::Resource(r) => Json::String(format!("<resource #{}>", r.0)),
+        Value::Asset(Asset::String(s)) => json!({ "<stringAsset>": s }),
+        Value::Asset(Asset::File(s, _)) => json!({ "<fileAsset>": s }),
+        Value::Asset(Asset::Remote(s)) => json!({ "<remoteAsset>": s }),
+        Value::Archive(Archive::File(s)) => json!({ "<fileArchive>": s }),
+        Value::Archive(Archive::Remote(s)) => json!({ "<remoteArchive>": s }),
+        Value::Archive(Archive::Assets(entries)) => {
+            let map: serde_json::Map<_, _> = entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), to_json(v)))
+                .collect();
+            json!({ "<assetArchive>": map })
+        }
+        Value::List(items) => Json::Array(items.iter().map(to_json).collect()),
+        Value::Object(entries) => Json::Object(
+            entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), to_json(v)))
+                .collect(),
+        ),
+        other => other.to_json(),
+    }
+}
+
+fn render_options(opts: &ResolvedResourceOptions) -> Vec<String> {
+    let mut out = Vec::new();
+    if let Some(parent) = &opts.parent_urn {
+        out.push(format!("parent: {}", parent));
+    }
+    if let Some(provider) = &opts.provider_ref {
+        out.push(format!("provider: {}", provider));
+    }
+    if !opts.depends_on.is_empty() {
+        let mut deps = opts.depends_on.clone();
+        deps.sort();
+        out.push(format!("dependsOn: {}", deps.join(", ")));
+    }
+    if opts.protect {
+        out.push("protect".to_string());
+    }
+    if !opts.additional_secret_outputs.is_empty() {
+        out.push(format!(
+            "additionalSecretOutputs: {}",
+            opts.additional_secret_outputs.join(", ")
+        ));
+    }
+    if !opts.ignore_changes.is_empty() {
+        out.push(format!("ignoreChanges: {}", opts.ignore_changes.join(", ")));
+    }
+    if !opts.providers.is_empty() {
+        let mut providers: Vec<String> = opts
+            .providers
+            .iter()
+            .map(|(pkg, provider)| format!("{}={}", pkg, provider))
+            .collect();
+        providers.sort();
+        out.push(format!("providers: {}", providers.join(", ")));
+    }
+    if opts.delete_before_replace {
+        out.push("deleteBeforeReplace".to_string());
+    }
+    if opts.retain_on_delete {
+        out.push("retainOnDelete".to_string());
+    }
+    if !opts.replace_on_changes.is_empty() {
+        out.push(format!(
+            "replaceOnChanges: {}",
+            opts.replace_on_changes.join(", ")
+        ));
+    }
+    if !opts.aliases.is_empty() {
+        let aliases: Vec<String> = opts
+            .aliases
+            .iter()
+            .map(|alias| match alias {
+                ResolvedAlias::Urn(urn) => urn.clone(),
+                ResolvedAlias::Spec { name, .. } => format!("name={}", name),
+            })
+            .collect();
+        out.push(format!("aliases: {}", aliases.join(", ")));
+    }
+    if !opts.import_id.is_empty() {
+        out.push(format!("import: {}", opts.import_id));
+    }
+    if let Some((create, update, delete)) = &opts.custom_timeouts {
+        out.push(format!(
+            "customTimeouts: create={} update={} delete={}",
+            create, update, delete
+        ));
+    }
+    if !opts.version.is_empty() {
+        out.push(format!("version: {}", opts.version));
+    }
+    if !opts.plugin_download_url.is_empty() {
+        out.push(format!("pluginDownloadURL: {}", opts.plugin_download_url));
+    }
+    if !opts.replace_with.is_empty() {
+        out.push(format!("replaceWith: {}", opts.replace_with.join(", ")));
+    }
+    if !opts.deleted_with.is_empty() {
+        out.push(format!("deletedWith: {}", opts.deleted_with));
+    }
+    if !opts.hide_diffs.is_empty() {
+        out.push(format!("hideDiffs: {}", opts.hide_diffs.join(", ")));
+    }
+    out
+}
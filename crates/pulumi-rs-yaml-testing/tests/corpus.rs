@@ -0,0 +1,180 @@
+//! Runs every corpus entry and conformance fixture and compares the result
+//! with its snapshot.
+//!
+//! Set `UPDATE_SNAPSHOTS=1` to rewrite the snapshots instead of comparing.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use pretty_assertions::StrComparison;
+use pulumi_rs_yaml_core::ast::parse::parse_template;
+use pulumi_rs_yaml_core::plan::expr_to_json;
+use pulumi_rs_yaml_testing::conformance::{self, conformance_dir, stale_fixtures, EXPR_TAGS};
+use pulumi_rs_yaml_testing::{
+    check_project, conformance_snapshot_dir, corpus_dir, corpus_entries, plan_json,
+    plan_snapshot_dir, snapshot_dir, UPDATE_SNAPSHOTS_ENV,
+};
+
+/// Renders every entry of `root` with `render` and compares it with the
+/// snapshot at `snapshot(name)`.
+fn assert_snapshots(
+    root: &Path,
+    entries: &[String],
+    render: impl Fn(&Path) -> String,
+    snapshot: impl Fn(&str) -> PathBuf,
+) {
+    let update = std::env::var(UPDATE_SNAPSHOTS_ENV).is_ok_and(|v| v == "1");
+    assert!(!entries.is_empty(), "{} is empty", root.display());
+
+    let mut failures = Vec::new();
+    for name in entries {
+        let actual = render(&root.join(name));
+        let path = snapshot(name);
+        if update {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, &actual).unwrap();
+            continue;
+        }
+        match fs::read_to_string(&path) {
+            Ok(expected) if expected == actual => {}
+            Ok(expected) => failures.push(format!(
+                "{}:\n{}",
+                name,
+                StrComparison::new(&expected, &actual)
+            )),
+            Err(_) => failures.push(format!("{}: missing snapshot {}", name, path.display())),
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{} entries drifted (re-run with {}=1 to accept):\n\n{}",
+        failures.len(),
+        UPDATE_SNAPSHOTS_ENV,
+        failures.join("\n\n")
+    );
+}
+
+#[test]
+fn test_corpus_matches_snapshots() {
+    assert_snapshots(
+        &corpus_dir(),
+        &corpus_entries().unwrap(),
+        |dir| check_project(dir).render(),
+        |name| snapshot_dir().join(format!("{}.snap", name)),
+    );
+}
+
+#[test]
+fn test_corpus_plans_match_snapshots() {
+    assert_snapshots(
+        &corpus_dir(),
+        &corpus_entries().unwrap(),
+        plan_json,
+        |name| plan_snapshot_dir().join(format!("{}.json", name)),
+    );
+}
+
+#[test]
+fn test_no_orphaned_snapshots() {
+    let entries = corpus_entries().unwrap();
+    for dir in [snapshot_dir(), plan_snapshot_dir()] {
+        for snapshot in fs::read_dir(dir).unwrap() {
+            let path = snapshot.unwrap().path();
+            if path.is_dir() {
+                continue;
+            }
+            let stem = path.file_stem().unwrap().to_string_lossy().into_owned();
+            assert!(
+                entries.contains(&stem),
+                "snapshot {} has no corpus entry",
+                path.display()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_corpus_evaluates_without_errors() {
+    for name in corpus_entries().unwrap() {
+        let report = check_project(&corpus_dir().join(&name));
+        let errors: Vec<_> = report
+            .parse
+            .iter()
+            .chain(&report.type_check)
+            .chain(&report.evaluate)
+            .filter(|d| d.starts_with("error"))
+            .collect();
+        assert!(errors.is_empty(), "{}: {:?}", name, errors);
+    }
+}
+
+fn conformance_names() -> Vec<String> {
+    conformance::fixtures()
+        .into_iter()
+        .map(|f| f.name)
+        .collect()
+}
+
+#[test]
+fn test_conformance_fixtures_are_up_to_date() {
+    let stale = stale_fixtures(&conformance_dir());
+    assert!(
+        stale.is_empty(),
+        "conformance fixtures are out of date: {:?} \
+         (run `cargo run -p pulumi-rs-yaml-testing --bin gen-conformance`)",
+        stale
+    );
+}
+
+#[test]
+fn test_conformance_matches_snapshots() {
+    assert_snapshots(
+        &conformance_dir(),
+        &conformance_names(),
+        |dir| check_project(dir).render(),
+        |name| conformance_snapshot_dir().join(format!("{}.snap", name)),
+    );
+}
+
+#[test]
+fn test_no_orphaned_conformance_snapshots() {
+    let names = conformance_names();
+    for snapshot in fs::read_dir(conformance_snapshot_dir()).unwrap() {
+        let path = snapshot.unwrap().path();
+        let stem = path.file_stem().unwrap().to_string_lossy().into_owned();
+        assert!(
+            names.contains(&stem),
+            "snapshot {} has no conformance fixture",
+            path.display()
+        );
+    }
+}
+
+#[test]
+fn test_conformance_covers_every_expression() {
+    fn collect_tags(json: &serde_json::Value, tags: &mut BTreeSet<String>) {
+        match json {
+            serde_json::Value::Object(map) => {
+                if let Some(tag) = map.get("t").and_then(|t| t.as_str()) {
+                    tags.insert(tag.to_string());
+                }
+                map.values().for_each(|v| collect_tags(v, tags));
+            }
+            serde_json::Value::Array(items) => items.iter().for_each(|v| collect_tags(v, tags)),
+            _ => {}
+        }
+    }
+
+    let mut tags = BTreeSet::new();
+    for fixture in conformance::fixtures() {
+        let (_, source) = &fixture.files[0];
+        let (template, diags) = parse_template(source, None);
+        assert!(!diags.has_errors(), "{}: {}", fixture.name, diags);
+        for var in &template.variables {
+            collect_tags(&expr_to_json(&var.value), &mut tags);
+        }
+    }
+    let missing: Vec<_> = EXPR_TAGS.iter().filter(|t| !tags.contains(**t)).collect();
+    assert!(missing.is_empty(), "no fixture exercises {:?}", missing);
+}
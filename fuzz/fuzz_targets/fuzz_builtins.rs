@@ -7,6 +7,7 @@
 //! - f64 → usize overflow in fn::select, fn::substring, fn::randomString
 //! - OOM in fn::randomString with huge length
 //! - Panics in fn::split, fn::join, fn::toJSON, fn::toBase64
+//! - Panics in fn::parseJSON, fn::parseYAML on arbitrary text
 //! - Secret value handling (fn::secret wrapping)
 
 #![no_main]
@@ -55,10 +56,23 @@ fuzz_target!(|input: FuzzInput| {
     let value = input.value.to_value();
     let index = input.index.to_value();
 
-    // fn::toJSON — must never panic
+    // fn::toJSON — must never panic, and fn::parseJSON must read it back
     {
         let mut diags = Diagnostics::new();
-        let _ = builtins::eval_to_json(&value, &mut diags);
+        if let Some(json) = builtins::eval_to_json(&value, &mut diags) {
+            assert!(
+                builtins::eval_parse_json(&json, &mut diags).is_some(),
+                "fn::parseJSON must accept fn::toJSON output"
+            );
+        }
+    }
+
+    // fn::parseJSON / fn::parseYAML — must never panic on arbitrary text
+    {
+        let mut diags = Diagnostics::new();
+        let text = Value::String(Cow::Owned(input.separator.clone()));
+        let _ = builtins::eval_parse_json(&text, &mut diags);
+        let _ = builtins::eval_parse_yaml(&text, &mut diags);
     }
 
     // fn::secret — must never panic
@@ -75,6 +89,26 @@ fuzz_target!(|input: FuzzInput| {
         let _ = builtins::eval_select(&index, &value, &mut diags);
     }
 
+    // fn::sort / fn::unique / fn::length / fn::coalesce — must never panic, even with NaN
+    {
+        let mut diags = Diagnostics::new();
+        let _ = builtins::eval_sort(&value, &mut diags);
+        let _ = builtins::eval_unique(&value, &mut diags);
+        let _ = builtins::eval_length(&value, &mut diags);
+        let _ = builtins::eval_coalesce(&value, &mut diags);
+    }
+
+    // fn::equals / fn::not / fn::and / fn::or / numeric comparisons — must never panic
+    {
+        let mut diags = Diagnostics::new();
+        let _ = builtins::eval_equals(&value, &value);
+        let _ = builtins::eval_not(&value, &mut diags);
+        let _ = builtins::eval_and(&value, &mut diags);
+        let _ = builtins::eval_or(&value, &mut diags);
+        let _ = builtins::eval_less_than(&value, &value, &mut diags);
+        let _ = builtins::eval_greater_than_or_equal(&value, &value, &mut diags);
+    }
+
     // fn::split — must never panic
     {
         let sep = Value::String(Cow::Owned(input.separator.clone()));
@@ -142,6 +176,25 @@ fuzz_target!(|input: FuzzInput| {
         let mut diags = Diagnostics::new();
         let _ = builtins::eval_substring(&value, &index, &value, &mut diags);
     }
+    {
+        let mut diags = Diagnostics::new();
+        let _ = builtins::eval_to_upper(&value, &mut diags);
+        let _ = builtins::eval_to_lower(&value, &mut diags);
+        let _ = builtins::eval_trim(&value, &mut diags);
+    }
+    {
+        let mut diags = Diagnostics::new();
+        let _ = builtins::eval_replace(&value, &index, &value, &mut diags);
+        let _ = builtins::eval_starts_with(&value, &index, &mut diags);
+        let _ = builtins::eval_format(&index, &value, &mut diags);
+    }
+    {
+        let mut diags = Diagnostics::new();
+        let _ = builtins::eval_cidr_subnet(&value, &index, &index, &mut diags);
+        let _ = builtins::eval_cidr_host(&value, &index, &mut diags);
+        let _ = builtins::eval_cidr_netmask(&value, &mut diags);
+        let _ = builtins::eval_assert(&value, &index, None, &mut diags);
+    }
 
     // v0.4.0: Value::from_json_owned — must never panic
     {